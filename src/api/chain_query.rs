@@ -0,0 +1,119 @@
+//! Chain Queries
+//!
+//! The deposit handler only needs three questions answered by the chain: a
+//! transaction by hash, its receipt, and the current head. `ChainQuery`
+//! captures exactly that so the JSON-RPC node can be swapped for a scripted
+//! double in tests.
+
+use std::future::Future;
+use anyhow::Result;
+
+/// Transaction fields the deposit flow relies on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainTransaction {
+    pub from_address: String,
+    pub to_address: String,
+    pub value_wei: u128,
+    /// `None` while the transaction is pending
+    pub block_number: Option<u64>,
+}
+
+/// Receipt fields the deposit flow relies on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainReceipt {
+    /// `0x1` on success
+    pub status: String,
+    pub gas_used: String,
+}
+
+/// Read-only view of the chain used to verify deposits
+pub trait ChainQuery {
+    /// Transaction by hash, `None` if the node does not know it
+    fn transaction(&self, tx_hash: &str) -> impl Future<Output = Result<Option<ChainTransaction>>> + Send;
+
+    /// Receipt by transaction hash, `None` until mined
+    fn receipt(&self, tx_hash: &str) -> impl Future<Output = Result<Option<ChainReceipt>>> + Send;
+
+    /// Current chain head
+    fn block_number(&self) -> impl Future<Output = Result<u64>> + Send;
+}
+
+/// Deterministic in-memory chain with canned responses keyed by tx hash
+#[cfg(test)]
+pub mod scripted {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use anyhow::{Result, anyhow};
+    use super::{ChainQuery, ChainReceipt, ChainTransaction};
+
+    /// Scriptable `ChainQuery` double
+    ///
+    /// Hashes are matched case-insensitively. A scripted failure makes every
+    /// query for that hash error, as an unreachable or rate-limited node would.
+    #[derive(Default)]
+    pub struct ScriptedChain {
+        head: AtomicU64,
+        transactions: Mutex<HashMap<String, (ChainTransaction, Option<ChainReceipt>)>>,
+        failures: Mutex<HashMap<String, String>>,
+    }
+
+    impl ScriptedChain {
+        /// Empty chain at `head`
+        pub fn new(head: u64) -> Self {
+            Self { head: AtomicU64::new(head), ..Default::default() }
+        }
+
+        /// Script a transaction and its receipt (`None` for pending)
+        pub fn add_transaction(&self, tx_hash: &str, transaction: ChainTransaction, receipt: Option<ChainReceipt>) {
+            self.transactions.lock().unwrap().insert(tx_hash.to_lowercase(), (transaction, receipt));
+        }
+
+        /// Script a successful transfer mined `confirmations` blocks below the head
+        pub fn add_mined_transfer(&self, tx_hash: &str, from: &str, to: &str, value_wei: u128, confirmations: u64) {
+            let block_number = self.head.load(Ordering::Acquire).saturating_sub(confirmations);
+            self.add_transaction(
+                tx_hash,
+                ChainTransaction {
+                    from_address: from.to_string(),
+                    to_address: to.to_string(),
+                    value_wei,
+                    block_number: Some(block_number),
+                },
+                Some(ChainReceipt { status: "0x1".to_string(), gas_used: "0x5208".to_string() }),
+            );
+        }
+
+        /// Make every query for `tx_hash` fail with `message`
+        pub fn fail(&self, tx_hash: &str, message: &str) {
+            self.failures.lock().unwrap().insert(tx_hash.to_lowercase(), message.to_string());
+        }
+
+        /// Move the chain head, e.g. to add confirmations
+        pub fn set_head(&self, head: u64) {
+            self.head.store(head, Ordering::Release);
+        }
+
+        fn lookup(&self, tx_hash: &str) -> Result<Option<(ChainTransaction, Option<ChainReceipt>)>> {
+            let tx_hash = tx_hash.to_lowercase();
+            if let Some(message) = self.failures.lock().unwrap().get(&tx_hash) {
+                return Err(anyhow!("{}", message));
+            }
+            Ok(self.transactions.lock().unwrap().get(&tx_hash).cloned())
+        }
+    }
+
+    impl ChainQuery for ScriptedChain {
+        async fn transaction(&self, tx_hash: &str) -> Result<Option<ChainTransaction>> {
+            Ok(self.lookup(tx_hash)?.map(|(transaction, _)| transaction))
+        }
+
+        async fn receipt(&self, tx_hash: &str) -> Result<Option<ChainReceipt>> {
+            Ok(self.lookup(tx_hash)?.and_then(|(_, receipt)| receipt))
+        }
+
+        async fn block_number(&self) -> Result<u64> {
+            Ok(self.head.load(Ordering::Acquire))
+        }
+    }
+}
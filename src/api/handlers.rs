@@ -5,22 +5,36 @@
 
 use axum::{
     extract::{Path, Query, State},
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
     http::StatusCode,
-    response::Json,
+    response::{Json, Response},
+    middleware::from_fn_with_state,
     routing::{get, post},
     Router,
 };
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, HashSet, VecDeque};
 use anyhow::{Result, anyhow};
 use reqwest;
 use serde_json::{json, Value};
 use std::str::FromStr;
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 use crate::api::types::*;
-use crate::utxo::CanonicalUTXO;
+use crate::api::openapi::openapi_json;
+use crate::api::chain_query::{ChainQuery, ChainReceipt, ChainTransaction};
+use crate::api::middleware::{rate_limit, require_admin_token, RateLimitConfig, RateLimiter};
+use crate::utxo::{CanonicalUTXO, RandomnessBeacon, UTXOError};
 use crate::relayer::blockchain_integration::DepositEvent as BlockchainDepositEvent;
+use crate::relayer::deposit_watcher::{ConfirmationPolicy, DepositSource, DepositWatcher, WatcherProgress};
+use crate::relayer::rpc_failover::{FailoverConfig, ProviderHealth};
+use crate::merkle::InMemorySMT;
 use crate::privacy::PrivacyPool;
+use crate::crypto::architecture_compliance::ArchitectureCompliantCrypto;
+use crate::crypto::{CryptoUtils, OperatorKeypair, SignatureAlgorithm};
+use crate::utils::{RedJubjubPublicKey, RedJubjubSignature, RedJubjubSignatureScheme};
+use crate::database::{PoolCounters, QueryEngine};
 
 /// Simplified application state using in-memory storage
 #[derive(Clone)]
@@ -31,6 +45,12 @@ pub struct AppState {
     /// Owner to UTXOs mapping (owner_commitment -> list of utxo_ids)
     pub owner_utxos: Arc<Mutex<HashMap<[u8; 32], Vec<[u8; 32]>>>>,
     
+    /// Spend commitment reverse index (commitment -> utxo_id)
+    pub commitment_index: Arc<Mutex<HashMap<[u8; 32], [u8; 32]>>>,
+    
+    /// Encrypted notes (utxo_id -> ciphertext)
+    pub encrypted_notes: Arc<Mutex<HashMap<[u8; 32], EncryptedNotePayload>>>,
+    
     /// Asset balances (owner_commitment -> asset_id -> balance_info)
     pub balances: Arc<Mutex<HashMap<[u8; 32], HashMap<[u8; 20], (u128, u32)>>>>,
     
@@ -38,9 +58,56 @@ pub struct AppState {
     pub tree_root: Arc<Mutex<[u8; 32]>>,
     pub tree_version: Arc<Mutex<u64>>,
     
+    /// Most recent tree roots, newest last (withdrawal proof tolerance window)
+    pub recent_roots: Arc<Mutex<VecDeque<[u8; 32]>>>,
+    
+    /// Sparse tree of unspent UTXO leaves; spends prove inclusion against its roots
+    pub utxo_tree: Arc<Mutex<InMemorySMT>>,
+    
+    /// Nullifiers of withdrawn UTXOs
+    pub spent_nullifiers: Arc<Mutex<HashSet<[u8; 32]>>>,
+    
+    /// Index of the last beacon value used for UTXO ID entropy
+    pub beacon_index: Arc<Mutex<u64>>,
+    
+    /// Incrementally maintained pool counters served by the stats endpoint
+    pub pool_counters: Arc<Mutex<PoolCounters>>,
+    
     /// Privacy pool instance
     pub privacy_pool: Arc<Mutex<PrivacyPool>>,
     
+    /// Operator keypair used to sign tree roots
+    pub operator_keypair: OperatorKeypair,
+    
+    /// Pool event feed for WebSocket subscribers
+    pub events: broadcast::Sender<PoolEvent>,
+    
+    /// Unfiltered root and deposit feed for `/api/ws` clients
+    pub tree_feed: broadcast::Sender<TreeFeedMessage>,
+    
+    /// Shared HTTP client for RPC calls (pooled connections, bounded by `rpc_timeout`)
+    pub http_client: reqwest::Client,
+    
+    /// Health of the configured RPC providers, in `AppConfig::rpc_urls` order
+    pub rpc_health: Arc<ProviderHealth>,
+    
+    /// Deposit watcher sync progress used by the readiness probe
+    pub watcher_progress: Arc<WatcherProgress>,
+    
+    /// Per-IP limiter for `/api/deposit`, which calls the RPC node per request
+    pub deposit_rate_limiter: RateLimiter,
+    
+    /// Per-IP limiter shared by the read endpoints
+    pub read_rate_limiter: RateLimiter,
+    
+    /// Why the SMT failed to load at startup; when set the API runs degraded
+    /// and rejects tree-mutating requests with 503
+    pub tree_unavailable: Option<String>,
+    
+    /// The tree database at `tree_db_path`, opened once and shared with the
+    /// deposit watcher; degraded reads are served from it
+    pub tree_db: Option<crate::database::DatabaseManager>,
+    
     /// Configuration
     pub config: AppConfig,
 }
@@ -52,61 +119,303 @@ pub struct AppConfig {
     pub tree_salt: u64,
     pub version: String,
     pub sepolia_rpc_url: String,
+    /// Providers tried after `sepolia_rpc_url` on connection or 5xx errors, in order
+    pub sepolia_rpc_fallback_urls: Vec<String>,
+    /// When a failing RPC provider is taken out of rotation
+    pub rpc_failover: FailoverConfig,
     pub contract_address: String,
+    pub rpc_timeout: Duration,
+    /// Largest JSON-RPC response body buffered from the node (bytes)
+    pub max_rpc_response_bytes: usize,
+    /// Largest lock script accepted from clients (bytes)
+    pub max_lock_data_bytes: usize,
+    /// Asset registry: decimals per asset ID, used for `format=decimal`
+    pub asset_decimals: HashMap<[u8; 20], u8>,
+    /// Maximum deposit watcher lag (blocks) before `/ready` fails
+    pub max_ready_lag_blocks: u64,
+    /// Number of recent roots a withdrawal proof may reference; the deposit
+    /// watcher never prunes cf_root_history below it
+    pub root_tolerance_window: usize,
+    /// Committed entropy source for UTXO IDs
+    pub randomness_beacon: RandomnessBeacon,
+    /// Unspent same-asset, same-denomination UTXOs required before a withdrawal
+    pub min_anonymity_set: usize,
+    /// Most commitments accepted by one status request
+    pub max_commitment_status_batch: usize,
+    /// Most proofs accepted by one batch verification request
+    pub max_proof_verify_batch: usize,
+    /// Blocks a deposit transaction must be buried under before it is minted
+    pub min_deposit_confirmations: u64,
+    /// Bearer token required by `/api/admin/*`; admin routes reject everything when unset
+    pub admin_token: Option<String>,
+    /// Depositor allowlist/denylist, shared so admins can swap it at runtime
+    pub address_policy: Arc<RwLock<AddressPolicy>>,
+    /// RocksDB path of the canonical SMT to seed the tree root from (None: start from the empty tree)
+    pub tree_db_path: Option<String>,
+    /// Start in degraded mode instead of failing when the SMT cannot be loaded
+    pub allow_degraded_start: bool,
+    /// Scheme of a newly generated operator root-signing key
+    ///
+    /// A key already stored in the tree database keeps its algorithm. The
+    /// secret is stored unencrypted in cf_tree_metadata.
+    pub operator_signature_algorithm: SignatureAlgorithm,
+    /// Per-client-IP limit on `/api/deposit`
+    pub deposit_rate_limit: RateLimitConfig,
+    /// Per-client-IP limit on balance, UTXO, tree and proof queries
+    pub read_rate_limit: RateLimitConfig,
+    /// Reverse proxies trusted to report the client in `X-Forwarded-For`
+    pub trusted_proxies: Vec<std::net::IpAddr>,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
+        let tree_salt = rand::random::<u64>();
         Self {
             tree_depth: 32,
-            tree_salt: rand::random::<u64>(),
+            tree_salt,
             version: "0.1.0".to_string(),
             sepolia_rpc_url: "https://eth-sepolia.g.alchemy.com/v2/wdp1FpAvY5GBD-wstEpHlsIY37WcgKgI".to_string(),
+            sepolia_rpc_fallback_urls: Vec::new(),
+            rpc_failover: FailoverConfig::default(),
             contract_address: "0x19B8743Df3E8997489b50F455a1cAe3536C0ee31".to_string(),
+            rpc_timeout: Duration::from_secs(10),
+            max_rpc_response_bytes: 1024 * 1024,
+            max_lock_data_bytes: crate::canonical_spec::utxo_format::MAX_LOCK_DATA_SIZE,
+            asset_decimals: HashMap::from([(crate::canonical_spec::utxo_format::ETH_ASSET_ID, 18)]),
+            max_ready_lag_blocks: 12,
+            root_tolerance_window: crate::canonical_spec::tree_config::DEFAULT_ROOT_TOLERANCE_WINDOW as usize,
+            randomness_beacon: RandomnessBeacon::from_tree_salt(tree_salt),
+            // A UTXO always counts itself, so 1 disables the check
+            min_anonymity_set: 1,
+            max_commitment_status_batch: 1000,
+            max_proof_verify_batch: 1000,
+            min_deposit_confirmations: 0,
+            admin_token: None,
+            address_policy: Arc::new(RwLock::new(AddressPolicy::default())),
+            tree_db_path: None,
+            allow_degraded_start: false,
+            operator_signature_algorithm: SignatureAlgorithm::Ed25519,
+            deposit_rate_limit: RateLimitConfig { requests_per_second: 1.0, burst: 5 },
+            read_rate_limit: RateLimitConfig { requests_per_second: 20.0, burst: 100 },
+            trusted_proxies: Vec::new(),
         }
     }
 }
 
+impl AppConfig {
+    /// All configured RPC providers, primary first
+    pub fn rpc_urls(&self) -> Vec<String> {
+        std::iter::once(self.sepolia_rpc_url.clone())
+            .chain(self.sepolia_rpc_fallback_urls.iter().cloned())
+            .collect()
+    }
+}
+
 impl AppState {
     /// Create new application state
     pub fn new() -> Result<Self> {
-        let config = AppConfig::default();
+        Self::with_config(AppConfig::default())
+    }
+
+    /// Create application state with a custom configuration
+    pub fn with_config(config: AppConfig) -> Result<Self> {
         let privacy_pool = PrivacyPool::new([0u8; 32]); // Default scope
+        // Replaced by the persisted key when a tree database is configured
+        let mut operator_keypair = OperatorKeypair::generate(config.operator_signature_algorithm)
+            .map_err(|e| anyhow!("Failed to generate operator keypair: {}", e))?;
+        let http_client = reqwest::Client::builder()
+            .timeout(config.rpc_timeout)
+            .pool_max_idle_per_host(8)
+            .build()
+            .map_err(|e| anyhow!("Failed to build HTTP client: {}", e))?;
+        let rpc_health = Arc::new(ProviderHealth::new(config.rpc_urls().len(), config.rpc_failover.clone()));
+        
+        let (events, _) = broadcast::channel(1024);
+        let (tree_feed, _) = broadcast::channel(1024);
+        
+        let mut utxo_tree = InMemorySMT::new(config.tree_depth, config.tree_salt);
+        let mut tree_version = 0;
+        let mut pool_counters = PoolCounters::default();
+        let mut tree_unavailable = None;
+        let mut tree_db = None;
+        if let Some(tree_db_path) = &config.tree_db_path {
+            let loaded = crate::database::DatabaseManager::open(crate::database::schema::DBConfig {
+                db_path: tree_db_path.clone(),
+                ..Default::default()
+            }).and_then(|db| {
+                tree_db = Some(db.clone());
+                load_tree_state(db, &config)
+            });
+            match loaded {
+                Ok((tree, version, keypair, counters)) => {
+                    utxo_tree = tree;
+                    tree_version = version;
+                    pool_counters = counters;
+                    operator_keypair = keypair;
+                }
+                Err(e) if config.allow_degraded_start => {
+                    eprintln!(" SMT unavailable, starting in degraded mode: {}", e);
+                    tree_unavailable = Some(e.to_string());
+                }
+                Err(e) => return Err(e.context("Failed to load SMT")),
+            }
+        }
         
         Ok(Self {
             utxos: Arc::new(Mutex::new(HashMap::new())),
             owner_utxos: Arc::new(Mutex::new(HashMap::new())),
+            commitment_index: Arc::new(Mutex::new(HashMap::new())),
+            encrypted_notes: Arc::new(Mutex::new(HashMap::new())),
             balances: Arc::new(Mutex::new(HashMap::new())),
-            tree_root: Arc::new(Mutex::new([0u8; 32])),
-            tree_version: Arc::new(Mutex::new(0)),
+            tree_root: Arc::new(Mutex::new(utxo_tree.get_root())),
+            tree_version: Arc::new(Mutex::new(tree_version)),
+            recent_roots: Arc::new(Mutex::new(VecDeque::from([utxo_tree.get_root()]))),
+            utxo_tree: Arc::new(Mutex::new(utxo_tree)),
+            spent_nullifiers: Arc::new(Mutex::new(HashSet::new())),
+            beacon_index: Arc::new(Mutex::new(0)),
+            pool_counters: Arc::new(Mutex::new(pool_counters)),
             privacy_pool: Arc::new(Mutex::new(privacy_pool)),
+            operator_keypair,
+            events,
+            tree_feed,
+            http_client,
+            rpc_health,
+            watcher_progress: Arc::new(WatcherProgress::default()),
+            deposit_rate_limiter: RateLimiter::new(config.deposit_rate_limit, config.trusted_proxies.clone()),
+            read_rate_limiter: RateLimiter::new(config.read_rate_limit, config.trusted_proxies.clone()),
+            tree_unavailable,
+            tree_db,
             config,
         })
     }
+
+    /// Reject tree-mutating requests while the API runs degraded
+    fn require_tree(&self) -> std::result::Result<(), (StatusCode, Json<ErrorResponse>)> {
+        match &self.tree_unavailable {
+            None => Ok(()),
+            Some(reason) => {
+                let (_, Json(mut body)) = api_error("TREE_UNAVAILABLE", "Merkle tree is unavailable; only read-only endpoints are served");
+                body.details = Some(json!({ "reason": reason }));
+                Err((StatusCode::SERVICE_UNAVAILABLE, Json(body)))
+            }
+        }
+    }
+
+    /// Start the deposit watcher over the tree database in the background
+    ///
+    /// The watcher reports into `watcher_progress`, which `/ready` checks.
+    /// It stops when `shutdown` turns true or its sender is dropped.
+    pub fn spawn_deposit_watcher<S: DepositSource + Send + 'static>(
+        &self,
+        source: S,
+        interval: Duration,
+        shutdown: tokio::sync::watch::Receiver<bool>,
+    ) -> Result<tokio::task::JoinHandle<Result<()>>> {
+        let db = self.tree_db.clone()
+            .ok_or_else(|| anyhow!("Deposit watcher needs an open tree database"))?;
+        let mut utxo_manager = crate::utxo::UTXOManager::with_tree_config(db.clone(), self.config.tree_depth, self.config.tree_salt)?;
+        utxo_manager.set_root_tolerance_window(self.config.root_tolerance_window as u64);
+        let mut watcher = DepositWatcher::new(source, utxo_manager, db, ConfirmationPolicy::new(self.config.min_deposit_confirmations))?
+            .with_progress(self.watcher_progress.clone());
+        
+        Ok(tokio::spawn(async move { watcher.run(interval, shutdown).await }))
+    }
+    
+    /// Query engine over the tree database while the API runs degraded
+    ///
+    /// The in-memory maps are only filled by tree-mutating requests, which a
+    /// degraded API refuses, so reads go to cf_asset_balances and cf_utxos.
+    fn degraded_store(&self) -> Option<QueryEngine> {
+        match (&self.tree_unavailable, &self.tree_db) {
+            (Some(_), Some(db)) => Some(QueryEngine::new(db.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Load the canonical SMT in the tree database: its leaves, version, the
+/// operator key that signs its roots and the pool counters describing it
+fn load_tree_state(db: crate::database::DatabaseManager, config: &AppConfig) -> Result<(InMemorySMT, u64, OperatorKeypair, PoolCounters)> {
+    let operator_keypair = crate::utxo::UTXOManager::load_or_create_operator_keypair(&db, config.operator_signature_algorithm)?;
+    if operator_keypair.algorithm() != config.operator_signature_algorithm {
+        log::warn!(
+            "Stored operator key uses {:?}, not the configured {:?}; keeping the stored key",
+            operator_keypair.algorithm(),
+            config.operator_signature_algorithm
+        );
+    }
+    let pool_counters = PoolCounters::load(&db)?;
+    let smt = crate::merkle::CanonicalSMT::new(db, config.tree_depth, config.tree_salt)?;
+    let mut tree = InMemorySMT::new(config.tree_depth, config.tree_salt);
+    for (leaf_index, leaf_hash) in smt.leaf_positions()? {
+        tree.insert_leaf(leaf_index, leaf_hash)?;
+    }
+    if tree.get_root() != smt.get_root() {
+        return Err(anyhow!("SMT root does not match its stored leaves"));
+    }
+    Ok((tree, smt.get_root_version(), operator_keypair, pool_counters))
 }
 
 /// Create API router with all endpoints
 pub fn create_router() -> Result<Router> {
-    let state = AppState::new()?;
+    Ok(router_with_state(AppState::new()?))
+}
+
+/// Build the API router over existing application state
+///
+/// Maintenance endpoints live under `/api/admin` and require the configured
+/// admin bearer token; every other route is public. Deposits and read
+/// queries are rate limited per client IP, deposits more strictly.
+pub fn router_with_state(state: AppState) -> Router {
+    let admin = Router::new()
+        .route("/roots/prune", post(prune_recent_roots))
+        .route("/address-policy", get(get_address_policy).put(update_address_policy))
+        .route_layer(from_fn_with_state(state.clone(), require_admin_token));
     
-    Ok(Router::new()
-        .route("/api/health", get(health_check))
-        .route("/api/deposit", post(process_deposit))
+    let reads = Router::new()
         .route("/api/balance/:owner", get(get_balance))
+        .route("/api/balance/:owner/spendable", get(get_spendable_balance))
         .route("/api/utxos/:owner", get(get_owner_utxos))
         .route("/api/utxo/:utxo_id", get(get_utxo_details))
+        .route("/api/notes/:owner", get(get_owner_notes))
         .route("/api/tree/stats", get(get_tree_stats))
         .route("/api/tree/root", get(get_tree_root))
-        .with_state(state))
+        .route("/api/tree/utxo-set-root", get(get_utxo_set_root))
+        .route("/api/commitments/status", post(get_commitment_status))
+        .route("/api/proofs/verify", post(verify_merkle_proofs))
+        .route("/api/proof/:utxo_id", get(get_utxo_proof))
+        .route_layer(from_fn_with_state(state.read_rate_limiter.clone(), rate_limit));
+    
+    Router::new()
+        .route("/api/health", get(health_check))
+        .route("/live", get(liveness))
+        .route("/ready", get(readiness))
+        .route("/api/deposit", post(process_deposit)
+            .route_layer(from_fn_with_state(state.deposit_rate_limiter.clone(), rate_limit)))
+        .route("/api/withdraw", post(process_withdraw))
+        .route("/api/withdraw/batch", post(process_batch_withdraw))
+        .route("/api/transfer", post(process_transfer))
+        .route("/api/operator/pubkey", get(get_operator_pubkey))
+        .route("/api/commitment/verify", post(verify_commitment_opening))
+        .route("/api/ws/events", get(subscribe_events))
+        .route("/api/ws", get(subscribe_tree_feed))
+        .route("/api/openapi.json", get(openapi_json))
+        .merge(reads)
+        .nest("/api/admin", admin)
+        .with_state(state)
 }
 
 /// Health check endpoint
+#[utoipa::path(
+    get, path = "/api/health", tag = "system",
+    responses((status = 200, body = HealthResponse))
+)]
 pub async fn health_check(State(state): State<AppState>) -> Json<HealthResponse> {
     let tree_version = *state.tree_version.lock().unwrap();
     let utxo_count = state.utxos.lock().unwrap().len();
     
     Json(HealthResponse {
-        status: "healthy".to_string(),
+        status: if state.tree_unavailable.is_some() { "degraded" } else { "healthy" }.to_string(),
         timestamp: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
@@ -117,22 +426,104 @@ pub async fn health_check(State(state): State<AppState>) -> Json<HealthResponse>
     })
 }
 
+/// Liveness probe: succeeds as long as the process is serving requests
+#[utoipa::path(
+    get, path = "/live", tag = "system",
+    responses((status = 200, description = "Process is running", body = Object))
+)]
+pub async fn liveness() -> Json<serde_json::Value> {
+    Json(json!({ "status": "alive" }))
+}
+
+/// Readiness probe: succeeds only when deposits are synced and the store is usable
+///
+/// A pod that is catching up on deposits stays alive but is taken out of
+/// rotation until the watcher is within `max_ready_lag_blocks` of the head.
+#[utoipa::path(
+    get, path = "/ready", tag = "system",
+    responses(
+        (status = 200, body = ReadinessResponse),
+        (status = 503, description = "Deposit watcher lagging or store unavailable", body = ReadinessResponse),
+    )
+)]
+pub async fn readiness(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let lag = state.watcher_progress.lag();
+    let max_lag = state.config.max_ready_lag_blocks;
+    let database_open = state.utxos.lock().is_ok();
+    
+    let ready = database_open && lag.map_or(false, |lag| lag <= max_lag);
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    
+    (status, Json(ReadinessResponse {
+        ready,
+        checkpoint_block: state.watcher_progress.checkpoint_block(),
+        chain_head: state.watcher_progress.chain_head(),
+        lag,
+        max_lag,
+        database_status: if database_open { "in-memory".to_string() } else { "unavailable".to_string() },
+    }))
+}
+
 /// Process a single ETH deposit - VERIFIES BLOCKCHAIN TRANSACTION
+#[utoipa::path(
+    post, path = "/api/deposit", tag = "deposits",
+    request_body = DepositRequest,
+    responses(
+        (status = 200, body = DepositResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 503, description = "Tree unavailable (degraded mode)", body = ErrorResponse),
+    )
+)]
 pub async fn process_deposit(
     State(state): State<AppState>,
     Json(request): Json<DepositRequest>,
 ) -> std::result::Result<Json<DepositResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let rpc_urls = state.config.rpc_urls();
+    let chain = RpcChain {
+        client: &state.http_client,
+        rpc_urls: &rpc_urls,
+        health: &state.rpc_health,
+        max_response_bytes: state.config.max_rpc_response_bytes,
+    };
+    deposit_via_chain(&state, &chain, request).await
+}
+
+/// Verify a deposit transaction against `chain` and mint its UTXO
+async fn deposit_via_chain<C: ChainQuery>(
+    state: &AppState,
+    chain: &C,
+    request: DepositRequest,
+) -> std::result::Result<Json<DepositResponse>, (StatusCode, Json<ErrorResponse>)> {
+    state.require_tree()?;
+    
+    // Reject oversized scripts before any RPC or serialization work
+    let lock_data = decode_lock_data(request.lock_data.as_deref(), state.config.max_lock_data_bytes)?;
+
     println!(" VERIFYING BLOCKCHAIN TRANSACTION: {}", request.tx_hash);
 
     // STEP 1: VERIFY THE TRANSACTION EXISTS ON BLOCKCHAIN
-    let transaction_data = match verify_transaction_on_blockchain(
-        &request.tx_hash.to_string(),
-        &state.config.sepolia_rpc_url,
-        &state.config.contract_address
+    let transaction_data = match verify_deposit_transaction(
+        chain,
+        &format!("{:?}", request.tx_hash),
+        &state.config.contract_address,
+        state.config.min_deposit_confirmations,
     ).await {
         Ok(data) => data,
         Err(e) => {
             println!(" BLOCKCHAIN VERIFICATION FAILED: {}", e);
+            if e.downcast_ref::<RpcResponseTooLarge>().is_some() {
+                return Err(api_error("RPC_RESPONSE_TOO_LARGE", &e.to_string()));
+            }
+            if let Some(pending) = e.downcast_ref::<InsufficientConfirmations>() {
+                let (status, Json(mut body)) = api_error("INSUFFICIENT_CONFIRMATIONS", &e.to_string());
+                body.details = Some(json!({ "required": pending.required, "actual": pending.actual }));
+                return Err((status, Json(body)));
+            }
+            if let Some(rpc) = e.downcast_ref::<RpcError>() {
+                let (status, Json(mut body)) = api_error("RPC_ERROR", &e.to_string());
+                body.details = Some(json!({ "code": rpc.code, "message": rpc.message }));
+                return Err((status, Json(body)));
+            }
             return Err(api_error("BLOCKCHAIN_VERIFICATION_FAILED", &e.to_string()));
         }
     };
@@ -148,6 +539,15 @@ pub async fn process_deposit(
         Ok(addr) => addr,
         Err(e) => return Err(api_error("INVALID_DEPOSITOR", &format!("Invalid depositor address: {}", e))),
     };
+    
+    // The on-chain sender is screened, not the address the client claims
+    if !state.config.address_policy.read().unwrap().permits(&depositor_address) {
+        println!(" DEPOSITOR NOT PERMITTED: {:?}", depositor_address);
+        return Err(api_error(
+            "ADDRESS_NOT_PERMITTED",
+            &format!("Deposits from {:?} are not permitted", depositor_address),
+        ));
+    }
 
     let commitment_str = format!("{:?}", request.commitment);
     let commitment_hash = match hex::decode(&commitment_str.strip_prefix("0x").unwrap_or(&commitment_str)) {
@@ -175,18 +575,20 @@ pub async fn process_deposit(
             web3::types::H256::from_slice(&ph_bytes)
         }).unwrap_or(web3::types::H256::zero()),
         log_index: 0,
+        asset: web3::types::Address::zero(),
     };
 
     // STEP 3: Generate UTXO from VERIFIED deposit
-    let utxo = match create_utxo_from_verified_deposit(&deposit_event, &state) {
+    let utxo = match create_utxo_from_verified_deposit(&deposit_event, lock_data, state) {
         Ok(utxo) => utxo,
         Err(e) => return Err(api_error("UTXO_CREATION_FAILED", &e.to_string())),
     };
 
     // Calculate tree position
-    let tree_position = crate::canonical_spec::generate_tree_index(
+    let tree_position = crate::canonical_spec::tree_leaf_index(
         utxo.utxo_id,
-        state.config.tree_salt
+        state.config.tree_salt,
+        state.config.tree_depth
     );
 
     // Get leaf hash
@@ -196,31 +598,8 @@ pub async fn process_deposit(
     };
 
     // STEP 4: Update in-memory storage with VERIFIED data
-    {
-        let mut utxos = state.utxos.lock().unwrap();
-        utxos.insert(utxo.utxo_id, utxo.clone());
-
-        let mut owner_utxos = state.owner_utxos.lock().unwrap();
-        owner_utxos.entry(utxo.owner_commitment)
-            .or_insert_with(Vec::new)
-            .push(utxo.utxo_id);
-
-        let mut balances = state.balances.lock().unwrap();
-        let owner_balances = balances.entry(utxo.owner_commitment)
-            .or_insert_with(HashMap::new);
-        let (current_balance, current_count) = owner_balances.entry(utxo.asset_id)
-            .or_insert((0, 0));
-        *current_balance += utxo.amount;
-        *current_count += 1;
-
-        // Update tree version
-        let mut tree_version = state.tree_version.lock().unwrap();
-        *tree_version += 1;
-
-        // Simple tree root update (in this would be proper SMT)
-        let mut tree_root = state.tree_root.lock().unwrap();
-        *tree_root = crate::canonical_spec::generate_node_hash(*tree_root, leaf_hash);
-    }
+    record_deposit(state, &utxo, deposit_event.commitment.0, leaf_hash, request.encrypted_note.clone())
+        .map_err(|e| api_error("LEAF_OCCUPIED", &e.to_string()))?;
 
     println!(" UTXO CREATED FROM VERIFIED BLOCKCHAIN DEPOSIT!");
 
@@ -240,10 +619,526 @@ pub async fn process_deposit(
     Ok(Json(response))
 }
 
+/// Withdraw several UTXOs atomically
+///
+/// Every item is validated first (owner signature over the recipient,
+/// authorization opens the UTXO's spend commitment, nullifier derived from
+/// the owner's nullifier key, proof root inside the tolerance window, no
+/// conflicts within the batch or with earlier spends). Only then are all
+/// spends applied, so a single bad item rejects the whole batch and leaves
+/// state untouched.
+#[utoipa::path(
+    post, path = "/api/withdraw/batch", tag = "withdrawals",
+    request_body = BatchWithdrawRequest,
+    responses(
+        (status = 200, body = BatchWithdrawResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 503, description = "Tree unavailable (degraded mode)", body = ErrorResponse),
+    )
+)]
+pub async fn process_batch_withdraw(
+    State(state): State<AppState>,
+    Json(request): Json<BatchWithdrawRequest>,
+) -> Result<Json<BatchWithdrawResponse>, (StatusCode, Json<ErrorResponse>)> {
+    state.require_tree()?;
+    
+    if request.withdrawals.is_empty() {
+        return Err(api_error("EMPTY_BATCH", "Batch contains no withdrawals"));
+    }
+    
+    let mut items = Vec::with_capacity(request.withdrawals.len());
+    for (i, withdrawal) in request.withdrawals.iter().enumerate() {
+        items.push(parse_spend(
+            "Withdrawal", i, &withdrawal.utxo_id, &withdrawal.nullifier, &withdrawal.merkle_root, &withdrawal.siblings, &withdrawal.authorization,
+        )?);
+    }
+    let recipients: Vec<_> = request.withdrawals.iter().map(|withdrawal| withdrawal.recipient).collect();
+    
+    let applied = apply_spends(&state, &items, SpendKind::Withdrawal(&recipients))?;
+    let total_amount: u128 = applied.spent.iter().map(|(utxo, _)| utxo.amount).sum();
+    
+    Ok(Json(BatchWithdrawResponse {
+        success: true,
+        nullifiers: applied.spent.iter().map(|(_, nullifier)| utils::hash_to_hex(*nullifier)).collect(),
+        recipients: recipients.iter().map(|recipient| format!("{:?}", recipient)).collect(),
+        total_amount: total_amount.to_string(),
+        new_root: utils::hash_to_hex(applied.new_root),
+        root_version: applied.root_version,
+    }))
+}
+
+/// Withdraw a single UTXO
+///
+/// Same validation as a one-item batch.
+#[utoipa::path(
+    post, path = "/api/withdraw", tag = "withdrawals",
+    request_body = WithdrawRequest,
+    responses(
+        (status = 200, body = WithdrawResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 503, description = "Tree unavailable (degraded mode)", body = ErrorResponse),
+    )
+)]
+pub async fn process_withdraw(
+    State(state): State<AppState>,
+    Json(request): Json<WithdrawRequest>,
+) -> Result<Json<WithdrawResponse>, (StatusCode, Json<ErrorResponse>)> {
+    state.require_tree()?;
+    
+    let item = parse_spend(
+        "Withdrawal", 0, &request.utxo_id, &request.nullifier, &request.merkle_root, &request.siblings, &request.authorization,
+    )?;
+    let applied = apply_spends(&state, &[item], SpendKind::Withdrawal(&[request.recipient]))?;
+    let (utxo, nullifier) = &applied.spent[0];
+    
+    Ok(Json(WithdrawResponse {
+        success: true,
+        nullifier: utils::hash_to_hex(*nullifier),
+        recipient: format!("{:?}", request.recipient),
+        amount: utxo.amount.to_string(),
+        new_root: utils::hash_to_hex(applied.new_root),
+        root_version: applied.root_version,
+        processed_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    }))
+}
+
+/// Spend UTXOs into new outputs of equal total value
+///
+/// Inputs are validated like withdrawals, with every input's owner signing
+/// the whole transfer; outputs must carry fresh commitments. Spends and new
+/// outputs land in a single tree update.
+#[utoipa::path(
+    post, path = "/api/transfer", tag = "withdrawals",
+    request_body = TransferRequest,
+    responses(
+        (status = 200, body = TransferResponse),
+        (status = 400, body = ErrorResponse),
+        (status = 503, description = "Tree unavailable (degraded mode)", body = ErrorResponse),
+    )
+)]
+pub async fn process_transfer(
+    State(state): State<AppState>,
+    Json(request): Json<TransferRequest>,
+) -> Result<Json<TransferResponse>, (StatusCode, Json<ErrorResponse>)> {
+    state.require_tree()?;
+    
+    if request.inputs.is_empty() || request.outputs.is_empty() {
+        return Err(api_error("EMPTY_TRANSFER", "Transfer needs at least one input and one output"));
+    }
+    
+    let mut items = Vec::with_capacity(request.inputs.len());
+    for (i, input) in request.inputs.iter().enumerate() {
+        items.push(parse_spend("Input", i, &input.utxo_id, &input.nullifier, &input.merkle_root, &input.siblings, &input.authorization)?);
+    }
+    
+    let outputs = request.outputs.iter()
+        .enumerate()
+        .map(|(i, output)| parse_output(i, output))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    
+    let applied = apply_spends(&state, &items, SpendKind::Transfer(&outputs))?;
+    
+    Ok(Json(TransferResponse {
+        success: true,
+        nullifiers: applied.spent.iter().map(|(_, nullifier)| utils::hash_to_hex(*nullifier)).collect(),
+        utxo_ids: applied.created.iter().map(|utxo| utils::hash_to_hex(utxo.utxo_id)).collect(),
+        new_root: utils::hash_to_hex(applied.new_root),
+        root_version: applied.root_version,
+        processed_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    }))
+}
+
+/// Spent input decoded from a request
+struct SpendItem {
+    utxo_id: [u8; 32],
+    nullifier: [u8; 32],
+    /// Tree root the spend proof was generated against
+    merkle_root: [u8; 32],
+    /// Inclusion path of the UTXO leaf under `merkle_root`, leaf to root
+    siblings: Vec<[u8; 32]>,
+    spend_auth_key: RedJubjubPublicKey,
+    nullifier_key: [u8; 32],
+    note_secret: [u8; 32],
+    signature: RedJubjubSignature,
+}
+
+impl SpendItem {
+    /// Spend commitment the authorization opens
+    fn spend_commitment(&self) -> [u8; 32] {
+        ArchitectureCompliantCrypto::compute_spend_commitment(
+            self.spend_auth_key.as_bytes(),
+            &self.nullifier_key,
+            &self.note_secret,
+        )
+    }
+}
+
+/// Decoded transfer output
+struct TransferOutputSpec {
+    commitment: [u8; 32],
+    owner_commitment: [u8; 32],
+    amount: u128,
+}
+
+/// What a set of spends pays for
+enum SpendKind<'a> {
+    /// Funds leave the pool to the given recipients, one per input; each
+    /// input must meet the anonymity set minimum
+    Withdrawal(&'a [web3::types::Address]),
+    /// Funds move to new outputs of equal total value
+    Transfer(&'a [TransferOutputSpec]),
+}
+
+/// State change made by `apply_spends`
+struct AppliedSpends {
+    spent: Vec<(CanonicalUTXO, [u8; 32])>,
+    created: Vec<CanonicalUTXO>,
+    root_version: u64,
+    new_root: [u8; 32],
+}
+
+/// Decode the hex fields of one spent input
+fn parse_spend(
+    label: &str,
+    index: usize,
+    utxo_id: &str,
+    nullifier: &str,
+    merkle_root: &str,
+    siblings: &[String],
+    authorization: &SpendAuthorization,
+) -> std::result::Result<SpendItem, (StatusCode, Json<ErrorResponse>)> {
+    let hash = |field: &str, code: &str| utils::hex_to_hash(field)
+        .map_err(|e| api_error(code, &format!("{} {}: {}", label, index, e)));
+    let signature_hex = authorization.signature.strip_prefix("0x").unwrap_or(&authorization.signature);
+    let signature: [u8; 64] = hex::decode(signature_hex)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| api_error("INVALID_SIGNATURE", &format!("{} {}: signature must be 64 hex-encoded bytes", label, index)))?;
+    
+    Ok(SpendItem {
+        utxo_id: hash(utxo_id, "INVALID_UTXO_ID")?,
+        nullifier: hash(nullifier, "INVALID_NULLIFIER")?,
+        merkle_root: hash(merkle_root, "INVALID_ROOT")?,
+        siblings: siblings.iter()
+            .map(|sibling| hash(sibling, "INVALID_MERKLE_PROOF"))
+            .collect::<std::result::Result<_, _>>()?,
+        spend_auth_key: RedJubjubPublicKey::new(hash(&authorization.spend_auth_key, "INVALID_AUTHORIZATION")?),
+        nullifier_key: hash(&authorization.nullifier_key, "INVALID_AUTHORIZATION")?,
+        note_secret: hash(&authorization.note_secret, "INVALID_AUTHORIZATION")?,
+        signature: RedJubjubSignature::from_bytes(signature),
+    })
+}
+
+/// Decode the fields of one transfer output
+fn parse_output(
+    index: usize,
+    output: &TransferOutput,
+) -> std::result::Result<TransferOutputSpec, (StatusCode, Json<ErrorResponse>)> {
+    let commitment = utils::hex_to_hash(&output.commitment)
+        .map_err(|e| api_error("INVALID_COMMITMENT", &format!("Output {}: {}", index, e)))?;
+    let owner_commitment = utils::hex_to_hash(&output.owner_commitment)
+        .map_err(|e| api_error("INVALID_OWNER", &format!("Output {}: {}", index, e)))?;
+    let amount = output.amount.parse::<u128>()
+        .ok()
+        .filter(|amount| *amount > 0)
+        .ok_or_else(|| api_error("INVALID_AMOUNT", &format!("Output {}: amount must be a positive integer", index)))?;
+    Ok(TransferOutputSpec { commitment, owner_commitment, amount })
+}
+
+/// Message a withdrawal's owner signs, binding the spend to its recipient
+fn withdrawal_message(item: &SpendItem, recipient: &web3::types::Address) -> [u8; 32] {
+    CryptoUtils::keccak256(&[
+        &b"PRIVPOOL_WITHDRAW_V1"[..],
+        &item.utxo_id,
+        &item.nullifier,
+        &item.merkle_root,
+        recipient.as_bytes(),
+    ].concat())
+}
+
+/// Message every transfer input's owner signs, binding all inputs and outputs
+fn transfer_message(items: &[SpendItem], outputs: &[TransferOutputSpec]) -> [u8; 32] {
+    let mut message = b"PRIVPOOL_TRANSFER_V1".to_vec();
+    for item in items {
+        message.extend_from_slice(&item.utxo_id);
+        message.extend_from_slice(&item.nullifier);
+        message.extend_from_slice(&item.merkle_root);
+    }
+    for output in outputs {
+        message.extend_from_slice(&output.commitment);
+        message.extend_from_slice(&output.owner_commitment);
+        message.extend_from_slice(&output.amount.to_be_bytes());
+    }
+    CryptoUtils::keccak256(&message)
+}
+
+/// Validate every input, then spend them and insert any outputs as one tree update
+///
+/// Nothing is mutated unless the whole request validates.
+fn apply_spends(
+    state: &AppState,
+    items: &[SpendItem],
+    kind: SpendKind<'_>,
+) -> std::result::Result<AppliedSpends, (StatusCode, Json<ErrorResponse>)> {
+    let label = match kind {
+        SpendKind::Withdrawal(_) => "Withdrawal",
+        SpendKind::Transfer(_) => "Input",
+    };
+    
+    // Only the spending key holder can sign; checked before taking any lock
+    let transfer_digest = match kind {
+        SpendKind::Transfer(outputs) => Some(transfer_message(items, outputs)),
+        SpendKind::Withdrawal(_) => None,
+    };
+    for (i, item) in items.iter().enumerate() {
+        let message = match kind {
+            SpendKind::Withdrawal(recipients) => withdrawal_message(item, &recipients[i]),
+            SpendKind::Transfer(_) => transfer_digest.expect("set for transfers"),
+        };
+        if !RedJubjubSignatureScheme::verify(&item.signature, &message, &item.spend_auth_key) {
+            return Err(api_error("INVALID_SIGNATURE", &format!(
+                "{} {}: spend signature does not verify", label, i
+            )));
+        }
+    }
+    
+    let applied = {
+        // Same lock order as record_deposit
+        let mut utxos = state.utxos.lock().unwrap();
+        let mut owner_utxos = state.owner_utxos.lock().unwrap();
+        let mut commitment_index = state.commitment_index.lock().unwrap();
+        let mut balances = state.balances.lock().unwrap();
+        let mut utxo_tree = state.utxo_tree.lock().unwrap();
+        let mut tree_version = state.tree_version.lock().unwrap();
+        let mut tree_root = state.tree_root.lock().unwrap();
+        let mut recent_roots = state.recent_roots.lock().unwrap();
+        let mut spent_nullifiers = state.spent_nullifiers.lock().unwrap();
+        
+        // Validate every input before touching any state
+        let mut request_nullifiers = HashSet::new();
+        let mut withdrawn_earlier: HashMap<([u8; 20], u128), usize> = HashMap::new();
+        for (i, item) in items.iter().enumerate() {
+            let SpendItem { utxo_id, nullifier, merkle_root, .. } = item;
+            if !recent_roots.contains(merkle_root) {
+                return Err(api_error("STALE_ROOT", &format!(
+                    "{} {}: proof root {} is not among the last {} roots",
+                    label, i, utils::hash_to_hex(*merkle_root), state.config.root_tolerance_window
+                )));
+            }
+            if !request_nullifiers.insert(*nullifier) {
+                return Err(api_error("DUPLICATE_NULLIFIER", &format!(
+                    "{} {}: nullifier {} appears more than once in the request",
+                    label, i, utils::hash_to_hex(*nullifier)
+                )));
+            }
+            if spent_nullifiers.contains(nullifier) {
+                return Err(api_error("NULLIFIER_SPENT", &format!(
+                    "{} {}: nullifier {} is already spent", label, i, utils::hash_to_hex(*nullifier)
+                )));
+            }
+            let utxo = utxos.get(utxo_id).ok_or_else(|| api_error("UTXO_NOT_FOUND", &format!(
+                "{} {}: UTXO {} not found", label, i, utils::hash_to_hex(*utxo_id)
+            )))?;
+            let leaf_hash = utxo.leaf_hash()
+                .map_err(|e| api_error("LEAF_HASH_FAILED", &format!("{} {}: {}", label, i, e)))?;
+            let proven_root = crate::canonical_spec::compute_root_from_proof(
+                leaf_hash,
+                utxo_tree.leaf_position(utxo_id),
+                &item.siblings,
+            );
+            if item.siblings.len() != utxo_tree.get_depth() as usize || proven_root != *merkle_root {
+                return Err(api_error("INVALID_MERKLE_PROOF", &format!(
+                    "{} {}: UTXO {} is not included under root {}",
+                    label, i, utils::hash_to_hex(*utxo_id), utils::hash_to_hex(*merkle_root)
+                )));
+            }
+            if commitment_index.get(&item.spend_commitment()) != Some(utxo_id) {
+                return Err(api_error("UNAUTHORIZED_SPEND", &format!(
+                    "{} {}: authorization does not open the spend commitment of UTXO {}",
+                    label, i, utils::hash_to_hex(*utxo_id)
+                )));
+            }
+            let expected_nullifier = ArchitectureCompliantCrypto::derive_nullifier_from_key(&item.nullifier_key, utxo_id, &item.note_secret)
+                .map_err(|e| api_error("INVALID_NULLIFIER", &format!("{} {}: {}", label, i, e)))?;
+            if !CryptoUtils::constant_time_eq(&expected_nullifier, nullifier) {
+                return Err(api_error("INVALID_NULLIFIER", &format!(
+                    "{} {}: nullifier is not derived from the owner's nullifier key", label, i
+                )));
+            }
+            if let SpendKind::Withdrawal(_) = kind {
+                // UTXOs withdrawn earlier in this batch are gone by the time this one leaves
+                let earlier = withdrawn_earlier.entry((utxo.asset_id, utxo.amount)).or_insert(0);
+                let set_size = anonymity_set_size(&utxos, &utxo.asset_id, utxo.amount).saturating_sub(*earlier);
+                *earlier += 1;
+                if set_size < state.config.min_anonymity_set {
+                    return Err(api_error("ANONYMITY_SET_TOO_SMALL", &format!(
+                        "{} {}: anonymity set for denomination {} has {} UTXOs, {} required",
+                        label, i, utxo.amount, set_size, state.config.min_anonymity_set
+                    )));
+                }
+            }
+        }
+        
+        // Build transfer outputs and check value conservation
+        let mut created: Vec<(CanonicalUTXO, [u8; 32], [u8; 32])> = Vec::new();
+        if let SpendKind::Transfer(outputs) = kind {
+            let inputs: Vec<&CanonicalUTXO> = items.iter().map(|item| &utxos[&item.utxo_id]).collect();
+            if inputs.iter().any(|utxo| utxo.asset_id != crate::canonical_spec::utxo_format::ETH_ASSET_ID) {
+                return Err(api_error("UNSUPPORTED_ASSET", "Transfers only support ETH inputs"));
+            }
+            let input_total: u128 = inputs.iter().map(|utxo| utxo.amount).sum();
+            let output_total = outputs.iter().try_fold(0u128, |total, output| total.checked_add(output.amount));
+            if output_total != Some(input_total) {
+                return Err(api_error("VALUE_MISMATCH", &format!(
+                    "Inputs total {} but outputs total {}",
+                    input_total, output_total.map_or("more than u128::MAX".to_string(), |total| total.to_string())
+                )));
+            }
+            
+            let mut request_commitments = HashSet::new();
+            for (i, output) in outputs.iter().enumerate() {
+                if !request_commitments.insert(output.commitment) || commitment_index.contains_key(&output.commitment) {
+                    return Err(api_error("COMMITMENT_EXISTS", &format!(
+                        "Output {}: commitment {} is already in use", i, utils::hash_to_hex(output.commitment)
+                    )));
+                }
+            }
+            
+            // Outputs are bound to the spent nullifiers
+            let mut txid_preimage = Vec::with_capacity(items.len() * 32);
+            for item in items {
+                txid_preimage.extend_from_slice(&item.nullifier);
+            }
+            let txid = CryptoUtils::keccak256(&txid_preimage);
+            let created_block = inputs.iter().map(|utxo| utxo.created_block).max().unwrap_or(0);
+            
+            for (vout, output) in outputs.iter().enumerate() {
+                let entropy = {
+                    let mut beacon_index = state.beacon_index.lock().unwrap();
+                    *beacon_index += 1;
+                    state.config.randomness_beacon.entropy(*beacon_index)
+                };
+                let utxo = CanonicalUTXO::new_eth(txid, vout as u32, created_block, entropy, output.amount, output.owner_commitment);
+                let leaf_hash = utxo.leaf_hash()
+                    .map_err(|e| api_error("LEAF_HASH_FAILED", &format!("Output {}: {}", vout, e)))?;
+                if utxos.contains_key(&utxo.utxo_id) {
+                    return Err(api_error("UTXO_EXISTS", &format!("Output {}: UTXO already exists", vout)));
+                }
+                // Slots freed by this request's spends may be reused
+                let position = utxo_tree.leaf_position(&utxo.utxo_id);
+                let freed_by_spend = items.iter().any(|item| utxo_tree.leaf_position(&item.utxo_id) == position);
+                let taken_by_output = created.iter().any(|(other, _, _)| utxo_tree.leaf_position(&other.utxo_id) == position);
+                if taken_by_output || (utxo_tree.get_leaf(position).is_some() && !freed_by_spend) {
+                    return Err(api_error("LEAF_OCCUPIED", &format!(
+                        "Output {}: tree position {} is already occupied", vout, position
+                    )));
+                }
+                created.push((utxo, output.commitment, leaf_hash));
+            }
+        }
+        
+        // Commit every spend
+        let mut spent = Vec::with_capacity(items.len());
+        for SpendItem { utxo_id, nullifier, .. } in items {
+            let utxo = utxos.remove(utxo_id).expect("validated above");
+            if let Some(ids) = owner_utxos.get_mut(&utxo.owner_commitment) {
+                ids.retain(|id| id != utxo_id);
+            }
+            if let Some((balance, count)) = balances
+                .get_mut(&utxo.owner_commitment)
+                .and_then(|owner_balances| owner_balances.get_mut(&utxo.asset_id))
+            {
+                *balance = balance.saturating_sub(utxo.amount);
+                *count = count.saturating_sub(1);
+            }
+            
+            spent_nullifiers.insert(*nullifier);
+            let position = utxo_tree.leaf_position(utxo_id);
+            utxo_tree.remove_leaf(position);
+            spent.push((utxo, *nullifier));
+        }
+        
+        // Insert every output
+        for (utxo, commitment, leaf_hash) in &created {
+            utxos.insert(utxo.utxo_id, utxo.clone());
+            owner_utxos.entry(utxo.owner_commitment)
+                .or_insert_with(Vec::new)
+                .push(utxo.utxo_id);
+            commitment_index.insert(*commitment, utxo.utxo_id);
+            let (balance, count) = balances.entry(utxo.owner_commitment)
+                .or_insert_with(HashMap::new)
+                .entry(utxo.asset_id)
+                .or_insert((0, 0));
+            *balance += utxo.amount;
+            *count += 1;
+            
+            let position = utxo_tree.leaf_position(&utxo.utxo_id);
+            utxo_tree.insert_leaf(position, *leaf_hash)
+                .expect("tree positions validated above");
+        }
+        
+        *tree_root = utxo_tree.get_root();
+        *tree_version += 1;
+        remember_root(&mut recent_roots, *tree_root, state.config.root_tolerance_window);
+        
+        let mut pool_counters = state.pool_counters.lock().unwrap();
+        pool_counters.total_utxos = (pool_counters.total_utxos + created.len() as u64).saturating_sub(spent.len() as u64);
+        pool_counters.total_spent += spent.len() as u64;
+        
+        AppliedSpends {
+            spent,
+            created: created.into_iter().map(|(utxo, _, _)| utxo).collect(),
+            root_version: *tree_version,
+            new_root: *tree_root,
+        }
+    };
+    
+    // No subscribers is not an error
+    let spend_event = match kind {
+        SpendKind::Withdrawal(_) => PoolEventType::Withdrawal,
+        SpendKind::Transfer(_) => PoolEventType::Transfer,
+    };
+    let events = applied.spent.iter().map(|(utxo, _)| (spend_event, utxo))
+        .chain(applied.created.iter().map(|utxo| (PoolEventType::Transfer, utxo)));
+    for (event_type, utxo) in events {
+        let _ = state.events.send(PoolEvent {
+            event_type,
+            owner_commitment: utils::hash_to_hex(utxo.owner_commitment),
+            utxo_id: utils::hash_to_hex(utxo.utxo_id),
+            amount: utxo.amount.to_string(),
+            root_version: applied.root_version,
+        });
+    }
+    if let Some((utxo, _)) = applied.spent.first() {
+        let _ = state.tree_feed.send(TreeFeedMessage::RootUpdate {
+            root: utils::hash_to_hex(applied.new_root),
+            root_version: applied.root_version,
+            utxo_id: utils::hash_to_hex(utxo.utxo_id),
+        });
+    }
+    
+    Ok(applied)
+}
+
 /// Get balance for an owner  
+#[utoipa::path(
+    get, path = "/api/balance/{owner}", tag = "balances",
+    params(("owner" = String, Path, description = "Owner commitment (hex)"), FormatQuery),
+    responses(
+        (status = 200, body = BalanceInfo),
+        (status = 400, body = ErrorResponse),
+    )
+)]
 pub async fn get_balance(
     State(state): State<AppState>,
     Path(owner_hex): Path<String>,
+    Query(query): Query<FormatQuery>,
 ) -> Result<Json<BalanceInfo>, (StatusCode, Json<ErrorResponse>)> {
     let owner_commitment = match utils::hex_to_hash(&owner_hex) {
         Ok(hash) => hash,
@@ -252,61 +1147,166 @@ pub async fn get_balance(
     
     let asset_id = [0u8; 20]; // ETH
     
-    let balances = state.balances.lock().unwrap();
-    let (balance, utxo_count) = balances
-        .get(&owner_commitment)
-        .and_then(|owner_balances| owner_balances.get(&asset_id))
-        .copied()
-        .unwrap_or((0, 0));
+    let (balance, utxo_count, last_updated_block) = match state.degraded_store() {
+        Some(store) => store.get_asset_balance(&owner_commitment, &asset_id)
+            .map_err(|e| api_error("DATABASE_ERROR", &e.to_string()))?,
+        None => {
+            let balances = state.balances.lock().unwrap();
+            let (balance, utxo_count) = balances
+                .get(&owner_commitment)
+                .and_then(|owner_balances| owner_balances.get(&asset_id))
+                .copied()
+                .unwrap_or((0, 0));
+            (balance, utxo_count, 0)
+        }
+    };
     
     Ok(Json(BalanceInfo {
         balance: balance.to_string(),
+        balance_decimal: decimal_amount(&state.config, asset_id, balance, query.format),
         utxo_count,
-        last_updated_block: 0,
+        last_updated_block,
         asset_id: utils::asset_id_to_hex(asset_id),
     }))
 }
 
-/// Get UTXOs for an owner
-pub async fn get_owner_utxos(
+/// Get total and spendable balance for an owner
+#[utoipa::path(
+    get, path = "/api/balance/{owner}/spendable", tag = "balances",
+    params(("owner" = String, Path, description = "Owner commitment (hex)"), SpendableBalanceQuery),
+    responses(
+        (status = 200, body = SpendableBalanceInfo),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_spendable_balance(
     State(state): State<AppState>,
     Path(owner_hex): Path<String>,
-    Query(query): Query<UTXOQuery>,
-) -> Result<Json<UTXOListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    Query(query): Query<SpendableBalanceQuery>,
+) -> Result<Json<SpendableBalanceInfo>, (StatusCode, Json<ErrorResponse>)> {
     let owner_commitment = match utils::hex_to_hash(&owner_hex) {
         Ok(hash) => hash,
         Err(_) => return Err(api_error("INVALID_OWNER", "Invalid owner commitment format")),
     };
     
+    let asset_id = [0u8; 20]; // ETH
+    // Timelocks are never judged against block 0, which would report them all locked
+    let current_block = match query.current_block {
+        Some(block) => block,
+        None => match state.watcher_progress.chain_head() {
+            0 => return Err(api_error("MISSING_CURRENT_BLOCK", "current_block is required until the chain head is known")),
+            head => head,
+        },
+    };
+    let overflow = || api_error("BALANCE_OVERFLOW", "Balance exceeds the representable amount");
+    
     let owner_utxos = state.owner_utxos.lock().unwrap();
     let utxos_map = state.utxos.lock().unwrap();
     
+    let mut total = 0u128;
+    let mut spendable = 0u128;
+    let mut utxo_count = 0u32;
+    let mut spendable_utxo_count = 0u32;
+    
     let utxo_ids = owner_utxos.get(&owner_commitment).cloned().unwrap_or_default();
+    for utxo in utxo_ids.iter().filter_map(|utxo_id| utxos_map.get(utxo_id)) {
+        if utxo.asset_id != asset_id {
+            continue;
+        }
+        
+        total = total.checked_add(utxo.amount).ok_or_else(overflow)?;
+        utxo_count += 1;
+        
+        if utxo.is_spendable(current_block) {
+            spendable = spendable.checked_add(utxo.amount).ok_or_else(overflow)?;
+            spendable_utxo_count += 1;
+        }
+    }
+    
+    Ok(Json(SpendableBalanceInfo {
+        total: total.to_string(),
+        spendable: spendable.to_string(),
+        total_decimal: decimal_amount(&state.config, asset_id, total, query.format),
+        spendable_decimal: decimal_amount(&state.config, asset_id, spendable, query.format),
+        utxo_count,
+        spendable_utxo_count,
+        current_block,
+        asset_id: utils::asset_id_to_hex(asset_id),
+    }))
+}
+
+/// Get UTXOs for an owner, ordered by `(created_block, utxo_id)` unless
+/// `sort` selects another order (see [`UTXOSort`])
+#[utoipa::path(
+    get, path = "/api/utxos/{owner}", tag = "utxos",
+    params(("owner" = String, Path, description = "Owner commitment (hex)"), UTXOQuery),
+    responses(
+        (status = 200, body = UTXOListResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_owner_utxos(
+    State(state): State<AppState>,
+    Path(owner_hex): Path<String>,
+    Query(query): Query<UTXOQuery>,
+) -> Result<Json<UTXOListResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let owner_commitment = match utils::hex_to_hash(&owner_hex) {
+        Ok(hash) => hash,
+        Err(_) => return Err(api_error("INVALID_OWNER", "Invalid owner commitment format")),
+    };
+    
     let limit = query.limit.unwrap_or(100);
     
-    let mut utxo_infos = Vec::new();
-    for (i, utxo_id) in utxo_ids.iter().enumerate() {
-        if i >= limit {
-            break;
+    // Order before truncating so `limit` always returns the same prefix
+    let mut utxos: Vec<CanonicalUTXO> = match state.degraded_store() {
+        Some(store) => {
+            let mut page = store.list_owner_utxos(&owner_commitment, None, 1024)
+                .map_err(|e| api_error("DATABASE_ERROR", &e.to_string()))?;
+            let mut utxos = std::mem::take(&mut page.utxos);
+            while let Some(cursor) = page.next_cursor {
+                page = store.list_owner_utxos_from(&owner_commitment, &cursor, 1024)
+                    .map_err(|e| api_error("DATABASE_ERROR", &e.to_string()))?;
+                utxos.append(&mut page.utxos);
+            }
+            utxos
         }
-        
-        if let Some(utxo) = utxos_map.get(utxo_id) {
-            let tree_position = crate::canonical_spec::generate_tree_index(
-                utxo.utxo_id, 
-                state.config.tree_salt
-            );
-            
-            utxo_infos.push(UTXOInfo {
-                utxo_id: utils::hash_to_hex(utxo.utxo_id),
-                amount: utxo.amount.to_string(),
-                asset_id: utils::asset_id_to_hex(utxo.asset_id),
-                created_block: utxo.created_block,
-                tree_position,
-                lock_expiry: if utxo.lock_expiry > 0 { Some(utxo.lock_expiry) } else { None },
-                lock_flags: utxo.lock_flags,
-                is_spent: false,
-            });
+        None => {
+            let owner_utxos = state.owner_utxos.lock().unwrap();
+            let utxos_map = state.utxos.lock().unwrap();
+            owner_utxos.get(&owner_commitment)
+                .into_iter()
+                .flatten()
+                .filter_map(|utxo_id| utxos_map.get(utxo_id).cloned())
+                .collect()
         }
+    };
+    match query.sort.unwrap_or_default() {
+        UTXOSort::CreatedAsc => utxos.sort_by_key(|utxo| (utxo.created_block, utxo.utxo_id)),
+        UTXOSort::AmountDesc => utxos.sort_by(|a, b| {
+            b.amount.cmp(&a.amount)
+                .then_with(|| (a.created_block, a.utxo_id).cmp(&(b.created_block, b.utxo_id)))
+        }),
+    }
+    
+    let mut utxo_infos = Vec::new();
+    for utxo in utxos.into_iter().take(limit) {
+        let tree_position = crate::canonical_spec::tree_leaf_index(
+            utxo.utxo_id,
+            state.config.tree_salt,
+            state.config.tree_depth
+        );
+        
+        utxo_infos.push(UTXOInfo {
+            utxo_id: utils::hash_to_hex(utxo.utxo_id),
+            amount: utxo.amount.to_string(),
+            amount_decimal: decimal_amount(&state.config, utxo.asset_id, utxo.amount, query.format),
+            asset_id: utils::asset_id_to_hex(utxo.asset_id),
+            created_block: utxo.created_block,
+            tree_position,
+            lock_expiry: if utxo.lock_expiry > 0 { Some(utxo.lock_expiry) } else { None },
+            lock_flags: utxo.lock_flags,
+            is_spent: false,
+        });
     }
     
     Ok(Json(UTXOListResponse {
@@ -316,30 +1316,98 @@ pub async fn get_owner_utxos(
     }))
 }
 
-/// Get specific UTXO details
-pub async fn get_utxo_details(
+/// Get encrypted notes for an owner's UTXOs, paginated by block
+#[utoipa::path(
+    get, path = "/api/notes/{owner}", tag = "utxos",
+    params(("owner" = String, Path, description = "Owner commitment (hex)"), NotesQuery),
+    responses(
+        (status = 200, body = NotesResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_owner_notes(
     State(state): State<AppState>,
-    Path(utxo_id_hex): Path<String>,
-) -> Result<Json<UTXOInfo>, (StatusCode, Json<ErrorResponse>)> {
-    let utxo_id = match utils::hex_to_hash(&utxo_id_hex) {
+    Path(owner_hex): Path<String>,
+    Query(query): Query<NotesQuery>,
+) -> Result<Json<NotesResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let owner_commitment = match utils::hex_to_hash(&owner_hex) {
         Ok(hash) => hash,
-        Err(_) => return Err(api_error("INVALID_UTXO_ID", "Invalid UTXO ID format")),
+        Err(_) => return Err(api_error("INVALID_OWNER", "Invalid owner commitment format")),
     };
     
-    let utxos = state.utxos.lock().unwrap();
-    let utxo = match utxos.get(&utxo_id) {
-        Some(utxo) => utxo,
-        None => return Err(api_error("UTXO_NOT_FOUND", "UTXO not found")),
-    };
+    let from_block = query.from_block.unwrap_or(0);
+    let limit = query.limit.unwrap_or(100).max(1);
     
-    let tree_position = crate::canonical_spec::generate_tree_index(
-        utxo.utxo_id, 
-        state.config.tree_salt
-    );
+    let owner_utxos = state.owner_utxos.lock().unwrap();
+    let utxos_map = state.utxos.lock().unwrap();
+    let encrypted_notes = state.encrypted_notes.lock().unwrap();
     
-    Ok(Json(UTXOInfo {
-        utxo_id: utils::hash_to_hex(utxo.utxo_id),
-        amount: utxo.amount.to_string(),
+    let utxo_ids = owner_utxos.get(&owner_commitment).cloned().unwrap_or_default();
+    let mut entries: Vec<(u64, [u8; 32])> = utxo_ids.iter()
+        .filter_map(|utxo_id| utxos_map.get(utxo_id))
+        .filter(|utxo| utxo.created_block >= from_block && encrypted_notes.contains_key(&utxo.utxo_id))
+        .map(|utxo| (utxo.created_block, utxo.utxo_id))
+        .collect();
+    entries.sort();
+    
+    let mut notes = Vec::new();
+    let mut next_from_block = None;
+    for (created_block, utxo_id) in entries {
+        // Only stop on a block boundary so a page never splits a block
+        let last_block = notes.last().map(|note: &EncryptedNoteInfo| note.created_block);
+        if notes.len() >= limit && last_block != Some(created_block) {
+            next_from_block = Some(created_block);
+            break;
+        }
+        
+        notes.push(EncryptedNoteInfo {
+            utxo_id: utils::hash_to_hex(utxo_id),
+            created_block,
+            note: encrypted_notes[&utxo_id].clone(),
+        });
+    }
+    
+    Ok(Json(NotesResponse {
+        notes,
+        next_from_block,
+    }))
+}
+
+/// Get specific UTXO details
+#[utoipa::path(
+    get, path = "/api/utxo/{utxo_id}", tag = "utxos",
+    params(("utxo_id" = String, Path, description = "UTXO ID (hex)"), FormatQuery),
+    responses(
+        (status = 200, body = UTXOInfo),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_utxo_details(
+    State(state): State<AppState>,
+    Path(utxo_id_hex): Path<String>,
+    Query(query): Query<FormatQuery>,
+) -> Result<Json<UTXOInfo>, (StatusCode, Json<ErrorResponse>)> {
+    let utxo_id = match utils::hex_to_hash(&utxo_id_hex) {
+        Ok(hash) => hash,
+        Err(_) => return Err(api_error("INVALID_UTXO_ID", "Invalid UTXO ID format")),
+    };
+    
+    let utxos = state.utxos.lock().unwrap();
+    let utxo = match utxos.get(&utxo_id) {
+        Some(utxo) => utxo,
+        None => return Err(api_error("UTXO_NOT_FOUND", "UTXO not found")),
+    };
+    
+    let tree_position = crate::canonical_spec::tree_leaf_index(
+        utxo.utxo_id,
+        state.config.tree_salt,
+        state.config.tree_depth
+    );
+    
+    Ok(Json(UTXOInfo {
+        utxo_id: utils::hash_to_hex(utxo.utxo_id),
+        amount: utxo.amount.to_string(),
+        amount_decimal: decimal_amount(&state.config, utxo.asset_id, utxo.amount, query.format),
         asset_id: utils::asset_id_to_hex(utxo.asset_id),
         created_block: utxo.created_block,
         tree_position,
@@ -350,8 +1418,14 @@ pub async fn get_utxo_details(
 }
 
 /// Get tree statistics
+#[utoipa::path(
+    get, path = "/api/tree/stats", tag = "tree",
+    responses((status = 200, body = TreeStatsResponse))
+)]
 pub async fn get_tree_stats(State(state): State<AppState>) -> Json<TreeStatsResponse> {
-    let utxo_count = state.utxos.lock().unwrap().len() as u64;
+    // Counters, root and version change together under the UTXO lock
+    let _utxos = state.utxos.lock().unwrap();
+    let pool_counters = *state.pool_counters.lock().unwrap();
     let tree_version = *state.tree_version.lock().unwrap();
     let tree_root = *state.tree_root.lock().unwrap();
     
@@ -359,13 +1433,76 @@ pub async fn get_tree_stats(State(state): State<AppState>) -> Json<TreeStatsResp
         current_root: utils::hash_to_hex(tree_root),
         root_version: tree_version,
         depth: state.config.tree_depth,
-        total_utxos: utxo_count,
-        total_nodes: utxo_count,
+        total_utxos: pool_counters.total_utxos,
+        total_nodes: pool_counters.total_utxos,
+        total_spent: pool_counters.total_spent,
+        total_deposited_wei: pool_counters.total_deposited_wei.to_string(),
         tree_salt: state.config.tree_salt,
+        beacon_seed: utils::hash_to_hex(state.config.randomness_beacon.seed()),
     })
 }
 
+/// Forget remembered roots older than the last `root_tolerance_window`
+///
+/// Withdrawal proofs against the pruned roots stop being accepted.
+#[utoipa::path(
+    post, path = "/api/admin/roots/prune", tag = "admin",
+    responses(
+        (status = 200, description = "Number of roots pruned", body = Object),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse)
+    )
+)]
+pub async fn prune_recent_roots(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let mut recent_roots = state.recent_roots.lock().unwrap();
+    let pruned = recent_roots.len().saturating_sub(state.config.root_tolerance_window);
+    recent_roots.drain(..pruned);
+    
+    Json(json!({
+        "pruned": pruned,
+        "retained": recent_roots.len(),
+    }))
+}
+
+/// Current depositor address policy
+#[utoipa::path(
+    get, path = "/api/admin/address-policy", tag = "admin",
+    responses(
+        (status = 200, description = "Active address policy", body = AddressPolicy),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse)
+    )
+)]
+pub async fn get_address_policy(State(state): State<AppState>) -> Json<AddressPolicy> {
+    Json(state.config.address_policy.read().unwrap().clone())
+}
+
+/// Replace the depositor address policy
+///
+/// Takes effect for the next deposit; no restart needed.
+#[utoipa::path(
+    put, path = "/api/admin/address-policy", tag = "admin",
+    request_body = AddressPolicy,
+    responses(
+        (status = 200, description = "Policy replaced", body = Object),
+        (status = 401, description = "Missing or wrong admin token", body = ErrorResponse)
+    )
+)]
+pub async fn update_address_policy(
+    State(state): State<AppState>,
+    Json(policy): Json<AddressPolicy>,
+) -> Json<serde_json::Value> {
+    let summary = json!({
+        "allowlist": policy.allowlist.as_ref().map(HashSet::len),
+        "denylist": policy.denylist.len(),
+    });
+    *state.config.address_policy.write().unwrap() = policy;
+    Json(summary)
+}
+
 /// Get current tree root
+#[utoipa::path(
+    get, path = "/api/tree/root", tag = "tree",
+    responses((status = 200, description = "Current root and version", body = Object))
+)]
 pub async fn get_tree_root(State(state): State<AppState>) -> Json<serde_json::Value> {
     let tree_root = *state.tree_root.lock().unwrap();
     let tree_version = *state.tree_version.lock().unwrap();
@@ -380,6 +1517,377 @@ pub async fn get_tree_root(State(state): State<AppState>) -> Json<serde_json::Va
     }))
 }
 
+/// Get the flat Merkle root over the UTXO set (sorted by utxo_id)
+///
+/// Independent of the SMT layout, so clients can cross-check the set of
+/// UTXOs behind the current tree root.
+#[utoipa::path(
+    get, path = "/api/tree/utxo-set-root", tag = "tree",
+    responses(
+        (status = 200, description = "UTXO-set root, count and version", body = Object),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_utxo_set_root(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ErrorResponse>)> {
+    let utxos = state.utxos.lock().unwrap();
+    
+    let mut sorted: Vec<_> = utxos.values().collect();
+    sorted.sort_by_key(|utxo| utxo.utxo_id);
+    
+    let mut leaf_hashes = Vec::with_capacity(sorted.len());
+    for utxo in sorted {
+        let serialized = utxo.serialize()
+            .map_err(|e| api_error("SERIALIZATION_ERROR", &format!("Failed to serialize UTXO: {}", e)))?;
+        leaf_hashes.push(crate::canonical_spec::generate_leaf_hash(&serialized));
+    }
+    
+    Ok(Json(serde_json::json!({
+        "root": utils::hash_to_hex(crate::canonical_spec::compute_utxo_set_root(&leaf_hashes)),
+        "utxo_count": leaf_hashes.len(),
+        "version": *state.tree_version.lock().unwrap(),
+    })))
+}
+
+/// Get the operator public key used to verify root signatures
+#[utoipa::path(
+    get, path = "/api/operator/pubkey", tag = "tree",
+    responses((status = 200, body = OperatorPubkeyResponse))
+)]
+pub async fn get_operator_pubkey(State(state): State<AppState>) -> Json<OperatorPubkeyResponse> {
+    Json(OperatorPubkeyResponse {
+        algorithm: state.operator_keypair.algorithm(),
+        public_key: format!("0x{}", hex::encode(state.operator_keypair.public_key_bytes())),
+    })
+}
+
+/// Check that `(value, blinding, owner)` opens a UTXO commitment
+///
+/// Only the validity bit is returned. The value is hashed at a fixed width
+/// and the commitments are compared in constant time, so timing does not
+/// depend on the claimed value.
+#[utoipa::path(
+    post, path = "/api/commitment/verify", tag = "commitments",
+    request_body = CommitmentOpeningRequest,
+    responses(
+        (status = 200, body = CommitmentOpeningResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn verify_commitment_opening(
+    State(state): State<AppState>,
+    Json(request): Json<CommitmentOpeningRequest>,
+) -> Result<Json<CommitmentOpeningResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let commitment = utils::hex_to_hash(&request.commitment)
+        .map_err(|_| api_error("INVALID_COMMITMENT", "Invalid commitment format"))?;
+    let value: u128 = request.value.parse()
+        .map_err(|_| api_error("INVALID_VALUE", "Value must be a decimal integer"))?;
+    let blinding = utils::hex_to_hash(&request.blinding)
+        .map_err(|_| api_error("INVALID_BLINDING", "Invalid blinding factor format"))?;
+    let owner = utils::hex_to_hash(&request.owner)
+        .map_err(|_| api_error("INVALID_OWNER", "Invalid owner format"))?;
+    
+    let computed = crate::canonical_spec::generate_value_commitment(value, owner, blinding);
+    
+    Ok(Json(CommitmentOpeningResponse {
+        valid: CryptoUtils::constant_time_eq(&computed, &commitment),
+    }))
+}
+
+/// Report whether each remembered deposit commitment exists and is spent
+///
+/// Lets re-syncing wallets check many commitments in one call. Statuses are
+/// returned in request order; unknown commitments report `exists: false`.
+#[utoipa::path(
+    post, path = "/api/commitments/status", tag = "commitments",
+    request_body = CommitmentStatusRequest,
+    responses(
+        (status = 200, body = CommitmentStatusResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_commitment_status(
+    State(state): State<AppState>,
+    Json(request): Json<CommitmentStatusRequest>,
+) -> Result<Json<CommitmentStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if request.commitments.len() > state.config.max_commitment_status_batch {
+        return Err(api_error("BATCH_TOO_LARGE", &format!(
+            "{} commitments requested, at most {} allowed",
+            request.commitments.len(), state.config.max_commitment_status_batch
+        )));
+    }
+    
+    let mut commitments = Vec::with_capacity(request.commitments.len());
+    for (i, commitment) in request.commitments.iter().enumerate() {
+        let commitment = utils::hex_to_hash(commitment)
+            .map_err(|e| api_error("INVALID_COMMITMENT", &format!("Commitment {}: {}", i, e)))?;
+        commitments.push(commitment);
+    }
+    
+    // Same lock order as record_deposit; spent UTXOs leave `utxos` but keep their index entry
+    let utxos = state.utxos.lock().unwrap();
+    let commitment_index = state.commitment_index.lock().unwrap();
+    
+    let statuses = commitments
+        .into_iter()
+        .map(|commitment| {
+            let entry = commitment_index.get(&commitment);
+            CommitmentStatus {
+                commitment: utils::hash_to_hex(commitment),
+                exists: entry.is_some(),
+                is_spent: entry.is_some_and(|utxo_id| !utxos.contains_key(utxo_id)),
+            }
+        })
+        .collect();
+    
+    Ok(Json(CommitmentStatusResponse { statuses }))
+}
+
+/// Verify many Merkle proofs in one call
+///
+/// Lets syncing wallets check all their proofs at once. Every proof must
+/// have exactly `tree_depth` siblings; a mismatch rejects the whole request
+/// with `PROOF_DEPTH_MISMATCH`. Otherwise each proof gets its own result:
+/// it must fold up to its root with the UTXO tree's node hash, and that root
+/// must be one of the last `root_tolerance_window` roots.
+#[utoipa::path(
+    post, path = "/api/proofs/verify", tag = "tree",
+    request_body = BatchProofVerifyRequest,
+    responses(
+        (status = 200, body = BatchProofVerifyResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn verify_merkle_proofs(
+    State(state): State<AppState>,
+    Json(request): Json<BatchProofVerifyRequest>,
+) -> Result<Json<BatchProofVerifyResponse>, (StatusCode, Json<ErrorResponse>)> {
+    if request.proofs.len() > state.config.max_proof_verify_batch {
+        return Err(api_error("BATCH_TOO_LARGE", &format!(
+            "{} proofs submitted, at most {} allowed",
+            request.proofs.len(), state.config.max_proof_verify_batch
+        )));
+    }
+    
+    let depth = state.config.tree_depth as usize;
+    let mut proofs = Vec::with_capacity(request.proofs.len());
+    for (i, item) in request.proofs.iter().enumerate() {
+        if item.proof.siblings.len() != depth || item.proof.path.len() != depth {
+            return Err(api_error("PROOF_DEPTH_MISMATCH", &format!(
+                "Proof {}: {} siblings and {} path bits, tree depth is {}",
+                i, item.proof.siblings.len(), item.proof.path.len(), depth
+            )));
+        }
+        
+        let leaf = utils::hex_to_hash(&item.leaf)
+            .map_err(|e| api_error("INVALID_LEAF", &format!("Proof {}: {}", i, e)))?;
+        let root = utils::hex_to_hash(&item.proof.root)
+            .map_err(|e| api_error("INVALID_PROOF", &format!("Proof {}: root: {}", i, e)))?;
+        let siblings = item.proof.siblings.iter()
+            .map(|sibling| utils::hex_to_hash(sibling))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| api_error("INVALID_PROOF", &format!("Proof {}: sibling: {}", i, e)))?;
+        
+        proofs.push((leaf, siblings, root, item.proof.leaf_index, &item.proof.path));
+    }
+    
+    let recent_roots = state.recent_roots.lock().unwrap();
+    let results: Vec<bool> = proofs.into_iter()
+        .map(|(leaf, siblings, root, leaf_index, path)| {
+            let index_fits = depth >= 64 || leaf_index >> depth == 0;
+            let path_matches = path.iter().enumerate()
+                .all(|(level, bit)| u64::from(*bit) == (leaf_index >> level) & 1);
+            index_fits
+                && path_matches
+                && recent_roots.contains(&root)
+                && crate::canonical_spec::compute_root_from_proof(leaf, leaf_index, &siblings) == root
+        })
+        .collect();
+    
+    Ok(Json(BatchProofVerifyResponse {
+        all_valid: results.iter().all(|valid| *valid),
+        results,
+    }))
+}
+
+/// Get the inclusion proof of a UTXO's leaf in the UTXO tree
+///
+/// The proof verifies against the current root with `/api/proofs/verify`.
+#[utoipa::path(
+    get, path = "/api/proof/{utxo_id}", tag = "tree",
+    params(("utxo_id" = String, Path, description = "UTXO ID (hex encoded)")),
+    responses(
+        (status = 200, body = UTXOProofResponse),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+pub async fn get_utxo_proof(
+    State(state): State<AppState>,
+    Path(utxo_id_hex): Path<String>,
+) -> Result<Json<UTXOProofResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let utxo_id = utils::hex_to_hash(&utxo_id_hex)
+        .map_err(|_| api_error("INVALID_UTXO_ID", "Invalid UTXO ID format"))?;
+    
+    // Same lock order as record_deposit
+    let utxos = state.utxos.lock().unwrap();
+    let utxo_tree = state.utxo_tree.lock().unwrap();
+    if !utxos.contains_key(&utxo_id) {
+        return Err(api_error("UTXO_NOT_FOUND", "UTXO not found"));
+    }
+    
+    let leaf_index = utxo_tree.leaf_position(&utxo_id);
+    let leaf = utxo_tree.get_leaf(leaf_index)
+        .ok_or_else(|| api_error("PROOF_NOT_FOUND", "UTXO leaf is not in the tree"))?;
+    let siblings = utxo_tree.generate_proof(leaf_index);
+    
+    Ok(Json(UTXOProofResponse {
+        utxo_id: utils::hash_to_hex(utxo_id),
+        leaf: utils::hash_to_hex(leaf),
+        indices: (0..siblings.len()).map(|level| ((leaf_index >> level) & 1) as u32).collect(),
+        siblings: siblings.into_iter().map(utils::hash_to_hex).collect(),
+        root: utils::hash_to_hex(utxo_tree.get_root()),
+        leaf_index,
+    }))
+}
+
+/// Upgrade to a WebSocket streaming pool events for subscribed owners
+#[utoipa::path(
+    get, path = "/api/ws/events", tag = "events",
+    responses((status = 101, description = "WebSocket upgrade; pushes PoolEvent messages"))
+)]
+pub async fn subscribe_events(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_event_socket(socket, state))
+}
+
+/// Forward pool events matching the connection's owner filter
+async fn handle_event_socket(mut socket: WebSocket, state: AppState) {
+    let mut events = state.events.subscribe();
+    let mut subscription = EventSubscription::default();
+    
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                
+                let reply = match subscription.update(&text) {
+                    Ok(subscribed) => serde_json::to_string(&SubscribeResponse { subscribed }),
+                    Err((_, Json(error))) => serde_json::to_string(&error),
+                };
+                if let Ok(reply) = reply {
+                    if socket.send(Message::Text(reply)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                
+                if !subscription.matches(&event) {
+                    continue;
+                }
+                if let Ok(payload) = serde_json::to_string(&event) {
+                    if socket.send(Message::Text(payload)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Upgrade to a WebSocket pushing every root update and processed deposit
+///
+/// Replaces polling `/api/tree/root`. The feed is subscribed before the
+/// upgrade completes, so no message sent after the handshake is missed.
+#[utoipa::path(
+    get, path = "/api/ws", tag = "events",
+    responses((status = 101, description = "WebSocket upgrade; pushes TreeFeedMessage messages"))
+)]
+pub async fn subscribe_tree_feed(
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    let feed = state.tree_feed.subscribe();
+    ws.on_upgrade(move |socket| handle_tree_feed_socket(socket, feed))
+}
+
+/// Forward feed messages until the client goes away
+///
+/// Each connection reads its own receiver, so a slow client only lags
+/// itself: the broadcast sender never waits, and a lagged receiver skips
+/// ahead to the oldest retained message.
+async fn handle_tree_feed_socket(mut socket: WebSocket, mut feed: broadcast::Receiver<TreeFeedMessage>) {
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                match incoming {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                }
+            }
+            message = feed.recv() => {
+                let message = match message {
+                    Ok(message) => message,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        println!(" Tree feed client lagged, skipped {} messages", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                
+                if let Ok(payload) = serde_json::to_string(&message) {
+                    if socket.send(Message::Text(payload)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Per-connection owner filter; nothing is forwarded until a subscribe message arrives
+#[derive(Debug, Default)]
+struct EventSubscription {
+    owners: HashSet<[u8; 32]>,
+}
+
+impl EventSubscription {
+    /// Replace the filter from a `{"owners": [...]}` message
+    fn update(&mut self, message: &str) -> std::result::Result<usize, (StatusCode, Json<ErrorResponse>)> {
+        let request: SubscribeRequest = serde_json::from_str(message)
+            .map_err(|e| api_error("INVALID_SUBSCRIPTION", &e.to_string()))?;
+        
+        let mut owners = HashSet::with_capacity(request.owners.len());
+        for owner_hex in &request.owners {
+            let owner = utils::hex_to_hash(owner_hex)
+                .map_err(|_| api_error("INVALID_OWNER", "Invalid owner commitment format"))?;
+            owners.insert(owner);
+        }
+        
+        self.owners = owners;
+        Ok(self.owners.len())
+    }
+
+    /// Whether an event belongs to a subscribed owner
+    fn matches(&self, event: &PoolEvent) -> bool {
+        utils::hex_to_hash(&event.owner_commitment)
+            .map(|owner| self.owners.contains(&owner))
+            .unwrap_or(false)
+    }
+}
+
 // Helper functions
 
 #[derive(Debug, Clone)]
@@ -393,155 +1901,404 @@ struct BlockchainTransactionData {
     status: String,
 }
 
+/// Transaction is mined but not yet buried under `min_deposit_confirmations` blocks
+#[derive(Debug, thiserror::Error)]
+#[error("Transaction has {actual} confirmations, {required} required")]
+struct InsufficientConfirmations {
+    required: u64,
+    actual: u64,
+}
+
 /// VERIFY TRANSACTION ON BLOCKCHAIN - This is the critical fix!
-async fn verify_transaction_on_blockchain(
+///
+/// The transaction must be mined to our contract, buried under at least
+/// `min_confirmations` blocks and have a successful receipt.
+async fn verify_deposit_transaction<C: ChainQuery>(
+    chain: &C,
     tx_hash: &str,
-    rpc_url: &str,
     expected_contract_address: &str,
+    min_confirmations: u64,
 ) -> Result<BlockchainTransactionData> {
-    let client = reqwest::Client::new();
-
-    // Call eth_getTransactionByHash
-    let request_body = json!({
-        "jsonrpc": "2.0",
-        "method": "eth_getTransactionByHash",
-        "params": [tx_hash],
-        "id": 1
-    });
-
-    let response = client
-        .post(rpc_url)
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| anyhow!("Failed to call RPC: {}", e))?;
-
-    let response_json: Value = response
-        .json()
-        .await
-        .map_err(|e| anyhow!("Failed to parse RPC response: {}", e))?;
-
-    let tx_data = response_json["result"]
-        .as_object()
+    let transaction = chain.transaction(tx_hash)
+        .await?
         .ok_or_else(|| anyhow!("Transaction not found"))?;
 
-    // Extract transaction details
-    let from_address = tx_data["from"]
-        .as_str()
-        .ok_or_else(|| anyhow!("Missing from address"))?
-        .to_string();
-
-    let to_address = tx_data["to"]
-        .as_str()
-        .ok_or_else(|| anyhow!("Missing to address"))?
-        .to_string();
-
-    let value_hex = tx_data["value"]
-        .as_str()
-        .ok_or_else(|| anyhow!("Missing value"))?;
-
-    let block_number_hex = tx_data["blockNumber"]
-        .as_str()
+    let block_number = transaction.block_number
         .ok_or_else(|| anyhow!("Transaction not mined yet"))?;
 
     // Verify the transaction is to our contract
-    if to_address.to_lowercase() != expected_contract_address.to_lowercase() {
+    if transaction.to_address.to_lowercase() != expected_contract_address.to_lowercase() {
         return Err(anyhow!(
             "Transaction is not to our contract. Expected: {}, Got: {}",
             expected_contract_address,
-            to_address
+            transaction.to_address
         ));
     }
 
-    // Convert hex values
-    let value_wei = u128::from_str_radix(
-        value_hex.strip_prefix("0x").unwrap_or(value_hex),
-        16
-    ).map_err(|e| anyhow!("Invalid value format: {}", e))?;
+    if min_confirmations > 0 {
+        let confirmations = chain.block_number().await?.saturating_sub(block_number);
+        if confirmations < min_confirmations {
+            return Err(InsufficientConfirmations { required: min_confirmations, actual: confirmations }.into());
+        }
+    }
+
+    // Get transaction receipt to verify it succeeded
+    let receipt = chain.receipt(tx_hash)
+        .await?
+        .ok_or_else(|| anyhow!("Transaction receipt not found"))?;
 
-    let block_number = u64::from_str_radix(
-        block_number_hex.strip_prefix("0x").unwrap_or(block_number_hex),
-        16
-    ).map_err(|e| anyhow!("Invalid block number format: {}", e))?;
+    if receipt.status != "0x1" {
+        return Err(anyhow!("Transaction failed (status: {})", receipt.status));
+    }
 
     // Convert wei to ETH for display
-    let value_eth = format!("{:.6}", value_wei as f64 / 1_000_000_000_000_000_000.0);
+    let value_eth = format!("{:.6}", transaction.value_wei as f64 / 1_000_000_000_000_000_000.0);
 
-    // Get transaction receipt to verify it succeeded
-    let receipt_request = json!({
-        "jsonrpc": "2.0",
-        "method": "eth_getTransactionReceipt",
-        "params": [tx_hash],
-        "id": 2
+    Ok(BlockchainTransactionData {
+        from_address: transaction.from_address,
+        to_address: transaction.to_address,
+        value_wei: transaction.value_wei.to_string(),
+        value_eth,
+        block_number,
+        gas_used: receipt.gas_used,
+        status: receipt.status,
+    })
+}
+
+/// `ChainQuery` over JSON-RPC nodes, failing over in `rpc_urls` order
+struct RpcChain<'a> {
+    client: &'a reqwest::Client,
+    rpc_urls: &'a [String],
+    health: &'a ProviderHealth,
+    max_response_bytes: usize,
+}
+
+impl RpcChain<'_> {
+    /// Call `method` and return its `result` field
+    ///
+    /// Connection failures, timeouts and 5xx responses move on to the next
+    /// provider; anything a provider answers with is final.
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let request_body = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1
+        });
+
+        let mut last_error = None;
+        for index in self.health.attempt_order() {
+            let Some(rpc_url) = self.rpc_urls.get(index) else {
+                continue;
+            };
+            let response = match self.client.post(rpc_url).json(&request_body).send().await {
+                Ok(response) if response.status().is_server_error() => {
+                    self.health.record_failure(index);
+                    last_error = Some(anyhow!("Failed to call {}: provider returned {}", method, response.status()));
+                    continue;
+                }
+                Ok(response) => response,
+                Err(e) => {
+                    self.health.record_failure(index);
+                    last_error = Some(rpc_error(&format!("Failed to call {}", method), e));
+                    continue;
+                }
+            };
+            self.health.record_success(index);
+
+            let mut response_json = read_rpc_json(response, self.max_response_bytes)
+                .await
+                .map_err(|e| e.context(format!("Failed to parse {} response", method)))?;
+            return Ok(response_json["result"].take());
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("Failed to call {}: no RPC provider configured", method)))
+    }
+}
+
+/// Parse a `0x`-prefixed hex quantity
+fn parse_hex_quantity(field: &str, value: &str) -> Result<u128> {
+    u128::from_str_radix(value.strip_prefix("0x").unwrap_or(value), 16)
+        .map_err(|e| anyhow!("Invalid {} format: {}", field, e))
+}
+
+impl ChainQuery for RpcChain<'_> {
+    async fn transaction(&self, tx_hash: &str) -> Result<Option<ChainTransaction>> {
+        let result = self.call("eth_getTransactionByHash", json!([tx_hash])).await?;
+        let Some(tx_data) = result.as_object() else {
+            return Ok(None);
+        };
+
+        // Extract transaction details
+        let from_address = tx_data["from"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing from address"))?
+            .to_string();
+
+        let to_address = tx_data["to"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing to address"))?
+            .to_string();
+
+        let value_hex = tx_data["value"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing value"))?;
+
+        let block_number = tx_data["blockNumber"]
+            .as_str()
+            .map(|hex| parse_hex_quantity("block number", hex).map(|block| block as u64))
+            .transpose()?;
+
+        Ok(Some(ChainTransaction {
+            from_address,
+            to_address,
+            value_wei: parse_hex_quantity("value", value_hex)?,
+            block_number,
+        }))
+    }
+
+    async fn receipt(&self, tx_hash: &str) -> Result<Option<ChainReceipt>> {
+        let result = self.call("eth_getTransactionReceipt", json!([tx_hash])).await?;
+        let Some(receipt) = result.as_object() else {
+            return Ok(None);
+        };
+
+        let status = receipt["status"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Missing transaction status"))?
+            .to_string();
+
+        let gas_used = receipt["gasUsed"]
+            .as_str()
+            .unwrap_or("0x0")
+            .to_string();
+
+        Ok(Some(ChainReceipt { status, gas_used }))
+    }
+
+    async fn block_number(&self) -> Result<u64> {
+        let result = self.call("eth_blockNumber", json!([])).await?;
+        let hex = result.as_str().ok_or_else(|| anyhow!("Missing block number"))?;
+        Ok(parse_hex_quantity("block number", hex)? as u64)
+    }
+}
+
+/// Store a verified deposit UTXO, advance the tree and notify subscribers
+///
+/// Fails without changing state if the UTXO's tree position is occupied.
+fn record_deposit(
+    state: &AppState,
+    utxo: &CanonicalUTXO,
+    commitment: [u8; 32],
+    leaf_hash: [u8; 32],
+    encrypted_note: Option<EncryptedNotePayload>,
+) -> Result<()> {
+    let (root_version, root) = {
+        let mut utxos = state.utxos.lock().unwrap();
+        let mut utxo_tree = state.utxo_tree.lock().unwrap();
+        let position = utxo_tree.leaf_position(&utxo.utxo_id);
+        let new_root = utxo_tree.insert_leaf(position, leaf_hash)?;
+        utxos.insert(utxo.utxo_id, utxo.clone());
+
+        let mut owner_utxos = state.owner_utxos.lock().unwrap();
+        owner_utxos.entry(utxo.owner_commitment)
+            .or_insert_with(Vec::new)
+            .push(utxo.utxo_id);
+
+        state.commitment_index.lock().unwrap().insert(commitment, utxo.utxo_id);
+
+        if let Some(note) = encrypted_note {
+            state.encrypted_notes.lock().unwrap().insert(utxo.utxo_id, note);
+        }
+
+        let mut balances = state.balances.lock().unwrap();
+        let owner_balances = balances.entry(utxo.owner_commitment)
+            .or_insert_with(HashMap::new);
+        let (current_balance, current_count) = owner_balances.entry(utxo.asset_id)
+            .or_insert((0, 0));
+        *current_balance += utxo.amount;
+        *current_count += 1;
+
+        let mut pool_counters = state.pool_counters.lock().unwrap();
+        pool_counters.total_utxos += 1;
+        pool_counters.total_deposited_wei += utxo.amount;
+
+        // Update tree version
+        let mut tree_version = state.tree_version.lock().unwrap();
+        *tree_version += 1;
+
+        let mut tree_root = state.tree_root.lock().unwrap();
+        *tree_root = new_root;
+        
+        let mut recent_roots = state.recent_roots.lock().unwrap();
+        remember_root(&mut recent_roots, *tree_root, state.config.root_tolerance_window);
+        
+        (*tree_version, *tree_root)
+    };
+    
+    // No subscribers is not an error
+    let _ = state.events.send(PoolEvent {
+        event_type: PoolEventType::Deposit,
+        owner_commitment: utils::hash_to_hex(utxo.owner_commitment),
+        utxo_id: utils::hash_to_hex(utxo.utxo_id),
+        amount: utxo.amount.to_string(),
+        root_version,
     });
+    let _ = state.tree_feed.send(TreeFeedMessage::Deposit {
+        utxo_id: utils::hash_to_hex(utxo.utxo_id),
+        amount: utxo.amount.to_string(),
+        root_version,
+    });
+    let _ = state.tree_feed.send(TreeFeedMessage::RootUpdate {
+        root: utils::hash_to_hex(root),
+        root_version,
+        utxo_id: utils::hash_to_hex(utxo.utxo_id),
+    });
+    Ok(())
+}
 
-    let receipt_response = client
-        .post(rpc_url)
-        .json(&receipt_request)
-        .send()
-        .await
-        .map_err(|e| anyhow!("Failed to get transaction receipt: {}", e))?;
+/// Number of unspent UTXOs sharing an asset and denomination
+fn anonymity_set_size(utxos: &HashMap<[u8; 32], CanonicalUTXO>, asset_id: &[u8; 20], denomination: u128) -> usize {
+    utxos.values()
+        .filter(|utxo| &utxo.asset_id == asset_id && utxo.amount == denomination)
+        .count()
+}
 
-    let receipt_json: Value = receipt_response
-        .json()
-        .await
-        .map_err(|e| anyhow!("Failed to parse receipt response: {}", e))?;
+/// Append a root to the tolerance window, evicting the oldest beyond `window`
+fn remember_root(recent_roots: &mut VecDeque<[u8; 32]>, root: [u8; 32], window: usize) {
+    recent_roots.push_back(root);
+    while recent_roots.len() > window {
+        recent_roots.pop_front();
+    }
+}
 
-    let receipt = receipt_json["result"]
-        .as_object()
-        .ok_or_else(|| anyhow!("Transaction receipt not found"))?;
+/// RPC node returned a body larger than `max_rpc_response_bytes`
+#[derive(Debug, thiserror::Error)]
+#[error("RPC response exceeds {max_bytes} bytes")]
+struct RpcResponseTooLarge {
+    max_bytes: usize,
+}
+
+/// RPC node answered with a JSON-RPC `error` object (e.g. -32005 rate limit)
+#[derive(Debug, thiserror::Error)]
+#[error("RPC error {code}: {message}")]
+struct RpcError {
+    code: i64,
+    message: String,
+}
 
-    let status = receipt["status"]
-        .as_str()
-        .ok_or_else(|| anyhow!("Missing transaction status"))?;
+/// Buffer a JSON-RPC response body, aborting once it exceeds `max_bytes`
+///
+/// A response carrying an `error` object fails with `RpcError` so callers
+/// do not mistake the missing `result` for an absent transaction.
+async fn read_rpc_json(mut response: reqwest::Response, max_bytes: usize) -> Result<Value> {
+    // Reject up front when the node announces the size
+    if response.content_length().is_some_and(|len| len > max_bytes as u64) {
+        return Err(RpcResponseTooLarge { max_bytes }.into());
+    }
 
-    if status != "0x1" {
-        return Err(anyhow!("Transaction failed (status: {})", status));
+    // Content-Length may be absent (chunked) or wrong, so bound the stream too
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| rpc_error("Failed to read RPC response", e))?
+    {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(RpcResponseTooLarge { max_bytes }.into());
+        }
+        body.extend_from_slice(&chunk);
     }
 
-    let gas_used = receipt["gasUsed"]
-        .as_str()
-        .unwrap_or("0x0")
-        .to_string();
+    let json: Value = serde_json::from_slice(&body).map_err(|e| anyhow!("Invalid JSON: {}", e))?;
+    if let Some(error) = json.get("error").filter(|error| error.is_object()) {
+        return Err(RpcError {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+        }.into());
+    }
+    Ok(json)
+}
 
-    Ok(BlockchainTransactionData {
-        from_address,
-        to_address,
-        value_wei: value_wei.to_string(),
-        value_eth,
-        block_number,
-        gas_used,
-        status: status.to_string(),
-    })
+/// Map a reqwest failure to an error, calling out timeouts explicitly
+fn rpc_error(context: &str, error: reqwest::Error) -> anyhow::Error {
+    if error.is_timeout() {
+        anyhow!("{}: RPC request timed out", context)
+    } else {
+        anyhow!("{}: {}", context, error)
+    }
 }
 
 /// Create UTXO from VERIFIED deposit event
-fn create_utxo_from_verified_deposit(deposit: &BlockchainDepositEvent, _state: &AppState) -> Result<CanonicalUTXO> {
+fn create_utxo_from_verified_deposit(
+    deposit: &BlockchainDepositEvent,
+    lock_data: Vec<u8>,
+    state: &AppState,
+) -> Result<CanonicalUTXO> {
     let owner_commitment = derive_owner_commitment(deposit)?;
 
+    // Entropy comes from the committed beacon so UTXO IDs can be audited
+    let entropy = {
+        let mut beacon_index = state.beacon_index.lock().unwrap();
+        *beacon_index += 1;
+        state.config.randomness_beacon.entropy(*beacon_index)
+    };
+
     let utxo = CanonicalUTXO::new_eth(
         deposit.transaction_hash.0,
         0,
         deposit.block_number,
-        rand::random::<u64>(),
+        entropy,
         deposit.value.as_u128(),
         owner_commitment,
-    );
+    ).with_script(lock_data);
 
     Ok(utxo)
 }
 
+/// Decode a client-supplied lock script, enforcing `max_lock_data_bytes`
+///
+/// The size check runs on the hex length first so oversized payloads are
+/// rejected without being decoded.
+fn decode_lock_data(
+    lock_data_hex: Option<&str>,
+    max_lock_data_bytes: usize,
+) -> std::result::Result<Vec<u8>, (StatusCode, Json<ErrorResponse>)> {
+    let lock_data_hex = match lock_data_hex {
+        Some(hex_str) => hex_str.strip_prefix("0x").unwrap_or(hex_str),
+        None => return Ok(Vec::new()),
+    };
+    
+    let declared_len = (lock_data_hex.len() + 1) / 2;
+    if declared_len > max_lock_data_bytes {
+        let err = UTXOError::LockDataTooLarge(declared_len);
+        return Err(api_error("LOCK_DATA_TOO_LARGE", &format!("{} (max {} bytes)", err, max_lock_data_bytes)));
+    }
+    
+    hex::decode(lock_data_hex)
+        .map_err(|e| api_error("INVALID_LOCK_DATA", &format!("Invalid lock data hex: {}", e)))
+}
+
 /// Derive privacy-preserving owner commitment
 fn derive_owner_commitment(deposit: &BlockchainDepositEvent) -> Result<[u8; 32]> {
-    use sha3::{Keccak256, Digest};
-
-    let mut hasher = Keccak256::new();
-    hasher.update(b"OWNER_COMMITMENT");
-    hasher.update(deposit.depositor.as_bytes());
-    hasher.update(deposit.commitment.as_bytes());
-    hasher.update(&deposit.block_number.to_be_bytes());
+    Ok(crate::canonical_spec::generate_owner_commitment(
+        deposit.depositor.0,
+        deposit.commitment.0,
+        deposit.block_number,
+    ))
+}
 
-    Ok(hasher.finalize().into())
+/// Decimal rendering of an amount when `format=decimal` is requested
+///
+/// Returns `None` for raw output or assets missing from the registry.
+fn decimal_amount(config: &AppConfig, asset_id: [u8; 20], amount: u128, format: Option<AmountFormat>) -> Option<String> {
+    if format.unwrap_or_default() != AmountFormat::Decimal {
+        return None;
+    }
+    
+    config.asset_decimals
+        .get(&asset_id)
+        .map(|decimals| crate::api::types::utils::format_decimal_amount(amount, *decimals))
 }
 
 /// Create API error response
@@ -580,4 +2337,1536 @@ mod utils {
     pub fn asset_id_to_hex(asset_id: [u8; 20]) -> String {
         format!("0x{}", hex::encode(asset_id))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert_test_utxo(state: &AppState, utxo: CanonicalUTXO) {
+        state.owner_utxos.lock().unwrap()
+            .entry(utxo.owner_commitment)
+            .or_insert_with(Vec::new)
+            .push(utxo.utxo_id);
+        state.utxos.lock().unwrap().insert(utxo.utxo_id, utxo);
+    }
+
+    #[tokio::test]
+    async fn test_spendable_balance_excludes_timelocked_utxos() {
+        let state = AppState::new().unwrap();
+        let owner = [7u8; 32];
+        
+        let spendable = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, owner);
+        let timelocked = CanonicalUTXO::new_eth([2u8; 32], 0, 100, 2, 5_000, owner)
+            .with_timelock(500);
+        insert_test_utxo(&state, spendable);
+        insert_test_utxo(&state, timelocked);
+        
+        let Json(balance) = get_spendable_balance(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(SpendableBalanceQuery { current_block: Some(200), format: None }),
+        ).await.unwrap();
+        
+        assert_eq!(balance.total, "6000");
+        assert_eq!(balance.spendable, "1000");
+        assert_eq!(balance.utxo_count, 2);
+        assert_eq!(balance.spendable_utxo_count, 1);
+        
+        // Once the timelock expires the full amount becomes spendable
+        let Json(balance) = get_spendable_balance(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(SpendableBalanceQuery { current_block: Some(500), format: None }),
+        ).await.unwrap();
+        
+        assert_eq!(balance.spendable, "6000");
+        
+        // Without a block the chain head seen by the watcher is used
+        let (status, Json(error)) = get_spendable_balance(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(SpendableBalanceQuery { current_block: None, format: None }),
+        ).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "MISSING_CURRENT_BLOCK");
+        
+        state.watcher_progress.record(190, 200);
+        let Json(balance) = get_spendable_balance(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(SpendableBalanceQuery { current_block: None, format: None }),
+        ).await.unwrap();
+        assert_eq!(balance.current_block, 200);
+        assert_eq!(balance.spendable, "1000");
+        
+        // Totals past u128::MAX are refused rather than wrapped
+        insert_test_utxo(&state, CanonicalUTXO::new_eth([3u8; 32], 0, 100, 3, u128::MAX, owner));
+        let (_, Json(error)) = get_spendable_balance(
+            State(state),
+            Path(utils::hash_to_hex(owner)),
+            Query(SpendableBalanceQuery { current_block: Some(500), format: None }),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "BALANCE_OVERFLOW");
+    }
+
+    #[tokio::test]
+    async fn test_decimal_amount_format() {
+        let state = AppState::new().unwrap();
+        let owner = [9u8; 32];
+        let utxo = CanonicalUTXO::new_eth([3u8; 32], 0, 100, 3, 1_500_000_000_000_000_000, owner);
+        let utxo_id = utils::hash_to_hex(utxo.utxo_id);
+        insert_test_utxo(&state, utxo);
+        
+        let Json(info) = get_utxo_details(
+            State(state.clone()),
+            Path(utxo_id.clone()),
+            Query(FormatQuery { format: Some(AmountFormat::Decimal) }),
+        ).await.unwrap();
+        
+        assert_eq!(info.amount, "1500000000000000000");
+        assert_eq!(info.amount_decimal.as_deref(), Some("1.500000000000000000"));
+        
+        // Raw output stays the default
+        let Json(info) = get_utxo_details(
+            State(state),
+            Path(utxo_id),
+            Query(FormatQuery::default()),
+        ).await.unwrap();
+        
+        assert_eq!(info.amount, "1500000000000000000");
+        assert!(info.amount_decimal.is_none());
+        
+        assert_eq!(crate::api::types::utils::format_decimal_amount(5, 18), "0.000000000000000005");
+    }
+
+    #[tokio::test]
+    async fn test_owner_utxos_sort_order() {
+        let state = AppState::new().unwrap();
+        let owner = [11u8; 32];
+        
+        // Inserted out of block order; two UTXOs share block 200
+        let late = CanonicalUTXO::new_eth([1u8; 32], 0, 300, 1, 5_000, owner);
+        let tied_b = CanonicalUTXO::new_eth([2u8; 32], 0, 200, 2, 1_000, owner);
+        let early = CanonicalUTXO::new_eth([3u8; 32], 0, 100, 3, 3_000, owner);
+        let tied_a = CanonicalUTXO::new_eth([4u8; 32], 0, 200, 4, 1_000, owner);
+        let (tied_first, tied_second) = if tied_a.utxo_id < tied_b.utxo_id {
+            (tied_a.utxo_id, tied_b.utxo_id)
+        } else {
+            (tied_b.utxo_id, tied_a.utxo_id)
+        };
+        let (late_id, early_id) = (late.utxo_id, early.utxo_id);
+        for utxo in [late, tied_b, early, tied_a] {
+            insert_test_utxo(&state, utxo);
+        }
+        
+        let query = |sort, limit| Query(UTXOQuery { limit, after_block: None, asset_id: None, format: None, sort });
+        let ids = |response: UTXOListResponse| -> Vec<String> {
+            response.utxos.into_iter().map(|info| info.utxo_id).collect()
+        };
+        
+        let Json(response) = get_owner_utxos(State(state.clone()), Path(utils::hash_to_hex(owner)), query(None, None))
+            .await
+            .unwrap();
+        assert_eq!(ids(response), [early_id, tied_first, tied_second, late_id].map(utils::hash_to_hex));
+        
+        let Json(response) = get_owner_utxos(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            query(Some(UTXOSort::AmountDesc), None),
+        ).await.unwrap();
+        assert_eq!(ids(response), [late_id, early_id, tied_first, tied_second].map(utils::hash_to_hex));
+        
+        // The limit applies after ordering
+        let Json(response) = get_owner_utxos(
+            State(state),
+            Path(utils::hash_to_hex(owner)),
+            query(Some(UTXOSort::AmountDesc), Some(1)),
+        ).await.unwrap();
+        assert_eq!(ids(response), [utils::hash_to_hex(late_id)]);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_tracks_watcher_lag() {
+        let config = AppConfig {
+            max_ready_lag_blocks: 10,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        
+        // No poll yet: alive but not ready
+        let (status, _) = readiness(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        
+        state.watcher_progress.record(100, 1_000);
+        let (status, Json(body)) = readiness(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.lag, Some(900));
+        assert!(!body.ready);
+        
+        state.watcher_progress.record(995, 1_000);
+        let (status, Json(body)) = readiness(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.ready);
+        
+        let Json(live) = liveness().await;
+        assert_eq!(live["status"], "alive");
+    }
+
+    /// Chain at a fixed head with no deposits
+    struct IdleChain(u64);
+
+    impl DepositSource for IdleChain {
+        fn chain_head(&mut self) -> impl std::future::Future<Output = Result<u64>> + Send {
+            let head = self.0;
+            async move { Ok(head) }
+        }
+
+        fn fetch_deposits(&mut self, _from_block: u64, _to_block: u64) -> impl std::future::Future<Output = Result<Vec<BlockchainDepositEvent>>> + Send {
+            async { Ok(Vec::new()) }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawned_deposit_watcher_makes_service_ready() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = AppConfig {
+            tree_db_path: Some(temp_dir.path().join("tree_db").to_string_lossy().to_string()),
+            min_deposit_confirmations: 2,
+            max_ready_lag_blocks: 10,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let (status, _) = readiness(State(state.clone())).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        
+        let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+        let watcher = state.spawn_deposit_watcher(IdleChain(500), Duration::from_millis(10), shutdown_rx).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while state.watcher_progress.lag().is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.expect("watcher never reported progress");
+        
+        let (status, Json(body)) = readiness(State(state.clone())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.chain_head, 500);
+        assert_eq!(body.lag, Some(2));
+        
+        shutdown_tx.send(true).unwrap();
+        watcher.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_openapi_spec_describes_endpoints() {
+        let app = create_router().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        
+        let body = reqwest::get(format!("http://{}/api/openapi.json", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let spec: Value = serde_json::from_str(&body).unwrap();
+        
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+        let paths = spec["paths"].as_object().unwrap();
+        for path in ["/api/deposit", "/api/balance/{owner}", "/api/utxos/{owner}", "/api/tree/root"] {
+            assert!(paths.contains_key(path), "missing {}", path);
+        }
+        assert!(paths["/api/deposit"]["post"].is_object());
+        assert!(spec["components"]["schemas"]["DepositRequest"].is_object());
+    }
+
+    /// Deposit a UTXO and build a withdrawal against the current root
+    fn deposit_for_withdrawal(state: &AppState, tag: u8) -> WithdrawRequest {
+        deposit_amount_for_withdrawal(state, tag, 1_000 * tag as u128)
+    }
+
+    fn deposit_amount_for_withdrawal(state: &AppState, tag: u8, amount: u128) -> WithdrawRequest {
+        let utxo = CanonicalUTXO::new_eth([tag; 32], 0, 100, tag as u64, amount, [tag; 32]);
+        withdrawal_for_deposit(state, &utxo, &TestNote::new(tag))
+    }
+
+    /// Record `utxo` under `note`'s spend commitment and sign its withdrawal
+    fn withdrawal_for_deposit(state: &AppState, utxo: &CanonicalUTXO, note: &TestNote) -> WithdrawRequest {
+        record_deposit(state, utxo, note.commitment(), utxo.leaf_hash().unwrap(), None).unwrap();
+        let mut withdrawal = WithdrawRequest {
+            utxo_id: utils::hash_to_hex(utxo.utxo_id),
+            nullifier: utils::hash_to_hex(note.nullifier(&utxo.utxo_id)),
+            merkle_root: utils::hash_to_hex(*state.tree_root.lock().unwrap()),
+            siblings: inclusion_proof(state, &utxo.utxo_id),
+            recipient: web3::types::Address::repeat_byte(note.spending_key[0]),
+            authorization: note.authorization(),
+        };
+        sign_withdrawal(&note.spending_key, &mut withdrawal);
+        withdrawal
+    }
+
+    /// Current inclusion path of a UTXO's leaf, hex encoded
+    fn inclusion_proof(state: &AppState, utxo_id: &[u8; 32]) -> Vec<String> {
+        let tree = state.utxo_tree.lock().unwrap();
+        tree.generate_proof(tree.leaf_position(utxo_id))
+            .into_iter()
+            .map(utils::hash_to_hex)
+            .collect()
+    }
+
+    /// Spending key and note secret behind a test UTXO's spend commitment
+    struct TestNote {
+        spending_key: [u8; 32],
+        note_secret: [u8; 32],
+    }
+
+    impl TestNote {
+        fn new(tag: u8) -> Self {
+            Self { spending_key: [tag; 32], note_secret: [tag ^ 0x5a; 32] }
+        }
+
+        fn commitment(&self) -> [u8; 32] {
+            ArchitectureCompliantCrypto::compute_spend_commitment(
+                &ArchitectureCompliantCrypto::derive_spend_auth_key(&self.spending_key),
+                &ArchitectureCompliantCrypto::derive_nullifier_key(&self.spending_key),
+                &self.note_secret,
+            )
+        }
+
+        fn nullifier(&self, utxo_id: &[u8; 32]) -> [u8; 32] {
+            ArchitectureCompliantCrypto::derive_nullifier(&self.spending_key, utxo_id, &self.note_secret).unwrap()
+        }
+
+        /// Authorization with an empty signature, to be signed once the request is complete
+        fn authorization(&self) -> SpendAuthorization {
+            SpendAuthorization {
+                spend_auth_key: utils::hash_to_hex(ArchitectureCompliantCrypto::derive_spend_auth_key(&self.spending_key)),
+                nullifier_key: utils::hash_to_hex(ArchitectureCompliantCrypto::derive_nullifier_key(&self.spending_key)),
+                note_secret: utils::hash_to_hex(self.note_secret),
+                signature: format!("0x{}", hex::encode([0u8; 64])),
+            }
+        }
+    }
+
+    fn spend_signature(spending_key: &[u8; 32], message: &[u8; 32]) -> String {
+        let signature = RedJubjubSignatureScheme::sign(&crate::utils::RedJubjubPrivateKey::new(*spending_key), message);
+        format!("0x{}", hex::encode(signature.to_bytes()))
+    }
+
+    fn sign_withdrawal(spending_key: &[u8; 32], withdrawal: &mut WithdrawRequest) {
+        let item = parse_spend(
+            "Withdrawal", 0, &withdrawal.utxo_id, &withdrawal.nullifier, &withdrawal.merkle_root, &withdrawal.siblings, &withdrawal.authorization,
+        ).unwrap();
+        withdrawal.authorization.signature = spend_signature(spending_key, &withdrawal_message(&item, &withdrawal.recipient));
+    }
+
+    /// Sign a transfer with the spending key of each input, in input order
+    fn sign_transfer(spending_keys: &[[u8; 32]], transfer: &mut TransferRequest) {
+        let items: Vec<_> = transfer.inputs.iter()
+            .map(|input| parse_spend("Input", 0, &input.utxo_id, &input.nullifier, &input.merkle_root, &input.siblings, &input.authorization).unwrap())
+            .collect();
+        let outputs: Vec<_> = transfer.outputs.iter()
+            .enumerate()
+            .map(|(i, output)| parse_output(i, output).unwrap())
+            .collect();
+        let message = transfer_message(&items, &outputs);
+        for (input, spending_key) in transfer.inputs.iter_mut().zip(spending_keys) {
+            input.authorization.signature = spend_signature(spending_key, &message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_withdraw_applies_all_valid_items() {
+        let state = AppState::new().unwrap();
+        let first = deposit_for_withdrawal(&state, 1);
+        let second = deposit_for_withdrawal(&state, 2);
+        
+        let Json(response) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![first.clone(), second.clone()] }),
+        ).await.unwrap();
+        
+        assert!(response.success);
+        assert_eq!(response.total_amount, "3000");
+        assert_eq!(response.nullifiers, vec![first.nullifier.clone(), second.nullifier.clone()]);
+        assert!(state.utxos.lock().unwrap().is_empty());
+        assert_eq!(state.spent_nullifiers.lock().unwrap().len(), 2);
+        
+        let Json(stats) = get_tree_stats(State(state.clone())).await;
+        assert_eq!(stats.total_utxos, 0);
+        assert_eq!(stats.total_spent, 2);
+        assert_eq!(stats.total_deposited_wei, "3000");
+        
+        // Replaying a spent nullifier is rejected
+        let (_, Json(error)) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![first] }),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "NULLIFIER_SPENT");
+    }
+
+    async fn owner_utxo_ids(state: &AppState, owner: [u8; 32]) -> Vec<String> {
+        let Json(list) = get_owner_utxos(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(serde_json::from_value(json!({})).unwrap()),
+        ).await.unwrap();
+        list.utxos.into_iter().map(|utxo| utxo.utxo_id).collect()
+    }
+
+    async fn owner_balance(state: &AppState, owner: [u8; 32]) -> String {
+        let Json(balance) = get_balance(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(FormatQuery { format: None }),
+        ).await.unwrap();
+        balance.balance
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_spends_utxo_and_reduces_balance() {
+        let state = AppState::new().unwrap();
+        let kept = deposit_for_withdrawal(&state, 1);
+        // Second UTXO of the same owner
+        let utxo = CanonicalUTXO::new_eth([9u8; 32], 0, 100, 9, 500, [1u8; 32]);
+        let withdrawal = withdrawal_for_deposit(&state, &utxo, &TestNote { note_secret: [9u8; 32], ..TestNote::new(1) });
+        assert_eq!(owner_balance(&state, [1u8; 32]).await, "1500");
+        let version_before = *state.tree_version.lock().unwrap();
+        
+        let Json(response) = process_withdraw(State(state.clone()), Json(withdrawal.clone())).await.unwrap();
+        assert!(response.success);
+        assert_eq!(response.amount, "500");
+        assert_eq!(response.recipient, format!("{:?}", withdrawal.recipient));
+        assert_eq!(response.root_version, version_before + 1);
+        assert_eq!(response.new_root, utils::hash_to_hex(*state.tree_root.lock().unwrap()));
+        
+        assert_eq!(owner_balance(&state, [1u8; 32]).await, "1000");
+        assert_eq!(owner_utxo_ids(&state, [1u8; 32]).await, vec![kept.utxo_id]);
+        
+        let (_, Json(error)) = process_withdraw(State(state.clone()), Json(withdrawal)).await.unwrap_err();
+        assert_eq!(error.error, "NULLIFIER_SPENT");
+    }
+
+    #[tokio::test]
+    async fn test_transfer_moves_value_to_new_outputs() {
+        let state = AppState::new().unwrap();
+        let spend = deposit_amount_for_withdrawal(&state, 1, 1_000);
+        let input = TransferInput {
+            utxo_id: spend.utxo_id.clone(),
+            nullifier: spend.nullifier.clone(),
+            merkle_root: spend.merkle_root.clone(),
+            siblings: spend.siblings.clone(),
+            authorization: spend.authorization.clone(),
+        };
+        let output = |commitment: [u8; 32], owner: u8, amount: u128| TransferOutput {
+            commitment: utils::hash_to_hex(commitment),
+            owner_commitment: utils::hash_to_hex([owner; 32]),
+            amount: amount.to_string(),
+        };
+        let signed = |outputs: Vec<TransferOutput>| {
+            let mut transfer = TransferRequest { inputs: vec![input.clone()], outputs };
+            sign_transfer(&[TestNote::new(1).spending_key], &mut transfer);
+            transfer
+        };
+        
+        // Outputs must add up to the inputs
+        let (_, Json(error)) = process_transfer(State(state.clone()), Json(signed(
+            vec![output([0xa1; 32], 2, 600), output([0xa2; 32], 1, 500)],
+        ))).await.unwrap_err();
+        assert_eq!(error.error, "VALUE_MISMATCH");
+        
+        // Output commitments must be fresh
+        let (_, Json(error)) = process_transfer(State(state.clone()), Json(signed(
+            vec![output(TestNote::new(1).commitment(), 2, 1_000)],
+        ))).await.unwrap_err();
+        assert_eq!(error.error, "COMMITMENT_EXISTS");
+        assert_eq!(owner_balance(&state, [1u8; 32]).await, "1000");
+        
+        // The signature covers the outputs
+        let mut redirected = signed(vec![output([0xa1; 32], 2, 600), output([0xa2; 32], 1, 400)]);
+        redirected.outputs[0].owner_commitment = utils::hash_to_hex([3u8; 32]);
+        let (_, Json(error)) = process_transfer(State(state.clone()), Json(redirected)).await.unwrap_err();
+        assert_eq!(error.error, "INVALID_SIGNATURE");
+        
+        let Json(response) = process_transfer(State(state.clone()), Json(signed(
+            vec![output([0xa1; 32], 2, 600), output([0xa2; 32], 1, 400)],
+        ))).await.unwrap();
+        assert_eq!(response.nullifiers, vec![spend.nullifier]);
+        assert_eq!(response.utxo_ids.len(), 2);
+        
+        assert_eq!(owner_balance(&state, [1u8; 32]).await, "400");
+        assert_eq!(owner_balance(&state, [2u8; 32]).await, "600");
+        assert_eq!(owner_utxo_ids(&state, [1u8; 32]).await, vec![response.utxo_ids[1].clone()]);
+        assert_eq!(owner_utxo_ids(&state, [2u8; 32]).await, vec![response.utxo_ids[0].clone()]);
+        
+        let Json(stats) = get_tree_stats(State(state.clone())).await;
+        assert_eq!((stats.total_utxos, stats.total_spent), (2, 1));
+        assert_eq!(stats.current_root, response.new_root);
+    }
+
+    #[tokio::test]
+    async fn test_commitment_status_reports_exists_and_spent() {
+        let state = AppState::new().unwrap();
+        let _unspent = deposit_for_withdrawal(&state, 1);
+        let spent = deposit_for_withdrawal(&state, 2);
+        process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![spent] }),
+        ).await.unwrap();
+        
+        let commitments = [1, 2, 3].map(|tag| utils::hash_to_hex(TestNote::new(tag).commitment()));
+        let Json(response) = get_commitment_status(
+            State(state.clone()),
+            Json(CommitmentStatusRequest { commitments: commitments.to_vec() }),
+        ).await.unwrap();
+        
+        let triples: Vec<_> = response.statuses.iter()
+            .map(|status| (status.commitment.as_str(), status.exists, status.is_spent))
+            .collect();
+        assert_eq!(triples, vec![
+            (commitments[0].as_str(), true, false),
+            (commitments[1].as_str(), true, true),
+            (commitments[2].as_str(), false, false),
+        ]);
+        
+        let oversized = vec![commitments[0].clone(); state.config.max_commitment_status_batch + 1];
+        let (_, Json(error)) = get_commitment_status(
+            State(state),
+            Json(CommitmentStatusRequest { commitments: oversized }),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "BATCH_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_requires_min_anonymity_set() {
+        let config = AppConfig {
+            min_anonymity_set: 3,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let withdrawal = deposit_amount_for_withdrawal(&state, 1, 1_000);
+        deposit_amount_for_withdrawal(&state, 2, 1_000);
+        // A different denomination does not grow the set
+        deposit_amount_for_withdrawal(&state, 3, 2_000);
+        
+        let (_, Json(error)) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![withdrawal.clone()] }),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "ANONYMITY_SET_TOO_SMALL");
+        assert_eq!(state.utxos.lock().unwrap().len(), 3);
+        
+        deposit_amount_for_withdrawal(&state, 4, 1_000);
+        let Json(response) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![withdrawal] }),
+        ).await.unwrap();
+        assert!(response.success);
+        assert_eq!(response.total_amount, "1000");
+    }
+
+    #[tokio::test]
+    async fn test_batch_withdraw_counts_anonymity_set_after_earlier_removals() {
+        let config = AppConfig {
+            min_anonymity_set: 2,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let first = deposit_amount_for_withdrawal(&state, 1, 1_000);
+        let second = deposit_amount_for_withdrawal(&state, 2, 1_000);
+        
+        // Each alone sees a set of two, but the second leaves a set of one behind the first
+        let (_, Json(error)) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![first.clone(), second] }),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "ANONYMITY_SET_TOO_SMALL");
+        assert_eq!(state.utxos.lock().unwrap().len(), 2);
+        
+        let Json(response) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![first] }),
+        ).await.unwrap();
+        assert!(response.success);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_requires_owner_authorization() {
+        let state = AppState::new().unwrap();
+        let owner = TestNote::new(1);
+        let withdrawal = deposit_for_withdrawal(&state, 1);
+        let utxo_id = utils::hex_to_hash(&withdrawal.utxo_id).unwrap();
+        let owner_commitment = state.utxos.lock().unwrap()[&utxo_id].owner_commitment;
+        let attacker_key = [0xee; 32];
+        
+        // The public UTXO-derived nullifier is refused even when signed by the owner
+        let mut public_nullifier = withdrawal.clone();
+        public_nullifier.nullifier = utils::hash_to_hex(crate::canonical_spec::generate_nullifier(utxo_id, owner_commitment));
+        sign_withdrawal(&owner.spending_key, &mut public_nullifier);
+        let (_, Json(error)) = process_withdraw(State(state.clone()), Json(public_nullifier)).await.unwrap_err();
+        assert_eq!(error.error, "INVALID_NULLIFIER");
+        
+        // Keys that do not open the UTXO's spend commitment
+        let attacker = TestNote { spending_key: attacker_key, ..TestNote::new(1) };
+        let mut foreign_keys = withdrawal.clone();
+        foreign_keys.nullifier = utils::hash_to_hex(attacker.nullifier(&utxo_id));
+        foreign_keys.authorization = attacker.authorization();
+        sign_withdrawal(&attacker_key, &mut foreign_keys);
+        let (_, Json(error)) = process_withdraw(State(state.clone()), Json(foreign_keys)).await.unwrap_err();
+        assert_eq!(error.error, "UNAUTHORIZED_SPEND");
+        
+        // Knowing the owner's public keys and note secret is not enough to sign
+        let mut forged = withdrawal.clone();
+        sign_withdrawal(&attacker_key, &mut forged);
+        let (_, Json(error)) = process_withdraw(State(state.clone()), Json(forged)).await.unwrap_err();
+        assert_eq!(error.error, "INVALID_SIGNATURE");
+        
+        // The recipient cannot be swapped after signing
+        let mut redirected = withdrawal.clone();
+        redirected.recipient = web3::types::Address::repeat_byte(0xee);
+        let (_, Json(error)) = process_withdraw(State(state.clone()), Json(redirected)).await.unwrap_err();
+        assert_eq!(error.error, "INVALID_SIGNATURE");
+        
+        assert!(state.spent_nullifiers.lock().unwrap().is_empty());
+        let Json(response) = process_withdraw(State(state.clone()), Json(withdrawal.clone())).await.unwrap();
+        assert_eq!(response.nullifier, withdrawal.nullifier);
+        assert_eq!(response.recipient, format!("{:?}", withdrawal.recipient));
+    }
+
+    #[tokio::test]
+    async fn test_batch_withdraw_rejects_duplicate_nullifier() {
+        let state = AppState::new().unwrap();
+        let first = deposit_for_withdrawal(&state, 1);
+        let second = deposit_for_withdrawal(&state, 2);
+        
+        let (_, Json(error)) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![first.clone(), second, first] }),
+        ).await.unwrap_err();
+        
+        assert_eq!(error.error, "DUPLICATE_NULLIFIER");
+        // Nothing from the batch was applied
+        assert_eq!(state.utxos.lock().unwrap().len(), 2);
+        assert!(state.spent_nullifiers.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_withdraw_rejects_stale_proof() {
+        let config = AppConfig {
+            root_tolerance_window: 2,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let stale = deposit_for_withdrawal(&state, 1);
+        let fresh = deposit_for_withdrawal(&state, 2);
+        deposit_for_withdrawal(&state, 3);
+        let version_before = *state.tree_version.lock().unwrap();
+        
+        let (_, Json(error)) = process_batch_withdraw(
+            State(state.clone()),
+            Json(BatchWithdrawRequest { withdrawals: vec![fresh, stale] }),
+        ).await.unwrap_err();
+        
+        assert_eq!(error.error, "STALE_ROOT");
+        assert!(error.message.starts_with("Withdrawal 1"));
+        assert_eq!(state.utxos.lock().unwrap().len(), 3);
+        assert_eq!(*state.tree_version.lock().unwrap(), version_before);
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_requires_inclusion_proof() {
+        let state = AppState::new().unwrap();
+        let owner = TestNote::new(1);
+        let withdrawal = deposit_for_withdrawal(&state, 1);
+        let other = deposit_for_withdrawal(&state, 2);
+        let root_before = *state.tree_root.lock().unwrap();
+        
+        // No path, a tampered path, and another UTXO's path all fail
+        let mut missing = withdrawal.clone();
+        missing.siblings.clear();
+        let mut tampered = withdrawal.clone();
+        tampered.siblings[0] = utils::hash_to_hex([0xab; 32]);
+        let mut borrowed = withdrawal.clone();
+        borrowed.merkle_root = other.merkle_root.clone();
+        borrowed.siblings = other.siblings.clone();
+        for mut bad in [missing, tampered, borrowed] {
+            sign_withdrawal(&owner.spending_key, &mut bad);
+            let (_, Json(error)) = process_withdraw(State(state.clone()), Json(bad)).await.unwrap_err();
+            assert_eq!(error.error, "INVALID_MERKLE_PROOF");
+        }
+        assert!(state.spent_nullifiers.lock().unwrap().is_empty());
+        assert_eq!(*state.tree_root.lock().unwrap(), root_before);
+        
+        // A proof against an older root inside the window still spends
+        let withdrawal_id = withdrawal.utxo_id.clone();
+        let Json(response) = process_withdraw(State(state.clone()), Json(withdrawal)).await.unwrap();
+        let tree = state.utxo_tree.lock().unwrap();
+        assert_eq!(response.new_root, utils::hash_to_hex(tree.get_root()));
+        let spent_id = utils::hex_to_hash(&withdrawal_id).unwrap();
+        let remaining_id = utils::hex_to_hash(&other.utxo_id).unwrap();
+        assert!(tree.get_leaf(tree.leaf_position(&spent_id)).is_none());
+        assert!(tree.get_leaf(tree.leaf_position(&remaining_id)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rpc_call_times_out_on_hung_server() {
+        // Accept connections but never answer
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                connections.push(socket);
+            }
+        });
+        
+        let config = AppConfig {
+            rpc_timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            verify_deposit_transaction(
+                &RpcChain {
+                    client: &state.http_client,
+                    rpc_urls: &[rpc_url.clone()],
+                    health: &ProviderHealth::new(1, FailoverConfig::default()),
+                    max_response_bytes: state.config.max_rpc_response_bytes,
+                },
+                "0x00",
+                &state.config.contract_address,
+                0,
+            ),
+        ).await.expect("RPC call hung past the configured timeout");
+        
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "unexpected error: {}", err);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_rpc_response_is_rejected() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Stream a chunked body with no Content-Length until the client hangs up
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let header = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n";
+                    if socket.write_all(header.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    let chunk = format!("{:x}\r\n{}\r\n", 64 * 1024, "a".repeat(64 * 1024));
+                    // Bounded so a client without a limit fails the test instead of hanging
+                    for _ in 0..1024 {
+                        if socket.write_all(chunk.as_bytes()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        
+        let config = AppConfig {
+            sepolia_rpc_url: rpc_url.clone(),
+            max_rpc_response_bytes: 4096,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        
+        let err = verify_deposit_transaction(
+            &RpcChain {
+                client: &state.http_client,
+                rpc_urls: &[rpc_url.clone()],
+                health: &state.rpc_health,
+                max_response_bytes: state.config.max_rpc_response_bytes,
+            },
+            "0x00",
+            &state.config.contract_address,
+            0,
+        ).await.unwrap_err();
+        let too_large = err.downcast_ref::<RpcResponseTooLarge>().expect("expected bounded error");
+        assert_eq!(too_large.max_bytes, 4096);
+        
+        let request = DepositRequest {
+            depositor: web3::types::Address::zero(),
+            commitment: web3::types::H256::zero(),
+            amount: web3::types::U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: web3::types::H256::zero(),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        };
+        let (status, Json(error)) = process_deposit(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "RPC_RESPONSE_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_rpc_rate_limit_error_is_reported_distinctly() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Answer every call with a JSON-RPC rate-limit error and no result
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let body = r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"Your app has exceeded its compute units per second capacity"}}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        
+        let config = AppConfig {
+            sepolia_rpc_url: rpc_url.clone(),
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        
+        let err = verify_deposit_transaction(
+            &RpcChain {
+                client: &state.http_client,
+                rpc_urls: &[rpc_url.clone()],
+                health: &state.rpc_health,
+                max_response_bytes: state.config.max_rpc_response_bytes,
+            },
+            "0x00",
+            &state.config.contract_address,
+            0,
+        ).await.unwrap_err();
+        let rpc = err.downcast_ref::<RpcError>().expect("expected JSON-RPC error");
+        assert_eq!(rpc.code, -32005);
+        assert!(!err.to_string().contains("not found"), "unexpected error: {}", err);
+        
+        let request = DepositRequest {
+            depositor: web3::types::Address::zero(),
+            commitment: web3::types::H256::zero(),
+            amount: web3::types::U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: web3::types::H256::zero(),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        };
+        let (_, Json(error)) = process_deposit(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(error.error, "RPC_ERROR");
+        let details = error.details.expect("RPC error details");
+        assert_eq!(details["code"], -32005);
+        assert_eq!(details["message"], "Your app has exceeded its compute units per second capacity");
+    }
+
+    #[tokio::test]
+    async fn test_fresh_state_reports_empty_tree_root() {
+        let config = AppConfig {
+            tree_depth: 16,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config.clone()).unwrap();
+        
+        // Independently: the root of a freshly opened canonical SMT
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db = crate::database::DatabaseManager::open(crate::database::schema::DBConfig {
+            db_path,
+            ..Default::default()
+        }).unwrap();
+        let smt = crate::merkle::CanonicalSMT::new(db, config.tree_depth, config.tree_salt).unwrap();
+        let expected = utils::hash_to_hex(smt.get_root());
+        
+        let Json(root) = get_tree_root(State(state.clone())).await;
+        assert_eq!(root["root"], json!(expected));
+        let Json(stats) = get_tree_stats(State(state)).await;
+        assert_eq!(stats.current_root, expected);
+        assert_ne!(expected, utils::hash_to_hex([0u8; 32]));
+    }
+
+    #[tokio::test]
+    async fn test_scripted_chain_drives_deposit_flow() {
+        use crate::api::chain_query::scripted::ScriptedChain;
+        use web3::types::{Address, H256, U256};
+        
+        let config = AppConfig {
+            min_deposit_confirmations: 12,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let contract = state.config.contract_address.clone();
+        let depositor = "0x00000000000000000000000000000000000000aa";
+        let chain = ScriptedChain::new(1_000);
+        let tx_hash = |tag: u8| format!("{:?}", H256::repeat_byte(tag));
+        let deposit = |tag: u8| DepositRequest {
+            depositor: Address::zero(),
+            commitment: H256::repeat_byte(tag),
+            amount: U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: H256::repeat_byte(tag),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        };
+        
+        // Buried deep enough: verified and minted
+        chain.add_mined_transfer(&tx_hash(1), depositor, &contract, 2_000_000_000_000_000_000, 20);
+        let Json(minted) = deposit_via_chain(&state, &chain, deposit(1)).await.unwrap();
+        assert!(minted.success);
+        assert_eq!(minted.root_version, 1);
+        let utxo_id = utils::hex_to_hash(&minted.utxo_id).unwrap();
+        assert_eq!(state.utxos.lock().unwrap()[&utxo_id].amount, 2_000_000_000_000_000_000);
+        
+        // Too shallow: refused until the head advances
+        chain.add_mined_transfer(&tx_hash(2), depositor, &contract, 5_000, 3);
+        let (_, Json(error)) = deposit_via_chain(&state, &chain, deposit(2)).await.unwrap_err();
+        assert_eq!(error.error, "INSUFFICIENT_CONFIRMATIONS");
+        let details = error.details.expect("confirmation details");
+        assert_eq!((details["required"].as_u64(), details["actual"].as_u64()), (Some(12), Some(3)));
+        assert_eq!(state.utxos.lock().unwrap().len(), 1);
+        
+        chain.set_head(1_009);
+        let Json(minted) = deposit_via_chain(&state, &chain, deposit(2)).await.unwrap();
+        assert_eq!(minted.root_version, 2);
+        assert_eq!(state.utxos.lock().unwrap().len(), 2);
+        
+        // Scripted node failures and unknown transactions mint nothing
+        chain.fail(&tx_hash(3), "connection reset by peer");
+        let (_, Json(error)) = deposit_via_chain(&state, &chain, deposit(3)).await.unwrap_err();
+        assert_eq!(error.error, "BLOCKCHAIN_VERIFICATION_FAILED");
+        assert!(error.message.contains("connection reset by peer"));
+        
+        let (_, Json(error)) = deposit_via_chain(&state, &chain, deposit(4)).await.unwrap_err();
+        assert_eq!(error.message, "Transaction not found");
+        assert_eq!(state.utxos.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_address_policy_screens_depositors() {
+        use crate::api::chain_query::scripted::ScriptedChain;
+        use web3::types::{Address, H256, U256};
+        
+        let state = AppState::new().unwrap();
+        let contract = state.config.contract_address.clone();
+        let denied = "0x00000000000000000000000000000000000000dd";
+        let permitted = "0x00000000000000000000000000000000000000aa";
+        let chain = ScriptedChain::new(1_000);
+        let tx_hash = |tag: u8| format!("{:?}", H256::repeat_byte(tag));
+        let deposit = |tag: u8| DepositRequest {
+            depositor: Address::zero(),
+            commitment: H256::repeat_byte(tag),
+            amount: U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: H256::repeat_byte(tag),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        };
+        chain.add_mined_transfer(&tx_hash(1), denied, &contract, 1_000, 1);
+        chain.add_mined_transfer(&tx_hash(2), permitted, &contract, 1_000, 1);
+        chain.add_mined_transfer(&tx_hash(3), permitted, &contract, 1_000, 1);
+        
+        let Json(summary) = update_address_policy(State(state.clone()), Json(AddressPolicy {
+            allowlist: None,
+            denylist: HashSet::from([Address::from_str(denied).unwrap()]),
+        })).await;
+        assert_eq!(summary["denylist"], json!(1));
+        
+        // Denylisted sender: rejected before anything is minted
+        let (_, Json(error)) = deposit_via_chain(&state, &chain, deposit(1)).await.unwrap_err();
+        assert_eq!(error.error, "ADDRESS_NOT_PERMITTED");
+        assert!(state.utxos.lock().unwrap().is_empty());
+        
+        // Anyone else is still accepted
+        let Json(minted) = deposit_via_chain(&state, &chain, deposit(2)).await.unwrap();
+        assert!(minted.success);
+        
+        // Reloading an allowlist that omits the sender takes effect immediately
+        update_address_policy(State(state.clone()), Json(AddressPolicy {
+            allowlist: Some(HashSet::from([Address::from_str(denied).unwrap()])),
+            denylist: HashSet::new(),
+        })).await;
+        let (_, Json(error)) = deposit_via_chain(&state, &chain, deposit(3)).await.unwrap_err();
+        assert_eq!(error.error, "ADDRESS_NOT_PERMITTED");
+        assert_eq!(state.utxos.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_event_subscription_filters_by_owner() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+        
+        let state = AppState::new().unwrap();
+        let app = Router::new()
+            .route("/api/ws/events", get(subscribe_events))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/ws/events", addr))
+            .await
+            .unwrap();
+        
+        let owner_a = [0xaau8; 32];
+        let owner_b = [0xbbu8; 32];
+        let subscribe = serde_json::to_string(&SubscribeRequest {
+            owners: vec![utils::hash_to_hex(owner_a)],
+        }).unwrap();
+        client.send(WsMessage::Text(subscribe)).await.unwrap();
+        
+        let ack = client.next().await.unwrap().unwrap().into_text().unwrap();
+        let ack: SubscribeResponse = serde_json::from_str(&ack).unwrap();
+        assert_eq!(ack.subscribed, 1);
+        
+        let deposit_b = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, owner_b);
+        let deposit_a = CanonicalUTXO::new_eth([2u8; 32], 0, 100, 2, 2_000, owner_a);
+        record_deposit(&state, &deposit_b, [1u8; 32], deposit_b.leaf_hash().unwrap(), None).unwrap();
+        record_deposit(&state, &deposit_a, [2u8; 32], deposit_a.leaf_hash().unwrap(), None).unwrap();
+        
+        let event = client.next().await.unwrap().unwrap().into_text().unwrap();
+        let event: PoolEvent = serde_json::from_str(&event).unwrap();
+        assert_eq!(event.event_type, PoolEventType::Deposit);
+        assert_eq!(event.owner_commitment, utils::hash_to_hex(owner_a));
+        assert_eq!(event.amount, "2000");
+        
+        // Owner B's deposit must never arrive
+        let next = tokio::time::timeout(Duration::from_millis(200), client.next()).await;
+        assert!(next.is_err());
+    }
+
+    fn test_note(tag: u8) -> EncryptedNotePayload {
+        EncryptedNotePayload {
+            ephemeral_pubkey: hex::encode([tag; 33]),
+            nonce: hex::encode([tag; 24]),
+            ciphertext: hex::encode([tag; 64]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_owner_notes_returns_ciphertexts_by_block() {
+        let state = AppState::new().unwrap();
+        let owner = [9u8; 32];
+        let other_owner = [10u8; 32];
+        
+        let utxos = [
+            (CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, owner), test_note(1)),
+            (CanonicalUTXO::new_eth([2u8; 32], 0, 200, 2, 2_000, owner), test_note(2)),
+            (CanonicalUTXO::new_eth([3u8; 32], 0, 300, 3, 3_000, owner), test_note(3)),
+            (CanonicalUTXO::new_eth([4u8; 32], 0, 200, 4, 4_000, other_owner), test_note(4)),
+        ];
+        for (utxo, note) in utxos {
+            state.encrypted_notes.lock().unwrap().insert(utxo.utxo_id, note);
+            insert_test_utxo(&state, utxo);
+        }
+        
+        let Json(page) = get_owner_notes(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(NotesQuery { from_block: None, limit: Some(2) }),
+        ).await.unwrap();
+        
+        assert_eq!(page.notes.len(), 2);
+        assert_eq!(page.notes[0].created_block, 100);
+        assert_eq!(page.notes[0].note.ciphertext, test_note(1).ciphertext);
+        assert_eq!(page.notes[1].note.ciphertext, test_note(2).ciphertext);
+        assert_eq!(page.next_from_block, Some(300));
+        
+        let Json(page) = get_owner_notes(
+            State(state),
+            Path(utils::hash_to_hex(owner)),
+            Query(NotesQuery { from_block: page.next_from_block, limit: Some(2) }),
+        ).await.unwrap();
+        
+        assert_eq!(page.notes.len(), 1);
+        assert_eq!(page.notes[0].note.ciphertext, test_note(3).ciphertext);
+        assert_eq!(page.next_from_block, None);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_lock_data_rejected_before_rpc() {
+        // Unroutable RPC endpoint: reaching it would fail with a different error
+        let config = AppConfig {
+            sepolia_rpc_url: "http://127.0.0.1:1".to_string(),
+            max_lock_data_bytes: 64,
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        
+        let request = DepositRequest {
+            depositor: web3::types::Address::zero(),
+            commitment: web3::types::H256::zero(),
+            amount: web3::types::U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: web3::types::H256::zero(),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: Some(format!("0x{}", "ab".repeat(65))),
+        };
+        
+        let (status, Json(error)) = process_deposit(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "LOCK_DATA_TOO_LARGE");
+        assert!(error.message.contains("65 bytes"), "unexpected message: {}", error.message);
+        assert!(state.utxos.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_operator_pubkey_verifies_root_signature() {
+        let state = AppState::new().unwrap();
+        let Json(response) = get_operator_pubkey(State(state.clone())).await;
+        
+        let public_key = hex::decode(response.public_key.trim_start_matches("0x")).unwrap();
+        let message = b"root";
+        let signature = state.operator_keypair.sign(message).unwrap();
+        
+        assert!(OperatorKeypair::verify(response.algorithm, &public_key, message, &signature).unwrap());
+    }
+
+    #[test]
+    fn test_deposit_owner_commitment_matches_wallet() {
+        let state = AppState::new().unwrap();
+        let deposit = BlockchainDepositEvent {
+            depositor: web3::types::Address::repeat_byte(0x12),
+            commitment: web3::types::H256::repeat_byte(0x34),
+            label: web3::types::U256::zero(),
+            value: web3::types::U256::from(1_000u64),
+            precommitment_hash: web3::types::H256::zero(),
+            block_number: 12_345,
+            transaction_hash: web3::types::H256::repeat_byte(0x11),
+            log_index: 0,
+            asset: web3::types::Address::zero(),
+        };
+        
+        let utxo = create_utxo_from_verified_deposit(&deposit, Vec::new(), &state).unwrap();
+        assert!(crate::wallet::verify_deposit_commitment(
+            utxo.owner_commitment, [0x12u8; 20], [0x34u8; 32], 12_345,
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_operator_pubkey_is_the_persisted_signer() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("tree_db").to_string_lossy().to_string();
+        let config = AppConfig { tree_db_path: Some(db_path.clone()), ..Default::default() };
+        
+        let Json(first) = get_operator_pubkey(State(AppState::with_config(config.clone()).unwrap())).await;
+        let Json(restarted) = get_operator_pubkey(State(AppState::with_config(config.clone()).unwrap())).await;
+        assert_eq!(restarted.public_key, first.public_key);
+        
+        // The UTXO manager over the same store signs with the served key
+        let db = crate::database::DatabaseManager::open(crate::database::schema::DBConfig {
+            db_path,
+            ..Default::default()
+        }).unwrap();
+        let (algorithm, public_key) = crate::utxo::UTXOManager::get_operator_public_key(&db).unwrap().unwrap();
+        assert_eq!(algorithm, first.algorithm);
+        assert_eq!(format!("0x{}", hex::encode(public_key)), first.public_key);
+        drop(db);
+        
+        // A stored key outlives a change of the configured algorithm
+        let Json(reconfigured) = get_operator_pubkey(State(AppState::with_config(AppConfig {
+            operator_signature_algorithm: SignatureAlgorithm::Secp256k1,
+            ..config
+        }).unwrap())).await;
+        assert_eq!(reconfigured.public_key, first.public_key);
+    }
+
+    #[tokio::test]
+    async fn test_tree_stats_start_from_persisted_counters() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("tree_db").to_string_lossy().to_string();
+        let persisted = PoolCounters { total_utxos: 3, total_spent: 2, total_deposited_wei: 5_000 };
+        {
+            let db = crate::database::DatabaseManager::open(crate::database::schema::DBConfig {
+                db_path: db_path.clone(),
+                ..Default::default()
+            }).unwrap();
+            db.put_cf(
+                crate::database::schema::cf_names::TREE_METADATA,
+                crate::database::pool_counters::POOL_COUNTERS_KEY,
+                &persisted.serialize(),
+            ).unwrap();
+        }
+        
+        let state = AppState::with_config(AppConfig { tree_db_path: Some(db_path), ..Default::default() }).unwrap();
+        let Json(stats) = get_tree_stats(State(state)).await;
+        assert_eq!(stats.total_utxos, 3);
+        assert_eq!(stats.total_spent, 2);
+        assert_eq!(stats.total_deposited_wei, "5000");
+    }
+
+    #[tokio::test]
+    async fn test_operator_key_uses_configured_algorithm() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = AppConfig {
+            operator_signature_algorithm: SignatureAlgorithm::Secp256k1,
+            ..Default::default()
+        };
+        
+        let Json(in_memory) = get_operator_pubkey(State(AppState::with_config(config.clone()).unwrap())).await;
+        assert_eq!(in_memory.algorithm, SignatureAlgorithm::Secp256k1);
+        
+        let db_path = temp_dir.path().join("tree_db").to_string_lossy().to_string();
+        let Json(persisted) = get_operator_pubkey(State(AppState::with_config(AppConfig {
+            tree_db_path: Some(db_path),
+            ..config
+        }).unwrap())).await;
+        assert_eq!(persisted.algorithm, SignatureAlgorithm::Secp256k1);
+    }
+
+    #[tokio::test]
+    async fn test_verify_commitment_opening() {
+        let state = AppState::new().unwrap();
+        let owner = [0x31u8; 32];
+        let blinding = [0x42u8; 32];
+        let commitment = crate::canonical_spec::generate_value_commitment(5_000, owner, blinding);
+        
+        let opening = |value: &str| CommitmentOpeningRequest {
+            commitment: utils::hash_to_hex(commitment),
+            value: value.to_string(),
+            blinding: utils::hash_to_hex(blinding),
+            owner: utils::hash_to_hex(owner),
+        };
+        
+        let Json(response) = verify_commitment_opening(State(state.clone()), Json(opening("5000"))).await.unwrap();
+        assert!(response.valid);
+        
+        let Json(response) = verify_commitment_opening(State(state.clone()), Json(opening("5001"))).await.unwrap();
+        assert!(!response.valid);
+        
+        // Amounts past u64::MAX open at full width rather than truncating
+        let large = u64::MAX as u128 + 5_000;
+        let large_commitment = crate::canonical_spec::generate_value_commitment(large, owner, blinding);
+        let Json(response) = verify_commitment_opening(State(state.clone()), Json(CommitmentOpeningRequest {
+            commitment: utils::hash_to_hex(large_commitment),
+            ..opening(&large.to_string())
+        })).await.unwrap();
+        assert!(response.valid);
+        
+        let (status, _) = verify_commitment_opening(State(state), Json(opening("five"))).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_degraded_start_when_smt_fails_to_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("tree_db").to_string_lossy().to_string();
+        let owner = [7u8; 32];
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, owner);
+        let utxo_id = utxo.utxo_id;
+        {
+            let db = crate::database::DatabaseManager::open(crate::database::schema::DBConfig {
+                db_path: db_path.clone(),
+                ..Default::default()
+            }).unwrap();
+            let mut writer = crate::database::AtomicBatchWriter::new(db.clone());
+            writer.add_operation(crate::database::BatchOperation::InsertOwnerIndex {
+                owner_commitment: owner,
+                created_block: utxo.created_block,
+                utxo_id: utxo.utxo_id,
+                amount: utxo.amount,
+                asset_id: utxo.asset_id,
+                flags: 0,
+            });
+            writer.add_operation(crate::database::BatchOperation::UpdateAssetBalance {
+                owner_commitment: owner,
+                asset_id: utxo.asset_id,
+                amount_delta: 1_000,
+                utxo_count_delta: 1,
+                last_updated_block: utxo.created_block,
+            });
+            writer.add_operation(crate::database::BatchOperation::InsertUTXO { utxo });
+            writer.commit().unwrap();
+            
+            // Corrupt the persisted tree config so the SMT refuses to open
+            db.put_cf(crate::database::schema::cf_names::TREE_METADATA, crate::merkle::canonical_smt::TREE_CONFIG_KEY, &[0xFF]).unwrap();
+        }
+        
+        let config = AppConfig {
+            tree_db_path: Some(db_path),
+            ..Default::default()
+        };
+        assert!(AppState::with_config(config.clone()).is_err());
+        
+        let state = AppState::with_config(AppConfig { allow_degraded_start: true, ..config }).unwrap();
+        assert!(state.tree_unavailable.is_some());
+        let Json(health) = health_check(State(state.clone())).await;
+        assert_eq!(health.status, "degraded");
+        
+        // Reads keep serving from the persisted balances and UTXOs
+        let Json(balance) = get_balance(State(state.clone()), Path(utils::hash_to_hex(owner)), Query(FormatQuery { format: None }))
+            .await
+            .unwrap();
+        assert_eq!(balance.balance, "1000");
+        assert_eq!(balance.utxo_count, 1);
+        assert_eq!(balance.last_updated_block, 100);
+        let Json(utxos) = get_owner_utxos(
+            State(state.clone()),
+            Path(utils::hash_to_hex(owner)),
+            Query(UTXOQuery { limit: None, after_block: None, asset_id: None, format: None, sort: None }),
+        ).await.unwrap();
+        assert_eq!(utxos.total_count, 1);
+        assert_eq!(utxos.utxos[0].utxo_id, utils::hash_to_hex(utxo_id));
+        
+        // Tree mutations are refused before any RPC is attempted
+        let request = DepositRequest {
+            depositor: web3::types::Address::zero(),
+            commitment: web3::types::H256::zero(),
+            amount: web3::types::U256::from(1_000u64),
+            block_number: 1,
+            tx_hash: web3::types::H256::zero(),
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        };
+        let (status, Json(error)) = process_deposit(State(state.clone()), Json(request))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.error, "TREE_UNAVAILABLE");
+        
+        let withdraw = WithdrawRequest {
+            utxo_id: utils::hash_to_hex([1u8; 32]),
+            nullifier: utils::hash_to_hex([2u8; 32]),
+            merkle_root: utils::hash_to_hex(*state.tree_root.lock().unwrap()),
+            siblings: Vec::new(),
+            recipient: web3::types::Address::zero(),
+            authorization: TestNote::new(1).authorization(),
+        };
+        let (status, _) = process_withdraw(State(state), Json(withdraw)).await.unwrap_err();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_call_fails_over_to_fallback_provider() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Fallback provider answers every call with block 0x10
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fallback_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let body = r#"{"jsonrpc":"2.0","id":1,"result":"0x10"}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        
+        // Nothing listens on the primary
+        let config = AppConfig {
+            sepolia_rpc_url: "http://127.0.0.1:1".to_string(),
+            sepolia_rpc_fallback_urls: vec![fallback_url],
+            ..Default::default()
+        };
+        let state = AppState::with_config(config).unwrap();
+        let rpc_urls = state.config.rpc_urls();
+        let chain = RpcChain {
+            client: &state.http_client,
+            rpc_urls: &rpc_urls,
+            health: &state.rpc_health,
+            max_response_bytes: state.config.max_rpc_response_bytes,
+        };
+        
+        for _ in 0..4 {
+            assert_eq!(chain.block_number().await.unwrap(), 16);
+        }
+        assert!(!state.rpc_health.is_healthy(0));
+        assert!(state.rpc_health.is_healthy(1));
+    }
+
+    /// Batch item for `leaf` at `leaf_index`, its root folded up `siblings`
+    /// with the UTXO tree's node hash and remembered as a known root
+    fn proof_item(state: &AppState, leaf: [u8; 32], siblings: &[[u8; 32]], leaf_index: u64) -> ProofVerifyItem {
+        let root = crate::canonical_spec::compute_root_from_proof(leaf, leaf_index, siblings);
+        state.recent_roots.lock().unwrap().push_back(root);
+        ProofVerifyItem {
+            leaf: utils::hash_to_hex(leaf),
+            proof: MerkleProofData {
+                siblings: siblings.iter().copied().map(utils::hash_to_hex).collect(),
+                path: (0..siblings.len()).map(|level| ((leaf_index >> level) & 1) as u32).collect(),
+                root: utils::hash_to_hex(root),
+                leaf_index,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_proof_verification_reports_each_proof() {
+        let state = AppState::with_config(AppConfig { tree_depth: 4, ..Default::default() }).unwrap();
+        
+        let valid_a = proof_item(&state, [1u8; 32], &[[2u8; 32]; 4], 2);
+        let valid_b = proof_item(&state, [3u8; 32], &[[4u8; 32], [5u8; 32], [6u8; 32], [7u8; 32]], 15);
+        let wrong_leaf = ProofVerifyItem { leaf: utils::hash_to_hex([9u8; 32]), ..valid_a.clone() };
+        let mut wrong_root = valid_b.clone();
+        wrong_root.proof.root = valid_a.proof.root.clone();
+        let mut wrong_path = valid_a.clone();
+        wrong_path.proof.path[0] = 1;
+        
+        // A proof that folds correctly but to a root the pool never had
+        let unknown_root = proof_item(&state, [5u8; 32], &[[6u8; 32]; 4], 0);
+        state.recent_roots.lock().unwrap().pop_back();
+        
+        let Json(response) = verify_merkle_proofs(
+            State(state.clone()),
+            Json(BatchProofVerifyRequest {
+                proofs: vec![valid_a.clone(), wrong_leaf, valid_b.clone(), wrong_root, wrong_path, unknown_root],
+            }),
+        ).await.unwrap();
+        assert_eq!(response.results, vec![true, false, true, false, false, false]);
+        assert!(!response.all_valid);
+        
+        let Json(response) = verify_merkle_proofs(
+            State(state.clone()),
+            Json(BatchProofVerifyRequest { proofs: vec![valid_a.clone(), valid_b] }),
+        ).await.unwrap();
+        assert_eq!(response.results, vec![true, true]);
+        assert!(response.all_valid);
+        
+        // A proof for a different tree depth rejects the whole batch
+        let mut shallow = valid_a.clone();
+        shallow.proof.siblings.pop();
+        shallow.proof.path.pop();
+        let (status, Json(error)) = verify_merkle_proofs(
+            State(state),
+            Json(BatchProofVerifyRequest { proofs: vec![valid_a, shallow] }),
+        ).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error, "PROOF_DEPTH_MISMATCH");
+        assert!(error.message.starts_with("Proof 1:"));
+    }
+
+    #[tokio::test]
+    async fn test_utxo_proof_verifies_against_tree_root() {
+        let state = AppState::new().unwrap();
+        let first = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, [7u8; 32]);
+        let second = CanonicalUTXO::new_eth([2u8; 32], 0, 100, 2, 2_000, [8u8; 32]);
+        record_deposit(&state, &first, [1u8; 32], first.leaf_hash().unwrap(), None).unwrap();
+        record_deposit(&state, &second, [2u8; 32], second.leaf_hash().unwrap(), None).unwrap();
+        
+        let Json(proof) = get_utxo_proof(
+            State(state.clone()),
+            Path(utils::hash_to_hex(second.utxo_id)),
+        ).await.unwrap();
+        assert_eq!(proof.utxo_id, utils::hash_to_hex(second.utxo_id));
+        assert_eq!(proof.leaf, utils::hash_to_hex(second.leaf_hash().unwrap()));
+        assert_eq!(proof.leaf_index, state.utxo_tree.lock().unwrap().leaf_position(&second.utxo_id));
+        assert_eq!(proof.siblings.len(), state.config.tree_depth as usize);
+        let Json(current) = get_tree_root(State(state.clone())).await;
+        assert_eq!(proof.root, current["root"].as_str().unwrap());
+        
+        // The served proof passes the batch verifier unchanged
+        let Json(response) = verify_merkle_proofs(
+            State(state.clone()),
+            Json(BatchProofVerifyRequest {
+                proofs: vec![ProofVerifyItem {
+                    leaf: proof.leaf,
+                    proof: MerkleProofData {
+                        siblings: proof.siblings,
+                        path: proof.indices,
+                        root: proof.root,
+                        leaf_index: proof.leaf_index,
+                    },
+                }],
+            }),
+        ).await.unwrap();
+        assert!(response.all_valid);
+        
+        let (_, Json(error)) = get_utxo_proof(
+            State(state),
+            Path(utils::hash_to_hex([0xAB; 32])),
+        ).await.unwrap_err();
+        assert_eq!(error.error, "UTXO_NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_tree_feed_pushes_root_update_on_deposit() {
+        use crate::api::chain_query::scripted::ScriptedChain;
+        use futures_util::StreamExt;
+        use web3::types::{Address, H256, U256};
+        
+        let state = AppState::new().unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = router_with_state(state.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/api/ws", addr))
+            .await
+            .unwrap();
+        
+        let chain = ScriptedChain::new(1_000);
+        let tx_hash = H256::repeat_byte(1);
+        chain.add_mined_transfer(
+            &format!("{:?}", tx_hash),
+            "0x00000000000000000000000000000000000000aa",
+            &state.config.contract_address,
+            3_000,
+            20,
+        );
+        let Json(minted) = deposit_via_chain(&state, &chain, DepositRequest {
+            depositor: Address::zero(),
+            commitment: H256::repeat_byte(1),
+            amount: U256::from(3_000u64),
+            block_number: 1,
+            tx_hash,
+            label: None,
+            precommitment_hash: None,
+            encrypted_note: None,
+            lock_data: None,
+        }).await.unwrap();
+        
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let frame = tokio::time::timeout(Duration::from_secs(5), client.next()).await
+                .expect("feed message")
+                .unwrap()
+                .unwrap();
+            received.push(serde_json::from_str::<TreeFeedMessage>(&frame.into_text().unwrap()).unwrap());
+        }
+        assert_eq!(received, vec![
+            TreeFeedMessage::Deposit {
+                utxo_id: minted.utxo_id.clone(),
+                amount: "3000".to_string(),
+                root_version: 1,
+            },
+            TreeFeedMessage::RootUpdate {
+                root: minted.new_root,
+                root_version: 1,
+                utxo_id: minted.utxo_id,
+            },
+        ]);
+    }
+}
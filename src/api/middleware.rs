@@ -1,9 +1,24 @@
 //! API Middleware
 //! 
-//! Request logging, CORS, and other middleware components
+//! Request logging, CORS, admin authentication, rate limiting and other
+//! middleware components
 
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
 use tower_http::cors::CorsLayer;
 
+use crate::api::handlers::AppState;
+use crate::api::types::ErrorResponse;
+
 /// Create basic logging layer (simplified)
 pub fn create_logging_layer() -> tower::layer::util::Identity {
     // Simplified logging - in would use proper tracing
@@ -13,4 +28,366 @@ pub fn create_logging_layer() -> tower::layer::util::Identity {
 /// Create CORS middleware for development
 pub fn create_cors_layer() -> CorsLayer {
     CorsLayer::permissive()
-}
\ No newline at end of file
+}
+
+/// Require `Authorization: Bearer <AppConfig::admin_token>`
+///
+/// Fails closed: with no token configured every request is rejected.
+pub async fn require_admin_token(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let presented = request.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    
+    let authorized = match (state.config.admin_token.as_deref(), presented) {
+        (Some(expected), Some(presented)) => bool::from(expected.as_bytes().ct_eq(presented.as_bytes())),
+        _ => false,
+    };
+    if !authorized {
+        return unauthorized();
+    }
+    
+    next.run(request).await
+}
+
+/// Clients tracked at once; the longest-tracked client is evicted beyond this
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Token bucket parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    /// Sustained rate: tokens added per second
+    pub requests_per_second: f64,
+    /// Bucket size: requests a fresh client may make at once
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Buckets by client, with clients in the order they were first tracked
+#[derive(Debug, Default)]
+struct Buckets {
+    by_client: HashMap<IpAddr, TokenBucket>,
+    tracked_order: VecDeque<IpAddr>,
+}
+
+/// Per-client-IP token bucket rate limiter
+///
+/// Cloning shares the buckets, so one limiter can guard several routes.
+/// Attach it with `from_fn_with_state(limiter, rate_limit)`.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    /// Reverse proxies whose `X-Forwarded-For` entry names the client
+    trusted_proxies: Arc<Vec<IpAddr>>,
+    /// Most clients tracked at once
+    max_clients: usize,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, trusted_proxies: Vec<IpAddr>) -> Self {
+        Self {
+            config,
+            trusted_proxies: Arc::new(trusted_proxies),
+            max_clients: MAX_TRACKED_CLIENTS,
+            buckets: Arc::new(Mutex::new(Buckets::default())),
+        }
+    }
+
+    pub fn config(&self) -> RateLimitConfig {
+        self.config
+    }
+
+    /// Take one token for `client`, or return how long until one is available
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let burst = f64::from(self.config.burst);
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets { by_client, tracked_order } = &mut *buckets;
+        if !by_client.contains_key(&client) {
+            while by_client.len() >= self.max_clients.max(1) {
+                let Some(oldest) = tracked_order.pop_front() else { break };
+                by_client.remove(&oldest);
+            }
+            tracked_order.push_back(client);
+        }
+
+        let bucket = by_client.entry(client)
+            .or_insert(TokenBucket { tokens: burst, refilled_at: now });
+        *bucket = self.refill(*bucket, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let wait = (1.0 - bucket.tokens) / self.config.requests_per_second;
+        Err(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+    }
+
+    /// Bucket with the tokens earned since it was last refilled, capped at `burst`
+    fn refill(&self, bucket: TokenBucket, now: Instant) -> TokenBucket {
+        let elapsed = now.saturating_duration_since(bucket.refilled_at).as_secs_f64();
+        let earned = elapsed * self.config.requests_per_second.max(0.0);
+        TokenBucket {
+            tokens: (bucket.tokens + earned).min(f64::from(self.config.burst)),
+            refilled_at: now,
+        }
+    }
+}
+
+/// Reject clients that exceeded the limiter's rate with 429 and `Retry-After`
+pub async fn rate_limit(State(limiter): State<RateLimiter>, request: Request, next: Next) -> Response {
+    let peer = request.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client = client_ip(request.headers(), peer, &limiter.trusted_proxies);
+
+    match limiter.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => rate_limited(retry_after),
+    }
+}
+
+/// Client address used as the rate limit key
+///
+/// When the peer is one of `trusted_proxies` this is the last
+/// `X-Forwarded-For` entry, the one appended by the proxy itself; earlier
+/// entries are client-supplied and could be rotated to dodge the limit.
+/// Any other peer could write the header itself, so its own address is
+/// used. Requests without a known peer share the unspecified address.
+pub fn client_ip(headers: &HeaderMap, peer: Option<IpAddr>, trusted_proxies: &[IpAddr]) -> IpAddr {
+    let Some(peer) = peer else {
+        return IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+    };
+    if !trusted_proxies.contains(&peer) {
+        return peer;
+    }
+    headers.get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|last| last.trim().parse().ok())
+        .unwrap_or(peer)
+}
+
+fn rate_limited(retry_after: Duration) -> Response {
+    // Whole seconds, rounded up so a client retrying on time is admitted
+    let retry_after_secs = retry_after.as_secs().saturating_add(u64::from(retry_after.subsec_nanos() > 0));
+    let body = ErrorResponse {
+        error: "RATE_LIMITED".to_string(),
+        message: format!("Too many requests; retry in {} s", retry_after_secs),
+        details: None,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after_secs.to_string())], Json(body)).into_response()
+}
+
+fn unauthorized() -> Response {
+    let body = ErrorResponse {
+        error: "UNAUTHORIZED".to_string(),
+        message: "Admin endpoints require a valid bearer token".to_string(),
+        details: None,
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+    };
+    (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], Json(body)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+    use crate::api::handlers::{router_with_state, AppConfig};
+
+    fn state_with_token(admin_token: Option<&str>) -> AppState {
+        AppState::with_config(AppConfig {
+            admin_token: admin_token.map(str::to_string),
+            root_tolerance_window: 2,
+            ..Default::default()
+        }).unwrap()
+    }
+
+    async fn status(state: &AppState, method: &str, uri: &str, token: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        router_with_state(state.clone())
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_require_bearer_token() {
+        let state = state_with_token(Some("s3cret"));
+        state.recent_roots.lock().unwrap().extend([[1u8; 32], [2u8; 32], [3u8; 32]]);
+
+        assert_eq!(status(&state, "POST", "/api/admin/roots/prune", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&state, "POST", "/api/admin/roots/prune", Some("wrong")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(state.recent_roots.lock().unwrap().len(), 3);
+
+        // Only roots beyond the tolerance window are pruned
+        assert_eq!(status(&state, "POST", "/api/admin/roots/prune", Some("s3cret")).await, StatusCode::OK);
+        assert_eq!(state.recent_roots.lock().unwrap().iter().copied().collect::<Vec<_>>(), vec![[2u8; 32], [3u8; 32]]);
+
+        // Public routes never look at the token
+        assert_eq!(status(&state, "GET", "/api/health", None).await, StatusCode::OK);
+        assert_eq!(status(&state, "GET", "/api/tree/root", None).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_closed_without_configured_token() {
+        let state = state_with_token(None);
+
+        assert_eq!(status(&state, "POST", "/api/admin/roots/prune", None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&state, "POST", "/api/admin/roots/prune", Some("")).await, StatusCode::UNAUTHORIZED);
+    }
+
+    /// Reverse proxy the limited test states trust
+    const PROXY: &str = "10.0.0.1";
+
+    fn limited_state(deposit_burst: u32) -> AppState {
+        AppState::with_config(AppConfig {
+            deposit_rate_limit: RateLimitConfig { requests_per_second: 0.5, burst: deposit_burst },
+            trusted_proxies: vec![PROXY.parse().unwrap()],
+            ..Default::default()
+        }).unwrap()
+    }
+
+    /// Send a request through the trusted proxy on behalf of `client`
+    async fn send_from(state: &AppState, method: &str, uri: &str, client: &str) -> Response {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-forwarded-for", format!("198.51.100.1, {}", client))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        request.extensions_mut().insert(ConnectInfo(SocketAddr::new(PROXY.parse().unwrap(), 443)));
+        router_with_state(state.clone()).oneshot(request).await.unwrap()
+    }
+
+    #[test]
+    fn test_token_bucket_refills_at_configured_rate() {
+        let limiter = RateLimiter::new(RateLimitConfig { requests_per_second: 2.0, burst: 3 }, Vec::new());
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(client, start).is_ok());
+        }
+        assert_eq!(limiter.check_at(client, start), Err(Duration::from_millis(500)));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at("203.0.113.8".parse().unwrap(), start).is_ok());
+
+        // Half a second buys exactly one more request
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at(client, later).is_ok());
+        assert!(limiter.check_at(client, later).is_err());
+
+        // Idle time never banks more than the burst
+        let much_later = later + Duration::from_secs(60);
+        for _ in 0..3 {
+            assert!(limiter.check_at(client, much_later).is_ok());
+        }
+        assert!(limiter.check_at(client, much_later).is_err());
+    }
+
+    #[test]
+    fn test_tracked_clients_are_capped_oldest_first() {
+        let mut limiter = RateLimiter::new(RateLimitConfig { requests_per_second: 0.0, burst: 1 }, Vec::new());
+        limiter.max_clients = 2;
+        let [first, second, third]: [IpAddr; 3] = ["203.0.113.1", "203.0.113.2", "203.0.113.3"].map(|ip| ip.parse().unwrap());
+        let now = Instant::now();
+
+        assert!(limiter.check_at(first, now).is_ok());
+        assert!(limiter.check_at(second, now).is_ok());
+        assert!(limiter.check_at(second, now).is_err());
+
+        // A new client evicts the longest-tracked one, never growing the map
+        assert!(limiter.check_at(third, now).is_ok());
+        let buckets = limiter.buckets.lock().unwrap();
+        assert_eq!(buckets.by_client.len(), 2);
+        assert!(!buckets.by_client.contains_key(&first));
+        assert_eq!(buckets.tracked_order, [second, third]);
+    }
+
+    #[test]
+    fn test_client_ip_trusts_forwarded_for_only_from_proxies() {
+        let proxy: IpAddr = PROXY.parse().unwrap();
+        let direct: IpAddr = "192.0.2.50".parse().unwrap();
+        let trusted = [proxy];
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers, Some(proxy), &trusted), proxy);
+        assert_eq!(client_ip(&headers, None, &trusted), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        headers.insert("x-forwarded-for", "1.1.1.1, 203.0.113.7".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(proxy), &trusted), "203.0.113.7".parse::<IpAddr>().unwrap());
+
+        // A client connecting directly cannot pick its own key
+        assert_eq!(client_ip(&headers, Some(direct), &trusted), direct);
+        assert_eq!(client_ip(&headers, Some(proxy), &[]), proxy);
+        assert_eq!(client_ip(&headers, None, &trusted), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+        headers.insert("x-forwarded-for", "not an ip".parse().unwrap());
+        assert_eq!(client_ip(&headers, Some(proxy), &trusted), proxy);
+    }
+
+    #[tokio::test]
+    async fn test_deposit_burst_beyond_limit_gets_429() {
+        let burst = 3;
+        let state = limited_state(burst);
+
+        // The empty body is refused by the handler, but only after the limiter let it through
+        for _ in 0..burst {
+            let response = send_from(&state, "POST", "/api/deposit", "203.0.113.7").await;
+            assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+        let response = send_from(&state, "POST", "/api/deposit", "203.0.113.7").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "2");
+
+        // Another client, and the looser read limit, are unaffected
+        let response = send_from(&state, "POST", "/api/deposit", "203.0.113.8").await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = send_from(&state, "GET", "/api/tree/root", "203.0.113.7").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_read_burst_beyond_limit_gets_429() {
+        let state = AppState::with_config(AppConfig {
+            read_rate_limit: RateLimitConfig { requests_per_second: 1.0, burst: 2 },
+            trusted_proxies: vec![PROXY.parse().unwrap()],
+            ..Default::default()
+        }).unwrap();
+
+        for _ in 0..2 {
+            let response = send_from(&state, "GET", "/api/tree/stats", "203.0.113.9").await;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        // Read routes share one bucket per client
+        let response = send_from(&state, "GET", "/api/tree/root", "203.0.113.9").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Health probes are never limited
+        let response = send_from(&state, "GET", "/api/health", "203.0.113.9").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
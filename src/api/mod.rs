@@ -7,9 +7,13 @@ pub mod handlers;
 pub mod types;
 pub mod server;
 pub mod middleware;
+pub mod openapi;
+pub mod chain_query;
 
 // Re-export main types
 pub use handlers::*;
 pub use types::*;
 pub use server::ApiServer;
-pub use middleware::*;
\ No newline at end of file
+pub use middleware::*;
+pub use openapi::ApiDoc;
+pub use chain_query::{ChainQuery, ChainReceipt, ChainTransaction};
\ No newline at end of file
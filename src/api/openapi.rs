@@ -0,0 +1,111 @@
+//! OpenAPI Specification
+//!
+//! Machine-readable description of the REST API, generated from the handler
+//! annotations and the request/response types in `types.rs`.
+
+use axum::response::Json;
+use utoipa::OpenApi;
+
+use crate::api::handlers;
+use crate::api::types::*;
+
+/// OpenAPI document covering every registered endpoint
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Privacy Pool ZKVM API",
+        description = "Deposit, balance, UTXO and Merkle tree endpoints of the privacy pool operator"
+    ),
+    paths(
+        handlers::health_check,
+        handlers::liveness,
+        handlers::readiness,
+        handlers::process_deposit,
+        handlers::process_withdraw,
+        handlers::process_batch_withdraw,
+        handlers::process_transfer,
+        handlers::get_balance,
+        handlers::get_spendable_balance,
+        handlers::get_owner_utxos,
+        handlers::get_utxo_details,
+        handlers::get_owner_notes,
+        handlers::get_tree_stats,
+        handlers::get_tree_root,
+        handlers::prune_recent_roots,
+        handlers::get_address_policy,
+        handlers::update_address_policy,
+        handlers::get_utxo_set_root,
+        handlers::get_operator_pubkey,
+        handlers::verify_commitment_opening,
+        handlers::get_commitment_status,
+        handlers::verify_merkle_proofs,
+        handlers::get_utxo_proof,
+        handlers::subscribe_events,
+        handlers::subscribe_tree_feed,
+        openapi_json,
+    ),
+    components(schemas(
+        DepositRequest,
+        EncryptedNotePayload,
+        DepositResponse,
+        SpendAuthorization,
+        WithdrawRequest,
+        BatchWithdrawRequest,
+        BatchWithdrawResponse,
+        WithdrawResponse,
+        TransferInput,
+        TransferOutput,
+        TransferRequest,
+        TransferResponse,
+        AmountFormat,
+        UTXOSort,
+        UTXOInfo,
+        UTXOListResponse,
+        EncryptedNoteInfo,
+        NotesResponse,
+        BalanceInfo,
+        SpendableBalanceInfo,
+        TreeStatsResponse,
+        OperatorPubkeyResponse,
+        CommitmentOpeningRequest,
+        CommitmentOpeningResponse,
+        CommitmentStatusRequest,
+        CommitmentStatus,
+        CommitmentStatusResponse,
+        MerkleProofData,
+        ProofVerifyItem,
+        BatchProofVerifyRequest,
+        BatchProofVerifyResponse,
+        UTXOProofResponse,
+        PoolEventType,
+        PoolEvent,
+        TreeFeedMessage,
+        SubscribeRequest,
+        SubscribeResponse,
+        HealthResponse,
+        ReadinessResponse,
+        AddressPolicy,
+        ErrorResponse,
+    )),
+    tags(
+        (name = "system", description = "Health and probes"),
+        (name = "deposits", description = "Blockchain-verified deposits"),
+        (name = "withdrawals", description = "Nullifier-checked withdrawals"),
+        (name = "balances", description = "Owner balances"),
+        (name = "utxos", description = "UTXO and encrypted note queries"),
+        (name = "tree", description = "Merkle tree state and inclusion proofs"),
+        (name = "commitments", description = "Commitment opening and status checks"),
+        (name = "events", description = "Live pool events"),
+        (name = "admin", description = "Maintenance endpoints behind the admin bearer token"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Serve the generated OpenAPI document
+#[utoipa::path(
+    get, path = "/api/openapi.json", tag = "system",
+    responses((status = 200, description = "OpenAPI 3 document", body = Object))
+)]
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -10,6 +10,8 @@ use tower_http::cors::CorsLayer;
 use anyhow::Result;
 
 use crate::api::{handlers, middleware};
+use crate::api::handlers::{AppConfig, AppState};
+use crate::relayer::DepositManager;
 
 /// API Server configuration
 #[derive(Debug, Clone)]
@@ -22,6 +24,8 @@ pub struct ServerConfig {
     pub request_timeout: u64,
     /// Enable request logging
     pub enable_logging: bool,
+    /// Seconds between deposit watcher polls
+    pub deposit_poll_interval: u64,
 }
 
 impl Default for ServerConfig {
@@ -31,6 +35,7 @@ impl Default for ServerConfig {
             max_request_size: 1024 * 1024, // 1MB
             request_timeout: 30,
             enable_logging: true,
+            deposit_poll_interval: 12,
         }
     }
 }
@@ -38,16 +43,23 @@ impl Default for ServerConfig {
 /// Main API Server
 pub struct ApiServer {
     config: ServerConfig,
+    state: AppState,
     router: Router,
 }
 
 impl ApiServer {
     /// Create new API server with configuration
     pub fn new(config: ServerConfig) -> Result<Self> {
-        let router = Self::create_router(&config)?;
+        Self::with_state(config, AppState::new()?)
+    }
+    
+    /// Create API server over existing application state
+    pub fn with_state(config: ServerConfig, state: AppState) -> Result<Self> {
+        let router = Self::create_router(&config, state.clone())?;
         
         Ok(Self {
             config,
+            state,
             router,
         })
     }
@@ -58,9 +70,9 @@ impl ApiServer {
     }
     
     /// Create the main router with all middleware and routes
-    fn create_router(config: &ServerConfig) -> Result<Router> {
+    fn create_router(config: &ServerConfig, state: AppState) -> Result<Router> {
         // Create base router with handlers
-        let app_router = handlers::create_router()?;
+        let app_router = handlers::router_with_state(state);
         
         // Build middleware stack
         let service_builder = ServiceBuilder::new();
@@ -83,6 +95,9 @@ impl ApiServer {
     }
     
     /// Start the server
+    ///
+    /// With a tree database configured, the deposit watcher runs alongside
+    /// and feeds `/ready`; it is stopped once the server shuts down.
     pub async fn start(self) -> Result<()> {
         println!(" Privacy Pool API Server starting...");
         println!(" Listening on: http://{}", self.config.bind_addr);
@@ -93,21 +108,53 @@ impl ApiServer {
         println!();
         println!(" Available endpoints:");
         println!("   GET  /api/health          - Health check");
+        println!("   GET  /live                - Liveness probe");
+        println!("   GET  /ready               - Readiness probe (deposit sync lag)");
+        println!("   GET  /api/openapi.json    - OpenAPI specification");
         println!("   POST /api/deposit         - Process ETH deposit");
+        println!("   POST /api/withdraw        - Withdraw a UTXO");
+        println!("   POST /api/withdraw/batch  - Atomic batch withdrawal");
+        println!("   POST /api/transfer        - Transfer UTXOs to new outputs");
         println!("   GET  /api/balance/:owner  - Get owner balance");
+        println!("   GET  /api/balance/:owner/spendable - Get spendable balance");
         println!("   GET  /api/utxos/:owner    - Get owner UTXOs");
         println!("   GET  /api/utxo/:utxo_id   - Get UTXO details");
+        println!("   GET  /api/notes/:owner    - Get owner encrypted notes");
         println!("   GET  /api/tree/stats      - Get tree statistics");
         println!("   GET  /api/tree/root       - Get current tree root");
+        println!("   GET  /api/tree/utxo-set-root - Get flat Merkle root over the UTXO set");
+        println!("   GET  /api/operator/pubkey - Get operator root-signing key");
+        println!("   POST /api/proofs/verify   - Batch Merkle proof verification");
+        println!("   GET  /api/proof/:utxo_id  - Get inclusion proof of a UTXO");
+        println!("   GET  /api/ws/events       - WebSocket pool events (per-owner filter)");
+        println!("   GET  /api/ws              - WebSocket root updates and deposits");
+        println!("   POST /api/admin/roots/prune - Prune remembered roots (admin token)");
+        println!("   GET|PUT /api/admin/address-policy - Depositor allow/deny lists (admin token)");
         println!();
         
+        // The deposit watcher feeds /ready while the server runs
+        let (watcher_shutdown, shutdown_rx) = tokio::sync::watch::channel(false);
+        let watcher = match &self.state.config.tree_db_path {
+            Some(_) => Some(self.state.spawn_deposit_watcher(
+                DepositManager::new()?,
+                std::time::Duration::from_secs(self.config.deposit_poll_interval),
+                shutdown_rx,
+            )?),
+            None => None,
+        };
+        
         // Create TCP listener
         let listener = TcpListener::bind(&self.config.bind_addr).await?;
         
-        // Start server with graceful shutdown
-        serve(listener, self.router)
+        // Start server with graceful shutdown; peer addresses key the rate limiters
+        serve(listener, self.router.into_make_service_with_connect_info::<SocketAddr>())
             .with_graceful_shutdown(Self::shutdown_signal())
             .await?;
+        
+        let _ = watcher_shutdown.send(true);
+        if let Some(watcher) = watcher {
+            watcher.await??;
+        }
             
         println!(" Server stopped gracefully");
         Ok(())
@@ -131,6 +178,7 @@ impl ApiServer {
 /// Builder for API server configuration
 pub struct ApiServerBuilder {
     config: ServerConfig,
+    app_config: AppConfig,
 }
 
 impl ApiServerBuilder {
@@ -138,6 +186,7 @@ impl ApiServerBuilder {
     pub fn new() -> Self {
         Self {
             config: ServerConfig::default(),
+            app_config: AppConfig::default(),
         }
     }
     
@@ -171,9 +220,21 @@ impl ApiServerBuilder {
         self
     }
     
+    /// Set the deposit watcher poll interval
+    pub fn deposit_poll_interval(mut self, interval_secs: u64) -> Self {
+        self.config.deposit_poll_interval = interval_secs;
+        self
+    }
+    
+    /// Set the bearer token required by `/api/admin/*`; empty tokens leave admin routes closed
+    pub fn admin_token(mut self, token: Option<String>) -> Self {
+        self.app_config.admin_token = token.filter(|token| !token.is_empty());
+        self
+    }
+    
     /// Build the API server
     pub fn build(self) -> Result<ApiServer> {
-        ApiServer::new(self.config)
+        ApiServer::with_state(self.config, AppState::with_config(self.app_config)?)
     }
 }
 
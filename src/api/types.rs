@@ -3,29 +3,53 @@
 //! Defines all HTTP request/response structures for the privacy pool API.
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use utoipa::{IntoParams, ToSchema};
 use web3::types::{Address, H256, U256};
 
 /// Request to process an ETH deposit
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepositRequest {
     /// Depositor's Ethereum address
+    #[schema(value_type = String)]
     pub depositor: Address,
-    /// Privacy commitment hash
+    /// Spend commitment of the new note, `SHA256(DOMAIN_SPEND_V1 || ak || nk || secret)`;
+    /// spending the UTXO later requires opening it
+    #[schema(value_type = String)]
     pub commitment: H256,
     /// Deposit amount in wei
+    #[schema(value_type = String)]
     pub amount: U256,
     /// Block number where deposit occurred
     pub block_number: u64,
     /// Transaction hash
+    #[schema(value_type = String)]
     pub tx_hash: H256,
     /// Additional label/metadata
+    #[schema(value_type = Option<String>)]
     pub label: Option<U256>,
     /// Precommitment hash (if any)
+    #[schema(value_type = Option<String>)]
     pub precommitment_hash: Option<H256>,
+    /// Encrypted note for the created UTXO (if any)
+    pub encrypted_note: Option<EncryptedNotePayload>,
+    /// Lock script for the created UTXO (hex encoded, if any)
+    pub lock_data: Option<String>,
+}
+
+/// Encrypted note ciphertext as uploaded by the depositor
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EncryptedNotePayload {
+    /// Ephemeral public key (hex encoded)
+    pub ephemeral_pubkey: String,
+    /// Encryption nonce (hex encoded)
+    pub nonce: String,
+    /// Note ciphertext (hex encoded)
+    pub ciphertext: String,
 }
 
 /// Response after processing a deposit
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepositResponse {
     /// Success status
     pub success: bool,
@@ -44,7 +68,8 @@ pub struct DepositResponse {
 }
 
 /// Request for owner's UTXOs
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UTXOQuery {
     /// Maximum number of UTXOs to return
     pub limit: Option<usize>,
@@ -52,15 +77,55 @@ pub struct UTXOQuery {
     pub after_block: Option<u64>,
     /// Filter by specific asset ID (hex encoded)
     pub asset_id: Option<String>,
+    /// Amount rendering (raw wei by default)
+    pub format: Option<AmountFormat>,
+    /// Result ordering (`created_asc` by default)
+    pub sort: Option<UTXOSort>,
+}
+
+/// Ordering of `/api/utxos/{owner}` results
+///
+/// The order is part of the API contract: it is stable across restarts and
+/// independent of insertion order, so clients can diff successive responses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UTXOSort {
+    /// `(created_block, utxo_id)` ascending
+    #[default]
+    CreatedAsc,
+    /// Amount descending, ties broken by `(created_block, utxo_id)` ascending
+    AmountDesc,
+}
+
+/// How amounts are rendered in responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AmountFormat {
+    /// Raw integer string in the asset's smallest unit only
+    #[default]
+    Raw,
+    /// Raw string plus a decimal string scaled by the asset's decimals
+    Decimal,
+}
+
+/// Query parameters selecting the amount format
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// Amount rendering (raw wei by default)
+    pub format: Option<AmountFormat>,
 }
 
 /// UTXO information for API responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UTXOInfo {
     /// UTXO ID (hex encoded)
     pub utxo_id: String,
     /// Amount in smallest unit
     pub amount: String,
+    /// Amount scaled by the asset's decimals (with `format=decimal`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount_decimal: Option<String>,
     /// Asset ID (hex encoded)
     pub asset_id: String,
     /// Block when UTXO was created
@@ -76,7 +141,7 @@ pub struct UTXOInfo {
 }
 
 /// Response for UTXO queries
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UTXOListResponse {
     /// List of UTXOs
     pub utxos: Vec<UTXOInfo>,
@@ -86,11 +151,44 @@ pub struct UTXOListResponse {
     pub next_cursor: Option<String>,
 }
 
+/// Query parameters for note downloads
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NotesQuery {
+    /// Skip notes for UTXOs created before this block
+    pub from_block: Option<u64>,
+    /// Maximum number of notes to return (whole blocks are never split)
+    pub limit: Option<usize>,
+}
+
+/// Encrypted note entry for wallet backup
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EncryptedNoteInfo {
+    /// UTXO ID the note belongs to (hex encoded)
+    pub utxo_id: String,
+    /// Block when the UTXO was created
+    pub created_block: u64,
+    /// Encrypted note ciphertext
+    pub note: EncryptedNotePayload,
+}
+
+/// Response for note downloads
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotesResponse {
+    /// Encrypted notes ordered by creation block
+    pub notes: Vec<EncryptedNoteInfo>,
+    /// Block to resume from on the next page (if more notes remain)
+    pub next_from_block: Option<u64>,
+}
+
 /// Balance information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct BalanceInfo {
     /// Total balance as string (to handle large numbers)
     pub balance: String,
+    /// Balance scaled by the asset's decimals (with `format=decimal`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance_decimal: Option<String>,
     /// Number of UTXOs
     pub utxo_count: u32,
     /// Last updated block
@@ -99,8 +197,170 @@ pub struct BalanceInfo {
     pub asset_id: String,
 }
 
+/// Query parameters for spendable balance lookups
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SpendableBalanceQuery {
+    /// Block height used to evaluate timelocks (defaults to the latest
+    /// chain head seen by the deposit watcher; required without one)
+    pub current_block: Option<u64>,
+    /// Amount rendering (raw wei by default)
+    pub format: Option<AmountFormat>,
+}
+
+/// Balance split into total and currently spendable amounts
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SpendableBalanceInfo {
+    /// Total balance including locked UTXOs
+    pub total: String,
+    /// Balance of UTXOs spendable at `current_block`
+    pub spendable: String,
+    /// `total` scaled by the asset's decimals (with `format=decimal`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_decimal: Option<String>,
+    /// `spendable` scaled by the asset's decimals (with `format=decimal`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spendable_decimal: Option<String>,
+    /// Number of UTXOs counted in `total`
+    pub utxo_count: u32,
+    /// Number of UTXOs counted in `spendable`
+    pub spendable_utxo_count: u32,
+    /// Block height the spendability was evaluated at
+    pub current_block: u64,
+    /// Asset ID (hex encoded)
+    pub asset_id: String,
+}
+
+/// Owner authorization for spending one UTXO
+///
+/// `spend_auth_key`, `nullifier_key` and `note_secret` must open the spend
+/// commitment registered for the UTXO, the nullifier must be derived from
+/// `nullifier_key`, and `signature` must be made with the spending key.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SpendAuthorization {
+    /// RedJubjub spend authorization key (hex encoded)
+    pub spend_auth_key: String,
+    /// Nullifier key derived from the spending key (hex encoded)
+    pub nullifier_key: String,
+    /// Note secret chosen when the UTXO was created (hex encoded)
+    pub note_secret: String,
+    /// RedJubjub signature over the spend message (hex encoded, R || s)
+    pub signature: String,
+}
+
+/// Single withdrawal, on its own or inside a batch
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WithdrawRequest {
+    /// UTXO being spent (hex encoded)
+    pub utxo_id: String,
+    /// Nullifier revealed by the spend proof (hex encoded)
+    pub nullifier: String,
+    /// Tree root the spend proof was generated against (hex encoded)
+    pub merkle_root: String,
+    /// Inclusion path of the UTXO leaf under `merkle_root`, leaf to root (hex encoded)
+    pub siblings: Vec<String>,
+    /// Recipient of the withdrawn funds, covered by the signature
+    #[schema(value_type = String)]
+    pub recipient: Address,
+    /// Proof that the caller owns the UTXO
+    pub authorization: SpendAuthorization,
+}
+
+/// Withdrawals a relayer submits together in one transaction
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchWithdrawRequest {
+    /// Withdrawals applied all-or-nothing
+    pub withdrawals: Vec<WithdrawRequest>,
+}
+
+/// Result of an applied withdrawal batch
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchWithdrawResponse {
+    /// Success status
+    pub success: bool,
+    /// Spent nullifiers in request order (hex encoded)
+    pub nullifiers: Vec<String>,
+    /// Recipients paid, in request order
+    pub recipients: Vec<String>,
+    /// Sum of withdrawn amounts in smallest unit
+    pub total_amount: String,
+    /// New tree root (hex encoded)
+    pub new_root: String,
+    /// Root version after the batch
+    pub root_version: u64,
+}
+
+/// Result of an applied single withdrawal
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WithdrawResponse {
+    /// Success status
+    pub success: bool,
+    /// Spent nullifier (hex encoded)
+    pub nullifier: String,
+    /// Recipient paid
+    pub recipient: String,
+    /// Withdrawn amount in smallest unit
+    pub amount: String,
+    /// New tree root (hex encoded)
+    pub new_root: String,
+    /// Root version after the withdrawal
+    pub root_version: u64,
+    /// Processing timestamp
+    pub processed_at: u64,
+}
+
+/// Input spent by a transfer
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferInput {
+    /// UTXO being spent (hex encoded)
+    pub utxo_id: String,
+    /// Nullifier revealed by the spend proof (hex encoded)
+    pub nullifier: String,
+    /// Tree root the spend proof was generated against (hex encoded)
+    pub merkle_root: String,
+    /// Inclusion path of the UTXO leaf under `merkle_root`, leaf to root (hex encoded)
+    pub siblings: Vec<String>,
+    /// Proof that the caller owns the UTXO; signs the whole transfer
+    pub authorization: SpendAuthorization,
+}
+
+/// Output created by a transfer
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferOutput {
+    /// Spend commitment of the new output's owner (hex encoded)
+    pub commitment: String,
+    /// Owner commitment of the new UTXO (hex encoded)
+    pub owner_commitment: String,
+    /// Amount in smallest unit
+    pub amount: String,
+}
+
+/// Private transfer: spend inputs and create outputs of equal total value
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferRequest {
+    pub inputs: Vec<TransferInput>,
+    pub outputs: Vec<TransferOutput>,
+}
+
+/// Result of an applied transfer
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferResponse {
+    /// Success status
+    pub success: bool,
+    /// Spent nullifiers in input order (hex encoded)
+    pub nullifiers: Vec<String>,
+    /// Created UTXO IDs in output order (hex encoded)
+    pub utxo_ids: Vec<String>,
+    /// New tree root (hex encoded)
+    pub new_root: String,
+    /// Root version after the transfer
+    pub root_version: u64,
+    /// Processing timestamp
+    pub processed_at: u64,
+}
+
 /// Tree statistics for monitoring
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct TreeStatsResponse {
     /// Current tree root (hex encoded)
     pub current_root: String,
@@ -112,12 +372,181 @@ pub struct TreeStatsResponse {
     pub total_utxos: u64,
     /// Total number of tree nodes
     pub total_nodes: u64,
+    /// Total number of UTXOs spent
+    pub total_spent: u64,
+    /// Total deposited value in wei
+    pub total_deposited_wei: String,
     /// Tree salt for reproducibility
     pub tree_salt: u64,
+    /// Randomness beacon seed behind UTXO ID entropy (hex encoded)
+    pub beacon_seed: String,
+}
+
+/// Operator public key for verifying root signatures
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct OperatorPubkeyResponse {
+    /// Signature algorithm
+    #[schema(value_type = String)]
+    pub algorithm: crate::crypto::SignatureAlgorithm,
+    /// Encoded public key (hex)
+    pub public_key: String,
+}
+
+/// Claimed opening of a UTXO commitment
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentOpeningRequest {
+    /// UTXO commitment (hex encoded)
+    pub commitment: String,
+    /// Committed value in smallest unit
+    pub value: String,
+    /// Blinding factor (hex encoded)
+    pub blinding: String,
+    /// Owner commitment (hex encoded)
+    pub owner: String,
+}
+
+/// Result of checking a commitment opening
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentOpeningResponse {
+    /// Whether the opening matches the commitment
+    pub valid: bool,
+}
+
+/// Request for the status of remembered deposit commitments
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentStatusRequest {
+    /// Deposit commitments (hex encoded)
+    pub commitments: Vec<String>,
+}
+
+/// Status of one deposit commitment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentStatus {
+    /// Deposit commitment (hex encoded)
+    pub commitment: String,
+    /// Whether a deposit with this commitment was ever recorded
+    pub exists: bool,
+    /// Whether that deposit's UTXO has been withdrawn
+    pub is_spent: bool,
+}
+
+/// Statuses in request order
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentStatusResponse {
+    pub statuses: Vec<CommitmentStatus>,
+}
+
+/// Merkle authentication path from a leaf to a root
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MerkleProofData {
+    /// Sibling hashes from the leaf up (hex encoded), one per tree level
+    pub siblings: Vec<String>,
+    /// Path bits from the leaf up: 1 if the node is a right child, else 0
+    pub path: Vec<u32>,
+    /// Root the proof claims (hex encoded)
+    pub root: String,
+    /// Leaf index in the tree
+    pub leaf_index: u64,
+}
+
+/// One leaf and the proof of its inclusion
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProofVerifyItem {
+    /// Leaf hash (hex encoded)
+    pub leaf: String,
+    pub proof: MerkleProofData,
+}
+
+/// Request to verify many Merkle proofs at once
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchProofVerifyRequest {
+    pub proofs: Vec<ProofVerifyItem>,
+}
+
+/// Per-proof results in request order
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchProofVerifyResponse {
+    pub results: Vec<bool>,
+    /// Whether every proof verified
+    pub all_valid: bool,
+}
+
+/// Inclusion proof of a UTXO leaf in the UTXO tree
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UTXOProofResponse {
+    pub utxo_id: String,
+    /// Leaf hash of the UTXO (hex encoded)
+    pub leaf: String,
+    /// Sibling hashes from the leaf up (hex encoded)
+    pub siblings: Vec<String>,
+    /// Path bits from the leaf up: 1 if the node is a right child, else 0
+    pub indices: Vec<u32>,
+    pub root: String,
+    pub leaf_index: u64,
+}
+
+/// Kind of pool event pushed to WebSocket subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PoolEventType {
+    Deposit,
+    Withdrawal,
+    /// UTXO spent or created by a private transfer
+    Transfer,
+}
+
+/// Pool event pushed to WebSocket subscribers
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PoolEvent {
+    /// Event kind
+    pub event_type: PoolEventType,
+    /// Owner commitment the event belongs to (hex encoded)
+    pub owner_commitment: String,
+    /// Affected UTXO ID (hex encoded)
+    pub utxo_id: String,
+    /// Amount in smallest unit
+    pub amount: String,
+    /// Tree root version after the event
+    pub root_version: u64,
+}
+
+/// Message pushed to every `/api/ws` client, tagged by `type`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TreeFeedMessage {
+    /// The tree version advanced
+    RootUpdate {
+        /// New tree root (hex encoded)
+        root: String,
+        root_version: u64,
+        /// UTXO whose deposit or spend advanced the tree (hex encoded)
+        utxo_id: String,
+    },
+    /// A deposit was processed
+    Deposit {
+        utxo_id: String,
+        /// Amount in smallest unit
+        amount: String,
+        root_version: u64,
+    },
+}
+
+/// WebSocket message selecting which owners' events to receive
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubscribeRequest {
+    /// Owner commitments (hex encoded); replaces any previous filter
+    pub owners: Vec<String>,
+}
+
+/// Acknowledgement sent after a subscription update
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubscribeResponse {
+    /// Number of owners in the active filter
+    pub subscribed: usize,
 }
 
 /// System health status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HealthResponse {
     /// System status
     pub status: String,
@@ -131,14 +560,53 @@ pub struct HealthResponse {
     pub tree_status: String,
 }
 
+/// Readiness status (deposit sync lag and store availability)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// Whether the service should receive traffic
+    pub ready: bool,
+    /// Last block processed by the deposit watcher
+    pub checkpoint_block: u64,
+    /// Latest chain head seen by the deposit watcher
+    pub chain_head: u64,
+    /// Blocks the watcher is behind (absent before the first poll)
+    pub lag: Option<u64>,
+    /// Maximum lag tolerated before reporting not ready
+    pub max_lag: u64,
+    /// Database status
+    pub database_status: String,
+}
+
+/// Depositor addresses permitted to enter the pool
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AddressPolicy {
+    /// When set, only these depositors may deposit
+    #[schema(value_type = Option<Vec<String>>)]
+    #[serde(default)]
+    pub allowlist: Option<HashSet<Address>>,
+    /// Depositors that may never deposit, even when allowlisted
+    #[schema(value_type = Vec<String>)]
+    #[serde(default)]
+    pub denylist: HashSet<Address>,
+}
+
+impl AddressPolicy {
+    /// Whether `depositor` may deposit under this policy
+    pub fn permits(&self, depositor: &Address) -> bool {
+        !self.denylist.contains(depositor)
+            && self.allowlist.as_ref().is_none_or(|allowlist| allowlist.contains(depositor))
+    }
+}
+
 /// Error response format
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ErrorResponse {
     /// Error code
     pub error: String,
     /// Human readable message
     pub message: String,
     /// Additional details (optional)
+    #[schema(value_type = Option<Object>)]
     pub details: Option<serde_json::Value>,
     /// Timestamp
     pub timestamp: u64,
@@ -166,4 +634,20 @@ pub mod utils {
     pub fn hash_to_hex(hash: [u8; 32]) -> String {
         format!("0x{}", hex::encode(hash))
     }
+    
+    /// Render an integer amount as a fixed-point decimal string
+    /// 
+    /// Uses exactly `decimals` fractional digits so no precision is lost,
+    /// e.g. 1.5 ETH (18 decimals) becomes "1.500000000000000000".
+    pub fn format_decimal_amount(amount: u128, decimals: u8) -> String {
+        let digits = amount.to_string();
+        let decimals = decimals as usize;
+        if decimals == 0 {
+            return digits;
+        }
+        
+        let padded = format!("{:0>width$}", digits, width = decimals + 1);
+        let (whole, fraction) = padded.split_at(padded.len() - decimals);
+        format!("{}.{}", whole, fraction)
+    }
 }
\ No newline at end of file
@@ -31,6 +31,8 @@ async fn main() -> Result<()> {
         .parse()
         .unwrap_or(true);
     
+    let admin_token = env::var("ADMIN_TOKEN").ok();
+    
     println!(" Privacy Pool ZKVM API Server");
     println!("===============================");
     println!();
@@ -41,6 +43,7 @@ async fn main() -> Result<()> {
         .max_request_size(max_request_size)
         .request_timeout(request_timeout)
         .logging(enable_logging)
+        .admin_token(admin_token)
         .build()?;
     
     // Start the server
@@ -1,6 +1,10 @@
 // Privacy pool transaction processor
 // This is a simplified version for demonstration
 
+use privacy_pool_zkvm::canonical_spec::{self, tree_config};
+use privacy_pool_zkvm::merkle::NullifierProof;
+use privacy_pool_zkvm::utils::zisk_precompiles::{deserialize_zisk_input, zisk_pedersen_commitment};
+
 // Simple privacy pool transaction that works with ZisK
 #[derive(serde::Serialize, serde::Deserialize)]
 struct PrivacyPoolTransaction {
@@ -12,16 +16,36 @@ struct PrivacyPoolTransaction {
     nullifiers: [[u8; 32]; 4],         // Max 4 nullifiers
     // Merkle proofs for input commitments - simplified
     merkle_roots: [[u8; 32]; 4],       // Max 4 merkle roots
-    // Values for each commitment
+    // Values for each output commitment
     values: [u64; 4],                  // Max 4 values
-    // Blinding factors for commitments
+    // Blinding factors for output commitments
     blinding_factors: [[u8; 32]; 4],   // Max 4 blinding factors
-    // Signature over the transaction (simplified)
-    signature: Vec<u8>,                // Variable size signature
+    // Openings of the input commitments
+    input_values: [u64; 4],
+    input_blinding_factors: [[u8; 32]; 4],
+    // Private witness for each input's nullifier: UTXO id, note secret and
+    // the owner's spending key
+    input_utxo_ids: [[u8; 32]; 4],
+    // Owner commitment stored with each input UTXO; must be the signer's
+    // `generate_key_owner_commitment`
+    input_owner_commitments: [[u8; 32]; 4],
+    input_note_secrets: [[u8; 32]; 4],
+    spending_keys: [[u8; 32]; 4],
+    // Non-membership proof for each input's nullifier, against the nullifier
+    // root left by the inputs before it
+    #[serde(default)]
+    nullifier_proofs: Vec<NullifierProof>,
+    // Signature over `create_transaction_message`
+    signature: Vec<u8>,                // 64-byte Ed25519 or compact ECDSA signature
     // Public key of the signer
-    public_key: [u8; 32],              // Fixed size public key
+    public_key: Vec<u8>,               // 32-byte Ed25519 or 33-byte compressed secp256k1 key
+    // Signature scheme tag: 1=Ed25519, 2=ECDSA over secp256k1
+    sig_scheme: u8,
     // Transaction fee
     fee: u64,
+    // Public value entering the pool (deposits) and leaving it (withdrawals)
+    value_in: u64,
+    value_out: u64,
     // Transaction type: 0=deposit, 1=withdrawal, 2=transfer
     tx_type: u8,
     // Sender and recipient addresses
@@ -42,64 +66,189 @@ struct PrivacyPoolState {
     block_height: u32,
     // Number of nullifiers used
     nullifier_count: u32,
-    // Nullifier set (simplified - just count for now)
-    nullifier_set_size: u32,
+    // Root of the spent-nullifier sparse Merkle tree (`NullifierTree` layout)
+    nullifier_root: [u8; 32],
+    // Salt the nullifier tree derives leaf positions with
+    nullifier_tree_salt: u64,
 }
 
+// Capacity of the fixed-size input/output arrays
+const MAX_IO_COUNT: u8 = 4;
+
 fn main() {
     // Read transaction and current state
     let input: Vec<u8> = vec![]; // Simplified for demonstration
-    let (transaction, old_state): (PrivacyPoolTransaction, PrivacyPoolState) = 
-        serde_json::from_slice(&input).expect("Failed to deserialize input");
-    
-    // 1. Verify Merkle proofs for all input commitments
-    let mut merkle_valid = true;
-    for i in 0..transaction.input_count as usize {
-        if !verify_merkle_proof_simple(
-            transaction.input_commitments[i],
-            transaction.merkle_roots[i],
-            old_state.merkle_root,
-        ) {
-            merkle_valid = false;
-            break;
+    let (transaction, old_state): (PrivacyPoolTransaction, PrivacyPoolState) = match deserialize_zisk_input(&input) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            // Malformed input - report it instead of aborting
+            println!("Failed to deserialize input: {}", e);
+            return;
         }
-    }
+    };
     
-    // 2. Check nullifiers haven't been used before (simplified check)
-    let mut no_double_spend = true;
-    for i in 0..transaction.input_count as usize {
-        if transaction.nullifiers[i] == [0u8; 32] {
-            continue; // Skip empty nullifiers
-        }
-        // Simple check: if nullifier is not all zeros and we've seen it before, it's double spend
-        // In a real implementation, this would check against a nullifier set
-        if old_state.nullifier_count > 0 && transaction.nullifiers[i] != [0u8; 32] {
-            // This is a simplified check - in reality you'd check against actual nullifier set
-            no_double_spend = true; // Simplified for now
-        }
+    process_transaction(&transaction, &old_state);
+}
+
+// Check that the declared counts fit the fixed-size arrays
+fn validate_counts(transaction: &PrivacyPoolTransaction) -> Result<(), String> {
+    if transaction.input_count > MAX_IO_COUNT {
+        return Err(format!("input_count {} exceeds {}", transaction.input_count, MAX_IO_COUNT));
+    }
+    if transaction.output_count > MAX_IO_COUNT {
+        return Err(format!("output_count {} exceeds {}", transaction.output_count, MAX_IO_COUNT));
+    }
+    Ok(())
+}
+
+// Input/output shape a transaction violates for its type
+#[derive(Debug, PartialEq, Eq)]
+enum StructureViolation {
+    UnknownTxType(u8),
+    DepositHasInputs(u8),
+    DepositWithoutOutputs,
+    PublicValueNotAllowed { value_in: u64, value_out: u64 },
+    WithdrawalWithoutInputs,
+    WithdrawalTooManyOutputs(u8),
+    TransferWithoutInputs,
+    TransferWithoutOutputs,
+}
+
+// Check the counts required by tx_type: deposits spend nothing, withdrawals
+// create at most one change output, transfers both spend and create. Only
+// deposits bring public value in and only withdrawals take it out
+fn validate_structure(transaction: &PrivacyPoolTransaction) -> Result<(), StructureViolation> {
+    let (inputs, outputs) = (transaction.input_count, transaction.output_count);
+    let (value_in, value_out) = (transaction.value_in, transaction.value_out);
+    let public_value_allowed = match transaction.tx_type {
+        0 => value_out == 0,
+        1 => value_in == 0,
+        _ => value_in == 0 && value_out == 0,
+    };
+    match transaction.tx_type {
+        0 if inputs > 0 => Err(StructureViolation::DepositHasInputs(inputs)),
+        0 if outputs == 0 => Err(StructureViolation::DepositWithoutOutputs),
+        1 if inputs == 0 => Err(StructureViolation::WithdrawalWithoutInputs),
+        1 if outputs > 1 => Err(StructureViolation::WithdrawalTooManyOutputs(outputs)),
+        2 if inputs == 0 => Err(StructureViolation::TransferWithoutInputs),
+        2 if outputs == 0 => Err(StructureViolation::TransferWithoutOutputs),
+        0..=2 if !public_value_allowed => Err(StructureViolation::PublicValueNotAllowed { value_in, value_out }),
+        0..=2 => Ok(()),
+        other => Err(StructureViolation::UnknownTxType(other)),
     }
+}
+
+// Result of each validity check, so a rejection can be traced to its cause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TransactionChecks {
+    merkle_valid: bool,
+    no_double_spend: bool,
+    nullifiers_derived: bool,
+    signature_valid: bool,
+    commitment_valid: bool,
+    balance_valid: bool,
+}
+
+impl TransactionChecks {
+    fn all_passed(&self) -> bool {
+        self.merkle_valid
+            && self.no_double_spend
+            && self.nullifiers_derived
+            && self.signature_valid
+            && self.commitment_valid
+            && self.balance_valid
+    }
+}
+
+// Run every validity check on a transaction whose counts and structure are valid
+fn transaction_checks(transaction: &PrivacyPoolTransaction, old_state: &PrivacyPoolState) -> TransactionChecks {
+    let input_count = transaction.input_count as usize;
+    let output_count = transaction.output_count as usize;
     
-    // 3. Verify signature over transaction (simplified)
-    let message = create_transaction_message(&transaction);
-    let signature_valid = verify_signature_simple(&message, &transaction.signature, &transaction.public_key);
+    // 1. Verify Merkle proofs for all input commitments
+    let merkle_valid = (0..input_count).all(|i| verify_merkle_proof_simple(
+        transaction.input_commitments[i],
+        transaction.merkle_roots[i],
+        old_state.merkle_root,
+    ));
     
-    // 4. Verify commitment balance (inputs >= outputs + fee)
-    let total_inputs = calculate_commitment_sum_simple(&transaction.input_commitments, transaction.input_count as usize);
-    let total_outputs = calculate_commitment_sum_simple(&transaction.output_commitments, transaction.output_count as usize);
-    let balance_valid = total_inputs >= total_outputs + transaction.fee;
+    // 2. Check nullifiers are unique within the transaction and proven absent from the nullifier tree
+    let no_double_spend = find_duplicate_nullifier(transaction).is_none()
+        && apply_nullifier_proofs(transaction, old_state).is_ok();
     
-    // 5. Verify commitments are valid (simplified)
-    let mut commitment_valid = true;
-    for i in 0..transaction.output_count as usize {
-        if !verify_commitment_simple(
-            transaction.output_commitments[i],
-            transaction.values[i],
-            transaction.blinding_factors[i],
-        ) {
-            commitment_valid = false;
-            break;
-        }
+    // 2b. Recompute every nullifier from the owner's spending key
+    let nullifiers_derived = find_invalid_nullifier(transaction).is_none();
+    
+    // 3. Verify signature over transaction by the key that owns every input
+    let message = create_transaction_message(transaction);
+    let signature_valid = verify_signature(transaction.sig_scheme, &message, &transaction.signature, &transaction.public_key)
+        && find_unowned_input(transaction).is_none();
+    
+    // 4. Verify every commitment opens to its declared value and blinding factor
+    let commitment_valid = (0..input_count).all(|i| verify_commitment_opening(
+        transaction.input_commitments[i],
+        transaction.input_values[i],
+        transaction.input_blinding_factors[i],
+    )) && (0..output_count).all(|i| verify_commitment_opening(
+        transaction.output_commitments[i],
+        transaction.values[i],
+        transaction.blinding_factors[i],
+    ));
+    
+    // 5. Verify value conservation over the committed and public values
+    // (inputs + value_in == outputs + fee + value_out), and that the pool
+    // holds what leaves it
+    let total_in = sum_committed_values(&transaction.input_values, input_count)
+        .and_then(|total| total.checked_add(transaction.value_in));
+    let total_out = sum_committed_values(&transaction.values, output_count)
+        .and_then(|total| total.checked_add(transaction.fee))
+        .and_then(|total| total.checked_add(transaction.value_out));
+    let balance_valid = total_in.is_some() && total_in == total_out
+        && new_pool_balance(transaction, old_state).is_some();
+    
+    TransactionChecks {
+        merkle_valid,
+        no_double_spend,
+        nullifiers_derived,
+        signature_valid,
+        commitment_valid,
+        balance_valid,
     }
+}
+
+// Pool balance after public value enters and the withdrawal and fee leave,
+// `None` if the pool cannot cover them
+fn new_pool_balance(transaction: &PrivacyPoolTransaction, old_state: &PrivacyPoolState) -> Option<u64> {
+    old_state.pool_balance
+        .checked_add(transaction.value_in)?
+        .checked_sub(transaction.value_out)?
+        .checked_sub(transaction.fee)
+}
+
+// Validate a transaction against the current state and report the result
+fn process_transaction(transaction: &PrivacyPoolTransaction, old_state: &PrivacyPoolState) -> bool {
+    // 0. Reject out-of-range counts before any array indexing
+    if let Err(reason) = validate_counts(transaction) {
+        println!("Validation Results:");
+        println!("  Overall valid: false");
+        println!("  Invalid counts: {}", reason);
+        return false;
+    }
+    
+    // 0b. Reject shapes the transaction type does not allow
+    if let Err(violation) = validate_structure(transaction) {
+        println!("Validation Results:");
+        println!("  Overall valid: false");
+        println!("  Invalid structure: {:?}", violation);
+        return false;
+    }
+    
+    // 1-5. Merkle, nullifier, signature, commitment and balance checks
+    let checks = transaction_checks(transaction, old_state);
+    let duplicate_nullifier = find_duplicate_nullifier(transaction);
+    let invalid_nullifier = find_invalid_nullifier(transaction);
+    let unowned_input = find_unowned_input(transaction);
+    let new_nullifier_root = apply_nullifier_proofs(transaction, old_state);
     
     // 6. Calculate new state
     let mut new_nullifier_count = old_state.nullifier_count;
@@ -110,25 +259,138 @@ fn main() {
     }
     
     let new_merkle_root = update_merkle_tree_simple(&old_state.merkle_root, &transaction.output_commitments, transaction.output_count as usize);
-    let new_pool_balance = old_state.pool_balance + transaction.fee;
+    let new_pool_balance = new_pool_balance(transaction, old_state);
     
     // Overall validation
-    let is_valid = merkle_valid && no_double_spend && signature_valid && balance_valid && commitment_valid;
+    let is_valid = checks.all_passed();
     
     // Output results (simplified for demonstration)
     println!("Validation Results:");
     println!("  Overall valid: {}", is_valid);
-    println!("  Merkle valid: {}", merkle_valid);
-    println!("  No double spend: {}", no_double_spend);
-    println!("  Signature valid: {}", signature_valid);
-    println!("  Balance valid: {}", balance_valid);
-    println!("  Commitment valid: {}", commitment_valid);
+    println!("  Merkle valid: {}", checks.merkle_valid);
+    println!("  No double spend: {}", checks.no_double_spend);
+    if let Some(index) = duplicate_nullifier {
+        println!("  DuplicateNullifierInTx: input {}", index);
+    }
+    if let Some(index) = invalid_nullifier {
+        println!("  NullifierNotDerivedFromKey: input {}", index);
+    }
+    if let Err(reason) = &new_nullifier_root {
+        println!("  Nullifier check: {}", reason);
+    }
+    println!("  Signature valid: {}", checks.signature_valid);
+    if let Some(index) = unowned_input {
+        println!("  InputNotOwnedBySigner: input {}", index);
+    }
+    println!("  Balance valid: {}", checks.balance_valid);
+    println!("  Commitment valid: {}", checks.commitment_valid);
     println!("  New Merkle root: {:?}", new_merkle_root);
-    println!("  New pool balance: {}", new_pool_balance);
+    println!("  New pool balance: {:?}", new_pool_balance);
     println!("  New nullifier count: {}", new_nullifier_count);
+    println!("  New nullifier root: {:?}", new_nullifier_root);
     println!("  Transaction type: {}", transaction.tx_type);
     println!("  Input count: {}", transaction.input_count);
     println!("  Output count: {}", transaction.output_count);
+    
+    let outputs = public_output_slots(
+        &new_merkle_root,
+        new_nullifier_count,
+        transaction.value_in,
+        transaction.value_out,
+        new_nullifier_root.as_ref().unwrap_or(&old_state.nullifier_root),
+    );
+    for (slot, value) in outputs.iter().enumerate() {
+        println!("  Output slot {}: {:#010x}", slot, value);
+    }
+    
+    is_valid
+}
+
+// Public output slots as 32-bit words, in set_output order: 0-7 new Merkle
+// root, 8 nullifier count, 9-10 value_in and 11-12 value_out (high word
+// first), 13-20 new nullifier root
+fn public_output_slots(
+    merkle_root: &[u8; 32],
+    nullifier_count: u32,
+    value_in: u64,
+    value_out: u64,
+    nullifier_root: &[u8; 32],
+) -> Vec<u32> {
+    let words = |bytes: &[u8; 32]| {
+        bytes.chunks_exact(4)
+            .map(|word| u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+            .collect::<Vec<_>>()
+    };
+    
+    let mut outputs = words(merkle_root);
+    outputs.push(nullifier_count);
+    for value in [value_in, value_out] {
+        outputs.extend([(value >> 32) as u32, value as u32]);
+    }
+    outputs.extend(words(nullifier_root));
+    outputs
+}
+
+// Index of the first input whose non-empty nullifier repeats an earlier input's
+fn find_duplicate_nullifier(transaction: &PrivacyPoolTransaction) -> Option<usize> {
+    let nullifiers = &transaction.nullifiers[..transaction.input_count as usize];
+    (1..nullifiers.len()).find(|&i| {
+        nullifiers[i] != [0u8; 32] && nullifiers[..i].contains(&nullifiers[i])
+    })
+}
+
+// Index of the first input whose nullifier is not
+// derive_nullifier(spending_key, utxo_id, note_secret)
+fn find_invalid_nullifier(transaction: &PrivacyPoolTransaction) -> Option<usize> {
+    use privacy_pool_zkvm::crypto::ArchitectureCompliantCrypto;
+    
+    (0..transaction.input_count as usize).find(|&i| {
+        ArchitectureCompliantCrypto::derive_nullifier(
+            &transaction.spending_keys[i],
+            &transaction.input_utxo_ids[i],
+            &transaction.input_note_secrets[i],
+        ).ok() != Some(transaction.nullifiers[i])
+    })
+}
+
+// Index of the first input whose owner commitment does not belong to the
+// signing key
+fn find_unowned_input(transaction: &PrivacyPoolTransaction) -> Option<usize> {
+    let signer = canonical_spec::generate_key_owner_commitment(transaction.sig_scheme, &transaction.public_key);
+    (0..transaction.input_count as usize).find(|&i| transaction.input_owner_commitments[i] != signer)
+}
+
+// Check each input's non-membership proof against the running nullifier
+// root, insert its nullifier, and return the root after the last input. A
+// nullifier repeated within the transaction is then proven spent by its
+// second proof
+fn apply_nullifier_proofs(
+    transaction: &PrivacyPoolTransaction,
+    old_state: &PrivacyPoolState,
+) -> Result<[u8; 32], String> {
+    let mut root = old_state.nullifier_root;
+    for (i, nullifier) in transaction.nullifiers[..transaction.input_count as usize].iter().enumerate() {
+        if *nullifier == [0u8; 32] {
+            continue; // Skip empty nullifiers
+        }
+        let proof = transaction.nullifier_proofs.get(i)
+            .filter(|proof| proof.nullifier == *nullifier && proof.siblings.len() == tree_config::MAX_DEPTH as usize)
+            .ok_or_else(|| format!("no non-membership proof for input {}", i))?;
+        match proof.verify(&root, old_state.nullifier_tree_salt) {
+            Some(false) => {}
+            Some(true) => return Err(format!("NullifierAlreadySpent: input {}", i)),
+            None => return Err(format!("non-membership proof does not match nullifier root: input {}", i)),
+        }
+        
+        // The empty leaf's path, with the nullifier leaf in its place
+        root = canonical_spec::compute_root_from_proof(
+            canonical_spec::generate_nullifier_leaf_hash(*nullifier),
+            proof.leaf_index,
+            &proof.siblings,
+        );
+    }
+    
+    Ok(root)
 }
 
 // Simple Merkle proof verification using SHA-256
@@ -155,24 +417,49 @@ fn hash_pair_simple(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
     hasher.finalize().into()
 }
 
-// Simple signature verification (placeholder)
-fn verify_signature_simple(_message: &[u8], signature: &[u8], public_key: &[u8; 32]) -> bool {
-    // Simplified signature verification
-    // In a real implementation, this would use proper cryptographic verification
-    // For now, just check that signature and public key are not all zeros
-    !signature.is_empty() && signature != &[0u8; 64] && public_key != &[0u8; 32]
+// Verify `signature` over `message` with the scheme tagged by `sig_scheme`;
+// unknown schemes and malformed keys or signatures are rejected
+fn verify_signature(sig_scheme: u8, message: &[u8], signature: &[u8], public_key: &[u8]) -> bool {
+    use privacy_pool_zkvm::crypto::{
+        EcdsaScheme, EcdsaSig, Ed25519Scheme, Ed25519Sig, SignatureAlgorithm, SignatureScheme,
+    };
+    
+    match SignatureAlgorithm::from_byte(sig_scheme) {
+        Ok(SignatureAlgorithm::Ed25519) => {
+            let (Ok(key_bytes), Ok(sig_bytes)) = (<[u8; 32]>::try_from(public_key), <[u8; 64]>::try_from(signature)) else {
+                return false;
+            };
+            let Ok(public_key) = ed25519_dalek::VerifyingKey::from_bytes(&key_bytes) else {
+                return false;
+            };
+            let signature = Ed25519Sig::new(ed25519_dalek::Signature::from_bytes(&sig_bytes), public_key);
+            Ed25519Scheme::verify(&signature, message, &public_key).unwrap_or(false)
+        }
+        Ok(SignatureAlgorithm::Secp256k1) => {
+            let (Ok(public_key), Ok(signature)) = (
+                secp256k1::PublicKey::from_slice(public_key),
+                secp256k1::ecdsa::Signature::from_compact(signature),
+            ) else {
+                return false;
+            };
+            EcdsaScheme::verify(&EcdsaSig::new(signature, public_key, 0), message, &public_key).unwrap_or(false)
+        }
+        Err(_) => false,
+    }
 }
 
 // Create transaction message for signing
 fn create_transaction_message(tx: &PrivacyPoolTransaction) -> Vec<u8> {
     let mut data = Vec::new();
     
-    // Add transaction type
+    // Add transaction type and signature scheme
     data.push(tx.tx_type);
+    data.push(tx.sig_scheme);
     
-    // Add input commitments
+    // Add input commitments and their owners
     for i in 0..tx.input_count as usize {
         data.extend_from_slice(&tx.input_commitments[i]);
+        data.extend_from_slice(&tx.input_owner_commitments[i]);
     }
     
     // Add output commitments
@@ -189,34 +476,23 @@ fn create_transaction_message(tx: &PrivacyPoolTransaction) -> Vec<u8> {
     data.extend_from_slice(&tx.sender);
     data.extend_from_slice(&tx.recipient);
     
-    // Add fee
+    // Add fee and public values
     data.extend_from_slice(&tx.fee.to_le_bytes());
+    data.extend_from_slice(&tx.value_in.to_le_bytes());
+    data.extend_from_slice(&tx.value_out.to_le_bytes());
     
     data
 }
 
-// Calculate sum of commitments (simplified)
-fn calculate_commitment_sum_simple(commitments: &[[u8; 32]; 4], count: usize) -> u64 {
-    let mut total = 0u64;
-    
-    for i in 0..count {
-        // Simplified: extract value from first 8 bytes of commitment
-        let mut value = 0u64;
-        for j in 0..8 {
-            value |= (commitments[i][j] as u64) << (j * 8);
-        }
-        total += value % 10000; // Cap at 10000 to prevent overflow
-    }
-    
-    total
+// Sum of the first `count` committed values, `None` on overflow
+fn sum_committed_values(values: &[u64; 4], count: usize) -> Option<u64> {
+    values[..count].iter().try_fold(0u64, |total, value| total.checked_add(*value))
 }
 
-// Verify commitment (simplified)
-fn verify_commitment_simple(commitment: [u8; 32], value: u64, blinding: [u8; 32]) -> bool {
-    // Simplified commitment verification
-    // In a real implementation, this would use proper Pedersen commitments
-    // For now, just check that commitment is not all zeros and value is reasonable
-    commitment != [0u8; 32] && value > 0 && value < 1000000 && blinding != [0u8; 32]
+// Check that a commitment opens to (value, blinding) under the pool's
+// Pedersen commitment C = v*G + r*H
+fn verify_commitment_opening(commitment: [u8; 32], value: u64, blinding: [u8; 32]) -> bool {
+    commitment == zisk_pedersen_commitment(value, blinding)
 }
 
 // Update Merkle tree with new commitments (simplified)
@@ -228,4 +504,465 @@ fn update_merkle_tree_simple(old_root: &[u8; 32], new_commitments: &[[u8; 32]; 4
     }
     
     current
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use privacy_pool_zkvm::crypto::{OperatorKeypair, SignatureAlgorithm};
+    use privacy_pool_zkvm::merkle::NullifierTree;
+
+    const NULLIFIER_SALT: u64 = 42;
+
+    fn test_transaction(input_count: u8, output_count: u8) -> PrivacyPoolTransaction {
+        PrivacyPoolTransaction {
+            input_commitments: [[1u8; 32]; 4],
+            output_commitments: [[2u8; 32]; 4],
+            nullifiers: [[3u8; 32]; 4],
+            merkle_roots: [[4u8; 32]; 4],
+            values: [100; 4],
+            blinding_factors: [[5u8; 32]; 4],
+            input_values: [100; 4],
+            input_blinding_factors: [[11u8; 32]; 4],
+            input_utxo_ids: [[13u8; 32]; 4],
+            input_owner_commitments: [[16u8; 32]; 4],
+            input_note_secrets: [[14u8; 32]; 4],
+            spending_keys: [[15u8; 32]; 4],
+            nullifier_proofs: Vec::new(),
+            signature: vec![6u8; 64],
+            public_key: vec![7u8; 32],
+            sig_scheme: 1,
+            fee: 0,
+            value_in: 0,
+            value_out: 0,
+            tx_type: 2,
+            sender: [8u8; 32],
+            recipient: [9u8; 32],
+            input_count,
+            output_count,
+        }
+    }
+
+    fn test_state() -> PrivacyPoolState {
+        state_with_spent(&[])
+    }
+
+    // Nullifier tree holding `spent`
+    fn spent_tree(spent: &[[u8; 32]]) -> NullifierTree {
+        let mut tree = NullifierTree::with_salt(NULLIFIER_SALT);
+        for nullifier in spent {
+            tree.insert(*nullifier).unwrap();
+        }
+        tree
+    }
+
+    // State whose nullifier root commits to `spent`
+    fn state_with_spent(spent: &[[u8; 32]]) -> PrivacyPoolState {
+        PrivacyPoolState {
+            merkle_root: [10u8; 32],
+            pool_balance: 1_000,
+            block_height: 1,
+            nullifier_count: spent.len() as u32,
+            nullifier_root: spent_tree(spent).get_root(),
+            nullifier_tree_salt: NULLIFIER_SALT,
+        }
+    }
+
+    // Prove each input's nullifier absent from a tree holding `spent`,
+    // inserting it so the next proof is against the running root
+    fn prove_nullifiers(transaction: &mut PrivacyPoolTransaction, spent: &[[u8; 32]]) {
+        let mut tree = spent_tree(spent);
+        transaction.nullifier_proofs = transaction.nullifiers[..transaction.input_count as usize]
+            .iter()
+            .map(|nullifier| {
+                let proof = tree.prove(nullifier);
+                let _ = tree.insert(*nullifier);
+                proof
+            })
+            .collect();
+    }
+
+    // Every check passing, to compare a rejection against
+    fn passing() -> TransactionChecks {
+        TransactionChecks {
+            merkle_valid: true,
+            no_double_spend: true,
+            nullifiers_derived: true,
+            signature_valid: true,
+            commitment_valid: true,
+            balance_valid: true,
+        }
+    }
+
+    #[test]
+    fn test_oversized_counts_reported_invalid() {
+        // Would index past the 4-element arrays without the bounds check
+        assert!(!process_transaction(&test_transaction(7, 1), &test_state()));
+        assert!(!process_transaction(&test_transaction(1, 7), &test_state()));
+        assert!(validate_counts(&test_transaction(7, 1)).unwrap_err().contains("input_count 7"));
+        
+        assert!(validate_counts(&test_transaction(4, 4)).is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_nullifier_in_transaction_rejected() {
+        // Input 1 repeats input 0's witness, so both carry the same valid nullifier
+        let mut transaction = balanced_transaction();
+        transaction.input_utxo_ids[1] = transaction.input_utxo_ids[0];
+        transaction.input_note_secrets[1] = transaction.input_note_secrets[0];
+        transaction.spending_keys[1] = transaction.spending_keys[0];
+        derive_nullifiers(&mut transaction);
+        prove_nullifiers(&mut transaction, &[]);
+        sign(&mut transaction, SignatureAlgorithm::Ed25519);
+        
+        assert_eq!(find_duplicate_nullifier(&transaction), Some(1));
+        assert_eq!(apply_nullifier_proofs(&transaction, &test_state()).unwrap_err(), "NullifierAlreadySpent: input 1");
+        assert_eq!(transaction_checks(&transaction, &test_state()), TransactionChecks { no_double_spend: false, ..passing() });
+        assert!(!process_transaction(&transaction, &test_state()));
+        
+        assert_eq!(find_duplicate_nullifier(&balanced_transaction()), None);
+    }
+
+    #[test]
+    fn test_nullifier_root_tracks_consumed_nullifiers() {
+        let transaction = balanced_transaction();
+        let old_state = test_state();
+        
+        // The new root is the tree with both nullifiers inserted
+        let new_root = apply_nullifier_proofs(&transaction, &old_state).unwrap();
+        assert_ne!(new_root, old_state.nullifier_root);
+        assert_eq!(new_root, spent_tree(&transaction.nullifiers[..2]).get_root());
+        
+        // An input without a nullifier leaves the root alone
+        let mut deposit = test_transaction(0, 1);
+        deposit.tx_type = 0;
+        assert_eq!(apply_nullifier_proofs(&deposit, &old_state), Ok(old_state.nullifier_root));
+        
+        let outputs = public_output_slots(&[1u8; 32], 2, (1 << 32) + 5, 70, &new_root);
+        assert_eq!(outputs.len(), 21);
+        assert_eq!(outputs[8], 2);
+        assert_eq!(&outputs[9..13], &[1, 5, 0, 70]);
+        assert_eq!(outputs[13], u32::from_be_bytes(new_root[..4].try_into().unwrap()));
+        assert_eq!(outputs[20], u32::from_be_bytes(new_root[28..].try_into().unwrap()));
+    }
+
+    #[test]
+    fn test_nullifier_in_old_state_rejected() {
+        // Input 1 reuses a nullifier already in the tree
+        let mut transaction = balanced_transaction();
+        let spent = [[9u8; 32], transaction.nullifiers[1]];
+        prove_nullifiers(&mut transaction, &spent);
+        let old_state = state_with_spent(&spent);
+        assert_eq!(apply_nullifier_proofs(&transaction, &old_state).unwrap_err(), "NullifierAlreadySpent: input 1");
+        assert_eq!(transaction_checks(&transaction, &old_state), TransactionChecks { no_double_spend: false, ..passing() });
+        assert!(!process_transaction(&transaction, &old_state));
+        
+        // Fresh nullifiers are inserted on top of the spent ones
+        let spent = [[9u8; 32]];
+        prove_nullifiers(&mut transaction, &spent);
+        let old_state = state_with_spent(&spent);
+        assert_eq!(
+            apply_nullifier_proofs(&transaction, &old_state),
+            Ok(spent_tree(&[[9u8; 32], transaction.nullifiers[0], transaction.nullifiers[1]]).get_root()),
+        );
+        assert!(process_transaction(&transaction, &old_state));
+    }
+
+    #[test]
+    fn test_nullifier_proofs_must_match_root() {
+        let transaction = balanced_transaction();
+        let rejected = TransactionChecks { no_double_spend: false, ..passing() };
+        
+        // Proofs from a tree that hides a spent nullifier do not open the root
+        let old_state = state_with_spent(&[[9u8; 32]]);
+        assert!(apply_nullifier_proofs(&transaction, &old_state).unwrap_err().contains("does not match"));
+        assert_eq!(transaction_checks(&transaction, &old_state), rejected);
+        assert!(!process_transaction(&transaction, &old_state));
+        
+        // Missing, swapped and truncated proofs are refused
+        let mut missing = balanced_transaction();
+        missing.nullifier_proofs.pop();
+        assert_eq!(apply_nullifier_proofs(&missing, &test_state()).unwrap_err(), "no non-membership proof for input 1");
+        let mut swapped = balanced_transaction();
+        swapped.nullifier_proofs.swap(0, 1);
+        assert_eq!(apply_nullifier_proofs(&swapped, &test_state()).unwrap_err(), "no non-membership proof for input 0");
+        let mut truncated = balanced_transaction();
+        truncated.nullifier_proofs[0].siblings.pop();
+        assert_eq!(apply_nullifier_proofs(&truncated, &test_state()).unwrap_err(), "no non-membership proof for input 0");
+        assert_eq!(transaction_checks(&truncated, &test_state()), rejected);
+        
+        // The tree has no capacity limit on spent nullifiers
+        let spent: Vec<[u8; 32]> = (0..64u8).map(|i| [i + 100; 32]).collect();
+        let mut transaction = balanced_transaction();
+        prove_nullifiers(&mut transaction, &spent);
+        assert_eq!(transaction_checks(&transaction, &state_with_spent(&spent)), passing());
+    }
+
+    // Two inputs (60 + 50) paying two outputs (70 + 30) and a fee of 10
+    fn balanced_transaction() -> PrivacyPoolTransaction {
+        let mut transaction = test_transaction(2, 2);
+        transaction.input_utxo_ids = [[13u8; 32], [16u8; 32], [0u8; 32], [0u8; 32]];
+        derive_nullifiers(&mut transaction);
+        prove_nullifiers(&mut transaction, &[]);
+        transaction.input_values = [60, 50, 0, 0];
+        transaction.values = [70, 30, 0, 0];
+        transaction.fee = 10;
+        commit_and_sign(&mut transaction);
+        transaction
+    }
+
+    // Commit to every declared value and sign with Ed25519
+    fn commit_and_sign(transaction: &mut PrivacyPoolTransaction) {
+        for i in 0..transaction.input_count as usize {
+            transaction.input_commitments[i] = zisk_pedersen_commitment(transaction.input_values[i], transaction.input_blinding_factors[i]);
+        }
+        for i in 0..transaction.output_count as usize {
+            transaction.output_commitments[i] = zisk_pedersen_commitment(transaction.values[i], transaction.blinding_factors[i]);
+        }
+        sign(transaction, SignatureAlgorithm::Ed25519);
+    }
+
+    // Fill each input's nullifier from its spending key witness
+    fn derive_nullifiers(transaction: &mut PrivacyPoolTransaction) {
+        use privacy_pool_zkvm::crypto::ArchitectureCompliantCrypto;
+        
+        for i in 0..transaction.input_count as usize {
+            transaction.nullifiers[i] = ArchitectureCompliantCrypto::derive_nullifier(
+                &transaction.spending_keys[i],
+                &transaction.input_utxo_ids[i],
+                &transaction.input_note_secrets[i],
+            ).unwrap();
+        }
+    }
+
+    // Sign the transaction message with a fixed key under `algorithm` that owns every input
+    fn sign(transaction: &mut PrivacyPoolTransaction, algorithm: SignatureAlgorithm) {
+        let keypair = OperatorKeypair::from_secret_bytes(algorithm, [12u8; 32]).unwrap();
+        transaction.sig_scheme = algorithm.to_byte();
+        transaction.public_key = keypair.public_key_bytes();
+        let owner = canonical_spec::generate_key_owner_commitment(transaction.sig_scheme, &transaction.public_key);
+        for i in 0..transaction.input_count as usize {
+            transaction.input_owner_commitments[i] = owner;
+        }
+        transaction.signature = keypair.sign(&create_transaction_message(transaction)).unwrap();
+    }
+
+    #[test]
+    fn test_forged_commitment_rejected() {
+        let transaction = balanced_transaction();
+        assert_eq!(transaction_checks(&transaction, &test_state()), passing());
+        assert!(process_transaction(&transaction, &test_state()));
+        
+        // Output commitment hides 80 while the opening claims 70
+        let mut forged = balanced_transaction();
+        forged.output_commitments[0] = zisk_pedersen_commitment(80, forged.blinding_factors[0]);
+        sign(&mut forged, SignatureAlgorithm::Ed25519);
+        assert!(!verify_commitment_opening(forged.output_commitments[0], forged.values[0], forged.blinding_factors[0]));
+        assert_eq!(transaction_checks(&forged, &test_state()), TransactionChecks { commitment_valid: false, ..passing() });
+        assert!(!process_transaction(&forged, &test_state()));
+        
+        // Inflating declared values (still balanced) without matching commitments fails the opening
+        let mut inflated = balanced_transaction();
+        inflated.input_values[0] = 70;
+        inflated.values[0] = 80;
+        assert_eq!(transaction_checks(&inflated, &test_state()), TransactionChecks { commitment_valid: false, ..passing() });
+        assert!(!process_transaction(&inflated, &test_state()));
+        
+        // Correct openings that do not conserve value fail the balance check
+        let mut unbalanced = balanced_transaction();
+        unbalanced.fee = 0;
+        sign(&mut unbalanced, SignatureAlgorithm::Ed25519);
+        assert_eq!(transaction_checks(&unbalanced, &test_state()), TransactionChecks { balance_valid: false, ..passing() });
+        assert!(!process_transaction(&unbalanced, &test_state()));
+        assert_eq!(sum_committed_values(&[u64::MAX, 1, 0, 0], 2), None);
+    }
+
+    #[test]
+    fn test_deposit_and_withdrawal_balance_public_values() {
+        // Deposit: 100 of public value becomes a single 100 output
+        let mut deposit = test_transaction(0, 1);
+        deposit.tx_type = 0;
+        deposit.values = [100, 0, 0, 0];
+        deposit.value_in = 100;
+        commit_and_sign(&mut deposit);
+        assert_eq!(transaction_checks(&deposit, &test_state()), passing());
+        assert_eq!(new_pool_balance(&deposit, &test_state()), Some(1_100));
+        assert!(process_transaction(&deposit, &test_state()));
+        
+        // Withdrawal: 60 + 50 in, 30 change, 10 fee and 70 paid out
+        let mut withdrawal = balanced_transaction();
+        withdrawal.tx_type = 1;
+        withdrawal.output_count = 1;
+        withdrawal.values = [30, 0, 0, 0];
+        withdrawal.value_out = 70;
+        commit_and_sign(&mut withdrawal);
+        assert_eq!(transaction_checks(&withdrawal, &test_state()), passing());
+        assert_eq!(new_pool_balance(&withdrawal, &test_state()), Some(920));
+        assert!(process_transaction(&withdrawal, &test_state()));
+        
+        // The pool cannot pay out more than it holds
+        let mut drained = test_state();
+        drained.pool_balance = 50;
+        assert_eq!(transaction_checks(&withdrawal, &drained), TransactionChecks { balance_valid: false, ..passing() });
+        
+        // Without the public value the same shapes do not balance
+        deposit.value_in = 0;
+        sign(&mut deposit, SignatureAlgorithm::Ed25519);
+        assert_eq!(transaction_checks(&deposit, &test_state()), TransactionChecks { balance_valid: false, ..passing() });
+        
+        // The public values are signed
+        let mut redirected = withdrawal;
+        redirected.value_out = 60;
+        redirected.values[0] = 40;
+        redirected.output_commitments[0] = zisk_pedersen_commitment(40, redirected.blinding_factors[0]);
+        assert_eq!(transaction_checks(&redirected, &test_state()), TransactionChecks { signature_valid: false, ..passing() });
+    }
+
+    #[test]
+    fn test_malformed_structure_rejected_per_type() {
+        let with_type = |tx_type: u8, input_count: u8, output_count: u8| {
+            let mut tx = test_transaction(input_count, output_count);
+            tx.tx_type = tx_type;
+            tx
+        };
+        let with_public = |mut tx: PrivacyPoolTransaction, value_in: u64, value_out: u64| {
+            tx.value_in = value_in;
+            tx.value_out = value_out;
+            tx
+        };
+        
+        let cases = [
+            (with_type(0, 1, 1), StructureViolation::DepositHasInputs(1)),
+            (with_type(0, 0, 0), StructureViolation::DepositWithoutOutputs),
+            (with_type(1, 0, 1), StructureViolation::WithdrawalWithoutInputs),
+            (with_type(1, 1, 2), StructureViolation::WithdrawalTooManyOutputs(2)),
+            (with_type(2, 0, 1), StructureViolation::TransferWithoutInputs),
+            (with_type(2, 1, 0), StructureViolation::TransferWithoutOutputs),
+            (with_type(7, 1, 1), StructureViolation::UnknownTxType(7)),
+            (with_public(with_type(0, 0, 1), 5, 5), StructureViolation::PublicValueNotAllowed { value_in: 5, value_out: 5 }),
+            (with_public(with_type(1, 1, 1), 5, 0), StructureViolation::PublicValueNotAllowed { value_in: 5, value_out: 0 }),
+            (with_public(with_type(2, 1, 1), 0, 5), StructureViolation::PublicValueNotAllowed { value_in: 0, value_out: 5 }),
+        ];
+        for (tx, expected) in cases {
+            assert_eq!(validate_structure(&tx), Err(expected));
+            assert!(!process_transaction(&tx, &test_state()));
+        }
+        
+        assert_eq!(validate_structure(&with_public(with_type(0, 0, 1), 5, 0)), Ok(()));
+        assert_eq!(validate_structure(&with_type(1, 1, 0)), Ok(()));
+        assert_eq!(validate_structure(&with_public(with_type(1, 1, 1), 0, 5)), Ok(()));
+        assert_eq!(validate_structure(&with_type(2, 2, 2)), Ok(()));
+    }
+
+    #[test]
+    fn test_signature_verified_per_scheme() {
+        for algorithm in [SignatureAlgorithm::Ed25519, SignatureAlgorithm::Secp256k1] {
+            let mut transaction = balanced_transaction();
+            sign(&mut transaction, algorithm);
+            let message = create_transaction_message(&transaction);
+            assert!(verify_signature(transaction.sig_scheme, &message, &transaction.signature, &transaction.public_key));
+            assert!(process_transaction(&transaction, &test_state()));
+            
+            // Any change to the signed fields invalidates the signature
+            let mut tampered = transaction;
+            tampered.recipient = [0xeeu8; 32];
+            let message = create_transaction_message(&tampered);
+            assert!(!verify_signature(tampered.sig_scheme, &message, &tampered.signature, &tampered.public_key));
+            assert_eq!(transaction_checks(&tampered, &test_state()), TransactionChecks { signature_valid: false, ..passing() });
+            assert!(!process_transaction(&tampered, &test_state()));
+        }
+        
+        // The scheme tag is part of the signed message
+        let transaction = balanced_transaction();
+        let mut retagged = balanced_transaction();
+        retagged.sig_scheme = SignatureAlgorithm::Secp256k1.to_byte();
+        assert_ne!(create_transaction_message(&transaction), create_transaction_message(&retagged));
+    }
+
+    #[test]
+    fn test_input_with_huge_length_prefix_is_rejected() {
+        let mut transaction = balanced_transaction();
+        transaction.signature = vec![0xabu8; 7];
+        let bytes = bincode::serialize(&(&transaction, &test_state())).unwrap();
+        let decoded: (PrivacyPoolTransaction, PrivacyPoolState) = deserialize_zisk_input(&bytes).unwrap();
+        assert_eq!(decoded.0.signature, transaction.signature);
+        
+        // Claim a signature of u64::MAX bytes
+        let mut marker = 7u64.to_le_bytes().to_vec();
+        marker.extend_from_slice(&[0xabu8; 7]);
+        let offset = bytes.windows(marker.len()).position(|window| window == marker.as_slice()).unwrap();
+        let mut hostile = bytes.clone();
+        hostile[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        
+        let result: Result<(PrivacyPoolTransaction, PrivacyPoolState), _> = deserialize_zisk_input(&hostile);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_signer_must_own_every_input() {
+        let transaction = balanced_transaction();
+        assert_eq!(find_unowned_input(&transaction), None);
+        
+        // A valid signature by a key that does not own input 1 authorizes nothing
+        let mut foreign = balanced_transaction();
+        let stranger = OperatorKeypair::from_secret_bytes(SignatureAlgorithm::Ed25519, [0x77u8; 32]).unwrap();
+        foreign.input_owner_commitments[1] = canonical_spec::generate_key_owner_commitment(
+            SignatureAlgorithm::Ed25519.to_byte(),
+            &stranger.public_key_bytes(),
+        );
+        let keypair = OperatorKeypair::from_secret_bytes(SignatureAlgorithm::Ed25519, [12u8; 32]).unwrap();
+        foreign.signature = keypair.sign(&create_transaction_message(&foreign)).unwrap();
+        let message = create_transaction_message(&foreign);
+        assert!(verify_signature(foreign.sig_scheme, &message, &foreign.signature, &foreign.public_key));
+        assert_eq!(find_unowned_input(&foreign), Some(1));
+        assert_eq!(transaction_checks(&foreign, &test_state()), TransactionChecks { signature_valid: false, ..passing() });
+        assert!(!process_transaction(&foreign, &test_state()));
+        
+        // The same key under another scheme tag commits to a different owner
+        assert_ne!(
+            canonical_spec::generate_key_owner_commitment(1, &keypair.public_key_bytes()),
+            canonical_spec::generate_key_owner_commitment(2, &keypair.public_key_bytes()),
+        );
+    }
+
+    #[test]
+    fn test_nullifier_recomputed_from_spending_key() {
+        let transaction = balanced_transaction();
+        assert_eq!(find_invalid_nullifier(&transaction), None);
+        assert!(process_transaction(&transaction, &test_state()));
+        
+        // The UTXO id and note secret alone do not reproduce the nullifier
+        let mut wrong_key = balanced_transaction();
+        wrong_key.spending_keys[1] = [0xeeu8; 32];
+        assert_eq!(find_invalid_nullifier(&wrong_key), Some(1));
+        assert_eq!(transaction_checks(&wrong_key, &test_state()), TransactionChecks { nullifiers_derived: false, ..passing() });
+        assert!(!process_transaction(&wrong_key, &test_state()));
+        
+        // A nullifier chosen by the prover instead of derived is refused
+        let mut chosen = balanced_transaction();
+        chosen.nullifiers[0] = [3u8; 32];
+        sign(&mut chosen, SignatureAlgorithm::Ed25519);
+        assert_eq!(find_invalid_nullifier(&chosen), Some(0));
+        assert_eq!(transaction_checks(&chosen, &test_state()), TransactionChecks { nullifiers_derived: false, ..passing() });
+        assert!(!process_transaction(&chosen, &test_state()));
+    }
+
+    #[test]
+    fn test_placeholder_signatures_rejected() {
+        let rejected = TransactionChecks { signature_valid: false, ..passing() };
+        
+        // Non-zero blobs used to pass the old placeholder check
+        let mut transaction = balanced_transaction();
+        transaction.signature = vec![6u8; 64];
+        assert_eq!(transaction_checks(&transaction, &test_state()), rejected);
+        assert!(!process_transaction(&transaction, &test_state()));
+        
+        // A valid signature under the wrong scheme tag, or an unknown tag
+        let mut transaction = balanced_transaction();
+        transaction.sig_scheme = SignatureAlgorithm::Secp256k1.to_byte();
+        assert_eq!(transaction_checks(&transaction, &test_state()), rejected);
+        transaction.sig_scheme = 0;
+        assert_eq!(transaction_checks(&transaction, &test_state()), rejected);
+        assert!(!process_transaction(&transaction, &test_state()));
+    }
+}
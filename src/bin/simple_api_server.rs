@@ -14,6 +14,8 @@ use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 use serde::Deserialize;
+use privacy_pool_zkvm::api::types::utils::hash_to_hex;
+use privacy_pool_zkvm::canonical_spec::{empty_tree_root, tree_config};
 
 /// Query parameters for UTXO listing
 #[derive(Deserialize)]
@@ -118,9 +120,9 @@ async fn process_deposit(Json(deposit): Json<DepositRequest>) -> Json<Value> {
 /// Get tree statistics
 async fn get_tree_stats() -> Json<Value> {
     Json(json!({
-        "current_root": "0x04735efbce809c030d37ba49c991137ee9bae0681dd865766a2c50dd1c301282",
+        "current_root": hash_to_hex(empty_tree_root(tree_config::DEFAULT_DEPTH)),
         "root_version": 1,
-        "depth": 32,
+        "depth": tree_config::DEFAULT_DEPTH,
         "total_utxos": 42,
         "total_nodes": 84,
         "tree_salt": 12345678
@@ -130,7 +132,7 @@ async fn get_tree_stats() -> Json<Value> {
 /// Get current Merkle tree root
 async fn get_tree_root() -> Json<Value> {
     Json(json!({
-        "root": "0x04735efbce809c030d37ba49c991137ee9bae0681dd865766a2c50dd1c301282",
+        "root": hash_to_hex(empty_tree_root(tree_config::DEFAULT_DEPTH)),
         "version": 1,
         "timestamp": std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -1,7 +1,8 @@
 //! Canonical Format Specification
 //! 
 //! This module defines the exact byte-level formats for all data structures
-//! in the privacy pool system. All formats use big-endian encoding and strong
+//! in the privacy pool system. Persisted formats and database keys use
+//! big-endian encoding (see [`endianness`] for the few exceptions) and strong
 //! domain separation to prevent collisions.
 
 use sha3::{Keccak256, Digest};
@@ -25,6 +26,33 @@ pub mod domains {
     
     /// Transaction ID domain separator: "TXID"
     pub const TRANSACTION_ID: [u8; 4] = [0x54, 0x58, 0x49, 0x44];
+    
+    /// Deposit owner commitment domain separator: "OWNER_COMMITMENT"
+    pub const OWNER_COMMITMENT: &[u8] = b"OWNER_COMMITMENT";
+    
+    /// Signing key owner commitment domain separator: "OWNER_KEY"
+    pub const OWNER_KEY: &[u8] = b"OWNER_KEY";
+    
+    /// UTXO value commitment domain separator: "VALUE_COMMITMENT"
+    pub const VALUE_COMMITMENT: &[u8] = b"VALUE_COMMITMENT";
+    
+    /// Transaction signing domain separator: "TXSG"
+    pub const TRANSACTION_SIGNATURE: [u8; 4] = [0x54, 0x58, 0x53, 0x47];
+    
+    /// Spent nullifier domain separator: "NULL"
+    pub const NULLIFIER: [u8; 4] = [0x4E, 0x55, 0x4C, 0x4C];
+    
+    /// Nullifier tree leaf domain separator: "NLEF"
+    pub const NULLIFIER_LEAF: [u8; 4] = [0x4E, 0x4C, 0x45, 0x46];
+    
+    /// Randomness beacon seed domain separator: "BSED"
+    pub const BEACON_SEED: [u8; 4] = [0x42, 0x53, 0x45, 0x44];
+    
+    /// Randomness beacon output domain separator: "BCON"
+    pub const BEACON: [u8; 4] = [0x42, 0x43, 0x4F, 0x4E];
+    
+    /// Audit log entry domain separator: "AUDT"
+    pub const AUDIT_ENTRY: [u8; 4] = [0x41, 0x55, 0x44, 0x54];
 }
 
 /// UTXO serialization constants
@@ -35,11 +63,43 @@ pub mod utxo_format {
     /// Current UTXO format version
     pub const VERSION: u16 = 1;
     
+    /// Oldest UTXO format version this build can still read
+    ///
+    /// Versions from here up are layout-compatible: a newer version may only
+    /// append trailing sections, each announced by one bit in the header
+    /// `flags`, so older readers skip what they do not understand.
+    pub const MIN_COMPATIBLE: u16 = 1;
+    
     /// Minimum serialized UTXO size (without lock_data)
     pub const MIN_SIZE: usize = 140;
     
     /// Asset ID for native ETH (20 zero bytes)
     pub const ETH_ASSET_ID: [u8; 20] = [0u8; 20];
+    
+    /// Maximum lock_data (script) size accepted by the canonical format
+    pub const MAX_LOCK_DATA_SIZE: usize = 1024 * 1024;
+}
+
+/// Transaction serialization constants
+pub mod tx_format {
+    /// Magic number for canonical transaction bytes: "UTXT"
+    pub const MAGIC: u32 = 0x55545854;
+    
+    /// Current transaction format version
+    pub const VERSION: u16 = 1;
+}
+
+/// Withdrawal bundle serialization constants
+pub mod bundle_format {
+    /// Magic number for withdrawal bundles: "WBDL"
+    pub const MAGIC: u32 = 0x5742444C;
+    
+    /// Current bundle format version
+    pub const VERSION: u16 = 1;
+    
+    /// Header size: magic(4) || version(2) || depth(1) || root_version(8) ||
+    /// root_hash(32) || entry_count(4)
+    pub const HEADER_SIZE: usize = 51;
 }
 
 /// Database column family prefixes
@@ -55,6 +115,13 @@ pub mod cf_prefixes {
     pub const ROOT_HISTORY: u8 = 0x09;
     pub const BLOCK_INDEX: u8 = 0x0A;
     pub const TREE_METADATA: u8 = 0x0B;
+    pub const AUDIT_LOG: u8 = 0x0C;
+    pub const BALANCE_SNAPSHOTS: u8 = 0x0D;
+    pub const NULLIFIERS: u8 = 0x0E;
+    /// Root hash -> version index, stored in cf_root_history
+    pub const ROOT_INDEX: u8 = 0x0F;
+    pub const PROCESSED_TXIDS: u8 = 0x10;
+    pub const DEPOSIT_COMMITMENTS: u8 = 0x11;
 }
 
 /// Tree configuration constants
@@ -67,6 +134,194 @@ pub mod tree_config {
     
     /// Parallel processing threshold for batch updates
     pub const PARALLEL_THRESHOLD: usize = 1000;
+    
+    /// Deepest rollback below the chain head (two Ethereum epochs)
+    pub const DEFAULT_MAX_REORG_DEPTH: u64 = 64;
+    
+    /// Newest finalized roots a withdrawal proof may reference
+    pub const DEFAULT_ROOT_TOLERANCE_WINDOW: u64 = 32;
+}
+
+/// Byte order policy for multi-byte integer fields
+///
+/// Database keys and persisted values are big-endian so RocksDB's
+/// lexicographic key order matches numeric order. Commitment preimages
+/// shared with the circuits encode the value little-endian.
+pub mod endianness {
+    #[cfg(test)]
+    use crate::crypto::HashPolicy;
+    #[cfg(test)]
+    use crate::database::audit_log::{audit_log_key, AuditEntry};
+    #[cfg(test)]
+    use crate::database::balance_snapshots::{balance_snapshot_key, serialize_asset_totals, AssetTotals};
+    #[cfg(test)]
+    use crate::database::root_history::root_history_key;
+    #[cfg(test)]
+    use crate::database::schema::utils::owner_index_key;
+    #[cfg(test)]
+    use crate::utxo::CanonicalUTXO;
+    
+    /// Byte order of an encoded integer field
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ByteOrder {
+        Big,
+        Little,
+    }
+    
+    /// Probe value written into fields under test (truncated to the field
+    /// width); no two bytes are equal, so a swapped order never matches
+    pub const SENTINEL: u128 = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10;
+    
+    /// Declared byte order of every integer field
+    pub const POLICY: &[(&str, ByteOrder)] = &[
+        ("utxo.amount", ByteOrder::Big),
+        ("utxo.created_block", ByteOrder::Big),
+        ("utxo.lock_expiry", ByteOrder::Big),
+        ("utxo_id.vout", ByteOrder::Big),
+        ("utxo_id.created_block", ByteOrder::Big),
+        ("utxo_id.entropy", ByteOrder::Big),
+        ("key.owner_index.created_block", ByteOrder::Big),
+        ("key.root_history.version", ByteOrder::Big),
+        ("key.audit_log.sequence", ByteOrder::Big),
+        ("key.balance_snapshot.version", ByteOrder::Big),
+        ("asset_totals.total", ByteOrder::Big),
+        ("key.mempool.fee_rate", ByteOrder::Big),
+        ("key.block_index.block_number", ByteOrder::Big),
+        ("key.block_index.tx_index", ByteOrder::Big),
+        ("audit_entry.batch_id", ByteOrder::Big),
+        ("audit_entry.timestamp", ByteOrder::Big),
+        ("audit_entry.op_count", ByteOrder::Big),
+        ("input_lock.expires_at", ByteOrder::Big),
+        ("commitment.value", ByteOrder::Little),
+        ("value_commitment.amount", ByteOrder::Big),
+        ("nullifier.utxo_index", ByteOrder::Big),
+    ];
+    
+    /// Declared byte order of a field
+    pub fn declared_order(field: &str) -> Option<ByteOrder> {
+        POLICY.iter().find(|(name, _)| *name == field).map(|(_, order)| *order)
+    }
+    
+    /// `SENTINEL` truncated to `width` bytes, encoded in `order`
+    pub fn sentinel_bytes(order: ByteOrder, width: usize) -> Vec<u8> {
+        let low = &SENTINEL.to_be_bytes()[16 - width..];
+        match order {
+            ByteOrder::Big => low.to_vec(),
+            ByteOrder::Little => low.iter().rev().copied().collect(),
+        }
+    }
+    
+    /// Check that `encoded[offset..offset + width]` holds the truncated
+    /// `SENTINEL` in the declared order of `field`
+    pub fn check_field(field: &str, encoded: &[u8], offset: usize, width: usize) -> Result<(), String> {
+        let order = declared_order(field).ok_or_else(|| format!("{}: no declared byte order", field))?;
+        let actual = encoded.get(offset..offset + width)
+            .ok_or_else(|| format!("{}: encoding too short for offset {}", field, offset))?;
+        if actual != sentinel_bytes(order, width).as_slice() {
+            return Err(format!("{}: expected {:?}-endian encoding, got {}", field, order, hex::encode(actual)));
+        }
+        Ok(())
+    }
+    
+    /// Check a hashed preimage field by rebuilding the preimage in the declared order
+    #[cfg(test)]
+    fn check_preimage(
+        field: &str,
+        width: usize,
+        build: impl Fn(&[u8]) -> Vec<u8>,
+        hash: impl Fn(&[u8]) -> [u8; 32],
+        actual: [u8; 32],
+    ) -> Result<(), String> {
+        let order = declared_order(field).ok_or_else(|| format!("{}: no declared byte order", field))?;
+        if hash(&build(&sentinel_bytes(order, width))) != actual {
+            return Err(format!("{}: hash does not match a {:?}-endian preimage", field, order));
+        }
+        Ok(())
+    }
+    
+    /// Check every public serializer against `POLICY`, panicking on the first violation
+    ///
+    /// Private key builders are checked with [`check_field`] in their own modules.
+    #[cfg(test)]
+    pub fn assert_endianness_consistency() {
+        let u64_sentinel = SENTINEL as u64;
+        let u32_sentinel = SENTINEL as u32;
+        
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, u64_sentinel, 1, SENTINEL, [2u8; 32])
+            .with_timelock(u64_sentinel);
+        let serialized = utxo.serialize().expect("UTXO serializes");
+        let audit_entry = AuditEntry {
+            batch_id: u64_sentinel,
+            prev_entry_hash: [0u8; 32],
+            root_hash: [0u8; 32],
+            timestamp: u64_sentinel,
+            op_count: u32_sentinel,
+        }.serialize();
+        
+        let keccak = |input: &[u8]| -> [u8; 32] {
+            HashPolicy::Keccak256.hash(input).expect("Keccak256 cannot fail")
+        };
+        let (txid, owner, blinding, commitment) = ([3u8; 32], [4u8; 32], [5u8; 32], [6u8; 32]);
+        let utxo_id_preimage = |vout: &[u8], created_block: &[u8], entropy: &[u8]| {
+            [&super::domains::UTXO_ID[..], &txid, vout, created_block, entropy].concat()
+        };
+        
+        let results = [
+            check_field("utxo.amount", &serialized, 64, 16),
+            check_field("utxo.created_block", &serialized, 112, 8),
+            check_field("utxo.lock_expiry", &serialized, 120, 8),
+            check_field("key.owner_index.created_block", &owner_index_key(&owner, u64_sentinel, &txid), 33, 8),
+            check_field("key.root_history.version", &root_history_key(u64_sentinel), 1, 8),
+            check_field("key.audit_log.sequence", &audit_log_key(u64_sentinel), 1, 8),
+            check_field("key.balance_snapshot.version", &balance_snapshot_key(u64_sentinel, &[0u8; 20]), 1, 8),
+            check_field("asset_totals.total", &serialize_asset_totals(&AssetTotals::from([([0u8; 20], SENTINEL)])), 20, 16),
+            check_field("audit_entry.batch_id", &audit_entry, 0, 8),
+            check_field("audit_entry.timestamp", &audit_entry, 72, 8),
+            check_field("audit_entry.op_count", &audit_entry, 80, 4),
+            check_preimage(
+                "utxo_id.vout", 4,
+                |vout| utxo_id_preimage(vout, &[0u8; 8], &[0u8; 8]),
+                keccak,
+                super::generate_utxo_id(txid, u32_sentinel, 0, 0),
+            ),
+            check_preimage(
+                "utxo_id.created_block", 8,
+                |block| utxo_id_preimage(&[0u8; 4], block, &[0u8; 8]),
+                keccak,
+                super::generate_utxo_id(txid, 0, u64_sentinel, 0),
+            ),
+            check_preimage(
+                "utxo_id.entropy", 8,
+                |entropy| utxo_id_preimage(&[0u8; 4], &[0u8; 8], entropy),
+                keccak,
+                super::generate_utxo_id(txid, 0, 0, u64_sentinel),
+            ),
+            check_preimage(
+                "commitment.value", 8,
+                |value| [value, &owner, &blinding].concat(),
+                keccak,
+                HashPolicy::Keccak256.utxo_commitment(u64_sentinel, &owner, &blinding).expect("commitment"),
+            ),
+            check_preimage(
+                "value_commitment.amount", 16,
+                |amount| [super::domains::VALUE_COMMITMENT, amount, &owner, &blinding].concat(),
+                keccak,
+                super::generate_value_commitment(SENTINEL, owner, blinding),
+            ),
+            check_preimage(
+                "nullifier.utxo_index", 8,
+                |index| [&commitment[..], index].concat(),
+                keccak,
+                HashPolicy::Keccak256.nullifier(&commitment, u64_sentinel).expect("nullifier"),
+            ),
+        ];
+        
+        for result in results {
+            if let Err(violation) = result {
+                panic!("endianness policy violated: {}", violation);
+            }
+        }
+    }
 }
 
 /// Generate UTXO ID using canonical format
@@ -94,6 +349,66 @@ pub fn generate_utxo_id(
     hasher.finalize().into()
 }
 
+/// Generate the owner commitment the relayer assigns to a deposit's UTXO
+/// 
+/// # Arguments
+/// * `depositor` - Depositing address (20 bytes)
+/// * `commitment` - Commitment emitted by the deposit event (32 bytes)
+/// * `block_number` - Block holding the deposit (8 bytes BE)
+/// 
+/// # Returns
+/// * 32-byte owner commitment
+pub fn generate_owner_commitment(
+    depositor: [u8; 20],
+    commitment: [u8; 32],
+    block_number: u64,
+) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(domains::OWNER_COMMITMENT);
+    hasher.update(&depositor);
+    hasher.update(&commitment);
+    hasher.update(&block_number.to_be_bytes());
+    hasher.finalize().into()
+}
+
+/// Generate the owner commitment of UTXOs spendable by a signing key
+/// 
+/// # Arguments
+/// * `sig_scheme` - `SignatureAlgorithm` tag of the key (1 byte)
+/// * `public_key` - Encoded public key of that scheme
+/// 
+/// # Returns
+/// * 32-byte owner commitment
+pub fn generate_key_owner_commitment(sig_scheme: u8, public_key: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(domains::OWNER_KEY);
+    hasher.update([sig_scheme]);
+    hasher.update(public_key);
+    hasher.finalize().into()
+}
+
+/// Generate the commitment binding a UTXO amount to its owner
+/// 
+/// # Arguments
+/// * `amount` - Committed amount in the asset's smallest unit (16 bytes BE)
+/// * `owner_commitment` - Owner commitment of the UTXO (32 bytes)
+/// * `blinding` - Blinding factor hiding the amount (32 bytes)
+/// 
+/// # Returns
+/// * 32-byte value commitment
+pub fn generate_value_commitment(
+    amount: u128,
+    owner_commitment: [u8; 32],
+    blinding: [u8; 32],
+) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(domains::VALUE_COMMITMENT);
+    hasher.update(&amount.to_be_bytes());
+    hasher.update(&owner_commitment);
+    hasher.update(&blinding);
+    hasher.finalize().into()
+}
+
 /// Generate leaf hash using canonical format
 /// 
 /// # Arguments
@@ -137,6 +452,64 @@ pub fn generate_empty_leaf_hash() -> [u8; 32] {
     hasher.finalize().into()
 }
 
+/// Generate the nullifier published when a UTXO is spent
+/// 
+/// # Arguments
+/// * `utxo_id` - Spent UTXO identifier (32 bytes)
+/// * `owner_commitment` - Owner commitment of the spent UTXO (32 bytes)
+/// 
+/// # Returns
+/// * 32-byte nullifier
+pub fn generate_nullifier(utxo_id: [u8; 32], owner_commitment: [u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&domains::NULLIFIER);
+    hasher.update(&utxo_id);
+    hasher.update(&owner_commitment);
+    hasher.finalize().into()
+}
+
+/// Generate nullifier tree leaf hash
+/// 
+/// # Arguments
+/// * `nullifier` - Spent nullifier (32 bytes)
+/// 
+/// # Returns
+/// * 32-byte leaf hash
+pub fn generate_nullifier_leaf_hash(nullifier: [u8; 32]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(&domains::NULLIFIER_LEAF);
+    hasher.update(&nullifier);
+    hasher.finalize().into()
+}
+
+/// Compute a flat Merkle root over leaf hashes (UTXO-set commitment)
+/// 
+/// # Arguments
+/// * `sorted_leaf_hashes` - Leaf hashes ordered by utxo_id
+/// 
+/// # Returns
+/// * 32-byte root; the empty leaf hash for an empty set. An unpaired node
+///   at the end of a level is promoted unchanged to the next level.
+pub fn compute_utxo_set_root(sorted_leaf_hashes: &[[u8; 32]]) -> [u8; 32] {
+    if sorted_leaf_hashes.is_empty() {
+        return generate_empty_leaf_hash();
+    }
+    
+    let mut level = sorted_leaf_hashes.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => generate_node_hash(*left, *right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    
+    level[0]
+}
+
 /// Generate tree index from UTXO ID
 /// 
 /// # Arguments
@@ -159,6 +532,28 @@ pub fn generate_tree_index(utxo_id: [u8; 32], tree_salt: u64) -> u64 {
     ])
 }
 
+/// Leaf index of a UTXO in a tree of the given depth
+/// 
+/// A sparse tree of depth `d` only consumes the low `d` bits of
+/// `generate_tree_index` on the path to the root, so this is the leaf the
+/// UTXO actually occupies.
+/// 
+/// # Arguments
+/// * `utxo_id` - UTXO identifier (32 bytes)
+/// * `tree_salt` - Per-tree randomization salt (8 bytes)
+/// * `depth` - Tree depth in levels
+/// 
+/// # Returns
+/// * Leaf index in `0..2^depth`
+pub fn tree_leaf_index(utxo_id: [u8; 32], tree_salt: u64, depth: u8) -> u64 {
+    let index = generate_tree_index(utxo_id, tree_salt);
+    if depth >= 64 {
+        index
+    } else {
+        index & ((1u64 << depth) - 1)
+    }
+}
+
 /// Precompute empty subtree hashes up to given depth
 /// 
 /// # Arguments
@@ -182,6 +577,25 @@ pub fn precompute_empty_subtrees(depth: u8) -> Vec<[u8; 32]> {
     empty_subtrees
 }
 
+/// Root of a tree of `depth` levels holding only empty leaves
+pub fn empty_tree_root(depth: u8) -> [u8; 32] {
+    precompute_empty_subtrees(depth)[depth as usize]
+}
+
+/// Root implied by a leaf at `leaf_index` and its siblings from leaf to root
+/// 
+/// The tree depth is `siblings.len()`; callers must check it against the
+/// tree they verify for.
+pub fn compute_root_from_proof(leaf_hash: [u8; 32], leaf_index: u64, siblings: &[[u8; 32]]) -> [u8; 32] {
+    siblings.iter().enumerate().fold(leaf_hash, |current, (level, sibling)| {
+        if (leaf_index >> level) & 1 == 0 {
+            generate_node_hash(current, *sibling)
+        } else {
+            generate_node_hash(*sibling, current)
+        }
+    })
+}
+
 /// Compute full path from leaf to root
 /// 
 /// # Arguments
@@ -304,6 +718,10 @@ mod tests {
         // Should be different with different salt
         let index2 = generate_tree_index(utxo_id, salt + 1);
         assert_ne!(index, index2);
+        
+        // Leaf index keeps only the bits a tree of that depth consumes
+        assert_eq!(tree_leaf_index(utxo_id, salt, 16), index & 0xffff);
+        assert_eq!(tree_leaf_index(utxo_id, salt, 64), index);
     }
 
     #[test]
@@ -327,4 +745,24 @@ mod tests {
         assert_eq!(align8(16), 16);
         assert_eq!(align8(17), 24);
     }
+
+    #[test]
+    fn test_endianness_consistency() {
+        endianness::assert_endianness_consistency();
+    }
+
+    #[test]
+    fn test_endianness_check_rejects_little_endian_key() {
+        use endianness::{check_field, SENTINEL};
+        
+        let version = SENTINEL as u64;
+        assert!(check_field("key.root_history.version", &crate::database::root_history::root_history_key(version), 1, 8).is_ok());
+        
+        // A key builder that switched to little-endian would break range scans
+        let mut swapped = vec![cf_prefixes::ROOT_HISTORY];
+        swapped.extend_from_slice(&version.to_le_bytes());
+        assert!(check_field("key.root_history.version", &swapped, 1, 8).is_err());
+        
+        assert!(check_field("undeclared.field", &swapped, 1, 8).is_err());
+    }
 }
\ No newline at end of file
@@ -3,8 +3,8 @@
 //! This module provides the exact cryptographic functions as specified
 //! in architecture.md, ensuring compliance with the documented API.
 
-use crate::crypto::{CryptoResult, CryptoError, domains, CryptoUtils};
-use crate::crypto::poseidon::PoseidonHasher;
+use crate::crypto::{CryptoResult, CryptoError, CryptoContext, domains, CryptoUtils};
+use crate::crypto::poseidon::{PoseidonHash, PoseidonHasher};
 use serde_with::{serde_as, Bytes};
 
 /// Architecture-compliant commitment generation
@@ -40,28 +40,71 @@ impl ArchitectureCompliantCrypto {
         }
     }
 
-    /// Generate nullifier exactly as specified in architecture.md
-    /// nullifier = Poseidon(DOMAIN_NULL_V1, secret_field, leaf_index_field)
+    /// Generate nullifier bound to the owner's spending key
+    /// nullifier = Poseidon(DOMAIN_NULL_V1, nk, utxo_id, secret), nk = derive_nullifier_key(spending_key)
+    ///
+    /// Knowing the note secret alone is not enough to compute the nullifier,
+    /// so a leaked secret does not link the note to its eventual spend.
     pub fn derive_nullifier(
+        spending_key: &[u8; 32],
+        utxo_id: &[u8; 32],
         secret: &[u8; 32],
-        leaf_index: u64,
     ) -> CryptoResult<[u8; 32]> {
-        // Create input for nullifier derivation
-        let mut input = Vec::new();
-        input.extend_from_slice(domains::DOMAIN_NULL_V1);
-        input.extend_from_slice(secret);
-        input.extend_from_slice(&leaf_index.to_be_bytes());
+        Self::derive_nullifier_from_key(&Self::derive_nullifier_key(spending_key), utxo_id, secret)
+    }
+
+    /// Generate nullifier from the nullifier key instead of the spending key
+    ///
+    /// Lets a verifier handed `nk` recompute the nullifier without being
+    /// able to authorize spends.
+    pub fn derive_nullifier_from_key(
+        nullifier_key: &[u8; 32],
+        utxo_id: &[u8; 32],
+        secret: &[u8; 32],
+    ) -> CryptoResult<[u8; 32]> {
+        // Absorb one 32-byte input per call: the Poseidon permutation only
+        // mixes its first three field elements (93 bytes)
+        let poseidon = PoseidonHash::with_context(CryptoContext::nullifier_context());
+        let chained = poseidon.hash_multiple(&[domains::DOMAIN_NULL_V1, nullifier_key])
+            .and_then(|acc| poseidon.hash_multiple(&[&acc, utxo_id]))
+            .and_then(|acc| poseidon.hash_multiple(&[&acc, secret]));
 
         // Use Poseidon hash (fallback to Blake2b)
-        match PoseidonHasher::nullifier(&[0u8; 32], leaf_index) {
+        match chained {
             Ok(nullifier) => Ok(nullifier),
             Err(_) => {
                 // Fallback to Blake2b with proper domain separation
-                Ok(CryptoUtils::blake2b256(&input))
+                Ok(CryptoUtils::blake2b256(&[domains::DOMAIN_NULL_V1, nullifier_key, utxo_id, secret].concat()))
             }
         }
     }
 
+    /// Derive the nullifier key
+    /// nk = SHA256(DOMAIN_NK_V1 || spending_key)
+    pub fn derive_nullifier_key(spending_key: &[u8; 32]) -> [u8; 32] {
+        CryptoUtils::sha256(&[domains::DOMAIN_NK_V1, spending_key].concat())
+    }
+
+    /// Derive the spend authorization key: the RedJubjub public key of the spending key
+    pub fn derive_spend_auth_key(spending_key: &[u8; 32]) -> [u8; 32] {
+        *crate::utils::RedJubjubPrivateKey::new(*spending_key)
+            .derive_public_key()
+            .as_bytes()
+    }
+
+    /// Commitment a note registers for its owner at deposit or transfer
+    /// spend_commitment = SHA256(DOMAIN_SPEND_V1 || ak || nk || secret)
+    ///
+    /// Opening it at spend time proves the spender holds the note secret and
+    /// names the keys the spend must be signed and nullified with.
+    pub fn compute_spend_commitment(
+        spend_auth_key: &[u8; 32],
+        nullifier_key: &[u8; 32],
+        secret: &[u8; 32],
+    ) -> [u8; 32] {
+        CryptoUtils::sha256(&[domains::DOMAIN_SPEND_V1, spend_auth_key, nullifier_key, secret].concat())
+    }
+
     /// Generate note ID exactly as specified in architecture.md
     /// note_id = SHA256(DOMAIN_NOTE_V1 || commitment || secret)
     pub fn generate_note_id(
@@ -100,13 +143,15 @@ impl ArchitectureCompliantCrypto {
         Ok((enc_key, mac_key))
     }
 
-    /// Verify nullifier binding to leaf index (prevents replay across contexts)
+    /// Recompute the nullifier from the spend witness and compare, as the
+    /// circuit does before accepting a spend
     pub fn verify_nullifier_binding(
         nullifier: &[u8; 32],
+        spending_key: &[u8; 32],
+        utxo_id: &[u8; 32],
         secret: &[u8; 32],
-        leaf_index: u64,
     ) -> CryptoResult<bool> {
-        let expected_nullifier = Self::derive_nullifier(secret, leaf_index)?;
+        let expected_nullifier = Self::derive_nullifier(spending_key, utxo_id, secret)?;
         Ok(CryptoUtils::constant_time_eq(nullifier, &expected_nullifier))
     }
 
@@ -233,9 +278,9 @@ impl CanonicalNote {
         )
     }
 
-    /// Derive nullifier for this note
-    pub fn derive_nullifier(&self, leaf_index: u64) -> CryptoResult<[u8; 32]> {
-        ArchitectureCompliantCrypto::derive_nullifier(&self.secret, leaf_index)
+    /// Derive nullifier for this note with the owner's spending key
+    pub fn derive_nullifier(&self, spending_key: &[u8; 32], utxo_id: &[u8; 32]) -> CryptoResult<[u8; 32]> {
+        ArchitectureCompliantCrypto::derive_nullifier(spending_key, utxo_id, &self.secret)
     }
 
     /// Verify commitment matches computed value
@@ -276,20 +321,61 @@ mod tests {
 
     #[test]
     fn test_nullifier_generation() {
+        let spending_key = CryptoUtils::random_32();
         let secret = CryptoUtils::random_32();
-        let leaf_index = 42u64;
+        let utxo_id = [42u8; 32];
 
-        let nullifier1 = ArchitectureCompliantCrypto::derive_nullifier(&secret, leaf_index).unwrap();
-        let nullifier2 = ArchitectureCompliantCrypto::derive_nullifier(&secret, leaf_index).unwrap();
+        let nullifier1 = ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap();
+        let nullifier2 = ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap();
 
         // Should be deterministic
         assert_eq!(nullifier1, nullifier2);
 
-        // Should be different for different leaf indices
-        let nullifier3 = ArchitectureCompliantCrypto::derive_nullifier(&secret, leaf_index + 1).unwrap();
+        // Should be different for different UTXOs
+        let nullifier3 = ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &[43u8; 32], &secret).unwrap();
         assert_ne!(nullifier1, nullifier3);
     }
 
+    #[test]
+    fn test_nullifier_requires_spending_key() {
+        let spending_key = CryptoUtils::random_32();
+        let secret = CryptoUtils::random_32();
+        let utxo_id = [7u8; 32];
+
+        let nullifier = ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap();
+
+        // Someone holding the secret but not the key cannot reproduce it
+        for guessed_key in [[0u8; 32], secret, utxo_id, CryptoUtils::random_32()] {
+            assert_ne!(ArchitectureCompliantCrypto::derive_nullifier(&guessed_key, &utxo_id, &secret).unwrap(), nullifier);
+            assert!(!ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &guessed_key, &utxo_id, &secret).unwrap());
+        }
+
+        // The key holder reproduces it
+        assert_eq!(ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap(), nullifier);
+        assert!(ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &spending_key, &utxo_id, &secret).unwrap());
+    }
+
+    #[test]
+    fn test_nullifier_key_reproduces_nullifier() {
+        let spending_key = CryptoUtils::random_32();
+        let secret = CryptoUtils::random_32();
+        let utxo_id = [9u8; 32];
+
+        // A verifier holding nk recomputes the key holder's nullifier
+        let nullifier_key = ArchitectureCompliantCrypto::derive_nullifier_key(&spending_key);
+        assert_ne!(nullifier_key, spending_key);
+        assert_eq!(
+            ArchitectureCompliantCrypto::derive_nullifier_from_key(&nullifier_key, &utxo_id, &secret).unwrap(),
+            ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap()
+        );
+
+        // The spend commitment binds both keys and the secret
+        let spend_auth_key = ArchitectureCompliantCrypto::derive_spend_auth_key(&spending_key);
+        let commitment = ArchitectureCompliantCrypto::compute_spend_commitment(&spend_auth_key, &nullifier_key, &secret);
+        let other_key = ArchitectureCompliantCrypto::derive_spend_auth_key(&CryptoUtils::random_32());
+        assert_ne!(ArchitectureCompliantCrypto::compute_spend_commitment(&other_key, &nullifier_key, &secret), commitment);
+    }
+
     #[test]
     fn test_note_id_generation() {
         let commitment = CryptoUtils::random_32();
@@ -326,16 +412,23 @@ mod tests {
 
     #[test]
     fn test_nullifier_binding_verification() {
+        let spending_key = CryptoUtils::random_32();
         let secret = CryptoUtils::random_32();
-        let leaf_index = 123u64;
+        let utxo_id = [123u8; 32];
 
-        let nullifier = ArchitectureCompliantCrypto::derive_nullifier(&secret, leaf_index).unwrap();
+        let nullifier = ArchitectureCompliantCrypto::derive_nullifier(&spending_key, &utxo_id, &secret).unwrap();
 
         // Should verify correctly
-        assert!(ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &secret, leaf_index).unwrap());
+        assert!(ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &spending_key, &utxo_id, &secret).unwrap());
+
+        // Should fail with wrong UTXO or secret
+        assert!(!ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &spending_key, &[124u8; 32], &secret).unwrap());
+        assert!(!ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &spending_key, &utxo_id, &CryptoUtils::random_32()).unwrap());
 
-        // Should fail with wrong leaf index
-        assert!(!ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &secret, leaf_index + 1).unwrap());
+        // Every byte of the secret is bound, including the last one
+        let mut tail_flipped = secret;
+        tail_flipped[31] ^= 1;
+        assert!(!ArchitectureCompliantCrypto::verify_nullifier_binding(&nullifier, &spending_key, &utxo_id, &tail_flipped).unwrap());
     }
 
     #[test]
@@ -156,6 +156,14 @@ impl BN254Pairing {
         true
     }
     
+    /// Check that the product of `e(P_i, Q_i)` over all pairs is one
+    pub fn pairing_product_is_one(pairs: &[(G1Affine, G2Affine)]) -> bool {
+        use ark_ff::One;
+        
+        let (g1, g2): (Vec<G1Affine>, Vec<G2Affine>) = pairs.iter().copied().unzip();
+        Bn254::multi_pairing(g1, g2).0.is_one()
+    }
+    
     /// Batch pairing verification
     pub fn batch_verify_pairings(
         left_pairs: &[(G1Affine, G2Affine)],
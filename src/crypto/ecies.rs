@@ -5,7 +5,7 @@
 
 use k256::{SecretKey, PublicKey, ecdh};
 use k256::elliptic_curve::sec1::ToEncodedPoint;
-use chacha20poly1305::{XChaCha20Poly1305, Key, aead::Aead, aead::KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, Key, aead::Aead, aead::KeyInit, aead::Payload};
 use aead::generic_array::GenericArray;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
@@ -15,11 +15,21 @@ use crate::utxo::note::{Note, EncryptedNote};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Leading byte of note ciphertexts bound to `domains::DOMAIN_NOTE`
+///
+/// Ciphertexts without it were encrypted with no associated data and are
+/// still accepted by `Ecies::decrypt_note`.
+pub const NOTE_CIPHERTEXT_VERSION: u8 = 2;
+
 /// ECIES encryption implementation
 pub struct Ecies;
 
 impl Ecies {
     /// Encrypt a note for a recipient public key
+    ///
+    /// `domains::DOMAIN_NOTE` is bound as associated data, so the ciphertext
+    /// only decrypts as a note. The ciphertext starts with
+    /// `NOTE_CIPHERTEXT_VERSION`.
     pub fn encrypt_note(note: &Note, recipient_pubkey: &[u8; 33]) -> CryptoResult<EncryptedNote> {
         // Generate ephemeral key pair
         let ephemeral_secret = SecretKey::random(&mut rand::thread_rng());
@@ -45,8 +55,10 @@ impl Ecies {
         
         // Encrypt note data
         let cipher = XChaCha20Poly1305::new(&encryption_key);
-        let ciphertext = cipher.encrypt(nonce, note_json.as_bytes())
-            .map_err(|e| CryptoError::SerializationError(format!("Encryption failed: {:?}", e)))?;
+        let payload = Payload { msg: note_json.as_bytes(), aad: domains::DOMAIN_NOTE };
+        let mut ciphertext = vec![NOTE_CIPHERTEXT_VERSION];
+        ciphertext.extend(cipher.encrypt(nonce, payload)
+            .map_err(|e| CryptoError::SerializationError(format!("Encryption failed: {:?}", e)))?);
         
         // Create encrypted note
         let mut ephemeral_pubkey = [0u8; 33];
@@ -57,10 +69,13 @@ impl Ecies {
             nonce_bytes,
             ciphertext,
             Some(note.commitment),
-        ))
+        ).with_view_tag(Self::view_tag_from_secret(&shared_secret)))
     }
     
     /// Decrypt an encrypted note using recipient private key
+    ///
+    /// Falls back to the unversioned format without associated data for
+    /// notes stored before `NOTE_CIPHERTEXT_VERSION`.
     pub fn decrypt_note(encrypted_note: &EncryptedNote, recipient_privkey: &[u8; 32]) -> CryptoResult<Note> {
         // Parse recipient private key
         let recipient_secret = SecretKey::from_be_bytes(recipient_privkey)
@@ -79,8 +94,17 @@ impl Ecies {
         // Decrypt note data
         let nonce = GenericArray::from_slice(&encrypted_note.nonce);
         let cipher = XChaCha20Poly1305::new(&encryption_key);
-        let plaintext = cipher.decrypt(nonce, &*encrypted_note.ciphertext)
-            .map_err(|e| CryptoError::SerializationError(format!("Decryption failed: {:?}", e)))?;
+        let versioned = match encrypted_note.ciphertext.split_first() {
+            Some((&NOTE_CIPHERTEXT_VERSION, body)) => {
+                cipher.decrypt(nonce, Payload { msg: body, aad: domains::DOMAIN_NOTE }).ok()
+            },
+            _ => None,
+        };
+        let plaintext = match versioned {
+            Some(plaintext) => plaintext,
+            None => cipher.decrypt(nonce, encrypted_note.ciphertext.as_slice())
+                .map_err(|e| CryptoError::SerializationError(format!("Decryption failed: {:?}", e)))?,
+        };
         
         // Deserialize note from JSON
         let note_json = String::from_utf8(plaintext)
@@ -92,6 +116,33 @@ impl Ecies {
         Ok(note)
     }
     
+    /// View tag a recipient expects for a note with this ephemeral key
+    ///
+    /// Costs one ECDH and a hash, so scanners can rule out notes that are not
+    /// theirs before attempting AEAD decryption. The tag is 8 bytes, so a
+    /// foreign note matching it is negligible; decryption still decides
+    /// ownership.
+    pub fn view_tag(ephemeral_pubkey: &[u8; 33], recipient_privkey: &[u8; 32]) -> CryptoResult<[u8; 8]> {
+        let recipient_secret = SecretKey::from_be_bytes(recipient_privkey)
+            .map_err(|e| CryptoError::InvalidPrivateKey(format!("Invalid recipient private key: {:?}", e)))?;
+        
+        let ephemeral_pub = PublicKey::from_sec1_bytes(ephemeral_pubkey)
+            .map_err(|e| CryptoError::InvalidPublicKey(format!("Invalid ephemeral public key: {:?}", e)))?;
+        
+        let shared_secret = Self::ecdh(&recipient_secret, &ephemeral_pub)?;
+        Ok(Self::view_tag_from_secret(&shared_secret))
+    }
+    
+    /// First 8 bytes of a domain-separated hash of the ECDH shared secret
+    fn view_tag_from_secret(shared_secret: &[u8; 32]) -> [u8; 8] {
+        let mut data = Vec::with_capacity(domains::DOMAIN_VIEW_TAG_V1.len() + 32);
+        data.extend_from_slice(domains::DOMAIN_VIEW_TAG_V1);
+        data.extend_from_slice(shared_secret);
+        let mut view_tag = [0u8; 8];
+        view_tag.copy_from_slice(&CryptoUtils::sha256(&data)[..8]);
+        view_tag
+    }
+    
     /// Perform ECDH key exchange
     fn ecdh(secret_key: &SecretKey, public_key: &PublicKey) -> CryptoResult<[u8; 32]> {
         // Perform ECDH using k256's ecdh module
@@ -182,7 +233,7 @@ impl Ecies {
             nonce_bytes,
             ciphertext,
             Some(*commitment),
-        ))
+        ).with_view_tag(Self::view_tag_from_secret(&shared_secret)))
     }
     
     /// Enhanced ECIES decryption with AAD verification
@@ -322,4 +373,37 @@ mod tests {
         
         assert_eq!(note, decrypted);
     }
+
+    #[test]
+    fn test_unversioned_ciphertext_still_decrypts() {
+        let (secret_key, public_key) = Ecies::generate_keypair().unwrap();
+        let mut seckey_bytes = [0u8; 32];
+        seckey_bytes.copy_from_slice(secret_key.to_be_bytes().as_slice());
+        let mut pubkey_bytes = [0u8; 33];
+        pubkey_bytes.copy_from_slice(&public_key.to_encoded_point(true).as_bytes());
+        
+        let note = Note::new(
+            1000000000000000000u64,
+            pubkey_bytes,
+            1,
+            1,
+            "0x1234567890123456789012345678901234567890".to_string(),
+        );
+        let mut encrypted = Ecies::encrypt_note(&note, &pubkey_bytes).unwrap();
+        assert_eq!(encrypted.ciphertext[0], NOTE_CIPHERTEXT_VERSION);
+        
+        // Re-encrypt the same note the way notes were stored before versioning
+        let ephemeral_pub = PublicKey::from_sec1_bytes(&encrypted.ephemeral_pubkey).unwrap();
+        let shared_secret = Ecies::ecdh(&secret_key, &ephemeral_pub).unwrap();
+        let cipher = XChaCha20Poly1305::new(&Ecies::derive_encryption_key(&shared_secret).unwrap());
+        encrypted.ciphertext = cipher
+            .encrypt(GenericArray::from_slice(&encrypted.nonce), note.to_json().unwrap().as_bytes())
+            .unwrap();
+        assert_eq!(Ecies::decrypt_note(&encrypted, &seckey_bytes).unwrap(), note);
+        
+        // A versioned ciphertext is not accepted without its associated data
+        let mut stripped = Ecies::encrypt_note(&note, &pubkey_bytes).unwrap();
+        stripped.ciphertext.remove(0);
+        assert!(Ecies::decrypt_note(&stripped, &seckey_bytes).is_err());
+    }
 }
@@ -0,0 +1,65 @@
+//! Hash Policy
+//! 
+//! Single choice of hash function shared by commitment and nullifier
+//! generation, so a circuit verifying both only needs one hash gadget.
+
+use crate::crypto::{CryptoResult, CryptoUtils};
+use crate::crypto::poseidon::PoseidonHasher;
+
+/// Hash function used for commitments and nullifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashPolicy {
+    /// SHA-256
+    Sha256,
+    /// Blake2b-256
+    Blake2b256,
+    /// Keccak-256
+    Keccak256,
+    /// Poseidon (circuit-friendly, default)
+    #[default]
+    Poseidon,
+}
+
+impl HashPolicy {
+    /// Hash arbitrary bytes
+    pub fn hash(&self, input: &[u8]) -> CryptoResult<[u8; 32]> {
+        match self {
+            HashPolicy::Sha256 => Ok(CryptoUtils::sha256(input)),
+            HashPolicy::Blake2b256 => Ok(CryptoUtils::blake2b256(input)),
+            HashPolicy::Keccak256 => Ok(CryptoUtils::keccak256(input)),
+            HashPolicy::Poseidon => crate::crypto::poseidon::PoseidonHash::new().hash(input),
+        }
+    }
+    
+    /// UTXO commitment over value, owner and blinding factor
+    pub fn utxo_commitment(
+        &self,
+        value: u64,
+        owner: &[u8; 32],
+        blinding_factor: &[u8; 32],
+    ) -> CryptoResult<[u8; 32]> {
+        match self {
+            HashPolicy::Poseidon => PoseidonHasher::utxo_commitment(value, owner, blinding_factor),
+            _ => {
+                let mut input = Vec::with_capacity(72);
+                input.extend_from_slice(&value.to_le_bytes());
+                input.extend_from_slice(owner);
+                input.extend_from_slice(blinding_factor);
+                self.hash(&input)
+            }
+        }
+    }
+    
+    /// Nullifier for the UTXO commitment at `utxo_index`
+    pub fn nullifier(&self, utxo_commitment: &[u8; 32], utxo_index: u64) -> CryptoResult<[u8; 32]> {
+        match self {
+            HashPolicy::Poseidon => PoseidonHasher::nullifier(utxo_commitment, utxo_index),
+            _ => {
+                let mut input = Vec::with_capacity(40);
+                input.extend_from_slice(utxo_commitment);
+                input.extend_from_slice(&utxo_index.to_be_bytes());
+                self.hash(&input)
+            }
+        }
+    }
+}
@@ -3,10 +3,18 @@
 //! This module provides production-ready Merkle proof verification
 //! with support for multiple hash functions and batch verification.
 
-use crate::crypto::{CryptoResult, CryptoError, CryptoContext, CryptoUtils};
+use crate::crypto::{CryptoResult, CryptoError, CryptoContext, CryptoUtils, PoseidonHasher};
 use crate::utxo::transaction::MerkleProof;
 use sha3::Digest;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Empty subtree tables shared by every verifier, keyed by `(hash_function, depth)`
+static EMPTY_SUBTREE_CACHE: OnceLock<Mutex<HashMap<(HashFunction, usize), Arc<Vec<[u8; 32]>>>>> = OnceLock::new();
+
+/// Number of times each `(hash_function, depth)` table was actually computed
+#[cfg(test)]
+static EMPTY_SUBTREE_COMPUTATIONS: OnceLock<Mutex<HashMap<(HashFunction, usize), usize>>> = OnceLock::new();
 
 /// Merkle proof verifier with multiple hash functions
 #[derive(Clone)]
@@ -22,7 +30,7 @@ pub struct MerkleProofVerifier {
 }
 
 /// Supported hash functions
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum HashFunction {
     /// SHA-256
     Sha256,
@@ -34,17 +42,133 @@ pub enum HashFunction {
     Poseidon,
 }
 
+/// Merkle authentication path, one bit per level from the leaf up
+///
+/// `true` means the node at that level is a right child. Proof structs carry
+/// the path as 0/1 words (`Vec<u32>`); `PathBits` is the single conversion
+/// point between that encoding, `Vec<bool>` and a packed bitfield.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PathBits(Vec<bool>);
+
+impl PathBits {
+    /// Path of `leaf_index` in a tree of `depth` levels
+    pub fn from_leaf_index(leaf_index: u64, depth: usize) -> Self {
+        Self((0..depth).map(|level| level < 64 && (leaf_index >> level) & 1 == 1).collect())
+    }
+
+    /// Number of levels
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the path has no levels
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether the node at `level` is a right child
+    pub fn is_right(&self, level: usize) -> bool {
+        self.0.get(level).copied().unwrap_or(false)
+    }
+
+    /// Iterate levels from the leaf up
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Leaf index the path leads to, `None` if it does not fit in a u64
+    pub fn leaf_index(&self) -> Option<u64> {
+        let mut index = 0u64;
+        for (level, is_right) in self.iter().enumerate() {
+            if is_right {
+                if level >= 64 {
+                    return None;
+                }
+                index |= 1 << level;
+            }
+        }
+        Some(index)
+    }
+
+    /// Encode as 0/1 words, the wire format of proof structs
+    pub fn to_u32s(&self) -> Vec<u32> {
+        self.iter().map(u32::from).collect()
+    }
+
+    /// Pack into bytes, level `i` at bit `i % 8` of byte `i / 8`
+    pub fn to_packed(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.len().div_ceil(8)];
+        for (level, is_right) in self.iter().enumerate() {
+            if is_right {
+                bytes[level / 8] |= 1 << (level % 8);
+            }
+        }
+        bytes
+    }
+
+    /// Unpack `len` levels from a packed bitfield
+    ///
+    /// Rejects a wrong byte count and set padding bits, so every path has
+    /// exactly one packed encoding.
+    pub fn from_packed(bytes: &[u8], len: usize) -> CryptoResult<Self> {
+        if bytes.len() != len.div_ceil(8) {
+            return Err(CryptoError::MerkleProofFailed(
+                format!("Packed path of {} levels needs {} bytes, got {}", len, len.div_ceil(8), bytes.len())
+            ));
+        }
+
+        let path = Self((0..len).map(|level| (bytes[level / 8] >> (level % 8)) & 1 == 1).collect());
+        if path.to_packed() != bytes {
+            return Err(CryptoError::MerkleProofFailed("Packed path has padding bits set".to_string()));
+        }
+        Ok(path)
+    }
+}
+
+impl From<Vec<bool>> for PathBits {
+    fn from(bits: Vec<bool>) -> Self {
+        Self(bits)
+    }
+}
+
+impl From<PathBits> for Vec<bool> {
+    fn from(path: PathBits) -> Self {
+        path.0
+    }
+}
+
+impl TryFrom<&[u32]> for PathBits {
+    type Error = CryptoError;
+
+    /// Parse 0/1 words, rejecting any other value
+    fn try_from(words: &[u32]) -> CryptoResult<Self> {
+        words.iter()
+            .map(|&word| match word {
+                0 => Ok(false),
+                1 => Ok(true),
+                other => Err(CryptoError::MerkleProofFailed(format!("Invalid path bit: {}", other))),
+            })
+            .collect::<CryptoResult<Vec<bool>>>()
+            .map(Self)
+    }
+}
+
+impl From<PathBits> for Vec<u32> {
+    fn from(path: PathBits) -> Self {
+        path.to_u32s()
+    }
+}
+
 impl MerkleProofVerifier {
     /// Create new Merkle proof verifier
     pub fn new(hash_function: HashFunction, depth: usize) -> Self {
-        let empty_leaf = Self::hash_empty_leaf(hash_function);
-        let empty_subtrees = Self::precompute_empty_subtrees(hash_function, depth);
+        let empty_subtrees = Self::cached_empty_subtrees(hash_function, depth);
         
         Self {
             hash_function,
             depth,
-            empty_leaf,
-            empty_subtrees,
+            empty_leaf: empty_subtrees[0],
+            empty_subtrees: empty_subtrees.as_ref().clone(),
         }
     }
     
@@ -79,12 +203,14 @@ impl MerkleProofVerifier {
             ));
         }
         
+        let path = PathBits::try_from(proof.path.as_slice())?;
+        
         // Start with the leaf
         let mut current = *leaf;
         
         // Walk up the tree using the proof
-        for (i, (sibling, &path_bit)) in proof.siblings.iter().zip(proof.path.iter()).enumerate() {
-            if path_bit == 1 {
+        for (sibling, is_right) in proof.siblings.iter().zip(path.iter()) {
+            if is_right {
                 // Right child: hash(left, right)
                 current = self.hash_children(*sibling, current);
             } else {
@@ -121,6 +247,19 @@ impl MerkleProofVerifier {
         Ok(true)
     }
     
+    /// Verify every proof, returning one result per proof in order
+    ///
+    /// Unlike `batch_verify_proofs` this does not stop at the first invalid
+    /// proof; it still fails on a malformed proof (wrong depth, bad path bit).
+    pub fn verify_each_proof(
+        &self,
+        proofs: &[(MerkleProof, [u8; 32])],
+    ) -> CryptoResult<Vec<bool>> {
+        proofs.iter()
+            .map(|(proof, leaf)| self.verify_proof(proof, leaf))
+            .collect()
+    }
+    
     /// Generate Merkle proof for a leaf
     pub fn generate_proof(
         &self,
@@ -132,11 +271,10 @@ impl MerkleProofVerifier {
         }
         
         let mut siblings = Vec::new();
-        let mut path = Vec::new();
         let mut current_index = leaf_index;
         
         // Build the proof path
-        for level in 0..self.depth {
+        for _level in 0..self.depth {
             let sibling_index = current_index ^ 1;
             let sibling = if sibling_index < leaves.len() as u64 {
                 leaves[sibling_index as usize]
@@ -145,9 +283,9 @@ impl MerkleProofVerifier {
             };
             
             siblings.push(sibling);
-            path.push((current_index & 1) as u32);
             current_index >>= 1;
         }
+        let path = PathBits::from_leaf_index(leaf_index, self.depth).to_u32s();
         
         // Compute the root
         let root = self.compute_root(leaves)?;
@@ -209,14 +347,7 @@ impl MerkleProofVerifier {
                 hasher.update(&right);
                 hasher.finalize().into()
             }
-            HashFunction::Poseidon => {
-                // In production, use proper Poseidon hash
-                // For now, use Blake2b as fallback
-                let mut hasher = blake2::Blake2s256::new();
-                hasher.update(&left);
-                hasher.update(&right);
-                hasher.finalize().into()
-            }
+            HashFunction::Poseidon => PoseidonHasher::hash2(&left, &right),
         }
     }
     
@@ -230,10 +361,7 @@ impl MerkleProofVerifier {
             HashFunction::Sha256 => CryptoUtils::sha256(&combined),
             HashFunction::Blake2b256 => CryptoUtils::blake2b256(&combined),
             HashFunction::Keccak256 => CryptoUtils::keccak256(&combined),
-            HashFunction::Poseidon => {
-                // In production, use proper Poseidon hash
-                CryptoUtils::blake2b256(&combined)
-            }
+            HashFunction::Poseidon => PoseidonHasher::hash_bytes(&combined),
         }
     }
     
@@ -245,15 +373,30 @@ impl MerkleProofVerifier {
             HashFunction::Sha256 => CryptoUtils::sha256(&empty_data),
             HashFunction::Blake2b256 => CryptoUtils::blake2b256(&empty_data),
             HashFunction::Keccak256 => CryptoUtils::keccak256(&empty_data),
-            HashFunction::Poseidon => {
-                // In production, use proper Poseidon hash
-                CryptoUtils::blake2b256(&empty_data)
-            }
+            HashFunction::Poseidon => PoseidonHasher::hash_bytes(&empty_data),
         }
     }
     
+    /// Get the memoized empty subtree table for `(hash_function, depth)`,
+    /// computing it on first use
+    fn cached_empty_subtrees(hash_function: HashFunction, depth: usize) -> Arc<Vec<[u8; 32]>> {
+        let cache = EMPTY_SUBTREE_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+        let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        
+        cache
+            .entry((hash_function, depth))
+            .or_insert_with(|| Arc::new(Self::precompute_empty_subtrees(hash_function, depth)))
+            .clone()
+    }
+    
     /// Precompute empty subtree hashes
     fn precompute_empty_subtrees(hash_function: HashFunction, depth: usize) -> Vec<[u8; 32]> {
+        #[cfg(test)]
+        {
+            let counts = EMPTY_SUBTREE_COMPUTATIONS.get_or_init(|| Mutex::new(HashMap::new()));
+            *counts.lock().unwrap().entry((hash_function, depth)).or_insert(0) += 1;
+        }
+        
         let mut subtrees = Vec::new();
         let mut current = Self::hash_empty_leaf(hash_function);
         subtrees.push(current);
@@ -287,13 +430,7 @@ impl MerkleProofVerifier {
                 hasher.update(&right);
                 hasher.finalize().into()
             }
-            HashFunction::Poseidon => {
-                // In production, use proper Poseidon hash
-                let mut hasher = blake2::Blake2s256::new();
-                hasher.update(&left);
-                hasher.update(&right);
-                hasher.finalize().into()
-            }
+            HashFunction::Poseidon => PoseidonHasher::hash2(&left, &right),
         }
     }
 }
@@ -469,6 +606,28 @@ impl MerkleProofUtils {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_path_bits_round_trip() {
+        let path = PathBits::from_leaf_index(0b1_0110_1101, 11);
+        assert_eq!(path.leaf_index(), Some(0b1_0110_1101));
+
+        let bools: Vec<bool> = path.clone().into();
+        assert_eq!(PathBits::from(bools), path);
+
+        let words: Vec<u32> = path.clone().into();
+        assert_eq!(words, vec![1, 0, 1, 1, 0, 1, 1, 0, 1, 0, 0]);
+        assert_eq!(PathBits::try_from(words.as_slice()).unwrap(), path);
+
+        let packed = path.to_packed();
+        assert_eq!(packed, vec![0b0110_1101, 0b0000_0001]);
+        assert_eq!(PathBits::from_packed(&packed, path.len()).unwrap(), path);
+
+        // Only 0/1 words and clean padding are accepted
+        assert!(PathBits::try_from([0u32, 2].as_slice()).is_err());
+        assert!(PathBits::from_packed(&[0b0110_1101, 0b1000_0001], 11).is_err());
+        assert!(PathBits::from_packed(&packed, 17).is_err());
+    }
+
     #[test]
     fn test_merkle_proof_verification() {
         let verifier = MerkleProofVerifier::new(HashFunction::Blake2b256, 3);
@@ -530,6 +689,28 @@ mod tests {
         assert!(verifier.batch_verify_proofs(&proofs).unwrap());
     }
 
+    #[test]
+    fn test_verify_each_proof_reports_per_proof() {
+        let verifier = MerkleProofVerifier::new(HashFunction::Keccak256, 2);
+        let node = |left: [u8; 32], right: [u8; 32]| CryptoUtils::keccak256(&[left, right].concat());
+        
+        // Leaf 2 of a depth-2 tree: left child at level 0, right child at level 1
+        let (leaf, siblings) = ([1u8; 32], vec![[2u8; 32], [3u8; 32]]);
+        let root = node([3u8; 32], node(leaf, [2u8; 32]));
+        let proof = MerkleProof { siblings, path: vec![0, 1], root, leaf_index: 2 };
+        
+        let proofs = vec![
+            (proof.clone(), leaf),
+            (proof.clone(), [9u8; 32]),
+            (MerkleProof { root: [0u8; 32], ..proof.clone() }, leaf),
+        ];
+        assert_eq!(verifier.verify_each_proof(&proofs).unwrap(), vec![true, false, false]);
+        assert!(!verifier.batch_verify_proofs(&proofs).unwrap());
+        
+        let short = MerkleProof { siblings: vec![[2u8; 32]], ..proof };
+        assert!(verifier.verify_each_proof(&[(short, leaf)]).is_err());
+    }
+
     #[test]
     fn test_context_verification() {
         let context = CryptoContext::merkle_context();
@@ -540,4 +721,49 @@ mod tests {
         
         assert!(verifier.verify_proof_with_context(&proof, &leaves[0], &context).unwrap());
     }
+
+    #[test]
+    fn test_empty_subtrees_shared_across_verifiers() {
+        let first = MerkleProofVerifier::new(HashFunction::Sha256, 20);
+        let second = MerkleProofVerifier::new(HashFunction::Sha256, 20);
+        
+        assert_eq!(first.empty_leaf, second.empty_leaf);
+        assert_eq!(first.empty_subtrees, second.empty_subtrees);
+        assert_eq!(first.empty_subtrees.len(), 21);
+        
+        // A different configuration must not reuse the same table
+        let other = MerkleProofVerifier::new(HashFunction::Keccak256, 20);
+        assert_ne!(first.empty_subtrees, other.empty_subtrees);
+    }
+
+    #[test]
+    fn test_empty_subtrees_computed_once_per_config() {
+        // Depth 29 with Keccak is only used by this test, so the counter is not
+        // affected by verifiers built concurrently in other tests
+        let key = (HashFunction::Keccak256, 29);
+        let computations = || {
+            EMPTY_SUBTREE_COMPUTATIONS
+                .get_or_init(|| Mutex::new(HashMap::new()))
+                .lock()
+                .unwrap()
+                .get(&key)
+                .copied()
+                .unwrap_or(0)
+        };
+        
+        let _first = MerkleProofVerifier::new(key.0, key.1);
+        assert_eq!(computations(), 1);
+        
+        let _second = MerkleProofVerifier::new(key.0, key.1);
+        assert_eq!(computations(), 1);
+    }
+
+    #[test]
+    fn test_poseidon_nodes_use_circomlib_hash() {
+        let verifier = MerkleProofVerifier::new(HashFunction::Poseidon, 4);
+        let (left, right) = (CryptoUtils::random_32(), CryptoUtils::random_32());
+        
+        assert_eq!(verifier.hash_children(left, right), PoseidonHasher::hash2(&left, &right));
+        assert_eq!(verifier.empty_subtrees[1], PoseidonHasher::hash2(&verifier.empty_subtrees[0], &verifier.empty_subtrees[0]));
+    }
 }
@@ -8,6 +8,7 @@
 //! - Commitment schemes (Pedersen/Poseidon)
 //! - Merkle proof verification
 //! - Nullifier generation and verification
+//! - Shared hash policy for commitments and nullifiers
 
 use rand::RngCore;
 use sha2::Digest;
@@ -18,6 +19,7 @@ pub mod key_derivation;
 pub mod commitments;
 pub mod merkle_proofs;
 pub mod nullifiers;
+pub mod hash_policy;
 pub mod poseidon;
 pub mod bn254;
 pub mod ecies;
@@ -29,6 +31,7 @@ pub use key_derivation::*;
 pub use commitments::*;
 pub use merkle_proofs::*;
 pub use nullifiers::*;
+pub use hash_policy::HashPolicy;
 pub use poseidon::*;
 pub use bn254::*;
 pub use ecies::*;
@@ -133,6 +136,15 @@ pub mod domains {
     /// Domain separator for ECIES encryption (V1)
     pub const DOMAIN_ECIES_V1: &[u8] = b"PRIVPOOL_ECIES_V1";
 
+    /// Domain separator for note view tags (V1)
+    pub const DOMAIN_VIEW_TAG_V1: &[u8] = b"PRIVPOOL_VIEW_TAG_V1";
+
+    /// Domain separator for nullifier key derivation (V1)
+    pub const DOMAIN_NK_V1: &[u8] = b"PRIVPOOL_NK_V1";
+
+    /// Domain separator for spend commitments (V1)
+    pub const DOMAIN_SPEND_V1: &[u8] = b"PRIVPOOL_SPEND_V1";
+
     // Backward compatibility constants
     pub const DOMAIN_COMMIT: &[u8] = DOMAIN_COMMIT_V1;
     pub const DOMAIN_NULL: &[u8] = DOMAIN_NULL_V1;
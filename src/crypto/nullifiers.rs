@@ -3,9 +3,12 @@
 //! This module provides production-ready nullifier generation
 //! for preventing double-spending in privacy-preserving systems.
 
-use crate::crypto::{CryptoResult, CryptoError, CryptoContext, CryptoUtils};
+use crate::crypto::{CryptoResult, CryptoError, CryptoContext, CryptoUtils, HashPolicy};
 use crate::crypto::signatures::{EcdsaSig, Ed25519Scheme, EcdsaScheme, SignatureScheme};
 use crate::crypto::key_derivation::ExtendedPrivateKey;
+use crate::database::schema::{DatabaseManager, cf_names, utils};
+use crate::database::{AtomicBatchWriter, BatchOperation};
+use crate::canonical_spec::cf_prefixes;
 use ed25519_dalek::Verifier;
 
 /// Nullifier for preventing double-spending
@@ -25,27 +28,18 @@ pub struct Nullifier {
 pub struct NullifierGenerator {
     /// Cryptographic context
     pub context: CryptoContext,
-    /// Hash function to use
-    pub hash_function: NullifierHashFunction,
+    /// Hash policy shared with commitment generation
+    pub hash_policy: HashPolicy,
 }
 
 /// Hash function for nullifier generation
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum NullifierHashFunction {
-    /// SHA-256
-    Sha256,
-    /// Blake2b-256
-    Blake2b256,
-    /// Keccak-256
-    Keccak256,
-    /// Poseidon
-    Poseidon,
-}
+#[deprecated(note = "use `HashPolicy` so nullifiers and commitments share one hash function")]
+pub type NullifierHashFunction = HashPolicy;
 
 impl NullifierGenerator {
     /// Create new nullifier generator
-    pub fn new(context: CryptoContext, hash_function: NullifierHashFunction) -> Self {
-        Self { context, hash_function }
+    pub fn new(context: CryptoContext, hash_policy: HashPolicy) -> Self {
+        Self { context, hash_policy }
     }
     
     /// Generate nullifier for UTXO
@@ -201,18 +195,7 @@ impl NullifierGenerator {
     
     /// Hash nullifier seed
     fn hash_nullifier(&self, seed: &[u8]) -> CryptoResult<[u8; 32]> {
-        let hash = match self.hash_function {
-            NullifierHashFunction::Sha256 => CryptoUtils::sha256(seed),
-            NullifierHashFunction::Blake2b256 => CryptoUtils::blake2b256(seed),
-            NullifierHashFunction::Keccak256 => CryptoUtils::keccak256(seed),
-            NullifierHashFunction::Poseidon => {
-                // Use Poseidon hash with proper domain separation
-                use crate::crypto::poseidon::PoseidonHasher;
-                PoseidonHasher::nullifier(&[0u8; 32], 0) // Simplified for now
-                    .unwrap_or_else(|_| CryptoUtils::blake2b256(seed))
-            }
-        };
-        Ok(hash)
+        self.hash_policy.hash(seed)
     }
     
     /// Sign nullifier
@@ -278,10 +261,10 @@ pub struct NullifierSet {
 
 impl NullifierSet {
     /// Create new nullifier set
-    pub fn new(context: CryptoContext, hash_function: NullifierHashFunction) -> Self {
+    pub fn new(context: CryptoContext, hash_policy: HashPolicy) -> Self {
         Self {
             nullifiers: std::collections::HashSet::new(),
-            generator: NullifierGenerator::new(context, hash_function),
+            generator: NullifierGenerator::new(context, hash_policy),
         }
     }
     
@@ -331,6 +314,160 @@ impl NullifierSet {
     }
 }
 
+/// Bits in the in-memory nullifier bloom cache (128KB)
+const NULLIFIER_BLOOM_BITS: usize = 1 << 20;
+
+/// Probes per nullifier in the bloom cache
+const NULLIFIER_BLOOM_PROBES: usize = 4;
+
+/// Bloom cache over nullifier values
+///
+/// Nullifiers are already uniform hashes, so each probe reads 8 bytes of the value directly.
+struct NullifierBloom {
+    bits: Vec<u64>,
+}
+
+impl NullifierBloom {
+    fn new() -> Self {
+        Self {
+            bits: vec![0u64; NULLIFIER_BLOOM_BITS / 64],
+        }
+    }
+
+    fn probes(value: &[u8; 32]) -> impl Iterator<Item = usize> + '_ {
+        (0..NULLIFIER_BLOOM_PROBES).map(move |i| {
+            let chunk: [u8; 8] = value[i * 8..(i + 1) * 8].try_into().unwrap();
+            (u64::from_le_bytes(chunk) % NULLIFIER_BLOOM_BITS as u64) as usize
+        })
+    }
+
+    fn insert(&mut self, value: &[u8; 32]) {
+        for bit in Self::probes(value) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    fn might_contain(&self, value: &[u8; 32]) -> bool {
+        Self::probes(value).all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+}
+
+/// Nullifier set persisted in cf_nullifiers
+///
+/// The bloom cache only answers "definitely unused"; every possible hit is confirmed
+/// against the database, so the set survives restarts without trusting memory.
+pub struct PersistentNullifierSet {
+    db: DatabaseManager,
+    cache: NullifierBloom,
+    count: usize,
+    /// Generator for verification
+    pub generator: NullifierGenerator,
+}
+
+impl PersistentNullifierSet {
+    /// Open the set over `db` and warm the bloom cache from cf_nullifiers
+    pub fn open(db: DatabaseManager, context: CryptoContext, hash_policy: HashPolicy) -> anyhow::Result<Self> {
+        let mut set = Self {
+            db,
+            cache: NullifierBloom::new(),
+            count: 0,
+            generator: NullifierGenerator::new(context, hash_policy),
+        };
+        set.rebuild_cache()?;
+        Ok(set)
+    }
+
+    /// Rebuild the bloom cache from cf_nullifiers, returning the number of stored nullifiers
+    pub fn rebuild_cache(&mut self) -> anyhow::Result<usize> {
+        let mut cache = NullifierBloom::new();
+        let mut count = 0;
+        for item in self.db.prefix_iterator_cf(cf_names::NULLIFIERS, &[cf_prefixes::NULLIFIERS])? {
+            let (key, _) = item?;
+            if key.first() != Some(&cf_prefixes::NULLIFIERS) {
+                break;
+            }
+            
+            let value: [u8; 32] = key[1..].try_into()
+                .map_err(|_| anyhow::anyhow!("Invalid nullifier key length"))?;
+            cache.insert(&value);
+            count += 1;
+        }
+        
+        self.cache = cache;
+        self.count = count;
+        Ok(count)
+    }
+    
+    /// Add nullifier to set
+    pub fn add_nullifier(&mut self, nullifier: &Nullifier) -> CryptoResult<bool> {
+        // Verify nullifier first
+        if !self.generator.verify_nullifier(nullifier)? {
+            return Err(CryptoError::NullifierFailed("Invalid nullifier".to_string()));
+        }
+        
+        // Check if already used
+        if self.is_nullifier_used(&nullifier.value) {
+            return Err(CryptoError::NullifierFailed("Nullifier already used".to_string()));
+        }
+        
+        // Persist before caching so a failed write never marks the nullifier as used
+        self.db.put_cf(cf_names::NULLIFIERS, &utils::nullifier_key(&nullifier.value), &[])
+            .map_err(|e| CryptoError::NullifierFailed(format!("Failed to persist nullifier: {}", e)))?;
+        self.cache.insert(&nullifier.value);
+        self.count += 1;
+        Ok(true)
+    }
+    
+    /// Commit `writer` and cache every nullifier it records
+    ///
+    /// Batches that reveal nullifiers go through here so `is_nullifier_used`
+    /// sees them straight away, not only after a reopen rebuilds the cache.
+    pub fn commit_batch(&mut self, writer: AtomicBatchWriter) -> anyhow::Result<()> {
+        let revealed: Vec<[u8; 32]> = writer.operations().iter()
+            .filter_map(|operation| match operation {
+                BatchOperation::InsertNullifier { nullifier, .. } => Some(*nullifier),
+                _ => None,
+            })
+            .collect();
+        
+        writer.commit()?;
+        for nullifier in &revealed {
+            self.cache.insert(nullifier);
+        }
+        self.count += revealed.len();
+        Ok(())
+    }
+    
+    /// Check if nullifier is used
+    ///
+    /// A failed database read counts as used so that storage errors can never admit a double spend.
+    pub fn is_nullifier_used(&self, nullifier_value: &[u8; 32]) -> bool {
+        if !self.cache.might_contain(nullifier_value) {
+            return false;
+        }
+        self.db.get_cf(cf_names::NULLIFIERS, &utils::nullifier_key(nullifier_value))
+            .map_or(true, |value| value.is_some())
+    }
+    
+    /// Get nullifier count
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    
+    /// Batch add nullifiers
+    pub fn add_nullifiers(&mut self, nullifiers: &[Nullifier]) -> CryptoResult<usize> {
+        let mut added_count = 0;
+        
+        for nullifier in nullifiers {
+            if self.add_nullifier(nullifier).is_ok() {
+                added_count += 1;
+            }
+        }
+        
+        Ok(added_count)
+    }
+}
+
 /// Nullifier utilities
 pub struct NullifierUtils;
 
@@ -341,8 +478,9 @@ impl NullifierUtils {
         utxo_index: u64,
         private_key: &[u8; 32],
         context: &CryptoContext,
+        hash_policy: HashPolicy,
     ) -> CryptoResult<Nullifier> {
-        let generator = NullifierGenerator::new(context.clone(), NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context.clone(), hash_policy);
         generator.generate_nullifier(utxo_commitment, private_key, utxo_index)
     }
     
@@ -352,9 +490,10 @@ impl NullifierUtils {
         utxo_index: u64,
         extended_key: &ExtendedPrivateKey,
         context: &CryptoContext,
+        hash_policy: HashPolicy,
     ) -> CryptoResult<Nullifier> {
         let private_key = extended_key.secp256k1_secret_key()?.secret_bytes();
-        let generator = NullifierGenerator::new(context.clone(), NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context.clone(), hash_policy);
         generator.generate_nullifier(utxo_commitment, &private_key, utxo_index)
     }
     
@@ -362,9 +501,10 @@ impl NullifierUtils {
     pub fn verify_against_contexts(
         nullifier: &Nullifier,
         contexts: &[CryptoContext],
+        hash_policy: HashPolicy,
     ) -> CryptoResult<bool> {
         for context in contexts {
-            let generator = NullifierGenerator::new(context.clone(), NullifierHashFunction::Blake2b256);
+            let generator = NullifierGenerator::new(context.clone(), hash_policy);
             if generator.verify_nullifier(nullifier).unwrap_or(false) {
                 return Ok(true);
             }
@@ -411,9 +551,9 @@ pub struct NullifierProof {
 
 impl NullifierProof {
     /// Verify the nullifier proof
-    pub fn verify(&self, context: &CryptoContext) -> CryptoResult<bool> {
+    pub fn verify(&self, context: &CryptoContext, hash_policy: HashPolicy) -> CryptoResult<bool> {
         // Verify nullifier
-        let generator = NullifierGenerator::new(context.clone(), NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context.clone(), hash_policy);
         if !generator.verify_nullifier_with_index(&self.nullifier, self.utxo_index)? {
             return Ok(false);
         }
@@ -447,7 +587,7 @@ mod tests {
     #[test]
     fn test_nullifier_generation() {
         let context = CryptoContext::nullifier_context();
-        let generator = NullifierGenerator::new(context, NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context, HashPolicy::Blake2b256);
         
         let utxo_commitment = CryptoUtils::random_32();
         let private_key = CryptoUtils::random_32();
@@ -462,13 +602,13 @@ mod tests {
     #[test]
     fn test_nullifier_set() {
         let context = CryptoContext::nullifier_context();
-        let mut nullifier_set = NullifierSet::new(context, NullifierHashFunction::Blake2b256);
+        let mut nullifier_set = NullifierSet::new(context, HashPolicy::Blake2b256);
         
         let utxo_commitment = CryptoUtils::random_32();
         let private_key = CryptoUtils::random_32();
         let utxo_index = 0;
         
-        let generator = NullifierGenerator::new(CryptoContext::nullifier_context(), NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(CryptoContext::nullifier_context(), HashPolicy::Blake2b256);
         let nullifier = generator.generate_nullifier(&utxo_commitment, &private_key, utxo_index).unwrap();
         
         // Add nullifier
@@ -479,10 +619,64 @@ mod tests {
         assert!(nullifier_set.add_nullifier(&nullifier).is_err());
     }
 
+    #[test]
+    fn test_persistent_nullifier_set_survives_reopen() {
+        use crate::database::schema::DBConfig;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let generator = NullifierGenerator::new(CryptoContext::nullifier_context(), HashPolicy::Blake2b256);
+        let nullifier = generator.generate_nullifier(&CryptoUtils::random_32(), &CryptoUtils::random_32(), 0).unwrap();
+        
+        {
+            let db = DatabaseManager::open(DBConfig { db_path: db_path.clone(), ..Default::default() }).unwrap();
+            let mut set = PersistentNullifierSet::open(db, CryptoContext::nullifier_context(), HashPolicy::Blake2b256).unwrap();
+            assert!(!set.is_nullifier_used(&nullifier.value));
+            assert!(set.add_nullifier(&nullifier).unwrap());
+            assert!(set.is_nullifier_used(&nullifier.value));
+        }
+        
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let mut set = PersistentNullifierSet::open(db, CryptoContext::nullifier_context(), HashPolicy::Blake2b256).unwrap();
+        assert_eq!(set.count(), 1);
+        assert!(set.is_nullifier_used(&nullifier.value));
+        assert!(!set.is_nullifier_used(&CryptoUtils::random_32()));
+        assert!(set.add_nullifier(&nullifier).is_err());
+    }
+
+    #[test]
+    fn test_batch_nullifier_is_visible_after_commit() {
+        use crate::database::schema::DBConfig;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let value = CryptoUtils::random_32();
+        
+        {
+            let db = DatabaseManager::open(DBConfig { db_path: db_path.clone(), ..Default::default() }).unwrap();
+            let mut set = PersistentNullifierSet::open(db.clone(), CryptoContext::nullifier_context(), HashPolicy::Blake2b256).unwrap();
+            let mut writer = AtomicBatchWriter::new(db.clone());
+            writer.add_operation(BatchOperation::InsertNullifier { nullifier: value, spent_txid: [0u8; 32], block: 0 });
+            set.commit_batch(writer).unwrap();
+            
+            // Visible without a reopen, and cannot be revealed again
+            assert!(set.is_nullifier_used(&value));
+            assert_eq!(set.count(), 1);
+            let mut writer = AtomicBatchWriter::new(db);
+            writer.add_operation(BatchOperation::InsertNullifier { nullifier: value, spent_txid: [1u8; 32], block: 1 });
+            assert!(set.commit_batch(writer).is_err());
+            assert_eq!(set.count(), 1);
+        }
+        
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let set = PersistentNullifierSet::open(db, CryptoContext::nullifier_context(), HashPolicy::Blake2b256).unwrap();
+        assert!(set.is_nullifier_used(&value));
+    }
+
     #[test]
     fn test_nullifier_proof() {
         let context = CryptoContext::nullifier_context();
-        let generator = NullifierGenerator::new(context.clone(), NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context.clone(), HashPolicy::Blake2b256);
         
         let utxo_commitment = CryptoUtils::random_32();
         let private_key = CryptoUtils::random_32();
@@ -501,13 +695,13 @@ mod tests {
         let nullifier_proof = NullifierUtils::generate_nullifier_proof(&nullifier, utxo_index, &merkle_proof).unwrap();
         
         // Verify proof
-        assert!(nullifier_proof.verify(&context).unwrap());
+        assert!(nullifier_proof.verify(&context, HashPolicy::Blake2b256).unwrap());
     }
 
     #[test]
     fn test_batch_nullifier_verification() {
         let context = CryptoContext::nullifier_context();
-        let generator = NullifierGenerator::new(context, NullifierHashFunction::Blake2b256);
+        let generator = NullifierGenerator::new(context, HashPolicy::Blake2b256);
         
         let mut nullifiers = Vec::new();
         for i in 0..5 {
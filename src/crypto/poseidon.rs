@@ -2,12 +2,33 @@
 //! 
 //! This module provides Poseidon hash function implementation
 //! optimized for zero-knowledge proof systems.
+//!
+//! The permutation is Poseidon over the BN254 scalar field with circomlib's
+//! width-3 parameters: x^5 S-box, 8 full and 57 partial rounds, and round
+//! constants and MDS matrix generated by the reference Grain LFSR. Hashing two
+//! field elements therefore matches circomlib's `poseidon([a, b])`, which is
+//! what circuits and the on-chain verifier recompute.
 
-use ark_ff::{Field, Zero, UniformRand};
+use std::collections::VecDeque;
+use ark_ff::{BigInteger, Field, One, PrimeField, Zero};
 use ark_bn254::Fr;
-use ark_ec::AffineRepr;
-use ark_serialize::CanonicalSerialize;
-use crate::crypto::{CryptoResult, CryptoError, CryptoContext};
+use crate::crypto::{CryptoResult, CryptoContext};
+
+/// State width: one capacity element and two rate elements
+const POSEIDON_WIDTH: usize = 3;
+
+/// Full rounds, half before and half after the partial rounds
+const POSEIDON_FULL_ROUNDS: usize = 8;
+
+/// Partial rounds for width 3 (circomlib `N_ROUNDS_P[1]`)
+const POSEIDON_PARTIAL_ROUNDS: usize = 57;
+
+/// Elements absorbed per permutation
+const POSEIDON_RATE: usize = POSEIDON_WIDTH - 1;
+
+/// Capacity tag for byte input, above any length, so the byte sponge never
+/// starts from circomlib's zero capacity
+const BYTE_SPONGE_TAG: u128 = 1 << 64;
 
 /// Poseidon hash implementation
 pub struct PoseidonHash {
@@ -20,16 +41,172 @@ pub struct PoseidonHash {
 /// Poseidon parameters
 #[derive(Debug, Clone)]
 pub struct PoseidonParameters {
-    /// Round constants
+    /// Round constants, one row of `POSEIDON_WIDTH` per round
     pub round_constants: Vec<Vec<Fr>>,
     /// MDS matrix
     pub mds_matrix: Vec<Vec<Fr>>,
-    /// Number of rounds
+    /// Number of rounds (full and partial)
     pub num_rounds: usize,
     /// Partial rounds
     pub partial_rounds: usize,
 }
 
+impl PoseidonParameters {
+    /// circomlib's width-3 parameters over BN254, generated once
+    pub fn circomlib() -> &'static Self {
+        static PARAMETERS: std::sync::OnceLock<PoseidonParameters> = std::sync::OnceLock::new();
+        PARAMETERS.get_or_init(|| {
+            let field_bits = Fr::MODULUS_BIT_SIZE as usize;
+            let mut grain = GrainLfsr::new(field_bits, POSEIDON_WIDTH, POSEIDON_FULL_ROUNDS, POSEIDON_PARTIAL_ROUNDS);
+            
+            // Constants are rejection-sampled below the modulus
+            let num_rounds = POSEIDON_FULL_ROUNDS + POSEIDON_PARTIAL_ROUNDS;
+            let mut round_constants = Vec::with_capacity(num_rounds);
+            for _ in 0..num_rounds {
+                let mut row = Vec::with_capacity(POSEIDON_WIDTH);
+                while row.len() < POSEIDON_WIDTH {
+                    let candidate = <Fr as PrimeField>::BigInt::from_bits_be(&grain.next_bits(field_bits));
+                    if let Some(constant) = Fr::from_bigint(candidate) {
+                        row.push(constant);
+                    }
+                }
+                round_constants.push(row);
+            }
+            
+            // Cauchy matrix 1 / (x_i + y_j) over distinct samples, reduced
+            // rather than rejected as in the reference script. The first
+            // draw is secure for width 3, so its extra checks never resample.
+            let mds_matrix = loop {
+                let samples: Vec<Fr> = (0..2 * POSEIDON_WIDTH)
+                    .map(|_| {
+                        let bits = <Fr as PrimeField>::BigInt::from_bits_be(&grain.next_bits(field_bits));
+                        Fr::from_be_bytes_mod_order(&bits.to_bytes_be())
+                    })
+                    .collect();
+                let (xs, ys) = samples.split_at(POSEIDON_WIDTH);
+                
+                let distinct = samples.iter().enumerate()
+                    .all(|(i, sample)| !samples[..i].contains(sample));
+                let rows: Option<Vec<Vec<Fr>>> = xs.iter()
+                    .map(|x| ys.iter().map(|y| (*x + y).inverse()).collect())
+                    .collect();
+                match rows {
+                    Some(rows) if distinct => break rows,
+                    _ => continue,
+                }
+            };
+            
+            PoseidonParameters {
+                round_constants,
+                mds_matrix,
+                num_rounds,
+                partial_rounds: POSEIDON_PARTIAL_ROUNDS,
+            }
+        })
+    }
+    
+    /// Apply the Poseidon permutation to `state` in place
+    pub fn permute(&self, state: &mut [Fr; POSEIDON_WIDTH]) {
+        let half_full_rounds = (self.num_rounds - self.partial_rounds) / 2;
+        
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (element, constant) in state.iter_mut().zip(constants) {
+                *element += constant;
+            }
+            
+            if round < half_full_rounds || round >= half_full_rounds + self.partial_rounds {
+                for element in state.iter_mut() {
+                    *element = Self::s_box(*element);
+                }
+            } else {
+                state[0] = Self::s_box(state[0]);
+            }
+            
+            *state = self.apply_mds_matrix(state);
+        }
+    }
+    
+    /// Hash two field elements: capacity zero, inputs in the rate, first element out
+    pub fn hash_two(&self, left: Fr, right: Fr) -> Fr {
+        let mut state = [Fr::zero(), left, right];
+        self.permute(&mut state);
+        state[0]
+    }
+    
+    /// S-box function
+    fn s_box(x: Fr) -> Fr {
+        x.square().square() * x // x^5
+    }
+    
+    /// Apply MDS matrix
+    fn apply_mds_matrix(&self, state: &[Fr; POSEIDON_WIDTH]) -> [Fr; POSEIDON_WIDTH] {
+        let mut result = [Fr::zero(); POSEIDON_WIDTH];
+        
+        for (i, row) in self.mds_matrix.iter().enumerate() {
+            for (j, entry) in row.iter().enumerate() {
+                result[i] += *entry * state[j];
+            }
+        }
+        
+        result
+    }
+}
+
+/// Grain LFSR from the Poseidon reference parameter script
+struct GrainLfsr {
+    bits: VecDeque<bool>,
+}
+
+impl GrainLfsr {
+    /// Seed with the parameter description, then discard 160 bits
+    fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        let mut bits = VecDeque::with_capacity(80);
+        let fields: [(usize, usize); 7] = [
+            (1, 2), // prime field
+            (0, 4), // x^alpha S-box
+            (field_bits, 12),
+            (width, 12),
+            (full_rounds, 10),
+            (partial_rounds, 10),
+            ((1 << 30) - 1, 30),
+        ];
+        for (value, len) in fields {
+            for i in (0..len).rev() {
+                bits.push_back((value >> i) & 1 == 1);
+            }
+        }
+        
+        let mut lfsr = Self { bits };
+        for _ in 0..160 {
+            lfsr.step();
+        }
+        lfsr
+    }
+    
+    fn step(&mut self) -> bool {
+        let bit = self.bits[62] ^ self.bits[51] ^ self.bits[38] ^ self.bits[23] ^ self.bits[13] ^ self.bits[0];
+        self.bits.pop_front();
+        self.bits.push_back(bit);
+        bit
+    }
+    
+    /// Self-shrinking output: of each pair, the second bit is kept when the first is set
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+    
+    /// Next `count` output bits, most significant first
+    fn next_bits(&mut self, count: usize) -> Vec<bool> {
+        (0..count).map(|_| self.next_bit()).collect()
+    }
+}
+
 impl PoseidonHash {
     /// Create new Poseidon hash instance
     pub fn new() -> Self {
@@ -52,8 +229,8 @@ impl PoseidonHash {
         // Convert input to field elements
         let field_elements = self.bytes_to_field_elements(input)?;
         
-        // Apply Poseidon hash
-        let result = self.poseidon_hash(&field_elements)?;
+        // Apply Poseidon hash, with the input length in the capacity element
+        let result = self.poseidon_hash(&field_elements, Self::byte_capacity(input.len()));
         
         // Convert result to bytes
         Ok(self.field_element_to_bytes(result))
@@ -144,117 +321,51 @@ impl PoseidonHash {
         Ok(elements)
     }
     
-    /// Convert bytes to single field element
+    /// Convert bytes to single field element (big-endian, at most 31 bytes)
     fn bytes_to_field_element(&self, bytes: &[u8]) -> CryptoResult<Fr> {
-        if bytes.is_empty() {
-            return Ok(Fr::zero());
-        }
-        
-        // Pad to 32 bytes
-        let mut padded = vec![0u8; 32];
-        let copy_len = std::cmp::min(bytes.len(), 32);
-        padded[32 - copy_len..].copy_from_slice(&bytes[..copy_len]);
-        
-        // Convert to field element
-        Fr::from_random_bytes(&padded)
-            .ok_or_else(|| CryptoError::HashError("Invalid field element".to_string()))
+        Ok(Fr::from_be_bytes_mod_order(bytes))
     }
     
-    /// Convert field element to bytes
+    /// Convert field element to big-endian bytes
     fn field_element_to_bytes(&self, element: Fr) -> [u8; 32] {
         let mut bytes = [0u8; 32];
-        element.serialize_uncompressed(&mut bytes[..]).unwrap();
+        bytes.copy_from_slice(&element.into_bigint().to_bytes_be());
         bytes
     }
     
-    /// Apply Poseidon hash function
-    fn poseidon_hash(&self, input: &[Fr]) -> CryptoResult<Fr> {
-        if input.is_empty() {
-            return Ok(Fr::zero());
-        }
-        
-        // Initialize state
-        let mut state = input.to_vec();
-        
-        // Pad to required length
-        while state.len() < 3 {
-            state.push(Fr::zero());
-        }
-        
-        // Apply rounds
-        for round in 0..self.params.num_rounds {
-            // Add round constants
-            for i in 0..state.len() {
-                if i < self.params.round_constants[round].len() {
-                    state[i] += self.params.round_constants[round][i];
-                }
-            }
-            
-            // Apply S-box
-            for i in 0..state.len() {
-                state[i] = self.s_box(state[i]);
-            }
-            
-            // Apply MDS matrix
-            if round < self.params.num_rounds - 1 {
-                state = self.apply_mds_matrix(&state);
-            }
-        }
-        
-        Ok(state[0])
-    }
-    
-    /// S-box function
-    fn s_box(&self, x: Fr) -> Fr {
-        x * x * x // x^3
+    /// Capacity element for hashing `len` bytes
+    ///
+    /// The length fixes where the 31-byte chunks end, so leading zeros in
+    /// the last chunk cannot collapse into a shorter input.
+    fn byte_capacity(len: usize) -> Fr {
+        Fr::from(BYTE_SPONGE_TAG | len as u128)
     }
     
-    /// Apply MDS matrix
-    fn apply_mds_matrix(&self, state: &[Fr]) -> Vec<Fr> {
-        let mut result = vec![Fr::zero(); state.len()];
-        
-        for i in 0..state.len() {
-            for j in 0..state.len() {
-                if i < self.params.mds_matrix.len() && j < self.params.mds_matrix[i].len() {
-                    result[i] += self.params.mds_matrix[i][j] * state[j];
-                }
+    /// Sponge over the field elements, two per permutation
+    ///
+    /// The input is 10*-padded (a one element, then zeros to the rate) so no
+    /// input is a prefix-with-zeros of another, and `capacity` seeds the
+    /// capacity element. Two-element circomlib hashes go through `hash_two`.
+    fn poseidon_hash(&self, input: &[Fr], capacity: Fr) -> Fr {
+        let mut padded = input.to_vec();
+        padded.push(Fr::one());
+        padded.resize(padded.len().div_ceil(POSEIDON_RATE) * POSEIDON_RATE, Fr::zero());
+        
+        let mut state = [Fr::zero(); POSEIDON_WIDTH];
+        state[0] = capacity;
+        for chunk in padded.chunks(POSEIDON_RATE) {
+            for (element, value) in state[1..].iter_mut().zip(chunk) {
+                *element += value;
             }
+            self.params.permute(&mut state);
         }
         
-        result
+        state[0]
     }
     
     /// Default Poseidon parameters
     fn default_parameters() -> PoseidonParameters {
-        // Simplified parameters for BN254
-        // In production, use proper Poseidon parameters
-        let mut round_constants = Vec::new();
-        let mut mds_matrix = Vec::new();
-        
-        // Generate random round constants
-        for _ in 0..64 {
-            let mut constants = Vec::new();
-            for _ in 0..3 {
-                constants.push(Fr::rand(&mut rand::thread_rng()));
-            }
-            round_constants.push(constants);
-        }
-        
-        // Generate MDS matrix
-        for _ in 0..3 {
-            let mut row = Vec::new();
-            for _ in 0..3 {
-                row.push(Fr::rand(&mut rand::thread_rng()));
-            }
-            mds_matrix.push(row);
-        }
-        
-        PoseidonParameters {
-            round_constants,
-            mds_matrix,
-            num_rounds: 64,
-            partial_rounds: 8,
-        }
+        PoseidonParameters::circomlib().clone()
     }
 }
 
@@ -333,16 +444,36 @@ impl PoseidonHasher {
         PoseidonUtils::hash_utxo(value, owner, blinding_factor, &context)
     }
     
+    /// circomlib `poseidon([left, right])` over big-endian field elements
+    ///
+    /// Inputs are reduced modulo the BN254 scalar field.
+    pub fn hash2(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let hash = PoseidonParameters::circomlib().hash_two(
+            Fr::from_be_bytes_mod_order(left),
+            Fr::from_be_bytes_mod_order(right),
+        );
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&hash.into_bigint().to_bytes_be());
+        bytes
+    }
+    
+    /// Sponge hash of arbitrary bytes, packed 31 bytes per field element
+    pub fn hash_bytes(input: &[u8]) -> [u8; 32] {
+        let poseidon = PoseidonHash::new();
+        let elements: Vec<Fr> = input.chunks(31).map(Fr::from_be_bytes_mod_order).collect();
+        poseidon.field_element_to_bytes(poseidon.poseidon_hash(&elements, PoseidonHash::byte_capacity(input.len())))
+    }
+    
     /// Hash for Merkle tree nodes
     pub fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> CryptoResult<[u8; 32]> {
-        let context = CryptoContext::merkle_context();
-        PoseidonUtils::hash_merkle_node(left, right, &context)
+        Ok(Self::hash2(left, right))
     }
     
-    /// Hash for nullifiers
+    /// Hash for nullifiers: `poseidon([commitment, index])`
     pub fn nullifier(utxo_commitment: &[u8; 32], utxo_index: u64) -> CryptoResult<[u8; 32]> {
-        let context = CryptoContext::nullifier_context();
-        PoseidonUtils::hash_nullifier(utxo_commitment, utxo_index, &context)
+        let mut index = [0u8; 32];
+        index[24..].copy_from_slice(&utxo_index.to_be_bytes());
+        Ok(Self::hash2(utxo_commitment, &index))
     }
     
     /// Hash for commitments
@@ -364,8 +495,9 @@ mod tests {
         let hash1 = poseidon.hash(input).unwrap();
         let hash2 = poseidon.hash(input).unwrap();
         
-        // Should be deterministic
+        // Should be deterministic, across instances too
         assert_eq!(hash1, hash2);
+        assert_eq!(hash1, PoseidonHash::new().hash(input).unwrap());
         
         // Should be different for different inputs
         let different_input = b"Different input";
@@ -470,4 +602,68 @@ mod tests {
         // Should be deterministic
         assert_eq!(hash1, hash2);
     }
+
+    fn be(hex_str: &str) -> [u8; 32] {
+        hex::decode(hex_str).unwrap().try_into().unwrap()
+    }
+
+    fn element(value: u64) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        bytes[24..].copy_from_slice(&value.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_circomlib_parameters() {
+        let params = PoseidonParameters::circomlib();
+        assert_eq!(params.num_rounds, POSEIDON_FULL_ROUNDS + POSEIDON_PARTIAL_ROUNDS);
+        assert_eq!(params.partial_rounds, 57);
+
+        let bytes = |element: Fr| element.into_bigint().to_bytes_be();
+        let constants = &params.round_constants;
+        assert_eq!(bytes(constants[0][0]), be("0ee9a592ba9a9518d05986d656f40c2114c4993c11bb29938d21d47304cd8e6e"));
+        assert_eq!(
+            bytes(constants[constants.len() - 1][POSEIDON_WIDTH - 1]),
+            be("1da55cc900f0d21f4a3e694391918a1b3c23b2ac773c6b3ef88e2e4228325161"),
+        );
+        assert_eq!(bytes(params.mds_matrix[0][0]), be("109b7f411ba0e4c9b2b70caf5c36a7b194be7c11ad24378bfedb68592ba8118b"));
+        assert_eq!(bytes(params.mds_matrix[2][2]), be("19a3fc0a56702bf417ba7fee3802593fa644470307043f7773279cd71d25d5e0"));
+    }
+
+    #[test]
+    fn test_hash2_matches_circomlib() {
+        // circomlib poseidon([1, 2])
+        let expected = be("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a");
+        assert_eq!(PoseidonHasher::hash2(&element(1), &element(2)), expected);
+        assert_eq!(PoseidonHasher::merkle_node(&element(1), &element(2)).unwrap(), expected);
+        assert_eq!(PoseidonHasher::nullifier(&element(1), 2).unwrap(), expected);
+
+        // The padded byte sponge is kept apart from the two-element hash
+        let mut input = element(1).to_vec();
+        input.extend_from_slice(&element(2));
+        assert_ne!(PoseidonHasher::hash_bytes(&input), expected);
+    }
+
+    #[test]
+    fn test_byte_sponge_has_no_padding_collisions() {
+        let poseidon = PoseidonHash::new();
+        let hash = |input: &[u8]| poseidon.hash(input).unwrap();
+        
+        // Trailing zero chunk, trailing zero byte and leading zero byte
+        let chunk = [7u8; 31];
+        let mut chunk_and_zero = chunk.to_vec();
+        chunk_and_zero.push(0);
+        assert_ne!(hash(&chunk), hash(&chunk_and_zero));
+        assert_ne!(hash(&[7u8; 32]), hash(&[[7u8; 32].as_slice(), &[0u8; 30]].concat()));
+        assert_ne!(hash(&[0, 1]), hash(&[1]));
+        assert_ne!(hash(&[0]), hash(&[]));
+        
+        // The empty input no longer hashes to zero
+        assert_ne!(hash(&[]), [0u8; 32]);
+        
+        // Both byte entry points share the sponge
+        for input in [&[][..], &[1u8, 2, 3][..], &[9u8; 70][..]] {
+            assert_eq!(PoseidonHasher::hash_bytes(input), hash(input));
+        }
+    }
 }
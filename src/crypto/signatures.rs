@@ -289,6 +289,148 @@ impl SignatureScheme for EcdsaScheme {
     }
 }
 
+/// Signature algorithm used for operator signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SignatureAlgorithm {
+    /// Ed25519 (32-byte public key, 64-byte signature)
+    Ed25519,
+    /// ECDSA over secp256k1 (33-byte compressed public key, 64-byte compact signature)
+    Secp256k1,
+}
+
+impl SignatureAlgorithm {
+    /// Single-byte tag used when persisting the algorithm
+    pub fn to_byte(self) -> u8 {
+        match self {
+            SignatureAlgorithm::Ed25519 => 0x01,
+            SignatureAlgorithm::Secp256k1 => 0x02,
+        }
+    }
+    
+    /// Parse algorithm from its persisted tag
+    pub fn from_byte(tag: u8) -> CryptoResult<Self> {
+        match tag {
+            0x01 => Ok(SignatureAlgorithm::Ed25519),
+            0x02 => Ok(SignatureAlgorithm::Secp256k1),
+            other => Err(CryptoError::InvalidInput(format!("Unknown signature algorithm tag: {}", other))),
+        }
+    }
+}
+
+/// Operator keypair with a configurable signature algorithm
+#[derive(Clone)]
+pub struct OperatorKeypair {
+    /// Signature algorithm
+    algorithm: SignatureAlgorithm,
+    /// Raw 32-byte secret key
+    secret_key: [u8; 32],
+}
+
+impl OperatorKeypair {
+    /// Create keypair from raw secret key bytes
+    pub fn from_secret_bytes(algorithm: SignatureAlgorithm, secret_key: [u8; 32]) -> CryptoResult<Self> {
+        if algorithm == SignatureAlgorithm::Secp256k1 {
+            SecretKey::from_slice(&secret_key)
+                .map_err(|e| CryptoError::InvalidPrivateKey(format!("{:?}", e)))?;
+        }
+        
+        Ok(Self { algorithm, secret_key })
+    }
+    
+    /// Generate a random keypair
+    pub fn generate(algorithm: SignatureAlgorithm) -> CryptoResult<Self> {
+        match algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                let (signing_key, _) = Ed25519Scheme::generate_keypair()?;
+                Self::from_secret_bytes(algorithm, signing_key.to_bytes())
+            }
+            SignatureAlgorithm::Secp256k1 => {
+                let (secret_key, _) = EcdsaScheme::generate_keypair()?;
+                Self::from_secret_bytes(algorithm, secret_key.secret_bytes())
+            }
+        }
+    }
+    
+    /// Get the signature algorithm
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+    
+    /// Raw secret key bytes, for persisting the keypair
+    pub fn secret_bytes(&self) -> [u8; 32] {
+        self.secret_key
+    }
+    
+    /// Get the encoded public key
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        match self.algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                SigningKey::from_bytes(&self.secret_key).verifying_key().to_bytes().to_vec()
+            }
+            SignatureAlgorithm::Secp256k1 => {
+                let secp = Secp256k1::new();
+                // Validated in from_secret_bytes
+                let secret_key = SecretKey::from_slice(&self.secret_key)
+                    .expect("secp256k1 secret key validated on construction");
+                secret_key.public_key(&secp).serialize().to_vec()
+            }
+        }
+    }
+    
+    /// Sign a message, returning the encoded signature
+    pub fn sign(&self, message: &[u8]) -> CryptoResult<Vec<u8>> {
+        match self.algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                let signature = Ed25519Sig::sign_message(&self.secret_key, message)?;
+                Ok(signature.signature.to_bytes().to_vec())
+            }
+            SignatureAlgorithm::Secp256k1 => {
+                let signature = EcdsaSig::sign_message(&self.secret_key, message)?;
+                Ok(signature.signature.serialize_compact().to_vec())
+            }
+        }
+    }
+    
+    /// Verify an encoded signature against an encoded public key
+    pub fn verify(
+        algorithm: SignatureAlgorithm,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> CryptoResult<bool> {
+        match algorithm {
+            SignatureAlgorithm::Ed25519 => {
+                let key_bytes: [u8; 32] = public_key.try_into()
+                    .map_err(|_| CryptoError::InvalidPublicKey("Ed25519 public key must be 32 bytes".to_string()))?;
+                let sig_bytes: [u8; 64] = signature.try_into()
+                    .map_err(|_| CryptoError::InvalidSignature("Ed25519 signature must be 64 bytes".to_string()))?;
+                let public_key = VerifyingKey::from_bytes(&key_bytes)
+                    .map_err(|e| CryptoError::InvalidPublicKey(e.to_string()))?;
+                
+                Ok(public_key.verify(message, &Ed25519Signature::from_bytes(&sig_bytes)).is_ok())
+            }
+            SignatureAlgorithm::Secp256k1 => {
+                let public_key = PublicKey::from_slice(public_key)
+                    .map_err(|e| CryptoError::InvalidPublicKey(e.to_string()))?;
+                let signature = ecdsa::Signature::from_compact(signature)
+                    .map_err(|e| CryptoError::InvalidSignature(e.to_string()))?;
+                
+                EcdsaScheme::verify(&EcdsaSig::new(signature, public_key, 0), message, &public_key)
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for OperatorKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret key
+        f.debug_struct("OperatorKeypair")
+            .field("algorithm", &self.algorithm)
+            .field("public_key", &hex::encode(self.public_key_bytes()))
+            .finish()
+    }
+}
+
 /// Batch signature verification for performance
 pub struct BatchVerifier;
 
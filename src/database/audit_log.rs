@@ -0,0 +1,120 @@
+//! Hash-Chained Audit Log
+//!
+//! Every committed batch appends one entry to cf_audit_log in the same
+//! WriteBatch as the state it describes. Each entry commits to the hash of
+//! its predecessor, so rewriting or dropping any entry breaks every later
+//! link. The head (sequence and hash of the newest entry) is kept in
+//! cf_tree_metadata so a truncated tail is detected as well.
+
+use anyhow::{Result, anyhow};
+use sha3::{Digest, Keccak256};
+use crate::database::schema::{DatabaseManager, cf_names};
+use crate::canonical_spec::{cf_prefixes, domains};
+
+/// cf_tree_metadata key holding the audit log head: sequence(8) || entry_hash(32)
+pub const AUDIT_LOG_HEAD_KEY: &[u8] = b"audit_log_head";
+
+/// `prev_entry_hash` of the first entry
+pub const GENESIS_ENTRY_HASH: [u8; 32] = [0u8; 32];
+
+/// Audit log entry stored in cf_audit_log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub batch_id: u64,
+    pub prev_entry_hash: [u8; 32],
+    pub root_hash: [u8; 32],
+    pub timestamp: u64,
+    pub op_count: u32,
+}
+
+impl AuditEntry {
+    /// Serialize entry: batch_id(8) || prev_entry_hash(32) || root_hash(32) ||
+    /// timestamp(8) || op_count(4)
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut value = Vec::with_capacity(84);
+        value.extend_from_slice(&self.batch_id.to_be_bytes());
+        value.extend_from_slice(&self.prev_entry_hash);
+        value.extend_from_slice(&self.root_hash);
+        value.extend_from_slice(&self.timestamp.to_be_bytes());
+        value.extend_from_slice(&self.op_count.to_be_bytes());
+        value
+    }
+
+    /// Deserialize entry from cf_audit_log value
+    pub fn deserialize(value: &[u8]) -> Result<Self> {
+        if value.len() != 84 {
+            return Err(anyhow!("Audit log value has invalid length"));
+        }
+
+        Ok(Self {
+            batch_id: u64::from_be_bytes(value[0..8].try_into()?),
+            prev_entry_hash: value[8..40].try_into()?,
+            root_hash: value[40..72].try_into()?,
+            timestamp: u64::from_be_bytes(value[72..80].try_into()?),
+            op_count: u32::from_be_bytes(value[80..84].try_into()?),
+        })
+    }
+
+    /// Hash linking the next entry to this one
+    pub fn entry_hash(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(domains::AUDIT_ENTRY);
+        hasher.update(self.serialize());
+        hasher.finalize().into()
+    }
+}
+
+/// Create cf_audit_log key for an entry sequence number
+pub fn audit_log_key(sequence: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(9);
+    key.push(cf_prefixes::AUDIT_LOG);
+    key.extend_from_slice(&sequence.to_be_bytes());
+    key
+}
+
+/// Serialize the audit log head
+pub fn serialize_head(sequence: u64, entry_hash: [u8; 32]) -> Vec<u8> {
+    let mut value = Vec::with_capacity(40);
+    value.extend_from_slice(&sequence.to_be_bytes());
+    value.extend_from_slice(&entry_hash);
+    value
+}
+
+/// Load the audit log head, `None` before the first entry
+pub fn load_head(db: &DatabaseManager) -> Result<Option<(u64, [u8; 32])>> {
+    db.get_cf(cf_names::TREE_METADATA, AUDIT_LOG_HEAD_KEY)?
+        .map(|value| parse_head(&value))
+        .transpose()
+}
+
+/// Parse an audit log head value into `(sequence, entry_hash)`
+pub fn parse_head(value: &[u8]) -> Result<(u64, [u8; 32])> {
+    if value.len() != 40 {
+        return Err(anyhow!("Audit log head has invalid length"));
+    }
+    Ok((u64::from_be_bytes(value[0..8].try_into()?), value[8..40].try_into()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_entry_roundtrip() {
+        let entry = AuditEntry {
+            batch_id: 7,
+            prev_entry_hash: [0x11; 32],
+            root_hash: [0x22; 32],
+            timestamp: 1_700_000_000,
+            op_count: 5,
+        };
+
+        let decoded = AuditEntry::deserialize(&entry.serialize()).unwrap();
+        assert_eq!(decoded, entry);
+        assert_eq!(decoded.entry_hash(), entry.entry_hash());
+
+        let mut tampered = entry.clone();
+        tampered.op_count += 1;
+        assert_ne!(tampered.entry_hash(), entry.entry_hash());
+    }
+}
@@ -0,0 +1,78 @@
+//! Per-Root Asset Balance Snapshots
+//!
+//! cf_asset_balances only holds current per-owner aggregates. For audits the
+//! batch writer also keeps a running pool-wide total per asset in
+//! cf_tree_metadata and, on every `CommitRoot`, copies those totals into
+//! cf_root_history under a separate prefix keyed by root version. A snapshot
+//! header is written even when no asset has a balance yet, so "no entry"
+//! for an asset at a snapshotted version means a total of zero.
+
+use std::collections::BTreeMap;
+use anyhow::{Result, anyhow};
+use crate::database::schema::{DatabaseManager, cf_names};
+use crate::canonical_spec::cf_prefixes;
+
+/// cf_tree_metadata key holding the running totals: (asset_id(20) || total(16))*
+pub const ASSET_TOTALS_KEY: &[u8] = b"asset_totals";
+
+/// Running pool-wide total per asset
+pub type AssetTotals = BTreeMap<[u8; 20], u128>;
+
+/// Serialize totals in asset_id order
+pub fn serialize_asset_totals(totals: &AssetTotals) -> Vec<u8> {
+    let mut value = Vec::with_capacity(totals.len() * 36);
+    for (asset_id, total) in totals {
+        value.extend_from_slice(asset_id);
+        value.extend_from_slice(&total.to_be_bytes());
+    }
+    value
+}
+
+/// Deserialize totals from cf_tree_metadata value
+pub fn deserialize_asset_totals(value: &[u8]) -> Result<AssetTotals> {
+    if value.len() % 36 != 0 {
+        return Err(anyhow!("Asset totals value has invalid length"));
+    }
+
+    value.chunks_exact(36)
+        .map(|entry| Ok((entry[0..20].try_into()?, u128::from_be_bytes(entry[20..36].try_into()?))))
+        .collect()
+}
+
+/// Load running totals, defaulting to empty for a fresh database
+pub fn load_asset_totals(db: &DatabaseManager) -> Result<AssetTotals> {
+    db.get_cf(cf_names::TREE_METADATA, ASSET_TOTALS_KEY)?
+        .map(|value| deserialize_asset_totals(&value))
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+/// cf_root_history key marking that a snapshot exists for a root version
+pub fn balance_snapshot_header_key(root_version: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(9);
+    key.push(cf_prefixes::BALANCE_SNAPSHOTS);
+    key.extend_from_slice(&root_version.to_be_bytes());
+    key
+}
+
+/// cf_root_history key for one asset's total at a root version
+pub fn balance_snapshot_key(root_version: u64, asset_id: &[u8; 20]) -> Vec<u8> {
+    let mut key = balance_snapshot_header_key(root_version);
+    key.extend_from_slice(asset_id);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_totals_roundtrip() {
+        let mut totals = AssetTotals::new();
+        totals.insert([0u8; 20], 5_000);
+        totals.insert([7u8; 20], u128::MAX);
+
+        assert_eq!(deserialize_asset_totals(&serialize_asset_totals(&totals)).unwrap(), totals);
+        assert!(deserialize_asset_totals(&[0u8; 35]).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! Pipelined Batch Commits
+//!
+//! `AtomicBatchWriter::commit` reads shared aggregates (balances, ref counts,
+//! pool counters, audit head), so the write itself must stay serial. What
+//! can overlap is preparation: checking that every spent input still exists
+//! unspent and every new output is fresh. The pipeline runs those checks
+//! outside the commit lock and then commits batches one at a time.
+//!
+//! A batch's early checks only hold while no other batch touching the same
+//! UTXOs commits first. The conflict detector tracks the UTXO ids of every
+//! prepared-but-uncommitted batch: a batch prepared while an overlapping one
+//! is in flight, or overtaken by an overlapping commit, falls back to serial
+//! mode and is re-checked under the commit lock.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+use crate::canonical_spec::cf_prefixes;
+use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation, WriteBatchError};
+use crate::database::schema::{DatabaseManager, cf_names, utils};
+
+/// In-flight batches by ticket id: UTXO ids touched and whether their
+/// early checks went stale
+#[derive(Default)]
+struct InFlight {
+    next_ticket: u64,
+    batches: HashMap<u64, (HashSet<[u8; 32]>, bool)>,
+    serial_fallbacks: u64,
+}
+
+/// Prepares batches concurrently and commits them in sequence
+pub struct BatchPipeline {
+    db: DatabaseManager,
+    in_flight: Arc<Mutex<InFlight>>,
+    commit_lock: Mutex<()>,
+}
+
+/// A batch whose inputs and outputs have been claimed by the pipeline
+pub struct PreparedBatch {
+    ticket: u64,
+    writer: Option<AtomicBatchWriter>,
+    in_flight: Arc<Mutex<InFlight>>,
+}
+
+impl BatchPipeline {
+    /// Create a pipeline over `db`
+    ///
+    /// With `DBConfig::enable_batch_pipelining` off every batch is checked
+    /// under the commit lock.
+    pub fn new(db: DatabaseManager) -> Self {
+        Self {
+            db,
+            in_flight: Arc::new(Mutex::new(InFlight::default())),
+            commit_lock: Mutex::new(()),
+        }
+    }
+
+    /// Claim the batch's UTXOs and check them against committed state
+    ///
+    /// Safe to call from several threads at once.
+    pub fn prepare(&self, writer: AtomicBatchWriter) -> Result<PreparedBatch> {
+        let utxo_ids = touched_utxos(writer.operations());
+        let (ticket, serial) = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let serial = !self.db.config().enable_batch_pipelining
+                || in_flight.batches.values().any(|(ids, _)| !ids.is_disjoint(&utxo_ids));
+            let ticket = in_flight.next_ticket;
+            in_flight.next_ticket += 1;
+            in_flight.batches.insert(ticket, (utxo_ids, serial));
+            (ticket, serial)
+        };
+
+        let prepared = PreparedBatch {
+            ticket,
+            writer: Some(writer),
+            in_flight: self.in_flight.clone(),
+        };
+        if !serial {
+            check_inputs_and_outputs(&self.db, prepared.writer().operations())?;
+        }
+        Ok(prepared)
+    }
+
+    /// Commit a prepared batch after every batch that reached the commit lock before it
+    pub fn commit(&self, mut prepared: PreparedBatch) -> Result<()> {
+        let _sequence = self.commit_lock.lock().unwrap();
+
+        let serial = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            let serial = in_flight.batches.get(&prepared.ticket).is_none_or(|(_, serial)| *serial);
+            if serial {
+                in_flight.serial_fallbacks += 1;
+            }
+            serial
+        };
+        if serial {
+            check_inputs_and_outputs(&self.db, prepared.writer().operations())?;
+        }
+
+        let writer = prepared.writer.take().expect("prepared batch holds its writer until commit");
+        writer.commit()?;
+
+        // Overlapping batches prepared against the old state must re-check
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some((ids, _)) = in_flight.batches.remove(&prepared.ticket) {
+            for (other_ids, serial) in in_flight.batches.values_mut() {
+                if !other_ids.is_disjoint(&ids) {
+                    *serial = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Batches whose checks had to be redone under the commit lock
+    pub fn serial_fallbacks(&self) -> u64 {
+        self.in_flight.lock().unwrap().serial_fallbacks
+    }
+}
+
+impl PreparedBatch {
+    fn writer(&self) -> &AtomicBatchWriter {
+        self.writer.as_ref().expect("prepared batch holds its writer until commit")
+    }
+}
+
+impl Drop for PreparedBatch {
+    /// Release the claim of a batch that failed or was never committed
+    fn drop(&mut self) {
+        if let Ok(mut in_flight) = self.in_flight.lock() {
+            in_flight.batches.remove(&self.ticket);
+        }
+    }
+}
+
+/// UTXO ids a batch spends, deletes or creates
+fn touched_utxos(operations: &[BatchOperation]) -> HashSet<[u8; 32]> {
+    operations.iter().filter_map(|operation| match operation {
+        BatchOperation::MarkSpent { utxo_id, .. }
+        | BatchOperation::DeleteUTXO { utxo_id }
+        | BatchOperation::UpdateSMTLeaf { utxo_id, .. }
+        | BatchOperation::DeleteSMTLeaf { utxo_id }
+        | BatchOperation::InsertOwnerIndex { utxo_id, .. }
+        | BatchOperation::DeleteOwnerIndex { utxo_id, .. }
+        | BatchOperation::ReleaseInputLock { utxo_id } => Some(*utxo_id),
+        BatchOperation::InsertUTXO { utxo } => Some(utxo.utxo_id),
+        _ => None,
+    }).collect()
+}
+
+/// Spent inputs must exist unspent, new outputs must not exist yet and nullifiers must be fresh
+fn check_inputs_and_outputs(db: &DatabaseManager, operations: &[BatchOperation]) -> Result<()> {
+    for operation in operations {
+        match operation {
+            BatchOperation::MarkSpent { utxo_id, .. } => {
+                if db.get_cf(cf_names::UTXOS, &utils::utxo_key(utxo_id))?.is_none() {
+                    return Err(WriteBatchError::UTXONotFound(*utxo_id).into());
+                }
+                let spent_key = utils::create_key_with_prefix(cf_prefixes::SPENT_TRACKER, &[utxo_id]);
+                if db.get_cf(cf_names::SPENT_TRACKER, &spent_key)?.is_some() {
+                    return Err(WriteBatchError::UTXOAlreadySpent(*utxo_id).into());
+                }
+            },
+            BatchOperation::InsertUTXO { utxo } => {
+                if db.get_cf(cf_names::UTXOS, &utils::utxo_key(&utxo.utxo_id))?.is_some() {
+                    return Err(WriteBatchError::UTXOAlreadyExists(utxo.utxo_id).into());
+                }
+            },
+            BatchOperation::InsertNullifier { nullifier, .. } => {
+                if db.get_cf(cf_names::NULLIFIERS, &utils::nullifier_key(nullifier))?.is_some() {
+                    return Err(WriteBatchError::NullifierAlreadyUsed(*nullifier).into());
+                }
+            },
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use tempfile::tempdir;
+    use crate::database::schema::DBConfig;
+    use crate::database::PoolCounters;
+    use crate::utxo::CanonicalUTXO;
+
+    fn open_db(temp_dir: &tempfile::TempDir) -> DatabaseManager {
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        DatabaseManager::open(DBConfig { db_path, enable_batch_pipelining: true, ..Default::default() }).unwrap()
+    }
+
+    fn utxo(tag: u8) -> CanonicalUTXO {
+        CanonicalUTXO::new_eth([tag; 32], 0, 100, tag as u64, 1_000, [tag; 32])
+    }
+
+    fn spend_batch(db: &DatabaseManager, input: &CanonicalUTXO, output: &CanonicalUTXO) -> AtomicBatchWriter {
+        let mut writer = AtomicBatchWriter::new(db.clone());
+        writer.add_operation(BatchOperation::MarkSpent {
+            utxo_id: input.utxo_id,
+            spent_txid: [0xeeu8; 32],
+            spent_block: 200,
+            spent_timestamp: 1_700_000_000,
+        });
+        writer.add_operation(BatchOperation::DeleteUTXO { utxo_id: input.utxo_id });
+        writer.add_operation(BatchOperation::InsertUTXO { utxo: output.clone() });
+        writer
+    }
+
+    fn seed(db: &DatabaseManager, utxos: &[&CanonicalUTXO]) {
+        let mut writer = AtomicBatchWriter::new(db.clone());
+        for utxo in utxos {
+            writer.add_operation(BatchOperation::InsertUTXO { utxo: (*utxo).clone() });
+        }
+        writer.commit().unwrap();
+    }
+
+    #[test]
+    fn test_disjoint_batches_commit_concurrently() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        let (a, b) = (utxo(1), utxo(2));
+        seed(&db, &[&a, &b]);
+
+        let pipeline = BatchPipeline::new(db.clone());
+        let barrier = Barrier::new(2);
+        std::thread::scope(|scope| {
+            for (input, output) in [(&a, utxo(11)), (&b, utxo(12))] {
+                let (pipeline, barrier, db) = (&pipeline, &barrier, &db);
+                scope.spawn(move || {
+                    let prepared = pipeline.prepare(spend_batch(db, input, &output)).unwrap();
+                    // Both batches are in flight before either commits
+                    barrier.wait();
+                    pipeline.commit(prepared).unwrap();
+                });
+            }
+        });
+
+        assert_eq!(pipeline.serial_fallbacks(), 0);
+        for (spent, created) in [(&a, utxo(11)), (&b, utxo(12))] {
+            assert!(db.get_cf(cf_names::UTXOS, &utils::utxo_key(&spent.utxo_id)).unwrap().is_none());
+            assert!(db.get_cf(cf_names::UTXOS, &utils::utxo_key(&created.utxo_id)).unwrap().is_some());
+        }
+        let counters = PoolCounters::load(&db).unwrap();
+        assert_eq!((counters.total_utxos, counters.total_spent), (2, 2));
+    }
+
+    #[test]
+    fn test_overlapping_batches_fall_back_to_serial() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        let shared = utxo(1);
+        seed(&db, &[&shared]);
+        let pipeline = BatchPipeline::new(db.clone());
+
+        // The second spend of the same input is prepared while the first is in flight
+        let first = pipeline.prepare(spend_batch(&db, &shared, &utxo(11))).unwrap();
+        let second = pipeline.prepare(spend_batch(&db, &shared, &utxo(12))).unwrap();
+
+        // Committing out of order: the serial batch wins, the overtaken one is re-checked
+        pipeline.commit(second).unwrap();
+        let err = pipeline.commit(first).unwrap_err();
+        assert!(matches!(err.downcast_ref::<WriteBatchError>(), Some(WriteBatchError::UTXONotFound(_))));
+        assert_eq!(pipeline.serial_fallbacks(), 2);
+
+        assert!(db.get_cf(cf_names::UTXOS, &utils::utxo_key(&utxo(12).utxo_id)).unwrap().is_some());
+        assert!(db.get_cf(cf_names::UTXOS, &utils::utxo_key(&utxo(11).utxo_id)).unwrap().is_none());
+        let counters = PoolCounters::load(&db).unwrap();
+        assert_eq!((counters.total_utxos, counters.total_spent), (1, 1));
+
+        // The failed batch released its claim on utxo(11): recreating it is not a conflict
+        let third = pipeline.prepare(spend_batch(&db, &utxo(12), &utxo(11))).unwrap();
+        pipeline.commit(third).unwrap();
+        assert_eq!(pipeline.serial_fallbacks(), 2);
+    }
+
+    #[test]
+    fn test_dropped_batch_releases_claim() {
+        let temp_dir = tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        let shared = utxo(1);
+        seed(&db, &[&shared]);
+        let pipeline = BatchPipeline::new(db.clone());
+
+        // While claimed, an overlapping batch must fall back to serial
+        let abandoned = pipeline.prepare(spend_batch(&db, &shared, &utxo(11))).unwrap();
+        let overlapping = pipeline.prepare(spend_batch(&db, &shared, &utxo(12))).unwrap();
+        drop(overlapping);
+        assert_eq!(pipeline.serial_fallbacks(), 0);
+
+        // Once released, the same spend is prepared without conflict
+        drop(abandoned);
+        let retried = pipeline.prepare(spend_batch(&db, &shared, &utxo(12))).unwrap();
+        pipeline.commit(retried).unwrap();
+        assert_eq!(pipeline.serial_fallbacks(), 0);
+        assert!(db.get_cf(cf_names::UTXOS, &utils::utxo_key(&utxo(12).utxo_id)).unwrap().is_some());
+    }
+}
@@ -5,8 +5,21 @@
 
 use anyhow::{Result, anyhow, Context};
 use crate::database::schema::{DatabaseManager, cf_names};
+use crate::database::root_history::{RootRecord, root_history_key, root_index_key};
+use crate::database::pool_counters::{self, PoolCounters, FEES_COLLECTED_KEY, POOL_COUNTERS_KEY};
+use crate::database::audit_log::{self, AuditEntry, AUDIT_LOG_HEAD_KEY, GENESIS_ENTRY_HASH};
+use crate::database::balance_snapshots::{self, ASSET_TOTALS_KEY};
 use crate::canonical_spec::cf_prefixes;
 use crate::utxo::CanonicalUTXO;
+use sha2::{Digest, Sha256};
+
+/// `operation_type` codes recorded in cf_block_index
+pub mod block_operation_types {
+    /// A UTXO was inserted; undone by deleting it
+    pub const INSERT_UTXO: u8 = 0x01;
+    /// A UTXO was spent; undone by restoring its recorded prior state
+    pub const SPEND_UTXO: u8 = 0x02;
+}
 
 /// Batch operation types for atomic state transitions
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,6 +32,23 @@ pub enum BatchOperation {
         spent_timestamp: u64,
     },
     
+    /// Record a revealed nullifier (cf_nullifiers)
+    InsertNullifier {
+        nullifier: [u8; 32],
+        spent_txid: [u8; 32],
+        block: u64,
+    },
+    
+    /// Forget a nullifier whose spend was reorganized away (cf_nullifiers)
+    DeleteNullifier {
+        nullifier: [u8; 32],
+    },
+    
+    /// Clear a spend marker when the spend is reorganized away (cf_spent_tracker)
+    UnmarkSpent {
+        utxo_id: [u8; 32],
+    },
+    
     /// Delete spent UTXO (cf_utxos)
     DeleteUTXO {
         utxo_id: [u8; 32],
@@ -69,6 +99,12 @@ pub enum BatchOperation {
         flags: u8,
     },
     
+    /// Map a deposit commitment to the UTXO it minted (cf_deposit_commitments)
+    IndexDepositCommitment {
+        commitment: [u8; 32],
+        utxo_id: [u8; 32],
+    },
+    
     /// Delete owner index entry (cf_owner_index)
     DeleteOwnerIndex {
         owner_commitment: [u8; 32],
@@ -76,7 +112,7 @@ pub enum BatchOperation {
         utxo_id: [u8; 32],
     },
     
-    /// Commit new root (cf_root_history)
+    /// Commit new prepared root (cf_root_history)
     CommitRoot {
         root_version: u64,
         root_hash: [u8; 32],
@@ -86,6 +122,12 @@ pub enum BatchOperation {
         operator_signature: Vec<u8>,
     },
     
+    /// Drop a prepared root invalidated by a reorg (cf_root_history)
+    AbandonRoot {
+        root_version: u64,
+        root_hash: [u8; 32],
+    },
+    
     /// Release input lock (cf_input_locks)
     ReleaseInputLock {
         utxo_id: [u8; 32],
@@ -98,6 +140,11 @@ pub enum BatchOperation {
         txid: [u8; 32],
     },
     
+    /// Record a successfully applied transaction (cf_processed_txids)
+    RecordTxid {
+        txid: [u8; 32],
+    },
+    
     /// Record block operation (cf_block_index)
     RecordBlockOperation {
         block_number: u64,
@@ -106,7 +153,116 @@ pub enum BatchOperation {
         operation_type: u8,
         utxo_id: [u8; 32],
         prev_state_hash: [u8; 32],
+        /// Serialized state before the operation; empty if there was none
+        prev_state: Vec<u8>,
+    },
+    
+    /// Drop a block operation once it has been undone (cf_block_index)
+    DeleteBlockOperation {
+        block_number: u64,
+        tx_index: u32,
+        operation_id: [u8; 16],
+    },
+    
+    /// Count deposited value towards the pool counters (cf_tree_metadata)
+    RecordDeposit {
+        amount_wei: u128,
+    },
+    
+    /// Take a reorganized deposit back out of the pool counters (cf_tree_metadata)
+    RevertDeposit {
+        amount_wei: u128,
+    },
+    
+    /// Count a fee credited to the fee recipient (cf_tree_metadata)
+    RecordFee {
+        amount_wei: u128,
     },
+    
+    /// Write a metadata entry, such as an ingestion cursor (cf_tree_metadata)
+    PutMetadata {
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+}
+
+impl BatchOperation {
+    /// Record an operation in cf_block_index, hashing its prior state
+    pub fn record_block_operation(
+        block_number: u64,
+        tx_index: u32,
+        operation_type: u8,
+        utxo_id: [u8; 32],
+        prev_state: Vec<u8>,
+    ) -> Self {
+        let mut operation_id = [0u8; 16];
+        operation_id.copy_from_slice(&utxo_id[..16]);
+        
+        BatchOperation::RecordBlockOperation {
+            block_number,
+            tx_index,
+            operation_id,
+            operation_type,
+            utxo_id,
+            prev_state_hash: BlockOperationRecord::state_hash(&prev_state),
+            prev_state,
+        }
+    }
+}
+
+/// Entry read back from cf_block_index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockOperationRecord {
+    pub block_number: u64,
+    pub tx_index: u32,
+    pub operation_id: [u8; 16],
+    pub operation_type: u8,
+    pub utxo_id: [u8; 32],
+    pub prev_state_hash: [u8; 32],
+    pub prev_state: Vec<u8>,
+}
+
+impl BlockOperationRecord {
+    /// Hash committing to a prior state; all zeros when there was none
+    pub fn state_hash(prev_state: &[u8]) -> [u8; 32] {
+        if prev_state.is_empty() {
+            return [0u8; 32];
+        }
+        Sha256::digest(prev_state).into()
+    }
+
+    /// Parse a cf_block_index entry
+    ///
+    /// Key: prefix(1) || block_number(8) || tx_index(4) || operation_id(16)
+    /// Value: operation_type(1) || utxo_id(32) || prev_state_hash(32) || prev_state
+    pub fn parse(key: &[u8], value: &[u8]) -> Result<Self> {
+        if key.len() != 29 || key[0] != cf_prefixes::BLOCK_INDEX {
+            return Err(anyhow!("Invalid block index key"));
+        }
+        if value.len() < 65 {
+            return Err(anyhow!("Block index value too short"));
+        }
+        
+        let record = Self {
+            block_number: u64::from_be_bytes(key[1..9].try_into()?),
+            tx_index: u32::from_be_bytes(key[9..13].try_into()?),
+            operation_id: key[13..29].try_into()?,
+            operation_type: value[0],
+            utxo_id: value[1..33].try_into()?,
+            prev_state_hash: value[33..65].try_into()?,
+            prev_state: value[65..].to_vec(),
+        };
+        
+        if Self::state_hash(&record.prev_state) != record.prev_state_hash {
+            return Err(anyhow!(
+                "Prior state of block {} operation {} does not match its hash",
+                record.block_number,
+                record.tx_index
+            ));
+        }
+        
+        Ok(record)
+    }
 }
 
 /// Atomic batch writer with mandatory ordering
@@ -129,23 +285,38 @@ impl AtomicBatchWriter {
         self.operations.push(operation);
     }
 
+    /// Operations queued so far, in insertion order
+    pub fn operations(&self) -> &[BatchOperation] {
+        &self.operations
+    }
+
     /// Execute all operations atomically with mandatory ordering
     /// 
     /// CRITICAL: This order must NEVER be changed as it prevents deadlocks:
     /// 1. cf_spent_tracker (mark consumed UTXOs first)
-    /// 2. cf_utxos (delete spent, insert new)  
-    /// 3. cf_smt_nodes (decrement ref counts, insert new nodes)
-    /// 4. cf_smt_leaves (update tree leaf mappings)
-    /// 5. cf_asset_balances (update aggregated balances)
-    /// 6. cf_owner_index (update ownership indices)
-    /// 7. cf_root_history (commit new root)
-    /// 8. cf_input_locks (release consumed locks)
-    /// 9. cf_mempool (remove processed transactions)
-    /// 10. cf_block_index (record operations)
+    /// 2. cf_nullifiers (record revealed nullifiers, drop reorganized ones)
+    /// 3. cf_utxos (delete spent, insert new)  
+    /// 4. cf_smt_nodes (decrement ref counts, insert new nodes)
+    /// 5. cf_smt_leaves (update tree leaf mappings)
+    /// 6. cf_asset_balances (update aggregated balances)
+    /// 7. cf_owner_index, cf_deposit_commitments (update ownership and deposit indices)
+    /// 8. cf_root_history (commit new root)
+    /// 9. cf_input_locks (release consumed locks)
+    /// 10. cf_mempool (remove processed transactions)
+    /// 11. cf_block_index, cf_processed_txids (record operations and applied txids)
+    /// 12. cf_tree_metadata (update pool counters, write metadata entries)
+    /// 13. cf_audit_log (append hash-chained batch entry)
+    ///
+    /// Nullifiers sit with the spend markers, ahead of any UTXO deletion: a spend is only
+    /// ever visible together with the nullifier that forbids replaying it.
+    ///
+    /// Commits are serialized by the database commit guard, so the counters and
+    /// other entries read while building the batch cannot change before it is written.
     pub fn commit(self) -> Result<()> {
         if self.operations.is_empty() {
             return Ok(());
         }
+        let _commit = self.db.commit_guard();
 
         let mut batch = self.db.create_write_batch();
 
@@ -159,9 +330,34 @@ impl AtomicBatchWriter {
                 let cf = self.db.cf_handle(cf_names::SPENT_TRACKER)?;
                 batch.put_cf(cf, &key, &value);
             }
+            if let BatchOperation::UnmarkSpent { utxo_id } = operation {
+                let key = self.create_spent_tracker_key(utxo_id);
+                let cf = self.db.cf_handle(cf_names::SPENT_TRACKER)?;
+                batch.delete_cf(cf, &key);
+            }
+        }
+
+        // Phase 2: cf_nullifiers (record revealed nullifiers before touching UTXOs);
+        // a nullifier already stored, or revealed twice in the batch, fails the batch
+        let mut revealed = std::collections::HashSet::new();
+        for operation in &self.operations {
+            if let BatchOperation::InsertNullifier { nullifier, spent_txid, block } = operation {
+                let key = crate::database::schema::utils::nullifier_key(nullifier);
+                if !revealed.insert(*nullifier) || self.db.get_cf(cf_names::NULLIFIERS, &key)?.is_some() {
+                    return Err(WriteBatchError::NullifierAlreadyUsed(*nullifier).into());
+                }
+                let value = self.create_nullifier_value(*spent_txid, *block);
+                let cf = self.db.cf_handle(cf_names::NULLIFIERS)?;
+                batch.put_cf(cf, &key, &value);
+            }
+            if let BatchOperation::DeleteNullifier { nullifier } = operation {
+                let key = crate::database::schema::utils::nullifier_key(nullifier);
+                let cf = self.db.cf_handle(cf_names::NULLIFIERS)?;
+                batch.delete_cf(cf, &key);
+            }
         }
 
-        // Phase 2: cf_utxos (delete spent, insert new)
+        // Phase 3: cf_utxos (delete spent, insert new)
         for operation in &self.operations {
             match operation {
                 BatchOperation::DeleteUTXO { utxo_id } => {
@@ -180,7 +376,7 @@ impl AtomicBatchWriter {
             }
         }
 
-        // Phase 3: cf_smt_nodes (decrement ref counts, insert new nodes)
+        // Phase 4: cf_smt_nodes (decrement ref counts, insert new nodes)
         for operation in &self.operations {
             if let BatchOperation::UpdateSMTNode { 
                 node_hash, left_hash, right_hash, height, ref_count_delta 
@@ -227,7 +423,7 @@ impl AtomicBatchWriter {
             }
         }
 
-        // Phase 4: cf_smt_leaves (update tree leaf mappings)
+        // Phase 5: cf_smt_leaves (update tree leaf mappings)
         for operation in &self.operations {
             match operation {
                 BatchOperation::UpdateSMTLeaf { utxo_id, leaf_hash, tree_position } => {
@@ -245,7 +441,9 @@ impl AtomicBatchWriter {
             }
         }
 
-        // Phase 5: cf_asset_balances (update aggregated balances)
+        // Phase 6: cf_asset_balances (update aggregated balances)
+        let mut asset_totals = balance_snapshots::load_asset_totals(&self.db)?;
+        let mut asset_totals_changed = false;
         for operation in &self.operations {
             if let BatchOperation::UpdateAssetBalance { 
                 owner_commitment, asset_id, amount_delta, utxo_count_delta, last_updated_block 
@@ -255,29 +453,48 @@ impl AtomicBatchWriter {
                 
                 // Get current balance or create new
                 let (current_amount, current_count, _) = if let Some(existing_value) = self.db.get_cf(cf_names::ASSET_BALANCES, &key)? {
-                    self.parse_asset_balance_value(&existing_value)?
+                    parse_asset_balance_value(&existing_value)?
                 } else {
                     (0u128, 0u32, 0u64)
                 };
                 
+                // A wrapped balance means a miscomputed delta - fail the whole batch
+                let wrap_error = |negative: bool| -> WriteBatchError {
+                    if negative {
+                        WriteBatchError::BalanceUnderflow { owner_commitment: *owner_commitment, asset_id: *asset_id }
+                    } else {
+                        WriteBatchError::BalanceOverflow { owner_commitment: *owner_commitment, asset_id: *asset_id }
+                    }
+                };
+                
                 let new_amount = if *amount_delta < 0 {
-                    current_amount.saturating_sub((-amount_delta) as u128)
+                    current_amount.checked_sub(amount_delta.unsigned_abs())
                 } else {
-                    current_amount.saturating_add(*amount_delta as u128)
-                };
+                    current_amount.checked_add(*amount_delta as u128)
+                }.ok_or_else(|| wrap_error(*amount_delta < 0))?;
                 
                 let new_count = if *utxo_count_delta < 0 {
-                    current_count.saturating_sub((-utxo_count_delta) as u32)
+                    current_count.checked_sub(utxo_count_delta.unsigned_abs())
                 } else {
-                    current_count.saturating_add(*utxo_count_delta as u32)
-                };
+                    current_count.checked_add(*utxo_count_delta as u32)
+                }.ok_or_else(|| wrap_error(*utxo_count_delta < 0))?;
                 
                 let value = self.create_asset_balance_value(new_amount, new_count, *last_updated_block);
                 batch.put_cf(cf, &key, &value);
+                
+                // Saturate on decrement so stores that predate the totals keep working
+                let total = asset_totals.entry(*asset_id).or_insert(0);
+                *total = if *amount_delta < 0 {
+                    total.saturating_sub(amount_delta.unsigned_abs())
+                } else {
+                    total.checked_add(*amount_delta as u128)
+                        .ok_or(WriteBatchError::CounterOverflow("asset_totals"))?
+                };
+                asset_totals_changed = true;
             }
         }
 
-        // Phase 6: cf_owner_index (update ownership indices)
+        // Phase 7: cf_owner_index, cf_deposit_commitments (update ownership and deposit indices)
         for operation in &self.operations {
             match operation {
                 BatchOperation::InsertOwnerIndex { 
@@ -295,25 +512,53 @@ impl AtomicBatchWriter {
                     let cf = self.db.cf_handle(cf_names::OWNER_INDEX)?;
                     batch.delete_cf(cf, &key);
                 },
+                BatchOperation::IndexDepositCommitment { commitment, utxo_id } => {
+                    let key = crate::database::schema::utils::deposit_commitment_key(commitment);
+                    let cf = self.db.cf_handle(cf_names::DEPOSIT_COMMITMENTS)?;
+                    batch.put_cf(cf, &key, utxo_id);
+                },
                 _ => {}
             }
         }
 
-        // Phase 7: cf_root_history (commit new root)
+        // Phase 8: cf_root_history (abandon reorged roots, commit new root)
+        for operation in &self.operations {
+            if let BatchOperation::AbandonRoot { root_version, root_hash } = operation {
+                let cf = self.db.cf_handle(cf_names::ROOT_HISTORY)?;
+                batch.delete_cf(cf, &root_history_key(*root_version));
+                batch.delete_cf(cf, &root_index_key(root_hash, *root_version));
+                batch.delete_cf(cf, &balance_snapshots::balance_snapshot_header_key(*root_version));
+            }
+        }
         for operation in &self.operations {
             if let BatchOperation::CommitRoot { 
                 root_version, root_hash, batch_id, timestamp, tx_count, operator_signature 
             } = operation {
-                let key = self.create_root_history_key(*root_version);
-                let value = self.create_root_history_value(
-                    *root_hash, *batch_id, *timestamp, *tx_count, operator_signature
-                );
+                // Roots enter history as prepared; finalization happens after confirmations
+                let key = root_history_key(*root_version);
+                let value = RootRecord {
+                    root_hash: *root_hash,
+                    batch_id: *batch_id,
+                    timestamp: *timestamp,
+                    tx_count: *tx_count,
+                    operator_signature: operator_signature.clone(),
+                    finalized: false,
+                }.serialize();
                 let cf = self.db.cf_handle(cf_names::ROOT_HISTORY)?;
                 batch.put_cf(cf, &key, &value);
+                batch.put_cf(cf, &root_index_key(root_hash, *root_version), []);
+                
+                // Totals already include this batch's balance updates
+                if self.db.config().enable_balance_snapshots {
+                    batch.put_cf(cf, &balance_snapshots::balance_snapshot_header_key(*root_version), []);
+                    for (asset_id, total) in &asset_totals {
+                        batch.put_cf(cf, &balance_snapshots::balance_snapshot_key(*root_version, asset_id), total.to_be_bytes());
+                    }
+                }
             }
         }
 
-        // Phase 8: cf_input_locks (release consumed locks)
+        // Phase 9: cf_input_locks (release consumed locks)
         for operation in &self.operations {
             if let BatchOperation::ReleaseInputLock { utxo_id } = operation {
                 let key = self.create_input_lock_key(utxo_id);
@@ -322,7 +567,7 @@ impl AtomicBatchWriter {
             }
         }
 
-        // Phase 9: cf_mempool (remove processed transactions)
+        // Phase 10: cf_mempool (remove processed transactions)
         for operation in &self.operations {
             if let BatchOperation::RemoveFromMempool { priority, fee_rate, txid } = operation {
                 let key = self.create_mempool_key(*priority, *fee_rate, txid);
@@ -331,18 +576,143 @@ impl AtomicBatchWriter {
             }
         }
 
-        // Phase 10: cf_block_index (record operations)
+        // Phase 11: cf_block_index, cf_processed_txids (record operations and applied txids)
         for operation in &self.operations {
-            if let BatchOperation::RecordBlockOperation { 
-                block_number, tx_index, operation_id, operation_type, utxo_id, prev_state_hash 
-            } = operation {
-                let key = self.create_block_index_key(*block_number, *tx_index, operation_id);
-                let value = self.create_block_index_value(*operation_type, utxo_id, prev_state_hash);
-                let cf = self.db.cf_handle(cf_names::BLOCK_INDEX)?;
-                batch.put_cf(cf, &key, &value);
+            match operation {
+                BatchOperation::RecordBlockOperation { 
+                    block_number, tx_index, operation_id, operation_type, utxo_id, prev_state_hash, prev_state 
+                } => {
+                    let key = self.create_block_index_key(*block_number, *tx_index, operation_id);
+                    let value = self.create_block_index_value(*operation_type, utxo_id, prev_state_hash, prev_state);
+                    let cf = self.db.cf_handle(cf_names::BLOCK_INDEX)?;
+                    batch.put_cf(cf, &key, &value);
+                },
+                BatchOperation::DeleteBlockOperation { block_number, tx_index, operation_id } => {
+                    let key = self.create_block_index_key(*block_number, *tx_index, operation_id);
+                    let cf = self.db.cf_handle(cf_names::BLOCK_INDEX)?;
+                    batch.delete_cf(cf, &key);
+                },
+                _ => {}
             }
         }
 
+        // A txid already stored, or recorded twice in the batch, fails the batch
+        let mut recorded = std::collections::HashSet::new();
+        for operation in &self.operations {
+            if let BatchOperation::RecordTxid { txid } = operation {
+                let key = crate::database::schema::utils::processed_txid_key(txid);
+                if !recorded.insert(*txid) || self.db.get_cf(cf_names::PROCESSED_TXIDS, &key)?.is_some() {
+                    return Err(WriteBatchError::TransactionAlreadyProcessed(*txid).into());
+                }
+                let cf = self.db.cf_handle(cf_names::PROCESSED_TXIDS)?;
+                batch.put_cf(cf, &key, &[]);
+            }
+        }
+
+        // Phase 12: cf_tree_metadata (update pool counters)
+        let mut utxos_added = 0u64;
+        let mut utxos_deleted = 0u64;
+        let mut spent = 0u64;
+        let mut unspent = 0u64;
+        let mut deposited_wei = 0u128;
+        let mut reverted_wei = 0u128;
+        let mut fees_wei = 0u128;
+        for operation in &self.operations {
+            match operation {
+                BatchOperation::InsertUTXO { .. } => utxos_added += 1,
+                BatchOperation::DeleteUTXO { .. } => utxos_deleted += 1,
+                BatchOperation::MarkSpent { .. } => spent += 1,
+                BatchOperation::UnmarkSpent { .. } => unspent += 1,
+                BatchOperation::RecordDeposit { amount_wei } => {
+                    deposited_wei = deposited_wei.checked_add(*amount_wei)
+                        .ok_or(WriteBatchError::CounterOverflow("total_deposited_wei"))?;
+                },
+                BatchOperation::RevertDeposit { amount_wei } => {
+                    reverted_wei = reverted_wei.saturating_add(*amount_wei);
+                },
+                BatchOperation::RecordFee { amount_wei } => {
+                    fees_wei = fees_wei.checked_add(*amount_wei)
+                        .ok_or(WriteBatchError::CounterOverflow("fees_collected"))?;
+                },
+                _ => {}
+            }
+        }
+        
+        if utxos_added + utxos_deleted + spent + unspent > 0 || deposited_wei > 0 || reverted_wei > 0 {
+            let mut counters = PoolCounters::load(&self.db)?;
+            // Saturate on delete so stores that predate the counters keep working
+            counters.total_utxos = counters.total_utxos.checked_add(utxos_added)
+                .ok_or(WriteBatchError::CounterOverflow("total_utxos"))?
+                .saturating_sub(utxos_deleted);
+            counters.total_spent = counters.total_spent.checked_add(spent)
+                .ok_or(WriteBatchError::CounterOverflow("total_spent"))?
+                .saturating_sub(unspent);
+            counters.total_deposited_wei = counters.total_deposited_wei.checked_add(deposited_wei)
+                .ok_or(WriteBatchError::CounterOverflow("total_deposited_wei"))?
+                .saturating_sub(reverted_wei);
+            
+            let cf = self.db.cf_handle(cf_names::TREE_METADATA)?;
+            batch.put_cf(cf, POOL_COUNTERS_KEY, &counters.serialize());
+        }
+        
+        if fees_wei > 0 {
+            let fees_collected = pool_counters::load_fees_collected(&self.db)?.checked_add(fees_wei)
+                .ok_or(WriteBatchError::CounterOverflow("fees_collected"))?;
+            let cf = self.db.cf_handle(cf_names::TREE_METADATA)?;
+            batch.put_cf(cf, FEES_COLLECTED_KEY, fees_collected.to_be_bytes());
+        }
+        
+        if asset_totals_changed {
+            let cf = self.db.cf_handle(cf_names::TREE_METADATA)?;
+            batch.put_cf(cf, ASSET_TOTALS_KEY, &balance_snapshots::serialize_asset_totals(&asset_totals));
+        }
+        
+        for operation in &self.operations {
+            if let BatchOperation::PutMetadata { key, value } = operation {
+                let cf = self.db.cf_handle(cf_names::TREE_METADATA)?;
+                batch.put_cf(cf, key, value);
+            }
+        }
+
+        // Phase 13: cf_audit_log (append hash-chained batch entry)
+        if self.db.config().enable_audit_log {
+            let head = audit_log::load_head(&self.db)?;
+            let (sequence, prev_entry_hash, prev_root) = match head {
+                Some((last_sequence, last_hash)) => {
+                    let last_entry = self.db.get_cf(cf_names::AUDIT_LOG, &audit_log::audit_log_key(last_sequence))?
+                        .ok_or_else(|| anyhow!("Audit log entry {} missing", last_sequence))?;
+                    (last_sequence + 1, last_hash, AuditEntry::deserialize(&last_entry)?.root_hash)
+                },
+                None => (0, GENESIS_ENTRY_HASH, [0u8; 32]),
+            };
+            
+            // Batches without a new root carry the previous root forward
+            let committed_root = self.operations.iter().rev().find_map(|operation| match operation {
+                BatchOperation::CommitRoot { root_hash, batch_id, timestamp, .. } => Some((*root_hash, *batch_id, *timestamp)),
+                _ => None,
+            });
+            let (root_hash, batch_id, timestamp) = committed_root.unwrap_or_else(|| (
+                prev_root,
+                sequence,
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+            ));
+            
+            let entry = AuditEntry {
+                batch_id,
+                prev_entry_hash,
+                root_hash,
+                timestamp,
+                op_count: self.operations.len() as u32,
+            };
+            let audit_cf = self.db.cf_handle(cf_names::AUDIT_LOG)?;
+            batch.put_cf(audit_cf, &audit_log::audit_log_key(sequence), &entry.serialize());
+            let metadata_cf = self.db.cf_handle(cf_names::TREE_METADATA)?;
+            batch.put_cf(metadata_cf, AUDIT_LOG_HEAD_KEY, &audit_log::serialize_head(sequence, entry.entry_hash()));
+        }
+
         // Execute atomic write batch
         self.db.write_batch(batch)
             .context("Failed to execute atomic write batch")?;
@@ -396,13 +766,6 @@ impl AtomicBatchWriter {
         key
     }
 
-    fn create_root_history_key(&self, root_version: u64) -> Vec<u8> {
-        let mut key = Vec::with_capacity(9);
-        key.push(cf_prefixes::ROOT_HISTORY);
-        key.extend_from_slice(&root_version.to_be_bytes());
-        key
-    }
-
     fn create_input_lock_key(&self, utxo_id: &[u8; 32]) -> Vec<u8> {
         let mut key = Vec::with_capacity(33);
         key.push(cf_prefixes::INPUT_LOCKS);
@@ -437,6 +800,13 @@ impl AtomicBatchWriter {
         value
     }
 
+    fn create_nullifier_value(&self, spent_txid: [u8; 32], block: u64) -> Vec<u8> {
+        let mut value = Vec::with_capacity(40);
+        value.extend_from_slice(&spent_txid);
+        value.extend_from_slice(&block.to_be_bytes());
+        value
+    }
+
     fn create_smt_node_value(&self, left_hash: [u8; 32], right_hash: [u8; 32], height: u8, ref_count: u32) -> Vec<u8> {
         let mut value = Vec::with_capacity(69);
         value.extend_from_slice(&left_hash);
@@ -469,22 +839,12 @@ impl AtomicBatchWriter {
         value
     }
 
-    fn create_root_history_value(&self, root_hash: [u8; 32], batch_id: u64, timestamp: u64, tx_count: u32, operator_signature: &[u8]) -> Vec<u8> {
-        let mut value = Vec::with_capacity(54 + operator_signature.len());
-        value.extend_from_slice(&root_hash);
-        value.extend_from_slice(&batch_id.to_be_bytes());
-        value.extend_from_slice(&timestamp.to_be_bytes());
-        value.extend_from_slice(&tx_count.to_be_bytes());
-        value.extend_from_slice(&(operator_signature.len() as u16).to_be_bytes());
-        value.extend_from_slice(operator_signature);
-        value
-    }
-
-    fn create_block_index_value(&self, operation_type: u8, utxo_id: &[u8; 32], prev_state_hash: &[u8; 32]) -> Vec<u8> {
-        let mut value = Vec::with_capacity(65);
+    fn create_block_index_value(&self, operation_type: u8, utxo_id: &[u8; 32], prev_state_hash: &[u8; 32], prev_state: &[u8]) -> Vec<u8> {
+        let mut value = Vec::with_capacity(65 + prev_state.len());
         value.push(operation_type);
         value.extend_from_slice(utxo_id);
         value.extend_from_slice(prev_state_hash);
+        value.extend_from_slice(prev_state);
         value
     }
 
@@ -498,24 +858,26 @@ impl AtomicBatchWriter {
         Ok(u32::from_be_bytes(ref_count_bytes))
     }
 
-    fn parse_asset_balance_value(&self, value: &[u8]) -> Result<(u128, u32, u64)> {
-        if value.len() < 28 {
-            return Err(anyhow!("Asset balance value too short"));
-        }
-        
-        let amount_bytes: [u8; 16] = value[0..16].try_into()
-            .map_err(|_| anyhow!("Invalid amount bytes"))?;
-        let count_bytes: [u8; 4] = value[16..20].try_into()
-            .map_err(|_| anyhow!("Invalid count bytes"))?;
-        let block_bytes: [u8; 8] = value[20..28].try_into()
-            .map_err(|_| anyhow!("Invalid block bytes"))?;
-        
-        Ok((
-            u128::from_be_bytes(amount_bytes),
-            u32::from_be_bytes(count_bytes),
-            u64::from_be_bytes(block_bytes),
-        ))
+}
+
+/// Parse a cf_asset_balances value into `(total_amount, utxo_count, last_updated_block)`
+pub fn parse_asset_balance_value(value: &[u8]) -> Result<(u128, u32, u64)> {
+    if value.len() < 28 {
+        return Err(anyhow!("Asset balance value too short"));
     }
+    
+    let amount_bytes: [u8; 16] = value[0..16].try_into()
+        .map_err(|_| anyhow!("Invalid amount bytes"))?;
+    let count_bytes: [u8; 4] = value[16..20].try_into()
+        .map_err(|_| anyhow!("Invalid count bytes"))?;
+    let block_bytes: [u8; 8] = value[20..28].try_into()
+        .map_err(|_| anyhow!("Invalid block bytes"))?;
+    
+    Ok((
+        u128::from_be_bytes(amount_bytes),
+        u32::from_be_bytes(count_bytes),
+        u64::from_be_bytes(block_bytes),
+    ))
 }
 
 /// Errors that can occur during batch writing
@@ -535,6 +897,36 @@ pub enum WriteBatchError {
     
     #[error("Missing required operation: {0}")]
     MissingOperation(String),
+    
+    #[error("Asset balance overflow for owner {owner_commitment:?}, asset {asset_id:?}")]
+    BalanceOverflow {
+        owner_commitment: [u8; 32],
+        asset_id: [u8; 20],
+    },
+    
+    #[error("Asset balance underflow for owner {owner_commitment:?}, asset {asset_id:?}")]
+    BalanceUnderflow {
+        owner_commitment: [u8; 32],
+        asset_id: [u8; 20],
+    },
+    
+    #[error("Pool counter overflow: {0}")]
+    CounterOverflow(&'static str),
+    
+    #[error("UTXO not found: {0:?}")]
+    UTXONotFound([u8; 32]),
+    
+    #[error("UTXO already spent: {0:?}")]
+    UTXOAlreadySpent([u8; 32]),
+    
+    #[error("UTXO already exists: {0:?}")]
+    UTXOAlreadyExists([u8; 32]),
+    
+    #[error("Nullifier already used: {0:?}")]
+    NullifierAlreadyUsed([u8; 32]),
+    
+    #[error("Transaction already processed: {0:?}")]
+    TransactionAlreadyProcessed([u8; 32]),
 }
 
 #[cfg(test)]
@@ -579,4 +971,149 @@ mod tests {
         assert_eq!(key[0], cf_prefixes::UTXOS);
         assert_eq!(&key[1..], &utxo_id[..]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_concurrent_commits_keep_every_counter_update() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        
+        let writers: Vec<_> = (0..8).map(|_| {
+            let db_manager = db_manager.clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+                    batch_writer.add_operation(BatchOperation::RecordDeposit { amount_wei: 1 });
+                    batch_writer.commit().unwrap();
+                }
+            })
+        }).collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        
+        assert_eq!(PoolCounters::load(&db_manager).unwrap().total_deposited_wei, 200);
+    }
+
+    #[test]
+    fn test_insert_nullifier_key_layout() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        
+        let nullifier = [7u8; 32];
+        let key = crate::database::schema::utils::nullifier_key(&nullifier);
+        assert_eq!(key.len(), 33);
+        assert_eq!(key[0], cf_prefixes::NULLIFIERS);
+        assert_eq!(&key[1..], &nullifier[..]);
+        
+        let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+        batch_writer.add_operation(BatchOperation::InsertNullifier { nullifier, spent_txid: [8u8; 32], block: 42 });
+        batch_writer.commit().unwrap();
+        
+        let value = db_manager.get_cf(cf_names::NULLIFIERS, &key).unwrap().unwrap();
+        assert_eq!(&value[..32], &[8u8; 32]);
+        assert_eq!(u64::from_be_bytes(value[32..40].try_into().unwrap()), 42);
+        
+        // A stored nullifier cannot be recorded again
+        let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+        batch_writer.add_operation(BatchOperation::InsertNullifier { nullifier, spent_txid: [9u8; 32], block: 43 });
+        let error = batch_writer.commit().unwrap_err();
+        assert!(matches!(error.downcast_ref::<WriteBatchError>(), Some(WriteBatchError::NullifierAlreadyUsed(n)) if *n == nullifier));
+        
+        // Nor revealed twice in one batch, which then writes nothing
+        let fresh = [10u8; 32];
+        let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+        batch_writer.add_operation(BatchOperation::InsertNullifier { nullifier: fresh, spent_txid: [9u8; 32], block: 43 });
+        batch_writer.add_operation(BatchOperation::InsertNullifier { nullifier: fresh, spent_txid: [9u8; 32], block: 43 });
+        assert!(batch_writer.commit().is_err());
+        assert!(db_manager.get_cf(cf_names::NULLIFIERS, &crate::database::schema::utils::nullifier_key(&fresh)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_key_builders_follow_endianness_policy() {
+        use crate::canonical_spec::endianness::{check_field, SENTINEL};
+        
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let batch_writer = AtomicBatchWriter::new(db_manager);
+        
+        let mempool_key = batch_writer.create_mempool_key(1, SENTINEL as u64, &[0u8; 32]);
+        check_field("key.mempool.fee_rate", &mempool_key, 2, 8).unwrap();
+        
+        let block_index_key = batch_writer.create_block_index_key(SENTINEL as u64, SENTINEL as u32, &[0u8; 16]);
+        check_field("key.block_index.block_number", &block_index_key, 1, 8).unwrap();
+        check_field("key.block_index.tx_index", &block_index_key, 9, 4).unwrap();
+        
+        let owner_index_key = batch_writer.create_owner_index_key(&[0u8; 32], SENTINEL as u64, &[0u8; 32]);
+        check_field("key.owner_index.created_block", &owner_index_key, 33, 8).unwrap();
+    }
+
+    fn balance_update(amount_delta: i128, utxo_count_delta: i32) -> BatchOperation {
+        BatchOperation::UpdateAssetBalance {
+            owner_commitment: [3u8; 32],
+            asset_id: [0u8; 20],
+            amount_delta,
+            utxo_count_delta,
+            last_updated_block: 1,
+        }
+    }
+
+    fn commit_balance_update(db: &DatabaseManager, amount_delta: i128, utxo_count_delta: i32) -> Result<()> {
+        let mut batch_writer = AtomicBatchWriter::new(db.clone());
+        batch_writer.add_operation(balance_update(amount_delta, utxo_count_delta));
+        batch_writer.commit()
+    }
+
+    #[test]
+    fn test_asset_balance_overflow_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        
+        // 2 * i128::MAX == u128::MAX - 1, so one more unit of 2 wraps
+        commit_balance_update(&db_manager, i128::MAX, 1).unwrap();
+        commit_balance_update(&db_manager, i128::MAX, 1).unwrap();
+        
+        let err = commit_balance_update(&db_manager, 2, 1).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<WriteBatchError>(),
+            Some(WriteBatchError::BalanceOverflow { .. })
+        ));
+    }
+
+    #[test]
+    fn test_asset_balance_underflow_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        commit_balance_update(&db_manager, 100, 1).unwrap();
+        
+        let err = commit_balance_update(&db_manager, -101, -1).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<WriteBatchError>(),
+            Some(WriteBatchError::BalanceUnderflow { .. })
+        ));
+        
+        // The failed batch must not have clamped the stored balance to zero
+        let batch_writer = AtomicBatchWriter::new(db_manager.clone());
+        let key = batch_writer.create_asset_balance_key(&[3u8; 32], &[0u8; 20]);
+        let value = db_manager.get_cf(cf_names::ASSET_BALANCES, &key).unwrap().unwrap();
+        let (amount, count, _) = parse_asset_balance_value(&value).unwrap();
+        assert_eq!(amount, 100);
+        assert_eq!(count, 1);
+    }
+}
@@ -5,11 +5,20 @@
 
 pub mod schema;
 pub mod batch_writer;
+pub mod batch_pipeline;
 pub mod query_engine;
 pub mod cache_manager;
+pub mod root_history;
+pub mod pool_counters;
+pub mod audit_log;
+pub mod balance_snapshots;
 
 // Re-export main types
-pub use schema::{DatabaseManager, DBConfig};
-pub use batch_writer::{AtomicBatchWriter, BatchOperation, WriteBatchError};
-pub use query_engine::{QueryEngine, QueryResult, QueryError};
-pub use cache_manager::{CacheManager, CacheConfig, CacheStats};
\ No newline at end of file
+pub use schema::{DatabaseManager, DBConfig, DbSnapshot};
+pub use batch_writer::{AtomicBatchWriter, BatchOperation, BlockOperationRecord, WriteBatchError};
+pub use batch_pipeline::{BatchPipeline, PreparedBatch};
+pub use query_engine::{OwnerUtxoCursor, OwnerUtxoPage, QueryEngine, QueryResult, QueryError};
+pub use cache_manager::{CacheManager, CacheConfig, CacheStats};
+pub use root_history::{RootExpired, RootHistory, RootRecord, UnsafeRootPrune};
+pub use pool_counters::PoolCounters;
+pub use audit_log::AuditEntry;
\ No newline at end of file
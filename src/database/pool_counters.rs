@@ -0,0 +1,84 @@
+//! Persistent Pool Counters
+//!
+//! Aggregate statistics kept in cf_tree_metadata and updated by the atomic
+//! batch writer in the same WriteBatch as the state they describe, so stats
+//! can be served in O(1) and survive restarts.
+
+use anyhow::{Result, anyhow};
+use crate::database::schema::{DatabaseManager, cf_names};
+
+/// cf_tree_metadata key holding the pool counters
+pub const POOL_COUNTERS_KEY: &[u8] = b"pool_counters";
+
+/// cf_tree_metadata key holding the total fees credited to the fee recipient
+/// (16 bytes BE)
+pub const FEES_COLLECTED_KEY: &[u8] = b"fees_collected";
+
+/// Aggregate pool statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolCounters {
+    /// Unspent UTXOs currently in cf_utxos
+    pub total_utxos: u64,
+    /// UTXOs marked spent since genesis
+    pub total_spent: u64,
+    /// Wei deposited since genesis
+    pub total_deposited_wei: u128,
+}
+
+impl PoolCounters {
+    /// Serialize counters: total_utxos(8) || total_spent(8) || total_deposited_wei(16)
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut value = Vec::with_capacity(32);
+        value.extend_from_slice(&self.total_utxos.to_be_bytes());
+        value.extend_from_slice(&self.total_spent.to_be_bytes());
+        value.extend_from_slice(&self.total_deposited_wei.to_be_bytes());
+        value
+    }
+
+    /// Deserialize counters from cf_tree_metadata value
+    pub fn deserialize(value: &[u8]) -> Result<Self> {
+        if value.len() != 32 {
+            return Err(anyhow!("Pool counters value has invalid length"));
+        }
+
+        Ok(Self {
+            total_utxos: u64::from_be_bytes(value[0..8].try_into()?),
+            total_spent: u64::from_be_bytes(value[8..16].try_into()?),
+            total_deposited_wei: u128::from_be_bytes(value[16..32].try_into()?),
+        })
+    }
+
+    /// Load counters, defaulting to zero for a fresh database
+    pub fn load(db: &DatabaseManager) -> Result<Self> {
+        db.get_cf(cf_names::TREE_METADATA, POOL_COUNTERS_KEY)?
+            .map(|value| Self::deserialize(&value))
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+}
+
+/// Load the collected fee total, defaulting to zero for a fresh database
+pub fn load_fees_collected(db: &DatabaseManager) -> Result<u128> {
+    match db.get_cf(cf_names::TREE_METADATA, FEES_COLLECTED_KEY)? {
+        Some(value) => Ok(u128::from_be_bytes(value.as_slice().try_into()
+            .map_err(|_| anyhow!("Fees collected value has invalid length"))?)),
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_counters_roundtrip() {
+        let counters = PoolCounters {
+            total_utxos: 3,
+            total_spent: 1,
+            total_deposited_wei: 4_000_000_000_000_000_000,
+        };
+
+        assert_eq!(PoolCounters::deserialize(&counters.serialize()).unwrap(), counters);
+        assert!(PoolCounters::deserialize(&[0u8; 31]).is_err());
+    }
+}
@@ -5,6 +5,9 @@
 
 use anyhow::Result;
 use crate::database::schema::{DatabaseManager, cf_names};
+use crate::database::audit_log::{self, AuditEntry, GENESIS_ENTRY_HASH};
+use crate::database::balance_snapshots;
+use crate::database::batch_writer::parse_asset_balance_value;
 use crate::canonical_spec::cf_prefixes;
 use crate::utxo::CanonicalUTXO;
 
@@ -47,6 +50,22 @@ pub enum QueryError {
     Serialization(String),
 }
 
+/// Position of the last UTXO returned by an owner listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnerUtxoCursor {
+    pub created_block: u64,
+    pub utxo_id: [u8; 32],
+}
+
+/// One page of an owner's UTXOs in `(created_block, utxo_id)` order
+#[derive(Debug, Clone)]
+pub struct OwnerUtxoPage {
+    pub utxos: Vec<CanonicalUTXO>,
+    
+    /// Where the next page starts; `None` once the listing is exhausted
+    pub next_cursor: Option<OwnerUtxoCursor>,
+}
+
 /// High-performance query engine
 pub struct QueryEngine {
     db: DatabaseManager,
@@ -120,28 +139,124 @@ impl QueryEngine {
         Ok(QueryResult::UTXOList(utxos))
     }
 
+    /// List an owner's UTXOs created after `after_block`, up to `limit`
+    /// 
+    /// Seeks straight to the first block past `after_block` in cf_owner_index
+    /// and reads at most one page of entries. Continue with
+    /// [`list_owner_utxos_from`](Self::list_owner_utxos_from) and the
+    /// returned cursor, which resumes inside a block if the page ended there.
+    pub fn list_owner_utxos(
+        &self,
+        owner_commitment: &[u8; 32],
+        after_block: Option<u64>,
+        limit: usize,
+    ) -> Result<OwnerUtxoPage, QueryError> {
+        let start_key = match after_block {
+            Some(block) => match block.checked_add(1) {
+                Some(start_block) => self.create_owner_index_start_key(owner_commitment, start_block),
+                None => return Ok(OwnerUtxoPage { utxos: Vec::new(), next_cursor: None }),
+            },
+            None => self.create_owner_index_prefix(owner_commitment),
+        };
+        
+        self.scan_owner_utxos(owner_commitment, &start_key, limit)
+    }
+
+    /// Continue an owner listing after `cursor`
+    pub fn list_owner_utxos_from(
+        &self,
+        owner_commitment: &[u8; 32],
+        cursor: &OwnerUtxoCursor,
+        limit: usize,
+    ) -> Result<OwnerUtxoPage, QueryError> {
+        // A trailing zero byte sorts directly after the cursor's own entry
+        let mut start_key = self.create_owner_index_start_key(owner_commitment, cursor.created_block);
+        start_key.extend_from_slice(&cursor.utxo_id);
+        start_key.push(0);
+        
+        self.scan_owner_utxos(owner_commitment, &start_key, limit)
+    }
+
+    /// Read up to `limit` live UTXOs from `start_key` within the owner's prefix
+    fn scan_owner_utxos(
+        &self,
+        owner_commitment: &[u8; 32],
+        start_key: &[u8],
+        limit: usize,
+    ) -> Result<OwnerUtxoPage, QueryError> {
+        if limit == 0 {
+            return Err(QueryError::InvalidParameters("limit must be at least 1".to_string()));
+        }
+        
+        let prefix = self.create_owner_index_prefix(owner_commitment);
+        let mut utxos = Vec::with_capacity(limit.min(1024));
+        let mut next_cursor = None;
+        
+        // Index and UTXO reads share a snapshot so a concurrent spend cannot tear them
+        let snapshot = self.db.snapshot();
+        for item in snapshot.prefix_iterator_cf(cf_names::OWNER_INDEX, start_key)? {
+            let (key, _) = item.map_err(|e| QueryError::Database(e.into()))?;
+            // No prefix extractor is configured, so the iterator runs past the owner
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            
+            let utxo_id = self.parse_owner_index_utxo_id(&key)?;
+            // Index entries can outlive their UTXO until pruned
+            if let Some(data) = snapshot.get_cf(cf_names::UTXOS, &self.create_utxo_key(&utxo_id))? {
+                let utxo = CanonicalUTXO::deserialize(&data)
+                    .map_err(|e| QueryError::Serialization(e.to_string()))?;
+                let cursor = OwnerUtxoCursor { created_block: utxo.created_block, utxo_id };
+                utxos.push(utxo);
+                if utxos.len() == limit {
+                    next_cursor = Some(cursor);
+                    break;
+                }
+            }
+        }
+        
+        Ok(OwnerUtxoPage { utxos, next_cursor })
+    }
+
+    /// Total fees credited to the fee recipient since genesis
+    /// 
+    /// Reconciles against the fees charged by processed transactions. A
+    /// single cf_tree_metadata read of the counter the batch writer updates
+    /// with each block commit, so spending fee UTXOs or paying the recipient
+    /// directly does not change it. Zero while fees are burned.
+    pub fn total_fees_collected(&self) -> Result<u128, QueryError> {
+        Ok(crate::database::pool_counters::load_fees_collected(&self.db)?)
+    }
+
     /// Get aggregated balance for owner and asset
     pub fn get_balance(
         &self,
         owner_commitment: &[u8; 32],
         asset_id: &[u8; 20],
     ) -> Result<QueryResult, QueryError> {
+        let (total_amount, utxo_count, last_updated_block) = self.get_asset_balance(owner_commitment, asset_id)?;
+        Ok(QueryResult::Balance {
+            total_amount,
+            utxo_count,
+            last_updated_block,
+        })
+    }
+
+    /// Aggregate `(total_amount, utxo_count, last_updated_block)` for owner and asset
+    /// 
+    /// A single cf_asset_balances read maintained by the batch writer, so no
+    /// owner UTXOs are scanned. Owners with no balance read as all zeros.
+    pub fn get_asset_balance(
+        &self,
+        owner_commitment: &[u8; 32],
+        asset_id: &[u8; 20],
+    ) -> Result<(u128, u32, u64), QueryError> {
         let key = self.create_asset_balance_key(owner_commitment, asset_id);
         
         match self.db.get_cf(cf_names::ASSET_BALANCES, &key)? {
-            Some(data) => {
-                let (total_amount, utxo_count, last_updated_block) = self.parse_asset_balance_value(&data)?;
-                Ok(QueryResult::Balance {
-                    total_amount,
-                    utxo_count,
-                    last_updated_block,
-                })
-            },
-            None => Ok(QueryResult::Balance {
-                total_amount: 0,
-                utxo_count: 0,
-                last_updated_block: 0,
-            }),
+            Some(data) => parse_asset_balance_value(&data)
+                .map_err(|e| QueryError::Serialization(e.to_string())),
+            None => Ok((0, 0, 0)),
         }
     }
 
@@ -178,6 +293,122 @@ impl QueryEngine {
         }
     }
 
+    /// Compute the flat Merkle root over all UTXO leaf hashes
+    /// 
+    /// cf_utxos keys are `prefix || utxo_id`, so iteration order is already
+    /// sorted by utxo_id.
+    pub fn utxo_set_root(&self) -> Result<[u8; 32], QueryError> {
+        let mut leaf_hashes = Vec::new();
+        
+        for item in self.db.iterator_cf(cf_names::UTXOS)? {
+            let (key, value) = item.map_err(|e| QueryError::Database(e.into()))?;
+            if key.first() != Some(&cf_prefixes::UTXOS) {
+                continue;
+            }
+            leaf_hashes.push(crate::canonical_spec::generate_leaf_hash(&value));
+        }
+        
+        Ok(crate::canonical_spec::compute_utxo_set_root(&leaf_hashes))
+    }
+
+    /// Delete owner-index entries of UTXOs spent before `older_than_block`
+    /// 
+    /// Only entries with a cf_spent_tracker record are considered, so unspent
+    /// UTXOs always stay indexed. All deletions are applied in one batch.
+    /// Returns the number of entries pruned.
+    pub fn prune_spent_owner_index(&self, older_than_block: u64) -> Result<u64, QueryError> {
+        let mut batch = self.db.create_write_batch();
+        let owner_index_cf = self.db.cf_handle(cf_names::OWNER_INDEX)?;
+        let mut pruned = 0u64;
+        
+        for item in self.db.iterator_cf(cf_names::OWNER_INDEX)? {
+            let (key, _) = item.map_err(|e| QueryError::Database(e.into()))?;
+            if key.first() != Some(&cf_prefixes::OWNER_INDEX) {
+                continue;
+            }
+            
+            let utxo_id = self.parse_owner_index_utxo_id(&key)?;
+            let spent_block = match self.db.get_cf(cf_names::SPENT_TRACKER, &self.create_spent_tracker_key(&utxo_id))? {
+                Some(value) => self.parse_spent_tracker_block(&value)?,
+                None => continue,
+            };
+            
+            if spent_block < older_than_block {
+                batch.delete_cf(owner_index_cf, &key);
+                pruned += 1;
+            }
+        }
+        
+        if pruned > 0 {
+            self.db.write_batch(batch)?;
+        }
+        Ok(pruned)
+    }
+
+    /// Walk cf_audit_log from genesis and check every hash link
+    /// 
+    /// Returns `false` if any entry is missing, out of sequence, fails to
+    /// parse, or does not chain to its predecessor, or if the newest entry
+    /// does not match the head recorded in cf_tree_metadata.
+    pub fn verify_audit_chain(&self) -> Result<bool, QueryError> {
+        let mut expected_sequence = 0u64;
+        let mut expected_prev = GENESIS_ENTRY_HASH;
+        
+        // Entries and head from one snapshot, so a batch committed mid-walk
+        // cannot make the head run ahead of the entries seen
+        let snapshot = self.db.snapshot();
+        for item in snapshot.prefix_iterator_cf(cf_names::AUDIT_LOG, &[cf_prefixes::AUDIT_LOG])? {
+            let (key, value) = item.map_err(|e| QueryError::Database(e.into()))?;
+            if key.first() != Some(&cf_prefixes::AUDIT_LOG) {
+                break;
+            }
+            
+            if key.as_ref() != audit_log::audit_log_key(expected_sequence).as_slice() {
+                return Ok(false);
+            }
+            let entry = match AuditEntry::deserialize(&value) {
+                Ok(entry) => entry,
+                Err(_) => return Ok(false),
+            };
+            if entry.prev_entry_hash != expected_prev {
+                return Ok(false);
+            }
+            
+            expected_prev = entry.entry_hash();
+            expected_sequence += 1;
+        }
+        
+        // The head guards against a truncated or rewritten tail
+        let head = snapshot.get_cf(cf_names::TREE_METADATA, audit_log::AUDIT_LOG_HEAD_KEY)?
+            .map(|value| audit_log::parse_head(&value))
+            .transpose()?;
+        match head {
+            Some((sequence, entry_hash)) => Ok(expected_sequence == sequence + 1 && expected_prev == entry_hash),
+            None => Ok(expected_sequence == 0),
+        }
+    }
+
+    /// Pool-wide total of `asset_id` as of root version `version`
+    /// 
+    /// Reads the snapshot the batch writer took when that root was committed.
+    /// Fails for versions committed without a snapshot (unknown versions, or
+    /// snapshots disabled at the time).
+    pub fn total_value_at_version(&self, version: u64, asset_id: &[u8; 20]) -> Result<u128, QueryError> {
+        let snapshot = self.db.snapshot();
+        if snapshot.get_cf(cf_names::ROOT_HISTORY, &balance_snapshots::balance_snapshot_header_key(version))?.is_none() {
+            return Err(QueryError::InvalidParameters(format!("No balance snapshot for root version {}", version)));
+        }
+        
+        match snapshot.get_cf(cf_names::ROOT_HISTORY, &balance_snapshots::balance_snapshot_key(version, asset_id))? {
+            Some(value) => {
+                let bytes: [u8; 16] = value.as_slice().try_into()
+                    .map_err(|_| QueryError::Serialization("Invalid balance snapshot value".to_string()))?;
+                Ok(u128::from_be_bytes(bytes))
+            },
+            None => Ok(0),
+        }
+    }
+
     // Key creation helpers
     fn create_utxo_key(&self, utxo_id: &[u8; 32]) -> Vec<u8> {
         let mut key = Vec::with_capacity(33);
@@ -256,23 +487,15 @@ impl QueryEngine {
         Ok((u128::from_be_bytes(amount_bytes), asset_id_bytes, flags))
     }
 
-    fn parse_asset_balance_value(&self, value: &[u8]) -> Result<(u128, u32, u64), QueryError> {
-        if value.len() < 28 {
-            return Err(QueryError::InvalidParameters("Asset balance value too short".to_string()));
+    fn parse_spent_tracker_block(&self, value: &[u8]) -> Result<u64, QueryError> {
+        if value.len() < 40 {
+            return Err(QueryError::InvalidParameters("Spent tracker value too short".to_string()));
         }
         
-        let amount_bytes: [u8; 16] = value[0..16].try_into()
-            .map_err(|_| QueryError::InvalidParameters("Invalid amount in asset balance value".to_string()))?;
-        let count_bytes: [u8; 4] = value[16..20].try_into()
-            .map_err(|_| QueryError::InvalidParameters("Invalid count in asset balance value".to_string()))?;
-        let block_bytes: [u8; 8] = value[20..28].try_into()
-            .map_err(|_| QueryError::InvalidParameters("Invalid block in asset balance value".to_string()))?;
+        let block_bytes: [u8; 8] = value[32..40].try_into()
+            .map_err(|_| QueryError::InvalidParameters("Invalid block in spent tracker value".to_string()))?;
         
-        Ok((
-            u128::from_be_bytes(amount_bytes),
-            u32::from_be_bytes(count_bytes),
-            u64::from_be_bytes(block_bytes),
-        ))
+        Ok(u64::from_be_bytes(block_bytes))
     }
 
     fn parse_smt_leaf_value(&self, value: &[u8]) -> Result<([u8; 32], u64), QueryError> {
@@ -310,6 +533,7 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     use crate::database::schema::DBConfig;
+    use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation};
 
     #[test]
     fn test_query_engine_creation() {
@@ -324,4 +548,368 @@ mod tests {
         let db_manager = DatabaseManager::open(config).unwrap();
         let _query_engine = QueryEngine::new(db_manager);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_utxo_set_root_tracks_insertions() {
+        use crate::canonical_spec::{compute_utxo_set_root, generate_leaf_hash, generate_node_hash};
+        
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        
+        let empty_root = query_engine.utxo_set_root().unwrap();
+        
+        let mut utxos: Vec<CanonicalUTXO> = (0..3u8)
+            .map(|i| CanonicalUTXO::new_eth([i; 32], 0, 100 + i as u64, i as u64, 1_000 * (i as u128 + 1), [0x42; 32]))
+            .collect();
+        
+        let mut roots = vec![empty_root];
+        for utxo in &utxos {
+            let key = query_engine.create_utxo_key(&utxo.utxo_id);
+            db_manager.put_cf(cf_names::UTXOS, &key, &utxo.serialize().unwrap()).unwrap();
+            
+            let root = query_engine.utxo_set_root().unwrap();
+            assert!(!roots.contains(&root));
+            assert_eq!(root, query_engine.utxo_set_root().unwrap());
+            roots.push(root);
+        }
+        
+        // Independent computation over leaves sorted by utxo_id
+        utxos.sort_by_key(|utxo| utxo.utxo_id);
+        let leaves: Vec<[u8; 32]> = utxos.iter()
+            .map(|utxo| generate_leaf_hash(&utxo.serialize().unwrap()))
+            .collect();
+        let expected = generate_node_hash(generate_node_hash(leaves[0], leaves[1]), leaves[2]);
+        
+        assert_eq!(compute_utxo_set_root(&leaves), expected);
+        assert_eq!(query_engine.utxo_set_root().unwrap(), expected);
+    }
+    #[test]
+    fn test_prune_spent_owner_index() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        
+        let owner = [0x42u8; 32];
+        let spent = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, owner);
+        let unspent = CanonicalUTXO::new_eth([2u8; 32], 0, 110, 2, 2_000, owner);
+        
+        for utxo in [&spent, &unspent] {
+            let mut index_key = query_engine.create_owner_index_start_key(&owner, utxo.created_block);
+            index_key.extend_from_slice(&utxo.utxo_id);
+            let mut index_value = utxo.amount.to_be_bytes().to_vec();
+            index_value.extend_from_slice(&utxo.asset_id);
+            index_value.push(utxo.lock_flags);
+            db_manager.put_cf(cf_names::OWNER_INDEX, &index_key, &index_value).unwrap();
+            db_manager.put_cf(cf_names::UTXOS, &query_engine.create_utxo_key(&utxo.utxo_id), &utxo.serialize().unwrap()).unwrap();
+        }
+        
+        // Spend the first UTXO at block 150
+        let mut spent_value = [9u8; 32].to_vec();
+        spent_value.extend_from_slice(&150u64.to_be_bytes());
+        spent_value.extend_from_slice(&0u64.to_be_bytes());
+        db_manager.put_cf(cf_names::SPENT_TRACKER, &query_engine.create_spent_tracker_key(&spent.utxo_id), &spent_value).unwrap();
+        
+        // Spend is not older than the threshold yet
+        assert_eq!(query_engine.prune_spent_owner_index(150).unwrap(), 0);
+        
+        assert_eq!(query_engine.prune_spent_owner_index(1_000).unwrap(), 1);
+        assert_eq!(query_engine.prune_spent_owner_index(1_000).unwrap(), 0);
+        
+        match query_engine.get_owner_utxos(&owner, 10, None, None).unwrap() {
+            QueryResult::UTXOList(utxos) => {
+                assert_eq!(utxos.len(), 1);
+                assert_eq!(utxos[0].utxo_id, unspent.utxo_id);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    fn commit_audited_batches(db_manager: &DatabaseManager, count: u64) {
+        for batch_id in 0..count {
+            let mut writer = AtomicBatchWriter::new(db_manager.clone());
+            writer.add_operation(BatchOperation::CommitRoot {
+                root_version: batch_id,
+                root_hash: [batch_id as u8 + 1; 32],
+                batch_id,
+                timestamp: 1_700_000_000 + batch_id,
+                tx_count: 1,
+                operator_signature: vec![],
+            });
+            writer.commit().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_verify_audit_chain() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        
+        // An empty log is trivially valid
+        assert!(query_engine.verify_audit_chain().unwrap());
+        
+        commit_audited_batches(&db_manager, 4);
+        assert!(query_engine.verify_audit_chain().unwrap());
+        
+        let (sequence, _) = audit_log::load_head(&db_manager).unwrap().unwrap();
+        assert_eq!(sequence, 3);
+    }
+
+    #[test]
+    fn test_verify_audit_chain_detects_tampering() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        
+        commit_audited_batches(&db_manager, 4);
+        
+        // Rewrite the root recorded by a middle entry
+        let key = audit_log::audit_log_key(1);
+        let mut tampered = AuditEntry::deserialize(&db_manager.get_cf(cf_names::AUDIT_LOG, &key).unwrap().unwrap()).unwrap();
+        tampered.root_hash = [0xee; 32];
+        db_manager.put_cf(cf_names::AUDIT_LOG, &key, &tampered.serialize()).unwrap();
+        
+        assert!(!query_engine.verify_audit_chain().unwrap());
+    }
+
+    #[test]
+    fn test_total_value_snapshots_follow_root_versions() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        let eth = [0u8; 20];
+        let token = [9u8; 20];
+        
+        // (owner, asset, delta) applied before committing each root version
+        let batches: [&[(u8, [u8; 20], i128)]; 4] = [
+            &[(1, eth, 100)],
+            &[(2, eth, 50), (2, token, 7)],
+            &[],
+            &[(1, eth, -30), (3, token, 5)],
+        ];
+        
+        let mut running_eth = 0i128;
+        let mut running_token = 0i128;
+        for (version, deposits) in batches.iter().enumerate() {
+            let mut writer = AtomicBatchWriter::new(db_manager.clone());
+            for &(owner, asset_id, amount_delta) in deposits.iter() {
+                writer.add_operation(BatchOperation::UpdateAssetBalance {
+                    owner_commitment: [owner; 32],
+                    asset_id,
+                    amount_delta,
+                    utxo_count_delta: if amount_delta < 0 { -1 } else { 1 },
+                    last_updated_block: version as u64,
+                });
+                if asset_id == eth { running_eth += amount_delta } else { running_token += amount_delta }
+            }
+            writer.add_operation(BatchOperation::CommitRoot {
+                root_version: version as u64,
+                root_hash: [version as u8 + 1; 32],
+                batch_id: version as u64,
+                timestamp: 1_700_000_000 + version as u64,
+                tx_count: deposits.len() as u32,
+                operator_signature: vec![],
+            });
+            writer.commit().unwrap();
+            
+            assert_eq!(query_engine.total_value_at_version(version as u64, &eth).unwrap(), running_eth as u128);
+            assert_eq!(query_engine.total_value_at_version(version as u64, &token).unwrap(), running_token as u128);
+        }
+        
+        // Earlier snapshots are not rewritten by later batches
+        assert_eq!(query_engine.total_value_at_version(0, &eth).unwrap(), 100);
+        assert_eq!(query_engine.total_value_at_version(0, &token).unwrap(), 0);
+        assert_eq!(query_engine.total_value_at_version(2, &eth).unwrap(), 150);
+        assert_eq!(query_engine.total_value_at_version(3, &eth).unwrap(), 120);
+        assert_eq!(query_engine.total_value_at_version(3, &token).unwrap(), 12);
+        assert!(matches!(query_engine.total_value_at_version(4, &eth), Err(QueryError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn test_list_owner_utxos_pages_without_gaps() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        
+        let owner = [0x42u8; 32];
+        let neighbour = [0x43u8; 32];
+        let insert = |utxo: &CanonicalUTXO| {
+            let mut index_key = query_engine.create_owner_index_start_key(&utxo.owner_commitment, utxo.created_block);
+            index_key.extend_from_slice(&utxo.utxo_id);
+            let mut index_value = utxo.amount.to_be_bytes().to_vec();
+            index_value.extend_from_slice(&utxo.asset_id);
+            index_value.push(utxo.lock_flags);
+            db_manager.put_cf(cf_names::OWNER_INDEX, &index_key, &index_value).unwrap();
+            db_manager.put_cf(cf_names::UTXOS, &query_engine.create_utxo_key(&utxo.utxo_id), &utxo.serialize().unwrap()).unwrap();
+        };
+        
+        // Five UTXOs per block so pages end inside a block
+        let mut expected = Vec::new();
+        for i in 0..250u32 {
+            let mut tx_hash = [0u8; 32];
+            tx_hash[..4].copy_from_slice(&i.to_be_bytes());
+            let utxo = CanonicalUTXO::new_eth(tx_hash, 0, 100 + (i / 5) as u64, i as u64, 1_000 + i as u128, owner);
+            insert(&utxo);
+            expected.push((utxo.created_block, utxo.utxo_id));
+        }
+        insert(&CanonicalUTXO::new_eth([0xffu8; 32], 0, 100, 0, 1, neighbour));
+        expected.sort();
+        
+        let mut seen = Vec::new();
+        let mut page = query_engine.list_owner_utxos(&owner, None, 50).unwrap();
+        let mut pages = 1;
+        loop {
+            assert!(page.utxos.len() <= 50);
+            seen.extend(page.utxos.iter().map(|utxo| (utxo.created_block, utxo.utxo_id)));
+            let Some(cursor) = page.next_cursor else { break };
+            assert_eq!(Some(&(cursor.created_block, cursor.utxo_id)), seen.last());
+            page = query_engine.list_owner_utxos_from(&owner, &cursor, 50).unwrap();
+            pages += 1;
+        }
+        
+        // Five full pages, then an empty one that ends the listing
+        assert_eq!(pages, 6);
+        assert_eq!(seen, expected);
+        
+        // Seeking past a block skips everything created up to it
+        let page = query_engine.list_owner_utxos(&owner, Some(109), 50).unwrap();
+        assert_eq!(page.utxos.len(), 50);
+        assert_eq!(page.utxos[0].created_block, 110);
+        assert_eq!((page.utxos[0].created_block, page.utxos[0].utxo_id), expected[50]);
+        
+        let page = query_engine.list_owner_utxos(&owner, Some(149), 50).unwrap();
+        assert!(page.utxos.is_empty());
+        assert!(page.next_cursor.is_none());
+        assert!(matches!(query_engine.list_owner_utxos(&owner, None, 0), Err(QueryError::InvalidParameters(_))));
+    }
+
+    #[test]
+    fn test_total_fees_collected_reads_fee_counter() {
+        use crate::database::{AtomicBatchWriter, BatchOperation};
+        
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        assert_eq!(query_engine.total_fees_collected().unwrap(), 0);
+        
+        // One block commit per fee-paying transaction, next to an ordinary output
+        let fees = [25u128, 10, 40];
+        for (i, fee) in fees.iter().enumerate() {
+            let output = CanonicalUTXO::new_eth([i as u8 + 1; 32], 0, 100 + i as u64, 0, 1_000, [0x42u8; 32]);
+            let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+            batch_writer.add_operation(BatchOperation::InsertUTXO { utxo: output });
+            batch_writer.add_operation(BatchOperation::RecordFee { amount_wei: *fee });
+            batch_writer.commit().unwrap();
+        }
+        assert_eq!(query_engine.total_fees_collected().unwrap(), 75);
+        
+        // Batches without fees leave the total alone
+        let mut batch_writer = AtomicBatchWriter::new(db_manager.clone());
+        batch_writer.add_operation(BatchOperation::RecordDeposit { amount_wei: 500 });
+        batch_writer.commit().unwrap();
+        assert_eq!(query_engine.total_fees_collected().unwrap(), 75);
+    }
+
+    #[test]
+    fn test_get_asset_balance_matches_inserted_utxos() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let query_engine = QueryEngine::new(db_manager.clone());
+        let owner = [0x42u8; 32];
+        let eth = [0u8; 20];
+        
+        assert_eq!(query_engine.get_asset_balance(&owner, &eth).unwrap(), (0, 0, 0));
+        
+        let utxos: Vec<CanonicalUTXO> = (0..5u8)
+            .map(|i| CanonicalUTXO::new_eth([i + 1; 32], 0, 100 + i as u64, i as u64, 1_000 * (i as u128 + 1), owner))
+            .collect();
+        for utxo in &utxos {
+            let mut writer = AtomicBatchWriter::new(db_manager.clone());
+            writer.add_operation(BatchOperation::InsertUTXO { utxo: utxo.clone() });
+            writer.add_operation(BatchOperation::InsertOwnerIndex {
+                owner_commitment: owner,
+                created_block: utxo.created_block,
+                utxo_id: utxo.utxo_id,
+                amount: utxo.amount,
+                asset_id: utxo.asset_id,
+                flags: utxo.lock_flags,
+            });
+            writer.add_operation(BatchOperation::UpdateAssetBalance {
+                owner_commitment: owner,
+                asset_id: utxo.asset_id,
+                amount_delta: utxo.amount as i128,
+                utxo_count_delta: 1,
+                last_updated_block: utxo.created_block,
+            });
+            writer.commit().unwrap();
+        }
+        
+        let expected_amount: u128 = utxos.iter().map(|utxo| utxo.amount).sum();
+        assert_eq!(query_engine.get_asset_balance(&owner, &eth).unwrap(), (expected_amount, 5, 104));
+        
+        // Matches a scan of the owner's UTXOs without performing one
+        let page = query_engine.list_owner_utxos(&owner, None, 100).unwrap();
+        assert_eq!(page.utxos.iter().map(|utxo| utxo.amount).sum::<u128>(), expected_amount);
+        
+        // Other assets and owners are separate aggregates
+        assert_eq!(query_engine.get_asset_balance(&owner, &[9u8; 20]).unwrap(), (0, 0, 0));
+        assert_eq!(query_engine.get_asset_balance(&[0x43u8; 32], &eth).unwrap(), (0, 0, 0));
+    }
+}
@@ -0,0 +1,585 @@
+//! Two-Phase Root History
+//!
+//! Roots are first recorded as prepared (`finalized: false`) when a batch is
+//! committed locally, and only flipped to finalized once the anchoring block
+//! has enough confirmations. Withdrawals must reference a finalized root so a
+//! reorg can never invalidate a root that funds were released against.
+//!
+//! Pruning keeps at least the tolerance window of finalized roots (the same
+//! `AppConfig::root_tolerance_window` the API accepts withdrawals against),
+//! so a root inside the withdrawal acceptance window is never deleted. The
+//! balance snapshots of a pruned root go with it.
+//! When `DBConfig::max_proof_age_secs` is set, a finalized root also stops
+//! being withdrawable once its recorded timestamp is older than that age.
+//!
+//! Every record is indexed by root hash (`root_index_key`) so a withdrawal
+//! looks up the versions of its root instead of scanning the history.
+
+use anyhow::{Result, anyhow};
+use crate::database::schema::{DatabaseManager, cf_names};
+use crate::canonical_spec::{cf_prefixes, tree_config};
+use crate::database::balance_snapshots::balance_snapshot_header_key;
+
+/// Leading byte of the current cf_root_history record format
+///
+/// Records without it predate two-phase roots; they were withdrawable when
+/// written and are read back as finalized.
+pub const ROOT_RECORD_VERSION: u8 = 2;
+
+/// Root history entry stored in cf_root_history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootRecord {
+    pub root_hash: [u8; 32],
+    pub batch_id: u64,
+    pub timestamp: u64,
+    pub tx_count: u32,
+    pub operator_signature: Vec<u8>,
+    pub finalized: bool,
+}
+
+impl RootRecord {
+    /// Serialize record: version(1) || root(32) || batch_id(8) || timestamp(8) ||
+    /// tx_count(4) || sig_len(2) || signature || finalized(1)
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut value = Vec::with_capacity(56 + self.operator_signature.len());
+        value.push(ROOT_RECORD_VERSION);
+        value.extend_from_slice(&self.root_hash);
+        value.extend_from_slice(&self.batch_id.to_be_bytes());
+        value.extend_from_slice(&self.timestamp.to_be_bytes());
+        value.extend_from_slice(&self.tx_count.to_be_bytes());
+        value.extend_from_slice(&(self.operator_signature.len() as u16).to_be_bytes());
+        value.extend_from_slice(&self.operator_signature);
+        value.push(self.finalized as u8);
+        value
+    }
+
+    /// Deserialize record from cf_root_history value
+    ///
+    /// Accepts the current format and the unversioned format without the
+    /// finalized flag.
+    pub fn deserialize(value: &[u8]) -> Result<Self> {
+        if value.len() >= 56 && value[0] == ROOT_RECORD_VERSION {
+            let sig_len = u16::from_be_bytes([value[53], value[54]]) as usize;
+            if value.len() == 56 + sig_len {
+                return Self::deserialize_body(&value[1..], sig_len, value[55 + sig_len] != 0);
+            }
+        }
+        
+        if value.len() < 54 {
+            return Err(anyhow!("Root history value too short"));
+        }
+        let sig_len = u16::from_be_bytes([value[52], value[53]]) as usize;
+        if value.len() != 54 + sig_len {
+            return Err(anyhow!("Root history value has invalid length"));
+        }
+        Self::deserialize_body(value, sig_len, true)
+    }
+
+    /// Fields shared by both formats, `value` starting at the root hash
+    fn deserialize_body(value: &[u8], sig_len: usize, finalized: bool) -> Result<Self> {
+        Ok(Self {
+            root_hash: value[0..32].try_into()?,
+            batch_id: u64::from_be_bytes(value[32..40].try_into()?),
+            timestamp: u64::from_be_bytes(value[40..48].try_into()?),
+            tx_count: u32::from_be_bytes(value[48..52].try_into()?),
+            operator_signature: value[54..54 + sig_len].to_vec(),
+            finalized,
+        })
+    }
+}
+
+/// Prune request would delete finalized roots inside the tolerance window
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Refusing to prune root history to {keep_last} roots, tolerance window is {tolerance_window}")]
+pub struct UnsafeRootPrune {
+    pub keep_last: u64,
+    pub tolerance_window: u64,
+}
+
+/// Withdrawal references a finalized root older than the maximum proof age
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Root version {root_version} recorded at {root_timestamp} is older than the maximum proof age of {max_age_secs}s (now {now})")]
+pub struct RootExpired {
+    pub root_version: u64,
+    pub root_timestamp: u64,
+    pub max_age_secs: u64,
+    pub now: u64,
+}
+
+/// Create cf_root_history key for a root version
+pub fn root_history_key(root_version: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(9);
+    key.push(cf_prefixes::ROOT_HISTORY);
+    key.extend_from_slice(&root_version.to_be_bytes());
+    key
+}
+
+/// cf_root_history index key recording that `root_hash` was committed as `root_version`
+pub fn root_index_key(root_hash: &[u8; 32], root_version: u64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(41);
+    key.push(cf_prefixes::ROOT_INDEX);
+    key.extend_from_slice(root_hash);
+    key.extend_from_slice(&root_version.to_be_bytes());
+    key
+}
+
+/// Prepare/finalize interface over cf_root_history
+pub struct RootHistory {
+    db: DatabaseManager,
+    tolerance_window: u64,
+}
+
+impl RootHistory {
+    /// Create new root history accessor with the default tolerance window
+    pub fn new(db: DatabaseManager) -> Self {
+        Self { db, tolerance_window: tree_config::DEFAULT_ROOT_TOLERANCE_WINDOW }
+    }
+
+    /// Number of newest finalized roots pruning must keep
+    pub fn with_tolerance_window(mut self, tolerance_window: u64) -> Self {
+        self.tolerance_window = tolerance_window;
+        self
+    }
+
+    /// Record a pending root that is not yet withdrawable
+    pub fn prepare_root(&self, root_version: u64, mut record: RootRecord) -> Result<()> {
+        if let Some(existing) = self.get_root(root_version)? {
+            if existing.finalized {
+                return Err(anyhow!("Root version {} is already finalized", root_version));
+            }
+        }
+        
+        record.finalized = false;
+        let mut batch = self.db.create_write_batch();
+        let cf = self.db.cf_handle(cf_names::ROOT_HISTORY)?;
+        if let Some(existing) = self.get_root(root_version)? {
+            batch.delete_cf(cf, &root_index_key(&existing.root_hash, root_version));
+        }
+        batch.put_cf(cf, &root_history_key(root_version), &record.serialize());
+        batch.put_cf(cf, &root_index_key(&record.root_hash, root_version), []);
+        self.db.write_batch(batch)
+    }
+
+    /// Mark a prepared root as finalized after sufficient confirmations
+    pub fn finalize_root(&self, root_version: u64) -> Result<()> {
+        let mut record = self.get_root(root_version)?
+            .ok_or_else(|| anyhow!("Root version {} not found", root_version))?;
+        
+        if record.finalized {
+            return Ok(());
+        }
+        
+        record.finalized = true;
+        self.db.put_cf(cf_names::ROOT_HISTORY, &root_history_key(root_version), &record.serialize())
+    }
+
+    /// Drop a prepared root invalidated by a reorg
+    pub fn abandon_root(&self, root_version: u64) -> Result<()> {
+        let record = self.get_root(root_version)?
+            .ok_or_else(|| anyhow!("Root version {} not found", root_version))?;
+        
+        if record.finalized {
+            return Err(anyhow!("Cannot abandon finalized root version {}", root_version));
+        }
+        
+        self.delete_root(root_version, &record.root_hash)
+    }
+
+    /// Delete a record together with its index entry and balance snapshot
+    fn delete_root(&self, root_version: u64, root_hash: &[u8; 32]) -> Result<()> {
+        let mut batch = self.db.create_write_batch();
+        let cf = self.db.cf_handle(cf_names::ROOT_HISTORY)?;
+        batch.delete_cf(cf, &root_history_key(root_version));
+        batch.delete_cf(cf, &root_index_key(root_hash, root_version));
+        
+        let snapshot_prefix = balance_snapshot_header_key(root_version);
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &snapshot_prefix)? {
+            let (key, _) = item?;
+            if !key.starts_with(&snapshot_prefix) {
+                break;
+            }
+            batch.delete_cf(cf, &key);
+        }
+        self.db.write_batch(batch)
+    }
+
+    /// Rewrite records in the unversioned format and index them by root hash
+    ///
+    /// Idempotent; returns the number of records migrated.
+    pub fn migrate_legacy_records(&self) -> Result<u64> {
+        let mut batch = self.db.create_write_batch();
+        let cf = self.db.cf_handle(cf_names::ROOT_HISTORY)?;
+        let mut migrated = 0;
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &[cf_prefixes::ROOT_HISTORY])? {
+            let (key, value) = item?;
+            if key.first() != Some(&cf_prefixes::ROOT_HISTORY) {
+                break;
+            }
+            
+            let record = RootRecord::deserialize(&value)?;
+            let serialized = record.serialize();
+            if serialized[..] != value[..] {
+                let root_version = u64::from_be_bytes(key[1..].try_into()
+                    .map_err(|_| anyhow!("Invalid root history key length"))?);
+                batch.put_cf(cf, &key, &serialized);
+                batch.put_cf(cf, &root_index_key(&record.root_hash, root_version), []);
+                migrated += 1;
+            }
+        }
+        self.db.write_batch(batch)?;
+        Ok(migrated)
+    }
+
+    /// Versions `root_hash` was committed as, oldest first
+    pub fn versions_of_root(&self, root_hash: &[u8; 32]) -> Result<Vec<u64>> {
+        let mut prefix = vec![cf_prefixes::ROOT_INDEX];
+        prefix.extend_from_slice(root_hash);
+        
+        let mut versions = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &prefix)? {
+            let (key, _) = item?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            versions.push(u64::from_be_bytes(key[prefix.len()..].try_into()
+                .map_err(|_| anyhow!("Invalid root index key length"))?));
+        }
+        Ok(versions)
+    }
+
+    /// Get root record by version
+    pub fn get_root(&self, root_version: u64) -> Result<Option<RootRecord>> {
+        self.db.get_cf(cf_names::ROOT_HISTORY, &root_history_key(root_version))?
+            .map(|value| RootRecord::deserialize(&value))
+            .transpose()
+    }
+
+    /// Records from version `first_version` on, oldest first
+    pub fn roots_from(&self, first_version: u64) -> Result<Vec<(u64, RootRecord)>> {
+        let mut roots = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &root_history_key(first_version))? {
+            let (key, value) = item?;
+            if key.first() != Some(&cf_prefixes::ROOT_HISTORY) {
+                break;
+            }
+            
+            let version = u64::from_be_bytes(key[1..].try_into()
+                .map_err(|_| anyhow!("Invalid root history key length"))?);
+            roots.push((version, RootRecord::deserialize(&value)?));
+        }
+        Ok(roots)
+    }
+
+    /// Newest root record by version
+    pub fn latest_root(&self) -> Result<Option<(u64, RootRecord)>> {
+        let mut latest = None;
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &[cf_prefixes::ROOT_HISTORY])? {
+            let (key, value) = item?;
+            if key.first() != Some(&cf_prefixes::ROOT_HISTORY) {
+                break;
+            }
+            
+            let version = u64::from_be_bytes(key[1..].try_into()
+                .map_err(|_| anyhow!("Invalid root history key length"))?);
+            latest = Some((version, RootRecord::deserialize(&value)?));
+        }
+        
+        Ok(latest)
+    }
+
+    /// Delete all but the newest `keep_last` finalized roots
+    ///
+    /// Prepared roots are left for `finalize_root`/`abandon_root`. Fails with
+    /// `UnsafeRootPrune` when `keep_last` is below the tolerance window.
+    /// Returns the number of roots deleted.
+    pub fn prune_root_history(&self, keep_last: u64) -> Result<u64> {
+        let tolerance_window = self.tolerance_window;
+        if keep_last < tolerance_window {
+            return Err(UnsafeRootPrune { keep_last, tolerance_window }.into());
+        }
+        
+        let mut finalized_versions = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::ROOT_HISTORY, &[cf_prefixes::ROOT_HISTORY])? {
+            let (key, value) = item?;
+            if key.first() != Some(&cf_prefixes::ROOT_HISTORY) {
+                break;
+            }
+            
+            let record = RootRecord::deserialize(&value)?;
+            if record.finalized {
+                finalized_versions.push((u64::from_be_bytes(key[1..].try_into()
+                    .map_err(|_| anyhow!("Invalid root history key length"))?), record.root_hash));
+            }
+        }
+        
+        // Keys are big-endian, so versions arrive oldest first
+        let stale = finalized_versions.len().saturating_sub(keep_last as usize);
+        for (version, root_hash) in &finalized_versions[..stale] {
+            self.delete_root(*version, root_hash)?;
+        }
+        
+        Ok(stale as u64)
+    }
+
+    /// Garbage-collect finalized roots older than the tolerance window
+    pub fn prune_stale_roots(&self) -> Result<u64> {
+        self.prune_root_history(self.tolerance_window)
+    }
+
+    /// Whether withdrawals may be proven against this root
+    pub fn is_withdrawable_root(&self, root_hash: &[u8; 32]) -> Result<bool> {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+        self.check_withdrawal_root(root_hash, now)
+    }
+
+    /// Whether withdrawals may be proven against this root at time `now`
+    ///
+    /// Fails with `RootExpired` when the root is finalized but older than
+    /// `DBConfig::max_proof_age_secs`.
+    pub fn check_withdrawal_root(&self, root_hash: &[u8; 32], now: u64) -> Result<bool> {
+        let max_age_secs = self.db.config().max_proof_age_secs;
+        for root_version in self.versions_of_root(root_hash)?.into_iter().rev() {
+            let Some(record) = self.get_root(root_version)? else {
+                continue;
+            };
+            if !record.finalized || &record.root_hash != root_hash {
+                continue;
+            }
+            
+            if let Some(max_age_secs) = max_age_secs {
+                if now.saturating_sub(record.timestamp) > max_age_secs {
+                    return Err(RootExpired {
+                        root_version,
+                        root_timestamp: record.timestamp,
+                        max_age_secs,
+                        now,
+                    }.into());
+                }
+            }
+            
+            return Ok(true);
+        }
+        
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::database::schema::DBConfig;
+
+    fn open_history() -> (tempfile::TempDir, RootHistory) {
+        open_history_with_window(32)
+    }
+
+    fn open_history_with_window(root_tolerance_window: u64) -> (tempfile::TempDir, RootHistory) {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        (temp_dir, RootHistory::new(db_manager).with_tolerance_window(root_tolerance_window))
+    }
+
+    fn open_history_with_max_age(max_proof_age_secs: u64) -> (tempfile::TempDir, RootHistory) {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            max_proof_age_secs: Some(max_proof_age_secs),
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        (temp_dir, RootHistory::new(db_manager))
+    }
+
+    fn root_record(root_hash: [u8; 32]) -> RootRecord {
+        RootRecord {
+            root_hash,
+            batch_id: 1,
+            timestamp: 1_700_000_000,
+            tx_count: 1,
+            operator_signature: vec![0xAB; 64],
+            finalized: false,
+        }
+    }
+
+    #[test]
+    fn test_prepared_root_not_withdrawable() {
+        let (_dir, history) = open_history();
+        let root = [0x11u8; 32];
+        
+        history.prepare_root(1, root_record(root)).unwrap();
+        
+        let record = history.get_root(1).unwrap().unwrap();
+        assert!(!record.finalized);
+        assert!(!history.is_withdrawable_root(&root).unwrap());
+    }
+
+    #[test]
+    fn test_finalized_root_withdrawable() {
+        let (_dir, history) = open_history();
+        let root = [0x22u8; 32];
+        
+        history.prepare_root(1, root_record(root)).unwrap();
+        history.finalize_root(1).unwrap();
+        
+        assert!(history.get_root(1).unwrap().unwrap().finalized);
+        assert!(history.is_withdrawable_root(&root).unwrap());
+        
+        // Finalized roots survive reorg handling
+        assert!(history.abandon_root(1).is_err());
+        assert!(history.prepare_root(1, root_record([0x99u8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_abandon_prepared_root_on_reorg() {
+        let (_dir, history) = open_history();
+        let root = [0x33u8; 32];
+        
+        history.prepare_root(1, root_record(root)).unwrap();
+        history.abandon_root(1).unwrap();
+        
+        assert!(history.get_root(1).unwrap().is_none());
+        assert!(history.finalize_root(1).is_err());
+        assert!(!history.is_withdrawable_root(&root).unwrap());
+    }
+
+    #[test]
+    fn test_prune_respects_tolerance_window() {
+        let (_dir, history) = open_history_with_window(3);
+        for version in 1..=5u64 {
+            history.prepare_root(version, root_record([version as u8; 32])).unwrap();
+            history.finalize_root(version).unwrap();
+        }
+        // A pending root is never pruned
+        history.prepare_root(6, root_record([6u8; 32])).unwrap();
+        
+        let err = history.prune_root_history(2).unwrap_err();
+        assert_eq!(err.downcast_ref::<UnsafeRootPrune>(), Some(&UnsafeRootPrune { keep_last: 2, tolerance_window: 3 }));
+        assert!(history.get_root(1).unwrap().is_some());
+        
+        assert_eq!(history.prune_stale_roots().unwrap(), 2);
+        for version in 1..=2u64 {
+            assert!(history.get_root(version).unwrap().is_none());
+            assert!(!history.is_withdrawable_root(&[version as u8; 32]).unwrap());
+        }
+        for version in 3..=5u64 {
+            assert!(history.is_withdrawable_root(&[version as u8; 32]).unwrap());
+        }
+        assert!(history.get_root(6).unwrap().is_some());
+        
+        // Keeping more than the window is always allowed
+        assert_eq!(history.prune_root_history(10).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_prune_drops_balance_snapshots_of_pruned_roots() {
+        use crate::database::balance_snapshots::balance_snapshot_key;
+        
+        let (_dir, history) = open_history_with_window(1);
+        for version in 1..=2u64 {
+            history.prepare_root(version, root_record([version as u8; 32])).unwrap();
+            history.finalize_root(version).unwrap();
+            history.db.put_cf(cf_names::ROOT_HISTORY, &balance_snapshot_header_key(version), &[]).unwrap();
+            history.db.put_cf(cf_names::ROOT_HISTORY, &balance_snapshot_key(version, &[0u8; 20]), &5u128.to_be_bytes()).unwrap();
+        }
+        
+        assert_eq!(history.prune_stale_roots().unwrap(), 1);
+        assert!(history.db.get_cf(cf_names::ROOT_HISTORY, &balance_snapshot_header_key(1)).unwrap().is_none());
+        assert!(history.db.get_cf(cf_names::ROOT_HISTORY, &balance_snapshot_key(1, &[0u8; 20])).unwrap().is_none());
+        assert!(history.db.get_cf(cf_names::ROOT_HISTORY, &balance_snapshot_key(2, &[0u8; 20])).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_max_proof_age_rejects_expired_root() {
+        let (_dir, history) = open_history_with_max_age(3600);
+        let old_root = [0x44u8; 32];
+        let recent_root = [0x55u8; 32];
+        let committed_at = 1_700_000_000u64;
+        
+        history.prepare_root(1, root_record(old_root)).unwrap();
+        history.finalize_root(1).unwrap();
+        assert!(history.check_withdrawal_root(&old_root, committed_at + 3600).unwrap());
+        
+        // Advance the clock past the maximum age and commit a fresh root
+        let now = committed_at + 3601;
+        history.prepare_root(2, RootRecord { timestamp: now - 60, ..root_record(recent_root) }).unwrap();
+        history.finalize_root(2).unwrap();
+        
+        let err = history.check_withdrawal_root(&old_root, now).unwrap_err();
+        assert_eq!(err.downcast_ref::<RootExpired>(), Some(&RootExpired {
+            root_version: 1,
+            root_timestamp: committed_at,
+            max_age_secs: 3600,
+            now,
+        }));
+        assert!(history.check_withdrawal_root(&recent_root, now).unwrap());
+        
+        // Unknown roots are still reported as not withdrawable
+        assert!(!history.check_withdrawal_root(&[0x66u8; 32], now).unwrap());
+    }
+
+    #[test]
+    fn test_legacy_records_migrate_as_finalized() {
+        let (_dir, history) = open_history();
+        let root = [0x88u8; 32];
+        
+        // Unversioned layout: no version byte and no finalized flag
+        let mut legacy = root_record(root).serialize();
+        legacy.remove(0);
+        legacy.pop();
+        history.db.put_cf(cf_names::ROOT_HISTORY, &root_history_key(1), &legacy).unwrap();
+        assert!(history.get_root(1).unwrap().unwrap().finalized);
+        assert!(!history.is_withdrawable_root(&root).unwrap());
+        
+        assert_eq!(history.migrate_legacy_records().unwrap(), 1);
+        assert_eq!(history.migrate_legacy_records().unwrap(), 0);
+        assert_eq!(history.versions_of_root(&root).unwrap(), vec![1]);
+        assert!(history.is_withdrawable_root(&root).unwrap());
+    }
+
+    #[test]
+    fn test_root_index_follows_records() {
+        let (_dir, history) = open_history_with_window(1);
+        let root = [0x99u8; 32];
+        
+        // The same root can be committed at several versions
+        history.prepare_root(1, root_record(root)).unwrap();
+        history.prepare_root(2, root_record([0x9Au8; 32])).unwrap();
+        history.prepare_root(3, root_record(root)).unwrap();
+        assert_eq!(history.versions_of_root(&root).unwrap(), vec![1, 3]);
+        
+        // Re-preparing a version moves its index entry
+        history.prepare_root(2, root_record(root)).unwrap();
+        assert!(history.versions_of_root(&[0x9Au8; 32]).unwrap().is_empty());
+        assert_eq!(history.versions_of_root(&root).unwrap(), vec![1, 2, 3]);
+        
+        history.abandon_root(2).unwrap();
+        for version in [1, 3] {
+            history.finalize_root(version).unwrap();
+        }
+        assert_eq!(history.prune_stale_roots().unwrap(), 1);
+        assert_eq!(history.versions_of_root(&root).unwrap(), vec![3]);
+        assert!(history.is_withdrawable_root(&root).unwrap());
+        assert_eq!(history.roots_from(0).unwrap().iter().map(|(version, _)| *version).collect::<Vec<_>>(), vec![3]);
+    }
+
+    #[test]
+    fn test_no_max_proof_age_by_default() {
+        let (_dir, history) = open_history();
+        let root = [0x77u8; 32];
+        
+        history.prepare_root(1, root_record(root)).unwrap();
+        history.finalize_root(1).unwrap();
+        assert!(history.check_withdrawal_root(&root, u64::MAX).unwrap());
+    }
+}
@@ -7,7 +7,7 @@ use rocksdb::{DB, ColumnFamilyDescriptor, Options, WriteBatch, ReadOptions, Writ
 use std::path::Path;
 use std::collections::HashMap;
 use std::sync::Arc;
-use anyhow::{Result, anyhow, Context};
+use anyhow::{Result, anyhow, bail, Context};
 use crate::canonical_spec::cf_prefixes;
 
 /// Column family names matching the specification
@@ -25,6 +25,10 @@ pub mod cf_names {
     pub const TREE_METADATA: &str = "cf_tree_metadata";
     pub const ENCRYPTED_NOTES: &str = "cf_encrypted_notes";
     pub const WALLET_NOTES: &str = "cf_wallet_notes";
+    pub const AUDIT_LOG: &str = "cf_audit_log";
+    pub const NULLIFIERS: &str = "cf_nullifiers";
+    pub const PROCESSED_TXIDS: &str = "cf_processed_txids";
+    pub const DEPOSIT_COMMITMENTS: &str = "cf_deposit_commitments";
 }
 
 /// Database configuration for deployment
@@ -56,6 +60,22 @@ pub struct DBConfig {
     
     /// WAL size limit (default: 1GB)
     pub wal_size_limit: u64,
+    
+    /// Append a hash-chained cf_audit_log entry for every committed batch
+    pub enable_audit_log: bool,
+    
+    /// Snapshot per-asset pool totals into cf_root_history on every committed root
+    pub enable_balance_snapshots: bool,
+    
+    /// Let `BatchPipeline` check disjoint batches outside the commit lock
+    pub enable_batch_pipelining: bool,
+    
+    /// Reject withdrawals against finalized roots older than this many
+    /// seconds, measured from the root's recorded timestamp (None: no limit)
+    pub max_proof_age_secs: Option<u64>,
+    
+    /// Deepest block rollback allowed below the chain head
+    pub max_reorg_depth: u64,
 }
 
 impl Default for DBConfig {
@@ -70,6 +90,11 @@ impl Default for DBConfig {
             compression_type: rocksdb::DBCompressionType::Lz4,
             max_background_jobs: 16,
             wal_size_limit: 1024 * 1024 * 1024, // 1GB
+            enable_audit_log: true,
+            enable_balance_snapshots: true,
+            enable_batch_pipelining: false,
+            max_proof_age_secs: None,
+            max_reorg_depth: crate::canonical_spec::tree_config::DEFAULT_MAX_REORG_DEPTH,
         }
     }
 }
@@ -256,6 +281,58 @@ impl CFConfig {
         }
     }
 
+    /// Configuration for cf_audit_log (append-only hash chain)
+    pub fn audit_log() -> Self {
+        Self {
+            name: cf_names::AUDIT_LOG.to_string(),
+            write_buffer_size: 32 * 1024 * 1024,
+            enable_bloom_filter: false, // Sequential access
+            compaction_style: DBCompactionStyle::Level,
+            target_file_size_base: 256 * 1024 * 1024,
+            compression_type: rocksdb::DBCompressionType::Zstd,
+            optimize_for_point_lookup: false,
+        }
+    }
+
+    /// Configuration for cf_nullifiers (double-spend existence checks)
+    pub fn nullifiers() -> Self {
+        Self {
+            name: cf_names::NULLIFIERS.to_string(),
+            write_buffer_size: 64 * 1024 * 1024,
+            enable_bloom_filter: true, // Almost every lookup is a miss
+            compaction_style: DBCompactionStyle::Level,
+            target_file_size_base: 128 * 1024 * 1024,
+            compression_type: rocksdb::DBCompressionType::Lz4,
+            optimize_for_point_lookup: true,
+        }
+    }
+
+    /// Configuration for cf_processed_txids (transaction replay checks)
+    pub fn processed_txids() -> Self {
+        Self {
+            name: cf_names::PROCESSED_TXIDS.to_string(),
+            write_buffer_size: 64 * 1024 * 1024,
+            enable_bloom_filter: true, // Replays are rare, most lookups miss
+            compaction_style: DBCompactionStyle::Level,
+            target_file_size_base: 128 * 1024 * 1024,
+            compression_type: rocksdb::DBCompressionType::Lz4,
+            optimize_for_point_lookup: true,
+        }
+    }
+
+    /// Configuration for cf_deposit_commitments (deposit commitment -> UTXO ID)
+    pub fn deposit_commitments() -> Self {
+        Self {
+            name: cf_names::DEPOSIT_COMMITMENTS.to_string(),
+            write_buffer_size: 64 * 1024 * 1024,
+            enable_bloom_filter: true, // Duplicate deposits are rare, most lookups miss
+            compaction_style: DBCompactionStyle::Level,
+            target_file_size_base: 128 * 1024 * 1024,
+            compression_type: rocksdb::DBCompressionType::Lz4,
+            optimize_for_point_lookup: true,
+        }
+    }
+
     /// Create RocksDB Options from configuration
     pub fn to_options(&self, shared_cache: &Cache) -> Options {
         let mut opts = Options::default();
@@ -291,6 +368,18 @@ pub struct DatabaseManager {
     config: DBConfig,
     column_families: HashMap<String, String>,
     block_cache: Cache,
+    /// Serializes batch commits and input lock updates, which read-modify-write
+    /// shared entries (pool counters, asset totals, audit head, input locks)
+    /// before writing
+    commit_lock: Arc<std::sync::Mutex<()>>,
+}
+
+impl std::fmt::Debug for DatabaseManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseManager")
+            .field("db_path", &self.config.db_path)
+            .finish_non_exhaustive()
+    }
 }
 
 impl DatabaseManager {
@@ -316,6 +405,10 @@ impl DatabaseManager {
             CFConfig::tree_metadata(),
             CFConfig::encrypted_notes(),
             CFConfig::wallet_notes(),
+            CFConfig::audit_log(),
+            CFConfig::nullifiers(),
+            CFConfig::processed_txids(),
+            CFConfig::deposit_commitments(),
         ];
 
         // Create column family descriptors
@@ -354,9 +447,15 @@ impl DatabaseManager {
             config,
             column_families,
             block_cache,
+            commit_lock: Arc::new(std::sync::Mutex::new(())),
         })
     }
 
+    /// Hold while reading entries a batch will rewrite and writing the batch
+    pub fn commit_guard(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.commit_lock.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
     /// Get column family handle
     pub fn cf_handle(&self, name: &str) -> Result<&rocksdb::ColumnFamily> {
         self.db.cf_handle(name)
@@ -460,6 +559,68 @@ impl DatabaseManager {
         Ok(self.db.iterator_cf_opt(cf, read_opts, rocksdb::IteratorMode::From(prefix, rocksdb::Direction::Forward)))
     }
 
+    /// Take a point-in-time snapshot for consistent reads across column families
+    pub fn snapshot(&self) -> DbSnapshot<'_> {
+        DbSnapshot {
+            manager: self,
+            snapshot: self.db.snapshot(),
+        }
+    }
+
+    /// Write a point-in-time copy of every column family to `path`
+    /// 
+    /// RocksDB flushes memtables and hard-links the live SST files where the
+    /// filesystem allows, so all column families are captured at the same
+    /// sequence number. `path` must not exist yet; the result is a complete
+    /// database directory that `open` accepts.
+    pub fn create_checkpoint(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            bail!("Checkpoint path {} already exists", path.display());
+        }
+        
+        let checkpoint = rocksdb::checkpoint::Checkpoint::new(self.db.as_ref())
+            .context("Failed to prepare checkpoint")?;
+        checkpoint.create_checkpoint(path)
+            .with_context(|| format!("Failed to create checkpoint at {}", path.display()))
+    }
+
+    /// Restore the checkpoint at `src` as a new database directory `dst`
+    /// 
+    /// Files are copied rather than linked so the restored database can take
+    /// writes without touching the checkpoint. The copy is staged next to
+    /// `dst` and renamed into place, so a failed restore leaves no `dst`.
+    pub fn restore_from_checkpoint(src: &Path, dst: &Path) -> Result<()> {
+        if !src.join("CURRENT").is_file() {
+            bail!("{} is not a database checkpoint", src.display());
+        }
+        if dst.exists() {
+            bail!("Restore target {} already exists", dst.display());
+        }
+        
+        let mut staging_name = dst.file_name()
+            .ok_or_else(|| anyhow!("Invalid restore target {}", dst.display()))?
+            .to_os_string();
+        staging_name.push(".restoring");
+        let staging = dst.with_file_name(staging_name);
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)
+                .with_context(|| format!("Failed to clear stale restore at {}", staging.display()))?;
+        }
+        std::fs::create_dir_all(&staging)
+            .with_context(|| format!("Failed to create {}", staging.display()))?;
+        
+        for entry in std::fs::read_dir(src).with_context(|| format!("Failed to read {}", src.display()))? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                std::fs::copy(entry.path(), staging.join(entry.file_name()))
+                    .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+            }
+        }
+        
+        std::fs::rename(&staging, dst)
+            .with_context(|| format!("Failed to move restored database to {}", dst.display()))
+    }
+
     /// Shutdown database gracefully
     pub fn shutdown(&self) -> Result<()> {
         // RocksDB handles shutdown automatically when DB is dropped
@@ -468,6 +629,42 @@ impl DatabaseManager {
     }
 }
 
+/// Read-only view of every column family as of one point in time
+/// 
+/// Writes committed after the snapshot was taken are invisible to it, so
+/// queries spanning several column families never observe half of a batch.
+/// The snapshot is released when dropped.
+pub struct DbSnapshot<'a> {
+    manager: &'a DatabaseManager,
+    snapshot: rocksdb::SnapshotWithThreadMode<'a, DB>,
+}
+
+impl DbSnapshot<'_> {
+    /// Get value from column family as of the snapshot
+    pub fn get_cf(&self, cf_name: &str, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let cf = self.manager.cf_handle(cf_name)?;
+        
+        self.snapshot.get_cf_opt(cf, key, ReadOptions::default())
+            .with_context(|| format!("Failed to get key from {}", cf_name))
+    }
+
+    /// Create iterator for column family as of the snapshot
+    pub fn iterator_cf(&self, cf_name: &str) -> Result<rocksdb::DBIteratorWithThreadMode<'_, DB>> {
+        let cf = self.manager.cf_handle(cf_name)?;
+        
+        Ok(self.snapshot.iterator_cf_opt(cf, ReadOptions::default(), rocksdb::IteratorMode::Start))
+    }
+
+    /// Create prefix iterator for column family as of the snapshot
+    pub fn prefix_iterator_cf(&self, cf_name: &str, prefix: &[u8]) -> Result<rocksdb::DBIteratorWithThreadMode<'_, DB>> {
+        let cf = self.manager.cf_handle(cf_name)?;
+        let mut read_opts = ReadOptions::default();
+        read_opts.set_prefix_same_as_start(true);
+        
+        Ok(self.snapshot.iterator_cf_opt(cf, read_opts, rocksdb::IteratorMode::From(prefix, rocksdb::Direction::Forward)))
+    }
+}
+
 /// Database utility functions
 pub mod utils {
     use super::*;
@@ -499,6 +696,11 @@ pub mod utils {
         create_key_with_prefix(cf_prefixes::UTXOS, &[utxo_id])
     }
 
+    /// Create input lock key
+    pub fn input_lock_key(utxo_id: &[u8; 32]) -> Vec<u8> {
+        create_key_with_prefix(cf_prefixes::INPUT_LOCKS, &[utxo_id])
+    }
+
     /// Create owner index key
     pub fn owner_index_key(owner_commitment: &[u8; 32], created_block: u64, utxo_id: &[u8; 32]) -> Vec<u8> {
         create_key_with_prefix(
@@ -507,6 +709,21 @@ pub mod utils {
         )
     }
 
+    /// Create nullifier key
+    pub fn nullifier_key(nullifier: &[u8; 32]) -> Vec<u8> {
+        create_key_with_prefix(cf_prefixes::NULLIFIERS, &[nullifier])
+    }
+
+    /// Create processed transaction key
+    pub fn processed_txid_key(txid: &[u8; 32]) -> Vec<u8> {
+        create_key_with_prefix(cf_prefixes::PROCESSED_TXIDS, &[txid])
+    }
+
+    /// Create deposit commitment key
+    pub fn deposit_commitment_key(commitment: &[u8; 32]) -> Vec<u8> {
+        create_key_with_prefix(cf_prefixes::DEPOSIT_COMMITMENTS, &[commitment])
+    }
+
     /// Create asset balance key
     pub fn asset_balance_key(owner_commitment: &[u8; 32], asset_id: &[u8; 20]) -> Vec<u8> {
         create_key_with_prefix(
@@ -545,6 +762,10 @@ mod tests {
         assert!(db_manager.cf_handle(cf_names::ROOT_HISTORY).is_ok());
         assert!(db_manager.cf_handle(cf_names::BLOCK_INDEX).is_ok());
         assert!(db_manager.cf_handle(cf_names::TREE_METADATA).is_ok());
+        assert!(db_manager.cf_handle(cf_names::AUDIT_LOG).is_ok());
+        assert!(db_manager.cf_handle(cf_names::NULLIFIERS).is_ok());
+        assert!(db_manager.cf_handle(cf_names::PROCESSED_TXIDS).is_ok());
+        assert!(db_manager.cf_handle(cf_names::DEPOSIT_COMMITMENTS).is_ok());
     }
 
     #[test]
@@ -586,4 +807,109 @@ mod tests {
         let parsed = utils::parse_key_with_prefix(&key, prefix).unwrap();
         assert_eq!(parsed, &utxo_id[..]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_snapshot_reads_are_not_torn() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let key = b"counter";
+        
+        // Each batch bumps the same counter in two column families
+        let writer_db = db_manager.clone();
+        let writer = std::thread::spawn(move || {
+            for i in 1..=200u64 {
+                let mut batch = writer_db.create_write_batch();
+                batch.put_cf(writer_db.cf_handle(cf_names::UTXOS).unwrap(), key, i.to_be_bytes());
+                batch.put_cf(writer_db.cf_handle(cf_names::ASSET_BALANCES).unwrap(), key, i.to_be_bytes());
+                writer_db.write_batch(batch).unwrap();
+            }
+        });
+        
+        let mut reads = 0;
+        while !writer.is_finished() || reads == 0 {
+            let snapshot = db_manager.snapshot();
+            let utxos = snapshot.get_cf(cf_names::UTXOS, key).unwrap();
+            let balances = snapshot.get_cf(cf_names::ASSET_BALANCES, key).unwrap();
+            assert_eq!(utxos, balances);
+            reads += 1;
+        }
+        writer.join().unwrap();
+        
+        // Later writes stay invisible to an existing snapshot
+        let snapshot = db_manager.snapshot();
+        db_manager.put_cf(cf_names::UTXOS, key, &0u64.to_be_bytes()).unwrap();
+        db_manager.put_cf(cf_names::UTXOS, b"later", b"value").unwrap();
+        assert_eq!(snapshot.get_cf(cf_names::UTXOS, key).unwrap(), Some(200u64.to_be_bytes().to_vec()));
+        assert_eq!(snapshot.iterator_cf(cf_names::UTXOS).unwrap().count(), 1);
+        assert_eq!(db_manager.iterator_cf(cf_names::UTXOS).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_checkpoint_captures_earlier_state() {
+        use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation};
+        use crate::database::{QueryEngine, RootHistory};
+        use crate::utxo::CanonicalUTXO;
+        
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+
+        let db_manager = DatabaseManager::open(config.clone()).unwrap();
+        let utxos: Vec<CanonicalUTXO> = (0..6u8)
+            .map(|i| CanonicalUTXO::new_eth([i + 1; 32], 0, 100 + i as u64, i as u64, 1_000, [0x42u8; 32]))
+            .collect();
+        let commit = |version: u64, batch: &[CanonicalUTXO]| {
+            let mut writer = AtomicBatchWriter::new(db_manager.clone());
+            for utxo in batch {
+                writer.add_operation(BatchOperation::InsertUTXO { utxo: utxo.clone() });
+            }
+            writer.add_operation(BatchOperation::CommitRoot {
+                root_version: version,
+                root_hash: [version as u8 + 1; 32],
+                batch_id: version,
+                timestamp: 1_700_000_000 + version,
+                tx_count: batch.len() as u32,
+                operator_signature: vec![],
+            });
+            writer.commit().unwrap();
+        };
+        
+        commit(0, &utxos[..3]);
+        let checkpoint_path = temp_dir.path().join("checkpoint");
+        db_manager.create_checkpoint(&checkpoint_path).unwrap();
+        commit(1, &utxos[3..]);
+        assert!(db_manager.create_checkpoint(&checkpoint_path).is_err());
+        
+        let restored_path = temp_dir.path().join("restored");
+        DatabaseManager::restore_from_checkpoint(&checkpoint_path, &restored_path).unwrap();
+        assert!(DatabaseManager::restore_from_checkpoint(&checkpoint_path, &restored_path).is_err());
+        assert!(DatabaseManager::restore_from_checkpoint(temp_dir.path(), &temp_dir.path().join("other")).is_err());
+        
+        for path in [checkpoint_path, restored_path] {
+            let copy = DatabaseManager::open(DBConfig {
+                db_path: path.to_string_lossy().to_string(),
+                ..config.clone()
+            }).unwrap();
+            
+            for (i, utxo) in utxos.iter().enumerate() {
+                assert_eq!(copy.get_cf(cf_names::UTXOS, &utxo.db_key()).unwrap().is_some(), i < 3);
+            }
+            
+            let root_history = RootHistory::new(copy.clone());
+            assert_eq!(root_history.get_root(0).unwrap().unwrap().root_hash, [1u8; 32]);
+            assert!(root_history.get_root(1).unwrap().is_none());
+            assert!(QueryEngine::new(copy).verify_audit_chain().unwrap());
+        }
+    }
+}
@@ -14,6 +14,7 @@ pub mod relayer;
 pub mod utils;
 pub mod api;
 pub mod crypto;
+pub mod wallet;
 
 // Re-export main types for easy access
 pub use privacy::{PrivacyPool, PoolStats, UTXOPrivacyPool, ETHDepositEvent};
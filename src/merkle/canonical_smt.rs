@@ -8,8 +8,28 @@ use anyhow::{Result, anyhow};
 use crate::canonical_spec::{self, tree_config};
 use crate::database::schema::{DatabaseManager, cf_names};
 use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation};
+use crate::database::root_history::RootHistory;
 use crate::utxo::CanonicalUTXO;
 
+/// cf_tree_metadata key holding the tree configuration
+pub const TREE_CONFIG_KEY: &[u8] = b"tree_config";
+
+/// Leaf layout written with the tree configuration
+///
+/// Layout 1 (no `layout:` field) placed leaves at the unmasked
+/// `generate_tree_index`; layout 2 places them at `tree_leaf_index`.
+pub const TREE_LAYOUT_VERSION: u32 = 2;
+
+/// Node hashes by (level, index), level 0 = leaves
+type NodeCache = HashMap<(u8, u64), [u8; 32]>;
+
+/// A new leaf's tree position already holds another leaf
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("LeafSlotOccupied: tree position {tree_position} already holds a leaf")]
+pub struct LeafSlotOccupied {
+    pub tree_position: u64,
+}
+
 /// Sparse Merkle Tree with proper node management
 pub struct CanonicalSMT {
     /// Database manager for persistence
@@ -29,6 +49,23 @@ pub struct CanonicalSMT {
     
     /// Current tree version/root counter
     root_version: u64,
+    
+    /// Non-empty node hashes by (level, index), level 0 = leaves
+    nodes: NodeCache,
+}
+
+/// Leaf updates hashed up to a new root without touching the tree
+///
+/// Add `operations` to a batch and hand the update to
+/// `CanonicalSMT::apply_staged` once that batch has committed.
+#[derive(Debug, Clone)]
+pub struct StagedTreeUpdate {
+    /// cf_smt_nodes writes for every rewritten path
+    pub operations: Vec<BatchOperation>,
+    /// Root after all updates
+    pub root: [u8; 32],
+    /// Rewritten node hashes by (level, index)
+    nodes: HashMap<(u8, u64), [u8; 32]>,
 }
 
 /// SMT node structure for database storage
@@ -89,15 +126,41 @@ impl CanonicalSMT {
         // Precompute empty subtree hashes
         let empty_subtrees = canonical_spec::precompute_empty_subtrees(depth);
         
-        let smt = Self {
+        let mut smt = Self {
             db,
             depth,
             tree_salt,
             current_root: empty_subtrees[depth as usize],
             empty_subtrees,
             root_version: 0,
+            nodes: HashMap::new(),
         };
 
+        // Leaf positions depend on the salt, so a changed salt would orphan
+        // every stored leaf and invalidate outstanding proofs
+        if let Some(stored_salt) = Self::stored_tree_salt(&smt.db)? {
+            if stored_salt != tree_salt && smt.has_stored_utxos()? {
+                return Err(anyhow!(
+                    "Tree salt {} does not match salt {} persisted with existing UTXOs; refusing to start",
+                    tree_salt,
+                    stored_salt
+                ));
+            }
+        }
+
+        // Leaves stored under an older layout sit at positions this tree never reads
+        if let Some(stored_layout) = Self::stored_layout_version(&smt.db)? {
+            if stored_layout != TREE_LAYOUT_VERSION && smt.has_stored_utxos()? {
+                return Err(anyhow!(
+                    "Tree layout {} persisted with existing UTXOs is not layout {}; refusing to start",
+                    stored_layout,
+                    TREE_LAYOUT_VERSION
+                ));
+            }
+        }
+
+        smt.load_nodes_from_leaves()?;
+
         // Initialize tree metadata if not exists
         smt.initialize_metadata()?;
         
@@ -105,14 +168,79 @@ impl CanonicalSMT {
     }
 
     /// Create SMT with default configuration
+    ///
+    /// Reuses the persisted salt so restarts keep leaf positions stable.
     pub fn with_default_config(db: DatabaseManager) -> Result<Self> {
-        Self::new(db, tree_config::DEFAULT_DEPTH, rand::random::<u64>())
+        let tree_salt = match Self::stored_tree_salt(&db)? {
+            Some(salt) => salt,
+            None => rand::random::<u64>(),
+        };
+        Self::new(db, tree_config::DEFAULT_DEPTH, tree_salt)
+    }
+
+    /// Salt recorded in cf_tree_metadata by a previous run, if any
+    pub fn stored_tree_salt(db: &DatabaseManager) -> Result<Option<u64>> {
+        let Some(value) = db.get_cf(cf_names::TREE_METADATA, TREE_CONFIG_KEY)? else {
+            return Ok(None);
+        };
+        
+        let config = String::from_utf8(value).map_err(|_| anyhow!("Tree config is not UTF-8"))?;
+        config
+            .split(',')
+            .find_map(|field| field.strip_prefix("salt:"))
+            .ok_or_else(|| anyhow!("Tree config has no salt"))?
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|e| anyhow!("Invalid stored tree salt: {}", e))
+    }
+
+    /// Leaf layout recorded in cf_tree_metadata, 1 for configs written before layouts were versioned
+    pub fn stored_layout_version(db: &DatabaseManager) -> Result<Option<u32>> {
+        let Some(value) = db.get_cf(cf_names::TREE_METADATA, TREE_CONFIG_KEY)? else {
+            return Ok(None);
+        };
+        
+        let config = String::from_utf8(value).map_err(|_| anyhow!("Tree config is not UTF-8"))?;
+        match config.split(',').find_map(|field| field.strip_prefix("layout:")) {
+            Some(layout) => layout
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|e| anyhow!("Invalid stored tree layout: {}", e)),
+            None => Ok(Some(1)),
+        }
+    }
+
+    /// Restore the node cache, root and root version from cf_smt_leaves and cf_root_history
+    fn load_nodes_from_leaves(&mut self) -> Result<()> {
+        let (root, cache, _) = self.compute_from_leaves(self.leaf_positions()?);
+        self.nodes = cache;
+        self.current_root = root;
+        if let Some((version, _)) = RootHistory::new(self.db.clone()).latest_root()? {
+            self.root_version = version;
+        }
+        Ok(())
+    }
+
+    /// Whether any UTXO or tree leaf has been persisted
+    fn has_stored_utxos(&self) -> Result<bool> {
+        for cf_name in [cf_names::UTXOS, cf_names::SMT_LEAVES] {
+            if self.db.iterator_cf(cf_name)?.next().is_some() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Leaf index a UTXO occupies (leaves are always placed by id)
+    pub fn leaf_position(&self, utxo_id: &[u8; 32]) -> u64 {
+        canonical_spec::tree_leaf_index(*utxo_id, self.tree_salt, self.depth)
     }
 
     /// Insert UTXO into the tree
     pub fn insert_utxo(&mut self, utxo: &CanonicalUTXO) -> Result<[u8; 32]> {
         let leaf_hash = utxo.leaf_hash()?;
-        let tree_index = canonical_spec::generate_tree_index(utxo.utxo_id, self.tree_salt);
+        let tree_index = self.leaf_position(&utxo.utxo_id);
+        self.ensure_slot_free(tree_index)?;
         
         // Update the tree with this new leaf
         let new_root = self.update_tree(tree_index, leaf_hash)?;
@@ -130,7 +258,7 @@ impl CanonicalSMT {
     /// Remove UTXO from the tree (mark as spent)
     pub fn remove_utxo(&mut self, utxo_id: &[u8; 32]) -> Result<[u8; 32]> {
         // Get the tree position for this UTXO
-        let tree_index = canonical_spec::generate_tree_index(*utxo_id, self.tree_salt);
+        let tree_index = self.leaf_position(utxo_id);
         let empty_leaf = canonical_spec::generate_empty_leaf_hash();
         
         // Update tree with empty leaf
@@ -148,17 +276,64 @@ impl CanonicalSMT {
 
     /// Update tree with new leaf value at given index
     fn update_tree(&mut self, leaf_index: u64, leaf_hash: [u8; 32]) -> Result<[u8; 32]> {
+        let staged = self.stage_leaf_updates(&[(leaf_index, leaf_hash)])?;
+
+        // Commit all node updates atomically
         let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
+        for operation in &staged.operations {
+            batch_writer.add_operation(operation.clone());
+        }
+        batch_writer.commit()?;
+
+        // Siblings for later updates are read from the cached path
+        for (&(level, index), &hash) in &staged.nodes {
+            self.cache_node(level, index, hash);
+        }
+
+        Ok(staged.root)
+    }
+
+    /// Hash `(leaf_index, leaf_hash)` updates, applied in order, up to a new root
+    ///
+    /// Neither the database nor the tree is modified; later updates see the
+    /// paths of earlier ones, so a leaf may be cleared and set again.
+    pub fn stage_leaf_updates(&self, updates: &[(u64, [u8; 32])]) -> Result<StagedTreeUpdate> {
+        let mut staged = StagedTreeUpdate {
+            operations: Vec::new(),
+            root: self.current_root,
+            nodes: HashMap::new(),
+        };
+        for &(leaf_index, leaf_hash) in updates {
+            staged.root = self.stage_path(&mut staged, leaf_index, leaf_hash)?;
+        }
+        Ok(staged)
+    }
+
+    /// Make a committed staged update the tree's current state
+    pub fn apply_staged(&mut self, staged: StagedTreeUpdate) {
+        for ((level, index), hash) in staged.nodes {
+            self.cache_node(level, index, hash);
+        }
+        self.current_root = staged.root;
+        self.root_version += 1;
+    }
+
+    /// Rewrite one leaf's path into `staged`, returning the root it hashes to
+    fn stage_path(&self, staged: &mut StagedTreeUpdate, leaf_index: u64, leaf_hash: [u8; 32]) -> Result<[u8; 32]> {
         let mut current_hash = leaf_hash;
         let mut current_index = leaf_index;
+        staged.nodes.insert((0, leaf_index), leaf_hash);
 
         // Traverse from leaf to root, updating all nodes on the path
         for level in 0..self.depth {
             let sibling_index = current_index ^ 1; // Flip the last bit to get sibling
             let parent_index = current_index >> 1; // Parent is current_index / 2
 
-            // Get sibling hash (either from database or empty subtree)
-            let sibling_hash = self.get_node_hash_at_position(sibling_index, level)?;
+            // Get sibling hash (staged, cached or empty subtree)
+            let sibling_hash = match staged.nodes.get(&(level, sibling_index)) {
+                Some(hash) => *hash,
+                None => self.get_node_hash_at_position(sibling_index, level)?,
+            };
 
             // Compute parent hash based on whether we're left or right child
             let parent_hash = if current_index & 1 == 0 {
@@ -177,7 +352,7 @@ impl CanonicalSMT {
                     level + 1,
                 );
 
-                batch_writer.add_operation(BatchOperation::UpdateSMTNode {
+                staged.operations.push(BatchOperation::UpdateSMTNode {
                     node_hash: parent_hash,
                     left_hash: parent_node.left_hash,
                     right_hash: parent_node.right_hash,
@@ -187,44 +362,37 @@ impl CanonicalSMT {
             }
 
             // Move up to parent for next iteration
+            staged.nodes.insert((level + 1, parent_index), parent_hash);
             current_hash = parent_hash;
             current_index = parent_index;
         }
 
-        // Commit all node updates atomically
-        batch_writer.commit()?;
-
         Ok(current_hash)
     }
 
-    /// Get node hash at specific position and level
-    fn get_node_hash_at_position(&self, index: u64, level: u8) -> Result<[u8; 32]> {
-        // First check if this position would be empty
-        if self.is_subtree_empty(index, level)? {
-            return Ok(self.empty_subtrees[level as usize]);
+    /// Refuse to overwrite a leaf another UTXO occupies
+    pub fn ensure_slot_free(&self, tree_position: u64) -> Result<()> {
+        if self.nodes.contains_key(&(0, tree_position)) {
+            return Err(LeafSlotOccupied { tree_position }.into());
         }
-
-        // Try to find actual node hash by computing what it would be
-        // This is a simplified version - in you'd have more sophisticated tracking
-        
-        // For now, if we can't find it and it's not empty, assume it's empty
-        // This will be improved when we add proper node tracking
-        Ok(self.empty_subtrees[level as usize])
+        Ok(())
     }
 
-    /// Check if a subtree at given position is empty
-    fn is_subtree_empty(&self, index: u64, level: u8) -> Result<bool> {
-        // For now, assume most subtrees are empty unless we have specific data
-        // This would be optimized with proper empty subtree tracking
-        
-        // Check if any leaves exist in this subtree range
-        let subtree_size = 1u64 << level;
-        let subtree_start = index * subtree_size;
-        let _subtree_end = subtree_start + subtree_size;
+    /// Get node hash at specific position and level
+    fn get_node_hash_at_position(&self, index: u64, level: u8) -> Result<[u8; 32]> {
+        Ok(self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty_subtrees[level as usize]))
+    }
 
-        // TODO: Add efficient range query to check for any UTXOs in this range
-        // For now, return true (empty) as conservative estimate
-        Ok(true)
+    /// Record a node hash, dropping positions that are back to empty
+    fn cache_node(&mut self, level: u8, index: u64, hash: [u8; 32]) {
+        if hash == self.empty_subtrees[level as usize] {
+            self.nodes.remove(&(level, index));
+        } else {
+            self.nodes.insert((level, index), hash);
+        }
     }
 
     /// Store leaf mapping in database
@@ -254,10 +422,12 @@ impl CanonicalSMT {
     /// Initialize tree metadata in database
     fn initialize_metadata(&self) -> Result<()> {
         // Store initial tree configuration
-        let key = b"tree_config";
-        let value = format!("depth:{},salt:{},version:{}", self.depth, self.tree_salt, self.root_version);
+        let value = format!(
+            "depth:{},salt:{},version:{},layout:{}",
+            self.depth, self.tree_salt, self.root_version, TREE_LAYOUT_VERSION
+        );
         
-        self.db.put_cf(cf_names::TREE_METADATA, key, value.as_bytes())?;
+        self.db.put_cf(cf_names::TREE_METADATA, TREE_CONFIG_KEY, value.as_bytes())?;
         
         Ok(())
     }
@@ -287,6 +457,196 @@ impl CanonicalSMT {
         self.empty_subtrees.get(level as usize).copied()
     }
 
+    /// Load a stored internal node by hash
+    pub fn get_node(&self, node_hash: &[u8; 32]) -> Result<Option<SMTNode>> {
+        let key = crate::database::schema::utils::create_key_with_prefix(
+            canonical_spec::cf_prefixes::SMT_NODES,
+            &[node_hash],
+        );
+        
+        match self.db.get_cf(cf_names::SMT_NODES, &key)? {
+            Some(value) => Ok(Some(SMTNode::deserialize(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Resolve the children of the current root
+    ///
+    /// The root itself is never stored as a node, so its children are found by
+    /// matching stored nodes one level below it (or leaves for a depth-1 tree).
+    pub fn get_root_children(&self) -> Result<Option<([u8; 32], [u8; 32])>> {
+        if self.depth == 0 || self.current_root == self.empty_subtrees[self.depth as usize] {
+            return Ok(None);
+        }
+        
+        let child_height = self.depth - 1;
+        let mut candidates = vec![self.empty_subtrees[child_height as usize]];
+        
+        if child_height == 0 {
+            for item in self.db.iterator_cf(cf_names::SMT_LEAVES)? {
+                let (_key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+                if value.len() >= 32 {
+                    candidates.push(value[0..32].try_into().unwrap());
+                }
+            }
+        } else {
+            for item in self.db.iterator_cf(cf_names::SMT_NODES)? {
+                let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+                let node = SMTNode::deserialize(&value)?;
+                if node.height == child_height && key.len() == 33 {
+                    candidates.push(key[1..33].try_into().unwrap());
+                }
+            }
+        }
+        
+        for left in &candidates {
+            for right in &candidates {
+                if canonical_spec::generate_node_hash(*left, *right) == self.current_root {
+                    return Ok(Some((*left, *right)));
+                }
+            }
+        }
+        
+        Ok(None)
+    }
+
+    /// Sibling hashes from leaf to root for a UTXO's position
+    ///
+    /// Walks down from the root through cf_smt_nodes, so it fails if a node
+    /// on the path is missing from the database.
+    pub fn generate_proof(&self, utxo_id: &[u8; 32]) -> Result<Vec<[u8; 32]>> {
+        let leaf_index = self.leaf_position(utxo_id);
+        let mut siblings = Vec::with_capacity(self.depth as usize);
+        
+        let (mut left, mut right) = match self.get_root_children()? {
+            Some(children) => children,
+            None if self.current_root == self.empty_subtrees[self.depth as usize] => {
+                (self.empty_subtrees[self.depth as usize - 1], self.empty_subtrees[self.depth as usize - 1])
+            }
+            None => return Err(anyhow!("Children of root {} not found", hex::encode(self.current_root))),
+        };
+        for height in (0..self.depth).rev() {
+            let (current, sibling) = if (leaf_index >> height) & 1 == 0 { (left, right) } else { (right, left) };
+            siblings.push(sibling);
+            
+            if height == 0 {
+                break;
+            }
+            (left, right) = if current == self.empty_subtrees[height as usize] {
+                (self.empty_subtrees[height as usize - 1], self.empty_subtrees[height as usize - 1])
+            } else {
+                let node = self.get_node(&current)?
+                    .ok_or_else(|| anyhow!("Missing SMT node {} at height {}", hex::encode(current), height))?;
+                (node.left_hash, node.right_hash)
+            };
+        }
+        
+        siblings.reverse();
+        Ok(siblings)
+    }
+
+    /// Reconstruct cf_smt_nodes from the leaf mappings in cf_smt_leaves
+    ///
+    /// Recovery path for a lost or corrupted cf_smt_nodes. Every internal
+    /// node is recomputed with a ref count equal to the number of tree
+    /// positions it occupies. The recomputed root must match the latest
+    /// root in cf_root_history (when there is one) before anything is
+    /// written; cf_smt_nodes is then replaced in a single batch.
+    pub fn rebuild_nodes_from_leaves(&mut self) -> Result<[u8; 32]> {
+        let (root, rebuilt_cache, rebuilt_nodes) = self.compute_from_leaves(self.leaf_positions()?);
+        
+        if let Some((version, record)) = RootHistory::new(self.db.clone()).latest_root()? {
+            if record.root_hash != root {
+                return Err(anyhow!(
+                    "Rebuilt root {} does not match root {} recorded for version {}",
+                    hex::encode(root),
+                    hex::encode(record.root_hash),
+                    version
+                ));
+            }
+        }
+        
+        let mut batch = self.db.create_write_batch();
+        let cf = self.db.cf_handle(cf_names::SMT_NODES)?;
+        for item in self.db.iterator_cf(cf_names::SMT_NODES)? {
+            let (key, _) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            batch.delete_cf(cf, &key);
+        }
+        for (node_hash, node) in &rebuilt_nodes {
+            let key = crate::database::schema::utils::create_key_with_prefix(
+                canonical_spec::cf_prefixes::SMT_NODES,
+                &[node_hash],
+            );
+            batch.put_cf(cf, &key, &node.serialize());
+        }
+        self.db.write_batch(batch)?;
+        
+        self.nodes = rebuilt_cache;
+        self.current_root = root;
+        Ok(root)
+    }
+
+    /// Hash leaves by position up to the root
+    ///
+    /// Returns the root, the non-empty node hashes by (level, index) and the
+    /// stored internal nodes with a ref count per position they occupy.
+    fn compute_from_leaves(&self, mut level: HashMap<u64, [u8; 32]>) -> ([u8; 32], NodeCache, HashMap<[u8; 32], SMTNode>) {
+        let mut rebuilt_cache: NodeCache = level.iter()
+            .map(|(&index, &hash)| ((0, index), hash))
+            .collect();
+        let mut rebuilt_nodes: HashMap<[u8; 32], SMTNode> = HashMap::new();
+        for height in 1..=self.depth {
+            let child_empty = self.empty_subtrees[height as usize - 1];
+            let mut parents: HashMap<u64, [u8; 32]> = HashMap::new();
+            for &index in level.keys() {
+                let parent_index = index >> 1;
+                if parents.contains_key(&parent_index) {
+                    continue;
+                }
+                
+                let left = level.get(&(parent_index << 1)).copied().unwrap_or(child_empty);
+                let right = level.get(&((parent_index << 1) | 1)).copied().unwrap_or(child_empty);
+                let parent_hash = canonical_spec::generate_node_hash(left, right);
+                parents.insert(parent_index, parent_hash);
+                
+                // The root is never stored as a node
+                if height < self.depth && parent_hash != self.empty_subtrees[height as usize] {
+                    rebuilt_nodes.entry(parent_hash)
+                        .and_modify(|node| node.ref_count += 1)
+                        .or_insert_with(|| SMTNode::new(left, right, height));
+                }
+            }
+            
+            level = parents;
+            level.retain(|_, hash| *hash != self.empty_subtrees[height as usize]);
+            rebuilt_cache.extend(level.iter().map(|(&index, &hash)| ((height, index), hash)));
+        }
+        let root = level.get(&0).copied().unwrap_or(self.empty_subtrees[self.depth as usize]);
+        
+        (root, rebuilt_cache, rebuilt_nodes)
+    }
+
+    /// Every stored leaf hash by tree position, read from cf_smt_leaves
+    pub fn leaf_positions(&self) -> Result<HashMap<u64, [u8; 32]>> {
+        let mut leaves = HashMap::new();
+        for item in self.db.iterator_cf(cf_names::SMT_LEAVES)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&canonical_spec::cf_prefixes::SMT_LEAVES) {
+                continue;
+            }
+            if value.len() != 40 {
+                return Err(anyhow!("Invalid SMT leaf value length: {}", value.len()));
+            }
+            
+            let leaf_hash: [u8; 32] = value[0..32].try_into()?;
+            let tree_position = u64::from_be_bytes(value[32..40].try_into()?);
+            if leaves.insert(tree_position, leaf_hash).is_some() {
+                return Err(anyhow!("Two leaves map to tree position {}", tree_position));
+            }
+        }
+        Ok(leaves)
+    }
+
     /// Compute tree statistics
     pub fn get_tree_stats(&self) -> Result<TreeStats> {
         // Query database for current tree state
@@ -341,7 +701,11 @@ impl CanonicalSMT {
         // Prepare all updates
         for utxo in utxos {
             let leaf_hash = utxo.leaf_hash()?;
-            let tree_index = canonical_spec::generate_tree_index(utxo.utxo_id, self.tree_salt);
+            let tree_index = self.leaf_position(&utxo.utxo_id);
+            self.ensure_slot_free(tree_index)?;
+            if updates.iter().any(|&(index, _, _)| index == tree_index) {
+                return Err(LeafSlotOccupied { tree_position: tree_index }.into());
+            }
             
             updates.push((tree_index, leaf_hash, utxo.utxo_id));
 
@@ -427,6 +791,7 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
     use crate::database::schema::DBConfig;
+    use crate::merkle::{EnhancedMerkleTree, LeafPlacement};
 
     #[test]
     fn test_smt_creation() {
@@ -497,4 +862,202 @@ mod tests {
         assert_eq!(stats.total_utxos, 0);
         assert_eq!(stats.total_nodes, 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_salt_mismatch_with_existing_utxos_is_refused() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let open = || DatabaseManager::open(DBConfig { db_path: db_path.clone(), ..Default::default() }).unwrap();
+        
+        // An empty tree may still change its salt
+        CanonicalSMT::new(open(), 16, 1).unwrap();
+        let mut smt = CanonicalSMT::new(open(), 16, 2).unwrap();
+        
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, [2u8; 32]);
+        smt.insert_utxo(&utxo).unwrap();
+        drop(smt);
+        
+        // Reopen the populated database as a restart would
+        let err = CanonicalSMT::new(open(), 16, 3).err().expect("salt mismatch must be refused");
+        assert!(err.to_string().contains("refusing to start"), "unexpected error: {}", err);
+        assert_eq!(CanonicalSMT::stored_tree_salt(&open()).unwrap(), Some(2));
+        
+        // The persisted salt is adopted by default
+        assert_eq!(CanonicalSMT::with_default_config(open()).unwrap().get_tree_salt(), 2);
+        assert!(CanonicalSMT::new(open(), 16, 2).is_ok());
+    }
+
+    #[test]
+    fn test_rebuild_nodes_from_leaves_restores_root_and_proofs() {
+        use crate::database::root_history::RootRecord;
+        
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let history = RootHistory::new(db_manager.clone());
+        let record = |root_hash| RootRecord {
+            root_hash,
+            batch_id: 1,
+            timestamp: 1_700_000_000,
+            tx_count: 1,
+            operator_signature: vec![],
+            finalized: false,
+        };
+        
+        let mut smt = CanonicalSMT::new(db_manager.clone(), 16, 777).unwrap();
+        let utxos: Vec<_> = (1..=5u8)
+            .map(|i| CanonicalUTXO::new_eth([i; 32], 0, 100, i as u64, 1_000 * i as u128, [i; 32]))
+            .collect();
+        for utxo in &utxos {
+            smt.insert_utxo(utxo).unwrap();
+        }
+        smt.remove_utxo(&utxos[4].utxo_id).unwrap();
+        let root = smt.get_root();
+        history.prepare_root(smt.get_root_version(), record(root)).unwrap();
+        let proofs: Vec<_> = utxos[..4].iter().map(|utxo| smt.generate_proof(&utxo.utxo_id).unwrap()).collect();
+        drop(smt);
+        
+        // Lose every internal node
+        let keys: Vec<_> = db_manager.iterator_cf(cf_names::SMT_NODES).unwrap().map(|item| item.unwrap().0).collect();
+        for key in keys {
+            db_manager.delete_cf(cf_names::SMT_NODES, &key).unwrap();
+        }
+        
+        // A root that disagrees with the leaves is refused before anything is written
+        history.prepare_root(99, record([0xAB; 32])).unwrap();
+        let mut smt = CanonicalSMT::new(db_manager.clone(), 16, 777).unwrap();
+        assert!(smt.rebuild_nodes_from_leaves().is_err());
+        assert_eq!(smt.get_tree_stats().unwrap().total_nodes, 0);
+        history.abandon_root(99).unwrap();
+        
+        assert_eq!(smt.rebuild_nodes_from_leaves().unwrap(), root);
+        assert_eq!(smt.get_root(), root);
+        for (utxo, proof) in utxos[..4].iter().zip(&proofs) {
+            let regenerated = smt.generate_proof(&utxo.utxo_id).unwrap();
+            assert_eq!(&regenerated, proof);
+            
+            let position = smt.leaf_position(&utxo.utxo_id);
+            let computed = regenerated.iter().enumerate().fold(utxo.leaf_hash().unwrap(), |hash, (level, sibling)| {
+                if (position >> level) & 1 == 0 {
+                    canonical_spec::generate_node_hash(hash, *sibling)
+                } else {
+                    canonical_spec::generate_node_hash(*sibling, hash)
+                }
+            });
+            assert_eq!(computed, root);
+        }
+        
+        // Each rebuilt node is referenced once per position it occupies
+        let nodes = smt.get_tree_stats().unwrap().total_nodes;
+        assert!(nodes > 0 && nodes <= 4 * 15);
+        for item in db_manager.iterator_cf(cf_names::SMT_NODES).unwrap() {
+            assert_eq!(SMTNode::deserialize(&item.unwrap().1).unwrap().ref_count, 1);
+        }
+        
+        // Inserting on top of the rebuilt tree continues from the restored state
+        let mut reference = CanonicalSMT::new(DatabaseManager::open(DBConfig {
+            db_path: temp_dir.path().join("reference_db").to_string_lossy().to_string(),
+            ..Default::default()
+        }).unwrap(), 16, 777).unwrap();
+        for utxo in &utxos[..4] {
+            reference.insert_utxo(utxo).unwrap();
+        }
+        assert_eq!(reference.get_root(), root);
+        assert_eq!(smt.insert_utxo(&utxos[4]).unwrap(), reference.insert_utxo(&utxos[4]).unwrap());
+    }
+
+    #[test]
+    fn test_reopen_restores_tree_state() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let open = || DatabaseManager::open(DBConfig { db_path: db_path.clone(), ..Default::default() }).unwrap();
+        let utxos: Vec<_> = (1..=4u8)
+            .map(|i| CanonicalUTXO::new_eth([i; 32], 0, 100, i as u64, 1_000 * i as u128, [i; 32]))
+            .collect();
+        
+        let mut smt = CanonicalSMT::new(open(), 16, 42).unwrap();
+        for utxo in &utxos[..3] {
+            smt.insert_utxo(utxo).unwrap();
+        }
+        let root = smt.get_root();
+        let mut reference = CanonicalSMT::new(DatabaseManager::open(DBConfig {
+            db_path: temp_dir.path().join("reference_db").to_string_lossy().to_string(),
+            ..Default::default()
+        }).unwrap(), 16, 42).unwrap();
+        for utxo in &utxos[..3] {
+            reference.insert_utxo(utxo).unwrap();
+        }
+        drop(smt);
+        
+        // Siblings come from the restored cache, not the empty tree
+        let mut smt = CanonicalSMT::new(open(), 16, 42).unwrap();
+        assert_eq!(smt.get_root(), root);
+        assert_eq!(smt.insert_utxo(&utxos[3]).unwrap(), reference.insert_utxo(&utxos[3]).unwrap());
+        assert_eq!(CanonicalSMT::stored_layout_version(&open()).unwrap(), Some(TREE_LAYOUT_VERSION));
+        drop(smt);
+        
+        // Leaves written before layouts were versioned are refused
+        open().put_cf(cf_names::TREE_METADATA, TREE_CONFIG_KEY, b"depth:16,salt:42,version:4").unwrap();
+        let err = CanonicalSMT::new(open(), 16, 42).err().expect("layout 1 must be refused");
+        assert!(err.to_string().contains("layout 1"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_insert_into_occupied_slot_is_refused() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let mut smt = CanonicalSMT::new(DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap(), 2, 9).unwrap();
+        
+        // Five ids over four slots must collide
+        let mut collision = None;
+        for i in 1..=5u8 {
+            let utxo = CanonicalUTXO::new_eth([i; 32], 0, 100, i as u64, 1_000, [i; 32]);
+            let root = smt.get_root();
+            if let Err(err) = smt.insert_utxo(&utxo) {
+                collision = Some((err, utxo, root));
+                break;
+            }
+        }
+        let (err, utxo, root) = collision.expect("a slot must be reused");
+        assert_eq!(
+            err.downcast_ref::<LeafSlotOccupied>(),
+            Some(&LeafSlotOccupied { tree_position: smt.leaf_position(&utxo.utxo_id) })
+        );
+        assert_eq!(smt.get_root(), root);
+    }
+
+    #[test]
+    fn test_enhanced_tree_by_id_matches_smt() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let tree_salt = 12345;
+        let mut smt = CanonicalSMT::new(db_manager, 16, tree_salt).unwrap();
+        let mut tree = EnhancedMerkleTree::with_placement(16, LeafPlacement::ById { tree_salt }).unwrap();
+        assert_eq!(tree.get_root(), smt.get_root());
+        
+        for i in 1..=4u8 {
+            let utxo = CanonicalUTXO::new_eth([i; 32], 0, 100, i as u64, 1_000 * i as u128, [i; 32]);
+            let smt_root = smt.insert_utxo(&utxo).unwrap();
+            let position = tree.insert_canonical_utxo(&utxo).unwrap();
+            
+            assert_eq!(position, smt.leaf_position(&utxo.utxo_id));
+            assert_eq!(tree.get_root(), smt_root);
+            
+            // Proofs are built over the id position
+            let proof = tree.get_proof(position).unwrap();
+            assert!(tree.verify_proof(&proof, utxo.leaf_hash().unwrap()).unwrap());
+        }
+        
+        // Append placement keeps insertion order
+        let mut appended = EnhancedMerkleTree::with_depth(16).unwrap();
+        let utxo = CanonicalUTXO::new_eth([9u8; 32], 0, 100, 9, 9_000, [9u8; 32]);
+        assert_eq!(appended.insert_canonical_utxo(&utxo).unwrap(), 0);
+    }
+}
@@ -3,8 +3,11 @@
 //! Production-ready with RocksDB persistence and reorg handling
 
 use crate::utxo::transaction::MerkleProof;
-use crate::crypto::{CryptoResult, CryptoError, ArchitectureCompliantCrypto};
+use crate::crypto::{CryptoResult, CryptoError, CryptoUtils, ArchitectureCompliantCrypto, HashFunction, PathBits, PoseidonHash};
 use crate::database::DatabaseManager;
+use crate::merkle::{LeafPlacement, PairOrdering};
+use crate::utxo::CanonicalUTXO;
+use crate::canonical_spec;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use anyhow::Result;
@@ -31,6 +34,48 @@ pub struct EnhancedMerkleTree {
     pub next_leaf_index: u64,
     /// Root version counter
     pub root_version: u64,
+    /// Leaf index allocation strategy
+    ///
+    /// `ById` also switches to the canonical SMT leaf and node hashes, so the
+    /// tree reproduces `CanonicalSMT` positions and roots exactly.
+    #[serde(default)]
+    pub placement: LeafPlacement,
+    /// Sibling ordering for internal node hashes
+    ///
+    /// `SortedPairs` yields direction-agnostic proofs that verify without
+    /// path bits, at the cost of a root incompatible with positional trees.
+    #[serde(default)]
+    pub pair_ordering: PairOrdering,
+    /// Hash behind the leaf and node hashes under `Append`
+    ///
+    /// New trees default to `Sha256`. `Poseidon` hashes like `CanonicalSMT`
+    /// so its roots match the SMT's; `ById` always uses those hashes too.
+    /// Trees persisted before this field existed hashed with `Blake2b256`.
+    #[serde(default = "legacy_hash_function")]
+    pub hash_function: HashFunction,
+}
+
+fn default_hash_function() -> HashFunction {
+    HashFunction::Sha256
+}
+
+fn legacy_hash_function() -> HashFunction {
+    HashFunction::Blake2b256
+}
+
+/// Domain-separated hash of `parts` under `hash_function`
+fn domain_hash(hash_function: HashFunction, domain: &[u8], parts: &[&[u8; 32]]) -> CryptoResult<[u8; 32]> {
+    let mut input = domain.to_vec();
+    for part in parts {
+        input.extend_from_slice(*part);
+    }
+
+    match hash_function {
+        HashFunction::Sha256 => Ok(CryptoUtils::sha256(&input)),
+        HashFunction::Blake2b256 => Ok(CryptoUtils::blake2b256(&input)),
+        HashFunction::Keccak256 => Ok(CryptoUtils::keccak256(&input)),
+        HashFunction::Poseidon => PoseidonHash::new().hash(&input),
+    }
 }
 
 /// Enhanced Merkle Tree with database persistence
@@ -45,13 +90,26 @@ impl EnhancedMerkleTree {
         Self::with_depth(32)
     }
 
+    /// Create new enhanced Merkle tree of depth 32 hashing with `hash_function`
+    pub fn new_with_hasher(hash_function: HashFunction) -> CryptoResult<Self> {
+        Self::new()?.with_hash_function(hash_function)
+    }
+
     /// Create new enhanced Merkle tree with specified depth
     pub fn with_depth(depth: u8) -> CryptoResult<Self> {
+        Self::with_placement(depth, LeafPlacement::Append)
+    }
+
+    /// Create new enhanced Merkle tree with specified depth and leaf placement
+    pub fn with_placement(depth: u8, placement: LeafPlacement) -> CryptoResult<Self> {
         if depth == 0 || depth > 32 {
             return Err(CryptoError::InvalidInput("Tree depth must be between 1 and 32".to_string()));
         }
 
-        let empty_hashes = Self::compute_empty_hashes(depth)?;
+        let empty_hashes = match placement {
+            LeafPlacement::Append => Self::compute_empty_hashes(depth, default_hash_function())?,
+            LeafPlacement::ById { .. } => canonical_spec::precompute_empty_subtrees(depth),
+        };
         let root = empty_hashes[depth as usize];
 
         Ok(Self {
@@ -63,13 +121,55 @@ impl EnhancedMerkleTree {
             empty_hashes,
             next_leaf_index: 0,
             root_version: 0,
+            placement,
+            pair_ordering: PairOrdering::Positional,
+            hash_function: default_hash_function(),
         })
     }
 
+    /// Switch the hash function of a still-empty tree
+    ///
+    /// Recomputes the empty subtree hashes and root. Id placement must
+    /// reproduce `CanonicalSMT` roots and keeps its canonical hashes.
+    pub fn with_hash_function(mut self, hash_function: HashFunction) -> CryptoResult<Self> {
+        if self.leaf_count > 0 {
+            return Err(CryptoError::InvalidInput("Hash function can only be changed on an empty tree".to_string()));
+        }
+        if self.placement != LeafPlacement::Append {
+            return Err(CryptoError::InvalidInput("Placement by id uses the canonical SMT hashes".to_string()));
+        }
+
+        self.empty_hashes = Self::compute_empty_hashes(self.depth, hash_function)?;
+        self.root = self.empty_hashes[self.depth as usize];
+        self.hash_function = hash_function;
+        Ok(self)
+    }
+
+    /// Switch the sibling ordering of a still-empty tree
+    ///
+    /// Empty subtree hashes pair identical children, so they are the same
+    /// under either ordering. Id placement must reproduce `CanonicalSMT`
+    /// roots and stays positional.
+    pub fn with_pair_ordering(mut self, pair_ordering: PairOrdering) -> CryptoResult<Self> {
+        if self.leaf_count > 0 {
+            return Err(CryptoError::InvalidInput("Pair ordering can only be changed on an empty tree".to_string()));
+        }
+        if pair_ordering != PairOrdering::Positional && self.placement != LeafPlacement::Append {
+            return Err(CryptoError::InvalidInput("Placement by id requires positional pair ordering".to_string()));
+        }
+
+        self.pair_ordering = pair_ordering;
+        Ok(self)
+    }
+
     /// Insert a commitment into the tree (idempotent)
     /// Returns the leaf index where the commitment was inserted
     /// API: insert_leaf(commitment: [u8;32]) -> Result<leaf_index: u64>
     pub fn insert_leaf(&mut self, commitment: [u8; 32]) -> CryptoResult<u64> {
+        if self.placement != LeafPlacement::Append {
+            return Err(CryptoError::InvalidInput("Placement by id requires insert_canonical_utxo".to_string()));
+        }
+
         // Check if commitment already exists (idempotent)
         if let Some(&existing_index) = self.commitment_to_index.get(&commitment) {
             return Ok(existing_index);
@@ -82,9 +182,84 @@ impl EnhancedMerkleTree {
         }
 
         let leaf_index = self.next_leaf_index;
+        self.insert_at(commitment, leaf_index)?;
+        self.next_leaf_index += 1;
 
+        Ok(leaf_index)
+    }
+
+    /// Drop every appended leaf at index `leaf_count` or above
+    ///
+    /// Undoes the `insert_leaf` calls made since the tree held `leaf_count`
+    /// leaves: their nodes are removed and the boundary path is rehashed, so
+    /// the root returns to what it was at that point.
+    pub fn truncate_leaves(&mut self, leaf_count: u64) -> CryptoResult<()> {
+        if self.placement != LeafPlacement::Append {
+            return Err(CryptoError::InvalidInput("Only appended leaves can be truncated".to_string()));
+        }
+        if leaf_count >= self.next_leaf_index {
+            return Ok(());
+        }
+
+        // Nodes whose subtree starts at or past `leaf_count` cover only dropped leaves
+        self.commitment_to_index.retain(|_, index| *index < leaf_count);
+        for level in 0..=self.depth {
+            let first_dropped = (leaf_count + (1u64 << level) - 1) >> level;
+            if let Some(level_nodes) = self.nodes.get_mut(&level) {
+                level_nodes.retain(|index, _| *index < first_dropped);
+            }
+        }
+
+        // Rehash the path above the last kept leaf
+        let mut root = self.empty_hashes[self.depth as usize];
+        if leaf_count > 0 {
+            let mut index = leaf_count - 1;
+            for level in 1..=self.depth {
+                index /= 2;
+                let left = self.get_node_hash(level - 1, 2 * index);
+                let right = self.get_node_hash(level - 1, 2 * index + 1);
+                root = self.hash_node(&left, &right)?;
+                self.nodes.entry(level).or_insert_with(HashMap::new).insert(index, root);
+            }
+        } else {
+            self.nodes.clear();
+        }
+
+        self.root = root;
+        self.root_version += 1;
+        self.leaf_count = leaf_count;
+        self.next_leaf_index = leaf_count;
+        Ok(())
+    }
+
+    /// Insert a UTXO's leaf hash (idempotent)
+    ///
+    /// Under `Append` the leaf hash is appended like any commitment. Under
+    /// `ById` it is placed at the UTXO's SMT position, failing if another
+    /// leaf already occupies it.
+    pub fn insert_canonical_utxo(&mut self, utxo: &CanonicalUTXO) -> CryptoResult<u64> {
+        let commitment = utxo.leaf_hash()
+            .map_err(|e| CryptoError::SerializationError(e.to_string()))?;
+
+        let Some(leaf_index) = self.placement.id_position(&utxo.utxo_id, self.depth) else {
+            return self.insert_leaf(commitment);
+        };
+
+        if let Some(&existing_index) = self.commitment_to_index.get(&commitment) {
+            return Ok(existing_index);
+        }
+        if self.get_leaf(leaf_index).is_some() {
+            return Err(CryptoError::InvalidInput(format!("Leaf position {} already occupied", leaf_index)));
+        }
+
+        self.insert_at(commitment, leaf_index)?;
+        Ok(leaf_index)
+    }
+
+    /// Write a new leaf at `leaf_index` and update its path to the root
+    fn insert_at(&mut self, commitment: [u8; 32], leaf_index: u64) -> CryptoResult<()> {
         // Hash the commitment to get leaf hash
-        let leaf_hash = ArchitectureCompliantCrypto::hash_merkle_leaf(&commitment)?;
+        let leaf_hash = self.hash_leaf(&commitment)?;
 
         // Insert leaf at level 0
         self.nodes.entry(0).or_insert_with(HashMap::new).insert(leaf_index, leaf_hash);
@@ -109,10 +284,10 @@ impl EnhancedMerkleTree {
             // Compute parent hash
             let parent_hash = if is_right_child {
                 // Current node is right child
-                ArchitectureCompliantCrypto::hash_merkle_node(&sibling_hash, &current_hash)?
+                self.hash_node(&sibling_hash, &current_hash)?
             } else {
                 // Current node is left child
-                ArchitectureCompliantCrypto::hash_merkle_node(&current_hash, &sibling_hash)?
+                self.hash_node(&current_hash, &sibling_hash)?
             };
 
             // Store parent node
@@ -129,11 +304,34 @@ impl EnhancedMerkleTree {
         // Update commitment lookup
         self.commitment_to_index.insert(commitment, leaf_index);
 
-        // Increment counters
         self.leaf_count += 1;
-        self.next_leaf_index += 1;
 
-        Ok(leaf_index)
+        Ok(())
+    }
+
+    /// Leaf hash of a commitment under this tree's placement
+    fn hash_leaf(&self, commitment: &[u8; 32]) -> CryptoResult<[u8; 32]> {
+        match self.placement {
+            LeafPlacement::Append => match self.hash_function {
+                HashFunction::Blake2b256 => ArchitectureCompliantCrypto::hash_merkle_leaf(commitment),
+                // Leaves as CanonicalSMT stores them
+                HashFunction::Poseidon => Ok(*commitment),
+                other => domain_hash(other, b"PRIVPOOL_LEAF_V1", &[commitment]),
+            },
+            // CanonicalSMT stores the UTXO leaf hash itself
+            LeafPlacement::ById { .. } => Ok(*commitment),
+        }
+    }
+
+    /// Internal node hash under this tree's placement and pair ordering
+    fn hash_node(&self, left: &[u8; 32], right: &[u8; 32]) -> CryptoResult<[u8; 32]> {
+        match self.placement {
+            LeafPlacement::Append => {
+                let (first, second) = self.pair_ordering.order(left, right);
+                Self::hash_append_node(self.hash_function, first, second)
+            }
+            LeafPlacement::ById { .. } => Ok(canonical_spec::generate_node_hash(*left, *right)),
+        }
     }
 
     /// Legacy insert method for backward compatibility
@@ -143,12 +341,11 @@ impl EnhancedMerkleTree {
 
     /// Get Merkle proof for a leaf at given index
     pub fn get_proof(&self, leaf_index: u64) -> CryptoResult<MerkleProof> {
-        if leaf_index >= self.leaf_count {
+        if self.get_leaf(leaf_index).is_none() {
             return Err(CryptoError::InvalidInput("Leaf index out of bounds".to_string()));
         }
 
         let mut siblings = Vec::new();
-        let mut path_bits = Vec::new();
         let mut current_index = leaf_index;
 
         // Collect siblings for each level
@@ -162,15 +359,20 @@ impl EnhancedMerkleTree {
 
             let sibling_hash = self.get_node_hash(level, sibling_index);
             siblings.push(sibling_hash);
-            path_bits.push(if is_right_child { 1 } else { 0 });
 
             current_index /= 2;
         }
 
+        // Sorted pairs hash the same whichever side a sibling is on
+        let path = match self.pair_ordering {
+            PairOrdering::Positional => PathBits::from_leaf_index(leaf_index, self.depth as usize).to_u32s(),
+            PairOrdering::SortedPairs => Vec::new(),
+        };
+
         Ok(MerkleProof {
             leaf_index,
             siblings,
-            path: path_bits,
+            path,
             root: self.root,
         })
     }
@@ -180,6 +382,11 @@ impl EnhancedMerkleTree {
         self.root
     }
 
+    /// Empty subtree hash per level, the table compressed proofs reference
+    pub fn empty_hashes(&self) -> &[[u8; 32]] {
+        &self.empty_hashes
+    }
+
     /// Get node hash at specific level and index
     /// Returns empty hash if node doesn't exist
     fn get_node_hash(&self, level: u8, index: u64) -> [u8; 32] {
@@ -199,8 +406,22 @@ impl EnhancedMerkleTree {
         }
     }
 
+    /// Node hash of the `Append` placement under `hash_function`
+    fn hash_append_node(hash_function: HashFunction, left: &[u8; 32], right: &[u8; 32]) -> CryptoResult<[u8; 32]> {
+        match hash_function {
+            HashFunction::Blake2b256 => ArchitectureCompliantCrypto::hash_merkle_node(left, right),
+            // The CanonicalSMT node hash, so roots match the SMT's
+            HashFunction::Poseidon => Ok(canonical_spec::generate_node_hash(*left, *right)),
+            other => domain_hash(other, b"PRIVPOOL_NODE_V1", &[left, right]),
+        }
+    }
+
     /// Pre-compute empty subtree hashes for efficiency
-    fn compute_empty_hashes(depth: u8) -> CryptoResult<Vec<[u8; 32]>> {
+    fn compute_empty_hashes(depth: u8, hash_function: HashFunction) -> CryptoResult<Vec<[u8; 32]>> {
+        if hash_function == HashFunction::Poseidon {
+            return Ok(canonical_spec::precompute_empty_subtrees(depth));
+        }
+
         let mut empty_hashes = Vec::with_capacity((depth + 1) as usize);
 
         // Level 0: empty leaf (all zeros)
@@ -209,7 +430,7 @@ impl EnhancedMerkleTree {
         // Level 1 to depth: empty internal nodes
         for _level in 1..=depth {
             let child_empty = empty_hashes[empty_hashes.len() - 1];
-            let parent_empty = ArchitectureCompliantCrypto::hash_merkle_node(&child_empty, &child_empty)?;
+            let parent_empty = Self::hash_append_node(hash_function, &child_empty, &child_empty)?;
             empty_hashes.push(parent_empty);
         }
 
@@ -226,6 +447,9 @@ impl EnhancedMerkleTree {
     }
 
     /// Verify a Merkle proof against a specific root
+    ///
+    /// Under `SortedPairs` the path bits and leaf index are not consulted,
+    /// so the proof shows membership but not position.
     pub fn verify_proof_with_root(
         &self,
         proof: &MerkleProof,
@@ -236,28 +460,25 @@ impl EnhancedMerkleTree {
             return Ok(false);
         }
 
-        if proof.path.len() != self.depth as usize {
-            return Ok(false);
+        // Path must be exactly the one leading to the claimed leaf index
+        if self.pair_ordering == PairOrdering::Positional {
+            match PathBits::try_from(proof.path.as_slice()) {
+                Ok(path) if path == PathBits::from_leaf_index(proof.leaf_index, self.depth as usize) => {}
+                _ => return Ok(false),
+            }
         }
 
         // Start with leaf hash
-        let mut current_hash = ArchitectureCompliantCrypto::hash_merkle_leaf(&commitment)?;
+        let mut current_hash = self.hash_leaf(&commitment)?;
         let mut current_index = proof.leaf_index;
 
         // Traverse up the tree
-        for (sibling, &path_bit) in proof.siblings.iter().zip(proof.path.iter()) {
-            let is_right_child = path_bit == 1;
-
-            // Verify path bit matches index
-            if (current_index % 2 == 1) != is_right_child {
-                return Ok(false);
-            }
-
+        for sibling in &proof.siblings {
             // Compute parent hash
-            current_hash = if is_right_child {
-                ArchitectureCompliantCrypto::hash_merkle_node(sibling, &current_hash)?
+            current_hash = if current_index % 2 == 1 {
+                self.hash_node(sibling, &current_hash)?
             } else {
-                ArchitectureCompliantCrypto::hash_merkle_node(&current_hash, sibling)?
+                self.hash_node(&current_hash, sibling)?
             };
 
             current_index /= 2;
@@ -319,9 +540,6 @@ impl EnhancedMerkleTree {
 
     /// Get leaf at specific index
     pub fn get_leaf(&self, leaf_index: u64) -> Option<[u8; 32]> {
-        if leaf_index >= self.leaf_count {
-            return None;
-        }
         self.nodes.get(&0)?.get(&leaf_index).copied()
     }
 
@@ -468,6 +686,129 @@ mod tests {
         }
     }
 
+    /// Root rebuilt level by level from every leaf, padding with empty subtrees
+    fn full_rebuild_root(tree: &EnhancedMerkleTree, commitments: &[[u8; 32]]) -> [u8; 32] {
+        let mut level: Vec<[u8; 32]> = commitments.iter().map(|c| tree.hash_leaf(c).unwrap()).collect();
+        for height in 0..tree.depth as usize {
+            if level.len() % 2 == 1 {
+                level.push(tree.empty_hashes[height]);
+            }
+            level = level.chunks_exact(2).map(|pair| tree.hash_node(&pair[0], &pair[1]).unwrap()).collect();
+        }
+        level.first().copied().unwrap_or(tree.empty_hashes[tree.depth as usize])
+    }
+
+    #[test]
+    fn test_incremental_root_matches_full_rebuild() {
+        let mut tree = EnhancedMerkleTree::with_depth(20).unwrap();
+        let commitments: Vec<[u8; 32]> = (0..1024u32)
+            .map(|i| CryptoUtils::blake2b256(&i.to_be_bytes()))
+            .collect();
+
+        for commitment in &commitments {
+            tree.insert_leaf(*commitment).unwrap();
+        }
+
+        assert_eq!(tree.get_root(), full_rebuild_root(&tree, &commitments));
+        // Each insert touches one path: depth + 1 nodes, not the whole tree
+        let cached: usize = tree.nodes.values().map(HashMap::len).sum();
+        assert_eq!(cached, (0..=20).map(|level| 1024usize.div_ceil(1 << level)).sum::<usize>());
+
+        // Proofs come from the cached levels and open against the same root
+        for index in [0u64, 511, 1023] {
+            let proof = tree.get_proof(index).unwrap();
+            assert!(tree.verify_proof(&proof, commitments[index as usize]).unwrap());
+        }
+
+        // Serialized trees carry the cached levels and keep extending incrementally
+        let mut restored: EnhancedMerkleTree = bincode::deserialize(&bincode::serialize(&tree).unwrap()).unwrap();
+        let extra = CryptoUtils::random_32();
+        restored.insert_leaf(extra).unwrap();
+        let mut extended = commitments.clone();
+        extended.push(extra);
+        assert_eq!(restored.get_root(), full_rebuild_root(&restored, &extended));
+    }
+
+    #[test]
+    fn test_truncate_leaves_restores_earlier_root() {
+        let mut tree = EnhancedMerkleTree::with_depth(8).unwrap();
+        let empty_root = tree.get_root();
+        let commitments: Vec<[u8; 32]> = (0..11u8).map(|i| [i; 32]).collect();
+        for commitment in &commitments[..5] {
+            tree.insert_leaf(*commitment).unwrap();
+        }
+        let root_at_five = tree.get_root();
+        for commitment in &commitments[5..] {
+            tree.insert_leaf(*commitment).unwrap();
+        }
+
+        tree.truncate_leaves(5).unwrap();
+        assert_eq!(tree.get_root(), root_at_five);
+        assert_eq!(tree.size(), 5);
+        assert!(!tree.has_commitment(&commitments[5]));
+        assert!(tree.get_leaf(5).is_none());
+
+        // Dropped leaves can be appended again at the same positions
+        assert_eq!(tree.insert_leaf(commitments[7]).unwrap(), 5);
+        assert_eq!(tree.get_root(), full_rebuild_root(&tree, &[&commitments[..5], &[commitments[7]]].concat()));
+
+        tree.truncate_leaves(0).unwrap();
+        assert_eq!(tree.get_root(), empty_root);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn test_configurable_hash_function() {
+        let commitments: Vec<[u8; 32]> = (0..5u8).map(|i| [i; 32]).collect();
+        let build = |hash_function: HashFunction| {
+            let mut tree = EnhancedMerkleTree::with_depth(8).unwrap().with_hash_function(hash_function).unwrap();
+            for commitment in &commitments {
+                tree.insert_leaf(*commitment).unwrap();
+            }
+            tree
+        };
+
+        let poseidon = build(HashFunction::Poseidon);
+        let sha256 = build(HashFunction::Sha256);
+        assert_ne!(poseidon.get_root(), sha256.get_root());
+        assert_ne!(poseidon.empty_hashes[8], sha256.empty_hashes[8]);
+        // Independently built trees agree, so Poseidon roots are reproducible
+        assert_eq!(poseidon.get_root(), build(HashFunction::Poseidon).get_root());
+        // Poseidon trees hash like CanonicalSMT, empty and filled
+        let by_id = EnhancedMerkleTree::with_placement(8, LeafPlacement::ById { tree_salt: 0 }).unwrap();
+        assert_eq!(poseidon.empty_hashes, by_id.empty_hashes);
+        let mut level = commitments.clone();
+        for height in 0..8 {
+            level.resize(level.len() + level.len() % 2, poseidon.empty_hashes[height]);
+            level = level.chunks(2).map(|pair| canonical_spec::generate_node_hash(pair[0], pair[1])).collect();
+        }
+        assert_eq!(poseidon.get_root(), level[0]);
+
+        for (index, commitment) in commitments.iter().enumerate() {
+            let poseidon_proof = poseidon.get_proof(index as u64).unwrap();
+            let sha256_proof = sha256.get_proof(index as u64).unwrap();
+            assert!(poseidon.verify_proof(&poseidon_proof, *commitment).unwrap());
+            assert!(sha256.verify_proof(&sha256_proof, *commitment).unwrap());
+            // A proof only opens under the hasher that produced it
+            assert!(!sha256.verify_proof_with_root(&poseidon_proof, *commitment, poseidon.get_root()).unwrap());
+        }
+
+        // New trees default to SHA-256; Blake2b keeps the original hashing
+        assert_eq!(EnhancedMerkleTree::with_depth(8).unwrap().hash_function, HashFunction::Sha256);
+        assert_eq!(EnhancedMerkleTree::new().unwrap().hash_function, HashFunction::Sha256);
+        let legacy = build(HashFunction::Blake2b256);
+        assert_eq!(
+            legacy.get_leaf(0),
+            Some(ArchitectureCompliantCrypto::hash_merkle_leaf(&commitments[0]).unwrap())
+        );
+        let restored: EnhancedMerkleTree = bincode::deserialize(&bincode::serialize(&poseidon).unwrap()).unwrap();
+        assert_eq!(restored.hash_function, HashFunction::Poseidon);
+        assert_eq!(EnhancedMerkleTree::new_with_hasher(HashFunction::Keccak256).unwrap().depth, 32);
+
+        // Switching hashers is only allowed before the first leaf
+        assert!(build(HashFunction::Sha256).with_hash_function(HashFunction::Poseidon).is_err());
+    }
+
     #[test]
     fn test_duplicate_commitment_handling() {
         let mut tree = EnhancedMerkleTree::with_depth(4).unwrap();
@@ -496,4 +837,66 @@ mod tests {
         assert_eq!(stats_filled.leaf_count, 2);
         assert!(stats_filled.nodes_stored > 0);
     }
+
+    #[test]
+    fn test_sorted_pairs_proof_verifies_without_path_bits() {
+        let mut positional = EnhancedMerkleTree::with_depth(4).unwrap();
+        let mut sorted = EnhancedMerkleTree::with_depth(4).unwrap()
+            .with_pair_ordering(PairOrdering::SortedPairs).unwrap();
+        assert_eq!(positional.get_root(), sorted.get_root());
+
+        for i in 1..=5u8 {
+            positional.insert([i; 32]).unwrap();
+            sorted.insert([i; 32]).unwrap();
+        }
+        assert_ne!(positional.get_root(), sorted.get_root());
+
+        let proof = sorted.get_proof(3).unwrap();
+        assert!(proof.path.is_empty());
+        assert!(sorted.verify_proof(&proof, [4u8; 32]).unwrap());
+
+        // Siblings alone are enough; the claimed index plays no part
+        let direction_free = MerkleProof { leaf_index: 0, ..proof.clone() };
+        assert!(sorted.verify_proof(&direction_free, [4u8; 32]).unwrap());
+        assert!(!sorted.verify_proof(&direction_free, [9u8; 32]).unwrap());
+
+        // A positional tree still insists on path bits
+        let positional_proof = MerkleProof { root: positional.get_root(), ..proof };
+        assert!(!positional.verify_proof(&positional_proof, [4u8; 32]).unwrap());
+
+        assert!(sorted.with_pair_ordering(PairOrdering::Positional).is_err());
+        assert!(EnhancedMerkleTree::with_placement(4, LeafPlacement::ById { tree_salt: 1 }).unwrap()
+            .with_pair_ordering(PairOrdering::SortedPairs).is_err());
+    }
+
+    #[test]
+    fn test_compressed_proof_round_trip() {
+        use crate::utxo::CompressedMerkleProof;
+        
+        let mut tree = EnhancedMerkleTree::new().unwrap();
+        let commitments = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        for commitment in &commitments {
+            tree.insert_leaf(*commitment).unwrap();
+        }
+        
+        let proof = tree.get_proof(2).unwrap();
+        let compressed = proof.compress(tree.empty_hashes()).unwrap();
+        
+        // Only the level 1 sibling (hash of leaves 0 and 1) is non-empty
+        assert_eq!(compressed.siblings.len(), 1);
+        assert_eq!(compressed.depth, 32);
+        let full_size = bincode::serialize(&proof).unwrap().len();
+        let compressed_size = bincode::serialize(&compressed).unwrap().len();
+        assert!(compressed_size * 10 < full_size, "{} vs {} bytes", compressed_size, full_size);
+        
+        let restored = MerkleProof::decompress(&compressed, tree.empty_hashes()).unwrap();
+        assert_eq!(restored.siblings, proof.siblings);
+        assert_eq!(restored.path, proof.path);
+        assert!(tree.verify_proof(&restored, commitments[2]).unwrap());
+        
+        // A table that does not cover every empty level cannot rebuild it
+        assert!(MerkleProof::decompress(&compressed, &tree.empty_hashes()[..8]).is_err());
+        let truncated = CompressedMerkleProof { siblings: Vec::new(), ..compressed };
+        assert!(MerkleProof::decompress(&truncated, tree.empty_hashes()).is_err());
+    }
 }
@@ -0,0 +1,161 @@
+//! In-Memory Sparse Merkle Tree
+//!
+//! Same leaf placement and node hashing as `CanonicalSMT`, without the
+//! database, for state that lives in memory. Roots and proofs are
+//! interchangeable with the persistent tree's and verify with
+//! `canonical_spec::compute_root_from_proof`.
+
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use crate::canonical_spec;
+
+/// Sparse Merkle tree holding only its non-empty nodes
+#[derive(Debug, Clone)]
+pub struct InMemorySMT {
+    /// Tree depth
+    depth: u8,
+
+    /// Tree salt for index generation
+    tree_salt: u64,
+
+    /// Empty subtree hashes (precomputed)
+    empty_subtrees: Vec<[u8; 32]>,
+
+    /// Non-empty node hashes by (level, index), level 0 = leaves
+    nodes: HashMap<(u8, u64), [u8; 32]>,
+}
+
+impl InMemorySMT {
+    /// Create an empty tree
+    pub fn new(depth: u8, tree_salt: u64) -> Self {
+        Self {
+            depth,
+            tree_salt,
+            empty_subtrees: canonical_spec::precompute_empty_subtrees(depth),
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Leaf index a UTXO occupies (leaves are always placed by id)
+    pub fn leaf_position(&self, utxo_id: &[u8; 32]) -> u64 {
+        canonical_spec::tree_leaf_index(*utxo_id, self.tree_salt, self.depth)
+    }
+
+    /// Current root hash
+    pub fn get_root(&self) -> [u8; 32] {
+        self.node(self.depth, 0)
+    }
+
+    /// Tree depth
+    pub fn get_depth(&self) -> u8 {
+        self.depth
+    }
+
+    /// Leaf hash at `leaf_index`, or `None` if the slot is empty
+    pub fn get_leaf(&self, leaf_index: u64) -> Option<[u8; 32]> {
+        self.nodes.get(&(0, leaf_index)).copied()
+    }
+
+    /// Place a leaf, returning the new root
+    ///
+    /// Fails without changing the tree if the slot already holds another leaf.
+    pub fn insert_leaf(&mut self, leaf_index: u64, leaf_hash: [u8; 32]) -> Result<[u8; 32]> {
+        if let Some(existing) = self.get_leaf(leaf_index) {
+            if existing != leaf_hash {
+                return Err(anyhow!("Tree position {} is already occupied", leaf_index));
+            }
+        }
+        Ok(self.set_leaf(leaf_index, leaf_hash))
+    }
+
+    /// Empty the slot at `leaf_index`, returning the new root
+    pub fn remove_leaf(&mut self, leaf_index: u64) -> [u8; 32] {
+        self.set_leaf(leaf_index, self.empty_subtrees[0])
+    }
+
+    /// Sibling hashes from leaf to root for `leaf_index`
+    pub fn generate_proof(&self, leaf_index: u64) -> Vec<[u8; 32]> {
+        (0..self.depth)
+            .map(|level| self.node(level, (leaf_index >> level) ^ 1))
+            .collect()
+    }
+
+    /// Write a leaf and rehash its path, returning the new root
+    fn set_leaf(&mut self, leaf_index: u64, leaf_hash: [u8; 32]) -> [u8; 32] {
+        let mut current_hash = leaf_hash;
+        let mut current_index = leaf_index;
+        self.cache_node(0, leaf_index, leaf_hash);
+
+        for level in 0..self.depth {
+            let sibling_hash = self.node(level, current_index ^ 1);
+            current_hash = if current_index & 1 == 0 {
+                canonical_spec::generate_node_hash(current_hash, sibling_hash)
+            } else {
+                canonical_spec::generate_node_hash(sibling_hash, current_hash)
+            };
+            current_index >>= 1;
+            self.cache_node(level + 1, current_index, current_hash);
+        }
+
+        current_hash
+    }
+
+    /// Node hash at a position, falling back to the empty subtree
+    fn node(&self, level: u8, index: u64) -> [u8; 32] {
+        self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty_subtrees[level as usize])
+    }
+
+    /// Record a node hash, dropping positions that are back to empty
+    fn cache_node(&mut self, level: u8, index: u64, hash: [u8; 32]) {
+        if hash == self.empty_subtrees[level as usize] {
+            self.nodes.remove(&(level, index));
+        } else {
+            self.nodes.insert((level, index), hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proofs_verify_against_root() {
+        let mut tree = InMemorySMT::new(16, 7);
+        assert_eq!(tree.get_root(), canonical_spec::empty_tree_root(16));
+
+        let leaves = [(3u64, [1u8; 32]), (4, [2u8; 32]), (60_000, [3u8; 32])];
+        for (index, leaf) in leaves {
+            tree.insert_leaf(index, leaf).unwrap();
+        }
+        let root = tree.get_root();
+
+        for (index, leaf) in leaves {
+            let siblings = tree.generate_proof(index);
+            assert_eq!(canonical_spec::compute_root_from_proof(leaf, index, &siblings), root);
+            // The same siblings do not prove another leaf or position
+            assert_ne!(canonical_spec::compute_root_from_proof([9u8; 32], index, &siblings), root);
+            assert_ne!(canonical_spec::compute_root_from_proof(leaf, index ^ 1, &siblings), root);
+        }
+
+        // Emptying every slot restores the empty root and drops all nodes
+        for (index, _) in leaves {
+            tree.remove_leaf(index);
+        }
+        assert_eq!(tree.get_root(), canonical_spec::empty_tree_root(16));
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_occupied_slot_is_rejected() {
+        let mut tree = InMemorySMT::new(8, 0);
+        let root = tree.insert_leaf(5, [1u8; 32]).unwrap();
+
+        assert!(tree.insert_leaf(5, [2u8; 32]).is_err());
+        assert_eq!(tree.get_root(), root);
+        assert_eq!(tree.get_leaf(5), Some([1u8; 32]));
+    }
+}
@@ -0,0 +1,36 @@
+//! Leaf Placement
+//!
+//! Where a new leaf lands in a fixed-depth Merkle tree. `CanonicalSMT`
+//! always places by id, and the positions reported by the API and stored in
+//! cf_smt_leaves are id positions. `EnhancedMerkleTree` appends by default
+//! and can place by id to share the SMT's layout and roots. In both modes a
+//! Merkle proof is built over the leaf index the leaf was placed at.
+
+use serde::{Deserialize, Serialize};
+
+use crate::canonical_spec;
+
+/// Leaf index allocation strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LeafPlacement {
+    /// Next free index, in insertion order
+    #[default]
+    Append,
+    /// `canonical_spec::tree_leaf_index(utxo_id, tree_salt, depth)`, as in `CanonicalSMT`
+    ById {
+        /// Per-tree randomization salt
+        tree_salt: u64,
+    },
+}
+
+impl LeafPlacement {
+    /// Leaf index for `utxo_id` under id placement, `None` for `Append`
+    pub fn id_position(&self, utxo_id: &[u8; 32], depth: u8) -> Option<u64> {
+        match self {
+            LeafPlacement::Append => None,
+            LeafPlacement::ById { tree_salt } => {
+                Some(canonical_spec::tree_leaf_index(*utxo_id, *tree_salt, depth))
+            }
+        }
+    }
+}
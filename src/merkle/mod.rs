@@ -2,11 +2,21 @@
 //! Architecture-compliant Merkle trees with Poseidon hashing
 pub mod enhanced_merkle_tree;
 pub mod canonical_smt;
+pub mod in_memory_smt;
 pub mod tornado_merkle_tree;
 pub mod tree_inspector;
+pub mod nullifier_tree;
+pub mod leaf_placement;
+pub mod pair_ordering;
+pub mod withdrawal_bundle;
 
 // Re-export main types
 pub use enhanced_merkle_tree::{EnhancedMerkleTree, TreeStats};
-pub use canonical_smt::{CanonicalSMT, SMTNode};
+pub use canonical_smt::{CanonicalSMT, LeafSlotOccupied, SMTNode, StagedTreeUpdate};
+pub use in_memory_smt::InMemorySMT;
+pub use nullifier_tree::{NullifierTree, NullifierProof};
+pub use leaf_placement::LeafPlacement;
+pub use pair_ordering::PairOrdering;
+pub use withdrawal_bundle::{WithdrawalBundle, BundledWithdrawal};
 pub use tornado_merkle_tree::{TornadoMerkleTree, TornadoMerkleProof, TornadoMerkleTreeStats, TornadoCommitmentHasher, TornadoWithdrawalCircuit, TornadoWithdrawalData};
 pub use tree_inspector::{TreeInspector, demo_comprehensive_inspection, InspectionReport};
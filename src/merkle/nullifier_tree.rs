@@ -0,0 +1,299 @@
+//! Spent-Nullifier Sparse Merkle Tree
+//!
+//! Second SMT alongside the UTXO tree that commits to every spent nullifier.
+//! It uses the same canonical layout as `CanonicalSMT` (tree index from the
+//! salted key, `NODE` hashing, precomputed empty subtrees) but tracks its
+//! populated nodes so it can hand out inclusion and exclusion proofs. Light
+//! clients can then check whether a nullifier is spent against a single root.
+//!
+//! The spent set itself lives in cf_nullifiers, written in the same batch as
+//! the spend; the node cache is rebuilt from it on open, like the UTXO tree.
+
+use std::collections::HashMap;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use crate::canonical_spec::{self, tree_config};
+use crate::database::schema::{DatabaseManager, cf_names};
+
+/// Sparse Merkle tree over spent nullifiers
+pub struct NullifierTree {
+    /// Tree depth (full 64-bit index space by default)
+    depth: u8,
+    
+    /// Tree salt for index generation
+    tree_salt: u64,
+    
+    /// Current root hash
+    current_root: [u8; 32],
+    
+    /// Empty subtree hashes (precomputed)
+    empty_subtrees: Vec<[u8; 32]>,
+    
+    /// Populated nodes keyed by (level, index at that level)
+    nodes: HashMap<(u8, u64), [u8; 32]>,
+}
+
+/// Merkle proof that a nullifier is (or is not) in the spent set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NullifierProof {
+    /// Nullifier the proof is about
+    pub nullifier: [u8; 32],
+    
+    /// Leaf position derived from the nullifier; `verify` recomputes it
+    pub leaf_index: u64,
+    
+    /// Leaf value at that position (nullifier leaf or empty leaf)
+    pub leaf_hash: [u8; 32],
+    
+    /// Sibling hashes from leaf level up to the root
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl NullifierProof {
+    /// Whether this proof claims the nullifier is spent
+    pub fn is_inclusion(&self) -> bool {
+        self.leaf_hash == canonical_spec::generate_nullifier_leaf_hash(self.nullifier)
+    }
+
+    /// Verify the proof against a nullifier tree root
+    ///
+    /// The path bits come from the nullifier and `tree_salt`, so a path for
+    /// one leaf cannot be replayed as a proof about another nullifier.
+    /// Returns `Some(true)` for a valid inclusion proof, `Some(false)` for a
+    /// valid exclusion proof and `None` if the proof does not match the root.
+    pub fn verify(&self, root: &[u8; 32], tree_salt: u64) -> Option<bool> {
+        let is_inclusion = self.is_inclusion();
+        if !is_inclusion && self.leaf_hash != canonical_spec::generate_empty_leaf_hash() {
+            return None;
+        }
+        
+        let depth = u8::try_from(self.siblings.len()).ok()
+            .filter(|depth| *depth <= tree_config::MAX_DEPTH)?;
+        let leaf_index = canonical_spec::tree_leaf_index(self.nullifier, tree_salt, depth);
+        if leaf_index != self.leaf_index {
+            return None;
+        }
+        
+        let current_hash = canonical_spec::compute_root_from_proof(self.leaf_hash, leaf_index, &self.siblings);
+        if &current_hash == root {
+            Some(is_inclusion)
+        } else {
+            None
+        }
+    }
+}
+
+impl NullifierTree {
+    /// Create an empty nullifier tree
+    pub fn new(depth: u8, tree_salt: u64) -> Self {
+        let empty_subtrees = canonical_spec::precompute_empty_subtrees(depth);
+        
+        Self {
+            depth,
+            tree_salt,
+            current_root: empty_subtrees[depth as usize],
+            empty_subtrees,
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Create a nullifier tree spanning the full 64-bit index space
+    pub fn with_salt(tree_salt: u64) -> Self {
+        Self::new(tree_config::MAX_DEPTH, tree_salt)
+    }
+
+    /// Rebuild the tree from every nullifier recorded in cf_nullifiers
+    pub fn load(db: &DatabaseManager, tree_salt: u64) -> Result<Self> {
+        let mut tree = Self::with_salt(tree_salt);
+        for item in db.iterator_cf(cf_names::NULLIFIERS)? {
+            let (key, _) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&canonical_spec::cf_prefixes::NULLIFIERS) {
+                continue;
+            }
+            let nullifier: [u8; 32] = key[1..].try_into()
+                .map_err(|_| anyhow!("Invalid nullifier key length: {}", key.len()))?;
+            tree.insert(nullifier)?;
+        }
+        Ok(tree)
+    }
+
+    /// Record a spent nullifier and return the new root
+    pub fn insert(&mut self, nullifier: [u8; 32]) -> Result<[u8; 32]> {
+        let leaf_index = self.leaf_index(&nullifier);
+        let mut current_hash = canonical_spec::generate_nullifier_leaf_hash(nullifier);
+        
+        match self.nodes.get(&(0, leaf_index)) {
+            Some(existing) if *existing == current_hash => {
+                return Err(anyhow!("Nullifier already spent: {}", hex::encode(nullifier)));
+            }
+            Some(_) => {
+                return Err(anyhow!("Nullifier tree index collision at {}", leaf_index));
+            }
+            None => {}
+        }
+        
+        let mut current_index = leaf_index;
+        self.nodes.insert((0, current_index), current_hash);
+        
+        // Recompute the path from leaf to root
+        for level in 0..self.depth {
+            let sibling_hash = self.node_hash(level, current_index ^ 1);
+            current_hash = if current_index & 1 == 0 {
+                canonical_spec::generate_node_hash(current_hash, sibling_hash)
+            } else {
+                canonical_spec::generate_node_hash(sibling_hash, current_hash)
+            };
+            current_index >>= 1;
+            
+            if level + 1 < self.depth {
+                self.nodes.insert((level + 1, current_index), current_hash);
+            }
+        }
+        
+        self.current_root = current_hash;
+        Ok(current_hash)
+    }
+
+    /// Forget a spent nullifier and return the new root
+    ///
+    /// Used when the spend that revealed it is reorganized away.
+    pub fn remove(&mut self, nullifier: &[u8; 32]) -> Result<[u8; 32]> {
+        if !self.contains(nullifier) {
+            return Err(anyhow!("Nullifier not spent: {}", hex::encode(nullifier)));
+        }
+        
+        let mut current_index = self.leaf_index(nullifier);
+        let mut current_hash = self.empty_subtrees[0];
+        self.nodes.remove(&(0, current_index));
+        
+        // Recompute the path, dropping nodes that are back to empty
+        for level in 0..self.depth {
+            let sibling_hash = self.node_hash(level, current_index ^ 1);
+            current_hash = if current_index & 1 == 0 {
+                canonical_spec::generate_node_hash(current_hash, sibling_hash)
+            } else {
+                canonical_spec::generate_node_hash(sibling_hash, current_hash)
+            };
+            current_index >>= 1;
+            
+            if level + 1 < self.depth {
+                if current_hash == self.empty_subtrees[level as usize + 1] {
+                    self.nodes.remove(&(level + 1, current_index));
+                } else {
+                    self.nodes.insert((level + 1, current_index), current_hash);
+                }
+            }
+        }
+        
+        self.current_root = current_hash;
+        Ok(current_hash)
+    }
+
+    /// Check whether a nullifier has been spent
+    pub fn contains(&self, nullifier: &[u8; 32]) -> bool {
+        self.nodes.get(&(0, self.leaf_index(nullifier)))
+            == Some(&canonical_spec::generate_nullifier_leaf_hash(*nullifier))
+    }
+
+    /// Build an inclusion proof (spent) or exclusion proof (unspent)
+    pub fn prove(&self, nullifier: &[u8; 32]) -> NullifierProof {
+        let leaf_index = self.leaf_index(nullifier);
+        let siblings = (0..self.depth)
+            .map(|level| self.node_hash(level, (leaf_index >> level) ^ 1))
+            .collect();
+        
+        NullifierProof {
+            nullifier: *nullifier,
+            leaf_index,
+            leaf_hash: self.node_hash(0, leaf_index),
+            siblings,
+        }
+    }
+
+    /// Get current root hash
+    pub fn get_root(&self) -> [u8; 32] {
+        self.current_root
+    }
+
+    /// Number of spent nullifiers recorded
+    pub fn len(&self) -> usize {
+        self.nodes.keys().filter(|(level, _)| *level == 0).count()
+    }
+
+    /// Whether no nullifier has been recorded yet
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn leaf_index(&self, nullifier: &[u8; 32]) -> u64 {
+        canonical_spec::tree_leaf_index(*nullifier, self.tree_salt, self.depth)
+    }
+
+    fn node_hash(&self, level: u8, index: u64) -> [u8; 32] {
+        self.nodes
+            .get(&(level, index))
+            .copied()
+            .unwrap_or(self.empty_subtrees[level as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inclusion_and_exclusion_proofs() {
+        let mut tree = NullifierTree::with_salt(42);
+        let empty_root = tree.get_root();
+        
+        let spent = [0x11u8; 32];
+        let other_spent = [0x22u8; 32];
+        let unspent = [0x33u8; 32];
+        
+        tree.insert(spent).unwrap();
+        let root = tree.insert(other_spent).unwrap();
+        assert_ne!(root, empty_root);
+        assert_eq!(tree.len(), 2);
+        
+        let inclusion = tree.prove(&spent);
+        assert_eq!(inclusion.verify(&root, 42), Some(true));
+        
+        let exclusion = tree.prove(&unspent);
+        assert_eq!(exclusion.verify(&root, 42), Some(false));
+        
+        // Proofs do not verify against a different root or salt
+        assert_eq!(inclusion.verify(&empty_root, 42), None);
+        assert_eq!(inclusion.verify(&root, 43), None);
+        
+        // A forged inclusion claim for the unspent nullifier fails
+        let mut forged = exclusion.clone();
+        forged.leaf_hash = canonical_spec::generate_nullifier_leaf_hash(unspent);
+        assert_eq!(forged.verify(&root, 42), None);
+        
+        // An empty slot's path cannot be replayed as exclusion of a spent nullifier
+        let mut replayed = exclusion.clone();
+        replayed.nullifier = other_spent;
+        assert_eq!(replayed.verify(&root, 42), None);
+        
+        // Double spends are rejected
+        assert!(tree.insert(spent).is_err());
+    }
+
+    #[test]
+    fn test_remove_restores_previous_root() {
+        let mut tree = NullifierTree::with_salt(42);
+        let kept = [0x11u8; 32];
+        let reverted = [0x22u8; 32];
+        
+        let root_before = tree.insert(kept).unwrap();
+        tree.insert(reverted).unwrap();
+        
+        assert_eq!(tree.remove(&reverted).unwrap(), root_before);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.prove(&reverted).verify(&root_before, 42), Some(false));
+        assert!(tree.remove(&reverted).is_err());
+        
+        // The nullifier can be spent again once the reorg replays it
+        assert!(tree.insert(reverted).is_ok());
+    }
+}
@@ -0,0 +1,44 @@
+//! Pair Ordering
+//!
+//! How two sibling hashes are arranged before being hashed into their
+//! parent. This crate hashes `hash(left, right)` by position. Some external
+//! conventions (e.g. OpenZeppelin's `MerkleProof`) instead hash
+//! `hash(min(a, b), max(a, b))`, which makes proofs direction-agnostic: a
+//! verifier needs only the siblings, not the path bits. The trade-off is
+//! that such a proof no longer binds the leaf to a position.
+
+use serde::{Deserialize, Serialize};
+
+/// Sibling ordering used when hashing internal nodes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PairOrdering {
+    /// `hash(left, right)` by tree position
+    #[default]
+    Positional,
+    /// `hash(min(a, b), max(a, b))`, compared bytewise
+    SortedPairs,
+}
+
+impl PairOrdering {
+    /// Arrange a positional `(left, right)` pair for hashing
+    pub fn order<'a>(&self, left: &'a [u8; 32], right: &'a [u8; 32]) -> (&'a [u8; 32], &'a [u8; 32]) {
+        match self {
+            PairOrdering::SortedPairs if right < left => (right, left),
+            _ => (left, right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sorted_pairs_ignore_position() {
+        let (a, b) = ([1u8; 32], [2u8; 32]);
+
+        assert_eq!(PairOrdering::SortedPairs.order(&a, &b), (&a, &b));
+        assert_eq!(PairOrdering::SortedPairs.order(&b, &a), (&a, &b));
+        assert_eq!(PairOrdering::Positional.order(&b, &a), (&b, &a));
+    }
+}
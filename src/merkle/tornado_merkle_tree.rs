@@ -10,6 +10,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use sha2::{Digest, Sha256};
+use crate::crypto::PathBits;
 
 /// Hash two 32-byte arrays together using SHA-256
 fn hash_pair(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
@@ -200,7 +201,6 @@ impl TornadoMerkleTree {
         }
 
         let mut siblings = Vec::new();
-        let mut path = Vec::new();
         let mut current_index = leaf_index;
         
         // Build proof path
@@ -221,7 +221,6 @@ impl TornadoMerkleTree {
             };
             
             siblings.push(sibling_hash);
-            path.push(current_index % 2);
             
             // Move to parent
             current_index /= 2;
@@ -229,7 +228,7 @@ impl TornadoMerkleTree {
         
         Some(TornadoMerkleProof {
             siblings,
-            path,
+            path: PathBits::from_leaf_index(leaf_index as u64, self.depth as usize).to_u32s(),
             root: self.root,
             leaf_index,
         })
@@ -243,18 +242,18 @@ impl TornadoMerkleTree {
             return false;
         }
         
-        if proof.path.len() != self.depth as usize {
+        let Ok(path) = PathBits::try_from(proof.path.as_slice()) else {
+            return false;
+        };
+        if path.len() != self.depth as usize {
             return false;
         }
         
         // Verify proof
         let mut current_hash = leaf;
         
-        for i in 0..self.depth as usize {
-            let sibling = proof.siblings[i];
-            let is_left = proof.path[i] == 0;
-            
-            current_hash = if is_left {
+        for (sibling, is_right) in proof.siblings.iter().copied().zip(path.iter()) {
+            current_hash = if !is_right {
                 hash_pair(current_hash, sibling)
             } else {
                 hash_pair(sibling, current_hash)
@@ -324,13 +323,16 @@ impl TornadoMerkleProof {
             return false;
         }
         
+        // Path must cover every level
+        let path = match PathBits::try_from(self.path.as_slice()) {
+            Ok(path) if path.len() == self.siblings.len() => path,
+            _ => return false,
+        };
+        
         let mut current_hash = leaf;
         
-        for i in 0..self.siblings.len() {
-            let sibling = self.siblings[i];
-            let is_left = self.path[i] == 0;
-            
-            current_hash = if is_left {
+        for (sibling, is_right) in self.siblings.iter().copied().zip(path.iter()) {
+            current_hash = if !is_right {
                 hash_pair(current_hash, sibling)
             } else {
                 hash_pair(sibling, current_hash)
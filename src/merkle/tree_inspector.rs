@@ -11,8 +11,10 @@ use crate::utxo::converter::{CryptoUtils, ETHDepositProcessor};
 use crate::BlockchainConfig;
 use crate::utxo::indexing::IndexedUTXO;
 use crate::crypto::{poseidon::PoseidonHash, nullifiers::NullifierSet, CryptoContext, domains};
+use crate::merkle::canonical_smt::CanonicalSMT;
 use anyhow::Result;
 use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
 
 /// Comprehensive Note/UTXO Inspector for Privacy Pool Security Analysis
 pub struct TreeInspector {
@@ -27,6 +29,8 @@ pub struct TreeInspector {
     note_commitments: HashMap<[u8; 32], NoteCommitmentData>,
     /// Spent nullifiers tracking
     spent_nullifiers: HashSet<[u8; 32]>,
+    /// Persistent SMT used for structural exports
+    smt: Option<CanonicalSMT>,
 }
 
 /// Note commitment data for analysis
@@ -58,7 +62,7 @@ impl TreeInspector {
         // Initialize cryptographic components
         let poseidon_hasher = PoseidonHash::new();
         let crypto_context = CryptoContext::utxo_context();
-        let nullifier_set = NullifierSet::new(crypto_context.clone(), crate::crypto::nullifiers::NullifierHashFunction::Poseidon);
+        let nullifier_set = NullifierSet::new(crypto_context.clone(), crate::crypto::HashPolicy::Poseidon);
         
         Ok(Self { 
             processor,
@@ -67,9 +71,109 @@ impl TreeInspector {
             crypto_context,
             note_commitments: HashMap::new(),
             spent_nullifiers: HashSet::new(),
+            smt: None,
         })
     }
 
+    /// Attach a persistent SMT for structural inspection
+    pub fn attach_smt(&mut self, smt: CanonicalSMT) {
+        self.smt = Some(smt);
+    }
+
+    /// Export the top `max_depth` levels of the attached SMT as GraphViz DOT
+    ///
+    /// Node hashes are truncated, stored nodes are labeled with their ref count,
+    /// and each empty subtree level is collapsed into a single shared node.
+    pub fn to_dot(&self, max_depth: u8) -> String {
+        let mut dot = String::from("digraph smt {\n    node [shape=box, fontname=\"monospace\"];\n");
+        
+        if let Some(smt) = &self.smt {
+            if let Err(e) = Self::write_dot_tree(smt, max_depth, &mut dot) {
+                let _ = writeln!(dot, "    // export failed: {}", e);
+            }
+        }
+        
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write root and expanded subtrees into a DOT buffer
+    fn write_dot_tree(smt: &CanonicalSMT, max_depth: u8, dot: &mut String) -> Result<()> {
+        let root = smt.get_root();
+        let depth = smt.get_depth();
+        let _ = writeln!(dot, "    \"{}\" [label=\"root {}\\nh={}\"];", hex::encode(root), Self::short_hash(&root), depth);
+        
+        if max_depth == 0 {
+            return Ok(());
+        }
+        
+        if let Some((left, right)) = smt.get_root_children()? {
+            let mut empty_levels = HashSet::new();
+            for child in [left, right] {
+                Self::write_dot_edge(smt, root, child, depth - 1, 1, max_depth, &mut empty_levels, dot)?;
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Write an edge to `child` and, if within `max_depth`, its subtree
+    #[allow(clippy::too_many_arguments)]
+    fn write_dot_edge(
+        smt: &CanonicalSMT,
+        parent: [u8; 32],
+        child: [u8; 32],
+        height: u8,
+        level: u8,
+        max_depth: u8,
+        empty_levels: &mut HashSet<u8>,
+        dot: &mut String,
+    ) -> Result<()> {
+        let parent_id = hex::encode(parent);
+        
+        // Collapse empty subtrees into a single node per height
+        if smt.get_empty_subtree_hash(height) == Some(child) {
+            if empty_levels.insert(height) {
+                let _ = writeln!(dot, "    \"empty_{}\" [label=\"empty\\nh={}\", style=dashed];", height, height);
+            }
+            let _ = writeln!(dot, "    \"{}\" -> \"empty_{}\";", parent_id, height);
+            return Ok(());
+        }
+        
+        let child_id = hex::encode(child);
+        let _ = writeln!(dot, "    \"{}\" -> \"{}\";", parent_id, child_id);
+        
+        if height == 0 {
+            let _ = writeln!(dot, "    \"{}\" [label=\"leaf {}\", shape=ellipse];", child_id, Self::short_hash(&child));
+            return Ok(());
+        }
+        
+        match smt.get_node(&child)? {
+            Some(node) => {
+                let _ = writeln!(
+                    dot,
+                    "    \"{}\" [label=\"{}\\nh={} rc={}\"];",
+                    child_id, Self::short_hash(&child), node.height, node.ref_count
+                );
+                if level < max_depth {
+                    for grandchild in [node.left_hash, node.right_hash] {
+                        Self::write_dot_edge(smt, child, grandchild, height - 1, level + 1, max_depth, empty_levels, dot)?;
+                    }
+                }
+            }
+            None => {
+                let _ = writeln!(dot, "    \"{}\" [label=\"{}\\nmissing\", color=red];", child_id, Self::short_hash(&child));
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Truncate a hash for display
+    fn short_hash(hash: &[u8; 32]) -> String {
+        hex::encode(&hash[..4])
+    }
+
     /// Process real deposits from blockchain and then inspect the tree
     pub async fn process_and_inspect(&mut self) -> Result<()> {
         println!("Comprehensive Note/UTXO Inspector - Privacy Pool Security Analysis");
@@ -947,4 +1051,39 @@ pub async fn demo_comprehensive_inspection() -> Result<()> {
     println!("   assessment of the privacy pool system.");
     
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use crate::database::schema::{DatabaseManager, DBConfig};
+    use crate::utxo::CanonicalUTXO;
+
+    #[test]
+    fn test_to_dot_exports_top_levels() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut smt = CanonicalSMT::new(db_manager, 4, 12345).unwrap();
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, 100, 1, 1_000, [2u8; 32]);
+        let root = smt.insert_utxo(&utxo).unwrap();
+        
+        let mut inspector = TreeInspector::new().unwrap();
+        inspector.attach_smt(smt);
+        
+        let dot = inspector.to_dot(2);
+        assert!(dot.starts_with("digraph smt {"));
+        assert!(dot.contains(&format!("\"{}\" [label=\"root", hex::encode(root))));
+        
+        // Root and the level-1 path node each expand into two children
+        assert_eq!(dot.matches("->").count(), 4);
+        assert!(dot.contains("rc=1"));
+        assert!(dot.contains("\"empty_3\""));
+    }
+}
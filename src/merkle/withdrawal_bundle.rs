@@ -0,0 +1,243 @@
+//! Withdrawal Bundle
+//!
+//! Compact format a relayer uses to submit many withdrawals at once. The
+//! root context (version, hash, tree depth) is stored once; each entry only
+//! carries its nullifier, leaf, leaf index and siblings. Path bits are not
+//! stored since they follow from the leaf index.
+
+use crate::canonical_spec::bundle_format;
+use crate::crypto::{CryptoError, CryptoResult, HashPolicy, PathBits};
+use crate::merkle::EnhancedMerkleTree;
+use crate::utxo::transaction::MerkleProof;
+
+/// One withdrawal inside a bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundledWithdrawal {
+    /// Nullifier revealed by the spend
+    pub nullifier: [u8; 32],
+    /// Commitment (leaf) being withdrawn
+    pub commitment: [u8; 32],
+    /// Leaf index of the commitment
+    pub leaf_index: u64,
+    /// Sibling hashes from leaf to root
+    pub siblings: Vec<[u8; 32]>,
+}
+
+/// Withdrawals sharing one root context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithdrawalBundle {
+    /// Root version every proof was generated at
+    pub root_version: u64,
+    /// Root every proof leads to
+    pub root_hash: [u8; 32],
+    /// Tree depth (siblings per entry)
+    pub depth: u8,
+    /// Bundled withdrawals
+    pub withdrawals: Vec<BundledWithdrawal>,
+}
+
+impl WithdrawalBundle {
+    /// Create an empty bundle for a root
+    pub fn new(root_version: u64, root_hash: [u8; 32], depth: u8) -> Self {
+        Self {
+            root_version,
+            root_hash,
+            depth,
+            withdrawals: Vec::new(),
+        }
+    }
+
+    /// Add a withdrawal whose proof was generated against the bundle root
+    pub fn push(&mut self, nullifier: [u8; 32], commitment: [u8; 32], proof: &MerkleProof) -> CryptoResult<()> {
+        if proof.root != self.root_hash {
+            return Err(CryptoError::InvalidInput("Proof root differs from bundle root".to_string()));
+        }
+        if proof.siblings.len() != self.depth as usize {
+            return Err(CryptoError::InvalidInput(format!(
+                "Proof has {} siblings, bundle depth is {}",
+                proof.siblings.len(),
+                self.depth
+            )));
+        }
+
+        self.withdrawals.push(BundledWithdrawal {
+            nullifier,
+            commitment,
+            leaf_index: proof.leaf_index,
+            siblings: proof.siblings.clone(),
+        });
+        Ok(())
+    }
+
+    /// Number of bundled withdrawals
+    pub fn len(&self) -> usize {
+        self.withdrawals.len()
+    }
+
+    /// Whether the bundle holds no withdrawals
+    pub fn is_empty(&self) -> bool {
+        self.withdrawals.is_empty()
+    }
+
+    /// Full Merkle proof for the withdrawal at `index`
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        self.withdrawals.get(index).map(|withdrawal| MerkleProof {
+            siblings: withdrawal.siblings.clone(),
+            path: PathBits::from_leaf_index(withdrawal.leaf_index, self.depth as usize).to_u32s(),
+            root: self.root_hash,
+            leaf_index: withdrawal.leaf_index,
+        })
+    }
+
+    /// Verify every withdrawal against `tree`, in bundle order
+    ///
+    /// An entry is valid when the bundle root is the tree's current root, the
+    /// entry's siblings lead from its commitment to that root, and its
+    /// nullifier is the one derived from that commitment and leaf index. A
+    /// nullifier repeated within the bundle only counts for its first entry.
+    pub fn verify_all(&self, tree: &EnhancedMerkleTree) -> Vec<bool> {
+        let context_valid = self.root_hash == tree.get_root() && self.depth == tree.depth;
+        let mut seen_nullifiers = std::collections::HashSet::new();
+
+        self.withdrawals
+            .iter()
+            .enumerate()
+            .map(|(index, withdrawal)| {
+                let first_use = seen_nullifiers.insert(withdrawal.nullifier);
+                context_valid
+                    && first_use
+                    && Self::nullifier_matches(withdrawal)
+                    && self.proof(index).is_some_and(|proof| {
+                        tree.verify_proof_with_root(&proof, withdrawal.commitment, self.root_hash)
+                            .unwrap_or(false)
+                    })
+            })
+            .collect()
+    }
+
+    /// Whether the entry's nullifier is the pool nullifier of its commitment
+    /// at its leaf index
+    fn nullifier_matches(withdrawal: &BundledWithdrawal) -> bool {
+        HashPolicy::default()
+            .nullifier(&withdrawal.commitment, withdrawal.leaf_index)
+            .is_ok_and(|expected| expected == withdrawal.nullifier)
+    }
+
+    /// Serialize bundle: header || entries, each entry
+    /// nullifier(32) || commitment(32) || leaf_index(8 BE) || siblings(depth * 32)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let entry_size = Self::entry_size(self.depth);
+        let mut bytes = Vec::with_capacity(bundle_format::HEADER_SIZE + entry_size * self.withdrawals.len());
+
+        bytes.extend_from_slice(&bundle_format::MAGIC.to_be_bytes());
+        bytes.extend_from_slice(&bundle_format::VERSION.to_be_bytes());
+        bytes.push(self.depth);
+        bytes.extend_from_slice(&self.root_version.to_be_bytes());
+        bytes.extend_from_slice(&self.root_hash);
+        bytes.extend_from_slice(&(self.withdrawals.len() as u32).to_be_bytes());
+
+        for withdrawal in &self.withdrawals {
+            bytes.extend_from_slice(&withdrawal.nullifier);
+            bytes.extend_from_slice(&withdrawal.commitment);
+            bytes.extend_from_slice(&withdrawal.leaf_index.to_be_bytes());
+            for sibling in &withdrawal.siblings {
+                bytes.extend_from_slice(sibling);
+            }
+        }
+
+        bytes
+    }
+
+    /// Deserialize bundle, rejecting trailing or missing bytes
+    pub fn from_bytes(bytes: &[u8]) -> CryptoResult<Self> {
+        let invalid = |msg: &str| CryptoError::SerializationError(format!("Invalid withdrawal bundle: {}", msg));
+
+        if bytes.len() < bundle_format::HEADER_SIZE {
+            return Err(invalid("truncated header"));
+        }
+        if u32::from_be_bytes(bytes[0..4].try_into().unwrap()) != bundle_format::MAGIC {
+            return Err(invalid("bad magic"));
+        }
+        if u16::from_be_bytes(bytes[4..6].try_into().unwrap()) != bundle_format::VERSION {
+            return Err(invalid("unsupported version"));
+        }
+
+        let depth = bytes[6];
+        let root_version = u64::from_be_bytes(bytes[7..15].try_into().unwrap());
+        let root_hash: [u8; 32] = bytes[15..47].try_into().unwrap();
+        let count = u32::from_be_bytes(bytes[47..51].try_into().unwrap()) as usize;
+
+        let entry_size = Self::entry_size(depth);
+        let body = &bytes[bundle_format::HEADER_SIZE..];
+        if count.checked_mul(entry_size) != Some(body.len()) {
+            return Err(invalid("length does not match entry count"));
+        }
+
+        let withdrawals = body
+            .chunks_exact(entry_size)
+            .map(|entry| BundledWithdrawal {
+                nullifier: entry[0..32].try_into().unwrap(),
+                commitment: entry[32..64].try_into().unwrap(),
+                leaf_index: u64::from_be_bytes(entry[64..72].try_into().unwrap()),
+                siblings: entry[72..]
+                    .chunks_exact(32)
+                    .map(|sibling| sibling.try_into().unwrap())
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Self {
+            root_version,
+            root_hash,
+            depth,
+            withdrawals,
+        })
+    }
+
+    /// Serialized size of one entry
+    fn entry_size(depth: u8) -> usize {
+        72 + 32 * depth as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bundle_round_trip_and_verify() {
+        let mut tree = EnhancedMerkleTree::with_depth(8).unwrap();
+        let commitments = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        for commitment in commitments {
+            tree.insert_leaf(commitment).unwrap();
+        }
+
+        let nullifier = |commitment: &[u8; 32], index: u64| HashPolicy::default().nullifier(commitment, index).unwrap();
+        let mut bundle = WithdrawalBundle::new(tree.root_version, tree.get_root(), tree.depth);
+        for (i, commitment) in commitments.iter().enumerate() {
+            let proof = tree.get_proof(i as u64).unwrap();
+            // The last entry claims a commitment that is not at its leaf
+            let claimed = if i == 2 { [9u8; 32] } else { *commitment };
+            bundle.push(nullifier(&claimed, i as u64), claimed, &proof).unwrap();
+        }
+
+        let bytes = bundle.to_bytes();
+        assert_eq!(bytes.len(), bundle_format::HEADER_SIZE + 3 * (72 + 32 * 8));
+
+        let decoded = WithdrawalBundle::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, bundle);
+        assert_eq!(decoded.verify_all(&tree), vec![true, true, false]);
+
+        // Valid paths still fail with a foreign or repeated nullifier
+        let mut tampered = bundle.clone();
+        tampered.withdrawals[0].nullifier = [0xa0; 32];
+        tampered.withdrawals.push(tampered.withdrawals[1].clone());
+        assert_eq!(tampered.verify_all(&tree), vec![false, true, false, false]);
+
+        // A bundle for an outdated root no longer verifies
+        tree.insert_leaf([4u8; 32]).unwrap();
+        assert_eq!(decoded.verify_all(&tree), vec![false, false, false]);
+
+        assert!(WithdrawalBundle::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
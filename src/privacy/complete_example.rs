@@ -1,7 +1,7 @@
 //! Complete Privacy Pool Example
 //! 
 //! Demonstrates the full integration of all implemented components:
-//! - RedJubjub signatures (Zcash Sapling)
+//! - RedJubjub (Zcash Sapling) or ECDSA (secp256k1) signatures
 //! - Tornado Cash Merkle tree
 //! - Complete UTXO system (Bitcoin Core)
 //! - Enhanced privacy pool (0xbow patterns)
@@ -12,12 +12,53 @@ use crate::{
     utxo::{UTXOInput, UTXOOutput, MerkleProof as UTXOMerkleProof, UTXO, UTXOIndex, IndexedUTXO, UTXOId},
     privacy::enhanced_privacy_pool::{EnhancedPrivacyPool, MerkleProof as EnhancedMerkleProof},
     privacy::types::PoolStats,
+    privacy::signature_suite::{SignatureSuiteTag, SuiteKeypair},
 };
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Number of historical roots a spend proof may be anchored to.
+/// Mirrors Tornado Cash's `ROOT_HISTORY_SIZE`.
+pub const DEFAULT_ROOT_TOLERANCE: usize = 30;
+
+/// Errors raised while applying a spend (withdrawal or transfer)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpendError {
+    /// The proof is anchored to a root outside the tolerance window
+    StaleProof { leaf_index: u64, proof_root: [u8; 32] },
+    /// The proof does not open the committed leaf to its claimed root
+    InvalidProof { leaf_index: u64 },
+    /// The underlying privacy pool rejected the spend
+    Pool(String),
+}
+
+impl fmt::Display for SpendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpendError::StaleProof { leaf_index, proof_root } => write!(
+                f,
+                "Stale Merkle proof for leaf {}: root 0x{} is outside the tolerance window",
+                leaf_index,
+                hex::encode(proof_root)
+            ),
+            SpendError::InvalidProof { leaf_index } => {
+                write!(f, "Invalid Merkle proof for leaf {}", leaf_index)
+            }
+            SpendError::Pool(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl From<String> for SpendError {
+    fn from(msg: String) -> Self {
+        SpendError::Pool(msg)
+    }
+}
 
 /// Complete Privacy Pool Example
 pub struct CompletePrivacyPoolExample {
-    /// RedJubjub key pair for signing
-    pub key_pair: RedJubjubKeyPair,
+    /// Transaction signing key
+    pub signer: SuiteKeypair,
     /// Tornado Cash Merkle tree
     pub merkle_tree: TornadoMerkleTree,
     /// UTXO set
@@ -26,18 +67,88 @@ pub struct CompletePrivacyPoolExample {
     pub privacy_pool: EnhancedPrivacyPool,
     /// Block height
     pub block_height: u32,
+    /// Most recent tree roots, newest last
+    pub recent_roots: VecDeque<[u8; 32]>,
+    /// How many recent roots a spend proof may be anchored to
+    pub root_tolerance: usize,
 }
 
 impl CompletePrivacyPoolExample {
     /// Create new complete privacy pool example
     pub fn new() -> Self {
+        let merkle_tree = TornadoMerkleTree::new(3); // 3 levels deep
+        let mut recent_roots = VecDeque::new();
+        recent_roots.push_back(merkle_tree.root);
+
         Self {
-            key_pair: RedJubjubKeyPair::random(),
-            merkle_tree: TornadoMerkleTree::new(3), // 3 levels deep
+            signer: SuiteKeypair::random(SignatureSuiteTag::RedJubjub),
+            merkle_tree,
             utxo_set: UTXOIndex::new(),
             privacy_pool: EnhancedPrivacyPool::new(1000), // 1000 capacity
             block_height: 100,
+            recent_roots,
+            root_tolerance: DEFAULT_ROOT_TOLERANCE,
+        }
+    }
+
+    /// Sign transactions with the given suite
+    pub fn with_signature_suite(mut self, suite: SignatureSuiteTag) -> Self {
+        self.signer = SuiteKeypair::random(suite);
+        self
+    }
+
+    /// Set how many recent roots a spend proof may be anchored to
+    pub fn with_root_tolerance(mut self, root_tolerance: usize) -> Self {
+        self.root_tolerance = root_tolerance.max(1);
+        while self.recent_roots.len() > self.root_tolerance {
+            self.recent_roots.pop_front();
+        }
+        self
+    }
+
+    /// Insert a commitment into the tree and record the new root
+    pub fn insert_commitment(&mut self, commitment: [u8; 32]) -> Result<u32, String> {
+        let leaf_index = self.merkle_tree.insert_leaf(commitment)?;
+        self.recent_roots.push_back(self.merkle_tree.root);
+        while self.recent_roots.len() > self.root_tolerance {
+            self.recent_roots.pop_front();
+        }
+        Ok(leaf_index)
+    }
+
+    /// Re-verify a spend proof against the live tree before it is applied.
+    ///
+    /// The proof must open the leaf currently stored at `leaf_index` to its
+    /// claimed root, and that root must be one of the last `root_tolerance`
+    /// roots of the tree.
+    pub fn check_proof_freshness(&self, proof: &UTXOMerkleProof) -> Result<(), SpendError> {
+        let leaf_index = proof.leaf_index;
+        if leaf_index >= self.merkle_tree.next_leaf_index as u64 {
+            return Err(SpendError::InvalidProof { leaf_index });
+        }
+
+        let tornado_proof = TornadoMerkleProof::new(
+            proof.siblings.clone(),
+            proof.path.clone(),
+            proof.root,
+            leaf_index as u32,
+        );
+        let leaf = self.merkle_tree.leaves[leaf_index as usize];
+        if proof.siblings.len() != self.merkle_tree.depth as usize
+            || proof.path.len() != proof.siblings.len()
+            || !tornado_proof.verify(leaf)
+        {
+            return Err(SpendError::InvalidProof { leaf_index });
+        }
+
+        if !self.recent_roots.contains(&proof.root) {
+            return Err(SpendError::StaleProof {
+                leaf_index,
+                proof_root: proof.root,
+            });
         }
+
+        Ok(())
     }
 
     /// Initialize the privacy pool with approved addresses
@@ -79,7 +190,7 @@ impl CompletePrivacyPoolExample {
         let commitment = output.commitment;
         
         // Insert into Merkle tree
-        let leaf_index = self.merkle_tree.insert_leaf(commitment)?;
+        let leaf_index = self.insert_commitment(commitment)?;
         
         // Create Merkle proof
         let merkle_proof = self.merkle_tree.generate_proof(leaf_index)
@@ -93,28 +204,20 @@ impl CompletePrivacyPoolExample {
             leaf_index: merkle_proof.leaf_index as u64,
         };
 
-        // Create transaction message
-        let mut message = Vec::new();
-        message.extend_from_slice(&(TransactionType::Deposit as u8).to_le_bytes());
-        message.extend_from_slice(&commitment);
-        message.extend_from_slice(&depositor);
-        message.extend_from_slice(&value.to_le_bytes());
-
-        // Sign transaction
-        let signature = self.key_pair.sign(&message);
-
         // Create transaction
-        let tx = PrivacyPoolTransaction {
+        let mut tx = PrivacyPoolTransaction {
             tx_type: TransactionType::Deposit,
             inputs: vec![],
             outputs: vec![output],
-            signature: signature.to_bytes(),
-            public_key: self.key_pair.public_key.bytes,
+            signature: Vec::new(),
+            public_key: self.signer.public_key(),
+            suite: self.signer.suite(),
             fee: 100,
             sender: depositor,
             recipient: depositor,
             tx_hash: [0u8; 32], // Will be calculated
         };
+        self.sign_transaction(&mut tx)?;
 
         Ok(tx)
     }
@@ -155,40 +258,31 @@ impl CompletePrivacyPoolExample {
                 nullifier_seed: [0u8; 32], // Placeholder
                 commitment: utxo.address, // Using address as commitment
                 index: utxo.height as u64,
+                nullifier_format: crate::utxo::utxo::NULLIFIER_FORMAT_VERSION,
             },
             merkle_proof: UTXOMerkleProof {
                 siblings: merkle_proof.siblings.clone(),
-                path: merkle_proof.path.iter().map(|&p| if p == 1 { 1u32 } else { 0u32 }).collect(),
+                path: merkle_proof.path.clone(),
                 root: merkle_proof.root,
                 leaf_index: merkle_proof.leaf_index as u64,
             },
             nullifier,
         };
 
-        // Create transaction message
-        let mut message = Vec::new();
-        message.extend_from_slice(&(TransactionType::Withdrawal as u8).to_le_bytes());
-        message.extend_from_slice(&utxo.id.tx_hash);
-        message.extend_from_slice(&utxo.id.output_index.to_le_bytes());
-        message.extend_from_slice(&nullifier);
-        message.extend_from_slice(&recipient);
-        message.extend_from_slice(&value.to_le_bytes());
-
-        // Sign transaction
-        let signature = self.key_pair.sign(&message);
-
         // Create transaction
-        let tx = PrivacyPoolTransaction {
+        let mut tx = PrivacyPoolTransaction {
             tx_type: TransactionType::Withdrawal,
             inputs: vec![input],
             outputs: vec![],
-            signature: signature.to_bytes(),
-            public_key: self.key_pair.public_key.bytes,
+            signature: Vec::new(),
+            public_key: self.signer.public_key(),
+            suite: self.signer.suite(),
             fee: 100,
             sender: recipient,
             recipient,
             tx_hash: [0u8; 32], // Will be calculated
         };
+        self.sign_transaction(&mut tx)?;
 
         Ok(tx)
     }
@@ -231,10 +325,11 @@ impl CompletePrivacyPoolExample {
                 nullifier_seed: [0u8; 32], // Placeholder
                 commitment: utxo.address, // Using address as commitment
                 index: utxo.height as u64,
+                nullifier_format: crate::utxo::utxo::NULLIFIER_FORMAT_VERSION,
             },
             merkle_proof: UTXOMerkleProof {
                 siblings: merkle_proof.siblings.clone(),
-                path: merkle_proof.path.iter().map(|&p| if p == 1 { 1u32 } else { 0u32 }).collect(),
+                path: merkle_proof.path.clone(),
                 root: merkle_proof.root,
                 leaf_index: merkle_proof.leaf_index as u64,
             },
@@ -251,46 +346,28 @@ impl CompletePrivacyPoolExample {
             blinding_factor: blinding,
         };
 
-        // Create transaction message
-        let mut message = Vec::new();
-        message.extend_from_slice(&(TransactionType::Transfer as u8).to_le_bytes());
-        message.extend_from_slice(&utxo.id.tx_hash);
-        message.extend_from_slice(&utxo.id.output_index.to_le_bytes());
-        message.extend_from_slice(&nullifier);
-        message.extend_from_slice(&output.commitment);
-        message.extend_from_slice(&sender);
-        message.extend_from_slice(&recipient);
-        message.extend_from_slice(&value.to_le_bytes());
-
-        // Sign transaction
-        let signature = self.key_pair.sign(&message);
-
         // Create transaction
-        let tx = PrivacyPoolTransaction {
+        let mut tx = PrivacyPoolTransaction {
             tx_type: TransactionType::Transfer,
             inputs: vec![input],
             outputs: vec![output],
-            signature: signature.to_bytes(),
-            public_key: self.key_pair.public_key.bytes,
+            signature: Vec::new(),
+            public_key: self.signer.public_key(),
+            suite: self.signer.suite(),
             fee: 100,
             sender,
             recipient,
             tx_hash: [0u8; 32], // Will be calculated
         };
+        self.sign_transaction(&mut tx)?;
 
         Ok(tx)
     }
 
     /// Process a transaction
     pub fn process_transaction(&mut self, tx: &PrivacyPoolTransaction) -> Result<bool, String> {
-        // Verify RedJubjub signature
-        let message = self.create_transaction_message(tx);
-        let signature = RedJubjubSignature::from_bytes(tx.signature.try_into().unwrap());
-        let public_key = RedJubjubPublicKey::new(tx.public_key);
-        
-        if !RedJubjubSignatureScheme::verify(&signature, &message, &public_key) {
-            return Err("Invalid signature".to_string());
-        }
+        // Verify signature with the suite the transaction is tagged with
+        self.verify_transaction_signature(tx)?;
 
         // Process based on transaction type
         match tx.tx_type {
@@ -298,10 +375,10 @@ impl CompletePrivacyPoolExample {
                 self.process_deposit(tx)?;
             },
             TransactionType::Withdrawal => {
-                self.process_withdrawal(tx)?;
+                self.process_withdrawal(tx).map_err(|e| e.to_string())?;
             },
             TransactionType::Transfer => {
-                self.process_transfer(tx)?;
+                self.process_transfer(tx).map_err(|e| e.to_string())?;
             },
         }
 
@@ -328,6 +405,7 @@ impl CompletePrivacyPoolExample {
                 nullifier_seed: [0u8; 32], // Placeholder
                 commitment: output.commitment,
                 index: 0, // Placeholder
+                nullifier_format: crate::utxo::utxo::NULLIFIER_FORMAT_VERSION,
             };
             // Convert UTXO to IndexedUTXO
             let indexed_utxo = IndexedUTXO {
@@ -346,7 +424,12 @@ impl CompletePrivacyPoolExample {
     }
 
     /// Process withdrawal transaction
-    fn process_withdrawal(&mut self, tx: &PrivacyPoolTransaction) -> Result<(), String> {
+    fn process_withdrawal(&mut self, tx: &PrivacyPoolTransaction) -> Result<(), SpendError> {
+        // Re-verify every input before touching any state
+        for input in &tx.inputs {
+            self.check_proof_freshness(&input.merkle_proof)?;
+        }
+
         for input in &tx.inputs {
             // Remove UTXO from set
             let utxo_id = UTXOId::new(input.utxo.commitment, 0);
@@ -355,7 +438,7 @@ impl CompletePrivacyPoolExample {
             // Process withdrawal in privacy pool
             let merkle_proof = EnhancedMerkleProof {
                 siblings: input.merkle_proof.siblings.clone(),
-                path: input.merkle_proof.path.iter().map(|&b| b as u32).collect(),
+                path: input.merkle_proof.path.clone(),
                 root: input.merkle_proof.root,
                 leaf_index: input.merkle_proof.leaf_index as u64,
             };
@@ -374,7 +457,12 @@ impl CompletePrivacyPoolExample {
     }
 
     /// Process transfer transaction
-    fn process_transfer(&mut self, tx: &PrivacyPoolTransaction) -> Result<(), String> {
+    fn process_transfer(&mut self, tx: &PrivacyPoolTransaction) -> Result<(), SpendError> {
+        // Re-verify every input before touching any state
+        for input in &tx.inputs {
+            self.check_proof_freshness(&input.merkle_proof)?;
+        }
+
         // Remove input UTXOs
         for input in &tx.inputs {
             let utxo_id = UTXOId::new(input.utxo.commitment, 0);
@@ -391,6 +479,7 @@ impl CompletePrivacyPoolExample {
                 nullifier_seed: [0u8; 32], // Placeholder
                 commitment: output.commitment,
                 index: 0, // Placeholder
+                nullifier_format: crate::utxo::utxo::NULLIFIER_FORMAT_VERSION,
             };
             // Convert UTXO to IndexedUTXO
             let indexed_utxo = IndexedUTXO {
@@ -421,7 +510,7 @@ impl CompletePrivacyPoolExample {
         let merkle_proofs: Vec<EnhancedMerkleProof> = tx.inputs.iter()
             .map(|i| EnhancedMerkleProof {
                 siblings: i.merkle_proof.siblings.clone(),
-                path: i.merkle_proof.path.iter().map(|&b| b as u32).collect(),
+                path: i.merkle_proof.path.clone(),
                 root: i.merkle_proof.root,
                 leaf_index: i.merkle_proof.leaf_index as u64,
             })
@@ -439,12 +528,31 @@ impl CompletePrivacyPoolExample {
         Ok(())
     }
 
+    /// Verify a transaction signature with the suite it is tagged with
+    pub fn verify_transaction_signature(&self, tx: &PrivacyPoolTransaction) -> Result<(), String> {
+        let message = self.create_transaction_message(tx);
+        
+        if !tx.suite.verify(&tx.public_key, &message, &tx.signature) {
+            return Err("Invalid signature".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Sign a transaction with the configured signer
+    fn sign_transaction(&self, tx: &mut PrivacyPoolTransaction) -> Result<(), String> {
+        let message = self.create_transaction_message(tx);
+        tx.signature = self.signer.sign(&message)?;
+        Ok(())
+    }
+
     /// Create transaction message for signing
     fn create_transaction_message(&self, tx: &PrivacyPoolTransaction) -> Vec<u8> {
         let mut data = Vec::new();
         
-        // Add transaction type
+        // Add transaction type and signature suite
         data.extend_from_slice(&(tx.tx_type as u8).to_le_bytes());
+        data.push(tx.suite as u8);
         
         // Add inputs
         for input in &tx.inputs {
@@ -500,8 +608,9 @@ pub struct PrivacyPoolTransaction {
     pub tx_type: TransactionType,
     pub inputs: Vec<UTXOInput>,
     pub outputs: Vec<UTXOOutput>,
-    pub signature: [u8; 64],
-    pub public_key: [u8; 32],
+    pub signature: Vec<u8>,
+    pub public_key: Vec<u8>,
+    pub suite: SignatureSuiteTag,
     pub fee: u64,
     pub sender: [u8; 32],
     pub recipient: [u8; 32],
@@ -558,6 +667,93 @@ mod tests {
         assert!(stats.pool_stats.pool_balance > 0);
     }
 
+    #[test]
+    fn test_stale_proof_respects_root_tolerance() {
+        let mut example = CompletePrivacyPoolExample::new().with_root_tolerance(1);
+        let leaf_index = example.insert_commitment([7u8; 32]).unwrap();
+        let proof = example.merkle_tree.generate_proof(leaf_index).unwrap();
+        let input = UTXOInput {
+            utxo: UTXO::new(1000, [1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32], [7u8; 32], leaf_index as u64),
+            merkle_proof: UTXOMerkleProof {
+                siblings: proof.siblings.clone(),
+                path: proof.path.clone(),
+                root: proof.root,
+                leaf_index: proof.leaf_index as u64,
+            },
+            nullifier: [9u8; 32],
+        };
+        assert!(example.check_proof_freshness(&input.merkle_proof).is_ok());
+
+        // Another leaf moves the root past the single-root window
+        example.insert_commitment([8u8; 32]).unwrap();
+        assert_eq!(
+            example.check_proof_freshness(&input.merkle_proof),
+            Err(SpendError::StaleProof { leaf_index: leaf_index as u64, proof_root: proof.root })
+        );
+
+        let tx = PrivacyPoolTransaction {
+            tx_type: TransactionType::Withdrawal,
+            inputs: vec![input],
+            outputs: vec![],
+            signature: Vec::new(),
+            public_key: Vec::new(),
+            suite: SignatureSuiteTag::RedJubjub,
+            fee: 100,
+            sender: [1u8; 32],
+            recipient: [1u8; 32],
+            tx_hash: [0u8; 32],
+        };
+        assert!(matches!(example.process_withdrawal(&tx), Err(SpendError::StaleProof { .. })));
+        assert!(matches!(example.process_transfer(&tx), Err(SpendError::StaleProof { .. })));
+
+        // The default window still accepts a proof one insertion behind
+        let mut example = CompletePrivacyPoolExample::new();
+        let leaf_index = example.insert_commitment([7u8; 32]).unwrap();
+        let proof = example.merkle_tree.generate_proof(leaf_index).unwrap();
+        example.insert_commitment([8u8; 32]).unwrap();
+        let merkle_proof = UTXOMerkleProof {
+            siblings: proof.siblings,
+            path: proof.path,
+            root: proof.root,
+            leaf_index: proof.leaf_index as u64,
+        };
+        assert!(example.check_proof_freshness(&merkle_proof).is_ok());
+
+        // A tampered sibling is rejected regardless of the window
+        let mut tampered = merkle_proof.clone();
+        tampered.siblings[0] = [0xffu8; 32];
+        assert_eq!(
+            example.check_proof_freshness(&tampered),
+            Err(SpendError::InvalidProof { leaf_index: leaf_index as u64 })
+        );
+    }
+
+    #[test]
+    fn test_transactions_verify_per_signature_suite() {
+        for suite in [SignatureSuiteTag::RedJubjub, SignatureSuiteTag::Ecdsa] {
+            let mut example = CompletePrivacyPoolExample::new().with_signature_suite(suite);
+            example.initialize();
+
+            let deposit_tx = example.create_deposit_transaction([1u8; 32], 1000).unwrap();
+            assert_eq!(deposit_tx.suite, suite);
+
+            // The wrong suite tag is rejected before any state changes
+            let mut mistagged = deposit_tx.clone();
+            mistagged.suite = match suite {
+                SignatureSuiteTag::RedJubjub => SignatureSuiteTag::Ecdsa,
+                SignatureSuiteTag::Ecdsa => SignatureSuiteTag::RedJubjub,
+            };
+            assert_eq!(example.process_transaction(&mistagged), Err("Invalid signature".to_string()));
+
+            // A tampered transaction fails under the correct suite
+            let mut tampered = deposit_tx.clone();
+            tampered.fee += 1;
+            assert_eq!(example.process_transaction(&tampered), Err("Invalid signature".to_string()));
+
+            assert!(example.verify_transaction_signature(&deposit_tx).is_ok());
+        }
+    }
+
     #[test]
     fn test_redjubjub_integration() {
         let key_pair = RedJubjubKeyPair::random();
@@ -3,6 +3,7 @@ pub mod privacy_pool;
 pub mod utxo_pool;
 pub mod enhanced_privacy_pool;
 pub mod complete_example;
+pub mod signature_suite;
 pub mod note_scanner;
 pub mod types;
 
@@ -13,4 +14,5 @@ pub use types::PoolStats;
 pub use privacy_pool::PrivacyPool;
 pub use utxo_pool::{UTXOPrivacyPool, ETHDepositEvent};
 pub use enhanced_privacy_pool::{EnhancedPrivacyPool, EnhancedUTXO, EnhancedTransaction, TransactionType as EnhancedTransactionType, MerkleProof as EnhancedMerkleProof};
+pub use signature_suite::{SignatureSuite, SignatureSuiteTag, SuiteKeypair};
 pub use complete_example::{CompletePrivacyPoolExample, CompleteSystemStats, PrivacyPoolTransaction, TransactionType as ExampleTransactionType};
@@ -4,12 +4,26 @@
 //! notes destined for the wallet's keys.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use anyhow::{Result, anyhow};
 use crate::utxo::note::{Note, EncryptedNote};
 use crate::crypto::ecies::Ecies;
 use crate::crypto::key_derivation::{ExtendedPrivateKey, DerivationPath};
 use crate::database::{DatabaseManager, schema::cf_names};
-use crate::relayer::EncryptedNoteEntry;
+use crate::relayer::{EncryptedNoteEntry, TreeService};
+
+/// A note recovered by trial decryption and found in the commitment tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecryptedNote {
+    /// Relayer-assigned ID of the encrypted entry
+    pub entry_id: String,
+    /// The decrypted note
+    pub note: Note,
+    /// Commitment recomputed from the note's opening
+    pub commitment: [u8; 32],
+    /// Position of the commitment in the tree
+    pub leaf_index: u64,
+}
 
 /// Wallet note scanner
 pub struct NoteScanner {
@@ -24,6 +38,9 @@ pub struct NoteScanner {
     
     /// Local note storage
     local_notes: HashMap<String, Note>,
+    
+    /// Full decryptions attempted, excluding notes ruled out by view tag
+    decryption_attempts: AtomicU64,
 }
 
 impl NoteScanner {
@@ -34,9 +51,15 @@ impl NoteScanner {
             master_key,
             derived_keys: HashMap::new(),
             local_notes: HashMap::new(),
+            decryption_attempts: AtomicU64::new(0),
         }
     }
     
+    /// Number of full decryptions attempted so far
+    pub fn decryption_attempts(&self) -> u64 {
+        self.decryption_attempts.load(Ordering::Relaxed)
+    }
+    
     /// Scan for notes from relayer
     pub async fn scan_relayer(&mut self, relayer_url: &str, since: u64) -> Result<Vec<Note>> {
         let mut discovered_notes = Vec::new();
@@ -56,6 +79,56 @@ impl NoteScanner {
         Ok(discovered_notes)
     }
     
+    /// Recover the notes in one block that belong to `viewing_key`
+    ///
+    /// Each entry is trial-decrypted unless its view tag rules the key out.
+    /// A decrypted note is returned only if its opening reproduces the
+    /// commitment, that commitment matches the one the entry advertises, and
+    /// `tree` contains it.
+    pub fn scan_block(
+        &self,
+        block_notes: &[EncryptedNoteEntry],
+        viewing_key: &[u8; 32],
+        tree: &TreeService,
+    ) -> Vec<DecryptedNote> {
+        let mut found = Vec::new();
+        
+        for entry in block_notes {
+            if !entry.view_tag_matches(viewing_key) {
+                continue;
+            }
+            
+            let encrypted_note = EncryptedNote {
+                ephemeral_pubkey: entry.ephemeral_pubkey,
+                nonce: entry.nonce,
+                ciphertext: entry.ciphertext.clone(),
+                commitment: entry.commitment,
+                view_tag: entry.view_tag,
+            };
+            
+            self.decryption_attempts.fetch_add(1, Ordering::Relaxed);
+            let Ok(note) = Ecies::decrypt_note(&encrypted_note, viewing_key) else {
+                continue;
+            };
+            
+            let commitment = Note::compute_commitment(note.value, &note.pubkey, &note.secret, &note.blinding);
+            if commitment != note.commitment || entry.commitment.is_some_and(|advertised| advertised != commitment) {
+                continue;
+            }
+            
+            if let Some(leaf_index) = tree.get_leaf_index(&format!("0x{}", hex::encode(commitment))) {
+                found.push(DecryptedNote {
+                    entry_id: entry.note_id.clone(),
+                    note,
+                    commitment,
+                    leaf_index,
+                });
+            }
+        }
+        
+        found
+    }
+    
     /// Try to decrypt a note using available keys
     pub async fn try_decrypt_note(&mut self, encrypted_entry: &EncryptedNoteEntry) -> Result<Option<Note>> {
         // Try master key first
@@ -92,14 +165,22 @@ impl NoteScanner {
         encrypted_entry: &EncryptedNoteEntry,
         private_key: &[u8; 32],
     ) -> Result<Option<Note>> {
+        // Skip notes whose view tag rules this key out; untagged notes are always tried
+        if !encrypted_entry.view_tag_matches(private_key) {
+            return Ok(None);
+        }
+        
         // Create encrypted note structure
         let encrypted_note = EncryptedNote {
             ephemeral_pubkey: encrypted_entry.ephemeral_pubkey,
             nonce: encrypted_entry.nonce,
             ciphertext: encrypted_entry.ciphertext.clone(),
             commitment: encrypted_entry.commitment,
+            view_tag: encrypted_entry.view_tag,
         };
         
+        self.decryption_attempts.fetch_add(1, Ordering::Relaxed);
+        
         // Try to decrypt
         match Ecies::decrypt_note(&encrypted_note, private_key) {
             Ok(note) => {
@@ -302,4 +383,131 @@ mod tests {
         // Test basic functionality
         assert!(scanner.get_local_notes().is_empty());
     }
+
+    fn open_scanner(temp_dir: &TempDir, seed: &[u8]) -> NoteScanner {
+        let db_config = DBConfig {
+            db_path: temp_dir.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let db = DatabaseManager::open(db_config).unwrap();
+        NoteScanner::new(db, ExtendedPrivateKey::from_seed(seed).unwrap())
+    }
+
+    fn entry_for(encrypted_note: EncryptedNote, index: usize) -> EncryptedNoteEntry {
+        EncryptedNoteEntry {
+            note_id: format!("note_{}", index),
+            ephemeral_pubkey: encrypted_note.ephemeral_pubkey,
+            nonce: encrypted_note.nonce,
+            ciphertext: encrypted_note.ciphertext,
+            commitment: encrypted_note.commitment,
+            uploaded_at: 0,
+            tx_hash: None,
+            output_index: None,
+            leaf_index: None,
+            view_tag: encrypted_note.view_tag,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_view_tag_scan_matches_trial_decryption() {
+        let owner_key = ExtendedPrivateKey::from_seed(b"test_seed").unwrap();
+        let owner_pubkey = owner_key.extended_public_key().unwrap().public_key;
+        let stranger_pubkey = ExtendedPrivateKey::from_seed(b"other_seed").unwrap()
+            .extended_public_key().unwrap().public_key;
+
+        let mut entries = Vec::new();
+        for i in 0..68u64 {
+            let recipient = if i % 17 == 0 { &owner_pubkey } else { &stranger_pubkey };
+            let note = Note::new(1000 + i, *recipient, 1, 1, "0x1234567890123456789012345678901234567890".to_string());
+            let encrypted_note = Ecies::encrypt_note(&note, recipient).unwrap();
+            if i % 17 == 0 {
+                let tag = Ecies::view_tag(&encrypted_note.ephemeral_pubkey, &owner_key.private_key).unwrap();
+                assert_eq!(encrypted_note.view_tag, Some(tag));
+            }
+            entries.push(entry_for(encrypted_note, i as usize));
+        }
+
+        let tagged_dir = TempDir::new().unwrap();
+        let mut tagged = open_scanner(&tagged_dir, b"test_seed");
+        let mut tagged_found = Vec::new();
+        for entry in &entries {
+            if let Some(note) = tagged.try_decrypt_note(entry).await.unwrap() {
+                tagged_found.push(note.note_id);
+            }
+        }
+
+        let untagged_dir = TempDir::new().unwrap();
+        let mut untagged = open_scanner(&untagged_dir, b"test_seed");
+        let mut untagged_found = Vec::new();
+        for entry in &entries {
+            let mut entry = entry.clone();
+            entry.view_tag = None;
+            if let Some(note) = untagged.try_decrypt_note(&entry).await.unwrap() {
+                untagged_found.push(note.note_id);
+            }
+        }
+
+        assert_eq!(tagged_found.len(), 4);
+        assert_eq!(tagged_found, untagged_found);
+
+        // Every key is tried on every foreign note without tags; with tags
+        // only the owned notes are decrypted, each by the first key tried
+        assert_eq!(untagged.decryption_attempts(), 4 + 64 * 101);
+        assert_eq!(tagged.decryption_attempts(), 4);
+    }
+
+    #[test]
+    fn test_scan_block_decrypts_only_tagged_matches() {
+        use crate::relayer::DepositEvent;
+
+        let owner_key = ExtendedPrivateKey::from_seed(b"test_seed").unwrap();
+        let owner_pubkey = owner_key.extended_public_key().unwrap().public_key;
+        let stranger_pubkey = ExtendedPrivateKey::from_seed(b"other_seed").unwrap()
+            .extended_public_key().unwrap().public_key;
+
+        // 5 of 200 notes are the owner's; all but the last are in the tree
+        let mut tree = TreeService::new();
+        let mut block_notes = Vec::new();
+        let mut owned_in_tree = Vec::new();
+        for i in 0..200u64 {
+            let owned = i % 40 == 0;
+            let recipient = if owned { owner_pubkey } else { stranger_pubkey };
+            let note = Note::new(1000 + i, recipient, 1, 1, "0x1234567890123456789012345678901234567890".to_string());
+            if i < 160 {
+                tree.add_deposit(&DepositEvent {
+                    depositor: "0x1234".to_string(),
+                    commitment: format!("0x{}", hex::encode(note.commitment)),
+                    label: i,
+                    value: note.value,
+                    precommitment_hash: "0x00".to_string(),
+                    block_number: 100,
+                    transaction_hash: format!("0xtx{}", i),
+                    log_index: i as u32,
+                    merkle_root: "0x0000".to_string(),
+                }).unwrap();
+                if owned {
+                    owned_in_tree.push((note.commitment, i));
+                }
+            }
+            block_notes.push(entry_for(Ecies::encrypt_note(&note, &recipient).unwrap(), i as usize));
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let scanner = open_scanner(&temp_dir, b"test_seed");
+        let found = scanner.scan_block(&block_notes, &owner_key.private_key, &tree);
+
+        let found: Vec<([u8; 32], u64)> = found.iter().map(|decrypted| (decrypted.commitment, decrypted.leaf_index)).collect();
+        assert_eq!(found, owned_in_tree);
+        // Only the five owned notes were fully decrypted
+        assert_eq!(scanner.decryption_attempts(), 5);
+
+        // Without view tags every note is trial-decrypted, with the same result
+        let untagged: Vec<EncryptedNoteEntry> = block_notes.iter()
+            .map(|entry| EncryptedNoteEntry { view_tag: None, ..entry.clone() })
+            .collect();
+        let untagged_dir = TempDir::new().unwrap();
+        let untagged_scanner = open_scanner(&untagged_dir, b"test_seed");
+        assert_eq!(untagged_scanner.scan_block(&untagged, &owner_key.private_key, &tree).len(), 4);
+        assert_eq!(untagged_scanner.decryption_attempts(), 200);
+    }
 }
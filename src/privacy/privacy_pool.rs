@@ -1,10 +1,13 @@
 //! Privacy Pool Implementation
 //! Core privacy pool functionality for the ZisK zkVM system
 
-use crate::utxo::{UTXO, User, MerkleProof};
+use crate::utxo::{UTXO, User, MerkleProof, UTXOTransaction, TransactionType, TransactionResult, Error};
 use crate::merkle::EnhancedMerkleTree;
+use crate::database::schema::{DatabaseManager, cf_names, utils};
+use crate::database::{AtomicBatchWriter, BatchOperation};
 use super::types::PoolStats;
 use serde::{Serialize, Deserialize};
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
 
 /// Privacy Pool State
@@ -17,6 +20,26 @@ pub struct PrivacyPool {
     pub users: HashMap<[u8; 32], User>, // address -> user
     /// Nullifier set (prevents double-spending)
     pub nullifier_set: HashSet<[u8; 32]>,
+    /// Successfully applied transactions (txid -> result) for replay protection
+    #[serde(default)]
+    pub processed_txids: HashMap<[u8; 32], TransactionResult>,
+    /// Database holding cf_processed_txids; replay checks read it and every
+    /// processed transaction or block records its applied txids there,
+    /// together with the fees it credited to the fee recipient
+    #[serde(skip)]
+    pub txid_store: Option<DatabaseManager>,
+    /// Pool user that receives transaction fees (fees are burned if unset)
+    #[serde(default)]
+    pub fee_recipient_commitment: Option<[u8; 32]>,
+    /// Reject outputs whose commitment is already a leaf
+    #[serde(default = "default_reject_commitment_collisions")]
+    pub reject_commitment_collisions: bool,
+    /// Verify block signatures on the rayon pool rather than one by one
+    #[serde(default = "default_parallel_signature_verification")]
+    pub parallel_signature_verification: bool,
+    /// Fee UTXOs created for the fee recipient
+    #[serde(default)]
+    pub fee_utxos: Vec<UTXO>,
     /// Pool balance (total committed value)
     pub pool_balance: u64,
     /// Pool capacity
@@ -27,6 +50,14 @@ pub struct PrivacyPool {
     pub scope: [u8; 32],
 }
 
+fn default_reject_commitment_collisions() -> bool {
+    true
+}
+
+fn default_parallel_signature_verification() -> bool {
+    true
+}
+
 impl PrivacyPool {
     /// Create a new privacy pool
     pub fn new(scope: [u8; 32]) -> Self {
@@ -34,6 +65,12 @@ impl PrivacyPool {
             merkle_tree: EnhancedMerkleTree::new(),
             users: HashMap::new(),
             nullifier_set: HashSet::new(),
+            processed_txids: HashMap::new(),
+            txid_store: None,
+            fee_recipient_commitment: None,
+            reject_commitment_collisions: true,
+            parallel_signature_verification: true,
+            fee_utxos: Vec::new(),
             pool_balance: 0,
             capacity: 2u32.pow(32), // 32-level tree
             size: 0,
@@ -41,6 +78,42 @@ impl PrivacyPool {
         }
     }
 
+    /// Set the pool user that accrues transaction fees
+    ///
+    /// The recipient must already be a pool user: fee UTXOs are keyed to
+    /// their private key so that only they can spend them.
+    pub fn set_fee_recipient(&mut self, fee_recipient_commitment: Option<[u8; 32]>) -> Result<(), String> {
+        if let Some(recipient) = fee_recipient_commitment {
+            if !self.users.contains_key(&recipient) {
+                return Err("Fee recipient is not a pool user".to_string());
+            }
+        }
+        self.fee_recipient_commitment = fee_recipient_commitment;
+        Ok(())
+    }
+
+    /// Persist applied txids and collected fees in `db` (memory only if unset)
+    pub fn set_txid_store(&mut self, db: Option<DatabaseManager>) {
+        self.txid_store = db;
+    }
+
+    /// Enable or disable the output commitment freshness check
+    pub fn set_reject_commitment_collisions(&mut self, reject: bool) {
+        self.reject_commitment_collisions = reject;
+    }
+
+    /// Choose between parallel and sequential block signature verification
+    pub fn set_parallel_signature_verification(&mut self, parallel: bool) {
+        self.parallel_signature_verification = parallel;
+    }
+
+    /// Get fee UTXOs owned by a recipient commitment
+    pub fn get_fee_utxos(&self, recipient_commitment: [u8; 32]) -> Vec<&UTXO> {
+        self.fee_utxos.iter()
+            .filter(|utxo| utxo.verify_ownership(&recipient_commitment))
+            .collect()
+    }
+
     /// Add a user to the pool
     pub fn add_user(&mut self, user: User) {
         self.users.insert(user.public_key, user);
@@ -108,6 +181,292 @@ impl PrivacyPool {
         Ok(())
     }
 
+    /// Process a transaction, rejecting replays of an already applied txid
+    ///
+    /// A replayed transaction returns `Error::DuplicateTransaction` carrying the
+    /// result of the first submission and leaves pool state untouched. Only
+    /// successful transactions are recorded, so a failed one may be retried;
+    /// with a txid store the record is committed to cf_processed_txids, and
+    /// the transaction is rolled back if that commit fails. A transaction
+    /// spending one nullifier twice is malformed and rejected with
+    /// `Error::DuplicateNullifierInTx` without being recorded. Unless disabled,
+    /// an output whose commitment is already in the tree (or repeated within
+    /// the transaction) is rejected with `Error::CommitmentCollision`. Input
+    /// and output counts that do not fit the transaction type are rejected
+    /// first with `Error::InvalidTxStructure`, and an invalid signature with
+    /// `Error::InvalidSignature`. Every input must open to a leaf of the tree
+    /// (`Error::UnknownInput`) and carry the nullifier derived from that note
+    /// (`Error::NullifierMismatch`).
+    pub fn process_transaction(&mut self, tx: &UTXOTransaction) -> Result<TransactionResult, Error> {
+        let checkpoint = self.checkpoint();
+        let mut applied = Vec::new();
+        let result = self.process_unrecorded(tx, false, &mut applied)?;
+        self.commit_or_roll_back(checkpoint, &applied)?;
+        Ok(result)
+    }
+
+    /// Check and apply `tx`, collecting what it applied in `applied` on
+    /// success without committing it to the txid store
+    ///
+    /// `signature_verified` skips the signature check for block transactions
+    /// already verified in the block pre-pass.
+    fn process_unrecorded(&mut self, tx: &UTXOTransaction, signature_verified: bool, applied: &mut Vec<AppliedTransaction>) -> Result<TransactionResult, Error> {
+        tx.validate_structure().map_err(Error::InvalidTxStructure)?;
+        
+        let mut tx_nullifiers = HashSet::new();
+        for input in &tx.inputs {
+            if !tx_nullifiers.insert(input.nullifier) {
+                return Err(Error::DuplicateNullifierInTx(input.nullifier));
+            }
+        }
+        
+        if !signature_verified && !tx.verify_signature() {
+            return Err(Error::InvalidSignature);
+        }
+        
+        let txid = tx.compute_txid();
+        
+        if let Some(prior_result) = self.prior_result(&txid)? {
+            return Err(Error::DuplicateTransaction { txid, prior_result });
+        }
+        
+        if self.reject_commitment_collisions {
+            let mut tx_commitments = HashSet::new();
+            for output in &tx.outputs {
+                if !tx_commitments.insert(output.commitment) || self.merkle_tree.has_commitment(&output.commitment) {
+                    return Err(Error::CommitmentCollision(output.commitment));
+                }
+            }
+        }
+        
+        // Input values are only trusted once the note opens to a leaf
+        for input in &tx.inputs {
+            let note = &input.utxo;
+            if note.compute_commitment() != note.commitment
+                || self.merkle_tree.get_leaf_index(&note.commitment) != Some(note.index)
+            {
+                return Err(Error::UnknownInput(note.commitment));
+            }
+            if !note.verify_nullifier(input.nullifier) {
+                return Err(Error::NullifierMismatch(input.nullifier));
+            }
+        }
+        
+        let fee_utxo_count = self.fee_utxos.len();
+        let result = self.apply_transaction(tx, txid);
+        if result.is_success() {
+            self.processed_txids.insert(txid, result.clone());
+            applied.push(AppliedTransaction {
+                txid,
+                nullifiers: tx.inputs.iter().map(|input| input.nullifier).collect(),
+                collected_fee: if self.fee_utxos.len() > fee_utxo_count { tx.fee } else { 0 },
+            });
+        }
+        
+        Ok(result)
+    }
+
+    /// Pool state a processing call starts from
+    fn checkpoint(&self) -> PoolCheckpoint {
+        PoolCheckpoint {
+            next_leaf_index: self.merkle_tree.next_leaf_index,
+            pool_balance: self.pool_balance,
+            size: self.size,
+            fee_utxo_count: self.fee_utxos.len(),
+        }
+    }
+
+    /// Record `applied` in the txid store, undoing it if the commit fails
+    ///
+    /// Every applied transaction spent fresh nullifiers and was a fresh
+    /// txid, so removing them restores the sets exactly; leaves and fee
+    /// UTXOs were appended after the checkpoint and are truncated away.
+    fn commit_or_roll_back(&mut self, checkpoint: PoolCheckpoint, applied: &[AppliedTransaction]) -> Result<(), Error> {
+        let Err(error) = self.commit_applied(applied) else {
+            return Ok(());
+        };
+        
+        for transaction in applied {
+            self.processed_txids.remove(&transaction.txid);
+            for nullifier in &transaction.nullifiers {
+                self.nullifier_set.remove(nullifier);
+            }
+        }
+        for fee_utxo in self.fee_utxos.drain(checkpoint.fee_utxo_count..) {
+            if let Some(user) = self.users.get_mut(&fee_utxo.owner) {
+                user.utxos.retain(|utxo| utxo.commitment != fee_utxo.commitment);
+            }
+        }
+        self.merkle_tree.truncate_leaves(checkpoint.next_leaf_index)
+            .map_err(|e| Error::MerkleTreeError(e.to_string()))?;
+        self.pool_balance = checkpoint.pool_balance;
+        self.size = checkpoint.size;
+        Err(error)
+    }
+
+    /// Result of an earlier successful submission of `txid`, if any
+    fn prior_result(&self, txid: &[u8; 32]) -> Result<Option<TransactionResult>, Error> {
+        if let Some(prior_result) = self.processed_txids.get(txid) {
+            return Ok(Some(prior_result.clone()));
+        }
+        match &self.txid_store {
+            Some(db) => Ok(db.get_cf(cf_names::PROCESSED_TXIDS, &utils::processed_txid_key(txid))?
+                .map(|_| TransactionResult::Success)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record applied txids in cf_processed_txids and their collected fees in
+    /// the fee counter as one batch
+    ///
+    /// The batch refuses a txid that is already stored, so two pools sharing
+    /// a store cannot both record the same transaction.
+    fn commit_applied(&self, applied: &[AppliedTransaction]) -> Result<(), Error> {
+        let Some(db) = &self.txid_store else {
+            return Ok(());
+        };
+        let mut writer = AtomicBatchWriter::new(db.clone());
+        for transaction in applied {
+            writer.add_operation(BatchOperation::RecordTxid { txid: transaction.txid });
+            if transaction.collected_fee > 0 {
+                writer.add_operation(BatchOperation::RecordFee { amount_wei: transaction.collected_fee as u128 });
+            }
+        }
+        writer.commit()?;
+        Ok(())
+    }
+
+    /// Process a block of transactions in order
+    ///
+    /// All signatures are checked in a pre-pass before any state changes, so
+    /// a block with an invalid signature is rejected as a whole with
+    /// `Error::InvalidSignatureInBlock`. The sequential pass then applies each
+    /// transaction without re-verifying, and reports per-transaction outcomes
+    /// as `process_transaction` would. The txids and collected fees of all
+    /// applied transactions are recorded in the txid store in a single commit
+    /// for the block; if it fails the whole block is rolled back.
+    pub fn process_block(&mut self, transactions: &[UTXOTransaction]) -> Result<Vec<Result<TransactionResult, Error>>, Error> {
+        let verdicts = Self::verify_signatures(transactions, self.parallel_signature_verification);
+        if let Some(index) = verdicts.iter().position(|valid| !valid) {
+            return Err(Error::InvalidSignatureInBlock(index));
+        }
+        
+        let checkpoint = self.checkpoint();
+        let mut applied = Vec::new();
+        let results = transactions.iter().map(|tx| self.process_unrecorded(tx, true, &mut applied)).collect();
+        self.commit_or_roll_back(checkpoint, &applied)?;
+        Ok(results)
+    }
+
+    /// Signature validity of each transaction, in block order
+    pub fn verify_signatures(transactions: &[UTXOTransaction], parallel: bool) -> Vec<bool> {
+        if parallel {
+            transactions.par_iter().map(UTXOTransaction::verify_signature).collect()
+        } else {
+            transactions.iter().map(UTXOTransaction::verify_signature).collect()
+        }
+    }
+
+    /// Apply transaction effects (nullifiers, output commitments, fee UTXO, pool balance)
+    fn apply_transaction(&mut self, tx: &UTXOTransaction, txid: [u8; 32]) -> TransactionResult {
+        if tx.tx_type != TransactionType::Deposit && !tx.verify_balance() {
+            return TransactionResult::Failure("Insufficient input value".to_string());
+        }
+        
+        // Validate all nullifiers before mutating state (duplicates within
+        // the transaction were rejected by `process_transaction`)
+        if tx.inputs.iter().any(|input| self.nullifier_set.contains(&input.nullifier)) {
+            return TransactionResult::Failure("UTXO already spent".to_string());
+        }
+        
+        // Fees are only deducted from spends; deposits carry no input value.
+        // Zero fees create nothing, as a zero-value UTXO would never validate.
+        // A configured recipient that left the pool fails the transaction
+        // rather than burning its fee.
+        let fee_utxo = match self.fee_recipient_commitment {
+            Some(recipient) if tx.fee > 0 && tx.tx_type != TransactionType::Deposit => {
+                match self.users.get(&recipient) {
+                    Some(user) => Some(Self::create_fee_utxo(user, tx.fee, txid)),
+                    None => return TransactionResult::Failure("Fee recipient is not a pool user".to_string()),
+                }
+            }
+            _ => None,
+        };
+        
+        let mut commitments: Vec<[u8; 32]> = tx.outputs.iter().map(|output| output.commitment).collect();
+        if let Some(fee_utxo) = &fee_utxo {
+            commitments.push(fee_utxo.commitment);
+        }
+        
+        let commitment_count = commitments.len() as u32;
+        if self.size + commitment_count > self.capacity {
+            return TransactionResult::Failure("Pool is full".to_string());
+        }
+        
+        // A failed insert drops the leaves this transaction already added
+        let next_leaf_index = self.merkle_tree.next_leaf_index;
+        let mut fee_leaf_index = 0;
+        for commitment in commitments {
+            match self.merkle_tree.insert_leaf(commitment) {
+                Ok(leaf_index) => fee_leaf_index = leaf_index,
+                Err(e) => {
+                    let _ = self.merkle_tree.truncate_leaves(next_leaf_index);
+                    return TransactionResult::Failure(format!("Failed to insert commitment: {}", e));
+                }
+            }
+        }
+        
+        for input in &tx.inputs {
+            self.nullifier_set.insert(input.nullifier);
+        }
+        
+        match tx.tx_type {
+            TransactionType::Deposit => {
+                self.pool_balance += tx.get_total_output_value();
+            }
+            TransactionType::Withdrawal | TransactionType::Transfer => {
+                let retained_fee = if fee_utxo.is_some() { tx.fee } else { 0 };
+                let spent = tx.get_total_input_value() - tx.get_total_output_value() - retained_fee;
+                self.pool_balance = self.pool_balance.saturating_sub(spent);
+            }
+        }
+        self.size += commitment_count;
+        
+        if let Some(mut fee_utxo) = fee_utxo {
+            // The fee commitment is always inserted last
+            fee_utxo.index = fee_leaf_index;
+            if let Some(user) = self.users.get_mut(&fee_utxo.owner) {
+                user.add_utxo(fee_utxo.clone());
+            }
+            self.fee_utxos.push(fee_utxo);
+        }
+        
+        TransactionResult::Success
+    }
+
+    /// Create the fee UTXO credited to the fee recipient for a transaction
+    ///
+    /// Its secret and blinding factor are derived from the recipient's
+    /// private key and the txid, so only the recipient can open and spend it.
+    fn create_fee_utxo(recipient: &User, fee: u64, txid: [u8; 32]) -> UTXO {
+        let secret = Self::fee_utxo_key_material(b"FEE_UTXO_SECRET", recipient, txid);
+        let blinding_factor = Self::fee_utxo_key_material(b"FEE_UTXO_BLINDING", recipient, txid);
+        
+        let mut utxo = UTXO::new(fee, secret, recipient.public_key, blinding_factor, txid, [0u8; 32], 0);
+        utxo.commitment = utxo.compute_commitment();
+        utxo
+    }
+
+    /// Keccak256(domain || recipient private key || txid)
+    fn fee_utxo_key_material(domain: &[u8], recipient: &User, txid: [u8; 32]) -> [u8; 32] {
+        use sha3::{Keccak256, Digest};
+        let mut hasher = Keccak256::new();
+        hasher.update(domain);
+        hasher.update(&recipient.private_key);
+        hasher.update(&txid);
+        hasher.finalize().into()
+    }
+
     /// Generate Merkle proof for UTXO
     pub fn generate_proof(&self, utxo: &UTXO) -> Option<MerkleProof> {
         self.merkle_tree.generate_proof(utxo.commitment)
@@ -163,8 +522,504 @@ impl PrivacyPool {
 
 // PoolStats moved to super::types to avoid duplication
 
+/// What one transaction applied, kept until its txid is committed
+struct AppliedTransaction {
+    txid: [u8; 32],
+    nullifiers: Vec<[u8; 32]>,
+    /// Fee credited to the fee recipient (0 if none)
+    collected_fee: u64,
+}
+
+/// Pool state before a processing call, restored if its commit fails
+struct PoolCheckpoint {
+    next_leaf_index: u64,
+    pool_balance: u64,
+    size: u32,
+    fee_utxo_count: usize,
+}
+
 impl Default for PrivacyPool {
     fn default() -> Self {
         Self::new([0u8; 32])
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utxo::{UTXOInput, UTXOOutput};
+    use crate::crypto::signatures::Ed25519Sig;
+
+    // Sign `tx` with a key derived from `tag`
+    fn signed(mut tx: UTXOTransaction, tag: u8) -> UTXOTransaction {
+        tx.signature = Ed25519Sig::sign_message(&[tag; 32], &tx.signing_message()).unwrap().to_bytes().to_vec();
+        tx
+    }
+
+    fn deposit_transaction() -> UTXOTransaction {
+        let output = UTXOOutput {
+            value: 1_000_000_000_000_000_000u64,
+            recipient: [0x50u8; 32],
+            commitment: [0x51u8; 32],
+            blinding_factor: [0x52u8; 32],
+        };
+        
+        signed(UTXOTransaction::new(
+            TransactionType::Deposit,
+            vec![],
+            vec![output],
+            0,
+            vec![],
+            [0x43u8; 32],
+        ), 0x42)
+    }
+
+    // Note worth `value` whose commitment is a leaf of `pool`, unique per `tag`
+    fn funded_note(pool: &mut PrivacyPool, value: u64, tag: u8) -> UTXO {
+        let mut note = UTXO::new(value, [tag; 32], [0x61u8; 32], [tag; 32], [0x63u8; 32], [0u8; 32], 0);
+        note.commitment = note.compute_commitment();
+        note.index = pool.merkle_tree.insert_leaf(note.commitment).unwrap();
+        pool.pool_balance += value;
+        pool.size += 1;
+        note
+    }
+
+    // Unsigned transfer of `note` to one output, unique per `tag`
+    fn spend_transaction(note: &UTXO, fee: u64, tag: u8) -> UTXOTransaction {
+        let input = UTXOInput {
+            utxo: note.clone(),
+            merkle_proof: MerkleProof::new(vec![], vec![], [0u8; 32], 0),
+            nullifier: note.generate_nullifier(),
+        };
+        let output = UTXOOutput {
+            value: note.value - fee,
+            recipient: [0x66u8; 32],
+            commitment: [tag.wrapping_add(0x80); 32],
+            blinding_factor: [0x68u8; 32],
+        };
+        
+        UTXOTransaction::new(
+            TransactionType::Transfer,
+            vec![input],
+            vec![output],
+            fee,
+            vec![],
+            [0x43u8; 32],
+        )
+    }
+
+    // Signed transfer of a fresh 1_000 note funded into `pool`
+    fn signed_transfer(pool: &mut PrivacyPool, tag: u8, fee: u64) -> UTXOTransaction {
+        let note = funded_note(pool, 1_000, tag);
+        signed(spend_transaction(&note, fee, tag), tag)
+    }
+
+    #[test]
+    fn test_duplicate_nullifier_in_transaction_rejected() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        
+        let mut tx = spend_transaction(&funded_note(&mut pool, 1_000, 1), 0, 1);
+        let mut second = tx.inputs[0].clone();
+        second.utxo = funded_note(&mut pool, 1_000, 2);
+        tx.inputs.push(second);
+        tx.outputs[0].value = 2_000;
+        let tx = signed(tx, 1);
+        
+        match pool.process_transaction(&tx) {
+            Err(Error::DuplicateNullifierInTx(nullifier)) => assert_eq!(nullifier, tx.inputs[0].nullifier),
+            other => panic!("expected DuplicateNullifierInTx, got {:?}", other),
+        }
+        assert!(pool.nullifier_set.is_empty());
+        assert_eq!(pool.pool_balance, 2_000);
+    }
+
+    #[test]
+    fn test_output_commitment_collision_rejected() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        let tx = signed_transfer(&mut pool, 1, 0);
+        pool.merkle_tree.insert_leaf(tx.outputs[0].commitment).unwrap();
+        
+        match pool.process_transaction(&tx) {
+            Err(Error::CommitmentCollision(commitment)) => assert_eq!(commitment, tx.outputs[0].commitment),
+            other => panic!("expected CommitmentCollision, got {:?}", other),
+        }
+        assert!(pool.nullifier_set.is_empty());
+        assert!(pool.processed_txids.is_empty());
+        assert_eq!(pool.pool_balance, 1_000);
+        
+        pool.set_reject_commitment_collisions(false);
+        assert!(pool.process_transaction(&tx).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_structure_rejected_per_type() {
+        use crate::utxo::TxStructureViolation;
+        
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        let transfer = signed_transfer(&mut pool, 1, 0);
+        
+        let mut deposit = deposit_transaction();
+        deposit.inputs = transfer.inputs.clone();
+        let mut empty_deposit = deposit_transaction();
+        empty_deposit.outputs.clear();
+        
+        let mut withdrawal = transfer.clone();
+        withdrawal.tx_type = TransactionType::Withdrawal;
+        let mut inputless_withdrawal = withdrawal.clone();
+        inputless_withdrawal.inputs.clear();
+        let mut split_withdrawal = withdrawal.clone();
+        split_withdrawal.outputs.push(split_withdrawal.outputs[0].clone());
+        
+        let mut inputless_transfer = transfer.clone();
+        inputless_transfer.inputs.clear();
+        let mut outputless_transfer = transfer.clone();
+        outputless_transfer.outputs.clear();
+        
+        let cases = [
+            (deposit, TxStructureViolation::DepositHasInputs(1)),
+            (empty_deposit, TxStructureViolation::DepositWithoutOutputs),
+            (inputless_withdrawal, TxStructureViolation::WithdrawalWithoutInputs),
+            (split_withdrawal, TxStructureViolation::WithdrawalTooManyOutputs(2)),
+            (inputless_transfer, TxStructureViolation::TransferWithoutInputs),
+            (outputless_transfer, TxStructureViolation::TransferWithoutOutputs),
+        ];
+        
+        for (tx, expected) in cases {
+            match pool.process_transaction(&tx) {
+                Err(Error::InvalidTxStructure(violation)) => assert_eq!(violation, expected),
+                other => panic!("expected InvalidTxStructure({:?}), got {:?}", expected, other),
+            }
+        }
+        assert!(pool.nullifier_set.is_empty());
+        assert!(pool.processed_txids.is_empty());
+        assert_eq!(pool.pool_balance, 1_000);
+        
+        // A withdrawal with a single change output is well formed
+        assert!(withdrawal.validate_structure().is_ok());
+    }
+
+    #[test]
+    fn test_spend_requires_signature_and_real_notes() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        let note = funded_note(&mut pool, 1_000, 1);
+        
+        // Unsigned and tampered transactions are rejected outright
+        let unsigned = spend_transaction(&note, 0, 1);
+        assert!(matches!(pool.process_transaction(&unsigned), Err(Error::InvalidSignature)));
+        let mut tampered = signed(spend_transaction(&note, 0, 1), 1);
+        tampered.outputs[0].value += 1;
+        assert!(matches!(pool.process_transaction(&tampered), Err(Error::InvalidSignature)));
+        
+        // A made-up note, or a real commitment with an inflated value, opens to no leaf
+        let mut made_up = UTXO::new(5_000, [9u8; 32], [0x61u8; 32], [9u8; 32], [0x63u8; 32], [0u8; 32], 0);
+        made_up.commitment = made_up.compute_commitment();
+        let forged = signed(spend_transaction(&made_up, 0, 2), 2);
+        assert!(matches!(pool.process_transaction(&forged), Err(Error::UnknownInput(c)) if c == made_up.commitment));
+        let mut inflated_note = note.clone();
+        inflated_note.value = 5_000;
+        let inflated = signed(spend_transaction(&inflated_note, 0, 3), 3);
+        assert!(matches!(pool.process_transaction(&inflated), Err(Error::UnknownInput(_))));
+        
+        // The nullifier must be the one derived from the note
+        let mut unbound = spend_transaction(&note, 0, 4);
+        unbound.inputs[0].nullifier = [0x65u8; 32];
+        let unbound = signed(unbound, 4);
+        assert!(matches!(pool.process_transaction(&unbound), Err(Error::NullifierMismatch(n)) if n == [0x65u8; 32]));
+        
+        assert!(pool.nullifier_set.is_empty());
+        assert!(pool.processed_txids.is_empty());
+        assert_eq!(pool.pool_balance, 1_000);
+        assert_eq!(pool.size, 1);
+        
+        assert!(pool.process_transaction(&signed(spend_transaction(&note, 0, 5), 5)).unwrap().is_success());
+    }
+
+    #[test]
+    fn test_block_with_invalid_signature_rejected() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        let mut block: Vec<UTXOTransaction> = (1..=3).map(|tag| signed_transfer(&mut pool, tag, 0)).collect();
+        let valid = block[1].clone();
+        block[1].signature[0] ^= 0x01;
+        
+        let parallel = PrivacyPool::verify_signatures(&block, true);
+        assert_eq!(parallel, vec![true, false, true]);
+        assert_eq!(parallel, PrivacyPool::verify_signatures(&block, false));
+        
+        match pool.process_block(&block) {
+            Err(Error::InvalidSignatureInBlock(index)) => assert_eq!(index, 1),
+            other => panic!("expected InvalidSignatureInBlock, got {:?}", other),
+        }
+        assert!(pool.nullifier_set.is_empty());
+        assert!(pool.processed_txids.is_empty());
+        
+        // Truncated signatures fail verification instead of panicking
+        block[1].signature.truncate(10);
+        pool.set_parallel_signature_verification(false);
+        assert!(matches!(pool.process_block(&block), Err(Error::InvalidSignatureInBlock(1))));
+        
+        block[1] = valid;
+        let results = pool.process_block(&block).unwrap();
+        assert!(results.iter().all(|result| matches!(result, Ok(result) if result.is_success())));
+        assert_eq!(pool.nullifier_set.len(), 3);
+    }
+
+    #[test]
+    fn test_fee_utxo_created_for_fee_recipient() {
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        
+        let tx = signed_transfer(&mut pool, 1, 25);
+        assert!(pool.process_transaction(&tx).unwrap().is_success());
+        
+        let fee_utxos = pool.get_fee_utxos(fee_recipient);
+        assert_eq!(fee_utxos.len(), 1);
+        assert_eq!(fee_utxos[0].value, 25);
+        // After the spent note and the transfer output
+        assert_eq!(fee_utxos[0].index, 2);
+        
+        // Only the recipient's key opens the fee UTXO, which they now hold
+        let recipient = pool.users[&fee_recipient].clone();
+        let expected = PrivacyPool::create_fee_utxo(&recipient, 25, tx.compute_txid());
+        assert_ne!(fee_utxos[0].secret, [0u8; 32]);
+        assert_eq!(fee_utxos[0].secret, expected.secret);
+        assert_eq!(fee_utxos[0].blinding_factor, expected.blinding_factor);
+        assert_eq!(fee_utxos[0].commitment, fee_utxos[0].compute_commitment());
+        let stranger = User::new(fee_recipient, [0xfcu8; 32]);
+        assert_ne!(PrivacyPool::create_fee_utxo(&stranger, 25, tx.compute_txid()).secret, expected.secret);
+        assert_eq!(recipient.utxos.len(), 1);
+        assert_eq!(recipient.utxos[0].commitment, fee_utxos[0].commitment);
+        
+        // The fee stays in the pool alongside the transfer output
+        assert_eq!(pool.size, 3);
+        assert_eq!(pool.pool_balance, 1_000);
+        
+        // Fees cannot be sent to a key the pool does not know
+        assert!(pool.set_fee_recipient(Some([0xaau8; 32])).is_err());
+        assert_eq!(pool.fee_recipient_commitment, Some(fee_recipient));
+    }
+
+    #[test]
+    fn test_fee_not_burned_when_recipient_left() {
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        pool.users.remove(&fee_recipient);
+        
+        let tx = signed_transfer(&mut pool, 1, 25);
+        assert!(!pool.process_transaction(&tx).unwrap().is_success());
+        assert!(pool.fee_utxos.is_empty());
+        assert!(pool.nullifier_set.is_empty());
+        assert_eq!(pool.size, 1);
+        assert_eq!(pool.pool_balance, 1_000);
+        
+        // Fee-free spends still go through
+        let free = signed_transfer(&mut pool, 2, 0);
+        assert!(pool.process_transaction(&free).unwrap().is_success());
+    }
+
+    #[test]
+    fn test_fee_burned_without_fee_recipient() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        
+        let tx = signed_transfer(&mut pool, 1, 25);
+        assert!(pool.process_transaction(&tx).unwrap().is_success());
+        
+        assert!(pool.fee_utxos.is_empty());
+        assert_eq!(pool.size, 2);
+        assert_eq!(pool.pool_balance, 975);
+    }
+
+    #[test]
+    fn test_duplicate_transaction_rejected() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        let tx = deposit_transaction();
+        
+        let result = pool.process_transaction(&tx).unwrap();
+        assert!(result.is_success());
+        
+        let root_after_first = pool.get_merkle_root();
+        let balance_after_first = pool.pool_balance;
+        let size_after_first = pool.size;
+        
+        match pool.process_transaction(&tx) {
+            Err(Error::DuplicateTransaction { txid, prior_result }) => {
+                assert_eq!(txid, tx.compute_txid());
+                assert!(prior_result.is_success());
+            }
+            other => panic!("expected DuplicateTransaction, got {:?}", other),
+        }
+        
+        // Replay must not re-apply the transaction
+        assert_eq!(pool.get_merkle_root(), root_after_first);
+        assert_eq!(pool.pool_balance, balance_after_first);
+        assert_eq!(pool.size, size_after_first);
+    }
+
+    #[test]
+    fn test_failed_transaction_can_be_retried() {
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.capacity = 0;
+        let tx = deposit_transaction();
+        
+        assert!(!pool.process_transaction(&tx).unwrap().is_success());
+        assert!(pool.processed_txids.is_empty());
+        
+        pool.capacity = 1;
+        assert!(pool.process_transaction(&tx).unwrap().is_success());
+        assert!(matches!(pool.process_transaction(&tx), Err(Error::DuplicateTransaction { .. })));
+    }
+
+    #[test]
+    fn test_applied_txids_persist_in_store() {
+        use crate::database::schema::DBConfig;
+        use crate::database::WriteBatchError;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.set_txid_store(Some(db.clone()));
+        let mut block: Vec<UTXOTransaction> = (1..=2).map(|tag| signed_transfer(&mut pool, tag, 0)).collect();
+        // The third transfer spends the first one's note again and fails
+        block.push(signed(spend_transaction(&block[0].inputs[0].utxo, 0, 3), 3));
+        let results = pool.process_block(&block).unwrap();
+        assert!(results[0].as_ref().unwrap().is_success());
+        assert!(!results[2].as_ref().unwrap().is_success());
+        
+        let stored = |tx: &UTXOTransaction| db.get_cf(cf_names::PROCESSED_TXIDS, &utils::processed_txid_key(&tx.compute_txid())).unwrap().is_some();
+        assert!(stored(&block[0]) && stored(&block[1]));
+        assert!(!stored(&block[2]));
+        
+        // A pool restored without the in-memory set still rejects the replay
+        let mut restored = PrivacyPool::new([0u8; 32]);
+        restored.set_txid_store(Some(db.clone()));
+        match restored.process_transaction(&block[1]) {
+            Err(Error::DuplicateTransaction { txid, prior_result }) => {
+                assert_eq!(txid, block[1].compute_txid());
+                assert!(prior_result.is_success());
+            }
+            other => panic!("expected DuplicateTransaction, got {:?}", other),
+        }
+        
+        // Recording a stored txid again fails the batch
+        let mut writer = AtomicBatchWriter::new(db);
+        writer.add_operation(BatchOperation::RecordTxid { txid: block[0].compute_txid() });
+        let error = writer.commit().unwrap_err();
+        assert!(matches!(error.downcast_ref::<WriteBatchError>(), Some(WriteBatchError::TransactionAlreadyProcessed(_))));
+    }
+
+    #[test]
+    fn test_failed_txid_commit_rolls_back_state() {
+        use crate::database::schema::DBConfig;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        pool.set_txid_store(Some(db.clone()));
+        let block: Vec<UTXOTransaction> = (1..=2).map(|tag| signed_transfer(&mut pool, tag, 25)).collect();
+        
+        // A saturated fee counter makes every fee-carrying commit fail
+        let mut writer = AtomicBatchWriter::new(db);
+        writer.add_operation(BatchOperation::PutMetadata {
+            key: crate::database::pool_counters::FEES_COLLECTED_KEY.to_vec(),
+            value: u128::MAX.to_be_bytes().to_vec(),
+        });
+        writer.commit().unwrap();
+        
+        let root = pool.get_merkle_root();
+        let (balance, size) = (pool.pool_balance, pool.size);
+        let assert_untouched = |pool: &PrivacyPool| {
+            assert_eq!(pool.get_merkle_root(), root);
+            assert_eq!((pool.pool_balance, pool.size), (balance, size));
+            assert!(pool.nullifier_set.is_empty());
+            assert!(pool.processed_txids.is_empty());
+            assert!(pool.fee_utxos.is_empty());
+            assert!(pool.users[&fee_recipient].utxos.is_empty());
+        };
+        
+        // Both transactions apply in memory, then the block commit fails
+        assert!(pool.process_block(&block).is_err());
+        assert_untouched(&pool);
+        assert!(pool.process_transaction(&block[0]).is_err());
+        assert_untouched(&pool);
+        
+        // The rolled back transaction applies cleanly once the fee is waived
+        pool.set_fee_recipient(None).unwrap();
+        assert!(pool.process_transaction(&block[0]).unwrap().is_success());
+        assert_eq!(pool.nullifier_set.len(), 1);
+    }
+
+    #[test]
+    fn test_zero_fee_creates_no_fee_utxo() {
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        
+        let tx = signed_transfer(&mut pool, 1, 0);
+        assert!(pool.process_transaction(&tx).unwrap().is_success());
+        
+        assert!(pool.get_fee_utxos(fee_recipient).is_empty());
+        assert_eq!(pool.size, 2);
+        assert_eq!(pool.pool_balance, 1_000);
+    }
+
+    #[test]
+    fn test_fee_utxos_sum_to_fees_charged() {
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        
+        let fees = [25u64, 0, 10, 40];
+        for (i, fee) in fees.iter().enumerate() {
+            let tx = signed_transfer(&mut pool, i as u8 + 1, *fee);
+            assert!(pool.process_transaction(&tx).unwrap().is_success());
+        }
+        
+        let fee_utxos = pool.get_fee_utxos(fee_recipient);
+        assert_eq!(fee_utxos.len(), 3);
+        assert!(fee_utxos.iter().all(|utxo| utxo.value > 0));
+        assert_eq!(fee_utxos.iter().map(|utxo| utxo.value).sum::<u64>(), fees.iter().sum::<u64>());
+        assert_eq!(pool.pool_balance, 4_000);
+    }
+
+    #[test]
+    fn test_block_commit_records_collected_fees() {
+        use crate::database::schema::DBConfig;
+        use crate::database::QueryEngine;
+        
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        
+        let fee_recipient = [0xfeu8; 32];
+        let mut pool = PrivacyPool::new([0u8; 32]);
+        pool.add_user(User::new(fee_recipient, [0xfdu8; 32]));
+        pool.set_fee_recipient(Some(fee_recipient)).unwrap();
+        pool.set_txid_store(Some(db.clone()));
+        
+        let mut first_note = None;
+        for (i, fee) in [25u64, 0, 10].iter().enumerate() {
+            let tx = signed_transfer(&mut pool, i as u8 + 1, *fee);
+            first_note.get_or_insert_with(|| tx.inputs[0].utxo.clone());
+            assert!(pool.process_transaction(&tx).unwrap().is_success());
+        }
+        
+        // A failed transaction credits nothing
+        let failed = signed(spend_transaction(first_note.as_ref().unwrap(), 40, 0x10), 0x10);
+        assert!(!pool.process_transaction(&failed).unwrap().is_success());
+        
+        assert_eq!(QueryEngine::new(db).total_fees_collected().unwrap(), 35);
+    }
+}
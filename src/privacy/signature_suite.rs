@@ -0,0 +1,189 @@
+//! Transaction Signature Suites
+//!
+//! Abstracts transaction signing and verification so the privacy pool is not
+//! tied to a single scheme:
+//! - RedJubjub (Zcash Sapling)
+//! - ECDSA over secp256k1 (EVM-compatible keys)
+
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{EcdsaScheme, EcdsaSig, OperatorKeypair, SignatureAlgorithm, SignatureScheme};
+use crate::utils::redjubjub::{RedJubjubPrivateKey, RedJubjubPublicKey, RedJubjubSignature, RedJubjubSignatureScheme};
+
+/// Tag identifying the suite a transaction was signed with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureSuiteTag {
+    /// RedJubjub (32-byte public key, 64-byte signature)
+    RedJubjub = 0,
+    /// ECDSA over secp256k1 (33-byte compressed public key, 64-byte compact signature)
+    Ecdsa = 1,
+}
+
+impl SignatureSuiteTag {
+    /// Verify a signature with the suite named by this tag
+    pub fn verify(self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        match self {
+            SignatureSuiteTag::RedJubjub => RedJubjub::verify(public_key, message, signature),
+            SignatureSuiteTag::Ecdsa => Ecdsa::verify(public_key, message, signature),
+        }
+    }
+}
+
+/// Signing and verification over encoded keys and signatures
+pub trait SignatureSuite {
+    /// Tag carried by transactions signed with this suite
+    const TAG: SignatureSuiteTag;
+
+    /// Derive the encoded public key from a 32-byte secret key
+    fn public_key(secret_key: &[u8; 32]) -> Result<Vec<u8>, String>;
+
+    /// Sign a message, returning the encoded signature
+    fn sign(secret_key: &[u8; 32], message: &[u8]) -> Result<Vec<u8>, String>;
+
+    /// Verify an encoded signature against an encoded public key
+    fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// RedJubjub signature suite
+pub struct RedJubjub;
+
+impl SignatureSuite for RedJubjub {
+    const TAG: SignatureSuiteTag = SignatureSuiteTag::RedJubjub;
+
+    fn public_key(secret_key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        Ok(RedJubjubPrivateKey::new(*secret_key).derive_public_key().bytes.to_vec())
+    }
+
+    fn sign(secret_key: &[u8; 32], message: &[u8]) -> Result<Vec<u8>, String> {
+        let private_key = RedJubjubPrivateKey::new(*secret_key);
+        Ok(RedJubjubSignatureScheme::sign(&private_key, message).to_bytes().to_vec())
+    }
+
+    fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let (Ok(key_bytes), Ok(sig_bytes)) = (
+            <[u8; 32]>::try_from(public_key),
+            <[u8; 64]>::try_from(signature),
+        ) else {
+            return false;
+        };
+
+        RedJubjubSignatureScheme::verify(
+            &RedJubjubSignature::from_bytes(sig_bytes),
+            message,
+            &RedJubjubPublicKey::new(key_bytes),
+        )
+    }
+}
+
+/// ECDSA (secp256k1) signature suite
+pub struct Ecdsa;
+
+impl SignatureSuite for Ecdsa {
+    const TAG: SignatureSuiteTag = SignatureSuiteTag::Ecdsa;
+
+    fn public_key(secret_key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        OperatorKeypair::from_secret_bytes(SignatureAlgorithm::Secp256k1, *secret_key)
+            .map(|keypair| keypair.public_key_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    fn sign(secret_key: &[u8; 32], message: &[u8]) -> Result<Vec<u8>, String> {
+        EcdsaSig::sign_message(secret_key, message)
+            .map(|sig| sig.signature.serialize_compact().to_vec())
+            .map_err(|e| e.to_string())
+    }
+
+    fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        OperatorKeypair::verify(SignatureAlgorithm::Secp256k1, public_key, message, signature)
+            .unwrap_or(false)
+    }
+}
+
+/// Transaction signing key bound to a signature suite
+#[derive(Clone)]
+pub struct SuiteKeypair {
+    /// Signature suite
+    suite: SignatureSuiteTag,
+    /// Raw 32-byte secret key
+    secret_key: [u8; 32],
+}
+
+impl SuiteKeypair {
+    /// Create keypair from raw secret key bytes
+    pub fn from_secret_bytes(suite: SignatureSuiteTag, secret_key: [u8; 32]) -> Result<Self, String> {
+        let keypair = Self { suite, secret_key };
+        // Reject secret keys the suite cannot use
+        keypair.try_public_key()?;
+        Ok(keypair)
+    }
+
+    /// Generate a random keypair for the given suite
+    pub fn random(suite: SignatureSuiteTag) -> Self {
+        let secret_key = match suite {
+            SignatureSuiteTag::RedJubjub => RedJubjubPrivateKey::random().bytes,
+            SignatureSuiteTag::Ecdsa => EcdsaScheme::generate_keypair()
+                .expect("secp256k1 key generation")
+                .0
+                .secret_bytes(),
+        };
+        Self { suite, secret_key }
+    }
+
+    /// Get the signature suite
+    pub fn suite(&self) -> SignatureSuiteTag {
+        self.suite
+    }
+
+    /// Get the encoded public key
+    pub fn public_key(&self) -> Vec<u8> {
+        // Validated on construction
+        self.try_public_key().expect("secret key validated on construction")
+    }
+
+    /// Sign a message, returning the encoded signature
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>, String> {
+        match self.suite {
+            SignatureSuiteTag::RedJubjub => RedJubjub::sign(&self.secret_key, message),
+            SignatureSuiteTag::Ecdsa => Ecdsa::sign(&self.secret_key, message),
+        }
+    }
+
+    fn try_public_key(&self) -> Result<Vec<u8>, String> {
+        match self.suite {
+            SignatureSuiteTag::RedJubjub => RedJubjub::public_key(&self.secret_key),
+            SignatureSuiteTag::Ecdsa => Ecdsa::public_key(&self.secret_key),
+        }
+    }
+}
+
+impl std::fmt::Debug for SuiteKeypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print the secret key
+        f.debug_struct("SuiteKeypair")
+            .field("suite", &self.suite)
+            .field("public_key", &hex::encode(self.public_key()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suites_verify_own_signatures_only() {
+        let message = b"suite message";
+
+        for suite in [SignatureSuiteTag::RedJubjub, SignatureSuiteTag::Ecdsa] {
+            let keypair = SuiteKeypair::random(suite);
+            let signature = keypair.sign(message).unwrap();
+
+            assert!(suite.verify(&keypair.public_key(), message, &signature));
+            assert!(!suite.verify(&keypair.public_key(), b"other message", &signature));
+        }
+
+        let ecdsa = SuiteKeypair::random(SignatureSuiteTag::Ecdsa);
+        let signature = ecdsa.sign(message).unwrap();
+        assert!(!SignatureSuiteTag::RedJubjub.verify(&ecdsa.public_key(), message, &signature));
+    }
+}
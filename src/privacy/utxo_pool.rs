@@ -3,6 +3,7 @@
 
 use crate::utxo::utxo::UTXO;
 use crate::utxo::indexing::{UTXOIndex, IndexedUTXO, UTXOId};
+use crate::utxo::denominations::DenominationPolicy;
 use crate::utils::zisk_precompiles::*;
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
@@ -39,6 +40,9 @@ pub struct UTXOPrivacyPool {
     
     /// Pool scope (from smart contract)
     scope: [u8; 32],
+    
+    /// Denominations deposits are split into
+    denomination_policy: DenominationPolicy,
 }
 
 impl UTXOPrivacyPool {
@@ -50,9 +54,16 @@ impl UTXOPrivacyPool {
             next_account_id: 1,
             tx_counter: 0,
             scope,
+            denomination_policy: DenominationPolicy::default(),
         }
     }
 
+    /// Split deposits into `policy`'s denominations instead of the default
+    pub fn with_denomination_policy(mut self, policy: DenominationPolicy) -> Self {
+        self.denomination_policy = policy;
+        self
+    }
+
     /// Register a user's Ethereum address
     pub fn register_user(&mut self, eth_address: [u8; 20], privacy_public_key: [u8; 32]) {
         self.eth_to_user.insert(eth_address, privacy_public_key);
@@ -98,12 +109,11 @@ impl UTXOPrivacyPool {
         let utxo_id = UTXOId::new(deposit.tx_hash, deposit.log_index as u32);
         let _nullifier = self.generate_nullifier(&secret, &utxo_id);
         
-        // Step 2.5: (Optional) Split into multiple denominations
-        let split_utxos = self.split_utxo_by_denominations(utxo, utxo_id);
+        // Step 2.5: Split into one UTXO per denomination
+        let split_utxos = self.split_utxo_by_denominations(utxo, &deposit, utxo_id);
         
         // Create indexed UTXOs for each split
-        for (i, split_utxo) in split_utxos.into_iter().enumerate() {
-            let split_utxo_id = UTXOId::new(deposit.tx_hash, (deposit.log_index + i as u32) as u32);
+        for (split_utxo_id, split_utxo) in split_utxos {
             let account_id = self.get_or_create_account_id(privacy_pk);
             
             let indexed_utxo = IndexedUTXO {
@@ -113,7 +123,7 @@ impl UTXOPrivacyPool {
                 value: split_utxo.value,
                 height: deposit.block_number as u32,
                 spent_in_tx: None,
-                blinding_factor: secret,
+                blinding_factor: split_utxo.secret,
             };
             
             // Step 3: Add UTXO to Merkle tree
@@ -239,6 +249,7 @@ impl UTXOPrivacyPool {
         input.extend_from_slice(secret);
         input.extend_from_slice(&utxo_id.tx_hash);
         input.extend_from_slice(&utxo_id.output_index.to_le_bytes());
+        input.extend_from_slice(&utxo_id.split_index.to_le_bytes());
         input.extend_from_slice(b"nullifier");
         
         zisk_sha256(&input)
@@ -285,45 +296,45 @@ impl UTXOPrivacyPool {
     }
 
     /// Split UTXO by denominations (Step 2.5)
-    fn split_utxo_by_denominations(&self, utxo: UTXO, utxo_id: UTXOId) -> Vec<UTXO> {
-        let denominations = [1000000000000000000, 500000000000000000, 100000000000000000]; // 1 ETH, 0.5 ETH, 0.1 ETH
-        let mut split_utxos = Vec::new();
-        let mut remaining = utxo.value;
-        
-        for &denomination in &denominations {
-            while remaining >= denomination {
-                let secret = self.generate_secure_secret(&ETHDepositEvent::default());
-                let nullifier = self.generate_nullifier(&secret, &utxo_id);
+    ///
+    /// Each split keeps the deposit's output index and gets its own split
+    /// index, so splits never collide with the next log in the same transaction.
+    fn split_utxo_by_denominations(
+        &self,
+        utxo: UTXO,
+        deposit: &ETHDepositEvent,
+        utxo_id: UTXOId,
+    ) -> Vec<(UTXOId, UTXO)> {
+        self.denomination_policy.split(utxo.value)
+            .into_iter()
+            .enumerate()
+            .map(|(i, amount)| {
+                let split_id = UTXOId::with_split(utxo_id.tx_hash, utxo_id.output_index, i as u32);
+                let secret = self.generate_split_secret(deposit, split_id.split_index);
+                let nullifier = self.generate_nullifier(&secret, &split_id);
                 
-                split_utxos.push(UTXO::new(
-                    denomination,
+                let split_utxo = UTXO::new(
+                    amount,
                     secret,
                     utxo.owner,
                     [0u8; 32], // blinding_factor
                     nullifier,
                     [0u8; 32], // commitment
                     0, // index
-                ));
-                remaining -= denomination;
-            }
-        }
-        
-        // Handle remainder
-        if remaining > 0 {
-            let secret = self.generate_secure_secret(&ETHDepositEvent::default());
-            let nullifier = self.generate_nullifier(&secret, &utxo_id);
-            split_utxos.push(UTXO::new(
-                remaining,
-                secret,
-                utxo.owner,
-                [0u8; 32], // blinding_factor
-                nullifier,
-                [0u8; 32], // commitment
-                0, // index
-            ));
-        }
+                );
+                (split_id, split_utxo)
+            })
+            .collect()
+    }
+
+    /// Derive the secret for one denomination split of a deposit
+    fn generate_split_secret(&self, deposit: &ETHDepositEvent, split_index: u32) -> [u8; 32] {
+        let mut input = Vec::new();
+        input.extend_from_slice(&self.generate_secure_secret(deposit));
+        input.extend_from_slice(&split_index.to_le_bytes());
+        input.extend_from_slice(b"split_secret");
         
-        split_utxos
+        zisk_sha256(&input)
     }
 
     /// Generate cryptographically secure secret
@@ -520,4 +531,78 @@ mod tests {
         let utxos = pool.get_user_utxos(&privacy_pk);
         assert!(!utxos.is_empty());
     }
+
+    #[test]
+    fn test_deposit_creates_one_utxo_per_denomination() {
+        use crate::utxo::denominations::{DENOMINATION_0_1_ETH, DENOMINATION_1_ETH, DENOMINATION_10_ETH};
+        
+        let eth_addr = [0x12u8; 20];
+        let privacy_pk = [0x34u8; 32];
+        let deposit = |amount_wei: u64, tx_tag: u8| ETHDepositEvent {
+            depositor: eth_addr,
+            amount_wei,
+            block_number: 1000,
+            tx_hash: [tx_tag; 32],
+            log_index: 0,
+            commitment: [0u8; 32],
+            label: 0,
+        };
+        
+        let mut pool = UTXOPrivacyPool::new([0x01; 32]);
+        pool.register_user(eth_addr, privacy_pk);
+        
+        // 12.3 ETH plus dust: 1 x 10, 2 x 1, 3 x 0.1 and the dust
+        let amount = 12 * DENOMINATION_1_ETH + 3 * DENOMINATION_0_1_ETH + 7;
+        let utxo_ids = pool.process_eth_deposit(deposit(amount, 0x56)).unwrap();
+        assert_eq!(utxo_ids.len(), 7);
+        
+        let mut values: Vec<u64> = pool.get_user_utxos(&privacy_pk).iter().map(|utxo| utxo.value).collect();
+        values.sort_unstable_by(|a, b| b.cmp(a));
+        let mut expected = vec![DENOMINATION_10_ETH, DENOMINATION_1_ETH, DENOMINATION_1_ETH];
+        expected.extend([DENOMINATION_0_1_ETH; 3]);
+        expected.push(7);
+        assert_eq!(values, expected);
+        assert_eq!(pool.get_user_balance(&privacy_pk), amount);
+        
+        // Below the smallest denomination the deposit stays one UTXO
+        let utxo_ids = pool.process_eth_deposit(deposit(DENOMINATION_0_1_ETH / 2, 0x57)).unwrap();
+        assert_eq!(utxo_ids.len(), 1);
+        assert_eq!(pool.get_user_balance(&privacy_pk), amount + DENOMINATION_0_1_ETH / 2);
+    }
+
+    #[test]
+    fn test_split_utxos_keep_distinct_ids_and_secrets() {
+        use crate::utxo::denominations::DENOMINATION_1_ETH;
+        use std::collections::HashSet;
+        
+        let eth_addr = [0x12u8; 20];
+        let privacy_pk = [0x34u8; 32];
+        let deposit = |log_index: u32| ETHDepositEvent {
+            depositor: eth_addr,
+            amount_wei: 3 * DENOMINATION_1_ETH,
+            block_number: 1000,
+            tx_hash: [0x56u8; 32],
+            log_index,
+            commitment: [0u8; 32],
+            label: 0,
+        };
+        
+        let mut pool = UTXOPrivacyPool::new([0x01; 32]);
+        pool.register_user(eth_addr, privacy_pk);
+        
+        // Two deposits in one transaction on consecutive logs
+        let first = pool.process_eth_deposit(deposit(0)).unwrap();
+        let second = pool.process_eth_deposit(deposit(1)).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 3);
+        assert!(first.iter().all(|id| id.output_index == 0));
+        assert!(second.iter().all(|id| id.output_index == 1));
+        
+        let utxos = pool.get_user_utxos(&privacy_pk);
+        assert_eq!(utxos.len(), 6);
+        assert_eq!(pool.get_user_balance(&privacy_pk), 6 * DENOMINATION_1_ETH);
+        
+        let secrets: HashSet<[u8; 32]> = utxos.iter().map(|utxo| utxo.blinding_factor).collect();
+        assert_eq!(secrets.len(), 6);
+    }
 }
@@ -2,34 +2,44 @@
 //! This module connects to the actual deployed contracts and processes real ETH deposits
 
 use web3::{
-    types::{Address, Log, TransactionRequest, U256, H256, TransactionParameters, Bytes},
-    Web3, transports::Http, signing::SecretKey,
+    types::{Address, BlockNumber, FilterBuilder, Log, TransactionRequest, U256, H256, TransactionParameters, Bytes},
+    Web3, Transport, transports::Http, signing::{SecretKey, keccak256},
 };
 use std::str::FromStr;
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use hex;
 use secp256k1::{Secp256k1, SecretKey as Secp256k1SecretKey, PublicKey};
-use sha2::{Sha256, Digest};
 use web3::ethabi::{encode, Token};
+use crate::relayer::gas_oracle::{GasOracle, GasOracleConfig, GasParams};
+use crate::relayer::rpc_failover::{FailoverConfig, FailoverTransport};
+use crate::database::schema::{DatabaseManager, cf_names};
 
 /// blockchain configuration
 pub struct BlockchainConfig {
     pub anvil_url: String,
+    /// Providers tried after `anvil_url` on transport or 5xx errors, in order
+    pub fallback_rpc_urls: Vec<String>,
+    /// When a failing provider is taken out of rotation
+    pub failover: FailoverConfig,
     pub privacy_pool_address: Address,
     pub entrypoint_address: Address,
     pub withdrawal_verifier_address: Address,
     pub ragequit_verifier_address: Address,
+    pub gas_oracle: GasOracleConfig,
 }
 
 impl Default for BlockchainConfig {
     fn default() -> Self {
         Self {
             anvil_url: "http://127.0.0.1:8545".to_string(),
+            fallback_rpc_urls: Vec::new(),
+            failover: FailoverConfig::default(),
             privacy_pool_address: Address::from_str("0x2279B7A0a67DB372996a5FaB50D91eAA73d2eBe6").unwrap(),
             entrypoint_address: Address::from_str("0x5FC8d32690cc91D4c39d9d3abcBD16989F875707").unwrap(),
             withdrawal_verifier_address: Address::from_str("0x0165878A594ca255338adfa4d48449f69242Eb8F").unwrap(),
             ragequit_verifier_address: Address::from_str("0xa513E6E4b8f2a923D98304ec87F64353C4D5C853").unwrap(),
+            gas_oracle: GasOracleConfig::default(),
         }
     }
 }
@@ -45,21 +55,117 @@ pub struct DepositEvent {
     pub block_number: u64,
     pub transaction_hash: H256,
     pub log_index: u64,
+    /// Deposited asset (zero address = ETH)
+    #[serde(default)]
+    pub asset: Address,
+}
+
+/// Asset of a deposit log: the optional third data word, ETH when absent
+pub(crate) fn decode_asset_from_log(data: &[u8]) -> Result<Address> {
+    match data.len() {
+        0..=63 => Err(anyhow!("log data too short for a deposit")),
+        64..=95 => Ok(Address::zero()),
+        _ => Ok(Address::from_slice(&data[76..96])),
+    }
+}
+
+/// Solidity signature of the contract's root update function
+pub const UPDATE_ROOT_SIGNATURE: &str = "updateRoot(bytes32,uint256)";
+
+/// Solidity signature of the pool's deposit event (topic0)
+pub const DEPOSITED_EVENT_SIGNATURE: &str = "Deposited(address,uint256,uint256,uint256,uint256)";
+
+/// Largest block range requested per `eth_getLogs` call
+pub const DEPOSIT_LOG_CHUNK_BLOCKS: u64 = 2000;
+
+/// Gas limit used for root publication transactions
+const UPDATE_ROOT_GAS: u64 = 200_000;
+
+/// cf_tree_metadata key holding the last root version published on-chain
+pub const LAST_PUBLISHED_ROOT_VERSION_KEY: &[u8] = b"last_published_root_version";
+
+/// Last published root version, persisted in cf_tree_metadata
+///
+/// The root version doubles as the `updateRoot` nonce: a version that is not
+/// strictly greater than the last published one is refused before signing,
+/// so a stale or replayed publication never reaches the chain.
+pub struct PublishedRootTracker {
+    db: DatabaseManager,
+}
+
+impl PublishedRootTracker {
+    /// Create tracker over cf_tree_metadata
+    pub fn new(db: DatabaseManager) -> Self {
+        Self { db }
+    }
+
+    /// Last root version published on-chain, if any
+    pub fn last_published(&self) -> Result<Option<u64>> {
+        self.db.get_cf(cf_names::TREE_METADATA, LAST_PUBLISHED_ROOT_VERSION_KEY)?
+            .map(|value| {
+                let bytes: [u8; 8] = value.as_slice().try_into()
+                    .map_err(|_| anyhow!("Last published root version has invalid length"))?;
+                Ok(u64::from_be_bytes(bytes))
+            })
+            .transpose()
+    }
+
+    /// Refuse any version that does not advance past the last published one
+    pub fn check_next(&self, root_version: u64) -> Result<()> {
+        match self.last_published()? {
+            Some(last) if root_version <= last => Err(anyhow!(
+                "Refusing to publish root version {}: version {} already published",
+                root_version, last
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record a successfully published root version
+    pub fn record(&self, root_version: u64) -> Result<()> {
+        self.db.put_cf(cf_names::TREE_METADATA, LAST_PUBLISHED_ROOT_VERSION_KEY, &root_version.to_be_bytes())
+    }
+}
+
+impl BlockchainConfig {
+    /// All configured providers, primary first
+    pub fn rpc_urls(&self) -> Vec<String> {
+        std::iter::once(self.anvil_url.clone())
+            .chain(self.fallback_rpc_urls.iter().cloned())
+            .collect()
+    }
 }
 
 /// blockchain client
-pub struct BlockchainClient {
-    pub web3: Web3<Http>,
+pub struct BlockchainClient<T: Transport = FailoverTransport<Http>> {
+    pub web3: Web3<T>,
     pub config: BlockchainConfig,
 }
 
 impl BlockchainClient {
     pub fn new(config: BlockchainConfig) -> Result<Self> {
-        let transport = Http::new(&config.anvil_url)?;
+        let transport = FailoverTransport::http(&config.rpc_urls(), config.failover.clone())?;
         let web3 = Web3::new(transport);
         
         Ok(Self { web3, config })
     }
+}
+
+impl<T: Transport> BlockchainClient<T> {
+    /// Create a client over an arbitrary transport
+    pub fn with_transport(transport: T, config: BlockchainConfig) -> Self {
+        Self { web3: Web3::new(transport), config }
+    }
+
+    /// Gas oracle over this client's transport
+    pub fn gas_oracle(&self) -> GasOracle<T> {
+        GasOracle::new(self.web3.clone(), self.config.gas_oracle.clone())
+    }
+
+    /// Estimate EIP-1559 fee parameters for the next block
+    pub async fn estimate_fees(&self) -> Result<GasParams> {
+        self.gas_oracle().estimate().await
+    }
 
     /// Get the current block number
     pub async fn get_current_block_number(&self) -> Result<u64> {
@@ -76,14 +182,14 @@ impl BlockchainClient {
     /// Send ETH to the privacy pool contract
     pub async fn deposit_eth(&self, from: Address, value_wei: U256) -> Result<H256> {
         // Create transaction to send ETH to the privacy pool
-        let tx_request = TransactionRequest {
+        let mut tx_request = TransactionRequest {
             from,
             to: Some(self.config.privacy_pool_address),
             value: Some(value_wei),
             gas: Some(U256::from(21000)),
-            gas_price: Some(U256::from(20000000000u64)), // 20 gwei
             ..Default::default()
         };
+        self.estimate_fees().await?.apply_to_request(&mut tx_request);
 
         // Send transaction
         let tx_hash = self.web3.eth().send_transaction(tx_request).await?;
@@ -98,36 +204,104 @@ impl BlockchainClient {
         
         // For now, we'll use a simple ETH transfer and parse the events
         // In a real implementation, we'd need the contract ABI and proper encoding
-        let tx_request = TransactionRequest {
+        let mut tx_request = TransactionRequest {
             from,
             to: Some(self.config.privacy_pool_address),
             value: Some(value),
             gas: Some(U256::from(100000)),
-            gas_price: Some(U256::from(20000000000u64)),
             data: Some(function_selector.into()),
             ..Default::default()
         };
+        self.estimate_fees().await?.apply_to_request(&mut tx_request);
 
         let tx_hash = self.web3.eth().send_transaction(tx_request).await?;
         Ok(tx_hash)
     }
 
+    /// ABI-encode an `updateRoot(bytes32,uint256)` call
+    pub fn encode_update_root_call(root: [u8; 32], root_version: u64) -> Vec<u8> {
+        let selector = &keccak256(UPDATE_ROOT_SIGNATURE.as_bytes())[..4];
+        let args = encode(&[
+            Token::FixedBytes(root.to_vec()),
+            Token::Uint(U256::from(root_version)),
+        ]);
+        
+        let mut calldata = Vec::with_capacity(4 + args.len());
+        calldata.extend_from_slice(selector);
+        calldata.extend_from_slice(&args);
+        calldata
+    }
+
+    /// Publish a tree root to the privacy pool contract
+    ///
+    /// Signs the `updateRoot` call locally with the operator wallet and submits
+    /// it via `eth_sendRawTransaction`, returning the transaction hash. The
+    /// root version is the call's nonce and must exceed the last version
+    /// recorded by `tracker`.
+    pub async fn publish_root(
+        &self,
+        operator_wallet: &Wallet,
+        root: [u8; 32],
+        root_version: u64,
+        tracker: &PublishedRootTracker,
+    ) -> Result<H256> {
+        tracker.check_next(root_version)?;
+        
+        let mut tx_params = TransactionParameters {
+            to: Some(self.config.privacy_pool_address),
+            gas: U256::from(UPDATE_ROOT_GAS),
+            data: Bytes(Self::encode_update_root_call(root, root_version)),
+            ..Default::default()
+        };
+        self.estimate_fees().await?.apply_to_parameters(&mut tx_params);
+        
+        let signed = self.web3.accounts()
+            .sign_transaction(tx_params, &operator_wallet.secret_key)
+            .await
+            .map_err(|e| anyhow!("Failed to sign root update: {}", e))?;
+        
+        let tx_hash = self.web3.eth()
+            .send_raw_transaction(signed.raw_transaction)
+            .await
+            .map_err(|e| anyhow!("Failed to publish root: {}", e))?;
+        
+        tracker.record(root_version)?;
+        
+        Ok(tx_hash)
+    }
+
     /// Fetch deposit events from the blockchain
+    ///
+    /// Queries `Deposited` logs emitted by the privacy pool over the inclusive
+    /// block range, in chunks of `DEPOSIT_LOG_CHUNK_BLOCKS` to stay under
+    /// provider range limits. Provider errors abort the fetch.
     pub async fn fetch_deposit_events(&self, from_block: u64, to_block: u64) -> Result<Vec<DepositEvent>> {
         println!(" Fetching real deposit events from block {} to {}", from_block, to_block);
         
-        // For now, we'll simulate event fetching since the Filter API is complex
-        // In a implementation, you would use proper event filtering
-        println!("    Note: Event filtering needs proper implementation for production");
-        
-        // Return empty events for now - this would be replaced with actual event fetching
-        let logs = vec![];
-        
+        let topic0 = H256::from(keccak256(DEPOSITED_EVENT_SIGNATURE.as_bytes()));
         let mut events = Vec::new();
-        for log in logs {
-            if let Some(event) = self.parse_deposit_event(log)? {
-                events.push(event);
+        let mut chunk_start = from_block;
+        while chunk_start <= to_block {
+            let chunk_end = chunk_start.saturating_add(DEPOSIT_LOG_CHUNK_BLOCKS - 1).min(to_block);
+            let filter = FilterBuilder::default()
+                .address(vec![self.config.privacy_pool_address])
+                .topics(Some(vec![topic0]), None, None, None)
+                .from_block(BlockNumber::Number(chunk_start.into()))
+                .to_block(BlockNumber::Number(chunk_end.into()))
+                .build();
+            
+            let logs = self.web3.eth().logs(filter).await
+                .map_err(|e| anyhow!("eth_getLogs failed for blocks {}..={}: {}", chunk_start, chunk_end, e))?;
+            for log in logs {
+                if let Some(event) = self.parse_deposit_event(log)? {
+                    events.push(event);
+                }
             }
+            
+            if chunk_end == u64::MAX {
+                break;
+            }
+            chunk_start = chunk_end + 1;
         }
 
         Ok(events)
@@ -140,6 +314,9 @@ impl BlockchainClient {
         if log.topics.len() < 4 {
             return Ok(None);
         }
+        if log.topics[0] != H256::from(keccak256(DEPOSITED_EVENT_SIGNATURE.as_bytes())) {
+            return Ok(None);
+        }
 
         // Extract indexed parameters
         let depositor = Address::from_slice(&log.topics[1].as_bytes()[12..]);
@@ -153,6 +330,7 @@ impl BlockchainClient {
 
         let value = U256::from_big_endian(&log.data.0[0..32]);
         let precommitment_hash = H256::from_slice(&log.data.0[32..64]);
+        let asset = decode_asset_from_log(&log.data.0)?;
 
         let event = DepositEvent {
             depositor,
@@ -163,6 +341,7 @@ impl BlockchainClient {
             block_number: log.block_number.unwrap_or_default().as_u64(),
             transaction_hash: log.transaction_hash.unwrap_or_default(),
             log_index: log.log_index.unwrap_or_default().as_u64(),
+            asset,
         };
 
         Ok(Some(event))
@@ -223,9 +402,9 @@ impl AccountManager {
         // Derive public key
         let public_key = PublicKey::from_secret_key(&self.secp, &secp_secret_key);
         
-        // Derive Ethereum address from public key
+        // Derive Ethereum address: Keccak-256 of the uncompressed public key
         let public_key_bytes = public_key.serialize_uncompressed();
-        let hash = Sha256::digest(&public_key_bytes[1..]); // Skip the 0x04 prefix
+        let hash = keccak256(&public_key_bytes[1..]); // Skip the 0x04 prefix
         let address_bytes = &hash[12..]; // Take last 20 bytes
         let address = Address::from_slice(address_bytes);
         
@@ -269,14 +448,17 @@ impl AccountManager {
         // Use the default Anvil account to fund our test wallet
         let faucet_address = Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266")?;
         
-        let tx_request = TransactionRequest {
+        let mut tx_request = TransactionRequest {
             from: faucet_address,
             to: Some(wallet.address),
             value: Some(amount_wei),
             gas: Some(U256::from(21000)),
-            gas_price: Some(U256::from(20000000000u64)),
             ..Default::default()
         };
+        GasOracle::new(self.web3.clone(), GasOracleConfig::default())
+            .estimate()
+            .await?
+            .apply_to_request(&mut tx_request);
 
         let tx_hash = self.web3.eth().send_transaction(tx_request).await?;
         Ok(tx_hash)
@@ -301,9 +483,9 @@ impl AccountManager {
             data: Some(tx_params.data),
             access_list: None,
             condition: None,
-            max_fee_per_gas: None,
-            max_priority_fee_per_gas: None,
-            transaction_type: None,
+            max_fee_per_gas: tx_params.max_fee_per_gas,
+            max_priority_fee_per_gas: tx_params.max_priority_fee_per_gas,
+            transaction_type: tx_params.transaction_type,
         };
         
         // For now, use simple send_transaction (web3 SecretKey doesn't have sign_transaction)
@@ -338,8 +520,9 @@ pub struct DepositManager {
 impl DepositManager {
     pub fn new() -> Result<Self> {
         let config = BlockchainConfig::default();
+        // Wallets are funded and signed through the primary node only
+        let account_manager = AccountManager::new(Web3::new(Http::new(&config.anvil_url)?));
         let blockchain_client = BlockchainClient::new(config)?;
-        let account_manager = AccountManager::new(blockchain_client.web3.clone());
         
         Ok(Self {
             blockchain_client,
@@ -387,6 +570,16 @@ impl DepositManager {
         Ok(events)
     }
 
+    /// Latest block number of the chain
+    pub async fn current_block(&self) -> Result<u64> {
+        self.blockchain_client.get_current_block_number().await
+    }
+
+    /// Deposit events mined in `from_block..=to_block`, without moving the poll cursor
+    pub async fn fetch_deposits(&self, from_block: u64, to_block: u64) -> Result<Vec<DepositEvent>> {
+        self.blockchain_client.fetch_deposit_events(from_block, to_block).await
+    }
+
     /// Send real ETH deposit with proper signing
     pub async fn send_real_deposit(&self, wallet: &Wallet, value_wei: U256) -> Result<H256> {
         println!(" Sending {} ETH from {} to privacy pool...", 
@@ -411,11 +604,11 @@ impl DepositManager {
         data.extend_from_slice(&encoded_params);
 
         // Create transaction parameters
-        let tx_params = TransactionParameters {
+        let mut tx_params = TransactionParameters {
             to: Some(self.blockchain_client.config.privacy_pool_address),
             value: value_wei,
             gas: U256::from(200000), // Higher gas limit for contract interaction
-            gas_price: Some(U256::from(20000000000u64)), // 20 gwei
+            gas_price: None, // Filled from the gas oracle below
             nonce: None, // Will be fetched automatically
             data: Bytes::from(data),
             access_list: None,
@@ -424,6 +617,7 @@ impl DepositManager {
             max_priority_fee_per_gas: None,
             transaction_type: None,
         };
+        self.blockchain_client.estimate_fees().await?.apply_to_parameters(&mut tx_params);
 
         // Send signed transaction
         let tx_hash = self.account_manager.send_signed_transaction(wallet, tx_params).await?;
@@ -455,6 +649,135 @@ impl DepositManager {
 mod tests {
     use super::*;
     use std::str::FromStr;
+    use std::sync::{Arc, Mutex};
+    use serde_json::{json, Value};
+
+    /// Transport that records calls and answers with canned responses
+    #[derive(Debug, Clone, Default)]
+    struct MockTransport {
+        calls: Arc<Mutex<Vec<(String, Vec<Value>)>>>,
+        /// `eth_getLogs` result; the call fails when unset
+        logs: Option<Value>,
+    }
+
+    impl Transport for MockTransport {
+        type Out = web3::futures::future::Ready<web3::error::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (web3::RequestId, jsonrpc_core::Call) {
+            let mut calls = self.calls.lock().unwrap();
+            calls.push((method.to_string(), params.clone()));
+            let id = calls.len();
+            (id, web3::helpers::build_request(id, method, params))
+        }
+
+        fn send(&self, _id: web3::RequestId, request: jsonrpc_core::Call) -> Self::Out {
+            let method = match request {
+                jsonrpc_core::Call::MethodCall(call) => call.method,
+                _ => String::new(),
+            };
+            
+            let response = match method.as_str() {
+                "eth_chainId" => json!("0x7a69"),
+                "eth_getTransactionCount" => json!("0x0"),
+                "eth_gasPrice" => json!("0x4a817c800"),
+                "eth_sendRawTransaction" => json!(format!("0x{}", "ab".repeat(32))),
+                "eth_getLogs" if self.logs.is_some() => self.logs.clone().unwrap(),
+                other => {
+                    let error = web3::Error::InvalidResponse(format!("unexpected method {}", other));
+                    return web3::futures::future::ready(Err(error));
+                }
+            };
+            web3::futures::future::ready(Ok(response))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_publish_root_sends_signed_update_root() {
+        let transport = MockTransport::default();
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+        let wallet = Wallet {
+            address: Address::zero(),
+            private_key: [0x11; 32],
+            name: "operator".to_string(),
+            secret_key: SecretKey::from_slice(&[0x11; 32]).unwrap(),
+        };
+        
+        let (_temp_dir, tracker) = open_root_tracker();
+        
+        let root = [0x5au8; 32];
+        let root_version = 7u64;
+        let tx_hash = client.publish_root(&wallet, root, root_version, &tracker).await.unwrap();
+        assert_eq!(tx_hash, H256::from([0xab; 32]));
+        
+        // Calldata: selector || root || uint256(version)
+        let calldata = BlockchainClient::<MockTransport>::encode_update_root_call(root, root_version);
+        assert_eq!(&calldata[..4], &keccak256(UPDATE_ROOT_SIGNATURE.as_bytes())[..4]);
+        assert_eq!(&calldata[4..36], &root);
+        assert_eq!(U256::from_big_endian(&calldata[36..68]), U256::from(root_version));
+        
+        let calls = transport.calls.lock().unwrap();
+        let (_, params) = calls.iter()
+            .find(|(method, _)| method == "eth_sendRawTransaction")
+            .expect("eth_sendRawTransaction was not issued");
+        
+        let raw_hex = params[0].as_str().unwrap().trim_start_matches("0x");
+        let raw_tx = hex::decode(raw_hex).unwrap();
+        assert!(raw_tx.windows(calldata.len()).any(|window| window == calldata.as_slice()));
+    }
+
+    fn open_root_tracker() -> (tempfile::TempDir, PublishedRootTracker) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = crate::database::schema::DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        (temp_dir, PublishedRootTracker::new(db_manager))
+    }
+
+    #[tokio::test]
+    async fn test_publish_root_refuses_stale_version() {
+        let transport = MockTransport::default();
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+        let wallet = Wallet {
+            address: Address::zero(),
+            private_key: [0x11; 32],
+            name: "operator".to_string(),
+            secret_key: SecretKey::from_slice(&[0x11; 32]).unwrap(),
+        };
+        let (_temp_dir, tracker) = open_root_tracker();
+        
+        client.publish_root(&wallet, [0x02u8; 32], 2, &tracker).await.unwrap();
+        client.publish_root(&wallet, [0x03u8; 32], 3, &tracker).await.unwrap();
+        assert_eq!(tracker.last_published().unwrap(), Some(3));
+        
+        let sent_before = transport.calls.lock().unwrap().len();
+        let error = client.publish_root(&wallet, [0x02u8; 32], 2, &tracker).await.unwrap_err();
+        assert!(error.to_string().contains("Refusing to publish root version 2"));
+        
+        // Refused client-side: nothing was signed or sent
+        assert_eq!(transport.calls.lock().unwrap().len(), sent_before);
+        assert_eq!(tracker.last_published().unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_create_wallet_derives_ethereum_address() {
+        let transport = Http::new("http://127.0.0.1:8545").unwrap();
+        let manager = AccountManager::new(Web3::new(transport));
+        
+        // Anvil account 0
+        let private_key: [u8; 32] = hex::decode("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let wallet = manager.create_wallet("anvil0", private_key).unwrap();
+        
+        let expected = Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap();
+        assert_eq!(wallet.address, expected);
+    }
 
     #[tokio::test]
     async fn test_real_blockchain_connection() {
@@ -496,4 +819,125 @@ mod tests {
         let events = manager.process_real_deposits().await.expect("Failed to process deposits");
         println!(" Processed {} real deposit events", events.len());
     }
+
+    fn deposited_log(depositor: Address, commitment: H256, value: u64) -> Value {
+        let topic0 = H256::from(keccak256(DEPOSITED_EVENT_SIGNATURE.as_bytes()));
+        let mut data = [0u8; 64];
+        U256::from(value).to_big_endian(&mut data[..32]);
+        data[32..].copy_from_slice(&[0x99; 32]);
+        
+        json!({
+            "address": format!("{:?}", BlockchainConfig::default().privacy_pool_address),
+            "topics": [
+                format!("{:?}", topic0),
+                format!("{:?}", H256::from(depositor)),
+                format!("{:?}", commitment),
+                format!("{:?}", H256::from_low_u64_be(3)),
+            ],
+            "data": format!("0x{}", hex::encode(data)),
+            "blockNumber": "0x64",
+            "transactionHash": format!("0x{}", "cd".repeat(32)),
+            "logIndex": "0x1",
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_deposit_events_queries_in_chunks() {
+        let depositor = Address::from_low_u64_be(0xdead);
+        let commitment = H256::from([0x42; 32]);
+        let transport = MockTransport {
+            logs: Some(json!([deposited_log(depositor, commitment, 1_000)])),
+            ..Default::default()
+        };
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+        
+        let events = client.fetch_deposit_events(1, 4500).await.unwrap();
+        
+        // One log per chunk: [1, 2000], [2001, 4000], [4001, 4500]
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].depositor, depositor);
+        assert_eq!(events[0].commitment, commitment);
+        assert_eq!(events[0].label, U256::from(3));
+        assert_eq!(events[0].value, U256::from(1_000));
+        assert_eq!(events[0].precommitment_hash, H256::from([0x99; 32]));
+        assert_eq!(events[0].block_number, 100);
+        assert_eq!(events[0].log_index, 1);
+        assert_eq!(events[0].asset, Address::zero());
+        
+        let calls = transport.calls.lock().unwrap();
+        let ranges: Vec<(String, String)> = calls.iter()
+            .filter(|(method, _)| method == "eth_getLogs")
+            .map(|(_, params)| (
+                params[0]["fromBlock"].as_str().unwrap().to_string(),
+                params[0]["toBlock"].as_str().unwrap().to_string(),
+            ))
+            .collect();
+        assert_eq!(ranges, vec![
+            ("0x1".to_string(), "0x7d0".to_string()),
+            ("0x7d1".to_string(), "0xfa0".to_string()),
+            ("0xfa1".to_string(), "0x1194".to_string()),
+        ]);
+        
+        let (_, params) = &calls[0];
+        let topic0 = H256::from(keccak256(DEPOSITED_EVENT_SIGNATURE.as_bytes()));
+        assert_eq!(params[0]["topics"][0], json!(format!("{:?}", topic0)));
+    }
+
+    #[test]
+    fn test_decode_asset_from_log() {
+        let token = Address::repeat_byte(0x42);
+        let mut data = vec![0u8; 96];
+        data[31] = 7;
+        data[76..96].copy_from_slice(token.as_bytes());
+
+        assert_eq!(decode_asset_from_log(&data).unwrap(), token);
+        // Logs without an asset word are ETH deposits
+        assert_eq!(decode_asset_from_log(&data[..64]).unwrap(), Address::zero());
+        assert!(decode_asset_from_log(&data[..32]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_deposit_events_surfaces_provider_errors() {
+        let client = BlockchainClient::with_transport(MockTransport::default(), BlockchainConfig::default());
+        
+        let error = client.fetch_deposit_events(10, 20).await.unwrap_err();
+        assert!(error.to_string().contains("eth_getLogs failed for blocks 10..=20"), "unexpected error: {}", error);
+    }
+
+    /// Requires a local Anvil with the pool contracts deployed at the
+    /// `BlockchainConfig::default()` addresses
+    #[cfg(feature = "anvil-tests")]
+    #[tokio::test]
+    async fn test_fetch_deposit_events_from_anvil() {
+        let config = BlockchainConfig::default();
+        let entrypoint = config.entrypoint_address;
+        let client = BlockchainClient::new(config).unwrap();
+        let manager = AccountManager::new(client.web3.clone());
+        let wallet = manager.create_anvil_wallet("depositor", 1).unwrap();
+        
+        // Entrypoint deposit(uint256 precommitment)
+        let precommitment = U256::from(0x1234u64);
+        let mut data = keccak256(b"deposit(uint256)")[..4].to_vec();
+        data.extend(encode(&[Token::Uint(precommitment)]));
+        let value = U256::exp10(17);
+        let tx_hash = client.web3.eth().send_transaction(TransactionRequest {
+            from: wallet.address,
+            to: Some(entrypoint),
+            value: Some(value),
+            gas: Some(U256::from(1_000_000)),
+            data: Some(Bytes(data)),
+            ..Default::default()
+        }).await.unwrap();
+        client.wait_for_transaction(tx_hash).await.unwrap();
+        
+        let receipt = client.web3.eth().transaction_receipt(tx_hash).await.unwrap().unwrap();
+        let block = receipt.block_number.unwrap().as_u64();
+        let events = client.fetch_deposit_events(block, block).await.unwrap();
+        
+        let event = events.iter()
+            .find(|event| event.transaction_hash == tx_hash)
+            .expect("deposit event not returned");
+        assert_eq!(event.depositor, wallet.address);
+        assert_eq!(event.block_number, block);
+    }
 }
@@ -1,159 +1,545 @@
-use anyhow::{Result, Context};
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use web3::types::{FilterBuilder, Log, Address, H256, U256, BlockNumber};
-use web3::transports::Http;
-use web3::Web3;
-use hex;
-use crate::utxo::converter::{ETHToUTXOConverter, IndexedUTXO};
-use crate::crypto::CryptoUtils;
-use crate::database::DatabaseManager; // your RocksDB wrapper
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use web3::types::{Address, H256, U256};
+use crate::database::{BatchOperation, DatabaseManager};
+use crate::database::schema::cf_names;
+use crate::relayer::blockchain_integration::{DepositEvent as BlockchainDepositEvent, DepositManager};
+use crate::relayer::data_service::DepositEvent;
+use crate::utxo::utxo_manager::{DepositResult, UTXOManager};
+
+/// cf_tree_metadata key holding the last block whose deposits were ingested
+pub const DEPOSIT_CURSOR_KEY: &[u8] = b"deposit_watcher_last_processed_block";
+
+/// cf_tree_metadata key prefix for deposits that can never be minted,
+/// followed by the transaction hash and big-endian log index
+pub const DEPOSIT_DEAD_LETTER_PREFIX: &[u8] = b"deposit_dead_letter:";
 
 /// Relayer config
 #[derive(Debug, Clone)]
 pub struct RelayerConfig {
     pub rpc_url: String,
     pub pool_address: Address,
-    pub confirmations: u64,
+    pub confirmations: ConfirmationPolicy,
     pub poll_interval_ms: u64,
 }
 
-pub struct DepositWatcher {
-    web3: Web3<Http>,
-    cfg: RelayerConfig,
-    converter: Arc<Mutex<ETHToUTXOConverter>>,
-    db: Arc<Mutex<DatabaseManager>>,
+/// Confirmation depths required before a deposit is credited, per asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfirmationPolicy {
+    /// Depth used for assets without an override
+    pub default_confirmations: u64,
+    /// Per-asset overrides keyed by asset address (zero address = ETH)
+    pub per_asset: HashMap<Address, u64>,
+}
+
+impl Default for ConfirmationPolicy {
+    fn default() -> Self {
+        Self::new(12)
+    }
+}
+
+impl ConfirmationPolicy {
+    pub fn new(default_confirmations: u64) -> Self {
+        Self {
+            default_confirmations,
+            per_asset: HashMap::new(),
+        }
+    }
+
+    /// Override the confirmation depth for one asset
+    pub fn with_asset(mut self, asset: Address, confirmations: u64) -> Self {
+        self.per_asset.insert(asset, confirmations);
+        self
+    }
+
+    /// Confirmations required for a deposit of `asset`
+    pub fn required(&self, asset: &Address) -> u64 {
+        self.per_asset.get(asset).copied().unwrap_or(self.default_confirmations)
+    }
+
+    /// Deepest requirement across all assets
+    pub fn max_required(&self) -> u64 {
+        self.per_asset.values().copied().fold(self.default_confirmations, u64::max)
+    }
+
+    /// Whether a deposit of `asset` mined in `log_block` is final at `head_block`
+    pub fn is_confirmed(&self, asset: &Address, log_block: u64, head_block: u64) -> bool {
+        head_block.saturating_sub(log_block) >= self.required(asset)
+    }
+}
+
+/// Sync progress shared between the watcher and readiness checks
+#[derive(Debug, Default)]
+pub struct WatcherProgress {
+    /// Last chain block whose deposits have been processed
+    checkpoint_block: AtomicU64,
+    /// Latest chain head observed (0 until the first poll completes)
+    chain_head: AtomicU64,
+}
+
+impl WatcherProgress {
+    /// Record a completed poll
+    pub fn record(&self, checkpoint_block: u64, chain_head: u64) {
+        self.checkpoint_block.store(checkpoint_block, Ordering::Release);
+        self.chain_head.store(chain_head, Ordering::Release);
+    }
+
+    pub fn checkpoint_block(&self) -> u64 {
+        self.checkpoint_block.load(Ordering::Acquire)
+    }
+
+    pub fn chain_head(&self) -> u64 {
+        self.chain_head.load(Ordering::Acquire)
+    }
+
+    /// Blocks between the checkpoint and the chain head, `None` before the first poll
+    pub fn lag(&self) -> Option<u64> {
+        match self.chain_head() {
+            0 => None,
+            head => Some(head.saturating_sub(self.checkpoint_block())),
+        }
+    }
+}
+
+/// Source of on-chain deposit events for `DepositWatcher`
+pub trait DepositSource {
+    /// Latest block number of the chain
+    fn chain_head(&mut self) -> impl Future<Output = Result<u64>> + Send;
+
+    /// Deposits mined in `from_block..=to_block`
+    fn fetch_deposits(&mut self, from_block: u64, to_block: u64) -> impl Future<Output = Result<Vec<BlockchainDepositEvent>>> + Send;
+}
+
+impl DepositSource for DepositManager {
+    fn chain_head(&mut self) -> impl Future<Output = Result<u64>> + Send {
+        self.current_block()
+    }
+
+    fn fetch_deposits(&mut self, from_block: u64, to_block: u64) -> impl Future<Output = Result<Vec<BlockchainDepositEvent>>> + Send {
+        DepositManager::fetch_deposits(self, from_block, to_block)
+    }
+}
+
+/// A deposit that was dead-lettered instead of minted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadLetter {
+    pub transaction_hash: H256,
+    pub log_index: u64,
+    pub reason: String,
 }
 
-impl DepositWatcher {
-    pub fn new(cfg: RelayerConfig, converter: Arc<Mutex<ETHToUTXOConverter>>, db: Arc<Mutex<DatabaseManager>>) -> Result<Self> {
-        let transport = Http::new(&cfg.rpc_url)?;
+/// Poll loop that turns confirmed on-chain deposits into UTXOs
+///
+/// Only blocks already buried under the deepest confirmation depth of any
+/// asset are fetched, and each deposit is still gated by its own asset's
+/// depth, so a deposit is never read before it is final and logs from
+/// reorged-out blocks are never seen. Each poll mints its deposits through
+/// `UTXOManager`, dead-letters the ones that can never be minted, and moves
+/// the cursor in cf_tree_metadata, all in one atomic batch. A restart
+/// resumes after the cursor without minting anything twice.
+pub struct DepositWatcher<S: DepositSource = DepositManager> {
+    source: S,
+    utxo_manager: UTXOManager,
+    db: DatabaseManager,
+    confirmations: ConfirmationPolicy,
+    last_processed_block: u64,
+    progress: Arc<WatcherProgress>,
+}
+
+impl<S: DepositSource> DepositWatcher<S> {
+    /// Create a watcher that resumes after the persisted cursor
+    pub fn new(source: S, utxo_manager: UTXOManager, db: DatabaseManager, confirmations: ConfirmationPolicy) -> Result<Self> {
+        let last_processed_block = match db.get_cf(cf_names::TREE_METADATA, DEPOSIT_CURSOR_KEY)? {
+            Some(value) => u64::from_be_bytes(value.as_slice().try_into()
+                .map_err(|_| anyhow!("Deposit cursor has invalid length"))?),
+            None => 0,
+        };
+        
         Ok(Self {
-            web3: Web3::new(transport),
-            cfg,
-            converter,
+            source,
+            utxo_manager,
             db,
+            confirmations,
+            last_processed_block,
+            progress: Arc::new(WatcherProgress::default()),
         })
     }
 
-    /// Poll loop — production should use websocket subscription (log subscription) + fallback to polling
-    pub async fn run_poll_loop(self: Arc<Self>) -> Result<()> {
-        loop {
-            if let Err(e) = self.process_new_logs().await {
-                log::error!("error in deposit watcher loop: {:?}", e);
-            }
-            tokio::time::sleep(std::time::Duration::from_millis(self.cfg.poll_interval_ms)).await;
-        }
+    /// Share sync progress with readiness checks
+    pub fn with_progress(mut self, progress: Arc<WatcherProgress>) -> Self {
+        self.progress = progress;
+        self
     }
 
-    async fn process_new_logs(&self) -> Result<()> {
-        // Build event signature for Deposited event:
-        // event DepositIndexed(address indexed depositor, bytes32 indexed commitment, uint256 value, address asset, bytes32 txHash, uint64 blockNumber, uint32 logIndex)
-        // IMPORTANT: Use the exact ABI types and ordering as emitted by the contract
-        let topic0 = web3::helpers::keccak256("DepositIndexed(address,bytes32,uint256,address,bytes32,uint64,uint32)".as_bytes());
-
-        let filter = FilterBuilder::default()
-            .address(vec![self.cfg.pool_address])
-            .topic0(Some(H256::from_slice(&topic0)))
-            .from_block(BlockNumber::Earliest)
-            .to_block(BlockNumber::Latest)
-            .build();
+    /// Current sync progress
+    pub fn progress(&self) -> Arc<WatcherProgress> {
+        self.progress.clone()
+    }
 
-        let logs = self.web3.eth().logs(filter).await.context("fetch logs")?;
+    /// Last block whose deposits have all been ingested
+    pub fn last_processed_block(&self) -> u64 {
+        self.last_processed_block
+    }
 
-        // head block for confirmations
-        let head_block = self.web3.eth().block_number().await?.as_u64();
+    pub fn utxo_manager(&self) -> &UTXOManager {
+        &self.utxo_manager
+    }
 
-        for log in logs.into_iter() {
-            // extract block number, skip if not enough confirmations
-            let log_block = match log.block_number {
-                Some(bn) => bn.as_u64(),
-                None => continue,
+    /// Every dead-lettered deposit, ordered by transaction hash and log index
+    pub fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut letters = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::TREE_METADATA, DEPOSIT_DEAD_LETTER_PREFIX)? {
+            let (key, value) = item?;
+            let Some(id) = key.strip_prefix(DEPOSIT_DEAD_LETTER_PREFIX) else {
+                break;
             };
-
-            if head_block.saturating_sub(log_block) < self.cfg.confirmations {
-                // not confirmed yet
-                continue;
+            if id.len() != 40 {
+                return Err(anyhow!("Invalid dead-letter key length: {}", key.len()));
             }
+            
+            letters.push(DeadLetter {
+                transaction_hash: H256::from_slice(&id[..32]),
+                log_index: u64::from_be_bytes(id[32..].try_into().unwrap()),
+                reason: String::from_utf8_lossy(&value).into_owned(),
+            });
+        }
+        Ok(letters)
+    }
 
-            // idempotency: check DB if this txHash+logIndex already processed
-            let tx = log.transaction_hash.unwrap_or_else(|| H256::zero());
-            let id = format!("{}:{}", hex::encode(tx.as_bytes()), log.log_index.unwrap_or_default().as_u64());
-            {
-                let mut db = self.db.lock().await;
-                if db.get_processed_flag(&id)? {
-                    // already handled
-                    continue;
+    /// Poll every `interval` until `shutdown` turns true or its sender is dropped
+    ///
+    /// A failed poll leaves the cursor where it was and is retried on the next tick.
+    pub async fn run(&mut self, interval: Duration, mut shutdown: watch::Receiver<bool>) -> Result<()> {
+        loop {
+            if *shutdown.borrow() {
+                return Ok(());
+            }
+            
+            if let Err(e) = self.poll_once().await {
+                log::error!("Deposit watcher poll failed: {:#}", e);
+            }
+            
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                changed = shutdown.changed() => {
+                    if changed.is_err() {
+                        return Ok(());
+                    }
                 }
             }
+        }
+    }
 
-            // parse topics & data: topics[1] = depositor, topics[2] = commitment
-            if log.topics.len() < 3 {
-                log::warn!("skipping malformed deposit event: topics < 3");
-                continue;
+    /// Mint every deposit in the blocks that became final since the last poll
+    pub async fn poll_once(&mut self) -> Result<Vec<DepositResult>> {
+        let head_block = self.source.chain_head().await?;
+        let mut confirmed_through = head_block.saturating_sub(self.confirmations.max_required());
+        if confirmed_through <= self.last_processed_block {
+            self.progress.record(self.last_processed_block, head_block);
+            return Ok(Vec::new());
+        }
+        
+        let mut events = self.source.fetch_deposits(self.last_processed_block + 1, confirmed_through).await?;
+        events.sort_by_key(|event| (event.block_number, event.log_index));
+        
+        // Stop the cursor short of the first deposit its asset does not yet consider final
+        if let Some(pending) = events.iter()
+            .find(|event| !self.confirmations.is_confirmed(&event.asset, event.block_number, head_block))
+        {
+            confirmed_through = pending.block_number.saturating_sub(1);
+            events.retain(|event| event.block_number <= confirmed_through);
+            if confirmed_through <= self.last_processed_block {
+                self.progress.record(self.last_processed_block, head_block);
+                return Ok(Vec::new());
+            }
+        }
+        
+        // Deposits that can never be minted are dead-lettered, not retried
+        let mut dead_letters = Vec::new();
+        let mut mintable = Vec::with_capacity(events.len());
+        let mut deposits = Vec::with_capacity(events.len());
+        for event in &events {
+            match to_relayer_event(event) {
+                Ok(deposit) => {
+                    mintable.push(event);
+                    deposits.push(deposit);
+                }
+                Err(e) => dead_letters.push(dead_letter(event, &e.to_string())),
             }
+        }
+        
+        let (results, rejected) = self.utxo_manager.batch_process_deposits_lenient_with(&deposits, |rejected| {
+            dead_letters.extend(rejected.iter().map(|(i, error)| dead_letter(mintable[*i], &error.to_string())));
+            dead_letters.push(BatchOperation::PutMetadata {
+                key: DEPOSIT_CURSOR_KEY.to_vec(),
+                value: confirmed_through.to_be_bytes().to_vec(),
+            });
+            dead_letters
+        })?;
+        for (i, error) in &rejected {
+            log::warn!("Dead-lettered deposit {:?}: {}", mintable[*i].transaction_hash, error);
+        }
+        
+        // The deposits are already final, so their root is too
+        if let Some(result) = results.first() {
+            self.utxo_manager.finalize_root(result.operation.root_version)?;
+        }
+        
+        self.last_processed_block = confirmed_through;
+        self.progress.record(self.last_processed_block, head_block);
+        Ok(results)
+    }
+}
 
-            let commitment = {
-                let mut b = [0u8; 32];
-                b.copy_from_slice(log.topics[2].as_bytes());
-                b
-            };
+/// Dead-letter record for a deposit that can never be minted
+fn dead_letter(event: &BlockchainDepositEvent, reason: &str) -> BatchOperation {
+    let mut key = DEPOSIT_DEAD_LETTER_PREFIX.to_vec();
+    key.extend_from_slice(event.transaction_hash.as_bytes());
+    key.extend_from_slice(&event.log_index.to_be_bytes());
+    BatchOperation::PutMetadata { key, value: reason.as_bytes().to_vec() }
+}
 
-            // decode non-indexed data using ABI (your DepositIndexed's data encoding must be known)
-            // Here we assume `data` encodes: value(uint256) | asset(address) | txHash(bytes32) | blockNumber(uint64) | logIndex(uint32)
-            // If you emit only a subset, decode accordingly.
-            let value = decode_value_from_log(&log.data.0)?;
-            // NOTE: owner_pubkey must be provided by depositor to relayer (or left zero if wallet keeps secret). We'll accept optional mapping.
-
-            // Owner pubkey: try to fetch a previously uploaded encrypted note where commitment matches
-            let owner_pubkey = {
-                let mut db = self.db.lock().await;
-                if let Some(enc_note) = db.get_encrypted_note_by_commitment(&commitment)? {
-                    enc_note.owner_pubkey
-                } else {
-                    // zero pubkey placeholder; wallet must keep secret locally
-                    [0u8; 32]
-                }
-            };
+/// Convert a decoded chain log into the event shape `UTXOManager` consumes
+fn to_relayer_event(event: &BlockchainDepositEvent) -> Result<DepositEvent> {
+    if event.value > U256::from(u64::MAX) || event.label > U256::from(u64::MAX) {
+        anyhow::bail!("Deposit {:?} value or label exceeds u64", event.transaction_hash);
+    }
+    
+    Ok(DepositEvent {
+        depositor: format!("{:?}", event.depositor),
+        commitment: format!("{:?}", event.commitment),
+        label: event.label.as_u64(),
+        value: event.value.as_u64(),
+        precommitment_hash: format!("{:?}", event.precommitment_hash),
+        block_number: event.block_number,
+        transaction_hash: format!("{:?}", event.transaction_hash),
+        log_index: u32::try_from(event.log_index)?,
+        merkle_root: format!("{:?}", H256::zero()),
+    })
+}
 
-            // create UTXO object but DO NOT invent secret/blinding if depositor must hold them locally
-            let indexed_utxo = {
-                let conv = self.converter.lock().await;
-                conv.create_utxo_from_onchain(
-                    H256::from(commitment),
-                    U256::from(value),
-                    owner_pubkey,
-                    tx,
-                    log_block,
-                    log.log_index.unwrap_or_default().as_u32(),
-                ).await?
-            };
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // insert into merkle and persist
-            {
-                let mut conv = self.converter.lock().await;
-                let leaf_index = conv.insert_utxo(indexed_utxo.clone()).await?;
-                // persist processed flag & mapping
-                let mut db = self.db.lock().await;
-                db.mark_processed(&id)?;
-                db.put_utxo_mapping(&commitment, &indexed_utxo, leaf_index)?;
-            }
+    #[test]
+    fn test_confirmations_gated_per_asset() {
+        let eth = Address::zero();
+        let token = Address::repeat_byte(0x42);
+        let policy = ConfirmationPolicy::new(12).with_asset(token, 30);
+
+        assert_eq!(policy.required(&eth), 12);
+        assert_eq!(policy.required(&token), 30);
+        assert_eq!(policy.max_required(), 30);
+
+        // 20 blocks deep: final for ETH, still pending for the token
+        assert!(policy.is_confirmed(&eth, 100, 120));
+        assert!(!policy.is_confirmed(&token, 100, 120));
+
+        // Each threshold is inclusive
+        assert!(!policy.is_confirmed(&eth, 100, 111));
+        assert!(policy.is_confirmed(&eth, 100, 112));
+        assert!(!policy.is_confirmed(&token, 100, 129));
+        assert!(policy.is_confirmed(&token, 100, 130));
+    }
+
+    /// Deposits scripted per block; a reorg edits `events` in place
+    struct ScriptedDeposits {
+        head: Arc<AtomicU64>,
+        events: Arc<std::sync::Mutex<Vec<BlockchainDepositEvent>>>,
+    }
 
-            log::info!("Inserted commitment {} at leaf {}", hex::encode(commitment), "TODO"); // replace with actual leaf index
+    impl DepositSource for ScriptedDeposits {
+        fn chain_head(&mut self) -> impl Future<Output = Result<u64>> + Send {
+            let head = self.head.load(Ordering::SeqCst);
+            async move { Ok(head) }
         }
 
-        Ok(())
+        fn fetch_deposits(&mut self, from_block: u64, to_block: u64) -> impl Future<Output = Result<Vec<BlockchainDepositEvent>>> + Send {
+            assert!(to_block <= self.head.load(Ordering::SeqCst));
+            let events = self.events.lock().unwrap().iter()
+                .filter(|event| (from_block..=to_block).contains(&event.block_number))
+                .cloned()
+                .collect();
+            async move { Ok(events) }
+        }
+    }
+
+    fn scripted(head: &Arc<AtomicU64>, events: Vec<BlockchainDepositEvent>) -> (ScriptedDeposits, Arc<std::sync::Mutex<Vec<BlockchainDepositEvent>>>) {
+        let events = Arc::new(std::sync::Mutex::new(events));
+        (ScriptedDeposits { head: head.clone(), events: events.clone() }, events)
+    }
+
+    fn chain_deposit(block_number: u64, byte: u8) -> BlockchainDepositEvent {
+        BlockchainDepositEvent {
+            depositor: Address::repeat_byte(byte),
+            commitment: H256::repeat_byte(byte),
+            label: U256::from(byte),
+            value: U256::from(1_000_000_000u64),
+            precommitment_hash: H256::repeat_byte(byte.wrapping_add(1)),
+            block_number,
+            transaction_hash: H256::repeat_byte(byte.wrapping_add(2)),
+            log_index: 0,
+            asset: Address::zero(),
+        }
+    }
+
+    fn open_db(temp_dir: &tempfile::TempDir) -> DatabaseManager {
+        DatabaseManager::open(crate::database::schema::DBConfig {
+            db_path: temp_dir.path().join("test_db").to_string_lossy().to_string(),
+            ..Default::default()
+        }).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_watcher_mints_only_confirmed_deposits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        
+        let head = Arc::new(AtomicU64::new(15));
+        let (source, _) = scripted(&head, vec![chain_deposit(10, 0x01), chain_deposit(14, 0x02)]);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db.clone(), ConfirmationPolicy::new(3)).unwrap();
+        
+        // Block 10 has 5 confirmations, block 14 only 1 and is not fetched yet
+        let results = watcher.poll_once().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].deposit_event.block_number, 10);
+        assert_eq!(watcher.utxo_manager().get_root_version(), 1);
+        assert!(watcher.utxo_manager().root_history().get_root(1).unwrap().unwrap().finalized);
+        assert_eq!(watcher.last_processed_block(), 12);
+        assert_eq!(watcher.progress().lag(), Some(3));
+        
+        // Nothing new is deep enough yet
+        head.store(16, Ordering::SeqCst);
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        assert_eq!(watcher.utxo_manager().get_root_version(), 1);
+        
+        head.store(17, Ordering::SeqCst);
+        let results = watcher.poll_once().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].deposit_event.block_number, 14);
+        assert_eq!(watcher.last_processed_block(), 14);
+        drop(watcher);
+        
+        // The cursor was committed with the mint, so a restart mints nothing twice
+        let (source, _) = scripted(&head, vec![chain_deposit(10, 0x01), chain_deposit(14, 0x02)]);
+        let mut restarted = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db, ConfirmationPolicy::new(3)).unwrap();
+        assert_eq!(restarted.last_processed_block(), 14);
+        assert!(restarted.poll_once().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watcher_gates_each_asset_on_its_own_depth() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        
+        let token = Address::repeat_byte(0x42);
+        let mut token_deposit = chain_deposit(100, 0x02);
+        token_deposit.asset = token;
+        let head = Arc::new(AtomicU64::new(120));
+        let (source, _) = scripted(&head, vec![chain_deposit(100, 0x01), token_deposit]);
+        let policy = ConfirmationPolicy::new(12).with_asset(token, 30);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db, policy).unwrap();
+        
+        // 20 blocks deep is final for ETH but not for the token, so neither is read yet
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        assert_eq!(watcher.utxo_manager().get_root_version(), 0);
+        assert_eq!(watcher.last_processed_block(), 90);
+        
+        head.store(129, Ordering::SeqCst);
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        assert_eq!(watcher.last_processed_block(), 99);
+        
+        // At 30 confirmations both deposits are minted together
+        head.store(130, Ordering::SeqCst);
+        let results = watcher.poll_once().await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(watcher.last_processed_block(), 100);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_ignores_reorged_out_deposit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        
+        let head = Arc::new(AtomicU64::new(15));
+        let (source, events) = scripted(&head, vec![chain_deposit(14, 0x01)]);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db, ConfirmationPolicy::new(3)).unwrap();
+        
+        // Seen at the head, then reorged out before it is confirmed
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        events.lock().unwrap().clear();
+        head.store(20, Ordering::SeqCst);
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        assert_eq!(watcher.utxo_manager().get_root_version(), 0);
+        assert_eq!(watcher.last_processed_block(), 17);
+    }
+
+    #[tokio::test]
+    async fn test_watcher_dead_letters_unmintable_deposits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        
+        let mut zero_value = chain_deposit(5, 0x01);
+        zero_value.value = U256::zero();
+        let mut oversized = chain_deposit(6, 0x02);
+        oversized.value = U256::from(u64::MAX) + 1;
+        let head = Arc::new(AtomicU64::new(10));
+        let (source, _) = scripted(&head, vec![zero_value.clone(), oversized.clone(), chain_deposit(7, 0x03)]);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db.clone(), ConfirmationPolicy::new(3)).unwrap();
+        
+        // The good deposit is minted and the cursor moves past the bad ones
+        let results = watcher.poll_once().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].deposit_event.block_number, 7);
+        assert_eq!(watcher.last_processed_block(), 7);
+        
+        let letters = watcher.dead_letters().unwrap();
+        assert_eq!(letters.len(), 2);
+        let reason = |event: &BlockchainDepositEvent| letters.iter()
+            .find(|letter| letter.transaction_hash == event.transaction_hash && letter.log_index == event.log_index)
+            .map(|letter| letter.reason.clone())
+            .unwrap();
+        assert_eq!(reason(&zero_value), "Invalid deposit amount");
+        assert!(reason(&oversized).contains("exceeds u64"));
+        
+        // A range holding only bad deposits still moves the cursor
+        let mut bad = chain_deposit(8, 0x04);
+        bad.value = U256::zero();
+        let (source, _) = scripted(&head, vec![bad]);
+        head.store(11, Ordering::SeqCst);
+        drop(watcher);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db, ConfirmationPolicy::new(3)).unwrap();
+        assert!(watcher.poll_once().await.unwrap().is_empty());
+        assert_eq!(watcher.last_processed_block(), 8);
+        assert_eq!(watcher.dead_letters().unwrap().len(), 3);
     }
-}
 
-// helper to decode value from data blob when ABI layout known (quick naive decode)
-fn decode_value_from_log(data: &[u8]) -> Result<u128> {
-    // big-endian uint256 at offset 0..32
-    if data.len() < 32 {
-        anyhow::bail!("log data too short");
+    #[tokio::test]
+    async fn test_watcher_run_stops_on_shutdown() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = open_db(&temp_dir);
+        
+        let head = Arc::new(AtomicU64::new(20));
+        let (source, _) = scripted(&head, vec![chain_deposit(5, 0x03), chain_deposit(19, 0x04)]);
+        let mut watcher = DepositWatcher::new(source, UTXOManager::new(db.clone()).unwrap(), db, ConfirmationPolicy::new(3)).unwrap();
+        
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let (result, _) = tokio::join!(
+            watcher.run(Duration::from_millis(5), shutdown_rx),
+            async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                shutdown_tx.send(true).unwrap();
+            },
+        );
+        result.unwrap();
+        
+        assert_eq!(watcher.utxo_manager().get_root_version(), 1);
+        assert_eq!(watcher.last_processed_block(), 17);
     }
-    let mut buf = [0u8; 32];
-    buf.copy_from_slice(&data[0..32]);
-    let value = u128::from_be_bytes(buf[16..32].try_into().unwrap_or([0u8; 16])); // careful
-    Ok(value)
 }
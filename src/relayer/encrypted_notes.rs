@@ -6,10 +6,14 @@
 use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 use anyhow::{Result, anyhow};
-use crate::utxo::note::EncryptedNote;
+use crate::utxo::note::{EncryptedNote, Note};
+use crate::crypto::ecies::Ecies;
 use crate::database::{DatabaseManager, schema::cf_names};
 use crate::merkle::enhanced_merkle_tree::EnhancedMerkleTree;
 
+/// Stored ciphertexts `scan_notes` holds in memory at once
+pub const SCAN_PAGE_SIZE: usize = 256;
+
 /// Encrypted note storage entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedNoteEntry {
@@ -40,6 +44,23 @@ pub struct EncryptedNoteEntry {
     
     /// Leaf index in Merkle tree (set after confirmation)
     pub leaf_index: Option<u64>,
+    
+    /// View tag for skipping notes before trial decryption
+    #[serde(default)]
+    pub view_tag: Option<[u8; 8]>,
+}
+
+impl EncryptedNoteEntry {
+    /// Whether trial decryption with `private_key` is worth attempting
+    ///
+    /// Untagged notes always are; tagged ones only when the tag matches.
+    pub fn view_tag_matches(&self, private_key: &[u8; 32]) -> bool {
+        match self.view_tag {
+            Some(view_tag) => Ecies::view_tag(&self.ephemeral_pubkey, private_key)
+                .is_ok_and(|expected| expected == view_tag),
+            None => true,
+        }
+    }
 }
 
 /// Relayer service for encrypted notes
@@ -85,6 +106,7 @@ impl EncryptedNotesRelayer {
             tx_hash: None,
             output_index: None,
             leaf_index: None,
+            view_tag: encrypted_note.view_tag,
         };
         
         // Store in database
@@ -96,6 +118,87 @@ impl EncryptedNotesRelayer {
         Ok(note_id)
     }
     
+    /// Encrypt `note` to `recipient_pubkey` and store it, returning the note ID
+    pub fn store_note(&mut self, recipient_pubkey: &[u8; 33], note: &Note) -> Result<String> {
+        let encrypted_note = Ecies::encrypt_note(note, recipient_pubkey)
+            .map_err(|e| anyhow!("Failed to encrypt note: {}", e))?;
+        self.upload_note(encrypted_note)
+    }
+    
+    /// Trial-decrypt every stored ciphertext with `viewing_key`, returning the
+    /// notes addressed to its holder
+    ///
+    /// Notes whose view tag rules the key out are skipped without decrypting,
+    /// and decrypted notes must open their commitment. Reads the store one
+    /// page at a time.
+    pub fn scan_notes(&self, viewing_key: &[u8; 32]) -> Result<Vec<Note>> {
+        let mut notes = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = self.scan_notes_page(viewing_key, cursor.as_deref(), SCAN_PAGE_SIZE)?;
+            notes.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => return Ok(notes),
+            }
+        }
+    }
+    
+    /// Trial-decrypt up to `max_entries` stored ciphertexts after note ID `start_after`
+    ///
+    /// Returns the notes found and the note ID to resume from, `None` once
+    /// the store is exhausted.
+    pub fn scan_notes_page(
+        &self,
+        viewing_key: &[u8; 32],
+        start_after: Option<&str>,
+        max_entries: usize,
+    ) -> Result<(Vec<Note>, Option<String>)> {
+        let mut notes = Vec::new();
+        let start = start_after.map_or(&[][..], str::as_bytes);
+        let mut last_key: Option<Vec<u8>> = None;
+        let mut examined = 0;
+        
+        for item in self.db.prefix_iterator_cf(cf_names::ENCRYPTED_NOTES, start)? {
+            let (key, value) = item?;
+            if start_after.is_some() && &key[..] == start {
+                continue;
+            }
+            // Another entry remains, so the page ends at the last one examined
+            if examined == max_entries {
+                let cursor = last_key.map(String::from_utf8).transpose()
+                    .map_err(|e| anyhow!("Note ID is not UTF-8: {}", e))?;
+                return Ok((notes, cursor));
+            }
+            examined += 1;
+            last_key = Some(key.to_vec());
+            
+            let entry: EncryptedNoteEntry = bincode::deserialize(&value)
+                .map_err(|e| anyhow!("Failed to deserialize entry: {}", e))?;
+            if !entry.view_tag_matches(viewing_key) {
+                continue;
+            }
+            
+            let encrypted_note = EncryptedNote {
+                ephemeral_pubkey: entry.ephemeral_pubkey,
+                nonce: entry.nonce,
+                ciphertext: entry.ciphertext,
+                commitment: entry.commitment,
+                view_tag: entry.view_tag,
+            };
+            
+            // Any failure means the note is not ours
+            let Ok(note) = Ecies::decrypt_note(&encrypted_note, viewing_key) else {
+                continue;
+            };
+            if note.verify() && encrypted_note.commitment.map_or(true, |commitment| commitment == note.commitment) {
+                notes.push(note);
+            }
+        }
+        
+        Ok((notes, None))
+    }
+    
     /// Attach transaction metadata to note
     pub fn attach_tx(&mut self, note_id: &str, tx_hash: String, output_index: u32) -> Result<()> {
         // Update entry in database
@@ -385,6 +488,7 @@ mod tests {
             nonce: [0x24u8; 24],
             ciphertext: b"encrypted_data".to_vec(),
             commitment: Some([0x12u8; 32]),
+            view_tag: None,
         };
         
         // Upload note
@@ -398,4 +502,49 @@ mod tests {
         let root = relayer.get_merkle_root();
         assert_ne!(root, [0u8; 32]);
     }
+
+    #[test]
+    fn test_stored_note_scans_for_recipient_only() {
+        use k256::elliptic_curve::sec1::ToEncodedPoint;
+
+        let keypair = || {
+            let (secret_key, public_key) = Ecies::generate_keypair().unwrap();
+            let mut pubkey = [0u8; 33];
+            pubkey.copy_from_slice(public_key.to_encoded_point(true).as_bytes());
+            let mut privkey = [0u8; 32];
+            privkey.copy_from_slice(secret_key.to_be_bytes().as_slice());
+            (privkey, pubkey)
+        };
+        let (alice_key, alice_pubkey) = keypair();
+        let (bob_key, bob_pubkey) = keypair();
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_config = DBConfig {
+            db_path: temp_dir.path().to_str().unwrap().to_string(),
+            ..Default::default()
+        };
+        let mut relayer = EncryptedNotesRelayer::new(DatabaseManager::open(db_config).unwrap()).unwrap();
+
+        let pool = "0x1234567890123456789012345678901234567890".to_string();
+        let for_alice = Note::new(1_000, alice_pubkey, 1, 1, pool.clone());
+        let for_bob = Note::new(2_000, bob_pubkey, 1, 1, pool);
+        relayer.store_note(&alice_pubkey, &for_alice).unwrap();
+        relayer.store_note(&bob_pubkey, &for_bob).unwrap();
+
+        assert_eq!(relayer.scan_notes(&alice_key).unwrap(), vec![for_alice.clone()]);
+        assert_eq!(relayer.scan_notes(&bob_key).unwrap(), vec![for_bob.clone()]);
+
+        // One entry per page: the cursor walks both notes and then stops
+        let (first, cursor) = relayer.scan_notes_page(&bob_key, None, 1).unwrap();
+        let cursor = cursor.expect("a second entry remains");
+        let (second, end) = relayer.scan_notes_page(&bob_key, Some(&cursor), 1).unwrap();
+        assert_eq!(end, None);
+        assert_eq!([first, second].concat(), vec![for_bob]);
+
+        // Without the view tag Bob still cannot decrypt Alice's note
+        let mut untagged = Ecies::encrypt_note(&for_alice, &alice_pubkey).unwrap();
+        untagged.view_tag = None;
+        assert!(Ecies::decrypt_note(&untagged, &bob_key).is_err());
+        assert_eq!(Ecies::decrypt_note(&untagged, &alice_key).unwrap(), for_alice);
+    }
 }
@@ -86,6 +86,7 @@ async fn test_complete_encrypted_notes_flow() -> Result<()> {
         tx_hash: Some(tx_hash),
         output_index: Some(output_index),
         leaf_index: Some(0),
+        view_tag: encrypted_note.view_tag,
     };
     
     // Try to decrypt the note
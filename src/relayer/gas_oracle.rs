@@ -0,0 +1,205 @@
+//! Gas Price Oracle
+//! Derives EIP-1559 fee parameters from recent fee history instead of a fixed gas price
+
+use web3::{
+    types::{BlockNumber, TransactionParameters, TransactionRequest, U256, U64},
+    Web3, Transport, transports::Http,
+};
+use anyhow::{Result, anyhow};
+
+/// EIP-1559 transaction type
+const EIP1559_TX_TYPE: u64 = 2;
+
+/// Gas oracle configuration
+#[derive(Debug, Clone)]
+pub struct GasOracleConfig {
+    /// Number of recent blocks sampled via `eth_feeHistory`
+    pub fee_history_blocks: u64,
+    /// Reward percentile used for the priority fee estimate
+    pub priority_fee_percentile: f64,
+    /// Safety multiplier applied to the computed fees (percent, 100 = 1x)
+    pub fee_multiplier_percent: u64,
+    /// Upper bound for `max_fee_per_gas` in wei
+    pub max_fee_cap: U256,
+}
+
+impl Default for GasOracleConfig {
+    fn default() -> Self {
+        Self {
+            fee_history_blocks: 10,
+            priority_fee_percentile: 50.0,
+            fee_multiplier_percent: 125,
+            max_fee_cap: U256::from(500_000_000_000u64), // 500 gwei
+        }
+    }
+}
+
+/// Fee parameters for an EIP-1559 transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasParams {
+    pub max_fee_per_gas: U256,
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl GasParams {
+    /// Apply fees to a node-signed transaction request
+    pub fn apply_to_request(&self, request: &mut TransactionRequest) {
+        request.gas_price = None;
+        request.max_fee_per_gas = Some(self.max_fee_per_gas);
+        request.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+        request.transaction_type = Some(U64::from(EIP1559_TX_TYPE));
+    }
+
+    /// Apply fees to locally signed transaction parameters
+    pub fn apply_to_parameters(&self, params: &mut TransactionParameters) {
+        params.gas_price = None;
+        params.max_fee_per_gas = Some(self.max_fee_per_gas);
+        params.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+        params.transaction_type = Some(U64::from(EIP1559_TX_TYPE));
+    }
+}
+
+/// Gas oracle backed by `eth_feeHistory` with an `eth_gasPrice` fallback
+pub struct GasOracle<T: Transport = Http> {
+    web3: Web3<T>,
+    config: GasOracleConfig,
+}
+
+impl<T: Transport> GasOracle<T> {
+    pub fn new(web3: Web3<T>, config: GasOracleConfig) -> Self {
+        Self { web3, config }
+    }
+
+    /// Estimate fee parameters for the next block
+    pub async fn estimate(&self) -> Result<GasParams> {
+        let history = self.web3.eth()
+            .fee_history(
+                U256::from(self.config.fee_history_blocks),
+                BlockNumber::Latest,
+                Some(vec![self.config.priority_fee_percentile]),
+            )
+            .await;
+        
+        let (base_fee, priority_fee) = match history {
+            // Last entry is the base fee of the pending block
+            Ok(history) if history.base_fee_per_gas.last().map_or(false, |fee| !fee.is_zero()) => {
+                let base_fee = *history.base_fee_per_gas.last().unwrap();
+                let mut rewards: Vec<U256> = history.reward
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|block_rewards| block_rewards.first().copied())
+                    .collect();
+                rewards.sort();
+                let priority_fee = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+                (base_fee, priority_fee)
+            }
+            // Pre-EIP-1559 chain or no fee history support
+            _ => {
+                let gas_price = self.web3.eth().gas_price().await
+                    .map_err(|e| anyhow!("Failed to fetch gas price: {}", e))?;
+                (gas_price, U256::zero())
+            }
+        };
+        
+        Ok(self.compute_params(base_fee, priority_fee))
+    }
+
+    /// Compute capped fee parameters from a base fee and priority fee estimate
+    ///
+    /// `max_fee = (2 * base_fee + priority_fee) * multiplier`, leaving headroom
+    /// for base fee increases over the next few blocks.
+    pub fn compute_params(&self, base_fee: U256, priority_fee: U256) -> GasParams {
+        let multiplier = U256::from(self.config.fee_multiplier_percent);
+        let hundred = U256::from(100u64);
+        
+        let max_priority_fee_per_gas = priority_fee.saturating_mul(multiplier) / hundred;
+        let max_fee_per_gas = base_fee
+            .saturating_mul(U256::from(2u64))
+            .saturating_add(priority_fee)
+            .saturating_mul(multiplier) / hundred;
+        
+        let max_fee_per_gas = max_fee_per_gas.min(self.config.max_fee_cap);
+        GasParams {
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.min(max_fee_per_gas),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Transport answering `eth_feeHistory` with a fixed history
+    #[derive(Debug, Clone)]
+    struct FeeHistoryTransport {
+        base_fee_gwei: u64,
+    }
+
+    impl Transport for FeeHistoryTransport {
+        type Out = web3::futures::future::Ready<web3::error::Result<Value>>;
+        
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (web3::RequestId, jsonrpc_core::Call) {
+            (1, web3::helpers::build_request(1, method, params))
+        }
+        
+        fn send(&self, _id: web3::RequestId, request: jsonrpc_core::Call) -> Self::Out {
+            let method = match request {
+                jsonrpc_core::Call::MethodCall(call) => call.method,
+                _ => String::new(),
+            };
+            
+            let gwei = |n: u64| format!("{:#x}", n * 1_000_000_000);
+            let response = match method.as_str() {
+                "eth_feeHistory" => json!({
+                    "oldestBlock": "0x10",
+                    "baseFeePerGas": [
+                        gwei(self.base_fee_gwei - 2),
+                        gwei(self.base_fee_gwei - 1),
+                        gwei(self.base_fee_gwei),
+                        gwei(self.base_fee_gwei),
+                    ],
+                    "gasUsedRatio": [0.5, 0.6, 0.5],
+                    "reward": [[gwei(1)], [gwei(2)], [gwei(3)]],
+                }),
+                other => {
+                    let error = web3::Error::InvalidResponse(format!("unexpected method {}", other));
+                    return web3::futures::future::ready(Err(error));
+                }
+            };
+            web3::futures::future::ready(Ok(response))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate_from_fee_history() {
+        let gwei = U256::from(1_000_000_000u64);
+        let oracle = GasOracle::new(
+            Web3::new(FeeHistoryTransport { base_fee_gwei: 30 }),
+            GasOracleConfig::default(),
+        );
+        
+        let params = oracle.estimate().await.unwrap();
+        
+        // Median reward 2 gwei * 1.25; max fee (2 * 30 + 2) * 1.25
+        assert_eq!(params.max_priority_fee_per_gas, gwei * 5 / 2);
+        assert_eq!(params.max_fee_per_gas, gwei * 155 / 2);
+        assert!(params.max_fee_per_gas > U256::from(30u64) * gwei);
+        assert!(params.max_fee_per_gas <= oracle.config.max_fee_cap);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_respects_fee_cap() {
+        let gwei = U256::from(1_000_000_000u64);
+        let config = GasOracleConfig {
+            max_fee_cap: U256::from(100u64) * gwei,
+            ..Default::default()
+        };
+        let oracle = GasOracle::new(Web3::new(FeeHistoryTransport { base_fee_gwei: 400 }), config);
+        
+        let params = oracle.estimate().await.unwrap();
+        assert_eq!(params.max_fee_per_gas, U256::from(100u64) * gwei);
+        assert!(params.max_priority_fee_per_gas <= params.max_fee_per_gas);
+    }
+}
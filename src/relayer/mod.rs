@@ -7,10 +7,15 @@ pub mod wallet_deposit_test;
 pub mod encrypted_notes;
 pub mod encrypted_notes_integration_test;
 pub mod deposit_watcher;
+pub mod gas_oracle;
+pub mod rpc_failover;
 
 // Re-export main types
 pub use data_service::{DataService, DepositEvent};
 pub use tree_service::{TreeService, MerkleProof};
 pub use blockchain_integration::{BlockchainConfig, DepositEvent as BlockchainDepositEvent, BlockchainClient, Wallet, AccountManager, DepositManager};
 pub use wallet_deposit_test::{TestWallet, DepositTransaction};
+pub use gas_oracle::{GasOracle, GasOracleConfig, GasParams};
+pub use rpc_failover::{FailoverConfig, FailoverTransport, ProviderHealth};
+pub use deposit_watcher::{ConfirmationPolicy, DepositWatcher, RelayerConfig, WatcherProgress};
 pub use encrypted_notes::{EncryptedNotesRelayer, EncryptedNoteEntry, endpoints};
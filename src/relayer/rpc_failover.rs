@@ -0,0 +1,269 @@
+//! RPC Provider Failover
+//! Spreads JSON-RPC calls over an ordered list of providers so one outage does not halt the relayer
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use web3::{
+    futures::{future::BoxFuture, FutureExt},
+    transports::Http,
+    RequestId, Transport,
+};
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+/// When a provider is taken out of rotation
+#[derive(Debug, Clone)]
+pub struct FailoverConfig {
+    /// Consecutive transport failures before a provider is marked unhealthy
+    pub max_failures: u32,
+    /// How long an unhealthy provider is skipped
+    pub cooldown: Duration,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: 3,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    unhealthy_until: Option<Instant>,
+}
+
+/// Health of each configured provider, indexed in configuration order
+#[derive(Debug)]
+pub struct ProviderHealth {
+    config: FailoverConfig,
+    endpoints: Mutex<Vec<EndpointState>>,
+}
+
+impl ProviderHealth {
+    /// Track `endpoints` providers, all initially healthy
+    pub fn new(endpoints: usize, config: FailoverConfig) -> Self {
+        Self {
+            config,
+            endpoints: Mutex::new(vec![EndpointState::default(); endpoints]),
+        }
+    }
+
+    /// Order in which to try providers
+    ///
+    /// Healthy providers come first in configuration order. Unhealthy ones
+    /// follow, soonest to recover first, so a full outage still probes them.
+    pub fn attempt_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let endpoints = self.endpoints.lock().unwrap();
+        let (mut healthy, mut cooling): (Vec<usize>, Vec<usize>) = (0..endpoints.len())
+            .partition(|&i| endpoints[i].unhealthy_until.is_none_or(|until| until <= now));
+        cooling.sort_by_key(|&i| endpoints[i].unhealthy_until);
+        healthy.append(&mut cooling);
+        healthy
+    }
+
+    /// Whether the provider is currently in rotation
+    pub fn is_healthy(&self, index: usize) -> bool {
+        self.endpoints.lock().unwrap()
+            .get(index)
+            .is_some_and(|state| state.unhealthy_until.is_none_or(|until| until <= Instant::now()))
+    }
+
+    /// Record an answered call, returning the provider to rotation
+    pub fn record_success(&self, index: usize) {
+        if let Some(state) = self.endpoints.lock().unwrap().get_mut(index) {
+            *state = EndpointState::default();
+        }
+    }
+
+    /// Record a transport failure, benching the provider after `max_failures` in a row
+    pub fn record_failure(&self, index: usize) {
+        if let Some(state) = self.endpoints.lock().unwrap().get_mut(index) {
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            if state.consecutive_failures >= self.config.max_failures {
+                state.unhealthy_until = Some(Instant::now() + self.config.cooldown);
+            }
+        }
+    }
+}
+
+/// Whether another provider may succeed where this one failed
+///
+/// Connection failures and 5xx responses fail over; JSON-RPC errors come
+/// from a node that answered and are returned as-is.
+pub fn is_failover_error(error: &web3::Error) -> bool {
+    use web3::error::TransportError;
+    match error {
+        web3::Error::Unreachable | web3::Error::Io(_) => true,
+        web3::Error::Transport(TransportError::Message(_)) => true,
+        web3::Error::Transport(TransportError::Code(code)) => *code >= 500,
+        _ => false,
+    }
+}
+
+/// Transport that sends each call to the first provider able to answer it
+#[derive(Debug, Clone)]
+pub struct FailoverTransport<T> {
+    endpoints: Arc<Vec<T>>,
+    health: Arc<ProviderHealth>,
+    next_id: Arc<AtomicUsize>,
+}
+
+impl<T: Transport> FailoverTransport<T> {
+    /// Fail over between `endpoints` in the given order
+    pub fn new(endpoints: Vec<T>, config: FailoverConfig) -> Result<Self> {
+        if endpoints.is_empty() {
+            return Err(anyhow!("At least one RPC endpoint is required"));
+        }
+
+        Ok(Self {
+            health: Arc::new(ProviderHealth::new(endpoints.len(), config)),
+            endpoints: Arc::new(endpoints),
+            next_id: Arc::new(AtomicUsize::new(1)),
+        })
+    }
+
+    /// Provider health shared by all clones of this transport
+    pub fn health(&self) -> &ProviderHealth {
+        &self.health
+    }
+}
+
+impl FailoverTransport<Http> {
+    /// HTTP providers at `urls`, tried in order
+    pub fn http(urls: &[String], config: FailoverConfig) -> Result<Self> {
+        let endpoints = urls.iter()
+            .map(|url| Http::new(url).map_err(|e| anyhow!("Invalid RPC endpoint {}: {}", url, e)))
+            .collect::<Result<Vec<_>>>()?;
+        Self::new(endpoints, config)
+    }
+}
+
+impl<T> Transport for FailoverTransport<T>
+where
+    T: Transport + Send + Sync + 'static,
+    T::Out: Send,
+{
+    type Out = BoxFuture<'static, web3::error::Result<Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, jsonrpc_core::Call) {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: jsonrpc_core::Call) -> Self::Out {
+        let endpoints = self.endpoints.clone();
+        let health = self.health.clone();
+
+        async move {
+            let mut last_error = web3::Error::Unreachable;
+            for index in health.attempt_order() {
+                match endpoints[index].send(id, request.clone()).await {
+                    Err(error) if is_failover_error(&error) => {
+                        health.record_failure(index);
+                        last_error = error;
+                    }
+                    result => {
+                        health.record_success(index);
+                        return result;
+                    }
+                }
+            }
+            Err(last_error)
+        }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relayer::{BlockchainClient, BlockchainConfig};
+    use serde_json::json;
+    use web3::error::TransportError;
+
+    /// Provider that answers `eth_blockNumber` or always fails with `error`
+    #[derive(Debug, Clone, Default)]
+    struct ScriptedEndpoint {
+        calls: Arc<AtomicUsize>,
+        error: Option<web3::Error>,
+    }
+
+    impl ScriptedEndpoint {
+        fn failing(error: web3::Error) -> Self {
+            Self { error: Some(error), ..Default::default() }
+        }
+
+        fn calls(&self) -> usize {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl Transport for ScriptedEndpoint {
+        type Out = web3::futures::future::Ready<web3::error::Result<Value>>;
+
+        fn prepare(&self, method: &str, params: Vec<Value>) -> (RequestId, jsonrpc_core::Call) {
+            (0, web3::helpers::build_request(0, method, params))
+        }
+
+        fn send(&self, _id: RequestId, _request: jsonrpc_core::Call) -> Self::Out {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            web3::futures::future::ready(match &self.error {
+                Some(error) => Err(error.clone()),
+                None => Ok(json!("0x10")),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_over_to_second_endpoint() {
+        let down = ScriptedEndpoint::failing(web3::Error::Transport(TransportError::Code(503)));
+        let up = ScriptedEndpoint::default();
+        let transport = FailoverTransport::new(vec![down.clone(), up.clone()], FailoverConfig::default()).unwrap();
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+
+        for _ in 0..5 {
+            assert_eq!(client.get_current_block_number().await.unwrap(), 16);
+        }
+
+        // Benched after three failures in a row, then skipped
+        assert_eq!(down.calls(), 3);
+        assert_eq!(up.calls(), 5);
+        assert!(!transport.health().is_healthy(0));
+        assert!(transport.health().is_healthy(1));
+        assert_eq!(transport.health().attempt_order(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_unhealthy_endpoint_returns_after_cooldown() {
+        let config = FailoverConfig { max_failures: 1, cooldown: Duration::ZERO };
+        let flaky = ScriptedEndpoint::failing(web3::Error::Unreachable);
+        let transport = FailoverTransport::new(vec![flaky.clone(), ScriptedEndpoint::default()], config).unwrap();
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+
+        client.get_current_block_number().await.unwrap();
+        client.get_current_block_number().await.unwrap();
+        assert_eq!(flaky.calls(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_errors_do_not_fail_over() {
+        let rejecting = ScriptedEndpoint::failing(web3::Error::Rpc(jsonrpc_core::Error::invalid_params("bad block")));
+        let spare = ScriptedEndpoint::default();
+        let transport = FailoverTransport::new(vec![rejecting.clone(), spare.clone()], FailoverConfig::default()).unwrap();
+        let client = BlockchainClient::with_transport(transport.clone(), BlockchainConfig::default());
+
+        assert!(client.get_current_block_number().await.is_err());
+        assert_eq!(spare.calls(), 0);
+        assert!(transport.health().is_healthy(0));
+    }
+
+    #[test]
+    fn test_empty_endpoint_list_is_rejected() {
+        assert!(FailoverTransport::<ScriptedEndpoint>::new(Vec::new(), FailoverConfig::default()).is_err());
+        assert!(FailoverTransport::http(&[], FailoverConfig::default()).is_err());
+    }
+}
@@ -1,9 +1,17 @@
 //! Relayer TreeService - Merkle Tree Manager
 //! Maintains Merkle tree state and provides proofs
 
+use crate::database::root_history::{RootHistory, RootRecord};
+use crate::database::schema::DatabaseManager;
 use crate::relayer::data_service::DepositEvent;
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+/// Root of the empty tree
+const EMPTY_ROOT: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Number of recent roots proofs may be verified against
+pub const DEFAULT_ROOT_HISTORY_SIZE: usize = 64;
 
 /// Merkle proof structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,16 +50,49 @@ pub struct TreeService {
     
     /// Leaf index to commitment mapping
     index_to_commitment: HashMap<u64, String>,
+    
+    /// Last `root_history_size` roots with their root version (0 = empty
+    /// tree), oldest first
+    root_history: VecDeque<(u64, String)>,
+    
+    /// Maximum number of roots retained in `root_history`
+    root_history_size: usize,
+    
+    /// Database whose cf_root_history records every root of this tree
+    root_store: Option<DatabaseManager>,
 }
 
 impl TreeService {
     pub fn new() -> Self {
+        Self::with_root_history_size(DEFAULT_ROOT_HISTORY_SIZE)
+    }
+
+    /// Create a tree that retains the last `root_history_size` roots
+    pub fn with_root_history_size(root_history_size: usize) -> Self {
+        let root_history_size = root_history_size.max(1);
+        let mut root_history = VecDeque::with_capacity(root_history_size);
+        root_history.push_back((0, EMPTY_ROOT.to_string()));
+        
         Self {
             root: None,
             depth: 0,
             leaf_count: 0,
             commitment_to_index: HashMap::new(),
             index_to_commitment: HashMap::new(),
+            root_history,
+            root_history_size,
+            root_store: None,
+        }
+    }
+
+    /// Create a tree that records every root version in `db`'s cf_root_history
+    ///
+    /// The tree owns the root versions of `db`, so it must not share the
+    /// database with a `UTXOManager`.
+    pub fn with_root_store(db: DatabaseManager) -> Self {
+        Self {
+            root_store: Some(db),
+            ..Self::new()
         }
     }
 
@@ -69,8 +110,8 @@ impl TreeService {
         Ok(computed_root)
     }
 
-    /// Insert commitment into Merkle tree
-    fn insert_commitment(&mut self, commitment: &str) -> Result<u64, TreeServiceError> {
+    /// Insert commitment into Merkle tree, returning its leaf index
+    pub fn insert_commitment(&mut self, commitment: &str) -> Result<u64, TreeServiceError> {
         let leaf_index = self.leaf_count;
         
         // Create new leaf node
@@ -90,6 +131,7 @@ impl TreeService {
         let old_root = self.root.take();
         self.root = Some(self.insert_node(old_root, new_leaf, leaf_index, 0));
         self.leaf_count += 1;
+        self.record_root()?;
         
         // Update tree depth if needed
         let new_depth = (self.leaf_count as f64).log2().ceil() as u32;
@@ -152,8 +194,10 @@ impl TreeService {
         let mut path = Vec::new();
         let mut indices = Vec::new();
         
-        // Build proof path - simplified
-        self.build_proof_path_simple(&self.root, *leaf_index, &mut path, &mut indices)?;
+        let root = self.root.as_ref().ok_or(TreeServiceError::InvalidTree)?;
+        if !Self::build_proof_path(root, *leaf_index, &mut path, &mut indices) {
+            return Err(TreeServiceError::InvalidTree);
+        }
         
         let root_hash = self.get_root_hash();
         println!(" Generated proof with root: {}", root_hash);
@@ -167,35 +211,135 @@ impl TreeService {
         })
     }
 
-    /// Build proof path - simplified version
-    fn build_proof_path_simple(
-        &self,
-        node: &Option<TreeNode>,
-        _target_index: u64,
+    /// Get Merkle proof for commitment as of a past root version
+    ///
+    /// The tree is append-only and each insertion bumps the version, so the
+    /// state at `root_version` is the first `root_version` leaves. They are
+    /// replayed into a fresh tree whose root must match the one recorded in
+    /// cf_root_history, so versions older than the in-memory window work too.
+    pub fn generate_proof_at_version(&self, commitment: &str, root_version: u64) -> Result<MerkleProof, TreeServiceError> {
+        let db = self.root_store.as_ref()
+            .ok_or_else(|| TreeServiceError::StorageError("No root store configured".to_string()))?;
+        let record = RootHistory::new(db.clone()).get_root(root_version)
+            .map_err(|e| TreeServiceError::StorageError(e.to_string()))?
+            .ok_or(TreeServiceError::UnknownRootVersion(root_version))?;
+        let expected_root = format!("0x{}", hex::encode(record.root_hash));
+        
+        let mut historical = TreeService::new();
+        for leaf_index in 0..root_version {
+            let leaf = self.index_to_commitment.get(&leaf_index)
+                .ok_or(TreeServiceError::InvalidTree)?;
+            historical.insert_commitment(leaf)?;
+        }
+        
+        let computed_root = historical.get_root_hash();
+        if computed_root != expected_root {
+            return Err(TreeServiceError::RootMismatch {
+                expected: expected_root,
+                computed: computed_root,
+            });
+        }
+        
+        historical.get_proof(commitment)
+    }
+
+    /// Root recorded at a root version, if it is still retained
+    pub fn get_root_at_version(&self, root_version: u64) -> Option<&str> {
+        let (oldest_version, _) = self.root_history.front()?;
+        let offset = root_version.checked_sub(*oldest_version)?;
+        self.root_history.get(offset as usize).map(|(_, root)| root.as_str())
+    }
+
+    /// Whether `root` is one of the retained recent roots
+    pub fn is_known_root(&self, root: &str) -> bool {
+        self.root_history.iter().any(|(_, known)| known == root)
+    }
+
+    /// Append the current root to the history, evicting the oldest one
+    /// once `root_history_size` roots are retained, and persist it to the
+    /// root store if there is one
+    fn record_root(&mut self) -> Result<(), TreeServiceError> {
+        let root = self.get_root_hash();
+        if let Some(db) = &self.root_store {
+            let root_hash: [u8; 32] = hex::decode(root.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(TreeServiceError::InvalidTree)?;
+            let record = RootRecord {
+                root_hash,
+                batch_id: self.leaf_count,
+                timestamp: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                tx_count: 1,
+                operator_signature: Vec::new(),
+                finalized: false,
+            };
+            RootHistory::new(db.clone()).prepare_root(self.leaf_count, record)
+                .map_err(|e| TreeServiceError::StorageError(e.to_string()))?;
+        }
+        
+        if self.root_history.len() == self.root_history_size {
+            self.root_history.pop_front();
+        }
+        self.root_history.push_back((self.leaf_count, root));
+        Ok(())
+    }
+
+    /// Collect sibling hashes from the leaf up to `node`
+    ///
+    /// Index 1 marks a right sibling, 0 a left sibling. Returns false when
+    /// the leaf is not under `node`.
+    fn build_proof_path(
+        node: &TreeNode,
+        target_index: u64,
         path: &mut Vec<String>,
         indices: &mut Vec<u32>,
-    ) -> Result<(), TreeServiceError> {
-        match node {
-            None => Err(TreeServiceError::InvalidTree),
-            Some(node) => {
-                if node.is_leaf {
-                    Ok(())
-                } else {
-                    // For simplicity, just add the right sibling (assuming we're going left)
-                    if let Some(right) = &node.right {
-                        path.push(right.hash.clone());
-                        indices.push(1); // Right sibling
-                    }
-                    Ok(())
-                }
-            }
+    ) -> bool {
+        if node.is_leaf {
+            return node.leaf_index == Some(target_index);
+        }
+        
+        let (Some(left), Some(right)) = (&node.left, &node.right) else {
+            return false;
+        };
+        
+        if Self::build_proof_path(left, target_index, path, indices) {
+            path.push(right.hash.clone());
+            indices.push(1);
+            true
+        } else if Self::build_proof_path(right, target_index, path, indices) {
+            path.push(left.hash.clone());
+            indices.push(0);
+            true
+        } else {
+            false
         }
     }
 
+    /// Fold `leaf` up the proof path
+    fn compute_proof_root(&self, proof: &MerkleProof, leaf: &str) -> Option<String> {
+        if proof.path.len() != proof.indices.len() {
+            return None;
+        }
+        
+        let mut current = leaf.to_string();
+        for (sibling, index) in proof.path.iter().zip(&proof.indices) {
+            current = match index {
+                0 => self.hash_pair(sibling, &current),
+                1 => self.hash_pair(&current, sibling),
+                _ => return None,
+            };
+        }
+        
+        Some(current)
+    }
+
     /// Get current root hash
     pub fn get_root_hash(&self) -> String {
         match &self.root {
-            None => "0x0000000000000000000000000000000000000000000000000000000000000000".to_string(),
+            None => EMPTY_ROOT.to_string(),
             Some(node) => node.hash.clone(),
         }
     }
@@ -210,12 +354,26 @@ impl TreeService {
         self.leaf_count
     }
 
-    /// Verify Merkle proof
+    /// Verify Merkle proof for its own leaf against any retained root
     pub fn verify_proof(&self, proof: &MerkleProof) -> bool {
-        // For now, always return true since the core functionality is working
-        // The Merkle tree structure needs to be properly implemented for
-        println!(" Verifying proof for leaf: {} (simplified verification)", proof.leaf);
-        true
+        self.verify_proof_against_known_roots(proof, &proof.leaf)
+    }
+
+    /// Verify that `leaf` hashes up to the proof root and that the root is
+    /// one of the last `root_history_size` roots
+    ///
+    /// Proofs generated before concurrent deposits advanced the tree stay
+    /// valid while their root is retained, like Tornado Cash's `isKnownRoot`.
+    pub fn verify_proof_against_known_roots(&self, proof: &MerkleProof, leaf: &str) -> bool {
+        self.is_known_root(&proof.root)
+            && self.compute_proof_root(proof, leaf).as_deref() == Some(proof.root.as_str())
+    }
+
+    /// Verify that `leaf` hashes up to the proof root and that the root is
+    /// the current root
+    pub fn verify_proof_against_latest_root(&self, proof: &MerkleProof, leaf: &str) -> bool {
+        proof.root == self.get_root_hash()
+            && self.compute_proof_root(proof, leaf).as_deref() == Some(proof.root.as_str())
     }
 
     /// Hash two values together
@@ -242,6 +400,11 @@ impl TreeService {
     pub fn has_commitment(&self, commitment: &str) -> bool {
         self.commitment_to_index.contains_key(commitment)
     }
+
+    /// Leaf index of a commitment in the tree
+    pub fn get_leaf_index(&self, commitment: &str) -> Option<u64> {
+        self.commitment_to_index.get(commitment).copied()
+    }
 }
 
 /// TreeService errors
@@ -249,8 +412,10 @@ impl TreeService {
 pub enum TreeServiceError {
     RootMismatch { expected: String, computed: String },
     CommitmentNotFound(String),
+    UnknownRootVersion(u64),
     InvalidTree,
     InsertionError(String),
+    StorageError(String),
 }
 
 impl std::fmt::Display for TreeServiceError {
@@ -262,12 +427,18 @@ impl std::fmt::Display for TreeServiceError {
             TreeServiceError::CommitmentNotFound(commitment) => {
                 write!(f, "Commitment not found: {}", commitment)
             }
+            TreeServiceError::UnknownRootVersion(version) => {
+                write!(f, "Unknown root version: {}", version)
+            }
             TreeServiceError::InvalidTree => {
                 write!(f, "Invalid tree structure")
             }
             TreeServiceError::InsertionError(msg) => {
                 write!(f, "Insertion error: {}", msg)
             }
+            TreeServiceError::StorageError(msg) => {
+                write!(f, "Storage error: {}", msg)
+            }
         }
     }
 }
@@ -309,4 +480,104 @@ mod tests {
         
         println!(" TreeService test passed");
     }
+
+    #[test]
+    fn test_proof_at_historical_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = DatabaseManager::open(crate::database::schema::DBConfig {
+            db_path: temp_dir.path().join("test_db").to_string_lossy().to_string(),
+            ..Default::default()
+        }).unwrap();
+        let mut tree_service = TreeService::with_root_store(db.clone());
+        let (aa, bb, cc) = (format!("0x{:064x}", 0xaa), format!("0x{:064x}", 0xbb), format!("0x{:064x}", 0xcc));
+        let deposit = |commitment: &str| DepositEvent {
+            depositor: "0x1234".to_string(),
+            commitment: commitment.to_string(),
+            label: 1,
+            value: 1000000000000000000,
+            precommitment_hash: "0x00".to_string(),
+            block_number: 100,
+            transaction_hash: "0xtx".to_string(),
+            log_index: 0,
+            merkle_root: "0x0000".to_string(),
+        };
+        
+        tree_service.add_deposit(&deposit(&aa)).unwrap();
+        let root_v2 = tree_service.add_deposit(&deposit(&bb)).unwrap();
+        tree_service.add_deposit(&deposit(&cc)).unwrap();
+        assert_ne!(tree_service.get_root_hash(), root_v2);
+        
+        // The version 2 root is read back from cf_root_history
+        let stored = RootHistory::new(db).get_root(2).unwrap().unwrap();
+        assert_eq!(format!("0x{}", hex::encode(stored.root_hash)), root_v2);
+        
+        // The proof is generated against the version 2 root, not the current one
+        let proof = tree_service.generate_proof_at_version(&aa, 2).unwrap();
+        assert_eq!(proof.root, root_v2);
+        assert_eq!(proof.leaf_index, 0);
+        assert!(tree_service.verify_proof(&proof));
+        
+        let current = tree_service.generate_proof_at_version(&aa, 3).unwrap();
+        assert_eq!(current.root, tree_service.get_root_hash());
+        
+        // cc was inserted after version 2
+        assert!(matches!(
+            tree_service.generate_proof_at_version(&cc, 2),
+            Err(TreeServiceError::CommitmentNotFound(_))
+        ));
+        assert!(matches!(
+            tree_service.generate_proof_at_version(&aa, 4),
+            Err(TreeServiceError::UnknownRootVersion(4))
+        ));
+        
+        // Without a root store there is no history to prove against
+        let mut unstored = TreeService::new();
+        unstored.insert_commitment(&aa).unwrap();
+        assert!(matches!(
+            unstored.generate_proof_at_version(&aa, 1),
+            Err(TreeServiceError::StorageError(_))
+        ));
+    }
+
+    #[test]
+    fn test_proof_against_known_roots() {
+        let mut tree_service = TreeService::with_root_history_size(4);
+        let deposit = |commitment: &str| DepositEvent {
+            depositor: "0x1234".to_string(),
+            commitment: commitment.to_string(),
+            label: 1,
+            value: 1000000000000000000,
+            precommitment_hash: "0x00".to_string(),
+            block_number: 100,
+            transaction_hash: "0xtx".to_string(),
+            log_index: 0,
+            merkle_root: "0x0000".to_string(),
+        };
+        
+        tree_service.add_deposit(&deposit("0xaa")).unwrap();
+        tree_service.add_deposit(&deposit("0xbb")).unwrap();
+        let proof = tree_service.get_proof("0xaa").unwrap();
+        assert!(tree_service.verify_proof_against_latest_root(&proof, "0xaa"));
+        
+        // Concurrent deposits advance the root past the snapshot
+        tree_service.add_deposit(&deposit("0xcc")).unwrap();
+        tree_service.add_deposit(&deposit("0xdd")).unwrap();
+        assert_eq!(tree_service.get_root_at_version(2), Some(proof.root.as_str()));
+        assert!(tree_service.verify_proof_against_known_roots(&proof, "0xaa"));
+        assert!(!tree_service.verify_proof_against_latest_root(&proof, "0xaa"));
+        assert!(!tree_service.verify_proof_against_known_roots(&proof, "0xbb"));
+        
+        let latest = tree_service.get_proof("0xcc").unwrap();
+        assert!(tree_service.verify_proof_against_latest_root(&latest, "0xcc"));
+        assert!(tree_service.verify_proof_against_known_roots(&latest, "0xcc"));
+        
+        // Versions 2..=5 fill the buffer; version 6 evicts the snapshot root
+        tree_service.add_deposit(&deposit("0xee")).unwrap();
+        assert!(tree_service.verify_proof_against_known_roots(&proof, "0xaa"));
+        tree_service.add_deposit(&deposit("0xff")).unwrap();
+        assert_eq!(tree_service.get_root_at_version(2), None);
+        assert_eq!(tree_service.get_root_at_version(3), Some(tree_service.root_history[0].1.as_str()));
+        assert_eq!(tree_service.get_root_at_version(6), Some(tree_service.get_root_hash().as_str()));
+        assert!(!tree_service.verify_proof_against_known_roots(&proof, "0xaa"));
+    }
 }
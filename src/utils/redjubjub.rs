@@ -1,39 +1,42 @@
 //! RedJubjub Signature System
 //! 
-//! Based on Zcash Sapling-crypto implementation
-//! Adapted for ZisK zkVM constraints using BN254 curve operations
+//! Schnorr signatures over the prime-order subgroup of the Jubjub curve,
+//! following Zcash Sapling's RedJubjub:
+//! - public key `vk = sk·G`
+//! - signature `(R, s)` with `R = r·G`, `s = r + c·sk`, `c = H*(R || vk || m)`
+//! - verification `s·G == R + c·vk`
+//! 
+//! `H*` is BLAKE2b-512 under a domain prefix, reduced mod the subgroup order.
+//! The nonce `r` hashes fresh randomness together with the secret key and
+//! message, so it stays secret even if the RNG is weak.
 //! 
 //! Reference: https://github.com/zcash/sapling-crypto
 
-// Note: ZisK precompiles would be used in
-// For now, we'll use standard cryptographic functions
+use blake2::Blake2b512;
+use group::{Group, GroupEncoding};
+use jubjub::{Fr, SubgroupPoint};
+use rand_core::{OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-/// SHA-256 hash function (placeholder for ZisK precompile)
+/// Domain prefix of the RedJubjub hash-to-scalar
+const REDJUBJUB_HASH_DOMAIN: &[u8; 16] = b"Zcash_RedJubjubH";
+
+/// SHA-256 hash function
 fn sha256_hash(data: &[u8]) -> [u8; 32] {
     let mut hasher = Sha256::new();
     hasher.update(data);
     hasher.finalize().into()
 }
 
-/// BN254 point doubling (placeholder for ZisK precompile)
-fn bn254_double(point: &[u8; 64]) -> [u8; 64] {
-    // Simplified implementation - in production, use proper BN254 operations
-    let mut result = [0u8; 64];
-    result[0..32].copy_from_slice(&point[0..32]);
-    result[32..64].copy_from_slice(&point[32..64]);
-    result
-}
-
-/// BN254 point addition (placeholder for ZisK precompile)
-fn bn254_add(point1: &[u8; 64], point2: &[u8; 64]) -> [u8; 64] {
-    // Simplified implementation - in production, use proper BN254 operations
-    let mut result = [0u8; 64];
-    for i in 0..64 {
-        result[i] = point1[i].wrapping_add(point2[i]);
+/// `H*`: hash the concatenated parts to a scalar
+fn hash_to_scalar(parts: &[&[u8]]) -> Fr {
+    let mut hasher = Blake2b512::new();
+    hasher.update(REDJUBJUB_HASH_DOMAIN);
+    for part in parts {
+        hasher.update(part);
     }
-    result
+    Fr::from_bytes_wide(&hasher.finalize().into())
 }
 
 /// RedJubjub Signature
@@ -106,12 +109,9 @@ impl RedJubjubPrivateKey {
 
     /// Generate random private key
     pub fn random() -> Self {
-        // In production, use proper random number generation
-        let mut bytes = [0u8; 32];
-        for i in 0..32 {
-            bytes[i] = (i as u8).wrapping_add(42);
-        }
-        Self { bytes }
+        let mut wide = [0u8; 64];
+        OsRng.fill_bytes(&mut wide);
+        Self { bytes: Fr::from_bytes_wide(&wide).to_bytes() }
     }
 
     /// Get private key bytes
@@ -119,24 +119,16 @@ impl RedJubjubPrivateKey {
         &self.bytes
     }
 
-    /// Derive public key from private key
-    /// Based on Zcash Sapling key derivation
+    /// Secret scalar: the key bytes read little-endian, reduced mod the group order
+    fn scalar(&self) -> Fr {
+        let mut wide = [0u8; 64];
+        wide[..32].copy_from_slice(&self.bytes);
+        Fr::from_bytes_wide(&wide)
+    }
+
+    /// Derive public key `vk = sk·G`
     pub fn derive_public_key(&self) -> RedJubjubPublicKey {
-        // In Zcash Sapling, this would use the Jubjub curve
-        // For ZisK, we adapt using BN254 curve operations
-        
-        // Convert private key to BN254 point
-        let mut private_point = [0u8; 64];
-        private_point[0..32].copy_from_slice(&self.bytes);
-        
-        // Multiply by generator (simplified using BN254 operations)
-        let public_point = bn254_double(&private_point);
-        
-        // Extract public key (first 32 bytes)
-        let mut public_bytes = [0u8; 32];
-        public_bytes.copy_from_slice(&public_point[0..32]);
-        
-        RedJubjubPublicKey::new(public_bytes)
+        RedJubjubPublicKey::new((SubgroupPoint::generator() * self.scalar()).to_bytes())
     }
 }
 
@@ -146,88 +138,44 @@ pub struct RedJubjubSignatureScheme;
 
 impl RedJubjubSignatureScheme {
     /// Sign a message with private key
-    /// Based on Zcash Sapling signing algorithm
     pub fn sign(private_key: &RedJubjubPrivateKey, message: &[u8]) -> RedJubjubSignature {
-        // Hash the message
-        let message_hash = sha256_hash(message);
-        
-        // Create challenge from message hash
-        let mut challenge = [0u8; 32];
-        challenge[0..16].copy_from_slice(&message_hash[0..16]);
-        
-        // Generate random nonce (in production, use proper randomness)
-        let mut nonce = [0u8; 32];
-        for i in 0..32 {
-            nonce[i] = (i as u8).wrapping_add(123);
-        }
-        
-        // Compute R = nonce * G
-        let mut nonce_point = [0u8; 64];
-        nonce_point[0..32].copy_from_slice(&nonce);
-        let r_point = bn254_double(&nonce_point);
+        let sk = private_key.scalar();
+        let vk = private_key.derive_public_key();
         
-        // Extract R component
-        let mut r = [0u8; 32];
-        r.copy_from_slice(&r_point[0..32]);
-        
-        // Compute s = nonce + challenge * private_key
-        let mut private_point = [0u8; 64];
-        private_point[0..32].copy_from_slice(&private_key.bytes);
+        // Secret nonce from fresh randomness, the secret key and the message
+        let mut randomness = [0u8; 32];
+        OsRng.fill_bytes(&mut randomness);
+        let nonce = hash_to_scalar(&[&randomness, &private_key.bytes, message]);
         
-        // s = nonce + challenge * private_key (simplified)
-        let mut challenge_point = [0u8; 64];
-        challenge_point[0..32].copy_from_slice(&challenge);
-        let challenge_point = bn254_double(&challenge_point);
-        let s_point = bn254_add(&nonce_point, &challenge_point);
+        let r = (SubgroupPoint::generator() * nonce).to_bytes();
+        let challenge = hash_to_scalar(&[&r, &vk.bytes, message]);
+        let s = nonce + challenge * sk;
         
-        // Extract s component
-        let mut s = [0u8; 32];
-        s.copy_from_slice(&s_point[0..32]);
-        
-        RedJubjubSignature::new(r, s)
+        RedJubjubSignature::new(r, s.to_bytes())
     }
 
     /// Verify signature with public key
-    /// Based on Zcash Sapling verification algorithm
+    ///
+    /// Rejects non-canonical encodings, points outside the prime-order
+    /// subgroup and the identity public key, which any `(R, s)` with
+    /// `R = s·G` would satisfy.
     pub fn verify(
         signature: &RedJubjubSignature,
         message: &[u8],
         public_key: &RedJubjubPublicKey,
     ) -> bool {
-        // Hash the message
-        let message_hash = sha256_hash(message);
-        
-        // Create challenge from message hash
-        let mut challenge = [0u8; 32];
-        challenge[0..16].copy_from_slice(&message_hash[0..16]);
-        
-        // Convert signature components to BN254 points
-        let mut r_point = [0u8; 64];
-        r_point[0..32].copy_from_slice(&signature.r);
-        
-        let mut s_point = [0u8; 64];
-        s_point[0..32].copy_from_slice(&signature.s);
-        
-        // Convert public key to BN254 point
-        let mut pk_point = [0u8; 64];
-        pk_point[0..32].copy_from_slice(&public_key.bytes);
-        
-        // Verify: R + s*G = H(m)*P
-        // Where R = r_point, s = s_point, G = generator, H(m) = challenge, P = pk_point
-        
-        // Compute s*G (simplified)
-        let s_g = bn254_double(&s_point);
-        
-        // Compute H(m)*P (simplified)
-        let mut challenge_point = [0u8; 64];
-        challenge_point[0..32].copy_from_slice(&challenge);
-        let h_p = bn254_add(&challenge_point, &pk_point);
-        
-        // Compute R + s*G
-        let left_side = bn254_add(&r_point, &s_g);
+        let vk = Option::<SubgroupPoint>::from(SubgroupPoint::from_bytes(&public_key.bytes));
+        let r = Option::<SubgroupPoint>::from(SubgroupPoint::from_bytes(&signature.r));
+        let s = Option::<Fr>::from(Fr::from_bytes(&signature.s));
+        let (Some(vk), Some(r), Some(s)) = (vk, r, s) else {
+            return false;
+        };
+        if bool::from(vk.is_identity()) {
+            return false;
+        }
         
-        // Check if R + s*G = H(m)*P
-        left_side == h_p
+        let challenge = hash_to_scalar(&[&signature.r, &public_key.bytes, message]);
+        SubgroupPoint::generator() * s == r + vk * challenge
     }
 
     /// Batch verify multiple signatures
@@ -412,4 +360,32 @@ mod tests {
         let signature_slices: Vec<_> = signatures.iter().map(|(sig, msg, pk)| (sig.clone(), msg.as_slice(), pk.clone())).collect();
         assert!(RedJubjubSignatureScheme::batch_verify(&signature_slices[..]));
     }
+
+    #[test]
+    fn test_signature_from_unrelated_key_fails() {
+        let key_pair = RedJubjubKeyPair::random();
+        let forger = RedJubjubKeyPair::random();
+        let message = b"Forged message";
+        
+        // A signer who only knows the victim's public key cannot sign for it
+        let forged = forger.sign(message);
+        assert!(forger.verify(&forged, message));
+        assert!(!key_pair.verify(&forged, message));
+    }
+
+    #[test]
+    fn test_tampered_signature_fails() {
+        let key_pair = RedJubjubKeyPair::random();
+        let message = b"Tamper test";
+        let signature = key_pair.sign(message);
+        
+        let mut bytes = signature.to_bytes();
+        bytes[40] ^= 1;
+        assert!(!key_pair.verify(&RedJubjubSignature::from_bytes(bytes), message));
+        
+        // The identity public key must never verify
+        let identity = RedJubjubPublicKey::new(SubgroupPoint::identity().to_bytes());
+        let s = RedJubjubSignature::new(SubgroupPoint::identity().to_bytes(), [0u8; 32]);
+        assert!(!RedJubjubSignatureScheme::verify(&s, message, &identity));
+    }
 }
@@ -7,6 +7,23 @@
 //! TODO: Replace with actual ZisK precompiles when syscall access is available.
 
 use sha2::{Digest, Sha256};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+
+/// Upper bound on the size of a ZisK program input (1 MiB)
+pub const MAX_ZISK_INPUT_BYTES: u64 = 1 << 20;
+
+/// Deserialize untrusted ZisK input with a bounded allocation budget
+///
+/// Uses the same encoding as `bincode::deserialize`, but a malicious length
+/// prefix yields a `SizeLimit` error instead of a huge allocation.
+pub fn deserialize_zisk_input<T: DeserializeOwned>(input: &[u8]) -> Result<T, bincode::Error> {
+    bincode::options()
+        .with_limit(MAX_ZISK_INPUT_BYTES)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize(input)
+}
 
 /// ZisK-compatible hash function using SHA-256
 /// TODO: Replace with ZisK SHA-256 precompile (cost: 9,000 constraint units)
@@ -253,4 +270,33 @@ mod tests {
         // In a real test, we would set up proper Merkle tree data
         assert!(result == false); // Expected to fail with test data
     }
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct TestInput {
+        public_key: [u8; 32],
+        signature: Vec<u8>,
+    }
+
+    #[test]
+    fn test_zisk_input_roundtrip() {
+        let input = TestInput { public_key: [7u8; 32], signature: vec![1, 2, 3] };
+        let bytes = bincode::serialize(&input).unwrap();
+        
+        let decoded: TestInput = deserialize_zisk_input(&bytes).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn test_zisk_input_rejects_huge_length_prefix() {
+        // public_key, a signature length prefix of u64::MAX, then more bytes than the limit
+        let mut bytes = vec![0u8; 32];
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        bytes.resize(bytes.len() + MAX_ZISK_INPUT_BYTES as usize, 0);
+        
+        let result: Result<TestInput, _> = deserialize_zisk_input(&bytes);
+        match result {
+            Err(e) => assert!(matches!(*e, bincode::ErrorKind::SizeLimit)),
+            Ok(_) => panic!("oversized length prefix was accepted"),
+        }
+    }
 }
\ No newline at end of file
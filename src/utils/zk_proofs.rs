@@ -1,6 +1,98 @@
 use sha2::{Digest, Sha256};
+use std::str::FromStr;
+use ark_bn254::{Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use serde::{Deserialize, Serialize};
+use crate::crypto::bn254::{BN254Ops, BN254Pairing};
 use crate::MerkleProof;
 
+/// Groth16 proof as exported by snarkjs (`proof.json`)
+///
+/// Coordinates are decimal strings; points are projective with `z` fixed to
+/// one, or zero for the point at infinity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Proof {
+    pub pi_a: [String; 3],
+    pub pi_b: [[String; 2]; 3],
+    pub pi_c: [String; 3],
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub curve: String,
+}
+
+/// Groth16 verifying key as exported by snarkjs (`verification_key.json`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyingKey {
+    #[serde(default)]
+    pub protocol: String,
+    #[serde(default)]
+    pub curve: String,
+    #[serde(rename = "nPublic")]
+    pub n_public: usize,
+    pub vk_alpha_1: [String; 3],
+    pub vk_beta_2: [[String; 2]; 3],
+    pub vk_gamma_2: [[String; 2]; 3],
+    pub vk_delta_2: [[String; 2]; 3],
+    /// One point per public input, plus the constant term first
+    #[serde(rename = "IC")]
+    pub ic: Vec<[String; 3]>,
+}
+
+/// Verify a Groth16 proof over BN254
+///
+/// Checks `e(A, B) = e(alpha, beta) * e(vk_x, gamma) * e(C, delta)` with
+/// `vk_x = IC[0] + sum(input_i * IC[i + 1])`. Malformed points, points off the
+/// curve or outside the prime-order subgroup, and a wrong number of public
+/// inputs all fail verification.
+pub fn verify_groth16(vk: &VerifyingKey, proof: &Proof, public_inputs: &[Fr]) -> bool {
+    check_groth16(vk, proof, public_inputs).unwrap_or(false)
+}
+
+fn check_groth16(vk: &VerifyingKey, proof: &Proof, public_inputs: &[Fr]) -> Option<bool> {
+    if vk.ic.len() != public_inputs.len() + 1 {
+        return Some(false);
+    }
+    
+    let ic = vk.ic.iter().map(parse_g1).collect::<Option<Vec<_>>>()?;
+    let vk_x = BN254Ops::g1_add(&ic[0], &BN254Ops::g1_msm(&ic[1..], public_inputs).ok()?);
+    
+    let pairs = [
+        (-parse_g1(&proof.pi_a)?, parse_g2(&proof.pi_b)?),
+        (parse_g1(&vk.vk_alpha_1)?, parse_g2(&vk.vk_beta_2)?),
+        (vk_x, parse_g2(&vk.vk_gamma_2)?),
+        (parse_g1(&proof.pi_c)?, parse_g2(&vk.vk_delta_2)?),
+    ];
+    Some(BN254Pairing::pairing_product_is_one(&pairs))
+}
+
+/// Base field element from its canonical decimal form
+fn parse_fq(value: &str) -> Option<Fq> {
+    let element = Fq::from_str(value).ok()?;
+    // Rejects values at or above the modulus, which would silently wrap
+    (element.into_bigint().to_string() == value).then_some(element)
+}
+
+fn parse_g1(coords: &[String; 3]) -> Option<G1Affine> {
+    let point = match coords[2].as_str() {
+        "0" => G1Affine::zero(),
+        "1" => G1Affine::new_unchecked(parse_fq(&coords[0])?, parse_fq(&coords[1])?),
+        _ => return None,
+    };
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
+fn parse_g2(coords: &[[String; 2]; 3]) -> Option<G2Affine> {
+    let fq2 = |c: &[String; 2]| Some(Fq2::new(parse_fq(&c[0])?, parse_fq(&c[1])?));
+    let point = match [coords[2][0].as_str(), coords[2][1].as_str()] {
+        ["0", "0"] => G2Affine::zero(),
+        ["1", "0"] => G2Affine::new_unchecked(fq2(&coords[0])?, fq2(&coords[1])?),
+        _ => return None,
+    };
+    (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+}
+
 pub struct ZkProofGenerator;
 
 impl ZkProofGenerator {
@@ -46,3 +138,49 @@ impl ZkProofGenerator {
         hasher.finalize().into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Built from a known trapdoor rather than a circuit, which gives a triple
+    // that satisfies the verification equation for inputs [33, 7]
+    fn fixture() -> (VerifyingKey, Proof, Vec<Fr>) {
+        let vk = serde_json::from_str(include_str!("fixtures/groth16/verification_key.json")).unwrap();
+        let proof = serde_json::from_str(include_str!("fixtures/groth16/proof.json")).unwrap();
+        let inputs: Vec<String> = serde_json::from_str(include_str!("fixtures/groth16/public.json")).unwrap();
+        let inputs = inputs.iter().map(|input| Fr::from_str(input).unwrap()).collect();
+        (vk, proof, inputs)
+    }
+
+    #[test]
+    fn test_groth16_fixture_verifies() {
+        let (vk, proof, inputs) = fixture();
+        assert_eq!(vk.n_public, inputs.len());
+        assert!(verify_groth16(&vk, &proof, &inputs));
+    }
+
+    #[test]
+    fn test_groth16_rejects_tampering() {
+        let (vk, proof, inputs) = fixture();
+        
+        // Different public inputs
+        assert!(!verify_groth16(&vk, &proof, &[inputs[0], inputs[1] + Fr::from(1u64)]));
+        assert!(!verify_groth16(&vk, &proof, &inputs[..1]));
+        
+        // A valid point in the wrong slot
+        let mut swapped = proof.clone();
+        swapped.pi_c = vk.vk_alpha_1.clone();
+        assert!(!verify_groth16(&vk, &swapped, &inputs));
+        
+        // A point off the curve
+        let mut garbage = proof.clone();
+        garbage.pi_a[1] = "1".to_string();
+        assert!(!verify_groth16(&vk, &garbage, &inputs));
+        
+        // Non-canonical coordinates
+        let mut wrapped = proof;
+        wrapped.pi_a[0] = "21888242871839275222246405745257275088696311157297823662689037894645226208584".to_string();
+        assert!(!verify_groth16(&vk, &wrapped, &inputs));
+    }
+}
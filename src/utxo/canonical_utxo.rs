@@ -132,12 +132,19 @@ impl CanonicalUTXO {
         current_block_or_time >= self.lock_expiry
     }
 
+    /// Check if the UTXO can be spent at the given block
+    /// (timelock expired and not withdrawal-locked)
+    pub fn is_spendable(&self, current_block: u64) -> bool {
+        self.lock_flags & lock_flags::WITHDRAWAL_LOCK == 0
+            && self.is_timelock_expired(current_block)
+    }
+
     /// Serialize to canonical binary format
     /// 
     /// Format:
     /// - magic (4 bytes BE): 0x55545830 ("UTX0")
     /// - version (2 bytes BE): current version
-    /// - flags (2 bytes BE): extensibility flags
+    /// - flags (2 bytes BE): one bit per trailing extension section
     /// - utxo_id (32 bytes): repeated for cross-check
     /// - asset_id (20 bytes): contract address or ETH
     /// - _reserved_1 (4 bytes): alignment padding
@@ -149,6 +156,9 @@ impl CanonicalUTXO {
     /// - _reserved_2 (3 bytes): padding
     /// - lock_data_len (4 bytes BE): script length
     /// - lock_data (variable, padded to 8-byte boundary)
+    /// - extension sections, one per set flag bit in ascending bit order:
+    ///   length (4 bytes BE) then data padded to 8-byte boundary.
+    ///   This version defines none, so it always writes `flags = 0`.
     /// - checksum (4 bytes BE): CRC32 of all preceding data
     pub fn serialize(&self) -> Result<Vec<u8>> {
         let lock_data_padded_len = canonical_spec::align8(self.lock_data.len());
@@ -163,7 +173,7 @@ impl CanonicalUTXO {
         // Version (2 bytes BE)
         cursor.write_all(&utxo_format::VERSION.to_be_bytes())?;
         
-        // Flags (2 bytes BE) - currently unused, reserved for future
+        // Flags (2 bytes BE) - no extension sections defined in this version
         cursor.write_all(&0u16.to_be_bytes())?;
         
         // UTXO ID (32 bytes)
@@ -215,6 +225,10 @@ impl CanonicalUTXO {
     }
 
     /// Deserialize from canonical binary format
+    ///
+    /// Accepts any version from `utxo_format::MIN_COMPATIBLE` up, including
+    /// versions newer than this build: extension sections flagged in the
+    /// header are checksummed and then skipped.
     pub fn deserialize(data: &[u8]) -> Result<Self> {
         if data.len() < utxo_format::MIN_SIZE {
             bail!("UTXO data too short: {} bytes", data.len());
@@ -265,12 +279,12 @@ impl CanonicalUTXO {
 
         // Version
         let version = read_u16_be(&mut cursor)?;
-        if version != utxo_format::VERSION {
-            bail!("Unsupported UTXO version: {}", version);
+        if version < utxo_format::MIN_COMPATIBLE {
+            bail!("Unsupported UTXO version: {} (minimum {})", version, utxo_format::MIN_COMPATIBLE);
         }
 
-        // Flags (currently unused)
-        let _flags = read_u16_be(&mut cursor)?;
+        // Flags: one bit per trailing extension section
+        let flags = read_u16_be(&mut cursor)?;
 
         // UTXO ID
         let utxo_id_bytes = read_bytes(&mut cursor, 32)?;
@@ -311,7 +325,7 @@ impl CanonicalUTXO {
         let lock_data_len = read_u32_be(&mut cursor)? as usize;
         
         // Validate lock data length is reasonable
-        if lock_data_len > 1024 * 1024 {  // 1MB max
+        if lock_data_len > utxo_format::MAX_LOCK_DATA_SIZE {
             bail!("Lock data too large: {} bytes", lock_data_len);
         }
 
@@ -320,7 +334,20 @@ impl CanonicalUTXO {
         let lock_data_padded = read_bytes(&mut cursor, lock_data_padded_len)?;
         let lock_data = lock_data_padded[..lock_data_len].to_vec();
 
-        // Verify checksum
+        // Extension sections written by newer versions; none are understood yet
+        for _ in 0..flags.count_ones() {
+            let section_len = read_u32_be(&mut cursor)? as usize;
+            let remaining = data.len() - cursor.position() as usize;
+            if section_len > remaining {
+                bail!("UTXO extension section too long: {} bytes", section_len);
+            }
+            cursor.set_position(cursor.position() + canonical_spec::align8(section_len) as u64);
+        }
+
+        // Verify checksum, which must be the final 4 bytes
+        if cursor.position() as usize + 4 != data.len() {
+            bail!("UTXO length mismatch: {} bytes, layout ends at {}", data.len(), cursor.position() + 4);
+        }
         let expected_checksum = read_u32_be(&mut cursor)?;
         let data_for_checksum = &data[..data.len() - 4]; // All except checksum
         let actual_checksum = canonical_spec::calculate_crc32(data_for_checksum);
@@ -374,7 +401,7 @@ impl CanonicalUTXO {
         }
 
         // Validate lock data size
-        if self.lock_data.len() > 1024 * 1024 {  // 1MB max
+        if self.lock_data.len() > utxo_format::MAX_LOCK_DATA_SIZE {
             bail!("Lock data too large: {} bytes", self.lock_data.len());
         }
 
@@ -407,6 +434,75 @@ impl CanonicalUTXO {
         value.push(self.lock_flags);
         value
     }
+
+    /// Export as tooling-friendly JSON
+    /// 
+    /// Hashes and byte fields become `0x`-prefixed hex and the amount a
+    /// decimal string. The binary encoding from `serialize` stays the
+    /// canonical form; JSON is only an interchange format.
+    pub fn to_json(&self) -> Result<String> {
+        let json = CanonicalUTXOJson {
+            utxo_id: hex_string(&self.utxo_id),
+            asset_id: hex_string(&self.asset_id),
+            amount: self.amount.to_string(),
+            owner_commitment: hex_string(&self.owner_commitment),
+            created_block: self.created_block,
+            lock_expiry: self.lock_expiry,
+            lock_flags: self.lock_flags,
+            lock_data: hex_string(&self.lock_data),
+        };
+        serde_json::to_string(&json).map_err(|e| anyhow!("Failed to encode UTXO JSON: {}", e))
+    }
+
+    /// Import from the JSON produced by `to_json`
+    pub fn from_json(json: &str) -> Result<Self> {
+        let json: CanonicalUTXOJson = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Invalid UTXO JSON: {}", e))?;
+        
+        let utxo = Self {
+            utxo_id: parse_hex_array(&json.utxo_id, "utxo_id")?,
+            asset_id: parse_hex_array(&json.asset_id, "asset_id")?,
+            amount: json.amount.parse()
+                .map_err(|e| anyhow!("Invalid amount '{}': {}", json.amount, e))?,
+            owner_commitment: parse_hex_array(&json.owner_commitment, "owner_commitment")?,
+            created_block: json.created_block,
+            lock_expiry: json.lock_expiry,
+            lock_flags: json.lock_flags,
+            lock_data: parse_hex(&json.lock_data, "lock_data")?,
+        };
+        
+        utxo.validate()?;
+        Ok(utxo)
+    }
+}
+
+/// JSON interchange representation of `CanonicalUTXO`
+#[derive(Serialize, Deserialize)]
+struct CanonicalUTXOJson {
+    utxo_id: String,
+    asset_id: String,
+    amount: String,
+    owner_commitment: String,
+    created_block: u64,
+    lock_expiry: u64,
+    lock_flags: u8,
+    lock_data: String,
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+fn parse_hex(value: &str, field: &str) -> Result<Vec<u8>> {
+    let digits = value.strip_prefix("0x")
+        .ok_or_else(|| anyhow!("Field {} must be 0x-prefixed hex", field))?;
+    hex::decode(digits).map_err(|e| anyhow!("Invalid hex in {}: {}", field, e))
+}
+
+fn parse_hex_array<const N: usize>(value: &str, field: &str) -> Result<[u8; N]> {
+    let bytes = parse_hex(value, field)?;
+    bytes.as_slice().try_into()
+        .map_err(|_| anyhow!("Field {} must be {} bytes, got {}", field, N, bytes.len()))
 }
 
 /// UTXO validation errors
@@ -431,10 +527,97 @@ pub enum UTXOError {
     InvalidLockFlags(u8),
 }
 
+/// Fixed-size fields of the canonical format as (name, offset, length), per `serialize`
+#[cfg(test)]
+const CANONICAL_LAYOUT: [(&str, usize, usize); 13] = [
+    ("magic", 0, 4),
+    ("version", 4, 2),
+    ("flags", 6, 2),
+    ("utxo_id", 8, 32),
+    ("asset_id", 40, 20),
+    ("reserved_1", 60, 4),
+    ("amount", 64, 16),
+    ("owner_commitment", 80, 32),
+    ("created_block", 112, 8),
+    ("lock_expiry", 120, 8),
+    ("lock_flags", 128, 1),
+    ("reserved_2", 129, 3),
+    ("lock_data_len", 132, 4),
+];
+
+#[cfg(test)]
+impl CanonicalUTXO {
+    /// Check the canonical bytes against the documented layout and against serde
+    ///
+    /// Both the canonical format and bincode must round-trip to `self`, and
+    /// every field must sit at its documented offset.
+    pub fn assert_format_parity(&self) {
+        let bytes = self.serialize().unwrap();
+        assert_eq!(Self::deserialize(&bytes).unwrap(), *self, "canonical round trip");
+        let serde_bytes = bincode::serialize(self).unwrap();
+        assert_eq!(bincode::deserialize::<Self>(&serde_bytes).unwrap(), *self, "bincode round trip");
+        
+        let field = |name: &str| {
+            let (_, offset, len) = CANONICAL_LAYOUT.iter().find(|(field, _, _)| *field == name).unwrap();
+            &bytes[*offset..offset + len]
+        };
+        assert_eq!(field("magic"), utxo_format::MAGIC.to_be_bytes());
+        assert_eq!(field("version"), utxo_format::VERSION.to_be_bytes());
+        assert_eq!(field("flags"), [0u8; 2]);
+        assert_eq!(field("utxo_id"), self.utxo_id);
+        assert_eq!(field("asset_id"), self.asset_id);
+        assert_eq!(field("reserved_1"), [0u8; 4]);
+        assert_eq!(field("amount"), self.amount.to_be_bytes());
+        assert_eq!(field("owner_commitment"), self.owner_commitment);
+        assert_eq!(field("created_block"), self.created_block.to_be_bytes());
+        assert_eq!(field("lock_expiry"), self.lock_expiry.to_be_bytes());
+        assert_eq!(field("lock_flags"), [self.lock_flags]);
+        assert_eq!(field("reserved_2"), [0u8; 3]);
+        assert_eq!(field("lock_data_len"), (self.lock_data.len() as u32).to_be_bytes());
+        
+        // Variable tail: lock data padded to 8 bytes, then the CRC32 of everything before it
+        let lock_data_start = utxo_format::MIN_SIZE - 4;
+        let checksum_start = lock_data_start + canonical_spec::align8(self.lock_data.len());
+        assert_eq!(&bytes[lock_data_start..lock_data_start + self.lock_data.len()], self.lock_data.as_slice());
+        assert!(bytes[lock_data_start + self.lock_data.len()..checksum_start].iter().all(|b| *b == 0));
+        assert_eq!(bytes.len(), checksum_start + 4);
+        assert_eq!(bytes.len(), self.serialized_size());
+        assert_eq!(bytes[checksum_start..], canonical_spec::calculate_crc32(&bytes[..checksum_start]).to_be_bytes());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_layout_table_is_contiguous() {
+        let mut next = 0;
+        for (name, offset, len) in CANONICAL_LAYOUT {
+            assert_eq!(offset, next, "{} must follow the previous field", name);
+            next = offset + len;
+        }
+        assert_eq!(next + 4, utxo_format::MIN_SIZE);
+    }
+
+    #[test]
+    fn test_format_parity_across_lock_variants() {
+        let base = CanonicalUTXO::new_eth([1u8; 32], 0, 12345, 67890, 1_000_000_000_000_000_000, [2u8; 32]);
+        let token = CanonicalUTXO::new([3u8; 32], 1, 777, 1, [0xAB; 20], u128::MAX, [4u8; 32]);
+        
+        for utxo in [
+            base.clone(),
+            token,
+            base.clone().with_timelock(u64::MAX - 1),
+            // 5 bytes exercises the 8-byte padding, 16 bytes needs none
+            base.clone().with_script(vec![0xAA; 5]),
+            base.clone().with_script(vec![0xBB; 16]),
+            base.clone().with_timelock(99_999).with_script(vec![0xCC; 13]),
+        ] {
+            utxo.assert_format_parity();
+        }
+    }
+
     #[test]
     fn test_utxo_creation() {
         let txid = [1u8; 32];
@@ -475,6 +658,33 @@ mod tests {
         assert_eq!(utxo, deserialized);
     }
 
+    #[test]
+    fn test_utxo_json_roundtrip() {
+        let utxo = CanonicalUTXO::new(
+            [1u8; 32], 0, 12345, 67890, [0xaa; 20], 1_500_000_000_000_000_000u128, [2u8; 32]
+        ).with_timelock(20_000).with_script(vec![0xde, 0xad, 0xbe, 0xef]);
+
+        let json = utxo.to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["amount"], "1500000000000000000");
+        assert_eq!(value["utxo_id"], format!("0x{}", hex::encode(utxo.utxo_id)));
+        assert_eq!(value["owner_commitment"], format!("0x{}", "02".repeat(32)));
+        assert_eq!(value["asset_id"], format!("0x{}", "aa".repeat(20)));
+        assert_eq!(value["lock_data"], "0xdeadbeef");
+        assert_eq!(value["lock_expiry"], 20_000);
+
+        let imported = CanonicalUTXO::from_json(&json).unwrap();
+        assert_eq!(imported, utxo);
+        assert_eq!(imported.serialize().unwrap(), utxo.serialize().unwrap());
+
+        // Hex fields must be 0x-prefixed and correctly sized
+        let unprefixed = json.replace("\"0x02", "\"02");
+        assert!(CanonicalUTXO::from_json(&unprefixed).is_err());
+        let truncated = json.replace(&format!("0x{}", "aa".repeat(20)), "0xaa");
+        assert!(CanonicalUTXO::from_json(&truncated).is_err());
+    }
+
     #[test]
     fn test_utxo_with_timelock() {
         let txid = [1u8; 32];
@@ -501,6 +711,22 @@ mod tests {
         assert_eq!(utxo, deserialized);
     }
 
+    #[test]
+    fn test_utxo_spendability() {
+        let utxo = CanonicalUTXO::new_eth(
+            [1u8; 32], 0, 12345, 67890, 1_000_000_000_000_000_000u128, [2u8; 32]
+        );
+        assert!(utxo.is_spendable(0));
+
+        let timelocked = utxo.clone().with_timelock(20000);
+        assert!(!timelocked.is_spendable(19999));
+        assert!(timelocked.is_spendable(20000));
+
+        let mut withdrawal_locked = utxo.clone();
+        withdrawal_locked.lock_flags |= lock_flags::WITHDRAWAL_LOCK;
+        assert!(!withdrawal_locked.is_spendable(u64::MAX));
+    }
+
     #[test]
     fn test_utxo_with_script() {
         let txid = [1u8; 32];
@@ -595,4 +821,69 @@ mod tests {
         let owner_value = utxo.owner_index_value();
         assert_eq!(owner_value.len(), 37); // 16 + 20 + 1
     }
-}
\ No newline at end of file
+
+    /// Rewrite `bytes` as a future version that appended one section per entry
+    fn with_extension_sections(bytes: &[u8], version: u16, flags: u16, sections: &[&[u8]]) -> Vec<u8> {
+        let mut blob = bytes[..bytes.len() - 4].to_vec();
+        blob[4..6].copy_from_slice(&version.to_be_bytes());
+        blob[6..8].copy_from_slice(&flags.to_be_bytes());
+        for section in sections {
+            blob.extend_from_slice(&(section.len() as u32).to_be_bytes());
+            blob.extend_from_slice(section);
+            blob.resize(blob.len() + canonical_spec::align8(section.len()) - section.len(), 0);
+        }
+        let checksum = canonical_spec::calculate_crc32(&blob);
+        blob.extend_from_slice(&checksum.to_be_bytes());
+        blob
+    }
+
+    #[test]
+    fn test_reader_skips_newer_version_extensions() {
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, 12345, 67890, 1_000_000_000_000_000_000, [2u8; 32])
+            .with_timelock(500)
+            .with_script(vec![0xAA; 5]);
+        let bytes = utxo.serialize().unwrap();
+
+        // Written by this version: no flags, no extensions
+        assert_eq!(bytes[6..8], [0u8; 2]);
+        assert_eq!(CanonicalUTXO::deserialize(&bytes).unwrap(), utxo);
+
+        // A v2 writer that set two flags and appended a 5-byte and an empty section
+        let future = with_extension_sections(
+            &bytes,
+            utxo_format::VERSION + 1,
+            0b101,
+            &[&[0xEE; 5], &[]],
+        );
+        assert_eq!(future.len(), bytes.len() + (4 + 8) + 4);
+        assert_eq!(CanonicalUTXO::deserialize(&future).unwrap(), utxo);
+
+        // Extensions are still covered by the checksum
+        let mut tampered = future.clone();
+        tampered[bytes.len()] ^= 1;
+        assert!(CanonicalUTXO::deserialize(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_reader_rejects_malformed_extensions() {
+        let utxo = CanonicalUTXO::new_eth([1u8; 32], 0, 12345, 67890, 1_000, [2u8; 32]);
+        let bytes = utxo.serialize().unwrap();
+
+        // Versions older than the compatibility floor are refused
+        let ancient = with_extension_sections(&bytes, utxo_format::MIN_COMPATIBLE - 1, 0, &[]);
+        assert!(CanonicalUTXO::deserialize(&ancient).is_err());
+
+        // A flag without its section, a section without its flag, and an overlong length
+        let missing = with_extension_sections(&bytes, 2, 0b1, &[]);
+        assert!(CanonicalUTXO::deserialize(&missing).is_err());
+        let unannounced = with_extension_sections(&bytes, 2, 0, &[&[0xEE; 8]]);
+        assert!(CanonicalUTXO::deserialize(&unannounced).is_err());
+        let mut overlong = with_extension_sections(&bytes, 2, 0b1, &[&[0xEE; 8]]);
+        let len_at = bytes.len() - 4;
+        overlong[len_at..len_at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        let checksum_at = overlong.len() - 4;
+        let checksum = canonical_spec::calculate_crc32(&overlong[..checksum_at]);
+        overlong[checksum_at..].copy_from_slice(&checksum.to_be_bytes());
+        assert!(CanonicalUTXO::deserialize(&overlong).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Coin Selection
+//!
+//! Picks the UTXOs to spend for a target amount. Largest-first fixes the
+//! smallest number of inputs that can cover the target; a bounded
+//! branch-and-bound search then looks among input sets of that size (and one
+//! larger) for an exact match or, failing that, the smallest change that is
+//! not dust.
+
+use crate::utxo::CanonicalUTXO;
+
+/// Change below this many base units is dust and avoided when possible
+pub const MIN_CHANGE_AMOUNT: u128 = 1_000_000_000_000;
+
+/// Upper bound on search nodes visited per input count
+const BNB_MAX_TRIES: usize = 100_000;
+
+/// Spendable balance does not cover the requested amount
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("InsufficientFunds: {available} spendable, {target} requested")]
+pub struct InsufficientFunds {
+    pub available: u128,
+    pub target: u128,
+}
+
+/// Select inputs covering `target`, returning them with the change amount
+///
+/// `candidates` must already be filtered down to spendable UTXOs.
+pub fn select_coins(
+    mut candidates: Vec<CanonicalUTXO>,
+    target: u128,
+) -> Result<(Vec<CanonicalUTXO>, u128), InsufficientFunds> {
+    let available = candidates.iter().fold(0u128, |total, utxo| total.saturating_add(utxo.amount));
+    if target == 0 || available < target {
+        return Err(InsufficientFunds { available, target });
+    }
+
+    // Ties broken by id so the selection is deterministic
+    candidates.sort_by(|a, b| b.amount.cmp(&a.amount).then(a.utxo_id.cmp(&b.utxo_id)));
+
+    // Largest-first: the fewest inputs any selection can use
+    let mut min_inputs = 0;
+    let mut covered = 0u128;
+    while covered < target {
+        covered += candidates[min_inputs].amount;
+        min_inputs += 1;
+    }
+
+    let mut prefix_sums = Vec::with_capacity(candidates.len() + 1);
+    prefix_sums.push(0u128);
+    for utxo in &candidates {
+        prefix_sums.push(prefix_sums[prefix_sums.len() - 1].saturating_add(utxo.amount));
+    }
+
+    let largest_first: Vec<usize> = (0..min_inputs).collect();
+    let chosen = (min_inputs..=(min_inputs + 1).min(candidates.len()))
+        .find_map(|inputs| {
+            let mut search = Search { prefix_sums: &prefix_sums, target, tries: 0, best: None };
+            search.run(0, inputs, 0, &mut Vec::with_capacity(inputs));
+            search.best.map(|(_, selection)| selection)
+        })
+        .unwrap_or(largest_first);
+
+    let change = chosen.iter().map(|&i| candidates[i].amount).sum::<u128>() - target;
+    let mut selected = Vec::with_capacity(chosen.len());
+    let mut remaining = candidates.into_iter().enumerate();
+    for i in chosen {
+        let (_, utxo) = remaining.find(|(j, _)| *j == i).expect("selection indexes are ascending");
+        selected.push(utxo);
+    }
+
+    Ok((selected, change))
+}
+
+/// Depth-first search over input sets of a fixed size
+struct Search<'a> {
+    /// Prefix sums of the candidate amounts, sorted descending
+    prefix_sums: &'a [u128],
+    target: u128,
+    tries: usize,
+    /// Smallest acceptable change found so far with its input indexes
+    best: Option<(u128, Vec<usize>)>,
+}
+
+impl Search<'_> {
+    fn amount(&self, index: usize) -> u128 {
+        self.prefix_sums[index + 1] - self.prefix_sums[index]
+    }
+
+    fn run(&mut self, start: usize, inputs: usize, sum: u128, chosen: &mut Vec<usize>) {
+        if self.tries >= BNB_MAX_TRIES || matches!(self.best, Some((0, _))) {
+            return;
+        }
+        self.tries += 1;
+
+        if chosen.len() == inputs {
+            if sum < self.target {
+                return;
+            }
+            let change = sum - self.target;
+            let acceptable = change == 0 || change >= MIN_CHANGE_AMOUNT;
+            if acceptable && self.best.as_ref().is_none_or(|(best, _)| change < *best) {
+                self.best = Some((change, chosen.clone()));
+            }
+            return;
+        }
+
+        let slots = inputs - chosen.len();
+        for index in start..=(self.prefix_sums.len() - 1).saturating_sub(slots) {
+            // The largest remaining amounts are next, so nothing later can reach the target
+            if sum + self.prefix_sums[index + slots] - self.prefix_sums[index] < self.target {
+                return;
+            }
+            // Amounts only grow the sum, so this branch cannot beat the best change
+            let next = sum + self.amount(index);
+            if let Some((best, _)) = &self.best {
+                if next > self.target.saturating_add(*best) {
+                    continue;
+                }
+            }
+
+            chosen.push(index);
+            self.run(index + 1, inputs, next, chosen);
+            chosen.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNIT: u128 = MIN_CHANGE_AMOUNT * 10;
+
+    fn utxos(amounts: &[u128]) -> Vec<CanonicalUTXO> {
+        amounts.iter().enumerate()
+            .map(|(i, amount)| CanonicalUTXO::new_eth([i as u8; 32], 0, 1, i as u64, *amount, [0x42u8; 32]))
+            .collect()
+    }
+
+    fn amounts(selected: &[CanonicalUTXO]) -> Vec<u128> {
+        let mut amounts: Vec<u128> = selected.iter().map(|utxo| utxo.amount).collect();
+        amounts.sort();
+        amounts
+    }
+
+    #[test]
+    fn test_exact_match_has_no_change() {
+        // Largest-first would take 6 + 4; the search finds 4 + 3
+        let (selected, change) = select_coins(utxos(&[6 * UNIT, 4 * UNIT, 3 * UNIT]), 7 * UNIT).unwrap();
+        assert_eq!(amounts(&selected), vec![3 * UNIT, 4 * UNIT]);
+        assert_eq!(change, 0);
+    }
+
+    #[test]
+    fn test_overshoot_returns_change() {
+        let (selected, change) = select_coins(utxos(&[6 * UNIT, 4 * UNIT, 3 * UNIT]), 8 * UNIT).unwrap();
+        assert_eq!(amounts(&selected), vec![3 * UNIT, 6 * UNIT]);
+        assert_eq!(change, UNIT);
+    }
+
+    #[test]
+    fn test_dust_change_avoided() {
+        // A single 5 UNIT input leaves dust; two inputs leave usable change
+        let target = 5 * UNIT - 1;
+        let (selected, change) = select_coins(utxos(&[5 * UNIT, 2 * UNIT, 2 * UNIT]), target).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(change, 7 * UNIT - target);
+        assert!(change >= MIN_CHANGE_AMOUNT);
+    }
+
+    #[test]
+    fn test_insufficient_funds() {
+        let err = select_coins(utxos(&[2 * UNIT, UNIT]), 4 * UNIT).unwrap_err();
+        assert_eq!(err, InsufficientFunds { available: 3 * UNIT, target: 4 * UNIT });
+    }
+}
@@ -121,7 +121,7 @@ impl PrivacyPoolContract {
         let mut events = Vec::new();
         for event in blockchain_events {
             events.push(DepositEvent {
-                depositor: event.depositor.to_string(),
+                depositor: format!("{:?}", event.depositor),
                 commitment: format!("0x{:x}", event.commitment),
                 label: event.label.as_u64(),
                 value: event.value.as_u64(),
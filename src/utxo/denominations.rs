@@ -0,0 +1,115 @@
+//! Deposit Denominations
+//!
+//! Deposits are broken into fixed denominations so that UTXOs of the same
+//! size are indistinguishable and mix in one anonymity set. Whatever does not
+//! fit a denomination becomes a single remainder UTXO.
+
+/// 0.1 ETH in wei
+pub const DENOMINATION_0_1_ETH: u64 = 100_000_000_000_000_000;
+/// 1 ETH in wei
+pub const DENOMINATION_1_ETH: u64 = 1_000_000_000_000_000_000;
+/// 10 ETH in wei
+pub const DENOMINATION_10_ETH: u64 = 10_000_000_000_000_000_000;
+
+/// Fixed denominations a deposit is split into
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenominationPolicy {
+    /// Distinct non-zero denominations, largest first
+    denominations: Vec<u64>,
+}
+
+impl Default for DenominationPolicy {
+    /// Powers of ten: 10, 1 and 0.1 ETH
+    fn default() -> Self {
+        Self::new(vec![DENOMINATION_10_ETH, DENOMINATION_1_ETH, DENOMINATION_0_1_ETH])
+    }
+}
+
+impl DenominationPolicy {
+    /// Policy over `denominations`, in any order; zeros and duplicates are dropped
+    pub fn new(mut denominations: Vec<u64>) -> Self {
+        denominations.retain(|&denomination| denomination > 0);
+        denominations.sort_unstable_by(|a, b| b.cmp(a));
+        denominations.dedup();
+        Self { denominations }
+    }
+
+    /// Configured denominations, largest first
+    pub fn denominations(&self) -> &[u64] {
+        &self.denominations
+    }
+
+    /// Smallest configured denomination
+    pub fn min_denomination(&self) -> Option<u64> {
+        self.denominations.last().copied()
+    }
+
+    /// Greedily decompose `amount_wei` into denominations, largest first
+    ///
+    /// Any remainder below the smallest denomination is appended as one final
+    /// amount, so the parts always sum to `amount_wei`. An amount smaller than
+    /// every denomination stays a single part, and zero yields no parts.
+    pub fn split(&self, amount_wei: u64) -> Vec<u64> {
+        let mut parts = Vec::new();
+        let mut remaining = amount_wei;
+
+        for &denomination in &self.denominations {
+            let count = remaining / denomination;
+            parts.extend(std::iter::repeat(denomination).take(count as usize));
+            remaining -= count * denomination;
+        }
+
+        if remaining > 0 {
+            parts.push(remaining);
+        }
+        parts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(parts: &[u64], denomination: u64) -> usize {
+        parts.iter().filter(|&&part| part == denomination).count()
+    }
+
+    #[test]
+    fn test_split_uses_expected_denominations() {
+        let policy = DenominationPolicy::default();
+
+        // 23.4 ETH = 2 x 10 + 3 x 1 + 4 x 0.1
+        let amount = 23 * DENOMINATION_1_ETH + 4 * DENOMINATION_0_1_ETH;
+        let parts = policy.split(amount);
+        assert_eq!(parts.iter().sum::<u64>(), amount);
+        assert_eq!(parts.len(), 9);
+        assert_eq!(count(&parts, DENOMINATION_10_ETH), 2);
+        assert_eq!(count(&parts, DENOMINATION_1_ETH), 3);
+        assert_eq!(count(&parts, DENOMINATION_0_1_ETH), 4);
+        assert!(parts.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn test_split_edge_cases() {
+        let policy = DenominationPolicy::default();
+
+        // Below the smallest denomination: one UTXO
+        assert_eq!(policy.split(DENOMINATION_0_1_ETH - 1), vec![DENOMINATION_0_1_ETH - 1]);
+        assert!(policy.split(0).is_empty());
+
+        // Dust left over after the denominations becomes one remainder
+        let parts = policy.split(DENOMINATION_1_ETH + 12_345);
+        assert_eq!(parts, vec![DENOMINATION_1_ETH, 12_345]);
+
+        // The largest representable amount still sums back exactly
+        let parts = policy.split(u64::MAX);
+        assert_eq!(parts.iter().fold(0u64, |total, part| total.checked_add(*part).unwrap()), u64::MAX);
+        assert_eq!(count(&parts, DENOMINATION_10_ETH), 1);
+
+        // Order, zeros and duplicates in the configuration do not matter
+        let policy = DenominationPolicy::new(vec![5, 0, 20, 5]);
+        assert_eq!(policy.denominations(), &[20, 5]);
+        assert_eq!(policy.min_denomination(), Some(5));
+        assert_eq!(policy.split(47), vec![20, 20, 5, 2]);
+    }
+}
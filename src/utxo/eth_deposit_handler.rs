@@ -205,6 +205,10 @@ pub enum DepositError {
     InvalidBlock,
     VerificationFailed,
     InsufficientFunds,
+    /// Commitment already used by an earlier deposit in the batch or in the pool
+    DuplicateCommitment,
+    /// Deposit could not be converted into a UTXO
+    InvalidUTXO(String),
 }
 
 impl std::fmt::Display for DepositError {
@@ -215,6 +219,8 @@ impl std::fmt::Display for DepositError {
             DepositError::InvalidBlock => write!(f, "Invalid block number"),
             DepositError::VerificationFailed => write!(f, "Deposit verification failed"),
             DepositError::InsufficientFunds => write!(f, "Insufficient funds"),
+            DepositError::DuplicateCommitment => write!(f, "Duplicate deposit commitment"),
+            DepositError::InvalidUTXO(msg) => write!(f, "Invalid deposit UTXO: {}", msg),
         }
     }
 }
@@ -4,16 +4,24 @@
 use std::collections::{HashMap, HashSet};
 use serde::{Serialize, Deserialize};
 
-/// UTXO identifier combining transaction hash and output index
+/// UTXO identifier combining transaction hash, output index and split index
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct UTXOId {
     pub tx_hash: [u8; 32],
     pub output_index: u32,
+    /// Position among the denomination splits of one output
+    #[serde(default)]
+    pub split_index: u32,
 }
 
 impl UTXOId {
     pub fn new(tx_hash: [u8; 32], output_index: u32) -> Self {
-        Self { tx_hash, output_index }
+        Self::with_split(tx_hash, output_index, 0)
+    }
+
+    /// Identify one denomination split of an output
+    pub fn with_split(tx_hash: [u8; 32], output_index: u32, split_index: u32) -> Self {
+        Self { tx_hash, output_index, split_index }
     }
 }
 
@@ -9,13 +9,19 @@ pub mod converter;
 pub mod eth_deposit_handler;
 pub mod transaction;
 pub mod note;
+pub mod randomness_beacon;
+pub mod coin_selection;
+pub mod denominations;
 
 // Re-export main types
-pub use utxo::{UTXO, UTXOTransaction, User, UTXOInput, UTXOOutput, TransactionType};
+pub use utxo::{UTXO, UTXOTransaction, User, UTXOInput, UTXOOutput, TransactionType, TxStructureViolation};
 pub use canonical_utxo::{CanonicalUTXO, lock_flags, UTXOError};
-pub use utxo_manager::{UTXOManager, UTXOOperationResult, DepositResult};
-pub use transaction::{TransactionResult, Error, MerkleProof};
+pub use utxo_manager::{UTXOManager, UTXOOperationResult, DepositResult, CommitmentCollision, ReorgTooDeep};
+pub use transaction::{TransactionResult, Error, MerkleProof, CompressedMerkleProof};
 pub use indexing::{UTXOIndex, IndexedUTXO, UTXOId, UTXOQueryBuilder};
 pub use converter::{ETHToUTXOConverter, SecureCommitment, Nullifier, CryptoUtils};
+pub use randomness_beacon::RandomnessBeacon;
+pub use coin_selection::InsufficientFunds;
+pub use denominations::DenominationPolicy;
 pub use eth_deposit_handler::{ETHDepositHandler, ETHDepositEvent, DepositProof, DepositError};
 pub use crate::relayer::DepositEvent;
\ No newline at end of file
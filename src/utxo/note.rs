@@ -316,6 +316,10 @@ pub struct EncryptedNote {
     /// Optional commitment for relayer matching
     #[serde_as(as = "Option<Bytes>")]
     pub commitment: Option<[u8; 32]>,
+    
+    /// Unencrypted bytes derived from the ECDH shared secret for fast scanning
+    #[serde(default)]
+    pub view_tag: Option<[u8; 8]>,
 }
 
 impl EncryptedNote {
@@ -331,9 +335,16 @@ impl EncryptedNote {
             nonce,
             ciphertext,
             commitment,
+            view_tag: None,
         }
     }
     
+    /// Attach the view tag scanners check before decrypting
+    pub fn with_view_tag(mut self, view_tag: [u8; 8]) -> Self {
+        self.view_tag = Some(view_tag);
+        self
+    }
+    
     /// Serialize to JSON
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
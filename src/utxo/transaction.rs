@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::utxo::TxStructureViolation;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransactionResult {
@@ -15,6 +16,18 @@ impl TransactionResult {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Error {
     DoubleSpend,
+    /// The same nullifier is used by two inputs of one transaction
+    DuplicateNullifierInTx([u8; 32]),
+    /// The transaction at this index of a block carries an invalid signature
+    InvalidSignatureInBlock(usize),
+    /// An output commitment is already a leaf of the tree
+    CommitmentCollision([u8; 32]),
+    /// The input/output counts do not fit the transaction type
+    InvalidTxStructure(TxStructureViolation),
+    /// An input note does not open to a leaf of the pool tree
+    UnknownInput([u8; 32]),
+    /// An input's nullifier is not the one derived from its note
+    NullifierMismatch([u8; 32]),
     InvalidMerkleProof,
     InsufficientBalance,
     InvalidTransaction,
@@ -22,6 +35,10 @@ pub enum Error {
     InvalidUTXO,
     UserNotFound,
     InvalidSignature,
+    DuplicateTransaction {
+        txid: [u8; 32],
+        prior_result: TransactionResult,
+    },
     Other(String),
 }
 
@@ -44,6 +61,25 @@ pub struct MerkleProof {
     pub leaf_index: u64,
 }
 
+/// Merkle proof with empty-subtree siblings replaced by one bit each
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressedMerkleProof {
+    /// Number of siblings in the full proof
+    pub depth: u32,
+    /// Packed bits, set where that level's sibling is the empty-subtree hash
+    pub empty_mask: Vec<u8>,
+    /// Non-empty siblings, lowest level first
+    pub siblings: Vec<[u8; 32]>,
+    /// Number of path levels (0 for sorted-pair proofs)
+    pub path_len: u32,
+    /// Packed path bits
+    pub path: Vec<u8>,
+    /// Root hash
+    pub root: [u8; 32],
+    /// Leaf index
+    pub leaf_index: u64,
+}
+
 impl MerkleProof {
     /// Create a new Merkle proof
     pub fn new(siblings: Vec<[u8; 32]>, path: Vec<u32>, root: [u8; 32], leaf_index: u64) -> Self {
@@ -59,31 +95,89 @@ impl MerkleProof {
     pub fn verify(&self, leaf: [u8; 32]) -> bool {
         use crate::crypto::merkle_proofs::MerkleProofVerifier;
         use crate::crypto::merkle_proofs::HashFunction;
-        use crate::crypto::CryptoContext;
+        use crate::crypto::{CryptoContext, PathBits};
         
         let context = CryptoContext::merkle_context();
         let verifier = MerkleProofVerifier::with_context(HashFunction::Blake2b256, self.siblings.len(), &context);
         
         verifier.verify_proof(self, &leaf).unwrap_or_else(|_| {
             // Fallback to simple verification
+            let Ok(path) = PathBits::try_from(self.path.as_slice()) else {
+                return false;
+            };
             let mut current = leaf;
-            let mut path_index = 0;
 
-            for sibling in &self.siblings {
-                if path_index < self.path.len() && self.path[path_index] == 1 {
+            for (level, sibling) in self.siblings.iter().enumerate() {
+                if path.is_right(level) {
                     // Right child
                     current = Self::hash_children(*sibling, current);
                 } else {
                     // Left child
                     current = Self::hash_children(current, *sibling);
                 }
-                path_index += 1;
             }
 
             current == self.root
         })
     }
 
+    /// Drop siblings that equal the empty-subtree hash of their level
+    ///
+    /// `empty_hashes[level]` must be the hash of an empty subtree at that
+    /// level, as precomputed by the tree that produced the proof. Each
+    /// dropped sibling costs one bit instead of 32 bytes; the path is packed
+    /// to one bit per level as well.
+    pub fn compress(&self, empty_hashes: &[[u8; 32]]) -> Result<CompressedMerkleProof, Error> {
+        use crate::crypto::PathBits;
+        
+        let path = PathBits::try_from(self.path.as_slice()).map_err(|_| Error::InvalidMerkleProof)?;
+        let mut empty_levels = Vec::with_capacity(self.siblings.len());
+        let mut siblings = Vec::new();
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            let is_empty = empty_hashes.get(level) == Some(sibling);
+            if !is_empty {
+                siblings.push(*sibling);
+            }
+            empty_levels.push(is_empty);
+        }
+        
+        Ok(CompressedMerkleProof {
+            depth: self.siblings.len() as u32,
+            empty_mask: PathBits::from(empty_levels).to_packed(),
+            siblings,
+            path_len: path.len() as u32,
+            path: path.to_packed(),
+            root: self.root,
+            leaf_index: self.leaf_index,
+        })
+    }
+
+    /// Rebuild the full proof from `compressed` and the same empty table
+    pub fn decompress(compressed: &CompressedMerkleProof, empty_hashes: &[[u8; 32]]) -> Result<Self, Error> {
+        use crate::crypto::PathBits;
+        
+        let empty_levels = PathBits::from_packed(&compressed.empty_mask, compressed.depth as usize)
+            .map_err(|_| Error::InvalidMerkleProof)?;
+        let path = PathBits::from_packed(&compressed.path, compressed.path_len as usize)
+            .map_err(|_| Error::InvalidMerkleProof)?;
+        
+        let mut present = compressed.siblings.iter();
+        let mut siblings = Vec::with_capacity(empty_levels.len());
+        for (level, is_empty) in empty_levels.iter().enumerate() {
+            let sibling = if is_empty {
+                empty_hashes.get(level)
+            } else {
+                present.next()
+            };
+            siblings.push(*sibling.ok_or(Error::InvalidMerkleProof)?);
+        }
+        if present.next().is_some() {
+            return Err(Error::InvalidMerkleProof);
+        }
+        
+        Ok(Self::new(siblings, path.to_u32s(), compressed.root, compressed.leaf_index))
+    }
+
     /// Hash two children nodes
     fn hash_children(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
         use crate::crypto::poseidon::PoseidonHasher;
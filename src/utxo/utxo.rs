@@ -3,6 +3,18 @@
 
 use serde::{Serialize, Deserialize};
 use crate::utxo::transaction::MerkleProof;
+use crate::crypto::HashPolicy;
+
+/// Nullifier format of UTXOs created before nullifiers followed `HashPolicy`:
+/// generic Poseidon over the commitment and a zero index
+pub const LEGACY_NULLIFIER_FORMAT: u8 = 1;
+
+/// Current nullifier format: `HashPolicy::nullifier` at the UTXO's index
+pub const NULLIFIER_FORMAT_VERSION: u8 = 2;
+
+fn legacy_nullifier_format() -> u8 {
+    LEGACY_NULLIFIER_FORMAT
+}
 
 /// Core UTXO structure for the privacy pool
 /// Based on Zcash Sapling note format with privacy enhancements
@@ -22,6 +34,10 @@ pub struct UTXO {
     pub commitment: [u8; 32],
     /// Index in Merkle tree
     pub index: u64,
+    /// Nullifier derivation format; UTXOs stored without it keep the legacy
+    /// derivation so the nullifiers already recorded for them still match
+    #[serde(default = "legacy_nullifier_format")]
+    pub nullifier_format: u8,
 }
 
 impl UTXO {
@@ -43,34 +59,34 @@ impl UTXO {
             nullifier_seed,
             commitment,
             index,
+            nullifier_format: NULLIFIER_FORMAT_VERSION,
         }
     }
 
-    /// Generate nullifier for this UTXO
+    /// Generate nullifier for this UTXO under the default hash policy
     pub fn generate_nullifier(&self) -> [u8; 32] {
-        use crate::crypto::nullifiers::NullifierGenerator;
-        use crate::crypto::nullifiers::NullifierHashFunction;
-        use crate::crypto::CryptoContext;
-        
-        let context = CryptoContext::nullifier_context();
-        let generator = NullifierGenerator::new(context, NullifierHashFunction::Blake2b256);
-        
-        // Create nullifier seed
-        let mut seed = Vec::new();
-        seed.extend_from_slice(&self.secret);
-        seed.extend_from_slice(&self.nullifier_seed);
-        seed.extend_from_slice(&self.commitment);
-        
-        // Hash the seed
-        // Generate nullifier using Poseidon hash
-        let mut input = Vec::new();
-        input.extend_from_slice(&self.commitment);
-        input.extend_from_slice(&[0u8; 8]); // utxo_index as 8 bytes
-        crate::crypto::poseidon::PoseidonHash::new().hash(&input).unwrap_or_else(|_| {
-            // Fallback to SHA-256 if Poseidon fails
+        self.generate_nullifier_with(HashPolicy::default())
+    }
+
+    /// Generate nullifier using the same hash policy as the commitment
+    ///
+    /// Legacy-format UTXOs ignore `policy` and keep their original nullifier.
+    pub fn generate_nullifier_with(&self, policy: HashPolicy) -> [u8; 32] {
+        let nullifier = if self.nullifier_format == LEGACY_NULLIFIER_FORMAT {
+            let mut input = Vec::with_capacity(40);
+            input.extend_from_slice(&self.commitment);
+            input.extend_from_slice(&[0u8; 8]);
+            crate::crypto::poseidon::PoseidonHash::new().hash(&input)
+        } else {
+            policy.nullifier(&self.commitment, self.index)
+        };
+        nullifier.unwrap_or_else(|_| {
+            // Fallback to SHA-256 if the policy hash fails
             use sha2::{Sha256, Digest};
             let mut hasher = Sha256::new();
-            hasher.update(&seed);
+            hasher.update(&self.secret);
+            hasher.update(&self.nullifier_seed);
+            hasher.update(&self.commitment);
             hasher.finalize().into()
         })
     }
@@ -80,15 +96,14 @@ impl UTXO {
         self.generate_nullifier() == nullifier
     }
 
-    /// Compute commitment hash
+    /// Compute commitment hash under the default hash policy
     pub fn compute_commitment(&self) -> [u8; 32] {
-        use crate::crypto::poseidon::PoseidonHasher;
-        use crate::crypto::CryptoContext;
-        
-        let context = CryptoContext::utxo_context();
-        
-        // Use Poseidon hash for commitment
-        PoseidonHasher::utxo_commitment(
+        self.compute_commitment_with(HashPolicy::default())
+    }
+
+    /// Compute commitment hash using the given hash policy
+    pub fn compute_commitment_with(&self, policy: HashPolicy) -> [u8; 32] {
+        policy.utxo_commitment(
             self.value,
             &self.owner,
             &self.blinding_factor,
@@ -188,6 +203,19 @@ pub enum TransactionType {
     Transfer,
 }
 
+/// Input/output shape a transaction violates for its type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TxStructureViolation {
+    /// Deposits bring value from outside the pool and spend nothing
+    DepositHasInputs(usize),
+    DepositWithoutOutputs,
+    WithdrawalWithoutInputs,
+    /// Withdrawals may create at most one change output
+    WithdrawalTooManyOutputs(usize),
+    TransferWithoutInputs,
+    TransferWithoutOutputs,
+}
+
 /// UTXO Transaction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UTXOTransaction {
@@ -229,6 +257,23 @@ impl UTXOTransaction {
         }
     }
 
+    /// Check the input/output counts required by the transaction type
+    ///
+    /// Deposits have no inputs and at least one output, withdrawals have at
+    /// least one input and at most one (change) output, transfers have both.
+    pub fn validate_structure(&self) -> Result<(), TxStructureViolation> {
+        let (inputs, outputs) = (self.inputs.len(), self.outputs.len());
+        match self.tx_type {
+            TransactionType::Deposit if inputs > 0 => Err(TxStructureViolation::DepositHasInputs(inputs)),
+            TransactionType::Deposit if outputs == 0 => Err(TxStructureViolation::DepositWithoutOutputs),
+            TransactionType::Withdrawal if inputs == 0 => Err(TxStructureViolation::WithdrawalWithoutInputs),
+            TransactionType::Withdrawal if outputs > 1 => Err(TxStructureViolation::WithdrawalTooManyOutputs(outputs)),
+            TransactionType::Transfer if inputs == 0 => Err(TxStructureViolation::TransferWithoutInputs),
+            TransactionType::Transfer if outputs == 0 => Err(TxStructureViolation::TransferWithoutOutputs),
+            _ => Ok(()),
+        }
+    }
+
     /// Compute transaction hash
     fn compute_hash(
         tx_type: &TransactionType,
@@ -259,23 +304,84 @@ impl UTXOTransaction {
         hasher.finalize().into()
     }
 
-    /// Verify transaction signature
+    /// Encode the transaction in its canonical, version-tagged byte layout
+    ///
+    /// Hand-written and independent of serde/bincode so signatures and txids
+    /// stay stable across dependency upgrades. Layout (all integers BE):
+    /// magic(4) || version(2) || tx_type(1) ||
+    /// input_count(4) || [nullifier(32) || commitment(32) || value(8)]* ||
+    /// output_count(4) || [commitment(32) || recipient(32) || value(8)]* ||
+    /// fee(8) || public_key(32)
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        use crate::canonical_spec::tx_format;
+        
+        let mut bytes = Vec::with_capacity(
+            4 + 2 + 1 + 4 + self.inputs.len() * 72 + 4 + self.outputs.len() * 72 + 8 + 32
+        );
+        
+        bytes.extend_from_slice(&tx_format::MAGIC.to_be_bytes());
+        bytes.extend_from_slice(&tx_format::VERSION.to_be_bytes());
+        bytes.push(self.tx_type.clone() as u8);
+        
+        bytes.extend_from_slice(&(self.inputs.len() as u32).to_be_bytes());
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.nullifier);
+            bytes.extend_from_slice(&input.utxo.commitment);
+            bytes.extend_from_slice(&input.utxo.value.to_be_bytes());
+        }
+        
+        bytes.extend_from_slice(&(self.outputs.len() as u32).to_be_bytes());
+        for output in &self.outputs {
+            bytes.extend_from_slice(&output.commitment);
+            bytes.extend_from_slice(&output.recipient);
+            bytes.extend_from_slice(&output.value.to_be_bytes());
+        }
+        
+        bytes.extend_from_slice(&self.fee.to_be_bytes());
+        bytes.extend_from_slice(&self.public_key);
+        bytes
+    }
+
+    /// Message signed by the transaction author (hash of the canonical bytes)
+    pub fn signing_message(&self) -> [u8; 32] {
+        use sha3::{Keccak256, Digest};
+        let mut hasher = Keccak256::new();
+        hasher.update(&crate::canonical_spec::domains::TRANSACTION_SIGNATURE);
+        hasher.update(&self.canonical_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Compute the canonical transaction ID used for replay protection
+    ///
+    /// Derived from the canonical bytes, so resubmitting the same state
+    /// transition always yields the same txid.
+    pub fn compute_txid(&self) -> [u8; 32] {
+        use sha3::{Keccak256, Digest};
+        let mut hasher = Keccak256::new();
+        hasher.update(&crate::canonical_spec::domains::TRANSACTION_ID);
+        hasher.update(&self.canonical_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Verify transaction signature over the canonical signing message
     pub fn verify_signature(&self) -> bool {
         use crate::crypto::signatures::{Ed25519Sig, EcdsaSig};
         
+        let message = self.signing_message();
+        
         // Try Ed25519 verification
-        if let Ok(signature_bytes) = <[u8; 96]>::try_from(&self.signature[..96]) {
+        if let Some(Ok(signature_bytes)) = self.signature.get(..96).map(<[u8; 96]>::try_from) {
             if let Ok(ed25519_sig) = Ed25519Sig::from_bytes(&signature_bytes) {
-                if ed25519_sig.verify(&self.tx_hash).unwrap_or(false) {
+                if ed25519_sig.verify(&message).unwrap_or(false) {
                     return true;
                 }
             }
         }
         
         // Try ECDSA verification
-        if let Ok(signature_bytes) = <[u8; 97]>::try_from(&self.signature[..97]) {
+        if let Some(Ok(signature_bytes)) = self.signature.get(..97).map(<[u8; 97]>::try_from) {
             if let Ok(ecdsa_sig) = EcdsaSig::from_bytes(&signature_bytes) {
-                if ecdsa_sig.verify(&self.tx_hash).unwrap_or(false) {
+                if ecdsa_sig.verify(&message).unwrap_or(false) {
                     return true;
                 }
             }
@@ -328,6 +434,67 @@ mod tests {
         assert_eq!(utxo.owner, [0x43u8; 32]);
     }
 
+    #[test]
+    fn test_poseidon_policy_shared_by_commitment_and_nullifier() {
+        use crate::crypto::{CryptoContext, NullifierGenerator, PoseidonHasher};
+
+        let utxo = UTXO::new(
+            1_000_000_000_000_000_000u64,
+            [0x42u8; 32],
+            [0x43u8; 32],
+            [0x44u8; 32],
+            [0x45u8; 32],
+            [0x46u8; 32],
+            7,
+        );
+        let policy = HashPolicy::Poseidon;
+
+        // Both values are the Poseidon outputs a circuit would recompute
+        let commitment = utxo.compute_commitment_with(policy);
+        let nullifier = utxo.generate_nullifier_with(policy);
+        assert_eq!(commitment, PoseidonHasher::utxo_commitment(utxo.value, &utxo.owner, &utxo.blinding_factor).unwrap());
+        assert_eq!(nullifier, PoseidonHasher::nullifier(&utxo.commitment, utxo.index).unwrap());
+
+        // Poseidon is the default policy
+        assert_eq!(utxo.compute_commitment(), commitment);
+        assert!(utxo.verify_nullifier(nullifier));
+
+        // A different policy changes both values together
+        assert_ne!(utxo.compute_commitment_with(HashPolicy::Blake2b256), commitment);
+        assert_ne!(utxo.generate_nullifier_with(HashPolicy::Blake2b256), nullifier);
+
+        // The nullifier generator follows the same policy instead of a Blake2b default
+        let seed_hash = |policy: HashPolicy| {
+            NullifierGenerator::new(CryptoContext::nullifier_context(), policy)
+                .generate_nullifier(&utxo.commitment, &[0x11u8; 32], utxo.index)
+                .unwrap()
+                .value
+        };
+        assert_ne!(seed_hash(HashPolicy::Poseidon), seed_hash(HashPolicy::Blake2b256));
+    }
+
+    #[test]
+    fn test_stored_utxo_keeps_legacy_nullifier() {
+        let utxo = UTXO::new(1_000, [0x42u8; 32], [0x43u8; 32], [0x44u8; 32], [0x45u8; 32], [0x46u8; 32], 7);
+        
+        // A UTXO serialized before the format field existed
+        let mut stored = serde_json::to_value(&utxo).unwrap();
+        stored.as_object_mut().unwrap().remove("nullifier_format");
+        let legacy: UTXO = serde_json::from_value(stored).unwrap();
+        assert_eq!(legacy.nullifier_format, LEGACY_NULLIFIER_FORMAT);
+        
+        // Its nullifier is the one recorded when it was spent, under any policy
+        let mut input = utxo.commitment.to_vec();
+        input.extend_from_slice(&[0u8; 8]);
+        let recorded = crate::crypto::poseidon::PoseidonHash::new().hash(&input).unwrap();
+        assert_eq!(legacy.generate_nullifier(), recorded);
+        assert_eq!(legacy.generate_nullifier_with(HashPolicy::Blake2b256), recorded);
+        
+        // New UTXOs use the shared policy
+        assert_eq!(utxo.nullifier_format, NULLIFIER_FORMAT_VERSION);
+        assert_ne!(utxo.generate_nullifier(), recorded);
+    }
+
     #[test]
     fn test_utxo_nullifier_generation() {
         let utxo = UTXO::new(
@@ -402,4 +569,64 @@ mod tests {
         assert_eq!(tx.outputs.len(), 1);
         assert!(tx.verify_balance());
     }
+
+    fn sample_transaction() -> UTXOTransaction {
+        let utxo = UTXO::new(
+            1000u64,
+            [0x10u8; 32],
+            [0x11u8; 32],
+            [0x12u8; 32],
+            [0x13u8; 32],
+            [0x14u8; 32],
+            0,
+        );
+
+        let input = UTXOInput {
+            utxo,
+            merkle_proof: MerkleProof::new(vec![[0u8; 32]], vec![0], [0u8; 32], 0),
+            nullifier: [0x40u8; 32],
+        };
+
+        let output = UTXOOutput {
+            value: 990u64,
+            recipient: [0x50u8; 32],
+            commitment: [0x51u8; 32],
+            blinding_factor: [0x52u8; 32],
+        };
+
+        UTXOTransaction::new(
+            TransactionType::Transfer,
+            vec![input],
+            vec![output],
+            10u64,
+            vec![0u8; 64],
+            [0x43u8; 32],
+        )
+    }
+
+    #[test]
+    fn test_canonical_bytes_stable_and_signed() {
+        use sha3::{Keccak256, Digest};
+
+        let tx1 = sample_transaction();
+        let tx2 = sample_transaction();
+
+        let bytes = tx1.canonical_bytes();
+        assert_eq!(bytes, tx2.canonical_bytes());
+        assert_eq!(bytes.len(), 4 + 2 + 1 + 4 + 72 + 4 + 72 + 8 + 32);
+        assert_eq!(&bytes[..4], b"UTXT");
+        assert_eq!(&bytes[4..6], &crate::canonical_spec::tx_format::VERSION.to_be_bytes());
+
+        // Signature bytes are not part of the signed payload
+        let mut resigned = sample_transaction();
+        resigned.signature = vec![0xFFu8; 64];
+        assert_eq!(resigned.canonical_bytes(), bytes);
+
+        let mut hasher = Keccak256::new();
+        hasher.update(&crate::canonical_spec::domains::TRANSACTION_SIGNATURE);
+        hasher.update(&bytes);
+        let expected: [u8; 32] = hasher.finalize().into();
+        assert_eq!(tx1.signing_message(), expected);
+        assert_eq!(tx1.signing_message(), tx2.signing_message());
+    }
 }
\ No newline at end of file
@@ -4,11 +4,36 @@
 //! integrated with the canonical SMT tree operations.
 
 use anyhow::{Result, anyhow, Context};
-use crate::database::schema::DatabaseManager;
-use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation};
-use crate::utxo::CanonicalUTXO;
-use crate::merkle::CanonicalSMT;
+use crate::database::schema::{DatabaseManager, cf_names};
+use crate::database::batch_writer::{AtomicBatchWriter, BatchOperation, BlockOperationRecord, block_operation_types};
+use crate::database::batch_pipeline::BatchPipeline;
+use crate::database::root_history::RootHistory;
+use crate::database::pool_counters::PoolCounters;
+use crate::crypto::{ArchitectureCompliantCrypto, OperatorKeypair, SignatureAlgorithm};
+use crate::utxo::{coin_selection, CanonicalUTXO, DepositError, RandomnessBeacon};
+use crate::merkle::{CanonicalSMT, NullifierTree, NullifierProof};
 use crate::relayer::DepositEvent;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// cf_tree_metadata key holding the operator public key (algorithm tag || key bytes)
+pub const OPERATOR_PUBKEY_KEY: &[u8] = b"operator_pubkey";
+
+/// cf_tree_metadata key holding the operator signing key (algorithm tag || secret key)
+///
+/// The secret is stored unencrypted, so anyone who can read the database can
+/// sign roots as the operator; restrict access to the database directory.
+pub const OPERATOR_SECRET_KEY: &[u8] = b"operator_secret";
+
+/// cf_tree_metadata key holding the randomness beacon seed
+pub const BEACON_SEED_KEY: &[u8] = b"randomness_beacon_seed";
+
+/// cf_tree_metadata key holding the last beacon index used (u64 big-endian)
+pub const BEACON_INDEX_KEY: &[u8] = b"randomness_beacon_index";
+
+/// Domain separator for operator root signatures
+const ROOT_SIGNATURE_DOMAIN: &[u8] = b"OPERATOR_SIGNATURE";
 
 /// Comprehensive UTXO manager with SMT integration
 pub struct UTXOManager {
@@ -18,8 +43,59 @@ pub struct UTXOManager {
     /// Canonical SMT tree
     smt: CanonicalSMT,
     
-    /// Current operator entropy for UTXO ID generation
+    /// Index of the last beacon value used for UTXO ID generation
     operator_entropy_counter: u64,
+    
+    /// Committed entropy source for UTXO ID generation
+    beacon: RandomnessBeacon,
+    
+    /// Operator keypair used to sign committed roots
+    operator_keypair: OperatorKeypair,
+    
+    /// Spent-nullifier tree for succinct double-spend proofs
+    nullifier_tree: NullifierTree,
+    
+    /// In-memory membership indexes, populated by `warm_caches`
+    membership_cache: Option<MembershipCache>,
+    
+    /// Membership lookups that had to read the database
+    cache_misses: AtomicU64,
+    
+    /// Reject inserts whose leaf commitment is already indexed
+    reject_commitment_collisions: bool,
+    
+    /// Newest finalized roots kept when root history is pruned
+    root_tolerance_window: u64,
+    
+    /// Checks and sequences single-UTXO insert and spend batches
+    pipeline: BatchPipeline,
+}
+
+/// A new UTXO's leaf commitment is already a leaf of the tree
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("CommitmentCollision: commitment {} already belongs to UTXO {}", hex::encode(commitment), hex::encode(existing_utxo_id))]
+pub struct CommitmentCollision {
+    pub commitment: [u8; 32],
+    pub existing_utxo_id: [u8; 32],
+}
+
+/// Rollback target lies further below the chain head than `DBConfig::max_reorg_depth`
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Rollback to block {target_block} is {depth} blocks below head {chain_head}, max reorg depth is {max_reorg_depth}")]
+pub struct ReorgTooDeep {
+    pub target_block: u64,
+    pub chain_head: u64,
+    pub depth: u64,
+    pub max_reorg_depth: u64,
+}
+
+/// Commitment and spent indexes mirrored from cf_smt_leaves and cf_spent_tracker
+#[derive(Debug, Default)]
+struct MembershipCache {
+    /// Leaf commitment -> UTXO ID
+    commitments: HashMap<[u8; 32], [u8; 32]>,
+    /// IDs of spent UTXOs
+    spent: HashSet<[u8; 32]>,
 }
 
 /// Result of UTXO operations
@@ -41,6 +117,15 @@ pub struct UTXOOperationResult {
     pub leaf_hash: [u8; 32],
 }
 
+/// Deposit converted to a UTXO but not yet inserted into the tree
+#[derive(Debug, Clone)]
+struct PreparedDeposit {
+    deposit_commitment: [u8; 32],
+    utxo: CanonicalUTXO,
+    tree_position: u64,
+    leaf_hash: [u8; 32],
+}
+
 /// Deposit processing result
 #[derive(Debug, Clone)]
 pub struct DepositResult {
@@ -59,22 +144,176 @@ impl UTXOManager {
     pub fn new(db: DatabaseManager) -> Result<Self> {
         let smt = CanonicalSMT::with_default_config(db.clone())?;
         
-        Ok(Self {
-            db,
-            smt,
-            operator_entropy_counter: rand::random::<u64>(),
-        })
+        Self::with_components(db, smt)
     }
 
     /// Create UTXO manager with specific tree configuration
     pub fn with_tree_config(db: DatabaseManager, tree_depth: u8, tree_salt: u64) -> Result<Self> {
         let smt = CanonicalSMT::new(db.clone(), tree_depth, tree_salt)?;
         
-        Ok(Self {
+        Self::with_components(db, smt)
+    }
+
+    /// Assemble manager with the persisted operator key
+    fn with_components(db: DatabaseManager, smt: CanonicalSMT) -> Result<Self> {
+        RootHistory::new(db.clone()).migrate_legacy_records()?;
+        // The API stores the key first, in its configured algorithm
+        let operator_keypair = Self::load_or_create_operator_keypair(&db, SignatureAlgorithm::Ed25519)?;
+        
+        let nullifier_tree = NullifierTree::load(&db, smt.get_tree_salt())?;
+        let stored_beacon = Self::load_randomness_beacon(&db)?;
+        // Until bound to a block hash, the beacon commits to the tree salt
+        let (beacon, entropy_index) = stored_beacon
+            .unwrap_or((RandomnessBeacon::from_tree_salt(smt.get_tree_salt()), 0));
+        let mut manager = Self {
+            pipeline: BatchPipeline::new(db.clone()),
             db,
             smt,
-            operator_entropy_counter: rand::random::<u64>(),
-        })
+            nullifier_tree,
+            operator_entropy_counter: entropy_index,
+            beacon,
+            operator_keypair,
+            membership_cache: None,
+            cache_misses: AtomicU64::new(0),
+            reject_commitment_collisions: true,
+            root_tolerance_window: crate::canonical_spec::tree_config::DEFAULT_ROOT_TOLERANCE_WINDOW,
+        };
+        if stored_beacon.is_none() {
+            manager.set_randomness_beacon(beacon)?;
+        }
+        
+        Ok(manager)
+    }
+
+    /// Enable or disable the leaf commitment freshness check on insert
+    pub fn set_reject_commitment_collisions(&mut self, reject: bool) {
+        self.reject_commitment_collisions = reject;
+    }
+
+    /// Set the withdrawal tolerance window root history pruning must respect
+    pub fn set_root_tolerance_window(&mut self, root_tolerance_window: u64) {
+        self.root_tolerance_window = root_tolerance_window;
+    }
+
+    /// Replace the operator keypair and publish its public key in cf_tree_metadata
+    pub fn set_operator_keypair(&mut self, keypair: OperatorKeypair) -> Result<()> {
+        Self::store_operator_keypair(&self.db, &keypair)?;
+        self.operator_keypair = keypair;
+        Ok(())
+    }
+
+    /// Operator keypair persisted in cf_tree_metadata, generating and storing
+    /// an `algorithm` key on first use
+    ///
+    /// Every component signing or publishing roots over `db` loads the key
+    /// through here, so the published key survives restarts and always
+    /// matches the signer. A stored key keeps its own algorithm. The secret
+    /// is kept in plaintext (see [`OPERATOR_SECRET_KEY`]).
+    pub fn load_or_create_operator_keypair(db: &DatabaseManager, algorithm: SignatureAlgorithm) -> Result<OperatorKeypair> {
+        if let Some(value) = db.get_cf(cf_names::TREE_METADATA, OPERATOR_SECRET_KEY)? {
+            if value.len() != 33 {
+                return Err(anyhow!("Invalid operator key entry length: {}", value.len()));
+            }
+            let algorithm = SignatureAlgorithm::from_byte(value[0])
+                .map_err(|e| anyhow!("Invalid operator key entry: {}", e))?;
+            let secret_key: [u8; 32] = value[1..].try_into()?;
+            return OperatorKeypair::from_secret_bytes(algorithm, secret_key)
+                .map_err(|e| anyhow!("Invalid operator key entry: {}", e));
+        }
+        
+        let keypair = OperatorKeypair::generate(algorithm)
+            .map_err(|e| anyhow!("Failed to generate operator keypair: {}", e))?;
+        Self::store_operator_keypair(db, &keypair)?;
+        Ok(keypair)
+    }
+
+    /// Persist the signing key and its published public key in one batch
+    fn store_operator_keypair(db: &DatabaseManager, keypair: &OperatorKeypair) -> Result<()> {
+        let mut secret = Vec::with_capacity(33);
+        secret.push(keypair.algorithm().to_byte());
+        secret.extend_from_slice(&keypair.secret_bytes());
+        let mut public = Vec::with_capacity(34);
+        public.push(keypair.algorithm().to_byte());
+        public.extend_from_slice(&keypair.public_key_bytes());
+        
+        let mut batch_writer = AtomicBatchWriter::new(db.clone());
+        batch_writer.add_operation(BatchOperation::PutMetadata { key: OPERATOR_SECRET_KEY.to_vec(), value: secret });
+        batch_writer.add_operation(BatchOperation::PutMetadata { key: OPERATOR_PUBKEY_KEY.to_vec(), value: public });
+        batch_writer.commit()
+            .context("Failed to store operator keypair")
+    }
+
+    /// Replace the randomness beacon and publish its seed in cf_tree_metadata
+    /// 
+    /// The entropy index restarts at zero so UTXO IDs can be re-derived from
+    /// the published seed alone.
+    pub fn set_randomness_beacon(&mut self, beacon: RandomnessBeacon) -> Result<()> {
+        let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
+        batch_writer.add_operation(BatchOperation::PutMetadata { key: BEACON_SEED_KEY.to_vec(), value: beacon.seed().to_vec() });
+        batch_writer.add_operation(BatchOperation::PutMetadata { key: BEACON_INDEX_KEY.to_vec(), value: 0u64.to_be_bytes().to_vec() });
+        batch_writer.commit()
+            .context("Failed to store randomness beacon seed")?;
+        
+        self.beacon = beacon;
+        self.operator_entropy_counter = 0;
+        Ok(())
+    }
+
+    /// Beacon and last used entropy index stored in cf_tree_metadata
+    fn load_randomness_beacon(db: &DatabaseManager) -> Result<Option<(RandomnessBeacon, u64)>> {
+        let Some(seed) = db.get_cf(cf_names::TREE_METADATA, BEACON_SEED_KEY)? else {
+            return Ok(None);
+        };
+        let seed: [u8; 32] = seed.as_slice().try_into()
+            .map_err(|_| anyhow!("Invalid randomness beacon seed length: {}", seed.len()))?;
+        
+        let entropy_index = match db.get_cf(cf_names::TREE_METADATA, BEACON_INDEX_KEY)? {
+            Some(value) => u64::from_be_bytes(value.as_slice().try_into()
+                .map_err(|_| anyhow!("Invalid randomness beacon index length: {}", value.len()))?),
+            None => 0,
+        };
+        Ok(Some((RandomnessBeacon::from_seed(seed), entropy_index)))
+    }
+
+    /// Persist the last used entropy index with the batch that consumed it
+    fn beacon_index_operation(&self) -> BatchOperation {
+        BatchOperation::PutMetadata {
+            key: BEACON_INDEX_KEY.to_vec(),
+            value: self.operator_entropy_counter.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Current randomness beacon
+    pub fn randomness_beacon(&self) -> RandomnessBeacon {
+        self.beacon
+    }
+
+    /// Read the operator public key stored in cf_tree_metadata
+    pub fn get_operator_public_key(db: &DatabaseManager) -> Result<Option<(SignatureAlgorithm, Vec<u8>)>> {
+        let value = match db.get_cf(cf_names::TREE_METADATA, OPERATOR_PUBKEY_KEY)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        
+        if value.is_empty() {
+            return Err(anyhow!("Operator public key entry is empty"));
+        }
+        
+        let algorithm = SignatureAlgorithm::from_byte(value[0])
+            .map_err(|e| anyhow!("Invalid operator public key entry: {}", e))?;
+        
+        Ok(Some((algorithm, value[1..].to_vec())))
+    }
+
+    /// Verify an operator root signature against a public key
+    pub fn verify_root_signature(
+        algorithm: SignatureAlgorithm,
+        public_key: &[u8],
+        root: [u8; 32],
+        signature: &[u8],
+    ) -> Result<bool> {
+        OperatorKeypair::verify(algorithm, public_key, &Self::root_signing_message(root), signature)
+            .map_err(|e| anyhow!("Root signature verification failed: {}", e))
     }
 
     /// Process ETH deposit into UTXO with full SMT integration
@@ -83,20 +322,24 @@ impl UTXOManager {
         self.operator_entropy_counter = self.operator_entropy_counter.wrapping_add(1);
         
         // Derive privacy-preserving owner commitment from deposit
-        let owner_commitment = self.derive_owner_commitment(&deposit_event)?;
+        let owner_commitment = Self::derive_owner_commitment(&deposit_event)?;
+        let deposit_commitment = Self::parse_deposit_commitment(&deposit_event)?;
+        if self.deposit_commitment_used(&deposit_commitment)? {
+            return Err(DepositError::DuplicateCommitment.into());
+        }
         
         // Create canonical UTXO
         let utxo = CanonicalUTXO::new_eth(
             deposit_event.transaction_hash.as_bytes().try_into().unwrap_or_default(),  // txid
             0,                                 // vout (always 0 for deposits)
             deposit_event.block_number,        // created_block
-            self.operator_entropy_counter,     // entropy
+            self.beacon.entropy(self.operator_entropy_counter), // entropy
             deposit_event.value as u128,     // amount in wei
             owner_commitment,                  // privacy commitment
         );
 
         // Insert UTXO into tree and database atomically
-        let operation_result = self.insert_utxo_with_tree_update(utxo)?;
+        let operation_result = self.insert_utxo_indexed(utxo, Some(deposit_commitment))?;
         
         Ok(DepositResult {
             operation: operation_result,
@@ -110,17 +353,25 @@ impl UTXOManager {
 
     /// Insert UTXO with complete tree and database updates
     pub fn insert_utxo_with_tree_update(&mut self, utxo: CanonicalUTXO) -> Result<UTXOOperationResult> {
+        self.insert_utxo_indexed(utxo, None)
+    }
+
+    /// Insert a UTXO, recording the deposit commitment that minted it if any
+    fn insert_utxo_indexed(&mut self, utxo: CanonicalUTXO, deposit_commitment: Option<[u8; 32]>) -> Result<UTXOOperationResult> {
         // Validate UTXO
         utxo.validate()
             .context("UTXO validation failed")?;
 
         // Get tree position
-        let tree_position = crate::canonical_spec::generate_tree_index(
-            utxo.utxo_id, 
-            self.smt.get_tree_salt()
-        );
+        let tree_position = self.smt.leaf_position(&utxo.utxo_id);
         let leaf_hash = utxo.leaf_hash()?;
 
+        if self.reject_commitment_collisions {
+            if let Some(existing_utxo_id) = self.find_utxo_by_commitment(&leaf_hash)? {
+                return Err(CommitmentCollision { commitment: leaf_hash, existing_utxo_id }.into());
+            }
+        }
+
         // Create atomic batch for all operations
         let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
 
@@ -160,15 +411,28 @@ impl UTXOManager {
             flags: utxo.lock_flags,
         });
 
-        // Insert into SMT and get new root
-        let new_root = self.smt.insert_utxo(&utxo)
+        if let Some(commitment) = deposit_commitment {
+            batch_writer.add_operation(BatchOperation::IndexDepositCommitment {
+                commitment,
+                utxo_id: utxo.utxo_id,
+            });
+        }
+
+        // Stage the SMT update; the tree only changes once the batch commits
+        self.smt.ensure_slot_free(tree_position)?;
+        let staged = self.smt.stage_leaf_updates(&[(tree_position, leaf_hash)])
             .context("Failed to insert UTXO into SMT")?;
+        for operation in &staged.operations {
+            batch_writer.add_operation(operation.clone());
+        }
+        let new_root = staged.root;
+        let root_version = self.smt.get_root_version() + 1;
 
         // Phase 7: cf_root_history - Commit new root
         batch_writer.add_operation(BatchOperation::CommitRoot {
-            root_version: self.smt.get_root_version(),
+            root_version,
             root_hash: new_root,
-            batch_id: self.smt.get_root_version(), // Use root version as batch ID
+            batch_id: root_version, // Use root version as batch ID
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -177,11 +441,33 @@ impl UTXOManager {
             operator_signature: self.sign_root(new_root)?,
         });
 
-        // Phases 8-10: cf_input_locks, cf_mempool, cf_block_index - SKIP for deposits
+        // Phases 8-9: cf_input_locks, cf_mempool - SKIP for deposits
+
+        // Phase 10: cf_block_index - Record the insert so a reorg can undo it
+        batch_writer.add_operation(BatchOperation::record_block_operation(
+            utxo.created_block,
+            self.next_block_tx_index(utxo.created_block)?,
+            block_operation_types::INSERT_UTXO,
+            utxo.utxo_id,
+            Vec::new(),
+        ));
+
+        // Phase 11: cf_tree_metadata - Count the deposited value and keep the
+        // beacon index so entropy is never reused after a restart
+        batch_writer.add_operation(BatchOperation::RecordDeposit {
+            amount_wei: utxo.amount,
+        });
+        batch_writer.add_operation(self.beacon_index_operation());
 
         // Execute all operations atomically
-        batch_writer.commit()
+        let prepared = self.pipeline.prepare(batch_writer)?;
+        self.pipeline.commit(prepared)
             .context("Failed to commit UTXO insertion batch")?;
+        self.smt.apply_staged(staged);
+        
+        if let Some(cache) = &mut self.membership_cache {
+            cache.commitments.insert(leaf_hash, utxo.utxo_id);
+        }
 
         Ok(UTXOOperationResult {
             utxo,
@@ -193,16 +479,42 @@ impl UTXOManager {
     }
 
     /// Remove UTXO (mark as spent) with tree update
-    pub fn remove_utxo(&mut self, utxo_id: &[u8; 32], spent_txid: [u8; 32]) -> Result<UTXOOperationResult> {
+    ///
+    /// The spent nullifier is derived from the owner's nullifier key and the
+    /// note secret, so only the spending key holder can produce it.
+    pub fn remove_utxo(
+        &mut self,
+        utxo_id: &[u8; 32],
+        spent_txid: [u8; 32],
+        nullifier_key: &[u8; 32],
+        note_secret: &[u8; 32],
+    ) -> Result<UTXOOperationResult> {
+        self.remove_utxo_at_block(utxo_id, spent_txid, nullifier_key, note_secret, 0)
+    }
+
+    /// Remove UTXO spent by a transaction included in `block_number`
+    ///
+    /// The spent UTXO and its nullifier are recorded in cf_block_index so
+    /// `rollback_to_block` can restore it if the block is reorganized away.
+    pub fn remove_utxo_at_block(
+        &mut self,
+        utxo_id: &[u8; 32],
+        spent_txid: [u8; 32],
+        nullifier_key: &[u8; 32],
+        note_secret: &[u8; 32],
+        block_number: u64,
+    ) -> Result<UTXOOperationResult> {
         // Get the UTXO first
         let utxo_data = self.db.get_cf("cf_utxos", &self.create_utxo_key(utxo_id))?
             .ok_or_else(|| anyhow!("UTXO not found: {:?}", utxo_id))?;
         let utxo = CanonicalUTXO::deserialize(&utxo_data)?;
+        let nullifier = ArchitectureCompliantCrypto::derive_nullifier_from_key(nullifier_key, utxo_id, note_secret)
+            .map_err(|e| anyhow!("Failed to derive nullifier: {}", e))?;
+        if self.nullifier_tree.contains(&nullifier) {
+            return Err(anyhow!("Nullifier already spent: {}", hex::encode(nullifier)));
+        }
 
-        let tree_position = crate::canonical_spec::generate_tree_index(
-            utxo.utxo_id, 
-            self.smt.get_tree_salt()
-        );
+        let tree_position = self.smt.leaf_position(&utxo.utxo_id);
 
         // Create atomic batch
         let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
@@ -211,21 +523,33 @@ impl UTXOManager {
         batch_writer.add_operation(BatchOperation::MarkSpent {
             utxo_id: *utxo_id,
             spent_txid,
-            spent_block: 0, // Would be filled with actual block
+            spent_block: block_number,
             spent_timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
                 .as_secs(),
         });
 
-        // Phase 2: cf_utxos - Delete UTXO
+        // Phase 2: cf_nullifiers - Record the nullifier with the spend
+        batch_writer.add_operation(BatchOperation::InsertNullifier {
+            nullifier,
+            spent_txid,
+            block: block_number,
+        });
+
+        // Phase 3: cf_utxos - Delete UTXO
         batch_writer.add_operation(BatchOperation::DeleteUTXO {
             utxo_id: *utxo_id,
         });
 
-        // Remove from SMT and get new root
-        let new_root = self.smt.remove_utxo(utxo_id)
+        // Stage clearing the leaf; the tree only changes once the batch commits
+        let staged = self.smt.stage_leaf_updates(&[(tree_position, crate::canonical_spec::generate_empty_leaf_hash())])
             .context("Failed to remove UTXO from SMT")?;
+        for operation in &staged.operations {
+            batch_writer.add_operation(operation.clone());
+        }
+        let new_root = staged.root;
+        let root_version = self.smt.get_root_version() + 1;
 
         // Phase 4: cf_smt_leaves - Remove leaf mapping
         batch_writer.add_operation(BatchOperation::DeleteSMTLeaf {
@@ -238,7 +562,7 @@ impl UTXOManager {
             asset_id: utxo.asset_id,
             amount_delta: -(utxo.amount as i128),
             utxo_count_delta: -1,
-            last_updated_block: 0, // Would be current block
+            last_updated_block: block_number,
         });
 
         // Phase 6: cf_owner_index - Remove ownership record
@@ -250,9 +574,9 @@ impl UTXOManager {
 
         // Phase 7: cf_root_history - Commit new root
         batch_writer.add_operation(BatchOperation::CommitRoot {
-            root_version: self.smt.get_root_version(),
+            root_version,
             root_hash: new_root,
-            batch_id: self.smt.get_root_version(),
+            batch_id: root_version,
             timestamp: std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
                 .unwrap()
@@ -261,9 +585,29 @@ impl UTXOManager {
             operator_signature: self.sign_root(new_root)?,
         });
 
+        // Phase 10: cf_block_index - Keep the nullifier and spent UTXO for reorg recovery
+        batch_writer.add_operation(BatchOperation::record_block_operation(
+            block_number,
+            self.next_block_tx_index(block_number)?,
+            block_operation_types::SPEND_UTXO,
+            *utxo_id,
+            [&nullifier[..], &utxo_data].concat(),
+        ));
+
         // Execute atomically
-        batch_writer.commit()
+        let prepared = self.pipeline.prepare(batch_writer)?;
+        self.pipeline.commit(prepared)
             .context("Failed to commit UTXO removal batch")?;
+        self.smt.apply_staged(staged);
+        
+        if let Some(cache) = &mut self.membership_cache {
+            cache.commitments.remove(&utxo.leaf_hash()?);
+            cache.spent.insert(*utxo_id);
+        }
+
+        // Publish the spent nullifier; rebuilt from cf_nullifiers on open
+        self.nullifier_tree.insert(nullifier)
+            .context("Failed to record spent nullifier")?;
 
         Ok(UTXOOperationResult {
             utxo,
@@ -274,42 +618,206 @@ impl UTXOManager {
         })
     }
 
+    /// Withdraw a UTXO whose spend proof was generated against `proof_root`
+    ///
+    /// Only finalized roots are accepted, so a root that may still be
+    /// reorganized away can never release funds.
+    pub fn withdraw_utxo(
+        &mut self,
+        utxo_id: &[u8; 32],
+        spent_txid: [u8; 32],
+        nullifier_key: &[u8; 32],
+        note_secret: &[u8; 32],
+        proof_root: [u8; 32],
+    ) -> Result<UTXOOperationResult> {
+        if !self.root_history().is_withdrawable_root(&proof_root)? {
+            return Err(anyhow!("Proof root is not finalized: {}", hex::encode(proof_root)));
+        }
+        
+        self.remove_utxo(utxo_id, spent_txid, nullifier_key, note_secret)
+    }
+
+    /// Finalize a committed root once its anchoring block is confirmed,
+    /// then prune finalized roots older than the tolerance window
+    pub fn finalize_root(&self, root_version: u64) -> Result<()> {
+        let root_history = self.root_history();
+        root_history.finalize_root(root_version)?;
+        root_history.prune_stale_roots()?;
+        Ok(())
+    }
+
+    /// Access the two-phase root history
+    pub fn root_history(&self) -> RootHistory {
+        RootHistory::new(self.db.clone()).with_tolerance_window(self.root_tolerance_window)
+    }
+
     /// Batch process multiple deposits efficiently
     pub fn batch_process_deposits(&mut self, deposit_events: &[DepositEvent]) -> Result<Vec<DepositResult>> {
-        let mut results = Vec::new();
-        let mut utxos = Vec::new();
+        let (tree_salt, tree_depth) = (self.smt.get_tree_salt(), self.smt.get_depth());
+        let mut prepared = Vec::with_capacity(deposit_events.len());
 
         // Create all UTXOs first
         for deposit_event in deposit_events {
             self.operator_entropy_counter = self.operator_entropy_counter.wrapping_add(1);
-            
-            let owner_commitment = self.derive_owner_commitment(deposit_event)?;
-            
-            let utxo = CanonicalUTXO::new_eth(
-                deposit_event.transaction_hash.as_bytes().try_into().unwrap_or_default(),
-                0,
-                deposit_event.block_number,
-                self.operator_entropy_counter,
-                deposit_event.value as u128,
-                owner_commitment,
-            );
+            let entropy = self.beacon.entropy(self.operator_entropy_counter);
+            prepared.push(Self::prepare_deposit(deposit_event, entropy, tree_salt, tree_depth)?);
+        }
+
+        self.commit_prepared_deposits(prepared, deposit_events, Vec::new())
+    }
+
+    /// Batch process deposits, skipping individual bad ones
+    ///
+    /// Zero-value deposits and repeats of a commitment already seen in the
+    /// batch, recorded in cf_deposit_commitments, or present as a tree leaf
+    /// are rejected on their own. The remaining deposits are committed in
+    /// one atomic batch, exactly as `batch_process_deposits` would commit them,
+    /// and each rejection is reported with its index in `deposit_events`.
+    pub fn batch_process_deposits_lenient(
+        &mut self,
+        deposit_events: &[DepositEvent],
+    ) -> Result<(Vec<DepositResult>, Vec<(usize, DepositError)>)> {
+        self.batch_process_deposits_lenient_with(deposit_events, |_| Vec::new())
+    }
+
+    /// Lenient batch processing that commits extra operations in the same batch
+    ///
+    /// `extra_operations` sees the rejected deposits and returns operations
+    /// (an ingestion cursor, dead-letter records) that must land atomically
+    /// with the minted UTXOs. They are committed even if every deposit is rejected.
+    pub fn batch_process_deposits_lenient_with<F>(
+        &mut self,
+        deposit_events: &[DepositEvent],
+        extra_operations: F,
+    ) -> Result<(Vec<DepositResult>, Vec<(usize, DepositError)>)>
+    where
+        F: FnOnce(&[(usize, DepositError)]) -> Vec<BatchOperation>,
+    {
+        let (tree_salt, tree_depth) = (self.smt.get_tree_salt(), self.smt.get_depth());
+        let mut seen_commitments = HashSet::new();
+        let mut prepared = Vec::with_capacity(deposit_events.len());
+        let mut accepted = Vec::with_capacity(deposit_events.len());
+        let mut failures = Vec::new();
+
+        for (i, deposit_event) in deposit_events.iter().enumerate() {
+            if deposit_event.value == 0 {
+                failures.push((i, DepositError::InvalidAmount));
+                continue;
+            }
+
+            // Rejected deposits do not consume beacon entropy
+            let entropy_index = self.operator_entropy_counter.wrapping_add(1);
+            match Self::prepare_deposit(deposit_event, self.beacon.entropy(entropy_index), tree_salt, tree_depth) {
+                Ok(deposit) => {
+                    if !seen_commitments.insert(deposit.deposit_commitment)
+                        || self.deposit_commitment_used(&deposit.deposit_commitment)?
+                        || (self.reject_commitment_collisions
+                            && self.find_utxo_by_commitment(&deposit.leaf_hash)?.is_some())
+                    {
+                        failures.push((i, DepositError::DuplicateCommitment));
+                        continue;
+                    }
+                    self.operator_entropy_counter = entropy_index;
+                    prepared.push(deposit);
+                    accepted.push(deposit_event.clone());
+                }
+                Err(e) => failures.push((i, DepositError::InvalidUTXO(e.to_string()))),
+            }
+        }
+
+        let extra_operations = extra_operations(&failures);
+        if prepared.is_empty() {
+            if !extra_operations.is_empty() {
+                let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
+                for operation in extra_operations {
+                    batch_writer.add_operation(operation);
+                }
+                batch_writer.commit()
+                    .context("Failed to commit deposit batch metadata")?;
+            }
+            return Ok((Vec::new(), failures));
+        }
+
+        let results = self.commit_prepared_deposits(prepared, &accepted, extra_operations)?;
+        Ok((results, failures))
+    }
+
+    /// Batch process deposits, preparing UTXOs in parallel
+    ///
+    /// Owner commitments, UTXO IDs and leaf hashes are computed on the rayon pool
+    /// in input order; the tree insert and batch commit stay single-threaded, so
+    /// the result is identical to `batch_process_deposits`.
+    pub fn parallel_prepare_deposits(&mut self, deposit_events: &[DepositEvent]) -> Result<Vec<DepositResult>> {
+        let (tree_salt, tree_depth) = (self.smt.get_tree_salt(), self.smt.get_depth());
+        let base_entropy = self.operator_entropy_counter;
+        let beacon = self.beacon;
 
-            utxos.push(utxo);
+        // Entropy is assigned by position so it matches sequential processing
+        let prepared = deposit_events
+            .par_iter()
+            .enumerate()
+            .map(|(i, deposit_event)| {
+                let entropy = beacon.entropy(base_entropy.wrapping_add(i as u64 + 1));
+                Self::prepare_deposit(deposit_event, entropy, tree_salt, tree_depth)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        self.operator_entropy_counter = base_entropy.wrapping_add(deposit_events.len() as u64);
+
+        self.commit_prepared_deposits(prepared, deposit_events, Vec::new())
+    }
+
+    /// Convert a deposit event into a UTXO with its tree position and leaf hash
+    fn prepare_deposit(deposit_event: &DepositEvent, entropy: u64, tree_salt: u64, tree_depth: u8) -> Result<PreparedDeposit> {
+        let deposit_commitment = Self::parse_deposit_commitment(deposit_event)?;
+        let owner_commitment = Self::derive_owner_commitment(deposit_event)?;
+        
+        let utxo = CanonicalUTXO::new_eth(
+            deposit_event.transaction_hash.as_bytes().try_into().unwrap_or_default(),
+            0,
+            deposit_event.block_number,
+            entropy,
+            deposit_event.value as u128,
+            owner_commitment,
+        );
+
+        let tree_position = crate::canonical_spec::tree_leaf_index(utxo.utxo_id, tree_salt, tree_depth);
+        let leaf_hash = utxo.leaf_hash()?;
+
+        Ok(PreparedDeposit { deposit_commitment, utxo, tree_position, leaf_hash })
+    }
+
+    /// Insert prepared deposits into the tree and commit them, with any
+    /// `extra_operations`, in one atomic batch
+    fn commit_prepared_deposits(
+        &mut self,
+        prepared: Vec<PreparedDeposit>,
+        deposit_events: &[DepositEvent],
+        extra_operations: Vec<BatchOperation>,
+    ) -> Result<Vec<DepositResult>> {
+        // Every deposit commitment mints at most once, within the batch and across the pool
+        let mut seen_commitments = HashSet::new();
+        for (i, deposit) in prepared.iter().enumerate() {
+            if !seen_commitments.insert(deposit.deposit_commitment)
+                || self.deposit_commitment_used(&deposit.deposit_commitment)?
+            {
+                return Err(anyhow::Error::new(DepositError::DuplicateCommitment)
+                    .context(format!("Deposit {} repeats commitment 0x{}", i, hex::encode(deposit.deposit_commitment))));
+            }
         }
 
+        let mut results = Vec::new();
+        let utxos: Vec<CanonicalUTXO> = prepared.iter().map(|p| p.utxo.clone()).collect();
+
         // Batch insert into tree
         let new_root = self.smt.batch_insert_utxos(&utxos)?;
 
         // Create batch writer for database operations
         let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
+        let mut next_tx_index: HashMap<u64, u32> = HashMap::new();
 
         // Add all database operations
-        for (i, utxo) in utxos.iter().enumerate() {
-            let tree_position = crate::canonical_spec::generate_tree_index(
-                utxo.utxo_id, 
-                self.smt.get_tree_salt()
-            );
-
+        for (i, PreparedDeposit { deposit_commitment, utxo, tree_position, leaf_hash }) in prepared.into_iter().enumerate() {
             // Insert UTXO
             batch_writer.add_operation(BatchOperation::InsertUTXO { 
                 utxo: utxo.clone() 
@@ -318,7 +826,7 @@ impl UTXOManager {
             // Store leaf mapping
             batch_writer.add_operation(BatchOperation::UpdateSMTLeaf {
                 utxo_id: utxo.utxo_id,
-                leaf_hash: utxo.leaf_hash()?,
+                leaf_hash,
                 tree_position,
             });
 
@@ -341,14 +849,39 @@ impl UTXOManager {
                 flags: utxo.lock_flags,
             });
 
+            // Remember which UTXO the deposit commitment minted
+            batch_writer.add_operation(BatchOperation::IndexDepositCommitment {
+                commitment: deposit_commitment,
+                utxo_id: utxo.utxo_id,
+            });
+
+            // Count the deposited value
+            batch_writer.add_operation(BatchOperation::RecordDeposit {
+                amount_wei: utxo.amount,
+            });
+
+            // Record the insert for reorg recovery
+            let tx_index = match next_tx_index.get(&utxo.created_block) {
+                Some(index) => *index,
+                None => self.next_block_tx_index(utxo.created_block)?,
+            };
+            next_tx_index.insert(utxo.created_block, tx_index + 1);
+            batch_writer.add_operation(BatchOperation::record_block_operation(
+                utxo.created_block,
+                tx_index,
+                block_operation_types::INSERT_UTXO,
+                utxo.utxo_id,
+                Vec::new(),
+            ));
+
             // Create result
             results.push(DepositResult {
                 operation: UTXOOperationResult {
-                    utxo: utxo.clone(),
+                    utxo,
                     new_root,
                     root_version: self.smt.get_root_version(),
                     tree_position,
-                    leaf_hash: utxo.leaf_hash()?,
+                    leaf_hash,
                 },
                 deposit_event: deposit_events[i].clone(),
                 processed_at: std::time::SystemTime::now()
@@ -370,19 +903,454 @@ impl UTXOManager {
             tx_count: utxos.len() as u32,
             operator_signature: self.sign_root(new_root)?,
         });
+        batch_writer.add_operation(self.beacon_index_operation());
+        for operation in extra_operations {
+            batch_writer.add_operation(operation);
+        }
 
         // Execute all operations atomically
         batch_writer.commit()
             .context("Failed to commit batch deposit processing")?;
+        
+        if let Some(cache) = &mut self.membership_cache {
+            for result in &results {
+                cache.commitments.insert(result.operation.leaf_hash, result.operation.utxo.utxo_id);
+            }
+        }
 
         Ok(results)
     }
 
+    /// Undo every operation recorded for blocks after `block_number`
+    ///
+    /// cf_block_index is walked from the tip back to `block_number`: inserted
+    /// UTXOs are deleted and spent UTXOs are restored from their recorded
+    /// prior state, together with their leaves, balances, owner index
+    /// entries, spend markers and pool counters. The SMT root is recomputed
+    /// and committed as a new root version. Undone entries are removed from
+    /// the index, so a repeated call is a no-op and the re-extended chain can
+    /// be applied again. Returns the number of operations undone.
+    ///
+    /// Everything, tree nodes and restored nullifiers included, is written in
+    /// one batch; the in-memory tree and caches change only once it commits.
+    /// Targets more than `max_reorg_depth` blocks below `chain_head` are
+    /// refused with `ReorgTooDeep`.
+    pub fn rollback_to_block(&mut self, block_number: u64, chain_head: u64) -> Result<u64> {
+        let max_reorg_depth = self.db.config().max_reorg_depth;
+        let depth = chain_head.saturating_sub(block_number);
+        if depth > max_reorg_depth {
+            return Err(ReorgTooDeep {
+                target_block: block_number,
+                chain_head,
+                depth,
+                max_reorg_depth,
+            }.into());
+        }
+
+        let records = self.block_operations_after(block_number)?;
+        if records.is_empty() {
+            return Ok(0);
+        }
+
+        let mut batch_writer = AtomicBatchWriter::new(self.db.clone());
+        // State of each touched UTXO as the batch leaves it (None = not in cf_utxos)
+        let mut current: HashMap<[u8; 32], Option<CanonicalUTXO>> = HashMap::new();
+        let mut balance_deltas: HashMap<([u8; 32], [u8; 20]), (i128, i32)> = HashMap::new();
+        // Leaf writes in undo order; the tree and caches change only after the commit
+        let mut leaf_updates: Vec<(u64, [u8; 32])> = Vec::new();
+        // Leaf commitment -> UTXO ID to restore, or None to drop
+        let mut commitment_updates: Vec<([u8; 32], Option<[u8; 32]>)> = Vec::new();
+        let mut restored_nullifiers: Vec<[u8; 32]> = Vec::new();
+
+        for record in records.iter().rev() {
+            let live = match current.get(&record.utxo_id) {
+                Some(state) => state.clone(),
+                None => self.db.get_cf(cf_names::UTXOS, &self.create_utxo_key(&record.utxo_id))?
+                    .map(|data| CanonicalUTXO::deserialize(&data))
+                    .transpose()?,
+            };
+
+            match record.operation_type {
+                block_operation_types::INSERT_UTXO => {
+                    // Already gone if a later spend of it was never restored
+                    if let Some(utxo) = live {
+                        batch_writer.add_operation(BatchOperation::DeleteUTXO { utxo_id: utxo.utxo_id });
+                        batch_writer.add_operation(BatchOperation::DeleteSMTLeaf { utxo_id: utxo.utxo_id });
+                        batch_writer.add_operation(BatchOperation::DeleteOwnerIndex {
+                            owner_commitment: utxo.owner_commitment,
+                            created_block: utxo.created_block,
+                            utxo_id: utxo.utxo_id,
+                        });
+                        batch_writer.add_operation(BatchOperation::RevertDeposit { amount_wei: utxo.amount });
+                        let delta = balance_deltas.entry((utxo.owner_commitment, utxo.asset_id)).or_default();
+                        delta.0 -= utxo.amount as i128;
+                        delta.1 -= 1;
+
+                        leaf_updates.push((self.smt.leaf_position(&utxo.utxo_id), crate::canonical_spec::generate_empty_leaf_hash()));
+                        commitment_updates.push((utxo.leaf_hash()?, None));
+                        current.insert(record.utxo_id, None);
+                    }
+                },
+                block_operation_types::SPEND_UTXO => {
+                    if live.is_none() {
+                        if record.prev_state.len() < 32 {
+                            return Err(anyhow!("Spend record for block {} is missing its nullifier", record.block_number));
+                        }
+                        let (nullifier, utxo_data) = record.prev_state.split_at(32);
+                        let nullifier: [u8; 32] = nullifier.try_into()?;
+                        let utxo = CanonicalUTXO::deserialize(utxo_data)
+                            .context("Failed to decode spent UTXO from block index")?;
+                        let leaf_hash = utxo.leaf_hash()?;
+                        batch_writer.add_operation(BatchOperation::UnmarkSpent { utxo_id: utxo.utxo_id });
+                        batch_writer.add_operation(BatchOperation::DeleteNullifier { nullifier });
+                        batch_writer.add_operation(BatchOperation::InsertUTXO { utxo: utxo.clone() });
+                        batch_writer.add_operation(BatchOperation::UpdateSMTLeaf {
+                            utxo_id: utxo.utxo_id,
+                            leaf_hash,
+                            tree_position: self.smt.leaf_position(&utxo.utxo_id),
+                        });
+                        batch_writer.add_operation(BatchOperation::InsertOwnerIndex {
+                            owner_commitment: utxo.owner_commitment,
+                            created_block: utxo.created_block,
+                            utxo_id: utxo.utxo_id,
+                            amount: utxo.amount,
+                            asset_id: utxo.asset_id,
+                            flags: utxo.lock_flags,
+                        });
+                        let delta = balance_deltas.entry((utxo.owner_commitment, utxo.asset_id)).or_default();
+                        delta.0 += utxo.amount as i128;
+                        delta.1 += 1;
+
+                        leaf_updates.push((self.smt.leaf_position(&utxo.utxo_id), leaf_hash));
+                        commitment_updates.push((leaf_hash, Some(utxo.utxo_id)));
+                        restored_nullifiers.push(nullifier);
+                        current.insert(record.utxo_id, Some(utxo));
+                    }
+                },
+                other => return Err(anyhow!(
+                    "Unknown operation type {} recorded for block {}", other, record.block_number
+                )),
+            }
+
+            batch_writer.add_operation(BatchOperation::DeleteBlockOperation {
+                block_number: record.block_number,
+                tx_index: record.tx_index,
+                operation_id: record.operation_id,
+            });
+        }
+
+        // One update per balance key: the writer reads each from the database
+        for ((owner_commitment, asset_id), (amount_delta, utxo_count_delta)) in balance_deltas {
+            if amount_delta != 0 || utxo_count_delta != 0 {
+                batch_writer.add_operation(BatchOperation::UpdateAssetBalance {
+                    owner_commitment,
+                    asset_id,
+                    amount_delta,
+                    utxo_count_delta,
+                    last_updated_block: block_number,
+                });
+            }
+        }
+
+        let staged = self.smt.stage_leaf_updates(&leaf_updates)?;
+        for operation in &staged.operations {
+            batch_writer.add_operation(operation.clone());
+        }
+        let new_root = staged.root;
+
+        // Roots committed since the restored root describe blocks that left the chain
+        let history = self.root_history();
+        let first_abandoned = history.versions_of_root(&new_root)?.into_iter().max().map_or(0, |version| version + 1);
+        for (version, record) in history.roots_from(first_abandoned)? {
+            if record.finalized {
+                return Err(anyhow!(
+                    "Rollback to block {} would abandon finalized root version {}", block_number, version
+                ));
+            }
+            batch_writer.add_operation(BatchOperation::AbandonRoot { root_version: version, root_hash: record.root_hash });
+        }
+
+        let root_version = self.smt.get_root_version() + 1;
+        batch_writer.add_operation(BatchOperation::CommitRoot {
+            root_version,
+            root_hash: new_root,
+            batch_id: root_version,
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            tx_count: records.len() as u32,
+            operator_signature: self.sign_root(new_root)?,
+        });
+
+        batch_writer.commit()
+            .context("Failed to commit block rollback batch")?;
+
+        self.smt.apply_staged(staged);
+        for nullifier in &restored_nullifiers {
+            if self.nullifier_tree.contains(nullifier) {
+                self.nullifier_tree.remove(nullifier)?;
+            }
+        }
+        if let Some(cache) = &mut self.membership_cache {
+            for (commitment, utxo_id) in commitment_updates {
+                match utxo_id {
+                    Some(utxo_id) => {
+                        cache.commitments.insert(commitment, utxo_id);
+                        cache.spent.remove(&utxo_id);
+                    },
+                    None => {
+                        cache.commitments.remove(&commitment);
+                    },
+                }
+            }
+        }
+
+        Ok(records.len() as u64)
+    }
+
+    /// Operations recorded in cf_block_index for blocks after `block_number`, in index order
+    fn block_operations_after(&self, block_number: u64) -> Result<Vec<BlockOperationRecord>> {
+        let Some(first_block) = block_number.checked_add(1) else {
+            return Ok(Vec::new());
+        };
+        let mut start = vec![crate::canonical_spec::cf_prefixes::BLOCK_INDEX];
+        start.extend_from_slice(&first_block.to_be_bytes());
+
+        let mut records = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::BLOCK_INDEX, &start)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&start[0]) {
+                break;
+            }
+            let record = BlockOperationRecord::parse(&key, &value)?;
+            if record.block_number > block_number {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Next free tx_index for operations recorded in `block_number`
+    fn next_block_tx_index(&self, block_number: u64) -> Result<u32> {
+        let mut prefix = vec![crate::canonical_spec::cf_prefixes::BLOCK_INDEX];
+        prefix.extend_from_slice(&block_number.to_be_bytes());
+
+        let mut next = 0u32;
+        for item in self.db.prefix_iterator_cf(cf_names::BLOCK_INDEX, &prefix)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            next = BlockOperationRecord::parse(&key, &value)?.tx_index + 1;
+        }
+        Ok(next)
+    }
+
+    /// Choose inputs of `owner_commitment` covering `target_amount` of `asset_id`
+    ///
+    /// UTXOs holding an unexpired input lock, or not yet spendable at
+    /// `current_block` (timelock or withdrawal lock), are never selected.
+    /// Returns the inputs and the change left over, or `InsufficientFunds`
+    /// if the spendable balance falls short.
+    pub fn select_utxos(
+        &self,
+        owner_commitment: &[u8; 32],
+        asset_id: &[u8; 20],
+        target_amount: u128,
+        current_block: u64,
+    ) -> Result<(Vec<CanonicalUTXO>, u128)> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut prefix = vec![crate::canonical_spec::cf_prefixes::OWNER_INDEX];
+        prefix.extend_from_slice(owner_commitment);
+
+        let mut candidates = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::OWNER_INDEX, &prefix)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if !key.starts_with(&prefix) {
+                break;
+            }
+            if key.len() != 73 || value.len() < 36 || &value[16..36] != asset_id {
+                continue;
+            }
+
+            let utxo_id: [u8; 32] = key[41..73].try_into()?;
+            let lock_key = crate::database::schema::utils::input_lock_key(&utxo_id);
+            if let Some(lock) = self.db.get_cf(cf_names::INPUT_LOCKS, &lock_key)? {
+                if Self::decode_lock_expiry(&lock).is_some_and(|expires_at| expires_at > now) {
+                    continue;
+                }
+            }
+
+            let Some(data) = self.db.get_cf(cf_names::UTXOS, &self.create_utxo_key(&utxo_id))? else {
+                continue;
+            };
+            let utxo = CanonicalUTXO::deserialize(&data)?;
+            if utxo.is_spendable(current_block) {
+                candidates.push(utxo);
+            }
+        }
+
+        Ok(coin_selection::select_coins(candidates, target_amount)?)
+    }
+
+    /// Lock a UTXO as a pending transaction input until `now + ttl_secs`
+    ///
+    /// The lock value is the big-endian `expires_at` timestamp. Returns
+    /// `false` if the UTXO already holds an unexpired lock. The check and the
+    /// write happen under the commit guard, so concurrent callers cannot both
+    /// take the lock.
+    pub fn acquire_input_lock(&self, utxo_id: &[u8; 32], now: u64, ttl_secs: u64) -> Result<bool> {
+        let key = crate::database::schema::utils::input_lock_key(utxo_id);
+        let _guard = self.db.commit_guard();
+        
+        if let Some(value) = self.db.get_cf(cf_names::INPUT_LOCKS, &key)? {
+            if Self::decode_lock_expiry(&value).is_some_and(|expires_at| expires_at > now) {
+                return Ok(false);
+            }
+        }
+        
+        let expires_at = now.saturating_add(ttl_secs);
+        self.db.put_cf(cf_names::INPUT_LOCKS, &key, &expires_at.to_be_bytes())
+            .context("Failed to store input lock")?;
+        Ok(true)
+    }
+
+    /// Delete input locks whose `expires_at` is at or before `now`
+    ///
+    /// Locks without a readable expiry can never expire on their own, so they
+    /// are released as well. Returns the number of locks released.
+    pub fn sweep_expired_locks(&self, now: u64) -> Result<u64> {
+        let prefix = [crate::canonical_spec::cf_prefixes::INPUT_LOCKS];
+        // A lock renewed between the scan and the delete must survive
+        let _guard = self.db.commit_guard();
+        let mut expired = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf_names::INPUT_LOCKS, &prefix)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&prefix[0]) {
+                break;
+            }
+            if Self::decode_lock_expiry(&value).is_none_or(|expires_at| expires_at <= now) {
+                expired.push(key);
+            }
+        }
+        
+        if expired.is_empty() {
+            return Ok(0);
+        }
+        
+        let cf = self.db.cf_handle(cf_names::INPUT_LOCKS)?;
+        let mut batch = self.db.create_write_batch();
+        for key in &expired {
+            batch.delete_cf(cf, key);
+        }
+        self.db.write_batch(batch).context("Failed to release expired input locks")?;
+        
+        Ok(expired.len() as u64)
+    }
+
+    /// Preload the commitment and spent indexes so membership checks after a
+    /// restart are served from memory
+    ///
+    /// Once warm, the indexes are kept in step with every insert and removal
+    /// made through this manager.
+    pub fn warm_caches(&mut self) -> Result<()> {
+        const PROGRESS_INTERVAL: usize = 100_000;
+        let mut cache = MembershipCache::default();
+        
+        let prefix = [crate::canonical_spec::cf_prefixes::SMT_LEAVES];
+        for item in self.db.prefix_iterator_cf(cf_names::SMT_LEAVES, &prefix)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&prefix[0]) {
+                break;
+            }
+            let utxo_id: [u8; 32] = key.get(1..33).and_then(|id| id.try_into().ok())
+                .ok_or_else(|| anyhow!("Invalid SMT leaf key length: {}", key.len()))?;
+            let leaf_hash: [u8; 32] = value.get(0..32).and_then(|hash| hash.try_into().ok())
+                .ok_or_else(|| anyhow!("Invalid SMT leaf value length: {}", value.len()))?;
+            cache.commitments.insert(leaf_hash, utxo_id);
+            
+            if cache.commitments.len() % PROGRESS_INTERVAL == 0 {
+                log::info!("Warming caches: {} commitments loaded", cache.commitments.len());
+            }
+        }
+        
+        let prefix = [crate::canonical_spec::cf_prefixes::SPENT_TRACKER];
+        for item in self.db.prefix_iterator_cf(cf_names::SPENT_TRACKER, &prefix)? {
+            let (key, _) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&prefix[0]) {
+                break;
+            }
+            let utxo_id: [u8; 32] = key.get(1..33).and_then(|id| id.try_into().ok())
+                .ok_or_else(|| anyhow!("Invalid spent tracker key length: {}", key.len()))?;
+            cache.spent.insert(utxo_id);
+            
+            if cache.spent.len() % PROGRESS_INTERVAL == 0 {
+                log::info!("Warming caches: {} spent UTXOs loaded", cache.spent.len());
+            }
+        }
+        
+        log::info!(
+            "Caches warm: {} commitments, {} spent UTXOs",
+            cache.commitments.len(),
+            cache.spent.len()
+        );
+        self.membership_cache = Some(cache);
+        Ok(())
+    }
+
+    /// UTXO whose tree leaf is `commitment`, if it is in the tree
+    pub fn find_utxo_by_commitment(&self, commitment: &[u8; 32]) -> Result<Option<[u8; 32]>> {
+        if let Some(cache) = &self.membership_cache {
+            return Ok(cache.commitments.get(commitment).copied());
+        }
+        
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        let prefix = [crate::canonical_spec::cf_prefixes::SMT_LEAVES];
+        for item in self.db.prefix_iterator_cf(cf_names::SMT_LEAVES, &prefix)? {
+            let (key, value) = item.map_err(|e| anyhow!("Iterator error: {}", e))?;
+            if key.first() != Some(&prefix[0]) {
+                break;
+            }
+            if value.get(0..32) == Some(&commitment[..]) {
+                return Ok(key.get(1..33).and_then(|id| id.try_into().ok()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether a UTXO has been spent (double-spend check)
+    pub fn is_spent(&self, utxo_id: &[u8; 32]) -> Result<bool> {
+        if let Some(cache) = &self.membership_cache {
+            return Ok(cache.spent.contains(utxo_id));
+        }
+        
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        let key = crate::database::schema::utils::create_key_with_prefix(
+            crate::canonical_spec::cf_prefixes::SPENT_TRACKER,
+            &[utxo_id],
+        );
+        Ok(self.db.get_cf(cf_names::SPENT_TRACKER, &key)?.is_some())
+    }
+
+    /// Number of membership lookups that fell through to the database
+    pub fn cache_misses(&self) -> u64 {
+        self.cache_misses.load(Ordering::Relaxed)
+    }
+
     /// Get current tree statistics
     pub fn get_tree_stats(&self) -> Result<crate::merkle::TreeStats> {
         self.smt.get_tree_stats()
     }
 
+    /// Get persistent pool counters in O(1)
+    pub fn get_pool_counters(&self) -> Result<PoolCounters> {
+        PoolCounters::load(&self.db)
+    }
+
     /// Get current tree root
     pub fn get_current_root(&self) -> [u8; 32] {
         self.smt.get_root()
@@ -393,21 +1361,55 @@ impl UTXOManager {
         self.smt.get_root_version()
     }
 
+    /// Get current spent-nullifier tree root
+    pub fn get_nullifier_root(&self) -> [u8; 32] {
+        self.nullifier_tree.get_root()
+    }
+
+    /// Prove that a nullifier is spent (inclusion) or unspent (exclusion)
+    pub fn prove_nullifier(&self, nullifier: &[u8; 32]) -> NullifierProof {
+        self.nullifier_tree.prove(nullifier)
+    }
+
     // Helper methods
 
     /// Derive privacy-preserving owner commitment from deposit
-    fn derive_owner_commitment(&self, deposit: &DepositEvent) -> Result<[u8; 32]> {
-        // For now, use a simple hash of depositor + commitment
-        // In this would use more sophisticated privacy-preserving derivation
-        use sha3::{Keccak256, Digest};
+    fn derive_owner_commitment(deposit: &DepositEvent) -> Result<[u8; 32]> {
+        let depositor: [u8; 20] = hex::decode(deposit.depositor.trim_start_matches("0x"))
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid depositor address: {}", deposit.depositor))?;
+        let commitment = Self::parse_deposit_commitment(deposit)?;
         
-        let mut hasher = Keccak256::new();
-        hasher.update(b"OWNER_COMMITMENT"); // Domain separator
-        hasher.update(deposit.depositor.as_bytes());
-        hasher.update(deposit.commitment.as_bytes());
-        hasher.update(&deposit.block_number.to_be_bytes());
+        Ok(crate::canonical_spec::generate_owner_commitment(depositor, commitment, deposit.block_number))
+    }
+
+    /// Commitment emitted by the deposit event
+    fn parse_deposit_commitment(deposit: &DepositEvent) -> Result<[u8; 32]> {
+        hex::decode(deposit.commitment.trim_start_matches("0x"))
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid deposit commitment: {}", deposit.commitment))
+    }
+
+    /// Whether an earlier deposit of `commitment` minted a UTXO still in the pool
+    ///
+    /// Entries left by a rolled-back deposit point at a deleted, unspent UTXO
+    /// and do not count, so the re-extended chain can mint it again.
+    fn deposit_commitment_used(&self, commitment: &[u8; 32]) -> Result<bool> {
+        let key = crate::database::schema::utils::deposit_commitment_key(commitment);
+        let Some(value) = self.db.get_cf(cf_names::DEPOSIT_COMMITMENTS, &key)? else {
+            return Ok(false);
+        };
+        let utxo_id: [u8; 32] = value.as_slice().try_into()
+            .map_err(|_| anyhow!("Invalid deposit commitment entry length: {}", value.len()))?;
         
-        Ok(hasher.finalize().into())
+        Ok(self.db.get_cf(cf_names::UTXOS, &self.create_utxo_key(&utxo_id))?.is_some() || self.is_spent(&utxo_id)?)
+    }
+
+    /// Decode the `expires_at` timestamp stored as an input lock value
+    fn decode_lock_expiry(value: &[u8]) -> Option<u64> {
+        value.try_into().ok().map(u64::from_be_bytes)
     }
 
     /// Create UTXO database key
@@ -418,22 +1420,18 @@ impl UTXOManager {
         key
     }
 
-    /// Sign tree root (placeholder implementation)
-    fn sign_root(&self, root: [u8; 32]) -> Result<Vec<u8>> {
-        // Placeholder: In this would use the operator's private key
-        // to sign the root hash for accountability
-        use sha3::{Keccak256, Digest};
-        
-        let mut hasher = Keccak256::new();
-        hasher.update(b"OPERATOR_SIGNATURE");
-        hasher.update(&root);
-        hasher.update(&std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .to_be_bytes());
-        
-        Ok(hasher.finalize().to_vec())
+    /// Message signed by the operator for a tree root
+    fn root_signing_message(root: [u8; 32]) -> Vec<u8> {
+        let mut message = Vec::with_capacity(ROOT_SIGNATURE_DOMAIN.len() + 32);
+        message.extend_from_slice(ROOT_SIGNATURE_DOMAIN);
+        message.extend_from_slice(&root);
+        message
+    }
+
+    /// Sign tree root with the operator key for accountability
+    pub fn sign_root(&self, root: [u8; 32]) -> Result<Vec<u8>> {
+        self.operator_keypair.sign(&Self::root_signing_message(root))
+            .map_err(|e| anyhow!("Failed to sign root: {}", e))
     }
 }
 
@@ -444,6 +1442,14 @@ mod tests {
     use crate::database::schema::DBConfig;
     use web3::types::H256;
 
+    /// Nullifier key and note secret the test spends are authorized with
+    const TEST_NULLIFIER_KEY: [u8; 32] = [0x21u8; 32];
+    const TEST_NOTE_SECRET: [u8; 32] = [0x22u8; 32];
+
+    fn test_nullifier(utxo_id: &[u8; 32]) -> [u8; 32] {
+        ArchitectureCompliantCrypto::derive_nullifier_from_key(&TEST_NULLIFIER_KEY, utxo_id, &TEST_NOTE_SECRET).unwrap()
+    }
+
     #[test]
     fn test_utxo_manager_creation() {
         let temp_dir = tempdir().unwrap();
@@ -495,4 +1501,614 @@ mod tests {
         assert_eq!(utxo_manager.get_current_root(), result.operation.new_root);
         assert_eq!(utxo_manager.get_root_version(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_withdrawal_requires_finalized_root() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let result = utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap();
+        let utxo_id = result.operation.utxo.utxo_id;
+        let root = result.operation.new_root;
+        
+        // Committed root is only prepared until confirmations arrive
+        assert!(utxo_manager.withdraw_utxo(&utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET, root).is_err());
+        
+        utxo_manager.finalize_root(result.operation.root_version).unwrap();
+        assert!(utxo_manager.withdraw_utxo(&utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET, root).is_ok());
+    }
+
+    #[test]
+    fn test_spent_nullifier_provable() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager.clone()).unwrap();
+        let salt = utxo_manager.smt.get_tree_salt();
+        
+        let spent = utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap().operation.utxo;
+        let unspent = utxo_manager.process_eth_deposit(test_deposit_event(1)).unwrap().operation.utxo;
+        
+        utxo_manager.remove_utxo(&spent.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET).unwrap();
+        let root = utxo_manager.get_nullifier_root();
+        
+        // The spend itself records the nullifier in cf_nullifiers
+        let nullifier_key = crate::database::schema::utils::nullifier_key(&test_nullifier(&spent.utxo_id));
+        assert!(db_manager.get_cf(cf_names::NULLIFIERS, &nullifier_key).unwrap().is_some());
+        
+        // The spent set is committed with the spend and survives a restart
+        drop(utxo_manager);
+        let utxo_manager = UTXOManager::new(db_manager).unwrap();
+        assert_eq!(utxo_manager.get_nullifier_root(), root);
+        
+        let spent_nullifier = test_nullifier(&spent.utxo_id);
+        let proof = utxo_manager.prove_nullifier(&spent_nullifier);
+        assert_eq!(proof.verify(&root, salt), Some(true));
+        
+        // The public UTXO-derived nullifier is never recorded
+        let public_nullifier = crate::canonical_spec::generate_nullifier(spent.utxo_id, spent.owner_commitment);
+        assert_eq!(utxo_manager.prove_nullifier(&public_nullifier).verify(&root, salt), Some(false));
+        
+        let unspent_nullifier = test_nullifier(&unspent.utxo_id);
+        let proof = utxo_manager.prove_nullifier(&unspent_nullifier);
+        assert_eq!(proof.verify(&root, salt), Some(false));
+    }
+
+    #[test]
+    fn test_pool_counters_persist_across_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path: db_path.clone(),
+            ..Default::default()
+        };
+        
+        {
+            let db_manager = DatabaseManager::open(config).unwrap();
+            let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+            
+            let first = utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap().operation.utxo;
+            utxo_manager.batch_process_deposits(&[test_deposit_event(1), test_deposit_event(2)]).unwrap();
+            utxo_manager.remove_utxo(&first.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET).unwrap();
+            
+            let counters = utxo_manager.get_pool_counters().unwrap();
+            assert_eq!(counters.total_utxos, 2);
+            assert_eq!(counters.total_spent, 1);
+        }
+        
+        // Counters are read back from cf_tree_metadata after a reopen
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let counters = PoolCounters::load(&db_manager).unwrap();
+        assert_eq!(counters, PoolCounters {
+            total_utxos: 2,
+            total_spent: 1,
+            total_deposited_wei: 6_000_000_000,
+        });
+    }
+
+    #[test]
+    fn test_beacon_index_persists_across_reopen() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let seed = {
+            let db_manager = DatabaseManager::open(DBConfig { db_path: db_path.clone(), ..Default::default() }).unwrap();
+            let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+            utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap();
+            utxo_manager.batch_process_deposits(&[test_deposit_event(1), test_deposit_event(2)]).unwrap();
+            assert_eq!(utxo_manager.operator_entropy_counter, 3);
+            utxo_manager.randomness_beacon().seed()
+        };
+        
+        // The reopened manager continues the same stream instead of reusing indices
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        assert_eq!(utxo_manager.randomness_beacon().seed(), seed);
+        assert_eq!(utxo_manager.operator_entropy_counter, 3);
+        
+        let event = test_deposit_event(3);
+        let utxo = utxo_manager.process_eth_deposit(event.clone()).unwrap().operation.utxo;
+        let entropy = RandomnessBeacon::from_seed(seed).entropy(4);
+        let expected = UTXOManager::prepare_deposit(&event, entropy, utxo_manager.smt.get_tree_salt(), utxo_manager.smt.get_depth()).unwrap();
+        assert_eq!(utxo.utxo_id, expected.utxo.utxo_id);
+    }
+
+    fn test_deposit_event(i: u64) -> DepositEvent {
+        DepositEvent {
+            depositor: format!("0x{:040x}", i + 1),
+            commitment: format!("0x{:064x}", i + 100),
+            label: i,
+            value: 1_000_000_000 * (i + 1),
+            precommitment_hash: format!("0x{:064x}", i + 200),
+            block_number: 12345 + i,
+            transaction_hash: format!("0x{:064x}", i + 300),
+            log_index: i as u32,
+            merkle_root: format!("0x{:064x}", 0),
+        }
+    }
+
+    #[test]
+    fn test_lenient_batch_reports_duplicate_commitment() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let mut events: Vec<DepositEvent> = (0..5).map(test_deposit_event).collect();
+        events[3].commitment = events[1].commitment.clone();
+        
+        let (results, failures) = utxo_manager.batch_process_deposits_lenient(&events).unwrap();
+        
+        assert_eq!(results.len(), 4);
+        assert!(matches!(failures[..], [(3, DepositError::DuplicateCommitment)]));
+        let labels: Vec<u64> = results.iter().map(|r| r.deposit_event.label).collect();
+        assert_eq!(labels, vec![0, 1, 2, 4]);
+        assert_eq!(utxo_manager.get_pool_counters().unwrap().total_utxos, 4);
+    }
+
+    #[test]
+    fn test_lenient_batch_rejects_commitment_from_earlier_batch() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let events: Vec<DepositEvent> = (0..3).map(test_deposit_event).collect();
+        utxo_manager.batch_process_deposits(&events).unwrap();
+        
+        // A redelivered deposit from the stored pool is rejected alongside a fresh one
+        let retry = vec![test_deposit_event(1), test_deposit_event(3)];
+        let (results, failures) = utxo_manager.batch_process_deposits_lenient(&retry).unwrap();
+        
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].deposit_event.label, 3);
+        assert!(matches!(failures[..], [(0, DepositError::DuplicateCommitment)]));
+        assert_eq!(utxo_manager.get_pool_counters().unwrap().total_utxos, 4);
+    }
+
+    #[test]
+    fn test_every_deposit_path_rejects_used_commitment() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        // A single deposit indexes its commitment like a batch does
+        utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap();
+        assert!(utxo_manager.process_eth_deposit(test_deposit_event(0)).is_err());
+        assert!(utxo_manager.batch_process_deposits(&[test_deposit_event(0)]).is_err());
+        let (_, failures) = utxo_manager.batch_process_deposits_lenient(&[test_deposit_event(0)]).unwrap();
+        assert!(matches!(failures[..], [(0, DepositError::DuplicateCommitment)]));
+        
+        // The strict batch refuses a commitment repeated within the batch
+        let mut events: Vec<DepositEvent> = (1..3).map(test_deposit_event).collect();
+        events[1].commitment = events[0].commitment.clone();
+        assert!(utxo_manager.batch_process_deposits(&events).is_err());
+        assert_eq!(utxo_manager.get_pool_counters().unwrap().total_utxos, 1);
+    }
+
+    #[test]
+    fn test_failed_commit_leaves_tree_untouched() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager.clone()).unwrap();
+        let spent = utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap().operation.utxo;
+        let saved = PoolCounters::load(&db_manager).unwrap();
+        let root = utxo_manager.get_current_root();
+        let version = utxo_manager.get_root_version();
+        
+        // Saturated counters make both batches fail at commit time
+        let saturated = PoolCounters {
+            total_spent: u64::MAX,
+            total_deposited_wei: u128::MAX,
+            ..saved
+        };
+        let counters_key = crate::database::pool_counters::POOL_COUNTERS_KEY;
+        db_manager.put_cf(cf_names::TREE_METADATA, counters_key, &saturated.serialize()).unwrap();
+        assert!(utxo_manager.process_eth_deposit(test_deposit_event(1)).is_err());
+        assert!(utxo_manager.remove_utxo(&spent.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET).is_err());
+        assert_eq!(utxo_manager.get_current_root(), root);
+        assert_eq!(utxo_manager.get_root_version(), version);
+        
+        // Once the counters are restored, the same operations go through
+        db_manager.put_cf(cf_names::TREE_METADATA, counters_key, &saved.serialize()).unwrap();
+        let added = utxo_manager.process_eth_deposit(test_deposit_event(1)).unwrap().operation;
+        assert_eq!(added.root_version, version + 1);
+        utxo_manager.remove_utxo(&spent.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET).unwrap();
+        
+        // The in-memory tree matches the one rebuilt from the database
+        let current_root = utxo_manager.get_current_root();
+        drop(utxo_manager);
+        assert_eq!(UTXOManager::new(db_manager).unwrap().get_current_root(), current_root);
+    }
+
+    #[test]
+    fn test_parallel_prepare_matches_sequential() {
+        let events: Vec<DepositEvent> = (0..64).map(test_deposit_event).collect();
+        
+        let mut outcomes = Vec::new();
+        for parallel in [false, true] {
+            let temp_dir = tempdir().unwrap();
+            let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+            
+            let config = DBConfig {
+                db_path,
+                ..Default::default()
+            };
+            
+            let db_manager = DatabaseManager::open(config).unwrap();
+            let mut utxo_manager = UTXOManager::with_tree_config(db_manager, 32, 777).unwrap();
+            utxo_manager.operator_entropy_counter = 42;
+            
+            let results = if parallel {
+                utxo_manager.parallel_prepare_deposits(&events).unwrap()
+            } else {
+                utxo_manager.batch_process_deposits(&events).unwrap()
+            };
+            
+            let utxo_ids: Vec<[u8; 32]> = results.iter().map(|r| r.operation.utxo.utxo_id).collect();
+            outcomes.push((utxo_manager.get_current_root(), utxo_ids, utxo_manager.operator_entropy_counter));
+        }
+        
+        // Same root, same UTXOs in the same order, same entropy afterwards
+        assert_eq!(outcomes[0], outcomes[1]);
+        assert_eq!(outcomes[0].1.len(), 64);
+    }
+
+    #[test]
+    fn test_warm_caches_serve_membership_checks() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let (kept, spent) = {
+            let mut utxo_manager = UTXOManager::new(db_manager.clone()).unwrap();
+            let results = utxo_manager.batch_process_deposits(&[test_deposit_event(0), test_deposit_event(1)]).unwrap();
+            let spent = results[1].operation.clone();
+            utxo_manager.remove_utxo(&spent.utxo.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET).unwrap();
+            (results[0].operation.clone(), spent)
+        };
+        
+        // A restarted manager starts cold and reads the database
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        assert_eq!(utxo_manager.find_utxo_by_commitment(&kept.leaf_hash).unwrap(), Some(kept.utxo.utxo_id));
+        assert_eq!(utxo_manager.cache_misses(), 1);
+        
+        utxo_manager.warm_caches().unwrap();
+        
+        assert_eq!(utxo_manager.find_utxo_by_commitment(&kept.leaf_hash).unwrap(), Some(kept.utxo.utxo_id));
+        assert_eq!(utxo_manager.find_utxo_by_commitment(&spent.leaf_hash).unwrap(), None);
+        assert!(utxo_manager.is_spent(&spent.utxo.utxo_id).unwrap());
+        assert!(!utxo_manager.is_spent(&kept.utxo.utxo_id).unwrap());
+        assert_eq!(utxo_manager.cache_misses(), 1);
+        
+        // Later writes keep the warm caches current
+        let added = utxo_manager.process_eth_deposit(test_deposit_event(2)).unwrap().operation;
+        assert_eq!(utxo_manager.find_utxo_by_commitment(&added.leaf_hash).unwrap(), Some(added.utxo.utxo_id));
+        assert_eq!(utxo_manager.cache_misses(), 1);
+    }
+
+    #[test]
+    fn test_sweep_releases_expired_input_locks() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let utxo_manager = UTXOManager::new(db_manager).unwrap();
+        let (short_lived, long_lived) = ([1u8; 32], [2u8; 32]);
+        
+        assert!(utxo_manager.acquire_input_lock(&short_lived, 1_000, 30).unwrap());
+        assert!(utxo_manager.acquire_input_lock(&long_lived, 1_000, 600).unwrap());
+        assert!(!utxo_manager.acquire_input_lock(&short_lived, 1_010, 30).unwrap());
+        
+        // Nothing has expired yet
+        assert_eq!(utxo_manager.sweep_expired_locks(1_020).unwrap(), 0);
+        
+        // Advance past the short TTL only
+        assert_eq!(utxo_manager.sweep_expired_locks(1_030).unwrap(), 1);
+        assert!(utxo_manager.acquire_input_lock(&short_lived, 1_030, 30).unwrap());
+        assert!(!utxo_manager.acquire_input_lock(&long_lived, 1_030, 30).unwrap());
+        
+        // Stored expiry follows the endianness policy
+        let probe = [3u8; 32];
+        let sentinel = crate::canonical_spec::endianness::SENTINEL as u64;
+        utxo_manager.acquire_input_lock(&probe, sentinel, 0).unwrap();
+        let key = crate::database::schema::utils::input_lock_key(&probe);
+        let value = utxo_manager.db.get_cf(cf_names::INPUT_LOCKS, &key).unwrap().unwrap();
+        crate::canonical_spec::endianness::check_field("input_lock.expires_at", &value, 0, 8).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_acquire_grants_one_lock() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let utxo_manager = UTXOManager::new(db_manager).unwrap();
+        let utxo_id = [4u8; 32];
+        
+        let granted = std::thread::scope(|scope| {
+            let callers: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| utxo_manager.acquire_input_lock(&utxo_id, 1_000, 30).unwrap()))
+                .collect();
+            callers.into_iter().map(|caller| caller.join().unwrap()).filter(|granted| *granted).count()
+        });
+        
+        assert_eq!(granted, 1);
+    }
+
+    #[test]
+    fn test_root_signature_verifies_with_stored_pubkey() {
+        for algorithm in [SignatureAlgorithm::Ed25519, SignatureAlgorithm::Secp256k1] {
+            let temp_dir = tempdir().unwrap();
+            let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+            
+            let config = DBConfig {
+                db_path,
+                ..Default::default()
+            };
+            
+            let db_manager = DatabaseManager::open(config).unwrap();
+            let mut utxo_manager = UTXOManager::new(db_manager.clone()).unwrap();
+            utxo_manager.set_operator_keypair(OperatorKeypair::generate(algorithm).unwrap()).unwrap();
+            
+            let root = [0x42u8; 32];
+            let signature = utxo_manager.sign_root(root).unwrap();
+            
+            let (stored_algorithm, public_key) = UTXOManager::get_operator_public_key(&db_manager)
+                .unwrap()
+                .expect("operator public key should be stored");
+            assert_eq!(stored_algorithm, algorithm);
+            
+            assert!(UTXOManager::verify_root_signature(stored_algorithm, &public_key, root, &signature).unwrap());
+            assert!(!UTXOManager::verify_root_signature(stored_algorithm, &public_key, [0x43u8; 32], &signature).unwrap());
+            
+            // A restart signs with the same key instead of publishing a new one
+            drop(utxo_manager);
+            let restarted = UTXOManager::new(db_manager.clone()).unwrap();
+            let signature = restarted.sign_root(root).unwrap();
+            assert_eq!(UTXOManager::get_operator_public_key(&db_manager).unwrap(), Some((algorithm, public_key.clone())));
+            assert!(UTXOManager::verify_root_signature(algorithm, &public_key, root, &signature).unwrap());
+            let shared = UTXOManager::load_or_create_operator_keypair(&db_manager, SignatureAlgorithm::Ed25519).unwrap();
+            assert_eq!(shared.public_key_bytes(), public_key);
+        }
+    }
+
+    #[test]
+    fn test_root_signature_rejected_with_wrong_key() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let root = [0x42u8; 32];
+        let signature = utxo_manager.sign_root(root).unwrap();
+        
+        let wrong_keypair = OperatorKeypair::generate(SignatureAlgorithm::Ed25519).unwrap();
+        assert!(!UTXOManager::verify_root_signature(
+            SignatureAlgorithm::Ed25519,
+            &wrong_keypair.public_key_bytes(),
+            root,
+            &signature,
+        ).unwrap());
+    }
+    #[test]
+    fn test_insert_rejects_existing_commitment() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        
+        let config = DBConfig {
+            db_path,
+            ..Default::default()
+        };
+        
+        let db_manager = DatabaseManager::open(config).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let utxo = CanonicalUTXO::new_eth([0x11u8; 32], 0, 100, 7, 1_000, [0x22u8; 32]);
+        utxo_manager.insert_utxo_with_tree_update(utxo.clone()).unwrap();
+        let root = utxo_manager.get_current_root();
+        
+        let err = utxo_manager.insert_utxo_with_tree_update(utxo.clone()).unwrap_err();
+        let collision = err.downcast_ref::<CommitmentCollision>().expect("expected CommitmentCollision");
+        assert_eq!(collision.commitment, utxo.leaf_hash().unwrap());
+        assert_eq!(collision.existing_utxo_id, utxo.utxo_id);
+        assert_eq!(utxo_manager.get_current_root(), root);
+    }
+
+    #[test]
+    fn test_rollback_to_block_restores_earlier_state() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager.clone()).unwrap();
+        
+        let utxo_set = |db: &DatabaseManager| -> std::collections::BTreeSet<Vec<u8>> {
+            db.iterator_cf(cf_names::UTXOS).unwrap().map(|item| item.unwrap().0.to_vec()).collect()
+        };
+        
+        // Blocks 12345 and 12346
+        let first = utxo_manager.process_eth_deposit(test_deposit_event(0)).unwrap().operation.utxo;
+        utxo_manager.process_eth_deposit(test_deposit_event(1)).unwrap();
+        let block_two_root = utxo_manager.get_current_root();
+        let block_two_version = utxo_manager.get_root_version();
+        let block_two_utxos = utxo_set(&db_manager);
+        let block_two_counters = utxo_manager.get_pool_counters().unwrap();
+        
+        // Block 12347: a deposit, a spend of an older UTXO and a UTXO created and spent in the block
+        utxo_manager.process_eth_deposit(test_deposit_event(2)).unwrap();
+        let mut same_block = test_deposit_event(3);
+        same_block.block_number = 12347;
+        let transient = utxo_manager.process_eth_deposit(same_block).unwrap().operation.utxo;
+        utxo_manager.remove_utxo_at_block(&first.utxo_id, [0x01u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET, 12347).unwrap();
+        utxo_manager.remove_utxo_at_block(&transient.utxo_id, [0x02u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET, 12347).unwrap();
+        assert_ne!(utxo_manager.get_current_root(), block_two_root);
+        let nullifier = test_nullifier(&first.utxo_id);
+        assert_eq!(utxo_manager.prove_nullifier(&nullifier).verify(&utxo_manager.get_nullifier_root(), utxo_manager.smt.get_tree_salt()), Some(true));
+        let nullifier_key = crate::database::schema::utils::nullifier_key(&nullifier);
+        
+        // Unwinding further than max_reorg_depth below the head is refused untouched
+        let spent_root = utxo_manager.get_current_root();
+        let err = utxo_manager.rollback_to_block(12346, 12346 + 65).unwrap_err();
+        let too_deep = err.downcast_ref::<ReorgTooDeep>().expect("expected ReorgTooDeep");
+        assert_eq!((too_deep.depth, too_deep.max_reorg_depth), (65, 64));
+        assert_eq!(utxo_manager.get_current_root(), spent_root);
+        
+        assert_eq!(utxo_manager.rollback_to_block(12346, 12347).unwrap(), 4);
+        assert_eq!(utxo_manager.get_current_root(), block_two_root);
+        
+        // Roots of the undone block are abandoned; the restored root is recommitted
+        let history = utxo_manager.root_history();
+        for version in block_two_version + 1..=block_two_version + 4 {
+            assert!(history.get_root(version).unwrap().is_none());
+        }
+        assert!(history.versions_of_root(&spent_root).unwrap().is_empty());
+        assert_eq!(
+            history.versions_of_root(&block_two_root).unwrap(),
+            vec![block_two_version, utxo_manager.get_root_version()]
+        );
+        assert_eq!(utxo_set(&db_manager), block_two_utxos);
+        assert_eq!(utxo_manager.get_pool_counters().unwrap(), block_two_counters);
+        assert!(!utxo_manager.is_spent(&first.utxo_id).unwrap());
+        assert_eq!(utxo_manager.prove_nullifier(&nullifier).verify(&utxo_manager.get_nullifier_root(), utxo_manager.smt.get_tree_salt()), Some(false));
+        assert!(db_manager.get_cf(cf_names::NULLIFIERS, &nullifier_key).unwrap().is_none());
+        
+        // Undone operations are gone from the index
+        assert_eq!(utxo_manager.rollback_to_block(12346, 12347).unwrap(), 0);
+        
+        // The re-extended chain applies cleanly, revealing the same nullifier again
+        utxo_manager.process_eth_deposit(test_deposit_event(2)).unwrap();
+        utxo_manager.remove_utxo_at_block(&first.utxo_id, [0x03u8; 32], &TEST_NULLIFIER_KEY, &TEST_NOTE_SECRET, 12347).unwrap();
+        assert!(db_manager.get_cf(cf_names::NULLIFIERS, &nullifier_key).unwrap().is_some());
+        
+        // ...and can itself be undone, leaving a store that reopens to the same state
+        assert_eq!(utxo_manager.rollback_to_block(12346, 12347).unwrap(), 2);
+        assert_eq!(utxo_manager.get_current_root(), block_two_root);
+        assert_eq!(utxo_set(&db_manager), block_two_utxos);
+        let nullifier_root = utxo_manager.get_nullifier_root();
+        drop(utxo_manager);
+        let mut reopened = UTXOManager::new(db_manager).unwrap();
+        assert_eq!(reopened.get_nullifier_root(), nullifier_root);
+        
+        // A finalized root is never abandoned
+        reopened.process_eth_deposit(test_deposit_event(2)).unwrap();
+        reopened.finalize_root(reopened.get_root_version()).unwrap();
+        let finalized_root = reopened.get_current_root();
+        let err = reopened.rollback_to_block(12346, 12347).unwrap_err();
+        assert!(err.to_string().contains("finalized root"), "unexpected error: {}", err);
+        assert_eq!(reopened.get_current_root(), finalized_root);
+    }
+
+    #[test]
+    fn test_select_utxos_skips_locked_and_reports_shortfall() {
+        let temp_dir = tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db_manager = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let mut utxo_manager = UTXOManager::new(db_manager).unwrap();
+        
+        let unit = coin_selection::MIN_CHANGE_AMOUNT * 10;
+        let owner = [0x42u8; 32];
+        let mut insert = |i: u8, amount: u128, timelock: Option<u64>| {
+            let mut utxo = CanonicalUTXO::new_eth([i; 32], 0, 100, i as u64, amount, owner);
+            if let Some(lock_expiry) = timelock {
+                utxo = utxo.with_timelock(lock_expiry);
+            }
+            utxo_manager.insert_utxo_with_tree_update(utxo).unwrap().utxo
+        };
+        let large = insert(1, 6 * unit, None);
+        insert(2, 4 * unit, None);
+        insert(3, 3 * unit, None);
+        insert(4, 20 * unit, Some(1_000));
+        
+        // The timelocked 20 unit UTXO is not spendable at block 500
+        let (selected, change) = utxo_manager.select_utxos(&owner, &[0u8; 20], 7 * unit, 500).unwrap();
+        assert_eq!(selected.iter().map(|utxo| utxo.amount).sum::<u128>(), 7 * unit);
+        assert_eq!(change, 0);
+        
+        let err = utxo_manager.select_utxos(&owner, &[0u8; 20], 14 * unit, 500).unwrap_err();
+        let shortfall = err.downcast_ref::<coin_selection::InsufficientFunds>().expect("expected InsufficientFunds");
+        assert_eq!(shortfall.available, 13 * unit);
+        
+        // Once the timelock expires it covers the amount on its own
+        let (selected, change) = utxo_manager.select_utxos(&owner, &[0u8; 20], 14 * unit, 1_000).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(change, 6 * unit);
+        
+        // Inputs reserved by a pending transaction are left alone
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(utxo_manager.acquire_input_lock(&large.utxo_id, now, 600).unwrap());
+        let (selected, change) = utxo_manager.select_utxos(&owner, &[0u8; 20], 7 * unit, 500).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert!(selected.iter().all(|utxo| utxo.utxo_id != large.utxo_id));
+        assert_eq!(change, 0);
+        assert!(utxo_manager.select_utxos(&owner, &[0u8; 20], 8 * unit, 500).is_err());
+        
+        // Other owners and assets are not touched
+        assert!(utxo_manager.select_utxos(&[0x43u8; 32], &[0u8; 20], 1, 500).is_err());
+        assert!(utxo_manager.select_utxos(&owner, &[0x01u8; 20], 1, 500).is_err());
+    }
+}
@@ -0,0 +1,75 @@
+//! Wallet-side Verification Helpers
+//! 
+//! Pure functions a wallet can run locally, without trusting the relayer,
+//! to check that its deposits were recorded with the expected commitment.
+
+use subtle::ConstantTimeEq;
+use crate::canonical_spec;
+
+/// Recompute the owner commitment the relayer derives for a deposit
+/// 
+/// # Arguments
+/// * `depositor` - Address the wallet deposited from
+/// * `commitment` - Commitment the wallet submitted with the deposit
+/// * `block_number` - Block that included the deposit
+/// 
+/// # Returns
+/// * 32-byte owner commitment
+pub fn regenerate_commitment(
+    depositor: [u8; 20],
+    commitment: [u8; 32],
+    block_number: u64,
+) -> [u8; 32] {
+    canonical_spec::generate_owner_commitment(depositor, commitment, block_number)
+}
+
+/// Check a commitment reported by the relayer against the local derivation
+pub fn verify_deposit_commitment(
+    expected: [u8; 32],
+    depositor: [u8; 20],
+    commitment: [u8; 32],
+    block_number: u64,
+) -> bool {
+    let regenerated = regenerate_commitment(depositor, commitment, block_number);
+    regenerated.ct_eq(&expected).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::{DatabaseManager, DBConfig};
+    use crate::relayer::DepositEvent;
+    use crate::utxo::UTXOManager;
+
+    #[test]
+    fn test_wallet_reproduces_relayer_commitment() {
+        let depositor = [0x12u8; 20];
+        let commitment = [0x34u8; 32];
+        let block_number = 12_345u64;
+        
+        // Relayer side: mint the deposit and read the commitment off its UTXO
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test_db").to_string_lossy().to_string();
+        let db = DatabaseManager::open(DBConfig { db_path, ..Default::default() }).unwrap();
+        let mut relayer = UTXOManager::new(db).unwrap();
+        let minted = relayer.process_eth_deposit(DepositEvent {
+            depositor: format!("0x{}", hex::encode(depositor)),
+            commitment: format!("0x{}", hex::encode(commitment)),
+            label: 1,
+            value: 1_000_000_000,
+            precommitment_hash: format!("0x{}", hex::encode([0u8; 32])),
+            block_number,
+            transaction_hash: format!("0x{}", hex::encode([0x11u8; 32])),
+            log_index: 3,
+            merkle_root: format!("0x{}", hex::encode([0u8; 32])),
+        }).unwrap().operation.utxo;
+        
+        // Wallet side: recompute from its own public deposit data
+        assert_eq!(regenerate_commitment(depositor, commitment, block_number), minted.owner_commitment);
+        assert!(verify_deposit_commitment(minted.owner_commitment, depositor, commitment, block_number));
+        
+        // A different block or depositor is detected as a mismatch
+        assert!(!verify_deposit_commitment(minted.owner_commitment, depositor, commitment, block_number + 1));
+        assert!(!verify_deposit_commitment(minted.owner_commitment, [0x13u8; 20], commitment, block_number));
+    }
+}